@@ -0,0 +1,142 @@
+//! LLM-assisted extraction for `method: agent` traces.
+//!
+//! The agent endpoint speaks the OpenAI-compatible chat completions API
+//! (`POST` a `model` and `messages`, read `choices[0].message.content`).
+//! Every call records the model that answered and the hash of the exact
+//! prompt in the trace's `agent_metadata`, so the claim can be audited.
+
+use crate::config::AgentConfig;
+use crate::crypto::hash_bytes;
+use crate::error::{Result, RhodiError};
+use crate::models::{AgentMetadata, TraceBlock};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// Environment variable consulted when no agent endpoint is given explicitly.
+pub const AGENT_URL_ENV: &str = "RHODI_AGENT_URL";
+
+/// Environment variable holding the bearer token for the agent endpoint.
+pub const AGENT_API_KEY_ENV: &str = "RHODI_AGENT_API_KEY";
+
+const AGENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Sources longer than this are truncated in the prompt.
+const MAX_SOURCE_CHARS: usize = 100_000;
+
+/// The value an agent extracted, with the metadata to record on the trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentAnswer {
+    pub value: String,
+    pub metadata: AgentMetadata,
+}
+
+pub struct AgentClient {
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl AgentClient {
+    pub fn new(endpoint: &str, model: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            model: model.to_string(),
+            api_key: None,
+            agent: ureq::AgentBuilder::new().timeout(AGENT_TIMEOUT).build(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Resolve the endpoint from an explicit value, `RHODI_AGENT_URL`, or the
+    /// workspace's `agent.endpoint`, and the model from an explicit value or
+    /// `agent.model`. The API key comes from `RHODI_AGENT_API_KEY`.
+    pub fn from_config(
+        endpoint: Option<String>,
+        model: Option<String>,
+        configured: &AgentConfig,
+    ) -> Result<Self> {
+        let endpoint = endpoint
+            .or_else(|| std::env::var(AGENT_URL_ENV).ok())
+            .filter(|u| !u.trim().is_empty())
+            .or_else(|| configured.endpoint.clone())
+            .ok_or_else(|| {
+                RhodiError::Agent(format!(
+                    "No agent endpoint configured. Pass --endpoint, set {}, or set agent.endpoint in rhodi.toml.",
+                    AGENT_URL_ENV
+                ))
+            })?;
+        let model = model.or_else(|| configured.model.clone()).ok_or_else(|| {
+            RhodiError::Agent("No agent model configured. Pass --model or set agent.model.".into())
+        })?;
+        let api_key = std::env::var(AGENT_API_KEY_ENV)
+            .ok()
+            .filter(|k| !k.is_empty());
+        Ok(Self::new(&endpoint, &model).with_api_key(api_key))
+    }
+
+    /// Ask the agent for the value `trace` claims, reading it from `source`.
+    pub fn extract(&self, trace: &TraceBlock, source: &[u8]) -> Result<AgentAnswer> {
+        let prompt = build_prompt(trace, &String::from_utf8_lossy(source));
+        let body = json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+
+        let mut request = self.agent.post(&self.endpoint);
+        if let Some(ref key) = self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let response = request
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+            .map_err(|e| RhodiError::Agent(format!("Request to {} failed: {}", self.endpoint, e)))?
+            .into_string()
+            .map_err(|e| RhodiError::Agent(format!("Failed to read response: {}", e)))?;
+        let response: Value = serde_json::from_str(&response)
+            .map_err(|e| RhodiError::Agent(format!("Invalid response: {}", e)))?;
+
+        let value = response["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or_else(|| RhodiError::Agent("Response has no choices[0].message.content".into()))?
+            .trim()
+            .to_string();
+        // Record the model that actually answered, which may be more specific
+        let model = response["model"].as_str().unwrap_or(&self.model);
+
+        Ok(AgentAnswer {
+            value,
+            metadata: AgentMetadata {
+                model: model.to_string(),
+                prompt_hash: Some(hash_bytes(prompt.as_bytes())),
+            },
+        })
+    }
+}
+
+/// The exact prompt sent for a trace. It depends only on the trace's source,
+/// selector and context (never on `expected`), so `prompt_hash` can be
+/// recomputed during an audit.
+pub fn build_prompt(trace: &TraceBlock, source: &str) -> String {
+    let source: String = source.chars().take(MAX_SOURCE_CHARS).collect();
+    let mut prompt = String::from(
+        "Extract a single value from the source document below. \
+         Reply with the value only, without explanation or formatting.\n\n",
+    );
+    if let Some(ref selector) = trace.selector {
+        prompt.push_str(&format!("Location hint: {}\n", selector));
+    }
+    if let Some(ref context) = trace.context {
+        prompt.push_str(&format!("Surrounding text: {}\n", context));
+    }
+    prompt.push_str(&format!(
+        "\nSource ({}):\n<<<\n{}\n>>>\n",
+        trace.source, source
+    ));
+    prompt
+}
@@ -0,0 +1,220 @@
+//! Derived artifacts: files rendered from a sealed document.
+//!
+//! A PDF or HTML export cannot carry the document's seal, so `rhodi export`
+//! records an [`ArtifactRecord`] in a companion log (`<doc>.artifacts`, JSON
+//! lines) instead. The record binds the artifact's digest to the document id
+//! and version hash and is signed with the document's key, so
+//! `rhodi verify-artifact` can later prove that a given file was exported
+//! from the sealed version it claims.
+
+use crate::crypto::{KeyPair, hash_bytes, parse_public_key, parse_signature};
+use crate::error::{Result, RhodiError};
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock};
+use crate::models::TracedDocument;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Domain separator so artifact signatures can never be confused with seals.
+const ARTIFACT_CONTEXT: &str = "rhodi-artifact-v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactRecord {
+    /// File name of the artifact when it was exported
+    pub artifact: String,
+    /// `sha256:` digest of the artifact's bytes
+    pub hash: String,
+    pub doc_id: Uuid,
+    /// Hex-encoded version hash of the document it was exported from
+    pub version_hash: String,
+    pub doc_version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Hex-encoded public key that signed the record
+    pub signer: String,
+    /// Hex-encoded Ed25519 signature over the record
+    pub signature: String,
+}
+
+impl ArtifactRecord {
+    /// Sign a record that `content` was exported from `doc`. The document
+    /// must be sealed and `keypair` must be the key it was sealed with.
+    pub fn create(
+        doc: &TracedDocument,
+        artifact: &str,
+        content: &[u8],
+        keypair: &KeyPair,
+    ) -> Result<Self> {
+        let version_hash = doc.frontmatter.version_hash.ok_or_else(|| {
+            RhodiError::Verification("Only sealed documents can be exported".into())
+        })?;
+        let signer = hex::encode(keypair.verifying_key.as_bytes());
+        if doc.frontmatter.public_key.as_deref() != Some(signer.as_str()) {
+            return Err(RhodiError::Verification(
+                "Artifacts must be signed with the key that sealed the document".into(),
+            ));
+        }
+
+        let mut record = Self {
+            artifact: artifact.to_string(),
+            hash: hash_bytes(content),
+            doc_id: doc.frontmatter.id,
+            version_hash: hex::encode(version_hash),
+            doc_version: doc.frontmatter.doc_version,
+            exported_at: Utc::now(),
+            signer,
+            signature: String::new(),
+        };
+        record.signature = hex::encode(keypair.sign(&record.message()).to_bytes());
+        Ok(record)
+    }
+
+    /// The exact bytes covered by the signature.
+    fn message(&self) -> Vec<u8> {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            ARTIFACT_CONTEXT,
+            self.doc_id,
+            self.version_hash,
+            self.doc_version,
+            self.hash,
+            self.artifact,
+            self.exported_at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.signer
+        )
+        .into_bytes()
+    }
+
+    /// Check the record's signature against its embedded signer key.
+    pub fn verify(&self) -> Result<()> {
+        let public_key = parse_public_key(&self.signer)?;
+        let signature = parse_signature(&self.signature)?;
+        public_key
+            .verify_strict(&self.message(), &signature)
+            .map_err(|e| RhodiError::Crypto(format!("Artifact record signature invalid: {}", e)))
+    }
+}
+
+/// The record that proves an artifact's origin, and what was wrong with
+/// the other records for the same bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtifactCheck<'a> {
+    pub record: &'a ArtifactRecord,
+    /// One entry per record for `content` that does not prove it
+    pub findings: Vec<String>,
+}
+
+/// Check that `content` was exported from the sealed version of `doc`:
+/// the document's seal holds and some record for `content`'s digest has a
+/// valid signature by the document's key and names the document's current
+/// version hash. Every other record for the digest yields a finding; when
+/// none proves the export, the findings make up the error.
+pub fn verify_artifact<'a>(
+    doc: &TracedDocument,
+    content: &[u8],
+    records: &'a [ArtifactRecord],
+) -> Result<ArtifactCheck<'a>> {
+    let pk_hex = doc.frontmatter.public_key.as_deref().ok_or_else(|| {
+        RhodiError::Verification("Document has no public_key to verify against".into())
+    })?;
+    doc.verify(&parse_public_key(pk_hex)?)?;
+
+    let hash = hash_bytes(content);
+    let current = doc.frontmatter.version_hash.map(hex::encode);
+    let mut proof = None;
+    let mut findings = Vec::new();
+    for record in records
+        .iter()
+        .filter(|r| r.doc_id == doc.frontmatter.id && r.hash == hash)
+    {
+        if let Err(e) = record.verify() {
+            findings.push(format!("Record of {}: {}", record.exported_at, e));
+        } else if record.signer != pk_hex {
+            findings.push(format!(
+                "Record of {} was signed by {}, not the document's key",
+                record.exported_at, record.signer
+            ));
+        } else if current.as_deref() != Some(record.version_hash.as_str()) {
+            findings.push(format!(
+                "Record of {} is for version {} of the document, not the current sealed version {}",
+                record.exported_at, record.doc_version, doc.frontmatter.doc_version
+            ));
+        } else if proof.is_none() {
+            proof = Some(record);
+        }
+    }
+
+    match proof {
+        Some(record) => Ok(ArtifactCheck { record, findings }),
+        None if findings.is_empty() => Err(RhodiError::Verification(format!(
+            "No export of this document is recorded for {}",
+            hash
+        ))),
+        None => Err(RhodiError::Verification(format!(
+            "No record proves this export: {}",
+            findings.join("; ")
+        ))),
+    }
+}
+
+/// Render the document body as HTML.
+pub fn render_html(doc: &TracedDocument) -> String {
+    html_page(&doc.frontmatter.title, &::markdown::to_html(&doc.body))
+}
+
+/// A standalone HTML page around rendered `body` HTML.
+pub(crate) fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Path of the artifact log kept next to a document.
+pub fn artifacts_path(doc_path: &Path) -> PathBuf {
+    let mut name = doc_path.as_os_str().to_owned();
+    name.push(".artifacts");
+    PathBuf::from(name)
+}
+
+/// Append a record to a JSON-lines log, creating it if needed.
+pub fn append(log: &Path, record: &ArtifactRecord) -> Result<()> {
+    let line = serde_json::to_string(record).map_err(|e| {
+        RhodiError::Serialization(format!("Failed to serialize artifact record: {}", e))
+    })?;
+
+    let _lock = FileLock::acquire(log, DEFAULT_LOCK_TIMEOUT)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Read every record in a JSON-lines log. A missing log is empty.
+pub fn read_all(log: &Path) -> Result<Vec<ArtifactRecord>> {
+    if !log.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(log)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| {
+                RhodiError::Format(format!(
+                    "Invalid artifact record in {}: {}",
+                    log.display(),
+                    e
+                ))
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,175 @@
+//! Signed statements that a document version was verified at a given time.
+//!
+//! An attestation records "key X verified document `id` at version hash H at
+//! time T". Attestations are appended as JSON lines, either next to the
+//! document (`<doc>.attestations`) or in the workspace ledger, so that an
+//! auditor can later prove verification happened at review time. An
+//! attestation exported from `rhodi review` also carries the reviewer's
+//! verdict on each trace.
+
+use crate::crypto::{KeyPair, parse_public_key, parse_signature};
+use crate::error::{Result, RhodiError};
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock};
+use crate::models::TracedDocument;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Domain separator so attestation signatures can never be confused with seals.
+const ATTESTATION_CONTEXT: &str = "rhodi-attestation-v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Attestation {
+    pub doc_id: Uuid,
+    /// Hex-encoded version hash of the verified document
+    pub version_hash: String,
+    pub doc_version: u32,
+    pub verified_at: DateTime<Utc>,
+    /// Hex-encoded public key of the verifier
+    pub verifier: String,
+    /// Warnings reported by verification (errors prevent attesting)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The reviewer's verdict on each trace, from `rhodi review`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reviews: Vec<TraceReview>,
+    /// Hex-encoded Ed25519 signature over the statement
+    pub signature: String,
+}
+
+/// A reviewer's verdict on one trace.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceReview {
+    /// The trace as reports name it, e.g. `t-004 (data.csv)`
+    pub trace: String,
+    /// Whether the automatic check passed at review time
+    pub passed: bool,
+    pub verdict: Verdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Approved,
+    Flagged,
+}
+
+impl Attestation {
+    /// Sign a statement that `doc` was verified now.
+    pub fn create(doc: &TracedDocument, keypair: &KeyPair, warnings: Vec<String>) -> Result<Self> {
+        Self::create_with_reviews(doc, keypair, warnings, Vec::new())
+    }
+
+    /// Sign a statement that `doc` was verified and reviewed now, with a
+    /// verdict per trace.
+    pub fn create_with_reviews(
+        doc: &TracedDocument,
+        keypair: &KeyPair,
+        warnings: Vec<String>,
+        reviews: Vec<TraceReview>,
+    ) -> Result<Self> {
+        let version_hash = doc.frontmatter.version_hash.ok_or_else(|| {
+            RhodiError::Verification("Only sealed documents can be attested".into())
+        })?;
+
+        let mut attestation = Self {
+            doc_id: doc.frontmatter.id,
+            version_hash: hex::encode(version_hash),
+            doc_version: doc.frontmatter.doc_version,
+            verified_at: Utc::now(),
+            verifier: hex::encode(keypair.verifying_key.as_bytes()),
+            warnings,
+            reviews,
+            signature: String::new(),
+        };
+        attestation.signature = hex::encode(keypair.sign(&attestation.message()?).to_bytes());
+        Ok(attestation)
+    }
+
+    /// The exact bytes covered by the signature.
+    fn message(&self) -> Result<Vec<u8>> {
+        let mut message = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            ATTESTATION_CONTEXT,
+            self.doc_id,
+            self.version_hash,
+            self.doc_version,
+            self.verified_at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.verifier
+        );
+        for warning in &self.warnings {
+            message.push('\n');
+            message.push_str(warning);
+        }
+        // Appended only when present, so plain attestations keep their form
+        for review in &self.reviews {
+            message.push_str("\nreview ");
+            let review = serde_json::to_string(review).map_err(|e| {
+                RhodiError::Serialization(format!("Failed to serialize trace review: {}", e))
+            })?;
+            message.push_str(&review);
+        }
+        Ok(message.into_bytes())
+    }
+
+    /// Check the attestation's signature against its embedded verifier key.
+    pub fn verify(&self) -> Result<()> {
+        let public_key = parse_public_key(&self.verifier)?;
+        let signature = parse_signature(&self.signature)?;
+        public_key
+            .verify_strict(&self.message()?, &signature)
+            .map_err(|e| RhodiError::Crypto(format!("Attestation signature invalid: {}", e)))
+    }
+
+    /// Whether this attestation covers the given document version.
+    pub fn covers(&self, doc: &TracedDocument) -> bool {
+        self.doc_id == doc.frontmatter.id
+            && doc
+                .frontmatter
+                .version_hash
+                .is_some_and(|h| hex::encode(h) == self.version_hash)
+    }
+}
+
+/// Path of the attestation log kept next to a document.
+pub fn attestations_path(doc_path: &Path) -> PathBuf {
+    let mut name = doc_path.as_os_str().to_owned();
+    name.push(".attestations");
+    PathBuf::from(name)
+}
+
+/// Append an attestation to a JSON-lines log, creating it if needed.
+pub fn append(log: &Path, attestation: &Attestation) -> Result<()> {
+    if let Some(parent) = log.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(attestation).map_err(|e| {
+        RhodiError::Serialization(format!("Failed to serialize attestation: {}", e))
+    })?;
+
+    let _lock = FileLock::acquire(log, DEFAULT_LOCK_TIMEOUT)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Read every attestation in a JSON-lines log. A missing log is empty.
+pub fn read_all(log: &Path) -> Result<Vec<Attestation>> {
+    if !log.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(log)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| {
+                RhodiError::Format(format!("Invalid attestation in {}: {}", log.display(), e))
+            })
+        })
+        .collect()
+}
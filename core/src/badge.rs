@@ -0,0 +1,196 @@
+//! Status badges for exports and READMEs.
+//!
+//! A [`Badge`] shows how a document or one of its traces fared in
+//! verification, shields-style: a grey label and a coloured message.
+//! `rhodi export --badges` renders one after every trace block and one for
+//! the whole document at the top; `rhodi verify --badge-json` writes the
+//! overall badge in the JSON format of shields.io endpoint badges
+//! (<https://shields.io/badges/endpoint-badge>), so a README or wiki can
+//! embed the live status of a workspace.
+
+use crate::artifact::{escape_html, html_page};
+use crate::compiler::{SectionOutcome, VerifiedDocument};
+use crate::error::{Result, RhodiError};
+use crate::lock::write_atomic;
+use crate::markdown::{Section, after_trace_blocks};
+use crate::models::TracedDocument;
+use crate::report::VerificationReport;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Version of the shields.io endpoint format.
+const SHIELDS_SCHEMA_VERSION: u32 = 1;
+
+/// Marks the spot of a badge while the body goes through the Markdown
+/// renderer; private-use characters cannot clash with document text.
+const PLACEHOLDER_OPEN: char = '\u{E000}';
+const PLACEHOLDER_CLOSE: char = '\u{E001}';
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    pub schema_version: u32,
+    pub label: String,
+    pub message: String,
+    /// A shields.io colour name
+    pub color: String,
+}
+
+impl Badge {
+    pub fn new(label: &str, message: &str, color: &str) -> Self {
+        Self {
+            schema_version: SHIELDS_SCHEMA_VERSION,
+            label: label.to_string(),
+            message: message.to_string(),
+            color: color.to_string(),
+        }
+    }
+
+    /// The badge for a document or trace that fared `outcome`.
+    pub fn for_outcome(label: &str, outcome: SectionOutcome) -> Self {
+        let (message, color) = match outcome {
+            SectionOutcome::Passed => ("verified", "brightgreen"),
+            SectionOutcome::Warning => ("warning", "yellow"),
+            SectionOutcome::Failed => ("failed", "red"),
+            SectionOutcome::Unverified => ("unverified", "lightgrey"),
+            SectionOutcome::TimedOut => ("timed out", "orange"),
+            SectionOutcome::NotChecked => ("not checked", "lightgrey"),
+        };
+        Self::new(label, message, color)
+    }
+
+    /// The badge for a whole verification run: the worst outcome of its
+    /// documents.
+    pub fn for_report(label: &str, report: &VerificationReport) -> Self {
+        let outcome = report
+            .documents
+            .iter()
+            .map(|doc| doc.outcome)
+            .fold(SectionOutcome::NotChecked, SectionOutcome::worse);
+        Self::for_outcome(label, outcome)
+    }
+
+    /// Write the badge as shields.io endpoint JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to serialize badge: {}", e)))?;
+        write_atomic(path, &format!("{}\n", json))
+    }
+
+    /// Hex code of the colour, for [`Self::svg`].
+    fn hex_color(&self) -> &'static str {
+        match self.color.as_str() {
+            "brightgreen" => "#4c1",
+            "yellow" => "#dfb317",
+            "orange" => "#fe7d37",
+            "red" => "#e05d44",
+            _ => "#9f9f9f",
+        }
+    }
+
+    /// The badge as an inline flat SVG image.
+    pub fn svg(&self) -> String {
+        // Approximate width of Verdana 11px text, which shields badges use
+        let width = |text: &str| text.chars().count() * 7 + 10;
+        let (label_width, message_width) = (width(&self.label), width(&self.message));
+        let total = label_width + message_width;
+        let (label, message) = (escape_html(&self.label), escape_html(&self.message));
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{total}\" height=\"20\" role=\"img\" aria-label=\"{label}: {message}\">\
+<title>{label}: {message}</title>\
+<rect width=\"{label_width}\" height=\"20\" fill=\"#555\"/>\
+<rect x=\"{label_width}\" width=\"{message_width}\" height=\"20\" fill=\"{color}\"/>\
+<g fill=\"#fff\" text-anchor=\"middle\" font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">\
+<text x=\"{label_x}\" y=\"14\">{label}</text>\
+<text x=\"{message_x}\" y=\"14\">{message}</text>\
+</g></svg>",
+            color = self.hex_color(),
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+        )
+    }
+
+    /// URL of the same badge as an img.shields.io static badge.
+    pub fn shields_url(&self) -> String {
+        // Dashes and underscores are separators in the path; spaces become
+        // underscores
+        let escape = |text: &str| {
+            let text = text.replace('-', "--").replace('_', "__").replace(' ', "_");
+            text.bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                        (b as char).to_string()
+                    }
+                    _ => format!("%{:02X}", b),
+                })
+                .collect::<String>()
+        };
+        format!(
+            "https://img.shields.io/badge/{}-{}-{}",
+            escape(&self.label),
+            escape(&self.message),
+            self.color
+        )
+    }
+
+    /// The badge as a Markdown image.
+    pub fn markdown(&self) -> String {
+        format!(
+            "![{}: {}]({})",
+            self.label,
+            self.message,
+            self.shields_url()
+        )
+    }
+}
+
+/// A badge for each trace block of `verified`, in document order.
+fn trace_badges(verified: &VerifiedDocument) -> Vec<Badge> {
+    verified
+        .sections
+        .iter()
+        .filter_map(|section| match &section.section {
+            Section::Trace(trace) => Some(Badge::for_outcome(
+                trace.id.as_deref().unwrap_or("trace"),
+                section.outcome,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `doc` as an HTML page with an inline badge after each trace block and
+/// the document's badge at the top.
+pub fn render_html_with_badges(doc: &TracedDocument, verified: &VerifiedDocument) -> String {
+    let badges = trace_badges(verified);
+    let body = after_trace_blocks(&doc.body, |n| {
+        if n < badges.len() {
+            format!("{}{}{}", PLACEHOLDER_OPEN, n, PLACEHOLDER_CLOSE)
+        } else {
+            String::new()
+        }
+    });
+    let mut html = ::markdown::to_html(&body);
+    for (n, badge) in badges.iter().enumerate() {
+        html = html.replace(
+            &format!("{}{}{}", PLACEHOLDER_OPEN, n, PLACEHOLDER_CLOSE),
+            &badge.svg(),
+        );
+    }
+    let document = Badge::for_outcome("rhodi", verified.outcome());
+    html_page(
+        &doc.frontmatter.title,
+        &format!("<p>{}</p>\n{}", document.svg(), html),
+    )
+}
+
+/// The body of `doc` as Markdown with a shields.io badge image after each
+/// trace block and the document's badge at the top.
+pub fn render_markdown_with_badges(doc: &TracedDocument, verified: &VerifiedDocument) -> String {
+    let badges = trace_badges(verified);
+    let body = after_trace_blocks(&doc.body, |n| {
+        badges.get(n).map(Badge::markdown).unwrap_or_default()
+    });
+    let document = Badge::for_outcome("rhodi", verified.outcome());
+    format!("{}\n\n{}", document.markdown(), body.trim_start())
+}
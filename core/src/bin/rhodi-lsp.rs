@@ -0,0 +1,6 @@
+fn main() {
+    if let Err(e) = rhodi_core::lsp::run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,24 @@
+//! Extension point for custom fenced block types.
+//!
+//! Downstream crates implement [`BlockHandler`] for their own blocks (for
+//! example ```` ```dataset ```` or ```` ```approval ````) and register it with
+//! [`crate::compiler::Compiler::with_handler`]. The compiler then hands every
+//! block of that kind to the handler during verification.
+
+use crate::error::Result;
+use crate::models::TracedDocument;
+use crate::resolver::SourceResolver;
+
+pub trait BlockHandler {
+    /// The fence info string this handler owns, e.g. `dataset` for ```` ```dataset ````.
+    fn kind(&self) -> &str;
+
+    /// Verify one block. `block` is the raw block including its fences;
+    /// YAML-bodied blocks can be decoded with [`crate::markdown::parse_fenced_yaml`].
+    fn verify(
+        &self,
+        block: &str,
+        doc: &TracedDocument,
+        resolver: &dyn SourceResolver,
+    ) -> Result<()>;
+}
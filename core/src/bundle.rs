@@ -0,0 +1,465 @@
+//! Evidence bundles: documents packed with every local source they cite.
+//!
+//! A bundle is a tar archive holding `manifest.json` and each file once
+//! under `objects/`, named by the hex of its SHA-256 digest, so datasets
+//! shared by many documents are stored a single time. The manifest maps
+//! every bundled document to its files, keyed by their path relative to
+//! that document. [`BundleResolver`] serves one document's sources from a
+//! bundle instead of the filesystem, so documents can be verified on a
+//! machine without the original directory layout. Zip and gzip-compressed
+//! tar bundles are accepted as well, and so are `rhodi-bundle/1` bundles,
+//! which hold one document's files under `files/` by path.
+
+use crate::crypto::hash_bytes;
+use crate::error::{Result, RhodiError, SecurityError};
+use crate::markdown::{Section, parse_include_block, parse_tmd, parse_tmd_sections};
+use crate::models::TracedDocument;
+use crate::resolver::{SourceResolver, normalize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Format identifier written to every manifest.
+pub const BUNDLE_FORMAT: &str = "rhodi-bundle/2";
+
+/// The format of bundles with one document and a file per path.
+const BUNDLE_FORMAT_V1: &str = "rhodi-bundle/1";
+
+/// Bundles whose files unpack to more than this, in total, are refused,
+/// so a small compressed archive cannot expand into unbounded memory.
+pub const MAX_BUNDLE_BYTES: u64 = 1024 * 1024 * 1024;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const FILES_DIR: &str = "files/";
+const OBJECTS_DIR: &str = "objects/";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleManifest {
+    pub format: String,
+    /// The first bundled document, relative to the bundle root
+    pub document: String,
+    /// Every file of `document` with its `sha256:` digest
+    pub files: BTreeMap<String, String>,
+    /// Every bundled document, relative to the bundle root, with its files
+    /// and their digests, keyed by path relative to the document
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub documents: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl BundleManifest {
+    /// Files listed across all documents, and how many distinct contents
+    /// they have, i.e. how many are stored.
+    pub fn file_counts(&self) -> (usize, usize) {
+        let listed = self.documents.values().map(BTreeMap::len).sum();
+        let mut stored: Vec<&String> = self.documents.values().flat_map(|f| f.values()).collect();
+        stored.sort_unstable();
+        stored.dedup();
+        (listed, stored.len())
+    }
+}
+
+/// Serves sources from an evidence bundle held in memory, as seen from one
+/// of its documents: the first, unless another is [`Self::select`]ed.
+#[derive(Debug)]
+pub struct BundleResolver {
+    manifest: BundleManifest,
+    /// File contents by `sha256:` digest
+    objects: BTreeMap<String, Vec<u8>>,
+    /// The document whose files are served
+    selected: String,
+}
+
+impl BundleResolver {
+    pub fn open(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).map_err(|e| {
+            RhodiError::Resolution(format!("Cannot read bundle '{}': {}", path.display(), e))
+        })?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Read a bundle from a stream, e.g. stdin.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Load a tar, tar.gz or zip bundle. Every file is checked against its
+    /// digest up front.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_bytes_with_limit(bytes, MAX_BUNDLE_BYTES)
+    }
+
+    /// [`Self::from_bytes`], refusing bundles whose files unpack to more
+    /// than `limit` bytes in total.
+    pub fn from_bytes_with_limit(bytes: Vec<u8>, limit: u64) -> Result<Self> {
+        let entries = if bytes.starts_with(b"PK\x03\x04") {
+            read_zip(bytes, limit)?
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            read_tar(flate2::read::GzDecoder::new(Cursor::new(bytes)), limit)?
+        } else {
+            read_tar(Cursor::new(bytes), limit)?
+        };
+
+        let mut manifest = None;
+        let mut files = BTreeMap::new();
+        let mut objects = BTreeMap::new();
+        for (name, content) in entries {
+            if name == MANIFEST_NAME {
+                manifest = Some(
+                    serde_json::from_slice::<BundleManifest>(&content).map_err(|e| {
+                        RhodiError::Format(format!("Invalid bundle manifest: {}", e))
+                    })?,
+                );
+            } else if let Some(path) = name.strip_prefix(FILES_DIR) {
+                files.insert(bundle_key(path), content);
+            } else if let Some(hex) = name.strip_prefix(OBJECTS_DIR) {
+                let digest = format!("sha256:{}", hex);
+                if hash_bytes(&content) != digest {
+                    return Err(RhodiError::Verification(format!(
+                        "Bundled object '{}' does not match its digest",
+                        hex
+                    )));
+                }
+                objects.insert(digest, content);
+            }
+        }
+        let mut manifest = manifest
+            .ok_or_else(|| RhodiError::Format(format!("Bundle has no {}", MANIFEST_NAME)))?;
+        match manifest.format.as_str() {
+            BUNDLE_FORMAT => {}
+            BUNDLE_FORMAT_V1 => {
+                // One document, its files stored by path
+                for (path, digest) in &manifest.files {
+                    let content = files.remove(&bundle_key(path)).ok_or_else(|| {
+                        RhodiError::Format(format!("Bundle manifest lists missing file '{}'", path))
+                    })?;
+                    if &hash_bytes(&content) != digest {
+                        return Err(RhodiError::Verification(format!(
+                            "Bundled file '{}' does not match its manifest digest",
+                            path
+                        )));
+                    }
+                    objects.insert(digest.clone(), content);
+                }
+                manifest
+                    .documents
+                    .insert(manifest.document.clone(), manifest.files.clone());
+            }
+            other => {
+                return Err(RhodiError::Format(format!(
+                    "Unsupported bundle format '{}' (expected {})",
+                    other, BUNDLE_FORMAT
+                )));
+            }
+        }
+
+        for (document, files) in &manifest.documents {
+            for (path, digest) in files {
+                if !objects.contains_key(digest) {
+                    return Err(RhodiError::Format(format!(
+                        "Bundle manifest lists missing file '{}' of {}",
+                        path, document
+                    )));
+                }
+            }
+        }
+        if !manifest.documents.contains_key(&manifest.document) {
+            return Err(RhodiError::Format(format!(
+                "Bundle manifest does not list the files of {}",
+                manifest.document
+            )));
+        }
+        // Objects the manifest does not vouch for are never served
+        objects.retain(|digest, _| {
+            manifest
+                .documents
+                .values()
+                .any(|files| files.values().any(|d| d == digest))
+        });
+
+        let selected = manifest.document.clone();
+        Ok(Self {
+            manifest,
+            objects,
+            selected,
+        })
+    }
+
+    pub fn manifest(&self) -> &BundleManifest {
+        &self.manifest
+    }
+
+    /// Whether `document` is one of the bundled documents, named relative
+    /// to the bundle root.
+    pub fn has_document(&self, document: &str) -> bool {
+        self.manifest.documents.contains_key(&bundle_key(document))
+    }
+
+    /// Serve the sources of `document`, another bundled document.
+    pub fn select(mut self, document: &str) -> Result<Self> {
+        let key = bundle_key(document);
+        if !self.manifest.documents.contains_key(&key) {
+            return Err(RhodiError::Resolution(format!(
+                "Document '{}' is not in the bundle",
+                document
+            )));
+        }
+        self.selected = key;
+        Ok(self)
+    }
+
+    /// The selected document: the one the bundle was exported for, unless
+    /// another was selected.
+    pub fn document(&self) -> Result<TracedDocument> {
+        let name = Path::new(&self.selected)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.resolve_document(&name)
+    }
+}
+
+impl SourceResolver for BundleResolver {
+    fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+        self.manifest
+            .documents
+            .get(&self.selected)
+            .and_then(|files| files.get(&bundle_key(source)))
+            .and_then(|digest| self.objects.get(digest))
+            .cloned()
+            .ok_or_else(|| {
+                RhodiError::Resolution(format!("Source '{}' is not in the bundle", source))
+            })
+    }
+
+    fn resolve_document(&self, source: &str) -> Result<TracedDocument> {
+        let bytes = self.resolve_bytes(source)?;
+        let content = String::from_utf8(bytes)
+            .map_err(|e| RhodiError::Format(format!("Invalid UTF-8 in document: {}", e)))?;
+        parse_tmd(&content)
+    }
+
+    fn identity(&self, source: &str) -> Result<String> {
+        Ok(bundle_key(source))
+    }
+}
+
+/// Pack the document at `doc_path` with every local trace source, include
+/// and quoted document it references (transitively) into a tar bundle.
+/// Remote sources are left out. Returns the manifest that was written.
+pub fn write_bundle(doc_path: &Path, out: impl Write) -> Result<BundleManifest> {
+    write_documents_bundle(&[doc_path.to_path_buf()], out)
+}
+
+/// Pack several documents, each as [`write_bundle`] would, into one
+/// bundle. Documents are named relative to the deepest directory holding
+/// them all; a file any number of them cite is stored once.
+pub fn write_documents_bundle(doc_paths: &[PathBuf], out: impl Write) -> Result<BundleManifest> {
+    let base = common_dir(doc_paths)?;
+    let mut objects: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut documents = BTreeMap::new();
+    for doc_path in doc_paths {
+        let doc_path = doc_path.canonicalize().map_err(|e| {
+            RhodiError::Resolution(format!("Cannot bundle '{}': {}", doc_path.display(), e))
+        })?;
+        let dir = doc_path.parent().unwrap_or(Path::new("/"));
+        let name = doc_path
+            .file_name()
+            .ok_or_else(|| RhodiError::Resolution(format!("Not a file: {}", doc_path.display())))?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut files = BTreeMap::new();
+        for (path, content) in document_files(dir, &name)? {
+            let digest = hash_bytes(&content);
+            objects.entry(digest.clone()).or_insert(content);
+            files.insert(path, digest);
+        }
+        let document = doc_path.strip_prefix(&base).unwrap_or(Path::new(&name));
+        documents.insert(bundle_key(&document.to_string_lossy()), files);
+    }
+
+    let (document, files) = documents
+        .first_key_value()
+        .map(|(document, files)| (document.clone(), files.clone()))
+        .ok_or_else(|| RhodiError::Resolution("No documents to bundle".into()))?;
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        document,
+        files,
+        documents,
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| RhodiError::Serialization(format!("Failed to write manifest: {}", e)))?;
+
+    let mut builder = tar::Builder::new(out);
+    append(&mut builder, MANIFEST_NAME, &manifest_json)?;
+    for (digest, content) in &objects {
+        let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+        append(&mut builder, &format!("{}{}", OBJECTS_DIR, hex), content)?;
+    }
+    builder.into_inner()?.flush()?;
+    Ok(manifest)
+}
+
+/// The document `name` in `dir` and every local file it references,
+/// transitively, keyed by path relative to `dir`.
+fn document_files(dir: &Path, name: &str) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![name.to_string()];
+    while let Some(path) = pending.pop() {
+        let key = bundle_key(&path);
+        if files.contains_key(&key) {
+            continue;
+        }
+        let content = fs::read(dir.join(&path))
+            .map_err(|e| RhodiError::Resolution(format!("Cannot bundle '{}': {}", path, e)))?;
+        if path.ends_with(".tmd") {
+            let doc = parse_tmd(&String::from_utf8_lossy(&content))?;
+            pending.extend(
+                referenced_files(&doc)
+                    .into_iter()
+                    .filter(|p| !p.contains("://")),
+            );
+        }
+        files.insert(key, content);
+    }
+    Ok(files)
+}
+
+/// The deepest directory holding every one of `paths`.
+fn common_dir(paths: &[PathBuf]) -> Result<PathBuf> {
+    let mut common: Option<PathBuf> = None;
+    for path in paths {
+        let dir = path
+            .canonicalize()
+            .map_err(|e| {
+                RhodiError::Resolution(format!("Cannot bundle '{}': {}", path.display(), e))
+            })?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        common = Some(match common {
+            None => dir,
+            Some(common) => common
+                .components()
+                .zip(dir.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common.ok_or_else(|| RhodiError::Resolution("No documents to bundle".into()))
+}
+
+/// Trace sources and schemas, includes and quoted documents named in
+/// `doc`'s body.
+fn referenced_files(doc: &TracedDocument) -> Vec<String> {
+    parse_tmd_sections(&doc.body)
+        .into_iter()
+        .flat_map(|section| match section {
+            Section::Trace(trace) => match doc.frontmatter.resolve_trace(&trace) {
+                Ok(trace) => [
+                    Some(
+                        crate::compression::split_archive_entry(&trace.source)
+                            .0
+                            .to_string(),
+                    ),
+                    trace.schema,
+                ]
+                .into_iter()
+                .flatten()
+                .collect(),
+                Err(_) => Vec::new(),
+            },
+            // Includes by id need the workspace index and are not bundled
+            Section::Include(block) => parse_include_block(&block)
+                .ok()
+                .filter(|i| i.id.is_none())
+                .map(|i| i.path)
+                .into_iter()
+                .collect(),
+            Section::Quote(quote) => vec![quote.source_doc],
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Sources are looked up by their normalized relative path, so `./a.csv`
+/// and `a.csv` name the same bundled file. An archive entry is looked up
+/// by its archive, which is what gets bundled.
+fn bundle_key(path: &str) -> String {
+    let (path, _) = crate::compression::split_archive_entry(path);
+    normalize(Path::new(path))
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn append<W: Write>(builder: &mut tar::Builder<W>, name: &str, content: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, content)?;
+    Ok(())
+}
+
+fn read_tar(reader: impl Read, limit: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let mut remaining = limit;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let content = read_capped(&mut entry, &mut remaining, limit)?;
+        entries.push((name, content));
+    }
+    Ok(entries)
+}
+
+fn read_zip(bytes: Vec<u8>, limit: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    let invalid =
+        |e: zip::result::ZipError| RhodiError::Format(format!("Invalid zip bundle: {}", e));
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(invalid)?;
+    let mut entries = Vec::new();
+    let mut remaining = limit;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(invalid)?;
+        if !file.is_file() {
+            continue;
+        }
+        if file.size() > remaining {
+            return Err(too_large(limit));
+        }
+        let name = file.name().to_string();
+        let content = read_capped(&mut file, &mut remaining, limit)?;
+        entries.push((name, content));
+    }
+    Ok(entries)
+}
+
+/// Read one bundled file, counting it against the `remaining` bytes of
+/// `limit` the whole bundle may still unpack to. Archive headers can
+/// understate sizes, so the count is of bytes actually read.
+fn read_capped(reader: impl Read, remaining: &mut u64, limit: u64) -> Result<Vec<u8>> {
+    let invalid = |e: &dyn std::fmt::Display| RhodiError::Format(format!("Invalid bundle: {}", e));
+    let content =
+        crate::compression::read_capped(reader, *remaining, invalid).map_err(|e| match e {
+            RhodiError::Security(SecurityError::DecompressedSizeExceeded { .. }) => {
+                too_large(limit)
+            }
+            e => e,
+        })?;
+    *remaining -= content.len() as u64;
+    Ok(content)
+}
+
+fn too_large(limit: u64) -> RhodiError {
+    RhodiError::Security(SecurityError::BundleSizeExceeded { limit })
+}
@@ -0,0 +1,189 @@
+//! Persistent cache of remote sources.
+//!
+//! The HTTP resolver keeps what it fetches in an [`HttpCache`], by default
+//! under the workspace's `.rhodi/cache/http`, together with the `ETag` and
+//! `Last-Modified` the server sent. A cached source is revalidated with a
+//! conditional request (`If-None-Match`, `If-Modified-Since`) every time it
+//! is used: a `304 Not Modified` answer re-uses the stored bytes instead of
+//! downloading them again, and a changed source is still noticed. Once the
+//! cache grows past its size limit, the least recently used entries are
+//! evicted.
+
+use crate::error::{Result, RhodiError};
+use crate::lock::{write_atomic, write_atomic_bytes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default limit on the total size of cached sources: 1 GiB.
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// What is known about a cached source, stored next to its bytes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CacheEntry {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// The `Last-Modified` header as sent, for `If-Modified-Since`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub size: u64,
+    /// Hex SHA-256 of the bytes, checked whenever they are read back
+    pub sha256: String,
+    pub last_used: DateTime<Utc>,
+}
+
+impl CacheEntry {
+    /// Whether the server gave anything to revalidate the entry with.
+    pub fn revalidatable(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+pub struct HttpCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl HttpCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: DEFAULT_CACHE_MAX_BYTES,
+        }
+    }
+
+    /// Limit the total size of cached sources. Sources larger than the
+    /// limit are not cached at all.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Entry files are named by the SHA-256 of the URL.
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = hex::encode(Sha256::digest(url.as_bytes()));
+        (
+            self.dir.join(format!("{}.json", key)),
+            self.dir.join(format!("{}.bin", key)),
+        )
+    }
+
+    /// The entry for `url`, if one is cached with its bytes.
+    pub fn lookup(&self, url: &str) -> Option<CacheEntry> {
+        let (meta, body) = self.paths(url);
+        let entry: CacheEntry = serde_json::from_str(&fs::read_to_string(meta).ok()?).ok()?;
+        let size = fs::metadata(body).ok()?.len();
+        (entry.url == url && entry.size == size).then_some(entry)
+    }
+
+    /// The bytes of `entry`, marking it as just used.
+    pub fn read(&self, entry: &CacheEntry) -> Result<Vec<u8>> {
+        let (meta, body) = self.paths(&entry.url);
+        let bytes = fs::read(body)?;
+        if hex::encode(Sha256::digest(&bytes)) != entry.sha256 {
+            self.remove(&entry.url)?;
+            return Err(RhodiError::Resolution(format!(
+                "Cached copy of {} was corrupt and has been dropped; it is downloaded again next time",
+                entry.url
+            )));
+        }
+        let entry = CacheEntry {
+            last_used: Utc::now(),
+            ..entry.clone()
+        };
+        write_atomic(&meta, &encode(&entry)?)?;
+        Ok(bytes)
+    }
+
+    /// Cache `bytes` as fetched from `url`, then evict the least recently
+    /// used entries until the cache fits its limit again. Sources the
+    /// server gave no validators for are not cached, since they could
+    /// never be revalidated.
+    pub fn store(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<()> {
+        let entry = CacheEntry {
+            url: url.to_string(),
+            etag: etag.map(str::to_string),
+            last_modified: last_modified.map(str::to_string),
+            content_type: content_type.map(str::to_string),
+            size: bytes.len() as u64,
+            sha256: hex::encode(Sha256::digest(bytes)),
+            last_used: Utc::now(),
+        };
+        if !entry.revalidatable() || entry.size > self.max_bytes {
+            return self.remove(url);
+        }
+        fs::create_dir_all(&self.dir)?;
+        let (meta, body) = self.paths(url);
+        write_atomic_bytes(&body, bytes)?;
+        write_atomic(&meta, &encode(&entry)?)?;
+        self.evict();
+        Ok(())
+    }
+
+    /// Drop the entry for `url`, if any.
+    pub fn remove(&self, url: &str) -> Result<()> {
+        let (meta, body) = self.paths(url);
+        for path in [meta, body] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Every cached entry.
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let Ok(files) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        files
+            .flatten()
+            .filter(|file| file.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|file| fs::read_to_string(file.path()).ok())
+            .filter_map(|json| serde_json::from_str::<CacheEntry>(&json).ok())
+            .filter(|entry| self.lookup(&entry.url).is_some())
+            .collect()
+    }
+
+    /// Total size of the cached sources.
+    pub fn size(&self) -> u64 {
+        self.entries().iter().map(|entry| entry.size).sum()
+    }
+
+    fn evict(&self) {
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        entries.sort_by_key(|entry| entry.last_used);
+        for entry in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            // An entry that cannot be removed still takes up its space
+            if self.remove(&entry.url).is_ok() {
+                total -= entry.size;
+            }
+        }
+    }
+}
+
+fn encode(entry: &CacheEntry) -> Result<String> {
+    serde_json::to_string_pretty(entry)
+        .map_err(|e| RhodiError::Serialization(format!("Failed to encode cache entry: {}", e)))
+}
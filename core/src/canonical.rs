@@ -0,0 +1,85 @@
+//! JSON Canonicalization Scheme (RFC 8785) used by protocol 2.0 hashing.
+//!
+//! Object members are sorted by their UTF-16 code units, no insignificant
+//! whitespace is emitted, and strings use the minimal JSON escaping of
+//! ECMAScript's `JSON.stringify`. The version hash only feeds strings,
+//! booleans, integers and nested objects through this encoder.
+
+use crate::error::{Result, RhodiError};
+use serde_json::Value;
+
+/// Serialize `value` in RFC 8785 canonical form.
+pub fn to_jcs(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                out.push_str(&i.to_string());
+            } else if let Some(u) = n.as_u64() {
+                out.push_str(&u.to_string());
+            } else {
+                let f = n.as_f64().filter(|f| f.is_finite()).ok_or_else(|| {
+                    RhodiError::Serialization(format!("Cannot canonicalize number {}", n))
+                })?;
+                // Integral values are written without a fraction, as in ECMAScript
+                if f.fract() == 0.0 && f.abs() < 1e21 {
+                    out.push_str(&format!("{}", f as i128));
+                } else {
+                    out.push_str(&n.to_string());
+                }
+            }
+        }
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut members: Vec<(&String, &Value)> = map.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(member, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
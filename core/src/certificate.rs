@@ -0,0 +1,142 @@
+//! Organization key hierarchy: a root key certifying author keys.
+//!
+//! An organization keeps one root key and issues each author a
+//! [`KeyCertificate`] binding the author's public key to the root. A document
+//! carries its author's certificate in the `key_certificate` frontmatter
+//! field, either inline or as a path to the certificate's JSON file. Verifying
+//! with a trust root (`rhodi verify --trust-root`) then accepts any document
+//! signed by a key the root has certified, without knowing the author keys in
+//! advance. Certificates are not covered by the version hash, so one can be
+//! attached or renewed without resealing.
+
+use crate::crypto::{KeyPair, parse_public_key, parse_signature};
+use crate::error::{Result, RhodiError};
+use crate::models::TracedDocument;
+use crate::version::VersionRegistry;
+use chrono::{DateTime, SecondsFormat, Utc};
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+/// Domain separator so certificate signatures can never be confused with
+/// seals, notarizations or attestations.
+const CERTIFICATE_CONTEXT: &str = "rhodi-key-certificate-v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct KeyCertificate {
+    /// Hex-encoded public key of the certified author
+    pub subject: String,
+    /// Display name of the author, covered by the signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Hex-encoded public key of the root that issued the certificate
+    pub issuer: String,
+    pub issued_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Hex-encoded Ed25519 signature by the issuer
+    pub signature: String,
+}
+
+/// The `key_certificate` frontmatter field: the certificate itself, or a
+/// path (resolved like a trace source) to its JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum CertificateRef {
+    Inline(KeyCertificate),
+    Path(String),
+}
+
+impl KeyCertificate {
+    /// Certify `subject` (hex public key) under `root`.
+    pub fn issue(
+        root: &KeyPair,
+        subject: &str,
+        name: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Self> {
+        let subject = parse_public_key(subject)?;
+        let mut certificate = Self {
+            subject: hex::encode(subject.as_bytes()),
+            name: name.map(str::to_string),
+            issuer: hex::encode(root.verifying_key.as_bytes()),
+            issued_at: Utc::now(),
+            expires_at,
+            signature: String::new(),
+        };
+        certificate.signature = hex::encode(root.sign(&certificate.message()).to_bytes());
+        Ok(certificate)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| RhodiError::Format(format!("Invalid key certificate: {}", e)))
+    }
+
+    /// The exact bytes covered by the signature.
+    fn message(&self) -> Vec<u8> {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            CERTIFICATE_CONTEXT,
+            self.issuer,
+            self.subject,
+            self.name.as_deref().unwrap_or_default(),
+            self.issued_at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.expires_at
+                .map(|t| t.to_rfc3339_opts(SecondsFormat::Nanos, true))
+                .unwrap_or_default()
+        )
+        .into_bytes()
+    }
+
+    /// Check that the certificate was issued by `root` and is valid at `at`.
+    pub fn verify(&self, root: &VerifyingKey, at: DateTime<Utc>) -> Result<()> {
+        if self.issuer != hex::encode(root.as_bytes()) {
+            return Err(RhodiError::Verification(format!(
+                "Key certificate was issued by {}, not the trust root",
+                self.issuer
+            )));
+        }
+        let signature = parse_signature(&self.signature)?;
+        root.verify_strict(&self.message(), &signature)
+            .map_err(|e| RhodiError::Crypto(format!("Key certificate signature invalid: {}", e)))?;
+        if at < self.issued_at {
+            return Err(RhodiError::Verification(format!(
+                "Key certificate is not valid before {}",
+                self.issued_at
+            )));
+        }
+        if let Some(expires_at) = self.expires_at
+            && at >= expires_at
+        {
+            return Err(RhodiError::Verification(format!(
+                "Key certificate expired at {}",
+                expires_at
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check that `doc` is signed by the subject of `certificate` and that the
+/// certificate chains to `root` now. Expiry is judged at verification time:
+/// once an author's certificate lapses, their documents are no longer
+/// accepted under the root. The document may use the protocol versions in
+/// `versions`.
+pub fn check_trust_root(
+    doc: &TracedDocument,
+    certificate: &KeyCertificate,
+    root: &VerifyingKey,
+    versions: &VersionRegistry,
+) -> Result<()> {
+    certificate.verify(root, Utc::now())?;
+    if let Some(pk_hex) = &doc.frontmatter.public_key
+        && pk_hex != &certificate.subject
+    {
+        return Err(RhodiError::Verification(format!(
+            "Key certificate is for {}, but the document declares public_key {}",
+            certificate.subject, pk_hex
+        )));
+    }
+    doc.verify_in(parse_public_key(&certificate.subject)?.as_bytes(), versions)
+}
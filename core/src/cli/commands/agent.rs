@@ -0,0 +1,86 @@
+use crate::agent::AgentClient;
+use crate::cli::io::{base_dir, note, read_document, write_document};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::markdown::{parse_tmd, render_tmd_preserving, update_trace_blocks};
+use crate::models::TraceMethod;
+use crate::resolver::SourceResolver;
+use serde_norway::{Mapping, Value};
+use std::path::PathBuf;
+
+/// Run every `method: agent` trace through the configured agent, recording
+/// `agent_metadata` and filling in (or confirming) `expected`.
+pub fn run(
+    path: PathBuf,
+    endpoint: Option<String>,
+    model: Option<String>,
+    fill: bool,
+) -> Result<()> {
+    let content = read_document(&path)?;
+    let mut doc = parse_tmd(&content)?;
+
+    let base_path = base_dir(&path)?;
+    let workspace = Workspace::for_document(&path)?;
+    let resolver = workspace.resolver(&base_path)?;
+    let client = AgentClient::from_config(endpoint, model, &workspace.config.agent)?;
+
+    let mut processed = 0;
+    let mut mismatches = Vec::new();
+    doc.body = update_trace_blocks(&doc.body, |trace| {
+        if trace.method != TraceMethod::Agent {
+            return Ok(None);
+        }
+        let source = resolver.resolve_bytes(&doc.frontmatter.resolve_trace(trace)?.source)?;
+        let answer = client.extract(trace, &source)?;
+        processed += 1;
+
+        let metadata = serde_norway::to_value(&answer.metadata).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to serialize agent metadata: {}", e))
+        })?;
+        let mut fields = Mapping::new();
+        fields.insert("agent_metadata".into(), metadata);
+
+        if fill || trace.expected.trim().is_empty() {
+            note(
+                &path,
+                format!("  {}: expected set to '{}'", trace.source, answer.value),
+            );
+            fields.insert("expected".into(), Value::String(answer.value));
+        } else if trace.accepts(&answer.value)? {
+            note(
+                &path,
+                format!("  {}: confirmed '{}'", trace.source, trace.expected),
+            );
+        } else {
+            mismatches.push(format!(
+                "{}: expected {}, agent found '{}'",
+                trace.source,
+                trace.comparison()?.describe(&trace.expected),
+                answer.value
+            ));
+        }
+        Ok(Some(Value::Mapping(fields)))
+    })?;
+
+    if processed == 0 {
+        note(&path, "No traces with method: agent");
+        return Ok(());
+    }
+    write_document(&path, &render_tmd_preserving(&content, &doc)?)?;
+    note(
+        &path,
+        format!(
+            "{} agent trace(s) recorded; re-seal to sign the changes",
+            processed
+        ),
+    );
+
+    if !mismatches.is_empty() {
+        return Err(RhodiError::Verification(format!(
+            "Agent disagrees with {} trace(s):\n  - {}",
+            mismatches.len(),
+            mismatches.join("\n  - ")
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::cli::io::{is_stdio, lock_document, note, read_document, write_document};
+use crate::cli::keys::KeyManager;
+use crate::config::Workspace;
+use crate::crypto::KeyPair;
+use crate::error::{Result, RhodiError};
+use crate::history::archive;
+use crate::markdown::{parse_tmd, render_sealed};
+use crate::models::{Amendment, Author};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Command-line options for `rhodi amend`.
+#[derive(Debug, Default, Clone)]
+pub struct AmendOptions {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// `KEY=VALUE` entries to set in `extra`
+    pub set: Vec<String>,
+    /// Keys to remove from `extra`
+    pub unset: Vec<String>,
+    pub reason: String,
+    /// Key name to sign with
+    pub key_name: Option<String>,
+}
+
+pub fn run(path: PathBuf, options: AmendOptions) -> Result<()> {
+    let AmendOptions {
+        title,
+        author,
+        set,
+        unset,
+        reason,
+        key_name,
+    } = options;
+    let mut extra = BTreeMap::new();
+    for entry in set {
+        let (key, value) = entry.split_once('=').ok_or_else(|| {
+            RhodiError::Format(format!("Expected KEY=VALUE for --set, got '{}'", entry))
+        })?;
+        extra.insert(key.trim().to_string(), Some(value.to_string()));
+    }
+    for key in unset {
+        extra.insert(key, None);
+    }
+    let amendment = Amendment {
+        title,
+        author: author.map(|name| Author {
+            name,
+            ..Author::default()
+        }),
+        extra,
+        reason,
+    };
+
+    let _lock = lock_document(&path)?;
+    let content = read_document(&path)?;
+    let doc = parse_tmd(&content)?;
+    let previous = doc.frontmatter.doc_version;
+
+    let workspace = Workspace::for_document(&path)?;
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+
+    let (doc, sealed) = render_sealed(&content, doc.amend(&amendment, &keypair)?, &keypair)?;
+    write_document(&path, &sealed)?;
+    if !is_stdio(&path) {
+        archive(&workspace.history_dir(), &doc, &sealed)?;
+    }
+
+    let extra = doc.frontmatter.extra.as_ref();
+    let field = |key: &str| extra.and_then(|extra| extra.get(key)).cloned();
+    note(
+        &path,
+        format!(
+            "Amended: {} (v{} -> v{})",
+            path.display(),
+            previous,
+            doc.frontmatter.doc_version
+        ),
+    );
+    note(
+        &path,
+        format!("  Changed: {}", field("amended_fields").unwrap_or_default()),
+    );
+    note(
+        &path,
+        format!(
+            "  Version hash: {}",
+            hex::encode(doc.frontmatter.version_hash.unwrap_or_default())
+        ),
+    );
+    Ok(())
+}
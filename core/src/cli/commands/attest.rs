@@ -0,0 +1,116 @@
+use crate::attestation::{self, Attestation, Verdict};
+use crate::cli::commands::verify::VerifyOptions;
+use crate::cli::keys::KeyManager;
+use crate::config::Workspace;
+use crate::crypto::KeyPair;
+use crate::error::Result;
+use crate::markdown::parse_tmd;
+use crate::report::VerificationReport;
+use std::fs;
+use std::path::PathBuf;
+
+/// Verify a document and record a signed attestation of the result. With
+/// `report_file`, the verification report is written there, pass or fail.
+pub fn run(
+    path: PathBuf,
+    key_name: Option<String>,
+    ledger: bool,
+    report_file: Option<PathBuf>,
+) -> Result<()> {
+    let options = VerifyOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let mut record = VerificationReport::new("attest");
+    let report =
+        crate::cli::commands::verify::run_recorded(Some(path.clone()), options, &mut record);
+    if let Some(ref file) = report_file {
+        record.write(file)?;
+    }
+    let report = report?;
+    let doc = parse_tmd(&fs::read_to_string(&path)?)?;
+
+    let workspace = Workspace::for_document(&path)?;
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+
+    let attestation = Attestation::create(&doc, &keypair, report.warnings)?;
+    let log = if ledger {
+        workspace.ledger_path()
+    } else {
+        attestation::attestations_path(&path)
+    };
+    attestation::append(&log, &attestation)?;
+
+    println!("Verification attested: {}", path.display());
+    println!("  Version hash: {}", attestation.version_hash);
+    println!("  Verifier: {}", attestation.verifier);
+    println!("  Verified at: {}", attestation.verified_at);
+    println!("  Recorded in: {}", log.display());
+
+    Ok(())
+}
+
+/// List the attestations recorded for a document and check their signatures.
+pub fn list(path: PathBuf, ledger: bool) -> Result<()> {
+    let doc = parse_tmd(&fs::read_to_string(&path)?)?;
+    let log = if ledger {
+        Workspace::for_document(&path)?.ledger_path()
+    } else {
+        attestation::attestations_path(&path)
+    };
+
+    let attestations: Vec<Attestation> = attestation::read_all(&log)?
+        .into_iter()
+        .filter(|a| a.doc_id == doc.frontmatter.id)
+        .collect();
+
+    if attestations.is_empty() {
+        println!("No attestations recorded in {}", log.display());
+        return Ok(());
+    }
+
+    println!("Attestations ({}):", log.display());
+    for a in &attestations {
+        let signature = if a.verify().is_ok() {
+            "valid"
+        } else {
+            "INVALID"
+        };
+        let version = if a.covers(&doc) {
+            "current"
+        } else {
+            "older version"
+        };
+        println!(
+            "  - {} by {} (v{}, {}, signature {})",
+            a.verified_at, a.verifier, a.doc_version, version, signature
+        );
+        if !a.reviews.is_empty() {
+            let flagged: Vec<_> = a
+                .reviews
+                .iter()
+                .filter(|r| r.verdict == Verdict::Flagged)
+                .collect();
+            println!(
+                "      reviewed {} trace(s), {} flagged",
+                a.reviews.len(),
+                flagged.len()
+            );
+            for review in flagged {
+                match &review.note {
+                    Some(note) => println!("        ! {}: {}", review.trace, note),
+                    None => println!("        ! {}", review.trace),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::error::Result;
+use crate::identity::{AuthorProfile, ProfileStore};
+
+pub fn add(profile: AuthorProfile) -> Result<()> {
+    let store = ProfileStore::new()?;
+    store.save(&profile)?;
+    println!("Author profile '{}' saved.", profile.profile);
+    println!(
+        "Use it with: rhodi init --author-profile {0} or rhodi seal <doc> --author-profile {0}",
+        profile.profile
+    );
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    let profiles = ProfileStore::new()?.list()?;
+    if profiles.is_empty() {
+        println!("No author profiles found. Run 'rhodi author add' to create one.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<24} {:<20} {:<12} EMAIL",
+        "PROFILE", "NAME", "ORCID", "KEY"
+    );
+    for profile in profiles {
+        println!(
+            "{:<16} {:<24} {:<20} {:<12} {}",
+            profile.profile,
+            profile.name,
+            profile.orcid.as_deref().unwrap_or("-"),
+            profile.key.as_deref().unwrap_or("-"),
+            profile.email.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+pub fn remove(profile: String) -> Result<()> {
+    ProfileStore::new()?.remove(&profile)?;
+    println!("Author profile '{}' removed.", profile);
+    Ok(())
+}
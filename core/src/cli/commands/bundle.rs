@@ -0,0 +1,49 @@
+use crate::bundle::write_documents_bundle;
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::index::WorkspaceIndex;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+/// Pack documents and their local evidence into a tar bundle: those at
+/// `paths`, or with `workspace`, every document of the current workspace.
+pub fn run(paths: Vec<PathBuf>, workspace: bool, output: Option<PathBuf>) -> Result<()> {
+    let paths = if workspace {
+        let workspace = Workspace::discover(&std::env::current_dir()?)?;
+        let index = WorkspaceIndex::build(&workspace.root)?;
+        index
+            .documents
+            .iter()
+            .map(|doc| index.root.join(&doc.path))
+            .collect()
+    } else {
+        paths
+    };
+    if paths.is_empty() {
+        return Err(RhodiError::Resolution("No documents to bundle".to_string()));
+    }
+    let output = match (output, paths.as_slice()) {
+        (Some(output), _) => output,
+        (None, [path]) => path.with_extension("rhodi.tar"),
+        (None, _) => PathBuf::from("workspace.rhodi.tar"),
+    };
+    let manifest = write_documents_bundle(&paths, BufWriter::new(File::create(&output)?))?;
+
+    println!("Created bundle: {}", output.display());
+    if manifest.documents.len() == 1 {
+        println!("  Document: {}", manifest.document);
+    } else {
+        println!("  Documents: {}", manifest.documents.len());
+    }
+    let (listed, stored) = manifest.file_counts();
+    if listed == stored {
+        println!("  Files: {}", listed);
+    } else {
+        println!(
+            "  Files: {} ({} stored, shared evidence deduplicated)",
+            listed, stored
+        );
+    }
+    Ok(())
+}
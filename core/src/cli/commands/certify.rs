@@ -0,0 +1,78 @@
+use crate::certificate::{CertificateRef, KeyCertificate};
+use crate::cli::keys::KeyManager;
+use crate::config::Workspace;
+use crate::crypto::KeyPair;
+use crate::discovery::parse_key;
+use crate::error::{Result, RhodiError};
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::models::parse_duration;
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+
+/// Issue a certificate for `subject` (hex or `did:key:`) signed with the
+/// root key `key_name`. The certificate is embedded in `attach`, written to
+/// `output`, or printed.
+pub fn run(
+    subject: String,
+    key_name: Option<String>,
+    name: Option<String>,
+    expires_in: Option<String>,
+    output: Option<PathBuf>,
+    attach: Option<PathBuf>,
+) -> Result<()> {
+    let workspace = Workspace::discover(&std::env::current_dir()?)?;
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let root = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+
+    let subject = hex::encode(parse_key(&subject)?.as_bytes());
+    let expires_at = expires_in
+        .as_deref()
+        .map(|d| parse_duration(d).map(|d| Utc::now() + d))
+        .transpose()?;
+    let certificate = KeyCertificate::issue(&root, &subject, name.as_deref(), expires_at)?;
+
+    if let Some(path) = attach {
+        let _lock = FileLock::acquire(&path, DEFAULT_LOCK_TIMEOUT)?;
+        let content = fs::read_to_string(&path)?;
+        let mut doc = parse_tmd(&content)?;
+        if let Some(pk_hex) = &doc.frontmatter.public_key
+            && pk_hex != &subject
+        {
+            return Err(RhodiError::Verification(format!(
+                "Document is signed by {}, not the certified key",
+                pk_hex
+            )));
+        }
+        doc.frontmatter.key_certificate = Some(CertificateRef::Inline(certificate.clone()));
+        write_atomic(&path, &render_tmd_preserving(&content, &doc)?)?;
+        println!("Attached key certificate to {}", path.display());
+    } else {
+        let json = serde_json::to_string_pretty(&certificate).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to write certificate: {}", e))
+        })?;
+        match output {
+            Some(ref output) => {
+                fs::write(output, json + "\n")?;
+                println!("Created key certificate: {}", output.display());
+            }
+            None => {
+                println!("{}", json);
+                return Ok(());
+            }
+        }
+    }
+    println!("  Subject: {}", certificate.subject);
+    println!("  Issuer: {}", certificate.issuer);
+    if let Some(expires_at) = certificate.expires_at {
+        println!("  Expires: {}", expires_at);
+    }
+    Ok(())
+}
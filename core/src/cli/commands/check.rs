@@ -0,0 +1,144 @@
+use crate::cli::io::{base_dir, is_stdio, read_document};
+use crate::cli::keys::KeyManager;
+use crate::compiler::{Compiler, VerifyPasses};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::index::documents_with_id;
+use crate::markdown::{Section, parse_tmd, parse_tmd_sections};
+use crate::models::DocStatus;
+use std::path::PathBuf;
+
+/// One pre-flight check and what it found wrong.
+struct Check {
+    name: &'static str,
+    summary: String,
+    problems: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, summary: impl Into<String>, problems: Vec<String>) -> Self {
+        Self {
+            name,
+            summary: summary.into(),
+            problems,
+        }
+    }
+}
+
+/// Run everything `seal` validates, without writing anything, and report
+/// whether the document is ready to seal.
+pub fn run(path: PathBuf, key_name: Option<String>) -> Result<()> {
+    let content = read_document(&path)?;
+    let mut doc = parse_tmd(&content)?;
+    let base_path = base_dir(&path)?;
+    let workspace = Workspace::for_document(&path)?;
+    let resolver = workspace.resolver(&base_path)?;
+    let mut checks = Vec::new();
+
+    let published = doc.frontmatter.doc_status == DocStatus::Published;
+    checks.push(Check::new(
+        "Status",
+        format!("{:?}", doc.frontmatter.doc_status),
+        if published {
+            vec!["Document is already published. Create a new version instead.".to_string()]
+        } else {
+            Vec::new()
+        },
+    ));
+
+    // A copied document keeps the id of the document it was copied from
+    let mut shared = Vec::new();
+    if !is_stdio(&path) {
+        let file = path.canonicalize()?;
+        for other in documents_with_id(&workspace.root, doc.frontmatter.id)? {
+            if workspace.root.join(&other) == file {
+                continue;
+            }
+            shared.push(format!(
+                "{} has the same id; give this copy its own with 'rhodi fix-id {}'",
+                other.display(),
+                path.display()
+            ));
+        }
+    }
+    checks.push(Check::new(
+        "Identity",
+        doc.frontmatter.id.to_string(),
+        shared,
+    ));
+
+    let sections = parse_tmd_sections(&doc.body);
+    let traces = sections
+        .iter()
+        .filter(|s| matches!(s, Section::Trace(_)))
+        .count();
+    let includes = sections
+        .iter()
+        .filter(|s| matches!(s, Section::Include(_)))
+        .count();
+
+    // Work on the refreshed hashes, as seal does
+    let hashed = doc.update_all_traces(&base_path);
+    checks.push(Check::new(
+        "Sources",
+        format!("{} trace(s) resolved and hashed", traces),
+        hashed.err().map(|e| e.to_string()).into_iter().collect(),
+    ));
+
+    let compiler = Compiler::new(&resolver)
+        .with_exec_extractor(workspace.exec_extractor())
+        .with_presets(workspace.config.presets.clone())
+        .with_version_registry(workspace.version_registry()?)
+        .with_limits(workspace.config.limits.compiler_limits());
+    let failing = compiler.failing_automatic_traces(&doc);
+    checks.push(Check::new(
+        "Extraction",
+        "automatic traces match their expected values",
+        failing
+            .iter()
+            .map(|(trace, error)| format!("{}: {}", trace.label(), error))
+            .collect(),
+    ));
+
+    let report = compiler
+        .with_passes(VerifyPasses::SKIP_TRACES)
+        .verify(&doc)?;
+    checks.push(Check::new(
+        "Includes",
+        format!("{} include(s) resolved and permitted", includes),
+        report.errors.iter().map(|e| e.to_string()).collect(),
+    ));
+
+    let key_name = key_name
+        .or(workspace.config.keys.default)
+        .unwrap_or_else(|| "default".to_string());
+    let key = KeyManager::new().and_then(|manager| manager.get_key(&key_name));
+    checks.push(Check::new(
+        "Key",
+        key_name,
+        key.err().map(|e| e.to_string()).into_iter().collect(),
+    ));
+
+    println!("Pre-flight check: {}", path.display());
+    for check in &checks {
+        let mark = if check.problems.is_empty() {
+            "✓"
+        } else {
+            "✗"
+        };
+        println!("  {} {}: {}", mark, check.name, check.summary);
+        for problem in &check.problems {
+            println!("      - {}", problem);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.problems.is_empty()).count();
+    if failed > 0 {
+        return Err(RhodiError::Verification(format!(
+            "Not ready to seal: {} check(s) failed",
+            failed
+        )));
+    }
+    println!("Ready to seal.");
+    Ok(())
+}
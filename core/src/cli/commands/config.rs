@@ -0,0 +1,36 @@
+use crate::config::{Workspace, update_config};
+use crate::error::Result;
+
+pub fn get(key: Option<String>) -> Result<()> {
+    let workspace = Workspace::discover(&std::env::current_dir()?)?;
+
+    match key {
+        Some(key) => match workspace.config.get(&key)? {
+            Some(value) => println!("{}", value),
+            None => {
+                return Err(crate::error::RhodiError::Format(format!(
+                    "Unknown config key '{}'",
+                    key
+                )));
+            }
+        },
+        None => {
+            if let Some(path) = &workspace.config_path {
+                println!("# {}", path.display());
+            }
+            print!("{}", workspace.config.to_toml()?);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn set(key: String, value: String) -> Result<()> {
+    let workspace = Workspace::discover(&std::env::current_dir()?)?;
+    let path = workspace.config_file();
+
+    update_config(&path, |config| config.set(&key, &value))?;
+
+    println!("Set {} in {}", key, path.display());
+    Ok(())
+}
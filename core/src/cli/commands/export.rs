@@ -0,0 +1,88 @@
+use crate::artifact::{self, ArtifactRecord};
+use crate::badge;
+use crate::cli::io::base_dir;
+use crate::cli::keys::KeyManager;
+use crate::compiler::Compiler;
+use crate::config::Workspace;
+use crate::crypto::{KeyPair, parse_public_key};
+use crate::error::{Result, RhodiError};
+use crate::markdown::parse_tmd;
+use std::fs;
+use std::path::PathBuf;
+
+/// Record `artifact` as exported from the sealed document at `path`,
+/// rendering it to HTML first when `render` is set, with status badges when
+/// `badges` is.
+pub fn run(
+    path: PathBuf,
+    artifact: PathBuf,
+    render: bool,
+    badges: bool,
+    key_name: Option<String>,
+) -> Result<()> {
+    let doc = parse_tmd(&fs::read_to_string(&path)?)?;
+    if doc.frontmatter.signature.is_none() {
+        return Err(RhodiError::Verification(format!(
+            "{} is not sealed; run 'rhodi seal' before exporting",
+            path.display()
+        )));
+    }
+
+    let workspace = Workspace::for_document(&path)?;
+    let versions = workspace.version_registry()?;
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+    let pk_hex = doc.frontmatter.public_key.as_deref().ok_or_else(|| {
+        RhodiError::Verification("Document has no public_key to verify against".into())
+    })?;
+    doc.verify_in(parse_public_key(pk_hex)?.as_bytes(), &versions)?;
+
+    if render && badges {
+        let resolver = workspace.resolver(&base_dir(&path)?)?;
+        let compiler = Compiler::new(&resolver)
+            .with_exec_extractor(workspace.exec_extractor())
+            .with_presets(workspace.config.presets.clone())
+            .with_version_registry(versions)
+            .with_limits(workspace.config.limits.compiler_limits());
+        let verified = compiler.verify_detailed(&doc)?;
+        let rendered = if artifact.extension().is_some_and(|ext| ext == "md") {
+            badge::render_markdown_with_badges(&doc, &verified)
+        } else {
+            badge::render_html_with_badges(&doc, &verified)
+        };
+        fs::write(&artifact, rendered)?;
+    } else if render {
+        fs::write(&artifact, artifact::render_html(&doc))?;
+    }
+    let content = fs::read(&artifact).map_err(|e| {
+        RhodiError::Resolution(format!(
+            "Cannot read artifact '{}': {}",
+            artifact.display(),
+            e
+        ))
+    })?;
+    let name = artifact
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let record = ArtifactRecord::create(&doc, &name, &content, &keypair)?;
+    let log = artifact::artifacts_path(&path);
+    artifact::append(&log, &record)?;
+
+    println!("Artifact recorded: {}", artifact.display());
+    println!("  Hash: {}", record.hash);
+    println!(
+        "  Document version: v{} ({})",
+        record.doc_version, record.version_hash
+    );
+    println!("  Recorded in: {}", log.display());
+
+    Ok(())
+}
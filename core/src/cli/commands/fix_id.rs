@@ -0,0 +1,133 @@
+use crate::cli::io::{is_stdio, lock_document, read_document};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::index::WorkspaceIndex;
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use crate::markdown::{parse_tmd, render_tmd_preserving, retarget_include_blocks};
+use crate::models::{DocStatus, TracedDocument};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Give the document at `path` a fresh id when other workspace documents
+/// share its id, and point includes by id that pinned this document's
+/// sealed version at the new id.
+pub fn run(path: PathBuf, dry_run: bool) -> Result<()> {
+    if is_stdio(&path) {
+        return Err(RhodiError::Resolution(
+            "fix-id needs a document in a workspace, not stdin".into(),
+        ));
+    }
+    let _lock = lock_document(&path)?;
+    let file = path.canonicalize()?;
+    let workspace = Workspace::for_document(&path)?;
+    let index = WorkspaceIndex::build(&workspace.root)?;
+    let content = read_document(&path)?;
+    let doc = parse_tmd(&content)?;
+    let old = doc.frontmatter.id;
+
+    let others: Vec<_> = index
+        .documents
+        .iter()
+        .filter(|other| other.id == old && workspace.root.join(&other.path) != file)
+        .collect();
+    if others.is_empty() {
+        println!("No other document has id {}; nothing to fix.", old);
+        return Ok(());
+    }
+
+    // Only an include pinned to a sealed version of this document and of
+    // no other copy surely meant this document; the rest keep the old id
+    let target = format!("id:{}", old);
+    let pinned = doc
+        .frontmatter
+        .version_hash
+        .map(hex::encode)
+        .filter(|hash| {
+            others
+                .iter()
+                .all(|other| other.version_hash.as_ref() != Some(hash))
+        });
+    let mut includers = Vec::new();
+    let mut unpinned = Vec::new();
+    for indexed in index
+        .documents
+        .iter()
+        .filter(|d| d.includes.contains(&target))
+    {
+        let includer = workspace.root.join(&indexed.path);
+        if includer == file {
+            continue;
+        }
+        let pin = parse_tmd(&fs::read_to_string(&includer)?)?
+            .frontmatter
+            .includes
+            .and_then(|includes| includes.get(&target).cloned());
+        match pin {
+            Some(pin) if Some(&pin) == pinned.as_ref() => includers.push(indexed.path.clone()),
+            Some(_) => {}
+            None => unpinned.push(indexed.path.clone()),
+        }
+    }
+
+    let new = Uuid::now_v7();
+    let shared: Vec<String> = others
+        .iter()
+        .map(|other| other.path.display().to_string())
+        .collect();
+    println!(
+        "{} shares id {} with {}",
+        path.display(),
+        old,
+        shared.join(", ")
+    );
+    if dry_run {
+        println!("Would give it id {}", new);
+        for includer in &includers {
+            println!("Would update the include in {}", includer.display());
+        }
+    } else {
+        let mut fixed = doc.clone();
+        fixed.frontmatter.id = new;
+        write_changed(&file, &content, fixed)?;
+        println!("New id: {}", new);
+
+        for includer in &includers {
+            let file = workspace.root.join(includer);
+            let _lock = FileLock::acquire(&file, DEFAULT_LOCK_TIMEOUT)?;
+            let original = fs::read_to_string(&file)?;
+            let mut doc = parse_tmd(&original)?;
+            doc.body = retarget_include_blocks(&doc.body, old, new);
+            if let Some(includes) = doc.frontmatter.includes.as_mut()
+                && let Some(hash) = includes.remove(&target)
+            {
+                includes.insert(format!("id:{}", new), hash);
+            }
+            write_changed(&file, &original, doc)?;
+            println!("Updated the include in {}", includer.display());
+        }
+    }
+    for path in &unpinned {
+        println!(
+            "Note: {} includes id {} without pinning a version; it now includes {}",
+            path.display(),
+            old,
+            shared.join(" or ")
+        );
+    }
+    if !dry_run && (doc.frontmatter.version_hash.is_some() || !includers.is_empty()) {
+        println!("The id is sealed into each version; re-seal the changed documents.");
+    }
+    Ok(())
+}
+
+/// Write `doc`, read from `original`, back to `file`. The id and includes
+/// are covered by the version hash, so a published document becomes a
+/// draft again.
+fn write_changed(file: &Path, original: &str, mut doc: TracedDocument) -> Result<()> {
+    if doc.frontmatter.doc_status == DocStatus::Published {
+        doc.frontmatter.doc_status = DocStatus::Draft;
+    }
+    let doc = doc.update_modified_time();
+    write_atomic(file, &render_tmd_preserving(original, &doc)?)
+}
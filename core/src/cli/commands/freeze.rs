@@ -0,0 +1,75 @@
+use crate::cli::keys::KeyManager;
+use crate::config::Workspace;
+use crate::crypto::KeyPair;
+use crate::error::{Result, RhodiError};
+use crate::freeze::{FREEZE_FILE_NAME, WorkspaceManifest};
+use std::path::PathBuf;
+
+/// Record the version hash of every sealed document in the workspace in a
+/// signed manifest.
+pub fn run(
+    dir: Option<PathBuf>,
+    key_name: Option<String>,
+    release: Option<String>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let workspace = Workspace::discover(&dir.unwrap_or_else(|| PathBuf::from(".")))?;
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+
+    let manifest = WorkspaceManifest::create(&workspace.root, release, &keypair)?;
+    let output = output.unwrap_or_else(|| workspace.root.join(FREEZE_FILE_NAME));
+    manifest.write(&output)?;
+
+    match &manifest.release {
+        Some(release) => println!("Release {} frozen: {}", release, output.display()),
+        None => println!("Workspace frozen: {}", output.display()),
+    }
+    for doc in &manifest.documents {
+        println!(
+            "  {} v{} {}",
+            doc.path,
+            doc.doc_version,
+            doc.version_hash.chars().take(16).collect::<String>()
+        );
+    }
+    println!("  Signer: {}", manifest.signer);
+    Ok(())
+}
+
+/// Check the workspace against a manifest written by [`run`].
+pub fn check(dir: Option<PathBuf>, manifest: Option<PathBuf>) -> Result<()> {
+    let workspace = Workspace::discover(&dir.unwrap_or_else(|| PathBuf::from(".")))?;
+    let path = manifest.unwrap_or_else(|| workspace.root.join(FREEZE_FILE_NAME));
+    let manifest = WorkspaceManifest::read(&path)?;
+    let report = manifest.check(&workspace.root)?;
+
+    for warning in &report.warnings {
+        println!("Warning: {}", warning);
+    }
+    for error in &report.errors {
+        println!("Error: {}", error);
+    }
+    if !report.errors.is_empty() {
+        return Err(RhodiError::Verification(format!(
+            "Workspace does not match {} ({} error(s))",
+            path.display(),
+            report.errors.len()
+        )));
+    }
+
+    println!(
+        "Release {} verified: {} document(s) at their frozen versions",
+        manifest.release.as_deref().unwrap_or("(unnamed)"),
+        manifest.documents.len()
+    );
+    println!("  Frozen at: {}", manifest.frozen_at);
+    println!("  Signer: {}", manifest.signer);
+    Ok(())
+}
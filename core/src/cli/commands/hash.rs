@@ -0,0 +1,64 @@
+use crate::cli::io::is_stdio;
+use crate::compiler::extract_value;
+use crate::compression::split_archive_entry;
+use crate::config::Workspace;
+use crate::crypto::hash_bytes;
+use crate::error::Result;
+use crate::models::TraceBlock;
+use crate::resolver::guess_content_type;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Print the `sha256:` digest of `path` (or stdin for `-`) as a trace's
+/// `hash` field expects it. With a selector, also print the value it
+/// extracts, as `hash`/`expected` lines ready to paste into a trace block.
+/// With `normalize` or `compression`, both are computed over the
+/// normalized source, as for a trace with those fields.
+pub fn run(
+    path: PathBuf,
+    selector: Option<String>,
+    extractor: Option<String>,
+    transforms: Vec<String>,
+    normalize: Option<String>,
+    compression: Option<String>,
+) -> Result<()> {
+    let source = path.to_string_lossy().into_owned();
+    let content = if is_stdio(&path) {
+        let mut content = Vec::new();
+        std::io::stdin().read_to_end(&mut content)?;
+        content
+    } else {
+        fs::read(split_archive_entry(&source).0)?
+    };
+    let trace = TraceBlock {
+        source,
+        extractor,
+        transforms,
+        compression,
+        normalize,
+        ..Default::default()
+    };
+    let content = trace.normalized_source(&content)?;
+
+    let Some(selector) = selector else {
+        println!("{}", hash_bytes(&content));
+        return Ok(());
+    };
+    let workspace = Workspace::discover(&std::env::current_dir()?)?;
+    let value = extract_value(
+        &trace,
+        &selector,
+        &content,
+        guess_content_type(&trace.source),
+        workspace.exec_extractor().as_ref(),
+        None,
+    )?;
+    println!("hash: {}", hash_bytes(&content));
+    // A JSON string is a valid YAML scalar, whatever the value contains
+    println!(
+        "expected: {}",
+        serde_json::to_string(&value).unwrap_or(value)
+    );
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::cli::git::hooks_dir;
+use crate::error::{Result, RhodiError};
+use std::fs;
+
+/// First line after the shebang of every hook rhodi writes, so reinstalling
+/// can tell its own hooks from the user's.
+const MARKER: &str = "# Installed by rhodi hooks install";
+
+/// Verifies the staged version of every staged `.tmd` file. Drafts only warn;
+/// a Published document whose hash or signature fails blocks the commit.
+const PRE_COMMIT: &str = r#"#!/bin/sh
+# Installed by rhodi hooks install
+exec "${RHODI:-rhodi}" verify --staged-only --skip-traces
+"#;
+
+/// Verifies every `.tmd` file changed by the pushed commits, as committed.
+const PRE_PUSH: &str = r#"#!/bin/sh
+# Installed by rhodi hooks install
+rhodi="${RHODI:-rhodi}"
+zero=0000000000000000000000000000000000000000
+status=0
+while read -r local_ref local_sha remote_ref remote_sha; do
+    [ "$local_sha" = "$zero" ] && continue
+    if [ "$remote_sha" = "$zero" ]; then
+        git ls-tree -r --name-only "$local_sha" | grep '\.tmd$'
+    else
+        git diff --name-only --diff-filter=ACMR "$remote_sha" "$local_sha" -- '*.tmd'
+    fi | {
+        failed=0
+        while IFS= read -r file; do
+            "$rhodi" verify "$file" --rev "$local_sha" --skip-traces </dev/null || failed=1
+        done
+        exit $failed
+    } || status=1
+done
+exit $status
+"#;
+
+/// Install the pre-commit and pre-push hooks in the current repository.
+/// Hooks not written by rhodi are left alone unless `force` is set.
+pub fn install(force: bool) -> Result<()> {
+    let dir = hooks_dir()?;
+    fs::create_dir_all(&dir)?;
+    for (name, script) in [("pre-commit", PRE_COMMIT), ("pre-push", PRE_PUSH)] {
+        let path = dir.join(name);
+        if !force
+            && let Ok(existing) = fs::read_to_string(&path)
+            && !existing.contains(MARKER)
+        {
+            return Err(RhodiError::Resolution(format!(
+                "{} already exists and was not installed by rhodi; use --force to replace it",
+                path.display()
+            )));
+        }
+        fs::write(&path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+        println!("Installed {}", path.display());
+    }
+    Ok(())
+}
+
+/// Remove the hooks rhodi installed, leaving any others in place.
+pub fn uninstall() -> Result<()> {
+    let dir = hooks_dir()?;
+    for name in ["pre-commit", "pre-push"] {
+        let path = dir.join(name);
+        if fs::read_to_string(&path).is_ok_and(|existing| existing.contains(MARKER)) {
+            fs::remove_file(&path)?;
+            println!("Removed {}", path.display());
+        }
+    }
+    Ok(())
+}
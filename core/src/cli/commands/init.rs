@@ -1,21 +1,68 @@
-use crate::error::Result;
-use crate::models::{DocStatus, FrontMatter};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::markdown::{markdown_title, suggest_trace_stubs};
+use crate::identity::ProfileStore;
+use crate::models::{Author, DocStatus, FrontMatter};
+use crate::version::get_latest_version;
 use chrono::Utc;
 use std::fs;
 use std::path::PathBuf;
 
-pub fn run(path: Option<PathBuf>, title: Option<String>, author: Option<String>) -> Result<()> {
-    let path = path.unwrap_or_else(|| PathBuf::from("document.tmd"));
+pub fn run(
+    path: Option<PathBuf>,
+    title: Option<String>,
+    author: Option<String>,
+    author_profile: Option<String>,
+    from_markdown: Option<PathBuf>,
+    suggest_traces: bool,
+) -> Result<()> {
+    // A converted file defaults to the source path with a .tmd extension
+    let path = path.unwrap_or_else(|| match from_markdown {
+        Some(ref md) => md.with_extension("tmd"),
+        None => PathBuf::from("document.tmd"),
+    });
 
     if path.exists() {
-        return Err(crate::error::RhodiError::Resolution(format!(
+        return Err(RhodiError::Resolution(format!(
             "File '{}' already exists",
             path.display()
         )));
     }
 
-    let title = title.unwrap_or_else(|| "Untitled Document".to_string());
-    let author = author.unwrap_or_else(|| "Anonymous".to_string());
+    let markdown = match from_markdown {
+        Some(ref md) => Some(strip_frontmatter(&fs::read_to_string(md).map_err(|e| {
+            RhodiError::Resolution(format!("Cannot read '{}': {}", md.display(), e))
+        })?)),
+        None => None,
+    };
+
+    let title = title
+        .or_else(|| markdown.as_deref().and_then(markdown_title))
+        .or_else(|| {
+            from_markdown
+                .as_ref()
+                .and_then(|md| md.file_stem())
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Untitled Document".to_string());
+    let author: Author = match author_profile {
+        Some(profile) => ProfileStore::new()?.get(&profile)?.author(),
+        None => author.unwrap_or_else(|| "Anonymous".to_string()).into(),
+    };
+
+    let workspace = Workspace::for_document(&path)?;
+    let versions = workspace.version_registry()?;
+    let protocol_version = workspace
+        .config
+        .canonicalization
+        .profile
+        .unwrap_or_else(|| get_latest_version().to_string());
+    if !versions.is_known(&protocol_version) {
+        return Err(RhodiError::Format(format!(
+            "Unknown canonicalization profile '{}' in rhodi.toml",
+            protocol_version
+        )));
+    }
 
     let frontmatter = FrontMatter {
         id: uuid::Uuid::now_v7(),
@@ -23,18 +70,20 @@ pub fn run(path: Option<PathBuf>, title: Option<String>, author: Option<String>)
         author: Some(author.clone()),
         doc_status: DocStatus::Draft,
         created_at: Utc::now(),
+        protocol_version,
         ..Default::default()
     };
 
     let fm_yaml = serde_norway::to_string(&frontmatter).map_err(|e| {
-        crate::error::RhodiError::Serialization(format!("Failed to serialize frontmatter: {}", e))
+        RhodiError::Serialization(format!("Failed to serialize frontmatter: {}", e))
     })?;
 
-    let content = format!(
-        "---\n{}\n---\n\n# {}\n\nStart writing your document here.\n",
-        fm_yaml.trim(),
-        title
-    );
+    let body = match markdown {
+        Some(ref markdown) if suggest_traces => suggest_trace_stubs(markdown.trim()),
+        Some(ref markdown) => format!("{}\n", markdown.trim()),
+        None => format!("# {}\n\nStart writing your document here.\n", title),
+    };
+    let content = format!("---\n{}\n---\n\n{}", fm_yaml.trim(), body);
 
     fs::write(&path, content)?;
 
@@ -42,6 +91,27 @@ pub fn run(path: Option<PathBuf>, title: Option<String>, author: Option<String>)
     println!("  Title: {}", title);
     println!("  Author: {}", author);
     println!("  Status: Draft");
+    if let Some(ref md) = from_markdown {
+        println!("  Converted from: {}", md.display());
+    }
+    if suggest_traces {
+        let stubs = body.matches("source: TODO").count();
+        println!(
+            "  Suggested {} trace stub(s); set their source before sealing",
+            stubs
+        );
+    }
 
     Ok(())
 }
+
+/// Drop a YAML frontmatter block from a plain Markdown file (static site
+/// generators add one); rhodi writes its own.
+fn strip_frontmatter(markdown: &str) -> String {
+    if let Some(rest) = markdown.strip_prefix("---\n")
+        && let Some(end) = rest.find("\n---\n")
+    {
+        return rest[end + 5..].to_string();
+    }
+    markdown.to_string()
+}
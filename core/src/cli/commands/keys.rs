@@ -0,0 +1,152 @@
+use crate::cli::keys::{KeyManager, KeyShare};
+use crate::config::Workspace;
+use crate::discovery::parse_key;
+use crate::error::{Result, RhodiError};
+use crate::fingerprint::KeyFingerprint;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+pub fn list() -> Result<()> {
+    let manager = KeyManager::new()?;
+    let keys = manager.key_infos()?;
+
+    if keys.is_empty() {
+        println!("No keys found. Run 'rhodi keygen' to create one.");
+        return Ok(());
+    }
+
+    println!("{:<20} {:<20} PUBLIC KEY", "NAME", "CREATED");
+    for key in keys {
+        let created = key
+            .created_at
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("{:<20} {:<20} {}", key.name, created, key.public_key);
+    }
+
+    Ok(())
+}
+
+/// Print the fingerprint of a stored key or a public key, and with
+/// `expect`, check it against one read out by someone else.
+pub fn fingerprint(key: String, expect: Option<String>) -> Result<()> {
+    let manager = KeyManager::new()?;
+    let public_key = match manager.get_public_key_hex(&key) {
+        Ok(hex) => parse_key(&hex)?,
+        Err(_) => parse_key(&key).map_err(|_| {
+            RhodiError::Resolution(format!(
+                "'{}' is neither a stored key nor a public key",
+                key
+            ))
+        })?,
+    };
+    let fingerprint = KeyFingerprint::of(&public_key);
+
+    println!("Fingerprint: {}", fingerprint.hex());
+    println!("Words:       {}", fingerprint.words());
+    if let Some(expected) = expect {
+        if !fingerprint.matches(&expected) {
+            return Err(RhodiError::Verification(format!(
+                "Fingerprint does not match '{}'",
+                expected.trim()
+            )));
+        }
+        println!("✓ Fingerprint matches");
+    }
+
+    Ok(())
+}
+
+pub fn delete(name: String, yes: bool, shred: bool) -> Result<()> {
+    let manager = KeyManager::new()?;
+    let public_key = manager.get_public_key_hex(&name)?;
+
+    if !yes {
+        println!("Deleting key '{}' ({}) cannot be undone.", name, public_key);
+        print!("Type the key name to confirm: ");
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        if answer.trim() != name {
+            return Err(RhodiError::Resolution("Deletion cancelled".into()));
+        }
+    }
+
+    manager.delete_key(&name, shred)?;
+    if shred {
+        println!("Key '{}' shredded and deleted.", name);
+    } else {
+        println!("Key '{}' deleted.", name);
+    }
+
+    Ok(())
+}
+
+/// Split a key into `shares` share files in `out_dir`, any `threshold` of
+/// which restore it.
+pub fn backup(
+    key_name: Option<String>,
+    shares: u8,
+    threshold: u8,
+    out_dir: Option<PathBuf>,
+) -> Result<()> {
+    let workspace = Workspace::discover(&std::env::current_dir()?)?;
+    let key_name = key_name
+        .or(workspace.config.keys.default)
+        .unwrap_or_else(|| "default".to_string());
+    let out_dir = out_dir.unwrap_or_else(|| PathBuf::from("."));
+    let key_shares = KeyManager::new()?.backup(&key_name, shares, threshold)?;
+
+    let paths: Vec<PathBuf> = key_shares
+        .iter()
+        .map(|share| {
+            out_dir.join(format!(
+                "{}.share-{}-of-{}.txt",
+                key_name, share.share.index, shares
+            ))
+        })
+        .collect();
+    if let Some(existing) = paths.iter().find(|path| path.exists()) {
+        return Err(RhodiError::Resolution(format!(
+            "{} already exists; choose another --out-dir",
+            existing.display()
+        )));
+    }
+    fs::create_dir_all(&out_dir)?;
+    for (share, path) in key_shares.iter().zip(&paths) {
+        fs::write(path, share.to_text())?;
+        KeyManager::set_key_permissions(path)?;
+        println!("Wrote {}", path.display());
+    }
+    println!(
+        "Key '{}' split into {} shares (backup {}); any {} restore it with 'rhodi keys restore'.",
+        key_name, shares, key_shares[0].backup, threshold
+    );
+    println!("Hand each share to a different custodian and delete these files.");
+    Ok(())
+}
+
+/// Rebuild a key from share files and store it.
+pub fn restore(paths: Vec<PathBuf>, name: Option<String>) -> Result<()> {
+    let shares = paths
+        .iter()
+        .map(|path| {
+            KeyShare::parse(&fs::read_to_string(path)?).map_err(|e| match e {
+                RhodiError::Format(reason) => {
+                    RhodiError::Format(format!("{}: {}", path.display(), reason))
+                }
+                e => e,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let key_file = KeyManager::new()?.restore(&shares, name.as_deref())?;
+    println!(
+        "Key '{}' restored from {} shares.",
+        key_file.name,
+        shares.len()
+    );
+    println!("Public key: {}", key_file.public_key);
+    Ok(())
+}
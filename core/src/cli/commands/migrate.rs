@@ -0,0 +1,37 @@
+use crate::error::Result;
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::version::get_latest_version;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn run(path: PathBuf, to: Option<String>, dry_run: bool) -> Result<()> {
+    let content = fs::read_to_string(&path)?;
+    let doc = parse_tmd(&content)?;
+
+    let from = doc.frontmatter.protocol_version.clone();
+    let to = to.unwrap_or_else(|| get_latest_version().to_string());
+    let was_sealed = doc.frontmatter.version_hash.is_some();
+
+    let (doc, changes) = doc.migrate(&to)?;
+
+    if changes.is_empty() {
+        println!("{} is already at protocol version {}", path.display(), to);
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("Would migrate {} from {} to {}:", path.display(), from, to);
+    } else {
+        fs::write(&path, render_tmd_preserving(&content, &doc)?)?;
+        println!("Migrated {} from {} to {}:", path.display(), from, to);
+    }
+    for change in &changes {
+        println!("  - {}", change);
+    }
+
+    if was_sealed && !dry_run {
+        println!("Re-seal with: rhodi seal {}", path.display());
+    }
+
+    Ok(())
+}
@@ -1,6 +1,34 @@
+pub mod agent;
+pub mod amend;
+pub mod attest;
+pub mod author;
+pub mod bundle;
+pub mod certify;
+pub mod check;
+pub mod config;
+pub mod export;
+pub mod fix_id;
+pub mod freeze;
+pub mod hash;
+pub mod hooks;
 pub mod init;
 pub mod keygen;
+pub mod keys;
+pub mod migrate;
+pub mod notarize;
+pub mod publish;
+pub mod redact;
+pub mod reseal;
+pub mod review;
 pub mod seal;
+pub mod search;
+pub mod serve;
+pub mod sign;
+pub mod stats;
 pub mod status;
+pub mod stub;
+pub mod suggest;
+pub mod supersede;
 pub mod update;
 pub mod verify;
+pub mod verify_artifact;
@@ -0,0 +1,82 @@
+use crate::cli::commands::verify::VerifyOptions;
+use crate::cli::keys::KeyManager;
+use crate::config::Workspace;
+use crate::crypto::KeyPair;
+use crate::error::Result;
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::report::VerificationReport;
+use std::fs;
+use std::path::PathBuf;
+
+/// Verify a sealed document and append a notarization signed with `key_name`.
+/// With `report_file`, the verification report is written there, pass or
+/// fail.
+pub fn run(
+    path: PathBuf,
+    key_name: Option<String>,
+    name: Option<String>,
+    report_file: Option<PathBuf>,
+) -> Result<()> {
+    // A reviewer signs off on a document still awaiting its reviews
+    let options = VerifyOptions {
+        strict: true,
+        pending_reviews: true,
+        ..Default::default()
+    };
+    let mut record = VerificationReport::new("notarize");
+    let verified =
+        crate::cli::commands::verify::run_recorded(Some(path.clone()), options, &mut record);
+    if let Some(ref file) = report_file {
+        record.write(file)?;
+    }
+    verified?;
+
+    let workspace = Workspace::for_document(&path)?;
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+
+    let _lock = FileLock::acquire(&path, DEFAULT_LOCK_TIMEOUT)?;
+    let content = fs::read_to_string(&path)?;
+    let doc = parse_tmd(&content)?;
+    // A listed contributor notarizes under their listed name
+    let notary = hex::encode(keypair.verifying_key.as_bytes());
+    let name = name.or_else(|| {
+        doc.frontmatter
+            .contributors
+            .iter()
+            .flatten()
+            .find(|c| {
+                c.key
+                    .as_deref()
+                    .is_some_and(|k| k.eq_ignore_ascii_case(&notary))
+            })
+            .map(|c| c.name.clone())
+    });
+    let versions = workspace.version_registry()?;
+    let doc = crate::notary::notarize(doc, &keypair, name.as_deref(), &versions)?;
+    write_atomic(&path, &render_tmd_preserving(&content, &doc)?)?;
+
+    let notarizations = doc.frontmatter.notarizations.as_deref().unwrap_or_default();
+    println!("Document notarized: {}", path.display());
+    if let Some(notarization) = notarizations.last() {
+        println!("  Notary: {}", notarization.notary);
+        println!("  Notarized at: {}", notarization.notarized_at);
+    }
+    println!("  Notarizations in chain: {}", notarizations.len());
+    if let Some(required) = doc.frontmatter.policy.required_reviewers {
+        println!(
+            "  Reviewer signatures: {} of {} required",
+            crate::notary::reviewer_signatures(&doc).len(),
+            required
+        );
+    }
+
+    Ok(())
+}
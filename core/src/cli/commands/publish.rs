@@ -0,0 +1,22 @@
+use crate::config::Workspace;
+use crate::error::Result;
+use crate::markdown::parse_tmd;
+use crate::registry::{RegistryClient, RegistryRecord};
+use std::fs;
+use std::path::PathBuf;
+
+pub fn run(path: PathBuf, registry: Option<String>) -> Result<()> {
+    let content = fs::read_to_string(&path)?;
+    let doc = parse_tmd(&content)?;
+
+    let workspace = Workspace::for_document(&path)?;
+    let client = RegistryClient::from_url_or_env(registry, workspace.config.registry.url)?;
+    let record = RegistryRecord::from_document(&doc)?;
+    client.publish(&record)?;
+
+    println!("Published to registry: {}", path.display());
+    println!("  ID: {}", record.id);
+    println!("  Version hash: {}", record.version_hash);
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::error::{Result, RhodiError};
+use crate::lock::write_atomic;
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::models::SealMode;
+use crate::redaction;
+use crate::version::commits_redactions;
+use std::fs;
+use std::path::PathBuf;
+
+/// Write the redacted rendition of the sealed document at `path` to
+/// `output`, or print it. The rendition verifies under the original seal.
+pub fn run(path: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let content = fs::read_to_string(&path)?;
+    let mut doc = parse_tmd(&content)?;
+    let Some(version_hash) = doc.frontmatter.version_hash else {
+        return Err(RhodiError::Verification(format!(
+            "{} is not sealed; run 'rhodi seal' before redacting",
+            path.display()
+        )));
+    };
+
+    // Earlier versions hash the plaintext, so no rendition verifies
+    if !commits_redactions(&doc.frontmatter.protocol_version) {
+        return Err(RhodiError::Verification(format!(
+            "Protocol version {} hashes redact blocks as written; run `rhodi migrate --to 2.4` and seal again first",
+            doc.frontmatter.protocol_version
+        )));
+    }
+
+    if doc.frontmatter.seal_mode == Some(SealMode::FileHash) {
+        return Err(RhodiError::Verification(format!(
+            "{} has a file-hash seal, which covers the redacted text itself; seal it in the semantic mode to share redacted renditions",
+            path.display()
+        )));
+    }
+
+    doc.body = redaction::redact(&doc.body)?;
+    // Redaction must never change what the seal covers
+    if doc.compute_version_hash()? != version_hash {
+        return Err(RhodiError::Verification(
+            "Redacted rendition no longer matches the version hash".to_string(),
+        ));
+    }
+    let rendered = render_tmd_preserving(&content, &doc)?;
+
+    match output {
+        Some(output) => {
+            write_atomic(&output, &rendered)?;
+            let (total, redacted) = redaction::count(&doc.body);
+            eprintln!(
+                "Redacted rendition written to {} ({} of {} block(s) redacted)",
+                output.display(),
+                redacted,
+                total
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
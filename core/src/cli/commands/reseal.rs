@@ -0,0 +1,110 @@
+use crate::cli::keys::KeyManager;
+use crate::config::Workspace;
+use crate::crypto::{KeyPair, parse_public_key};
+use crate::error::{Result, RhodiError};
+use crate::history::archive;
+use crate::index::WorkspaceIndex;
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use crate::markdown::{parse_tmd, render_sealed};
+use std::fs;
+use std::path::PathBuf;
+
+/// Re-seal every workspace document signed with `compromised` using `key_name`.
+pub fn run(
+    dir: Option<PathBuf>,
+    compromised: String,
+    key_name: Option<String>,
+    reason: Option<String>,
+    dry_run: bool,
+) -> Result<()> {
+    let workspace = Workspace::discover(&dir.unwrap_or_else(|| PathBuf::from(".")))?;
+    let manager = KeyManager::new()?;
+
+    // Accept either a hex public key or the name of a stored key
+    let compromised = match parse_public_key(&compromised) {
+        Ok(key) => hex::encode(key.as_bytes()),
+        Err(_) => manager.get_public_key_hex(&compromised)?,
+    };
+
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = manager.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+    if hex::encode(keypair.verifying_key.as_bytes()) == compromised {
+        return Err(RhodiError::Crypto(format!(
+            "Key '{}' is the compromised key; choose a new key with --key",
+            key_name
+        )));
+    }
+
+    let reason = reason.unwrap_or_else(|| "signing key compromised".to_string());
+    let index = WorkspaceIndex::build(&workspace.root)?;
+    let affected: Vec<_> = index
+        .documents
+        .iter()
+        .filter(|doc| doc.public_key.as_deref() == Some(compromised.as_str()))
+        .collect();
+
+    if affected.is_empty() {
+        println!("No documents are signed with {}.", compromised);
+        return Ok(());
+    }
+
+    let mut refused = 0;
+    for indexed in &affected {
+        let path = workspace.root.join(&indexed.path);
+        if dry_run {
+            println!(
+                "Would re-seal: {} (v{})",
+                indexed.path.display(),
+                indexed.doc_version
+            );
+            continue;
+        }
+
+        let _lock = FileLock::acquire(&path, DEFAULT_LOCK_TIMEOUT)?;
+        let content = fs::read_to_string(&path)?;
+        let doc = match parse_tmd(&content)?.reseal(&keypair, &reason) {
+            Ok(doc) => doc,
+            Err(e) => {
+                eprintln!("Not re-sealed: {}: {}", indexed.path.display(), e);
+                refused += 1;
+                continue;
+            }
+        };
+        let (doc, sealed) = render_sealed(&content, doc, &keypair)?;
+        write_atomic(&path, &sealed)?;
+        archive(&workspace.history_dir(), &doc, &sealed)?;
+        println!(
+            "Re-sealed: {} (v{} -> v{})",
+            indexed.path.display(),
+            indexed.doc_version,
+            doc.frontmatter.doc_version
+        );
+    }
+
+    if dry_run {
+        println!(
+            "{} document(s) would be re-sealed with key '{}'.",
+            affected.len(),
+            key_name
+        );
+    } else {
+        println!(
+            "{} document(s) re-sealed with key '{}'.",
+            affected.len() - refused,
+            key_name
+        );
+    }
+    if refused > 0 {
+        return Err(RhodiError::Verification(format!(
+            "{} document(s) no longer verify under the compromised key and were not re-sealed",
+            refused
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use crate::attestation::{self, Attestation, Verdict};
+use crate::cli::commands::verify::{self, VerifyOptions};
+use crate::cli::io::base_dir;
+use crate::cli::keys::KeyManager;
+use crate::comments::{ReviewComment, append_review};
+use crate::compiler::Compiler;
+use crate::config::Workspace;
+use crate::crypto::KeyPair;
+use crate::error::{Result, RhodiError};
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::report::VerificationReport;
+use crate::review::{ReviewItem, ReviewSession};
+use crate::version::excludes_review_blocks;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use std::fs;
+use std::path::PathBuf;
+
+/// How the auditor left the review.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Exit {
+    Quit,
+    Export,
+}
+
+/// Where keystrokes go: review commands, or the note of a trace being
+/// flagged.
+enum Mode {
+    Browse,
+    Note(String),
+}
+
+struct App {
+    session: ReviewSession,
+    title: String,
+    mode: Mode,
+    /// One-line feedback shown in the footer until the next key
+    message: Option<String>,
+}
+
+/// Walk through the trace blocks of the document at `path` in a terminal
+/// UI, approving or flagging each, and optionally export the verdicts as a
+/// signed attestation. The document must first pass strict verification,
+/// as for `rhodi attest`.
+pub fn run(path: PathBuf, key_name: Option<String>, ledger: bool) -> Result<()> {
+    let options = VerifyOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let report = verify::run_recorded(
+        Some(path.clone()),
+        options,
+        &mut VerificationReport::new("review"),
+    )?;
+    let doc = parse_tmd(&fs::read_to_string(&path)?)?;
+    let workspace = Workspace::for_document(&path)?;
+    let resolver = workspace.resolver(&base_dir(&path)?)?;
+    let compiler = Compiler::new(&resolver)
+        .with_exec_extractor(workspace.exec_extractor())
+        .with_presets(workspace.config.presets.clone())
+        .with_version_registry(workspace.version_registry()?)
+        .with_limits(workspace.config.limits.compiler_limits());
+    let session = ReviewSession::new(&compiler, &doc);
+    if session.items.is_empty() {
+        return Err(RhodiError::Verification(
+            "Document has no trace blocks to review".to_string(),
+        ));
+    }
+
+    let mut app = App {
+        session,
+        title: doc.frontmatter.title.clone(),
+        mode: Mode::Browse,
+        message: None,
+    };
+    let mut terminal = ratatui::init();
+    let exit = app.run(&mut terminal);
+    ratatui::restore();
+    let exit = exit?;
+
+    let reviews = app.session.reviews();
+    let flagged = reviews
+        .iter()
+        .filter(|r| r.verdict == Verdict::Flagged)
+        .count();
+    println!(
+        "Reviewed {} of {} trace(s): {} approved, {} flagged",
+        reviews.len(),
+        app.session.items.len(),
+        reviews.len() - flagged,
+        flagged
+    );
+    if exit == Exit::Quit {
+        println!("No attestation exported.");
+        return Ok(());
+    }
+
+    let key_name = key_name
+        .or(workspace.config.keys.default.clone())
+        .unwrap_or_else(|| "default".to_string());
+    let signing_key = KeyManager::new()?.get_key(&key_name)?;
+    let keypair = KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    };
+    let attestation = Attestation::create_with_reviews(&doc, &keypair, report.warnings, reviews)?;
+    let log = if ledger {
+        workspace.ledger_path()
+    } else {
+        attestation::attestations_path(&path)
+    };
+    attestation::append(&log, &attestation)?;
+
+    println!("Review attested: {}", path.display());
+    println!("  Version hash: {}", attestation.version_hash);
+    println!("  Reviewer: {}", attestation.verifier);
+    println!("  Recorded in: {}", log.display());
+    Ok(())
+}
+
+/// Append a review comment to the document at `path`, signed with
+/// `key_name` unless `unsigned`. The author defaults to the contributor
+/// listed with the signing key.
+pub fn add(
+    path: PathBuf,
+    comment: String,
+    author: Option<String>,
+    key_name: Option<String>,
+    unsigned: bool,
+) -> Result<()> {
+    let keypair = if unsigned {
+        None
+    } else {
+        let workspace = Workspace::for_document(&path)?;
+        let key_name = key_name
+            .or(workspace.config.keys.default.clone())
+            .unwrap_or_else(|| "default".to_string());
+        let signing_key = KeyManager::new()?.get_key(&key_name)?;
+        Some(KeyPair {
+            verifying_key: signing_key.verifying_key(),
+            signing_key,
+        })
+    };
+
+    let _lock = FileLock::acquire(&path, DEFAULT_LOCK_TIMEOUT)?;
+    let content = fs::read_to_string(&path)?;
+    let mut doc = parse_tmd(&content)?;
+    // Earlier versions hash review blocks, so a comment would break the seal
+    if !excludes_review_blocks(&doc.frontmatter.protocol_version) {
+        return Err(RhodiError::Format(format!(
+            "Protocol version {} covers review comments with the version hash; run `rhodi migrate --to 2.2` first",
+            doc.frontmatter.protocol_version
+        )));
+    }
+    let author = author
+        .or_else(|| {
+            let key = hex::encode(keypair.as_ref()?.verifying_key.as_bytes());
+            doc.frontmatter
+                .contributors
+                .iter()
+                .flatten()
+                .find(|c| {
+                    c.key
+                        .as_deref()
+                        .is_some_and(|k| k.eq_ignore_ascii_case(&key))
+                })
+                .map(|c| c.name.clone())
+        })
+        .ok_or_else(|| {
+            RhodiError::Format("No contributor is listed with this key; pass --author".to_string())
+        })?;
+    let review = ReviewComment::new(&doc, &author, &comment);
+    let review = match &keypair {
+        Some(keypair) => review.sign(&doc, keypair),
+        None => review,
+    };
+    doc.body = append_review(&doc.body, &review)?;
+    write_atomic(&path, &render_tmd_preserving(&content, &doc)?)?;
+
+    println!("Review comment added: {}", path.display());
+    println!("  Author: {}", review.author);
+    match &review.key {
+        Some(key) => println!("  Signed by: {}", key),
+        None => println!("  Unsigned"),
+    }
+    Ok(())
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<Exit> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.message = None;
+            if let Some(exit) = self.handle(key.code) {
+                return Ok(exit);
+            }
+        }
+    }
+
+    fn handle(&mut self, code: KeyCode) -> Option<Exit> {
+        match &mut self.mode {
+            Mode::Note(note) => match code {
+                KeyCode::Enter => {
+                    let note = std::mem::take(note);
+                    self.session.flag(Some(note));
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    note.pop();
+                }
+                KeyCode::Char(c) => note.push(c),
+                _ => {}
+            },
+            Mode::Browse => match code {
+                KeyCode::Char('a') | KeyCode::Enter => self.session.approve(),
+                KeyCode::Char('f') => self.mode = Mode::Note(String::new()),
+                KeyCode::Char('n') | KeyCode::Char('j') | KeyCode::Right | KeyCode::Down => {
+                    self.session.next()
+                }
+                KeyCode::Char('p') | KeyCode::Char('k') | KeyCode::Left | KeyCode::Up => {
+                    self.session.previous()
+                }
+                KeyCode::Char('x') if self.session.is_complete() => return Some(Exit::Export),
+                KeyCode::Char('x') => {
+                    self.message = Some(format!(
+                        "{} trace(s) still need a verdict",
+                        self.session.items.len() - self.session.decided()
+                    ));
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Some(Exit::Quit),
+                _ => {}
+            },
+        }
+        None
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let Some(item) = self.session.current() else {
+            return;
+        };
+        let [header, claim, trace, source, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let progress = format!(
+            "{}  ·  trace {}/{}  ·  {} decided",
+            self.title,
+            self.session.current + 1,
+            self.session.items.len(),
+            self.session.decided()
+        );
+        frame.render_widget(
+            Paragraph::new(progress).block(Block::default().borders(Borders::ALL).title("Review")),
+            header,
+        );
+
+        let mut claim_lines = Vec::new();
+        if !item.heading_path.is_empty() {
+            claim_lines.push(Line::styled(
+                item.heading_path.join(" > "),
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+        claim_lines.push(Line::from(
+            item.claim
+                .clone()
+                .unwrap_or_else(|| "(no prose before this trace)".to_string()),
+        ));
+        frame.render_widget(
+            Paragraph::new(claim_lines)
+                .wrap(Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title("Claim")),
+            claim,
+        );
+
+        frame.render_widget(
+            Paragraph::new(trace_lines(item))
+                .wrap(Wrap { trim: false })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Trace {}", item.trace.label())),
+                ),
+            trace,
+        );
+
+        let snippet_lines = match &item.snippet {
+            Some(snippet) => snippet
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let number = snippet.first_line + i;
+                    let text = format!("{:>5} | {}", number, line);
+                    if number == snippet.match_line {
+                        Line::styled(text, Style::default().fg(Color::Yellow))
+                    } else {
+                        Line::from(text)
+                    }
+                })
+                .collect(),
+            None => vec![Line::from("(match site not found in the source)")],
+        };
+        frame.render_widget(
+            Paragraph::new(snippet_lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Source around the match"),
+            ),
+            source,
+        );
+
+        let help = match (&self.mode, &self.message) {
+            (Mode::Note(note), _) => format!("Flag note (Enter to flag, Esc to cancel): {}_", note),
+            (Mode::Browse, Some(message)) => message.clone(),
+            (Mode::Browse, None) => {
+                "a approve  f flag  n/p next/previous  x export attestation  q quit".to_string()
+            }
+        };
+        frame.render_widget(Paragraph::new(help), footer);
+    }
+}
+
+/// The trace's fields, its current check result and the verdict so far.
+fn trace_lines(item: &ReviewItem) -> Vec<Line<'static>> {
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(
+                format!("{:<10}", name),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(value),
+        ])
+    };
+    let trace = &item.trace;
+    let mut lines = vec![
+        field("Source", trace.source.clone()),
+        field("Selector", trace.selector.clone().unwrap_or_default()),
+        field("Expected", trace.expected.clone()),
+        field("Actual", item.result.actual.clone().unwrap_or_default()),
+    ];
+    lines.push(match (&item.result.passed, &item.result.error) {
+        (true, _) => Line::from(vec![
+            Span::styled(
+                format!("{:<10}", "Status"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("passes", Style::default().fg(Color::Green)),
+        ]),
+        (false, error) => Line::from(vec![
+            Span::styled(
+                format!("{:<10}", "Status"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!("fails: {}", error.as_deref().unwrap_or("unknown error")),
+                Style::default().fg(Color::Red),
+            ),
+        ]),
+    });
+    let verdict = match (item.verdict, &item.note) {
+        (None, _) => "(undecided)".to_string(),
+        (Some(Verdict::Approved), _) => "approved".to_string(),
+        (Some(Verdict::Flagged), None) => "flagged".to_string(),
+        (Some(Verdict::Flagged), Some(note)) => format!("flagged: {}", note),
+    };
+    lines.push(field("Verdict", verdict));
+    lines
+}
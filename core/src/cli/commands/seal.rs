@@ -1,15 +1,80 @@
+use crate::cli::io::{base_dir, is_stdio, lock_document, note, read_document, write_document};
 use crate::cli::keys::KeyManager;
-use crate::crypto::KeyPair;
+use crate::compiler::Compiler;
+use crate::config::Workspace;
+use crate::crypto::{KeyPair, SignatureScheme, parse_public_key};
 use crate::error::Result;
-use crate::markdown::parse_tmd;
-use crate::models::DocStatus;
+use crate::history::archive;
+use crate::identity::ProfileStore;
+use crate::index::DocumentLocator;
+use crate::lock::{write_atomic, write_atomic_bytes};
+use crate::markdown::{
+    Section, parse_include_block, parse_tmd, parse_tmd_sections, render_sealed,
+    render_tmd_preserving, repin_quote_blocks,
+};
+use crate::models::{DocStatus, QuoteBlock, SealMode, TracedDocument};
+use crate::openpgp::GpgSigner;
+use crate::opentimestamps::{Calendars, proof_path};
+use crate::registry::{RegistryClient, RegistryRecord};
+use crate::resolver::{OverlayResolver, SourceResolver, normalize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub fn run(path: PathBuf, key_name: Option<String>) -> Result<()> {
-    let key_name = key_name.unwrap_or_else(|| "default".to_string());
+/// Path of the pending file written by an external seal.
+pub fn pending_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".pending");
+    PathBuf::from(name)
+}
+
+/// Command-line options for `rhodi seal`.
+#[derive(Debug, Default, Clone)]
+pub struct SealOptions {
+    /// Key name to sign with
+    pub key_name: Option<String>,
+    /// Write a pending seal for an external signer instead of signing
+    pub external: bool,
+    /// Hex-encoded public key of the external signer
+    pub public_key: Option<String>,
+    /// Anchor the sealed version in this registry
+    pub registry: Option<String>,
+    /// Where the signing key can be discovered
+    pub key_uri: Option<String>,
+    /// Seal despite failing automatic traces
+    pub force: bool,
+    /// Stored author profile to record as the author
+    pub author_profile: Option<String>,
+    /// Seal in the file-hash mode, covering the exact file text
+    pub file_hash: bool,
+    /// Sign with this key from the user's GnuPG keyring instead
+    pub gpg_key: Option<String>,
+    /// Timestamp the version hash with OpenTimestamps
+    pub timestamp: bool,
+}
 
-    let content = fs::read_to_string(&path)?;
+pub fn run(path: PathBuf, options: SealOptions) -> Result<()> {
+    let SealOptions {
+        key_name,
+        external,
+        public_key,
+        registry,
+        key_uri,
+        force,
+        author_profile,
+        file_hash,
+        gpg_key,
+        timestamp,
+    } = options;
+    if timestamp && is_stdio(&path) {
+        return Err(crate::error::RhodiError::Resolution(
+            "--timestamp needs a document path to write the proof next to".into(),
+        ));
+    }
+    // Held until the sealed document is written, so a concurrent seal or
+    // update cannot interleave and overwrite the fresh signature
+    let _lock = lock_document(&path)?;
+    let content = read_document(&path)?;
     let mut doc = parse_tmd(&content)?;
 
     if doc.frontmatter.doc_status == DocStatus::Published {
@@ -18,42 +83,499 @@ pub fn run(path: PathBuf, key_name: Option<String>) -> Result<()> {
         ));
     }
 
-    let base_path = if let Some(p) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
-        p.to_path_buf()
-    } else {
-        std::env::current_dir()?
-    };
+    let base_path = base_dir(&path)?;
+
+    let workspace = Workspace::for_document(&path)?;
 
+    // Presets are workspace configuration, which the seal does not cover
+    doc.expand_presets(&workspace.config.presets)?;
     doc.update_all_traces(&base_path)?;
 
-    let manager = KeyManager::new()?;
-    let signing_key = manager.get_key(&key_name)?;
-    let verifying_key = signing_key.verifying_key();
+    // Hashes were just refreshed, so re-extract to catch stale `expected` values
+    let resolver = workspace.resolver(&base_path)?;
+    let compiler = Compiler::new(&resolver)
+        .with_exec_extractor(workspace.exec_extractor())
+        .with_presets(workspace.config.presets.clone())
+        .with_version_registry(workspace.version_registry()?)
+        .with_limits(workspace.config.limits.compiler_limits())
+        .with_document_locator(Some(workspace.document_locator()));
+    check_automatic_traces(&compiler, &doc, &path, force)?;
 
-    let keypair = KeyPair {
-        signing_key,
-        verifying_key,
-    };
+    // Pin the sealed version of every included document
+    let includes = compiler.include_manifest(&doc)?;
+    doc.frontmatter.includes = (!includes.is_empty()).then_some(includes);
+
+    let profile = author_profile
+        .map(|profile| ProfileStore::new()?.get(&profile))
+        .transpose()?;
+    if let Some(ref profile) = profile {
+        doc.frontmatter.author = Some(profile.author());
+    }
+
+    for contributor in doc.frontmatter.contributors.iter().flatten() {
+        contributor.validate()?;
+    }
+
+    if key_uri.is_some() {
+        doc.frontmatter.key_uri = key_uri;
+    }
+    if file_hash {
+        doc.frontmatter.seal_mode = Some(SealMode::FileHash);
+    }
 
-    doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+    if external {
+        if doc.frontmatter.seal_mode == Some(SealMode::FileHash) {
+            return Err(crate::error::RhodiError::Resolution(
+                "External seals are not supported in the file-hash seal mode".into(),
+            ));
+        }
+        if is_stdio(&path) {
+            return Err(crate::error::RhodiError::Resolution(
+                "--external needs a document path to write the pending seal next to".into(),
+            ));
+        }
+        let public_key = public_key.ok_or_else(|| {
+            crate::error::RhodiError::Crypto(
+                "--external requires --public-key of the external signer".into(),
+            )
+        })?;
+        let verifying_key = parse_public_key(&public_key)?;
+        doc.frontmatter.public_key = Some(hex::encode(verifying_key.as_bytes()));
+        // The external signer makes Ed25519 signatures, which are not recorded
+        doc.frontmatter.signature_algorithm = None;
 
-    doc = doc.seal(&keypair);
+        doc = doc.prepare_seal()?;
+        let pending = pending_path(&path);
+        fs::write(&pending, render_tmd_preserving(&content, &doc)?)?;
 
-    let fm_yaml = serde_norway::to_string(&doc.frontmatter).map_err(|e| {
-        crate::error::RhodiError::Serialization(format!("Failed to serialize frontmatter: {}", e))
-    })?;
+        println!("Pending seal written to: {}", pending.display());
+        println!(
+            "  Version hash (sign these 32 bytes): {}",
+            hex::encode(doc.frontmatter.version_hash.unwrap_or_default())
+        );
+        println!(
+            "Complete with: rhodi sign {} --signature <hex>",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    let (doc, sealed) = if let Some(gpg_key) = gpg_key {
+        if doc.frontmatter.seal_mode == Some(SealMode::FileHash) {
+            return Err(crate::error::RhodiError::Resolution(
+                "Sealing with gpg is not supported in the file-hash seal mode".into(),
+            ));
+        }
+        let signer = GpgSigner::from_env(&gpg_key);
+        doc.frontmatter.public_key = Some(hex::encode(signer.public_key()?));
+        doc.frontmatter.signature_algorithm = Some(SignatureScheme::OpenPgpEd25519);
+        let doc = doc.prepare_seal()?;
+        let signature = signer.sign(&doc.frontmatter.version_hash.unwrap_or_default())?;
+        let doc = doc.attach_signature(signature)?;
+        let sealed = render_tmd_preserving(&content, &doc)?;
+        (doc, sealed)
+    } else {
+        let key_name = key_name
+            .or(profile.and_then(|profile| profile.key))
+            .or(workspace.config.keys.default.clone())
+            .unwrap_or_else(|| "default".to_string());
+        let keypair = load_keypair(&key_name)?;
 
-    let full_content = format!("---\n{}\n---\n\n{}", fm_yaml.trim(), doc.body);
-    fs::write(&path, full_content)?;
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+
+        render_sealed(&content, doc.seal(&keypair)?, &keypair)?
+    };
+    check_review_policy(&doc)?;
+    write_document(&path, &sealed)?;
+    if !is_stdio(&path) {
+        archive(&workspace.history_dir(), &doc, &sealed)?;
+    }
 
-    println!("Document sealed successfully: {}", path.display());
-    println!("  Status: Published");
-    println!(
-        "  Version hash: {}",
-        hex::encode(doc.frontmatter.version_hash.unwrap())
+    note(
+        &path,
+        format!("Document sealed successfully: {}", path.display()),
+    );
+    note(&path, "  Status: Published");
+    note(
+        &path,
+        format!(
+            "  Version hash: {}",
+            hex::encode(doc.frontmatter.version_hash.unwrap_or_default())
+        ),
+    );
+    if let Some(fingerprint) = doc
+        .frontmatter
+        .signature
+        .as_deref()
+        .filter(|_| doc.signature_scheme() == SignatureScheme::OpenPgpEd25519)
+        .and_then(|signature| crate::openpgp::SignaturePacket::parse(signature).ok())
+        .and_then(|packet| packet.issuer_fingerprint)
+    {
+        note(&path, format!("  OpenPGP key: {}", fingerprint));
+    }
+    note(
+        &path,
+        format!("  Protocol version: {}", doc.frontmatter.protocol_version),
+    );
+    note(
+        &path,
+        format!("  Document version: {}", doc.frontmatter.doc_version),
     );
-    println!("  Protocol version: {}", doc.frontmatter.protocol_version);
-    println!("  Document version: {}", doc.frontmatter.doc_version);
 
+    if let Some(required) = doc.frontmatter.policy.required_reviewers {
+        note(
+            &path,
+            format!(
+                "  Awaiting {} reviewer signature(s): reviewers sign off with 'rhodi notarize'",
+                required
+            ),
+        );
+    }
+
+    if timestamp {
+        let calendars = Calendars::new(&workspace.config.timestamp.calendars);
+        let proof = calendars
+            .stamp(doc.frontmatter.version_hash.unwrap_or_default())
+            .map_err(|e| {
+                crate::error::RhodiError::Resolution(format!(
+                    "Document sealed, but not timestamped: {}",
+                    e
+                ))
+            })?;
+        let proof_file = proof_path(&path);
+        write_atomic_bytes(&proof_file, &proof.to_bytes())?;
+        note(
+            &path,
+            format!(
+                "  Timestamp proof: {} (pending until anchored in Bitcoin, usually within hours)",
+                proof_file.display()
+            ),
+        );
+    }
+
+    if let Some(url) = registry.or(workspace.config.registry.url) {
+        let record = RegistryRecord::from_document(&doc)?;
+        RegistryClient::new(&url).publish(&record)?;
+        note(&path, format!("  Anchored in registry: {}", url));
+    }
+
+    Ok(())
+}
+
+/// A document of a batch seal, read and checked but not yet sealed.
+struct BatchEntry {
+    path: PathBuf,
+    base: PathBuf,
+    workspace: Workspace,
+    /// Resolver identity of the document's own location
+    identity: String,
+    content: String,
+    doc: TracedDocument,
+    keypair: KeyPair,
+}
+
+/// Seal `paths` as one transaction. Every document is checked before any
+/// is sealed; documents that include or quote each other are sealed in
+/// dependency order, against the new versions of the others; and files are
+/// only written once all of them sealed. A failed write restores the
+/// documents already written.
+pub fn run_batch(paths: Vec<PathBuf>, options: SealOptions) -> Result<()> {
+    let SealOptions {
+        key_name,
+        registry,
+        key_uri,
+        force,
+        author_profile,
+        file_hash,
+        ..
+    } = options;
+    if paths.iter().any(|path| is_stdio(path)) {
+        return Err(crate::error::RhodiError::Resolution(
+            "--batch seals documents in place; stdin (-) cannot be part of a batch".into(),
+        ));
+    }
+
+    let profile = author_profile
+        .map(|profile| ProfileStore::new()?.get(&profile))
+        .transpose()?;
+
+    // Pre-flight: every document is readable, unpublished and has a key.
+    // The locks are held until every document is written.
+    let mut locks = Vec::with_capacity(paths.len());
+    let mut entries: Vec<BatchEntry> = Vec::with_capacity(paths.len());
+    for path in paths {
+        locks.push(lock_document(&path)?);
+        let identity = normalize(&path.canonicalize()?).display().to_string();
+        if entries.iter().any(|entry| entry.identity == identity) {
+            return Err(crate::error::RhodiError::Resolution(format!(
+                "{} is listed more than once in the batch",
+                path.display()
+            )));
+        }
+        let content = read_document(&path)?;
+        let doc = parse_tmd(&content)?;
+        if doc.frontmatter.doc_status == DocStatus::Published {
+            return Err(crate::error::RhodiError::Verification(format!(
+                "{} is already published. Create a new version instead.",
+                path.display()
+            )));
+        }
+        let workspace = Workspace::for_document(&path)?;
+        let key_name = key_name
+            .clone()
+            .or(profile.as_ref().and_then(|profile| profile.key.clone()))
+            .or(workspace.config.keys.default.clone())
+            .unwrap_or_else(|| "default".to_string());
+        entries.push(BatchEntry {
+            base: base_dir(&path)?,
+            path,
+            workspace,
+            identity,
+            content,
+            doc,
+            keypair: load_keypair(&key_name)?,
+        });
+    }
+
+    let order = batch_order(&entries)?;
+
+    // Seal in memory. `overlay` serves the sealed text of every document
+    // sealed so far, by location; `sealed` serves them by id.
+    let mut overlay: BTreeMap<String, String> = BTreeMap::new();
+    let mut hashes: BTreeMap<String, String> = BTreeMap::new();
+    let mut sealed: Vec<Option<(TracedDocument, String)>> = vec![None; entries.len()];
+    for index in order {
+        let entry = &entries[index];
+        let resolver = entry.workspace.resolver(&entry.base)?;
+
+        // Quotes of batch documents pin the versions being sealed now
+        let mut repinned = Vec::new();
+        let content = repin_quote_blocks(&entry.content, |quote| {
+            let identity = resolver.identity(&quote.source_doc).ok()?;
+            let hash = hashes.get(&identity)?.clone();
+            repinned.push(QuoteBlock {
+                version_hash: hash.clone(),
+                ..quote.clone()
+            });
+            Some(hash)
+        });
+        let mut doc = if content == entry.content {
+            entry.doc.clone()
+        } else {
+            parse_tmd(&content)?
+        };
+        doc.expand_presets(&entry.workspace.config.presets)?;
+        doc.update_all_traces(&entry.base)?;
+
+        let (doc, text) = {
+            let overlay_resolver = OverlayResolver::new(&resolver, &overlay);
+            let locator = sealed.iter().flatten().map(|(doc, _)| doc.clone()).fold(
+                entry.workspace.document_locator(),
+                DocumentLocator::with_document,
+            );
+            let compiler = Compiler::new(&overlay_resolver)
+                .with_exec_extractor(entry.workspace.exec_extractor())
+                .with_presets(entry.workspace.config.presets.clone())
+                .with_version_registry(entry.workspace.version_registry()?)
+                .with_limits(entry.workspace.config.limits.compiler_limits())
+                .with_document_locator(Some(locator));
+            check_automatic_traces(&compiler, &doc, &entry.path, force)?;
+            for quote in &repinned {
+                compiler.check_quote(quote).map_err(|e| {
+                    crate::error::RhodiError::Verification(format!(
+                        "{}: quote of {} does not hold against its new version: {}",
+                        entry.path.display(),
+                        quote.source_doc,
+                        e
+                    ))
+                })?;
+            }
+
+            let includes = compiler.include_manifest(&doc)?;
+            doc.frontmatter.includes = (!includes.is_empty()).then_some(includes);
+            if let Some(ref profile) = profile {
+                doc.frontmatter.author = Some(profile.author());
+            }
+            for contributor in doc.frontmatter.contributors.iter().flatten() {
+                contributor.validate()?;
+            }
+            if key_uri.is_some() {
+                doc.frontmatter.key_uri = key_uri.clone();
+            }
+            if file_hash {
+                doc.frontmatter.seal_mode = Some(SealMode::FileHash);
+            }
+            doc.frontmatter.public_key = Some(hex::encode(entry.keypair.verifying_key.as_bytes()));
+
+            render_sealed(&content, doc.seal(&entry.keypair)?, &entry.keypair)?
+        };
+        check_review_policy(&doc)?;
+
+        overlay.insert(entry.identity.clone(), text.clone());
+        hashes.insert(
+            entry.identity.clone(),
+            hex::encode(doc.frontmatter.version_hash.unwrap_or_default()),
+        );
+        sealed[index] = Some((doc, text));
+    }
+    let sealed: Vec<(TracedDocument, String)> = sealed.into_iter().flatten().collect();
+
+    // Write everything or nothing
+    for (written, (entry, (_, text))) in entries.iter().zip(&sealed).enumerate() {
+        if let Err(e) = write_atomic(&entry.path, text) {
+            for entry in &entries[..written] {
+                if let Err(restore) = write_atomic(&entry.path, &entry.content) {
+                    eprintln!(
+                        "Error: could not restore {}: {}",
+                        entry.path.display(),
+                        restore
+                    );
+                }
+            }
+            return Err(crate::error::RhodiError::Resolution(format!(
+                "Batch not sealed, writing {} failed: {}",
+                entry.path.display(),
+                e
+            )));
+        }
+    }
+
+    println!("Sealed {} document(s):", sealed.len());
+    for (entry, (doc, text)) in entries.iter().zip(&sealed) {
+        archive(&entry.workspace.history_dir(), doc, text)?;
+        println!(
+            "  {}  {}",
+            hex::encode(doc.frontmatter.version_hash.unwrap_or_default()),
+            entry.path.display()
+        );
+    }
+
+    for (entry, (doc, _)) in entries.iter().zip(&sealed) {
+        if let Some(url) = registry
+            .clone()
+            .or(entry.workspace.config.registry.url.clone())
+        {
+            let record = RegistryRecord::from_document(doc)?;
+            RegistryClient::new(&url).publish(&record)?;
+            println!("  Anchored {} in registry: {}", entry.path.display(), url);
+        }
+    }
+
+    Ok(())
+}
+
+/// The order to seal a batch in: every document after the batch documents
+/// it includes or quotes. Cycles cannot be sealed, since each version hash
+/// would depend on the other.
+fn batch_order(entries: &[BatchEntry]) -> Result<Vec<usize>> {
+    let mut dependencies: Vec<BTreeSet<usize>> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let resolver = entry.workspace.resolver(&entry.base)?;
+        let by_location = |source: &str| {
+            let identity = resolver.identity(source).ok()?;
+            entries.iter().position(|other| other.identity == identity)
+        };
+        let mut depends_on = BTreeSet::new();
+        for section in parse_tmd_sections(&entry.doc.body) {
+            let target = match section {
+                Section::Include(content) => {
+                    let include = parse_include_block(&content)?;
+                    match include.id {
+                        Some(id) => entries
+                            .iter()
+                            .position(|other| other.doc.frontmatter.id == id),
+                        None => by_location(&include.path),
+                    }
+                }
+                Section::Quote(quote) => by_location(&quote.source_doc),
+                _ => None,
+            };
+            depends_on.extend(target);
+        }
+        dependencies.push(depends_on);
+    }
+
+    let mut order = Vec::with_capacity(entries.len());
+    while order.len() < entries.len() {
+        let ready = (0..entries.len()).find(|index| {
+            !order.contains(index)
+                && dependencies[*index]
+                    .iter()
+                    .all(|dependency| order.contains(dependency))
+        });
+        match ready {
+            Some(index) => order.push(index),
+            None => {
+                let cycle: Vec<String> = (0..entries.len())
+                    .filter(|index| !order.contains(index))
+                    .map(|index| entries[index].path.display().to_string())
+                    .collect();
+                return Err(crate::error::RhodiError::Verification(format!(
+                    "Documents include or quote each other in a cycle and cannot be sealed: {}",
+                    cycle.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// Refuse to seal while automatic traces no longer extract their expected
+/// value, or warn about them under `--force`.
+fn check_automatic_traces<R: SourceResolver>(
+    compiler: &Compiler<'_, R>,
+    doc: &TracedDocument,
+    path: &Path,
+    force: bool,
+) -> Result<()> {
+    let failing = compiler.failing_automatic_traces(doc);
+    if !failing.is_empty() {
+        let details: Vec<String> = failing
+            .iter()
+            .map(|(trace, error)| format!("  - {}: {}", trace.source, error))
+            .collect();
+        if !force {
+            return Err(crate::error::RhodiError::Verification(format!(
+                "{} automatic trace(s) no longer match their expected value; fix them or seal with --force:\n{}",
+                failing.len(),
+                details.join("\n")
+            )));
+        }
+        note(
+            path,
+            format!(
+                "Warning: sealing with {} failing automatic trace(s) (--force):",
+                failing.len()
+            ),
+        );
+        for line in details {
+            note(path, line);
+        }
+    }
+    Ok(())
+}
+
+/// The key pair stored under `key_name`.
+fn load_keypair(key_name: &str) -> Result<KeyPair> {
+    let manager = KeyManager::new()?;
+    let signing_key = manager.get_key(key_name)?;
+    let verifying_key = signing_key.verifying_key();
+    Ok(KeyPair {
+        signing_key,
+        verifying_key,
+    })
+}
+
+/// A review policy must be satisfiable by the reviewers the document lists.
+fn check_review_policy(doc: &TracedDocument) -> Result<()> {
+    if let Some(required) = doc.frontmatter.policy.required_reviewers {
+        let eligible = crate::notary::eligible_reviewers(doc).len();
+        if eligible < required as usize {
+            return Err(crate::error::RhodiError::Verification(format!(
+                "Review policy requires {} reviewer signature(s), but only {} reviewer(s) other than the signer are listed with a key",
+                required, eligible
+            )));
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,88 @@
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::index::{Query, WorkspaceIndex};
+use crate::models::DocStatus;
+use std::path::{Path, PathBuf};
+
+pub struct SearchOptions {
+    pub source: Option<String>,
+    pub author: Option<String>,
+    pub status: Option<String>,
+    pub text: Option<String>,
+    pub json: bool,
+}
+
+pub fn run(dir: Option<PathBuf>, options: SearchOptions) -> Result<()> {
+    let start = dir.unwrap_or_else(|| PathBuf::from("."));
+    let workspace = Workspace::discover(&start)?;
+    let index = WorkspaceIndex::build(&workspace.root)?;
+
+    let status = options
+        .status
+        .map(|s| {
+            serde_json::from_value::<DocStatus>(serde_json::Value::String(s.to_lowercase()))
+                .map_err(|_| {
+                    RhodiError::Format(format!(
+                        "Unknown status '{}' (expected notes, draft, published or revoked)",
+                        s
+                    ))
+                })
+        })
+        .transpose()?;
+    let query = Query {
+        source: options
+            .source
+            .map(|s| workspace_relative(&workspace.root, &s)),
+        author: options.author,
+        status,
+        text: options.text,
+    };
+    let matches = index.search(&query);
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&matches)
+            .map_err(|e| RhodiError::Serialization(e.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    for (path, reason) in &index.skipped {
+        eprintln!("Skipped {}: {}", path.display(), reason);
+    }
+    if matches.is_empty() {
+        println!("No matching documents.");
+        return Ok(());
+    }
+    for doc in &matches {
+        println!(
+            "{}  {} [{}, v{}]{}",
+            doc.path.display(),
+            doc.title,
+            format!("{:?}", doc.status).to_lowercase(),
+            doc.doc_version,
+            doc.author
+                .as_deref()
+                .map(|a| format!(" by {}", a))
+                .unwrap_or_default()
+        );
+        if let Some(ref source) = query.source {
+            for claim in doc.claims.iter().filter(|c| c.cites(source)) {
+                println!("    {} = {}", claim.source, claim.expected);
+            }
+        }
+    }
+    println!("{} document(s)", matches.len());
+
+    Ok(())
+}
+
+/// Express an existing local file relative to the workspace root so it
+/// compares equal to indexed sources; anything else is used as given.
+fn workspace_relative(root: &Path, source: &str) -> String {
+    Path::new(source)
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.strip_prefix(root).ok().map(Path::to_path_buf))
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|| source.to_string())
+}
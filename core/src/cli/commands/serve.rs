@@ -0,0 +1,17 @@
+use crate::config::Workspace;
+use crate::error::Result;
+use crate::server::{self, Service};
+use std::path::PathBuf;
+
+/// Serve verify/status/extract over HTTP for the workspace containing `dir`.
+pub fn run(dir: Option<PathBuf>, addr: String, workers: usize) -> Result<()> {
+    let workspace = Workspace::discover(&dir.unwrap_or_else(|| PathBuf::from(".")))?;
+    println!(
+        "Serving {} on http://{} ({} workers)",
+        workspace.root.display(),
+        addr,
+        workers
+    );
+    println!("Endpoints: GET /health, POST /verify, POST /status, POST /extract");
+    server::run(Service::new(workspace), &addr, workers)
+}
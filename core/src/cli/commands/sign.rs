@@ -0,0 +1,48 @@
+use crate::cli::commands::seal::pending_path;
+use crate::cli::io::lock_document;
+use crate::config::Workspace;
+use crate::crypto::parse_signature;
+use crate::error::{Result, RhodiError};
+use crate::history::archive;
+use crate::lock::write_atomic;
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use std::fs;
+use std::path::PathBuf;
+
+/// Complete an external seal by attaching a signature produced outside rhodi.
+pub fn run(path: PathBuf, signature: String) -> Result<()> {
+    let _lock = lock_document(&path)?;
+    let pending = pending_path(&path);
+    if !pending.exists() {
+        return Err(RhodiError::Resolution(format!(
+            "No pending seal found at '{}'. Run 'rhodi seal {} --external' first.",
+            pending.display(),
+            path.display()
+        )));
+    }
+
+    let content = fs::read_to_string(&pending)?;
+    let doc = parse_tmd(&content)?;
+    let signature = parse_signature(&signature)?;
+
+    let doc = doc.complete_seal(signature)?;
+
+    let sealed = render_tmd_preserving(&content, &doc)?;
+    write_atomic(&path, &sealed)?;
+    fs::remove_file(&pending)?;
+    archive(
+        &Workspace::for_document(&path)?.history_dir(),
+        &doc,
+        &sealed,
+    )?;
+
+    println!("Document sealed successfully: {}", path.display());
+    println!("  Status: Published");
+    println!(
+        "  Version hash: {}",
+        hex::encode(doc.frontmatter.version_hash.unwrap_or_default())
+    );
+    println!("  Document version: {}", doc.frontmatter.doc_version);
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use crate::attestation::{self, Attestation};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::index::WorkspaceIndex;
+use crate::stats::WorkspaceStats;
+use chrono::Utc;
+use std::path::PathBuf;
+
+/// Print aggregate metrics of the workspace containing `dir` and its
+/// ledger, as a summary or as JSON.
+pub fn run(dir: Option<PathBuf>, json: bool) -> Result<()> {
+    let start = dir.unwrap_or_else(|| PathBuf::from("."));
+    let workspace = Workspace::discover(&start)?;
+    let index = WorkspaceIndex::build(&workspace.root)?;
+    // Attestations whose signature does not verify are not counted
+    let (ledger, forged): (Vec<Attestation>, Vec<Attestation>) =
+        attestation::read_all(&workspace.ledger_path())?
+            .into_iter()
+            .partition(|attestation| attestation.verify().is_ok());
+    let stats = WorkspaceStats::collect(&index, &workspace.config.presets, &ledger, Utc::now())?;
+
+    if json {
+        let json = serde_json::to_string_pretty(&stats)
+            .map_err(|e| RhodiError::Serialization(e.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    for (path, reason) in &index.skipped {
+        eprintln!("Skipped {}: {}", path.display(), reason);
+    }
+    if !forged.is_empty() {
+        eprintln!(
+            "Skipped {} ledger attestation(s) whose signature does not verify",
+            forged.len()
+        );
+    }
+    println!("Workspace: {}", workspace.root.display());
+    let statuses: Vec<String> = stats
+        .by_status
+        .iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect();
+    println!(
+        "Documents: {} ({}), {} sealed",
+        stats.documents,
+        statuses.join(", "),
+        stats.sealed
+    );
+    if stats.unreadable > 0 {
+        eprintln!(
+            "Warning: {} document(s) could not be read; their traces are not counted",
+            stats.unreadable
+        );
+    }
+    println!(
+        "Traces: {} ({:.1} per document)",
+        stats.traces,
+        stats.mean_traces_per_document()
+    );
+    for (extractor, count) in &stats.extractors {
+        println!("  {:<10} {}", extractor, count);
+    }
+    match stats.average_evidence_age_days {
+        Some(days) => println!("Average evidence age: {:.1} days", days),
+        None => println!("Average evidence age: unknown (no trace timestamps)"),
+    }
+
+    println!("Ledger: {} attestation(s)", stats.attestations);
+    for period in &stats.ledger {
+        let failure_rate = period
+            .failure_rate()
+            .map(|rate| format!("{:.1}%", rate * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {}  {} attested, {} with warnings, {} trace(s) reviewed, {} failing ({}), {} flagged",
+            period.month,
+            period.attestations,
+            period.with_warnings,
+            period.reviewed_traces,
+            period.failed_traces,
+            failure_rate,
+            period.flagged_traces
+        );
+    }
+    Ok(())
+}
@@ -1,14 +1,26 @@
+use crate::cli::io::{base_dir, is_stdio, read_document};
+use crate::config::Workspace;
+use crate::crypto::{hash_bytes, parse_public_key};
 use crate::error::Result;
-use crate::markdown::parse_tmd;
-use crate::version::{get_version_status, VersionStatus};
-use std::fs;
-use std::path::PathBuf;
+use crate::extraction::select_extractor;
+use crate::fingerprint::KeyFingerprint;
+use crate::markdown::{Section, parse_include_block, parse_tmd, parse_tmd_sections};
+use crate::models::{TraceBlock, TraceMethod, TracedDocument};
+use crate::resolver::{FileResolver, SourceResolver, guess_content_type};
+use crate::version::VersionStatus;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-pub fn run(path: PathBuf) -> Result<()> {
-    let content = fs::read_to_string(&path)?;
+pub fn run(path: PathBuf, deep: bool) -> Result<()> {
+    let content = read_document(&path)?;
     let doc = parse_tmd(&content)?;
 
-    println!("Document: {}", path.display());
+    if is_stdio(&path) {
+        println!("Document: <stdin>");
+    } else {
+        println!("Document: {}", path.display());
+    }
     println!("{}", "=".repeat(50));
     println!("Title:      {}", doc.frontmatter.title);
     println!("ID:         {}", doc.frontmatter.id);
@@ -16,6 +28,17 @@ pub fn run(path: PathBuf) -> Result<()> {
         "Author:     {}",
         doc.frontmatter.author.as_deref().unwrap_or("(none)")
     );
+    if let Some(ref author) = doc.frontmatter.author {
+        for (label, value) in [
+            ("Email:", &author.email),
+            ("ORCID:", &author.orcid),
+            ("Affiliation:", &author.affiliation),
+        ] {
+            if let Some(value) = value {
+                println!("  {:<12}{}", label, value);
+            }
+        }
+    }
     println!("Status:     {:?}", doc.frontmatter.doc_status);
     println!(
         "Created:    {}",
@@ -27,7 +50,8 @@ pub fn run(path: PathBuf) -> Result<()> {
     }
 
     println!("Protocol Version: {}", doc.frontmatter.protocol_version);
-    let version_status = get_version_status(&doc.frontmatter.protocol_version);
+    let versions = Workspace::discover(&base_dir(&path)?)?.version_registry()?;
+    let version_status = versions.status(&doc.frontmatter.protocol_version);
     println!(
         "Protocol Status:  {:?}",
         match version_status {
@@ -36,6 +60,20 @@ pub fn run(path: PathBuf) -> Result<()> {
             VersionStatus::Obsolete => "Obsolete",
         }
     );
+    if let Some(entry) = versions.entry(&doc.frontmatter.protocol_version) {
+        if let Some(base) = entry.based_on {
+            println!("  Based On:       {}", base);
+        }
+        if let Some(date) = entry.deprecated_on {
+            println!("  Deprecated On:  {}", date);
+        }
+        if let Some(date) = entry.obsolete_on {
+            println!("  Obsolete On:    {}", date);
+        }
+        if let Some(note) = entry.note {
+            println!("  Note:           {}", note);
+        }
+    }
     println!("Document Version: {}", doc.frontmatter.doc_version);
     if doc.frontmatter.doc_version > 0
         && let Some(ref prev_hash) = doc.frontmatter.prev_version_hash
@@ -45,6 +83,9 @@ pub fn run(path: PathBuf) -> Result<()> {
 
     if let Some(ref pk) = doc.frontmatter.public_key {
         println!("Public Key: {}", pk);
+        if let Ok(key) = parse_public_key(pk) {
+            println!("Fingerprint: {}", KeyFingerprint::of(&key));
+        }
     }
 
     if let Some(ref hash) = doc.frontmatter.version_hash {
@@ -62,5 +103,117 @@ pub fn run(path: PathBuf) -> Result<()> {
     println!("{}", "=".repeat(50));
     println!("Body length: {} characters", doc.body.len());
 
+    if deep {
+        print_inventory(&doc, &base_dir(&path)?)?;
+    }
+
+    Ok(())
+}
+
+fn print_inventory(doc: &TracedDocument, base_path: &Path) -> Result<()> {
+    let resolver = FileResolver::new(base_path)?;
+    let mut traces = Vec::new();
+    let mut includes = Vec::new();
+
+    for section in parse_tmd_sections(&doc.body) {
+        match section {
+            Section::Trace(trace) => {
+                traces.push(doc.frontmatter.resolve_trace(&trace).unwrap_or(trace))
+            }
+            Section::Include(block) => match parse_include_block(&block) {
+                Ok(include) => includes.push(include.target()),
+                Err(_) => includes.push("(invalid include block)".to_string()),
+            },
+            _ => {}
+        }
+    }
+
+    println!();
+    println!("Traces ({}):", traces.len());
+    let mut by_method: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_extractor: BTreeMap<String, usize> = BTreeMap::new();
+    for (i, trace) in traces.iter().enumerate() {
+        let method = method_name(&trace.method);
+        let extractor = match &trace.selector {
+            None => "none".to_string(),
+            Some(selector) => select_extractor(
+                trace.extractor.as_deref(),
+                guess_content_type(&trace.source),
+                selector,
+            )
+            .to_lowercase(),
+        };
+        println!(
+            "  {}. {} [{}, {}] hash: {} age: {}",
+            i + 1,
+            trace.source,
+            method,
+            extractor,
+            hash_status(trace, &resolver),
+            trace
+                .timestamp
+                .map(format_age)
+                .unwrap_or_else(|| "(no timestamp)".to_string())
+        );
+        *by_method.entry(method.to_string()).or_default() += 1;
+        *by_extractor.entry(extractor).or_default() += 1;
+    }
+
+    println!("Includes ({}):", includes.len());
+    for (i, include) in includes.iter().enumerate() {
+        println!("  {}. {}", i + 1, include);
+    }
+
+    println!("Summary:");
+    println!("  By method:    {}", format_counts(&by_method));
+    println!("  By extractor: {}", format_counts(&by_extractor));
+
     Ok(())
 }
+
+fn method_name(method: &TraceMethod) -> &'static str {
+    match method {
+        TraceMethod::Automatic => "automatic",
+        TraceMethod::Manual => "manual",
+        TraceMethod::Agent => "agent",
+    }
+}
+
+fn hash_status(trace: &TraceBlock, resolver: &FileResolver) -> &'static str {
+    let Some(expected) = &trace.hash else {
+        return "unhashed";
+    };
+    match resolver.resolve_bytes(&trace.source) {
+        Ok(content)
+            if trace
+                .normalized_source(&content)
+                .is_ok_and(|content| &hash_bytes(&content) == expected) =>
+        {
+            "ok"
+        }
+        Ok(_) => "MISMATCH",
+        Err(_) => "source unavailable",
+    }
+}
+
+fn format_age(timestamp: DateTime<Utc>) -> String {
+    let age = Utc::now().signed_duration_since(timestamp);
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+fn format_counts(counts: &BTreeMap<String, usize>) -> String {
+    if counts.is_empty() {
+        return "(none)".to_string();
+    }
+    counts
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,22 @@
+use crate::error::{Result, RhodiError};
+use crate::markdown::parse_tmd;
+use crate::models::IncludeStub;
+use std::fs;
+use std::path::PathBuf;
+
+/// Write an include stub for a sealed document, for readers who cannot
+/// access the document itself.
+pub fn run(path: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let doc = parse_tmd(&fs::read_to_string(&path)?)?;
+    let stub = IncludeStub::from_document(&doc)?;
+    let output =
+        output.unwrap_or_else(|| PathBuf::from(IncludeStub::path_for(&path.to_string_lossy())));
+    let json = serde_json::to_string_pretty(&stub)
+        .map_err(|e| RhodiError::Serialization(format!("Failed to write stub: {}", e)))?;
+    fs::write(&output, json + "\n")?;
+
+    println!("Created include stub: {}", output.display());
+    println!("  Document: {}", stub.id);
+    println!("  Version hash: {}", stub.version_hash);
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::cli::git::content_at;
+use crate::cli::io::{base_dir, is_stdio, lock_document, note, read_document, write_document};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::models::DocStatus;
+use crate::suggest::{
+    Claim, Suggestion, collect_evidence, insert_traces, suggest, untraced_claims,
+};
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// Suggestions offered per claim.
+const SUGGESTIONS_PER_CLAIM: usize = 3;
+
+/// Command-line options for `rhodi suggest`.
+#[derive(Debug, Default, Clone)]
+pub struct SuggestOptions {
+    /// Directories to search (default: `resolver.allow`, else the
+    /// document's directory)
+    pub evidence: Vec<PathBuf>,
+    /// Only claims on lines added or changed since this git revision
+    pub since: Option<String>,
+    /// Accept the first suggestion for every claim without asking
+    pub yes: bool,
+    /// List suggestions without changing the document
+    pub dry_run: bool,
+}
+
+pub fn run(path: PathBuf, options: SuggestOptions) -> Result<()> {
+    let SuggestOptions {
+        evidence,
+        since,
+        yes,
+        dry_run,
+    } = options;
+    if is_stdio(&path) && !yes && !dry_run {
+        return Err(RhodiError::Resolution(
+            "suggest on stdin cannot prompt; pass --yes or --dry-run".into(),
+        ));
+    }
+    let _lock = lock_document(&path)?;
+    let content = read_document(&path)?;
+    let mut doc = parse_tmd(&content)?;
+
+    let mut claims = untraced_claims(&doc.body);
+    if let Some(revision) = since {
+        // Lines already in the document at `revision` are not new claims
+        let old = parse_tmd(&content_at(&path, Some(&revision))?)?;
+        let old_lines: BTreeSet<&str> = old.body.lines().map(str::trim).collect();
+        claims.retain(|claim| !old_lines.contains(claim.line.as_str()));
+    }
+    if claims.is_empty() {
+        note(&path, "Every figure in the document is backed by a trace");
+        return Ok(());
+    }
+
+    let base = base_dir(&path)?;
+    let dirs = if evidence.is_empty() {
+        let workspace = Workspace::for_document(&path)?;
+        let allowed = &workspace.config.resolver.allow;
+        if allowed.is_empty() {
+            vec![base.clone()]
+        } else {
+            allowed.iter().map(|dir| workspace.root.join(dir)).collect()
+        }
+    } else {
+        evidence
+    };
+    let files = collect_evidence(&dirs, &base)?;
+
+    let mut accepted: Vec<(usize, String)> = Vec::new();
+    let mut unmatched = Vec::new();
+    for claim in &claims {
+        let suggestions = suggest(claim, &files, SUGGESTIONS_PER_CLAIM);
+        if suggestions.is_empty() {
+            unmatched.push(claim);
+            continue;
+        }
+        note(
+            &path,
+            format!(
+                "\nLine {}: {} ({})",
+                claim.line_index + 1,
+                claim.figure,
+                excerpt(claim)
+            ),
+        );
+        for (i, suggestion) in suggestions.iter().enumerate() {
+            note(&path, format!("  {}. {}", i + 1, describe(suggestion)));
+        }
+        let choice = if dry_run {
+            None
+        } else if yes {
+            Some(0)
+        } else {
+            match choose(suggestions.len())? {
+                Choice::Take(i) => Some(i),
+                Choice::Skip => None,
+                Choice::Quit => break,
+            }
+        };
+        if let Some(i) = choice {
+            accepted.push((claim.paragraph_end, suggestions[i].to_block()));
+        }
+    }
+
+    if !unmatched.is_empty() {
+        note(
+            &path,
+            format!("\nNo evidence found for {} figure(s):", unmatched.len()),
+        );
+        for claim in unmatched {
+            note(
+                &path,
+                format!("  - line {}: {}", claim.line_index + 1, claim.figure),
+            );
+        }
+    }
+    if dry_run || accepted.is_empty() {
+        note(&path, "\nNo traces added");
+        return Ok(());
+    }
+
+    doc.body = insert_traces(&doc.body, &accepted);
+    if doc.frontmatter.doc_status == DocStatus::Published {
+        doc.frontmatter.doc_status = DocStatus::Draft;
+    }
+    let doc = doc.update_modified_time();
+    write_document(&path, &render_tmd_preserving(&content, &doc)?)?;
+    note(&path, format!("\nAdded {} trace(s)", accepted.len()));
+    note(
+        &path,
+        "Review them, then run 'rhodi verify' and seal to sign them",
+    );
+    Ok(())
+}
+
+fn excerpt(claim: &Claim) -> String {
+    let line: String = claim.line.chars().take(60).collect();
+    if line.len() < claim.line.len() {
+        format!("\"{}...\"", line)
+    } else {
+        format!("\"{}\"", line)
+    }
+}
+
+fn describe(suggestion: &Suggestion) -> String {
+    format!(
+        "{} [{}] {} = {}",
+        suggestion.source, suggestion.extractor, suggestion.selector, suggestion.expected
+    )
+}
+
+enum Choice {
+    Take(usize),
+    Skip,
+    Quit,
+}
+
+fn choose(count: usize) -> Result<Choice> {
+    loop {
+        print!("Add trace [1-{}, Enter to skip, q to stop]: ", count);
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Ok(Choice::Quit);
+        }
+        match answer.trim() {
+            "" | "s" => return Ok(Choice::Skip),
+            "q" => return Ok(Choice::Quit),
+            n => match n.parse::<usize>() {
+                Ok(n) if (1..=count).contains(&n) => return Ok(Choice::Take(n - 1)),
+                _ => println!("Enter a number from 1 to {}", count),
+            },
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::cli::io::{is_stdio, lock_document, read_document};
+use crate::error::{Result, RhodiError};
+use crate::lock::write_atomic;
+use crate::markdown::{parse_tmd, render_tmd_preserving};
+use crate::models::{DocStatus, DocumentLink};
+use std::path::PathBuf;
+
+/// Link the sealed document at `old` to `new`, the document replacing it.
+/// A `new` without the link gets `supersedes`, which its next seal commits
+/// to; once `new` is sealed with it, `old` gets `superseded_by`.
+pub fn run(old: PathBuf, new: PathBuf) -> Result<()> {
+    if is_stdio(&old) || is_stdio(&new) {
+        return Err(RhodiError::Resolution(
+            "supersede links two documents on disk, not stdin".into(),
+        ));
+    }
+    if old.canonicalize()? == new.canonicalize()? {
+        return Err(RhodiError::Resolution(
+            "A document cannot supersede itself".into(),
+        ));
+    }
+    let _old_lock = lock_document(&old)?;
+    let _new_lock = lock_document(&new)?;
+    let old_content = read_document(&old)?;
+    let mut old_doc = parse_tmd(&old_content)?;
+    let new_content = read_document(&new)?;
+    let mut new_doc = parse_tmd(&new_content)?;
+
+    let link = DocumentLink::to(&old_doc).ok_or_else(|| {
+        RhodiError::Verification(format!(
+            "{} is not sealed; only a sealed version can be superseded",
+            old.display()
+        ))
+    })?;
+    match &new_doc.frontmatter.supersedes {
+        Some(existing) if existing != &link => {
+            return Err(RhodiError::Verification(format!(
+                "{} already supersedes {}",
+                new.display(),
+                existing
+            )));
+        }
+        Some(_) => {}
+        None => {
+            // `supersedes` is covered by the version hash
+            new_doc.frontmatter.supersedes = Some(link);
+            if new_doc.frontmatter.doc_status == DocStatus::Published {
+                new_doc.frontmatter.doc_status = DocStatus::Draft;
+            }
+            let new_doc = new_doc.update_modified_time();
+            write_atomic(&new, &render_tmd_preserving(&new_content, &new_doc)?)?;
+            println!("{} now supersedes {}", new.display(), old.display());
+            println!(
+                "Seal it, then run 'rhodi supersede {} {}' again to link the old version to it.",
+                old.display(),
+                new.display()
+            );
+            return Ok(());
+        }
+    }
+
+    let Some(successor) = DocumentLink::to(&new_doc) else {
+        println!(
+            "{} supersedes {} but is not sealed yet; seal it, then run this again.",
+            new.display(),
+            old.display()
+        );
+        return Ok(());
+    };
+    if new_doc.current_version_hash().ok() != new_doc.frontmatter.version_hash {
+        return Err(RhodiError::Verification(format!(
+            "{} no longer matches its version hash; seal it again first",
+            new.display()
+        )));
+    }
+    if old_doc.frontmatter.superseded_by.as_ref() == Some(&successor) {
+        println!("{} already links to {}", old.display(), new.display());
+        return Ok(());
+    }
+    // Not hashed, so the old version keeps its seal
+    old_doc.frontmatter.superseded_by = Some(successor);
+    write_atomic(&old, &render_tmd_preserving(&old_content, &old_doc)?)?;
+    println!("{} is superseded by {}", old.display(), new.display());
+    Ok(())
+}
@@ -1,26 +1,28 @@
+use crate::cli::io::{base_dir, lock_document, note, read_document, write_document};
 use crate::error::Result;
-use crate::markdown::parse_tmd;
-use std::fs;
+use crate::markdown::{parse_tmd, render_tmd_preserving};
 use std::path::PathBuf;
 
-pub fn run(path: PathBuf) -> Result<()> {
-    let content = fs::read_to_string(&path)?;
+pub fn run(path: PathBuf, anchor: bool, ids: bool) -> Result<()> {
+    let _lock = lock_document(&path)?;
+    let content = read_document(&path)?;
     let mut doc = parse_tmd(&content)?;
 
-    let base_path = if let Some(p) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
-        p.to_path_buf()
-    } else {
-        std::env::current_dir()?
-    };
+    let base_path = base_dir(&path)?;
 
     doc.update_all_traces(&base_path)?;
+    let anchored = if anchor { doc.anchor_traces()? } else { 0 };
+    let numbered = if ids { doc.assign_trace_ids()? } else { 0 };
 
-    let fm_yaml = serde_norway::to_string(&doc.frontmatter).map_err(|e| {
-        crate::error::RhodiError::Serialization(format!("Failed to serialize frontmatter: {}", e))
-    })?;
+    write_document(&path, &render_tmd_preserving(&content, &doc)?)?;
 
-    let full_content = format!("---\n{}\n---\n\n{}", fm_yaml.trim(), doc.body);
-    fs::write(&path, full_content)?;
+    note(&path, "Trace hashes updated successfully");
+    if anchor {
+        note(&path, format!("{} trace(s) anchored", anchored));
+    }
+    if ids {
+        note(&path, format!("{} trace(s) given an id", numbered));
+    }
 
     Ok(())
 }
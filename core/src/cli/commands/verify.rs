@@ -1,27 +1,413 @@
-use crate::compiler::{CompilationReport, Compiler};
-use crate::error::Result;
-use crate::markdown::parse_tmd;
-use crate::resolver::FileResolver;
-use std::fs;
-use std::path::PathBuf;
-
-pub fn run(path: PathBuf, strict: bool) -> Result<CompilationReport> {
-    let content = fs::read_to_string(&path)?;
-    let doc = parse_tmd(&content)?;
-
-    let base_path = if let Some(p) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
-        p.to_path_buf()
+use crate::bundle::BundleResolver;
+use crate::cli::git::{changed_since, content_at};
+use crate::cli::io::{base_dir, is_stdio, note, read_document, write_document};
+use crate::compiler::{CompilationReport, Compiler, TraceFix, VerifyPasses};
+use crate::config::Workspace;
+use crate::crypto::parse_public_key;
+use crate::discovery::parse_key;
+use crate::error::{Result, RhodiError};
+use crate::fingerprint::KeyFingerprint;
+use crate::freeze::WorkspaceManifest;
+use crate::history::walk_chain;
+use crate::incremental::{ChangeSet, changed_since_manifest};
+use crate::index::{IndexedDocument, WorkspaceIndex};
+use crate::markdown::{
+    Section, annotate_trace_blocks, parse_tmd, parse_tmd_sections, render_tmd_preserving,
+    update_trace_blocks,
+};
+use crate::lock::write_atomic_bytes;
+use crate::models::{DocStatus, TracedDocument, parse_duration};
+use crate::opentimestamps::{
+    Calendars, DEFAULT_EXPLORER, DetachedTimestamp, Esplora, TimestampStatus, proof_path,
+};
+use crate::registry::RegistryClient;
+use crate::report::{DocumentReport, VerificationReport};
+use crate::resolver::{ResolverRegistry, SourceResolver};
+use crate::telemetry::Telemetry;
+use crate::version::excludes_trace_annotations;
+use chrono::Utc;
+use serde_norway::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Command-line options for `rhodi verify`.
+#[derive(Debug, Default, Clone)]
+pub struct VerifyOptions {
+    pub strict: bool,
+    pub check_registry: bool,
+    /// Verify the OpenTimestamps proof next to the document
+    pub check_timestamp: bool,
+    pub registry: Option<String>,
+    /// On a version hash mismatch, report which components changed
+    pub diagnose: bool,
+    /// Write each trace's result back into the document
+    pub annotate: bool,
+    /// Which verification passes to run
+    pub passes: VerifyPasses,
+    /// Resolve sources from this evidence bundle (- for stdin) instead of the filesystem
+    pub bundle: Option<PathBuf>,
+    /// Offer to refresh hashes and expected values of traces whose source changed
+    pub fix: bool,
+    /// Accept every fix without asking
+    pub yes: bool,
+    /// Print a diff and source snippet for each failing trace
+    pub explain: bool,
+    /// Read the document as staged in git rather than from the working tree
+    pub staged: bool,
+    /// Read the document as committed at this git revision
+    pub revision: Option<String>,
+    /// Skip checks that need the network instead of failing them
+    pub offline: bool,
+    /// Require a key certificate issued by this root key (hex or `did:key:`)
+    pub trust_root: Option<String>,
+    /// How far in the future a timestamp may lie before it is reported, e.g. `10m`
+    pub clock_skew: Option<String>,
+    /// Walk `prev_version_hash` back through earlier versions, checking each link
+    pub chain: bool,
+    /// Report missing reviewer signatures as warnings, for a reviewer about
+    /// to sign off
+    pub pending_reviews: bool,
+    /// Fetch remote sources in full instead of revalidating cached copies
+    pub no_cache: bool,
+    /// Record spans and metrics of the run, for OpenTelemetry export
+    pub telemetry: Option<Rc<Telemetry>>,
+}
+
+/// Verify the document at `path`. With a bundle, `path` may be omitted (the
+/// bundle's own document is verified) or name a document inside the bundle.
+pub fn run(path: Option<PathBuf>, options: VerifyOptions) -> Result<CompilationReport> {
+    run_recorded(path, options, &mut VerificationReport::new("verify"))
+}
+
+/// Like [`run`], and add the document's entry to `record`, also when it
+/// cannot be verified at all.
+pub fn run_recorded(
+    path: Option<PathBuf>,
+    options: VerifyOptions,
+    record: &mut VerificationReport,
+) -> Result<CompilationReport> {
+    let recorded = record.documents.len();
+    let started = Instant::now();
+    let label = path.as_ref().map(|p| p.display().to_string());
+    let result = verify_document(path, options, record);
+    if let Err(ref e) = result
+        && record.documents.len() == recorded
+    {
+        record
+            .documents
+            .push(DocumentReport::unverifiable(label, e, started.elapsed()));
+    }
+    result
+}
+
+/// What `verify --since` has to do.
+#[derive(Debug, Default)]
+pub struct Since {
+    /// Documents to verify again, relative to the current directory where
+    /// possible
+    pub affected: Vec<PathBuf>,
+    /// Documents passed from cache
+    pub cached: Vec<IndexedDocument>,
+    /// The error to report with each document of `affected` that shares
+    /// its id with other workspace documents
+    pub shared_ids: BTreeMap<PathBuf, String>,
+}
+
+/// The documents `verify --since` has to verify again and those passed
+/// from cache. `baseline` is a freeze manifest file or a git revision.
+/// Documents sharing an id are always verified again, so the shared id is
+/// reported with them.
+pub fn since(baseline: &str) -> Result<Since> {
+    let cwd = std::env::current_dir()?.canonicalize()?;
+    let workspace = Workspace::discover(&cwd)?;
+    let index = WorkspaceIndex::build(&workspace.root)?;
+    let shared = shared_ids(&index);
+    let changed = if Path::new(baseline).is_file() {
+        let manifest = WorkspaceManifest::read(Path::new(baseline))?;
+        manifest.verify()?;
+        changed_since_manifest(&index, &manifest)
     } else {
-        std::env::current_dir()?
+        changed_since(baseline)?
+            .into_iter()
+            .filter_map(|path| {
+                path.strip_prefix(&workspace.root)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect()
+    };
+
+    let changes = ChangeSet::new(&index, &changed);
+    let relative = |path: &Path| {
+        let path = workspace.root.join(path);
+        path.strip_prefix(&cwd)
+            .map(Path::to_path_buf)
+            .unwrap_or(path)
+    };
+    let (shared_cached, cached): (Vec<_>, Vec<_>) = changes
+        .cached
+        .into_iter()
+        .partition(|doc| shared.contains_key(&doc.path));
+    Ok(Since {
+        affected: changes
+            .affected
+            .iter()
+            .chain(&shared_cached)
+            .map(|doc| relative(&doc.path))
+            .collect(),
+        cached: cached.into_iter().cloned().collect(),
+        shared_ids: shared
+            .into_iter()
+            .map(|(path, error)| (relative(&path), error))
+            .collect(),
+    })
+}
+
+/// An error for each document of the workspace that shares its id, keyed
+/// by its path relative to the root: an include by that id could mean any
+/// of the copies, and each claims the others' history.
+fn shared_ids(index: &WorkspaceIndex) -> BTreeMap<PathBuf, String> {
+    let mut errors = BTreeMap::new();
+    for (id, docs) in index.duplicate_ids() {
+        for doc in &docs {
+            let others: Vec<String> = docs
+                .iter()
+                .filter(|other| other.path != doc.path)
+                .map(|other| other.path.display().to_string())
+                .collect();
+            errors.insert(
+                doc.path.clone(),
+                format!(
+                    "Shares id {} with {}; give this copy its own with 'rhodi fix-id {}'",
+                    id,
+                    others.join(", "),
+                    doc.path.display()
+                ),
+            );
+        }
+    }
+    errors
+}
+
+fn verify_document(
+    path: Option<PathBuf>,
+    options: VerifyOptions,
+    record: &mut VerificationReport,
+) -> Result<CompilationReport> {
+    let started = Instant::now();
+    let label = path.as_ref().map(|p| p.display().to_string());
+    let VerifyOptions {
+        strict,
+        check_registry,
+        check_timestamp,
+        registry,
+        diagnose,
+        annotate,
+        passes,
+        bundle,
+        fix,
+        yes,
+        explain,
+        staged,
+        revision,
+        offline,
+        trust_root,
+        clock_skew,
+        chain,
+        pending_reviews,
+        no_cache,
+        telemetry,
+    } = options;
+
+    let bundle = bundle.as_deref().map(open_bundle).transpose()?;
+    // In a bundle of several documents, the path names the one whose
+    // sources are served
+    let bundle = match (bundle, &path) {
+        (Some(bundle), Some(path)) if bundle.has_document(&path.to_string_lossy()) => {
+            Some(bundle.select(&path.to_string_lossy())?)
+        }
+        (bundle, _) => bundle,
+    };
+    // The document is read from disk unless it only exists in the bundle
+    let on_disk = path
+        .as_ref()
+        .filter(|p| bundle.is_none() || is_stdio(p) || p.exists());
+    let original = on_disk
+        .map(|p| {
+            if staged || revision.is_some() {
+                content_at(p, revision.as_deref())
+            } else {
+                read_document(p)
+            }
+        })
+        .transpose()?;
+    let doc = match (&original, &path, &bundle) {
+        (Some(content), _, _) => parse_tmd(content)?,
+        (None, Some(path), Some(bundle)) if bundle.has_document(&path.to_string_lossy()) => {
+            bundle.document()?
+        }
+        (None, Some(path), Some(bundle)) => bundle.resolve_document(&path.to_string_lossy())?,
+        (None, None, Some(bundle)) => bundle.document()?,
+        _ => {
+            return Err(RhodiError::Resolution(
+                "No document to verify: pass a path or --bundle".to_string(),
+            ));
+        }
+    };
+    if (annotate || fix) && on_disk.is_none() {
+        return Err(RhodiError::Resolution(
+            "--annotate and --fix need a document on disk, not one inside a bundle".to_string(),
+        ));
+    }
+    // Earlier versions hash annotations, so writing them would break the seal
+    if annotate && !excludes_trace_annotations(&doc.frontmatter.protocol_version) {
+        return Err(RhodiError::Format(format!(
+            "Protocol version {} covers trace annotations with the version hash; run `rhodi migrate --to 2.3` first",
+            doc.frontmatter.protocol_version
+        )));
+    }
+
+    let mut workspace = match on_disk {
+        Some(path) => Workspace::for_document(path)?,
+        None => Workspace::discover(&std::env::current_dir()?)?,
+    };
+    if no_cache {
+        workspace.config.resolver.https.cache = Some(false);
+    }
+    let strict = strict || workspace.config.verify.strict;
+    // A bundle is hermetic: nothing is read from the filesystem or network
+    let locator = bundle.is_none().then(|| workspace.document_locator());
+    let resolver = match bundle {
+        Some(bundle) => ResolverRegistry::new(bundle),
+        None => workspace.resolver(&base_dir(path.as_deref().unwrap_or(Path::new(".")))?)?,
+    };
+    let registry_client = check_registry
+        .then(|| RegistryClient::from_url_or_env(registry, workspace.config.registry.url.clone()))
+        .transpose()?;
+    let mut compiler = Compiler::new(&resolver)
+        .with_passes(passes)
+        .with_exec_extractor(workspace.exec_extractor())
+        .with_presets(workspace.config.presets.clone())
+        .with_version_registry(workspace.version_registry()?)
+        .with_limits(workspace.config.limits.compiler_limits())
+        .with_offline(offline)
+        .with_pending_reviews(pending_reviews)
+        .with_telemetry(telemetry.as_deref())
+        .with_document_locator(locator)
+        .with_trust_root(trust_root.as_deref().map(parse_key).transpose()?);
+    if let Some(skew) = clock_skew {
+        compiler = compiler.with_clock_skew(parse_duration(&skew)?);
+    }
+    if let Some(ref client) = registry_client {
+        compiler = compiler.with_revocation_check(client);
+    }
+
+    let verified = compiler.verify_detailed(&doc)?;
+    let mut document = DocumentReport::new(label.clone(), &doc, &verified);
+    let mut report = verified.into_report();
+
+    if annotate && let (Some(path), Some(original)) = (on_disk, &original) {
+        let mut annotated = doc.clone();
+        annotated.body = annotate_trace_blocks(&doc.body, Utc::now(), |trace| {
+            compiler.check_document_trace(&doc, trace)
+        })?;
+        write_document(path, &render_tmd_preserving(original, &annotated)?)?;
+    }
+
+    if fix
+        && let (Some(path), Some(original)) = (on_disk, &original)
+        && let Some(fixed) = fix_traces(&compiler, &doc, path, yes)?
+    {
+        write_document(path, &render_tmd_preserving(original, &fixed)?)?;
+        let verified = compiler.verify_detailed(&fixed)?;
+        document = DocumentReport::new(label, &fixed, &verified);
+        report = verified.into_report();
+    }
+
+    if explain {
+        for section in parse_tmd_sections(&doc.body) {
+            if let Section::Trace(trace) = section
+                && let Some(explanation) = compiler.explain_trace(&doc, &trace)
+            {
+                println!("{}", explanation);
+            }
+        }
+    }
+
+    if diagnose
+        && let Some(stored_hash) = doc.frontmatter.version_hash
+        && doc.current_version_hash().ok() != Some(stored_hash)
+    {
+        report.warnings.push(tamper_diagnosis(&doc));
+    }
+
+    if let Some(ref client) = registry_client
+        && let Err(e) = client.verify_anchored(&doc)
+    {
+        report.errors.push(e);
+    }
+
+    let say = |message: String| match on_disk {
+        Some(path) => note(path, message),
+        None => println!("{}", message),
     };
+    if passes.signature
+        && doc.frontmatter.signature.is_some()
+        && let Some(key) = doc
+            .frontmatter
+            .public_key
+            .as_deref()
+            .and_then(|pk| parse_public_key(pk).ok())
+    {
+        say(format!("Signer fingerprint: {}", KeyFingerprint::of(&key)));
+    }
 
-    let resolver = FileResolver::new(&base_path)?;
-    let compiler = Compiler::new(&resolver);
+    if check_timestamp {
+        match on_disk.filter(|p| !is_stdio(p)) {
+            Some(path) => {
+                if let Some(confirmed) =
+                    check_timestamp_proof(path, &doc, &workspace, offline, &mut report)?
+                {
+                    say(confirmed);
+                }
+            }
+            None => report.errors.push(RhodiError::Resolution(
+                "--check-timestamp needs a document file to find the proof next to".to_string(),
+            )),
+        }
+    }
 
-    let report = compiler.verify(&doc)?;
+    if chain {
+        let mut search = vec![workspace.history_dir().join(doc.frontmatter.id.to_string())];
+        if let Some(path) = on_disk.filter(|p| !is_stdio(p)) {
+            search.push(base_dir(path)?);
+        }
+        let chain = walk_chain(&doc, &search);
+        say(format!("Version chain ({} version(s)):", chain.links.len()));
+        for link in &chain.links {
+            let found = link
+                .path
+                .as_ref()
+                .map(|p| format!(" ({})", p.display()))
+                .unwrap_or_default();
+            say(format!(
+                "  v{} {}{}",
+                link.doc_version,
+                &link.version_hash[..link.version_hash.len().min(16)],
+                found
+            ));
+        }
+        report.merge(chain.report);
+    }
 
+    record
+        .documents
+        .push(document.finish(&report, started.elapsed()));
     if strict && !report.errors.is_empty() {
-        return Err(crate::error::RhodiError::Verification(format!(
+        return Err(RhodiError::Verification(format!(
             "Verification failed with {} error(s)",
             report.errors.len()
         )));
@@ -29,3 +415,208 @@ pub fn run(path: PathBuf, strict: bool) -> Result<CompilationReport> {
 
     Ok(report)
 }
+
+/// Check the OpenTimestamps proof next to the document at `path`. A
+/// pending proof is first completed from its calendars where possible, and
+/// the completed proof written back. Returns the line to print for a proof
+/// anchored in Bitcoin.
+fn check_timestamp_proof(
+    path: &Path,
+    doc: &TracedDocument,
+    workspace: &Workspace,
+    offline: bool,
+    report: &mut CompilationReport,
+) -> Result<Option<String>> {
+    let proof_file = proof_path(path);
+    let Some(version_hash) = doc.frontmatter.version_hash else {
+        report.errors.push(RhodiError::Verification(
+            "Document is not sealed, so it has no version hash to timestamp".to_string(),
+        ));
+        return Ok(None);
+    };
+    let data = match std::fs::read(&proof_file) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            report.errors.push(RhodiError::Verification(format!(
+                "No timestamp proof at {}; seal with --timestamp to create one",
+                proof_file.display()
+            )));
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut proof = match DetachedTimestamp::parse(&data) {
+        Ok(proof) => proof,
+        Err(e) => {
+            report.errors.push(e);
+            return Ok(None);
+        }
+    };
+    if offline {
+        report.unverified.push(format!(
+            "Timestamp proof {}: unverified (offline)",
+            proof_file.display()
+        ));
+        return Ok(None);
+    }
+
+    let config = &workspace.config.timestamp;
+    if !proof.is_complete()? {
+        match Calendars::new(&config.calendars).upgrade(&mut proof) {
+            Ok(true) => write_atomic_bytes(&proof_file, &proof.to_bytes())?,
+            Ok(false) => {}
+            Err(e) => report
+                .warnings
+                .push(format!("Could not upgrade timestamp proof: {}", e)),
+        }
+    }
+    let explorer = Esplora::new(config.explorer.as_deref().unwrap_or(DEFAULT_EXPLORER));
+    match crate::opentimestamps::verify(&proof, &version_hash, &explorer) {
+        Ok(TimestampStatus::Confirmed { height, time }) => Ok(Some(format!(
+            "Timestamp: version existed by {} (Bitcoin block {})",
+            time.to_rfc3339(),
+            height
+        ))),
+        Ok(TimestampStatus::Pending { calendars }) => {
+            report.warnings.push(format!(
+                "Timestamp proof is pending, not yet anchored in Bitcoin (calendars: {})",
+                calendars.join(", ")
+            ));
+            Ok(None)
+        }
+        Err(e) => {
+            report.errors.push(e);
+            Ok(None)
+        }
+    }
+}
+
+/// Offer each failing trace's [`TraceFix`], apply the accepted ones and
+/// return the document to Draft. Returns `None` when nothing was fixed.
+fn fix_traces<R: SourceResolver>(
+    compiler: &Compiler<R>,
+    doc: &TracedDocument,
+    path: &Path,
+    yes: bool,
+) -> Result<Option<TracedDocument>> {
+    if !yes && is_stdio(path) {
+        return Err(RhodiError::Resolution(
+            "--fix on stdin cannot prompt; pass --yes".to_string(),
+        ));
+    }
+
+    let mut applied = Vec::new();
+    let mut skipped = Vec::new();
+    let mut shared_hashes = BTreeMap::new();
+    let body = update_trace_blocks(&doc.body, |trace| {
+        let fix = match compiler.propose_fix(doc, trace) {
+            Ok(Some(fix)) => fix,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                skipped.push(format!("{}: {}", trace.label(), e));
+                return Ok(None);
+            }
+        };
+        let change = match &trace.id {
+            Some(id) => format!("trace {} {}", id, describe_fix(&fix)),
+            None => describe_fix(&fix),
+        };
+        if !yes && !confirm(&format!("Fix {}?", change))? {
+            skipped.push(format!("{}: declined", trace.label()));
+            return Ok(None);
+        }
+
+        let mut fields = Mapping::new();
+        if fix.new_expected != fix.old_expected {
+            fields.insert("expected".into(), Value::String(fix.new_expected));
+        }
+        match fix.shared_source {
+            Some(name) => {
+                shared_hashes.insert(name, fix.new_hash);
+            }
+            None => {
+                fields.insert("hash".into(), Value::String(fix.new_hash));
+            }
+        }
+        applied.push(change);
+        Ok((!fields.is_empty()).then_some(Value::Mapping(fields)))
+    })?;
+
+    for entry in &skipped {
+        note(path, format!("  not fixed: {}", entry));
+    }
+    if applied.is_empty() {
+        note(path, "No traces fixed");
+        return Ok(None);
+    }
+
+    let mut fixed = doc.clone();
+    fixed.body = body;
+    if let Some(ref mut sources) = fixed.frontmatter.sources {
+        for (name, hash) in shared_hashes {
+            if let Some(decl) = sources.get_mut(&name) {
+                decl.hash = Some(hash);
+            }
+        }
+    }
+    if fixed.frontmatter.doc_status == DocStatus::Published {
+        fixed.frontmatter.doc_status = DocStatus::Draft;
+    }
+    fixed = fixed.update_modified_time();
+
+    note(path, format!("Fixed {} trace(s):", applied.len()));
+    for change in &applied {
+        note(path, format!("  - {}", change));
+    }
+    note(path, "Document set to Draft; re-seal to sign the fixes");
+    Ok(Some(fixed))
+}
+
+fn describe_fix(fix: &TraceFix) -> String {
+    if fix.new_expected != fix.old_expected {
+        format!(
+            "{}: expected '{}' -> '{}'",
+            fix.source, fix.old_expected, fix.new_expected
+        )
+    } else {
+        format!("{}: hash refreshed to {}", fix.source, fix.new_hash)
+    }
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn open_bundle(path: &Path) -> Result<BundleResolver> {
+    if is_stdio(path) {
+        BundleResolver::from_reader(std::io::stdin().lock())
+    } else {
+        BundleResolver::open(path)
+    }
+}
+
+fn tamper_diagnosis(doc: &TracedDocument) -> String {
+    match doc.diagnose_tamper() {
+        None => "Tamper diagnosis unavailable: this version was sealed without a hash manifest"
+            .to_string(),
+        Some(changed) if changed.is_empty() => {
+            "Tamper diagnosis: every component matches the seal-time manifest; \
+             the version_hash itself was altered"
+                .to_string()
+        }
+        Some(changed) => {
+            let mut message = format!(
+                "Tamper diagnosis: first diverging component is '{}'",
+                changed[0]
+            );
+            if changed.len() > 1 {
+                message.push_str(&format!(" (also changed: {})", changed[1..].join(", ")));
+            }
+            message
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::artifact;
+use crate::error::{Result, RhodiError};
+use crate::markdown::parse_tmd;
+use std::fs;
+use std::path::PathBuf;
+
+/// Check that `artifact` was exported from the sealed version of `doc_path`.
+pub fn run(artifact: PathBuf, doc_path: PathBuf) -> Result<()> {
+    let doc = parse_tmd(&fs::read_to_string(&doc_path)?)?;
+    let content = fs::read(&artifact).map_err(|e| {
+        RhodiError::Resolution(format!(
+            "Cannot read artifact '{}': {}",
+            artifact.display(),
+            e
+        ))
+    })?;
+    let records = artifact::read_all(&artifact::artifacts_path(&doc_path))?;
+    let check = artifact::verify_artifact(&doc, &content, &records)?;
+    let record = check.record;
+
+    println!("Artifact verified: {}", artifact.display());
+    println!(
+        "  Exported from: {} v{}",
+        doc_path.display(),
+        record.doc_version
+    );
+    println!("  Version hash: {}", record.version_hash);
+    println!("  Exported at: {}", record.exported_at);
+    for finding in &check.findings {
+        println!("Warning: {}", finding);
+    }
+
+    Ok(())
+}
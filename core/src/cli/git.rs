@@ -0,0 +1,95 @@
+//! The few git queries the CLI needs, run through the `git` executable.
+
+use crate::error::{Result, RhodiError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run `git` with `args` in the current directory and return its stdout.
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    git_in(Path::new("."), args)
+}
+
+/// Run `git` with `args` in `dir` and return its stdout.
+fn git_in(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| RhodiError::Resolution(format!("Cannot run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(RhodiError::Resolution(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// `.tmd` files added, copied, modified or renamed in the index, relative to
+/// the current directory (and limited to it).
+pub fn staged_documents() -> Result<Vec<PathBuf>> {
+    staged_documents_in(Path::new("."))
+}
+
+/// [`staged_documents`] relative to `dir` instead of the current directory.
+pub(crate) fn staged_documents_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let names = git_in(
+        dir,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--relative",
+            "--diff-filter=ACMR",
+            "-z",
+            "--",
+            "*.tmd",
+        ],
+    )?;
+    Ok(names
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| PathBuf::from(String::from_utf8_lossy(name).into_owned()))
+        .collect())
+}
+
+/// Files changed since `revision`, committed or not, plus untracked files,
+/// as absolute paths. Deleted files are included.
+pub fn changed_since(revision: &str) -> Result<Vec<PathBuf>> {
+    let top = git(&["rev-parse", "--show-toplevel"])?;
+    let top = PathBuf::from(String::from_utf8_lossy(&top).trim());
+    let mut names = git(&["diff", "--name-only", "--no-renames", "-z", revision, "--"])?;
+    names.extend(git(&[
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+        "--full-name",
+        "-z",
+        "--",
+        ":/",
+    ])?);
+    Ok(names
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| top.join(String::from_utf8_lossy(name).as_ref()))
+        .collect())
+}
+
+/// Content of `path` as committed at `revision`, or as staged in the index
+/// when `revision` is `None`.
+pub fn content_at(path: &Path, revision: Option<&str>) -> Result<String> {
+    let spec = format!(
+        "{}:./{}",
+        revision.unwrap_or_default(),
+        path.to_string_lossy().replace('\\', "/")
+    );
+    String::from_utf8(git(&["show", &spec])?)
+        .map_err(|e| RhodiError::Format(format!("Invalid UTF-8 in document: {}", e)))
+}
+
+/// Directory git runs hooks from, honoring `core.hooksPath`.
+pub fn hooks_dir() -> Result<PathBuf> {
+    let dir = git(&["rev-parse", "--git-path", "hooks"])?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&dir).trim()))
+}
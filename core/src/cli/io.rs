@@ -0,0 +1,82 @@
+//! Document input/output for CLI commands, where a path of `-` means
+//! stdin (when reading) and stdout (when writing).
+
+use crate::error::Result;
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use std::fmt::Display;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The path that stands for stdin/stdout.
+pub const STDIO_PATH: &str = "-";
+
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+/// Read a document from `path`, or from stdin for `-`.
+pub fn read_document(path: &Path) -> Result<String> {
+    read_document_from(path, std::io::stdin())
+}
+
+/// [`read_document`], reading `stdin` for `-`.
+pub(crate) fn read_document_from(path: &Path, mut stdin: impl Read) -> Result<String> {
+    if is_stdio(path) {
+        let mut content = String::new();
+        stdin.read_to_string(&mut content)?;
+        Ok(content)
+    } else {
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+/// Lock the document at `path` for a read-modify-write cycle, so another
+/// rhodi process (an editor plugin, CI) cannot write it in between. The lock
+/// file is `<path>.lock`; there is nothing to lock for `-`.
+pub fn lock_document(path: &Path) -> Result<Option<FileLock>> {
+    if is_stdio(path) {
+        Ok(None)
+    } else {
+        FileLock::acquire(path, DEFAULT_LOCK_TIMEOUT).map(Some)
+    }
+}
+
+/// Write a document to `path` atomically, or to stdout for `-`
+/// (newline-terminated).
+pub fn write_document(path: &Path, content: &str) -> Result<()> {
+    write_document_to(path, content, std::io::stdout().lock())
+}
+
+/// [`write_document`], writing to `stdout` for `-`.
+pub(crate) fn write_document_to(path: &Path, content: &str, mut stdout: impl Write) -> Result<()> {
+    if is_stdio(path) {
+        stdout.write_all(content.as_bytes())?;
+        if !content.ends_with('\n') {
+            stdout.write_all(b"\n")?;
+        }
+        stdout.flush()?;
+        Ok(())
+    } else {
+        write_atomic(path, content)
+    }
+}
+
+/// Directory that relative sources are resolved against: the document's
+/// directory, or the current directory for `-` and bare file names.
+pub fn base_dir(path: &Path) -> Result<PathBuf> {
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(p) if !is_stdio(path) => Ok(p.to_path_buf()),
+        _ => Ok(std::env::current_dir()?),
+    }
+}
+
+/// Print a progress message, moving it to stderr when the document itself
+/// is being written to stdout.
+pub fn note(path: &Path, message: impl Display) {
+    if is_stdio(path) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
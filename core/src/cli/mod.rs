@@ -1,6 +1,12 @@
 pub mod commands;
+pub mod git;
+pub mod io;
 pub mod keys;
 
+use crate::badge::Badge;
+use crate::compiler::{CompilationReport, VerifyPasses};
+use crate::identity::AuthorProfile;
+use crate::report::{DocumentReport, VerificationReport};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -24,32 +30,494 @@ enum Commands {
         /// Author name
         #[arg(long)]
         author: Option<String>,
+        /// Take the author (name, email, ORCID, affiliation) from a stored profile
+        #[arg(long, value_name = "PROFILE", conflicts_with = "author")]
+        author_profile: Option<String>,
+        /// Convert an existing Markdown file, keeping its body
+        #[arg(long, value_name = "MD")]
+        from_markdown: Option<PathBuf>,
+        /// Add trace block stubs after paragraphs that state figures
+        #[arg(long, requires = "from_markdown")]
+        suggest_traces: bool,
+    },
+    /// Run every check seal would, without writing anything, and report whether the document is ready
+    Check {
+        /// Path to the .tmd document (- for stdin)
+        path: PathBuf,
+        /// Key name seal would sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// Find figures no trace backs and propose traces from matching values in evidence files
+    Suggest {
+        /// Path to the .tmd document (- for stdin/stdout, with --yes or --dry-run)
+        path: PathBuf,
+        /// Directory to search for evidence (repeatable; default: resolver.allow, else the document's directory)
+        #[arg(long, value_name = "DIR")]
+        evidence: Vec<PathBuf>,
+        /// Only figures on lines added or changed since this git revision
+        #[arg(long, value_name = "REV")]
+        since: Option<String>,
+        /// Add the first suggestion for every figure without asking
+        #[arg(long, short)]
+        yes: bool,
+        /// List suggestions without changing the document
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
     },
     /// Compute hashes, sign, and publish a document
     Seal {
-        /// Path to the .tmd document
-        path: PathBuf,
+        /// Path to the .tmd document (- for stdin/stdout)
+        #[arg(required_unless_present = "batch", conflicts_with = "batch")]
+        path: Option<PathBuf>,
+        /// Seal these documents together: all are checked first, includes and quotes between them pin the new versions, and either every file is written or none
+        #[arg(long, value_name = "DOCS", num_args = 1.., conflicts_with_all = ["external", "gpg", "timestamp"])]
+        batch: Vec<PathBuf>,
         /// Key name to use (default: default)
         #[arg(long)]
         key: Option<String>,
+        /// Compute the version hash and write a pending seal for an external signer
+        #[arg(long, requires = "public_key")]
+        external: bool,
+        /// Hex-encoded public key of the external signer
+        #[arg(long)]
+        public_key: Option<String>,
+        /// Anchor the sealed version in this registry after sealing
+        #[arg(long)]
+        registry: Option<String>,
+        /// Record where the signing key can be discovered (did:key: or https:// URL)
+        #[arg(long)]
+        key_uri: Option<String>,
+        /// Seal even if automatic traces no longer extract their expected value
+        #[arg(long)]
+        force: bool,
+        /// Record this profile as the author and sign with its key unless --key is given
+        #[arg(long, value_name = "PROFILE")]
+        author_profile: Option<String>,
+        /// Hash the exact file text rather than the parsed document (recorded as seal_mode: file-hash)
+        #[arg(long, conflicts_with = "external")]
+        file_hash: bool,
+        /// Sign with this key (ID, fingerprint or user ID) from your GnuPG keyring; it must be Ed25519
+        #[arg(long, value_name = "KEY", conflicts_with_all = ["key", "external", "file_hash"])]
+        gpg: Option<String>,
+        /// Timestamp the version hash with OpenTimestamps calendars, writing the proof to <document>.ots
+        #[arg(long, conflicts_with = "external")]
+        timestamp: bool,
     },
-    /// Verify document integrity and traces
-    Verify {
+    /// Complete an external seal with a signature produced elsewhere
+    Sign {
         /// Path to the .tmd document
         path: PathBuf,
+        /// Hex-encoded Ed25519 signature over the pending version hash
+        #[arg(long)]
+        signature: String,
+    },
+    /// Verify document integrity and traces
+    Verify {
+        /// Path to the .tmd document (- for stdin/stdout; with --bundle, optional)
+        #[arg(required_unless_present_any = ["bundle", "staged_only", "since"])]
+        path: Option<PathBuf>,
         /// Exit with error if any trace fails (default: warn only)
         #[arg(long, short)]
         strict: bool,
+        /// Confirm the version hash is anchored in the registry
+        #[arg(long)]
+        check_registry: bool,
+        /// Verify the OpenTimestamps proof in <document>.ots against the Bitcoin blockchain
+        #[arg(long)]
+        check_timestamp: bool,
+        /// Registry URL (default: $RHODI_REGISTRY_URL)
+        #[arg(long)]
+        registry: Option<String>,
+        /// On a version hash mismatch, report which component changed
+        #[arg(long)]
+        diagnose: bool,
+        /// Record each trace's result (verified_at, last_result) in the document
+        #[arg(long)]
+        annotate: bool,
+        /// Check hashes and signatures, including included documents, but not traces
+        #[arg(long, conflicts_with_all = ["annotate", "traces_only", "signature_only"])]
+        skip_traces: bool,
+        /// Check only this document's hash and signature
+        #[arg(long, conflicts_with_all = ["annotate", "traces_only"])]
+        signature_only: bool,
+        /// Check traces and includes without verifying signatures
+        #[arg(long)]
+        traces_only: bool,
+        /// Resolve sources from an evidence bundle (- for stdin) instead of the filesystem
+        #[arg(long, value_name = "BUNDLE")]
+        bundle: Option<PathBuf>,
+        /// Refresh hash and expected value of traces whose source changed (sets Draft)
+        #[arg(long, conflicts_with_all = ["annotate", "bundle", "skip_traces", "signature_only"])]
+        fix: bool,
+        /// Apply every --fix without asking
+        #[arg(long, short, requires = "fix")]
+        yes: bool,
+        /// For each failing trace, show a diff of expected vs extracted value and the source around the match
+        #[arg(long, conflicts_with_all = ["annotate", "fix", "skip_traces", "signature_only"])]
+        explain: bool,
+        /// Verify the staged version of every .tmd file staged in git (for pre-commit hooks)
+        #[arg(long, conflicts_with_all = ["path", "bundle", "annotate", "fix"])]
+        staged_only: bool,
+        /// Verify only the workspace documents affected by changes since this git revision or freeze manifest; the rest pass from cache
+        #[arg(long, value_name = "REV|MANIFEST", conflicts_with_all = ["path", "staged_only", "rev", "bundle", "annotate", "fix"])]
+        since: Option<String>,
+        /// Verify the document as committed at this git revision (for pre-push hooks)
+        #[arg(long, value_name = "REV", conflicts_with_all = ["staged_only", "bundle", "annotate", "fix"])]
+        rev: Option<String>,
+        /// Skip sources, includes and key URIs that need the network, listing them as unverified
+        #[arg(long, conflicts_with_all = ["check_registry", "fix"])]
+        offline: bool,
+        /// Download remote sources in full instead of revalidating the cached copies
+        #[arg(long)]
+        no_cache: bool,
+        /// Accept only documents whose key is certified by this organization root key (hex or did:key)
+        #[arg(long, value_name = "PUBKEY", conflicts_with = "traces_only")]
+        trust_root: Option<String>,
+        /// How far in the future a timestamp may lie before it is reported (default: 5m)
+        #[arg(long, value_name = "DURATION")]
+        clock_skew: Option<String>,
+        /// Walk prev_version_hash back through the history store and sibling files, checking each version's hash and signature
+        #[arg(long)]
+        chain: bool,
+        /// Also write a machine-readable JSON report of the verification here
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<PathBuf>,
+        /// Also write the overall status as a shields.io endpoint badge here
+        #[arg(long, value_name = "FILE")]
+        badge_json: Option<PathBuf>,
+        /// Export spans and metrics of the run to this OpenTelemetry collector over OTLP/HTTP (default: $OTEL_EXPORTER_OTLP_ENDPOINT)
+        #[arg(long, value_name = "URL")]
+        otlp_endpoint: Option<String>,
+    },
+    /// Pack a document and its local sources into an evidence bundle
+    Bundle {
+        /// Paths to the .tmd documents; sources they share are stored once
+        #[arg(required_unless_present = "workspace")]
+        paths: Vec<PathBuf>,
+        /// Bundle every document of the workspace containing the current directory
+        #[arg(long, conflicts_with = "paths")]
+        workspace: bool,
+        /// Output file (default: <document>.rhodi.tar, or workspace.rhodi.tar for several documents)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Write an include stub for a sealed document, so documents including it verify without access to it
+    Stub {
+        /// Path to the sealed .tmd document
+        path: PathBuf,
+        /// Output file (default: <document>.stub.json)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Verify a document and record a signed attestation of the result
+    Attest {
+        /// Path to the .tmd document
+        path: PathBuf,
+        /// Key name to sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Record in the workspace ledger instead of next to the document
+        #[arg(long)]
+        ledger: bool,
+        /// List recorded attestations instead of creating one
+        #[arg(long, conflicts_with = "key")]
+        list: bool,
+        /// Also write a machine-readable JSON report of the verification here
+        #[arg(long, value_name = "FILE", conflicts_with = "list")]
+        report_file: Option<PathBuf>,
+    },
+    /// Review a document's traces one by one in a terminal UI and export a signed attestation of the verdicts; `review add` appends a comment
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Review {
+        #[command(subcommand)]
+        action: Option<ReviewAction>,
+        /// Path to the .tmd document
+        #[arg(required = true)]
+        path: Option<PathBuf>,
+        /// Key name to sign the attestation with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Record in the workspace ledger instead of next to the document
+        #[arg(long)]
+        ledger: bool,
+    },
+    /// Verify a sealed document and append your counter-signature to it
+    Notarize {
+        /// Path to the .tmd document
+        path: PathBuf,
+        /// Key name to sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Name to record alongside the notary's key
+        #[arg(long)]
+        name: Option<String>,
+        /// Also write a machine-readable JSON report of the verification here
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<PathBuf>,
+    },
+    /// Sign a manifest of the version hash of every sealed document in the workspace
+    Freeze {
+        /// Directory inside the workspace (default: current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Key name to sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Name of the release, e.g. v2.1
+        #[arg(long)]
+        release: Option<String>,
+        /// Manifest file (default: rhodi.freeze.json at the workspace root)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Check the workspace against an existing manifest instead of writing one
+        #[arg(long, conflicts_with_all = ["key", "release"])]
+        check: bool,
+    },
+    /// Certify an author's public key with an organization root key
+    Certify {
+        /// Author public key to certify (hex or did:key)
+        subject: String,
+        /// Root key name to sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Author name to record in the certificate
+        #[arg(long)]
+        name: Option<String>,
+        /// Validity period, e.g. 365d (default: no expiry)
+        #[arg(long, value_name = "DURATION")]
+        expires_in: Option<String>,
+        /// Write the certificate to this file (default: stdout)
+        #[arg(long, short, conflicts_with = "attach")]
+        output: Option<PathBuf>,
+        /// Embed the certificate in this document's frontmatter
+        #[arg(long, value_name = "DOCUMENT")]
+        attach: Option<PathBuf>,
+    },
+    /// Print the sha256 hash of a file as trace blocks expect it, optionally with the value a selector extracts
+    Hash {
+        /// File to hash (- for stdin)
+        path: PathBuf,
+        /// Selector to run against the file
+        #[arg(long)]
+        selector: Option<String>,
+        /// Extractor for the selector (default: regex)
+        #[arg(long, requires = "selector")]
+        extractor: Option<String>,
+        /// Transform applied to the extracted value (repeatable, in order)
+        #[arg(long = "transform", value_name = "STEP", requires = "selector")]
+        transforms: Vec<String>,
+        /// Normalize the file before hashing and extraction (html)
+        #[arg(long, value_name = "KIND")]
+        normalize: Option<String>,
+        /// Decompress the file first (gzip, zstd or zip; name a zip entry as archive.zip!entry)
+        #[arg(long, value_name = "KIND")]
+        compression: Option<String>,
+    },
+    /// Record the hash of an artifact (PDF, HTML, ...) exported from a sealed document
+    Export {
+        /// Path to the sealed .tmd document
+        path: PathBuf,
+        /// The exported artifact
+        artifact: PathBuf,
+        /// Render the document to HTML at the artifact path before recording it
+        #[arg(long)]
+        render: bool,
+        /// Verify the document and render a status badge after each trace and one for the document (Markdown for a .md artifact)
+        #[arg(long, requires = "render")]
+        badges: bool,
+        /// Key name to sign with; must be the key that sealed the document
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// Check that an artifact was exported from the sealed version of a document
+    VerifyArtifact {
+        /// The exported artifact
+        artifact: PathBuf,
+        /// Path to the .tmd document it was exported from
+        #[arg(long)]
+        doc: PathBuf,
+    },
+    /// Write a rendition of a sealed document with its redact blocks replaced by their commitments
+    Redact {
+        /// Path to the sealed .tmd document
+        path: PathBuf,
+        /// Where to write the redacted rendition (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Serve verify, status and extract as JSON endpoints over HTTP
+    Serve {
+        /// Directory inside the workspace to serve (default: current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8750")]
+        addr: String,
+        /// Number of worker threads handling requests
+        #[arg(long, default_value_t = 4)]
+        workers: usize,
+    },
+    /// Check one trace against its source in a confined process; run by
+    /// the verifier for sandboxed sources, with the request on stdin
+    #[command(hide = true)]
+    SandboxExtract,
+    /// Extract or confirm `method: agent` traces with the configured LLM endpoint
+    Agent {
+        /// Path to the .tmd document (- for stdin/stdout)
+        path: PathBuf,
+        /// Chat completions URL (default: $RHODI_AGENT_URL or agent.endpoint)
+        #[arg(long)]
+        endpoint: Option<String>,
+        /// Model name (default: agent.model)
+        #[arg(long)]
+        model: Option<String>,
+        /// Overwrite expected values with the agent's answers
+        #[arg(long)]
+        fill: bool,
+    },
+    /// Anchor a sealed document version in a registry
+    Publish {
+        /// Path to the .tmd document
+        path: PathBuf,
+        /// Registry URL (default: $RHODI_REGISTRY_URL)
+        #[arg(long)]
+        registry: Option<String>,
+    },
+    /// Change the title, author or extra metadata of a published document and re-seal it as a new version
+    Amend {
+        /// Path to the .tmd document (- for stdin/stdout)
+        path: PathBuf,
+        /// New title
+        #[arg(long)]
+        title: Option<String>,
+        /// New author name
+        #[arg(long)]
+        author: Option<String>,
+        /// Set an extra metadata entry (repeatable)
+        #[arg(long, value_name = "KEY=VALUE")]
+        set: Vec<String>,
+        /// Remove an extra metadata entry (repeatable)
+        #[arg(long, value_name = "KEY")]
+        unset: Vec<String>,
+        /// Why the document is amended, recorded in extra.amendment_reason
+        #[arg(long, short)]
+        reason: String,
+        /// Key name to sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// Re-seal every workspace document signed with a compromised key
+    ResealAll {
+        /// Compromised key: hex public key or stored key name
+        #[arg(long)]
+        compromised: String,
+        /// New key name to sign with (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Reason recorded in each document's extra.reseal_reason
+        #[arg(long)]
+        reason: Option<String>,
+        /// Directory inside the workspace (default: current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// List the affected documents without changing them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Refresh hash in all trace blocks
     Update {
+        /// Path to the .tmd document (- for stdin/stdout)
+        path: PathBuf,
+        /// Also anchor unanchored traces to the sentence or heading they follow
+        #[arg(long)]
+        anchor: bool,
+        /// Also give traces without an id the next free `t-NNN` id
+        #[arg(long)]
+        ids: bool,
+    },
+    /// Give a document that shares its id with another workspace document a fresh id
+    FixId {
+        /// Path to the .tmd document to re-identify (the copy)
+        path: PathBuf,
+        /// Show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Link a sealed document to the document that replaces it
+    Supersede {
+        /// Path to the sealed .tmd document being replaced
+        old: PathBuf,
+        /// Path to the .tmd document replacing it
+        new: PathBuf,
+    },
+    /// Upgrade a document to a newer protocol version
+    Migrate {
         /// Path to the .tmd document
         path: PathBuf,
+        /// Target protocol version (default: latest)
+        #[arg(long)]
+        to: Option<String>,
+        /// List the changes without writing the document
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Show document status and metadata
     Status {
-        /// Path to the .tmd document
+        /// Path to the .tmd document (- for stdin/stdout)
         path: PathBuf,
+        /// Also list every trace and include with a summary
+        #[arg(long)]
+        deep: bool,
+    },
+    /// Find documents in the workspace by cited source, author, status or text
+    Search {
+        /// Documents citing this source file or URL
+        #[arg(long)]
+        source: Option<String>,
+        /// Author name (case-insensitive substring)
+        #[arg(long)]
+        author: Option<String>,
+        /// Document status: notes, draft, published or revoked
+        #[arg(long)]
+        status: Option<String>,
+        /// Text in the title or a claimed value
+        text: Option<String>,
+        /// Directory inside the workspace to search (default: current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Print matches as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show workspace metrics: sealed documents, traces, extractors, evidence age and ledger failure rates
+    Stats {
+        /// Directory inside the workspace (default: current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Print the metrics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Install git hooks that verify documents before commit and push
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+    /// List, fingerprint or delete stored signing keys
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+    /// Manage author profiles (name, email, ORCID, default key)
+    Author {
+        #[command(subcommand)]
+        action: AuthorAction,
+    },
+    /// Read or change workspace settings in rhodi.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
     },
     /// Generate a new Ed25519 keypair
     Keygen {
@@ -62,6 +530,133 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ReviewAction {
+    /// Append a review comment to a document, signed unless --unsigned; the version hash does not cover it
+    Add {
+        /// Path to the .tmd document
+        path: PathBuf,
+        /// The comment
+        #[arg(long, short)]
+        comment: String,
+        /// Reviewer name (default: the contributor listed with your key)
+        #[arg(long)]
+        author: Option<String>,
+        /// Key name to sign with (default: keys.default or default)
+        #[arg(long, conflicts_with = "unsigned")]
+        key: Option<String>,
+        /// Record the comment without a signature
+        #[arg(long)]
+        unsigned: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksAction {
+    /// Install pre-commit and pre-push hooks that verify changed documents
+    Install {
+        /// Replace existing hooks not installed by rhodi
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the hooks installed by rhodi
+    Uninstall,
+}
+
+#[derive(Subcommand)]
+enum KeysAction {
+    /// Show every key with its creation date and public key
+    List,
+    /// Print the short fingerprint of a key, in hex and as words, to compare it out loud
+    Fingerprint {
+        /// Name of a stored key, or a public key (hex or did:key)
+        key: String,
+        /// Fail unless the fingerprint is this one (hex or words)
+        #[arg(long, value_name = "FINGERPRINT")]
+        expect: Option<String>,
+    },
+    /// Split a key into printable share files, any THRESHOLD of which restore it (Shamir secret sharing)
+    Backup {
+        /// Key to back up (default: keys.default or default)
+        #[arg(long)]
+        key: Option<String>,
+        /// Number of shares, one per custodian
+        #[arg(long, value_parser = clap::value_parser!(u8).range(2..))]
+        shares: u8,
+        /// Shares needed to restore the key
+        #[arg(long, value_parser = clap::value_parser!(u8).range(2..))]
+        threshold: u8,
+        /// Directory to write the share files to (default: current directory)
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Rebuild a key from share files written by 'keys backup'
+    Restore {
+        /// Share files, at least the backup's threshold
+        #[arg(required = true)]
+        shares: Vec<PathBuf>,
+        /// Store the key under this name instead of the one it was backed up under
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Delete a key
+    Delete {
+        /// Name of the key
+        name: String,
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+        /// Overwrite the key file with random bytes before removing it
+        #[arg(long)]
+        shred: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthorAction {
+    /// Create or replace an author profile
+    Add {
+        /// Name to select the profile by, e.g. ada
+        profile: String,
+        /// Author name as it appears in documents
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        email: Option<String>,
+        /// ORCID iD, e.g. 0000-0002-1825-0097
+        #[arg(long)]
+        orcid: Option<String>,
+        #[arg(long)]
+        affiliation: Option<String>,
+        /// Key seal signs with under this profile
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// Show every author profile
+    List,
+    /// Delete an author profile
+    Remove {
+        /// Name of the profile
+        profile: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a setting, or the whole configuration if no key is given
+    Get {
+        /// Dotted key, e.g. keys.default
+        key: Option<String>,
+    },
+    /// Change a setting
+    Set {
+        /// Dotted key, e.g. registry.url
+        key: String,
+        /// New value (TOML literal or plain string)
+        value: String,
+    },
+}
+
 pub fn run() {
     let cli = Cli::parse();
 
@@ -70,53 +665,588 @@ pub fn run() {
             path,
             title,
             author,
+            author_profile,
+            from_markdown,
+            suggest_traces,
         } => {
-            if let Err(e) = crate::cli::commands::init::run(path, title, author) {
+            if let Err(e) = crate::cli::commands::init::run(
+                path,
+                title,
+                author,
+                author_profile,
+                from_markdown,
+                suggest_traces,
+            ) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
-        Commands::Seal { path, key } => {
-            if let Err(e) = crate::cli::commands::seal::run(path, key) {
+        Commands::Check { path, key } => {
+            if let Err(e) = crate::cli::commands::check::run(path, key) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
-        Commands::Verify { path, strict } => {
-            match crate::cli::commands::verify::run(path, strict) {
-                Ok(report) => {
-                    if !report.warnings.is_empty() {
-                        println!("Warnings:");
-                        for warning in &report.warnings {
-                            println!("  - {}", warning);
-                        }
+        Commands::Suggest {
+            path,
+            evidence,
+            since,
+            yes,
+            dry_run,
+        } => {
+            let options = crate::cli::commands::suggest::SuggestOptions {
+                evidence,
+                since,
+                yes,
+                dry_run,
+            };
+            if let Err(e) = crate::cli::commands::suggest::run(path, options) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Seal {
+            path,
+            batch,
+            key,
+            external,
+            public_key,
+            registry,
+            key_uri,
+            force,
+            author_profile,
+            file_hash,
+            gpg,
+            timestamp,
+        } => {
+            let options = crate::cli::commands::seal::SealOptions {
+                key_name: key,
+                external,
+                public_key,
+                registry,
+                key_uri,
+                force,
+                author_profile,
+                file_hash,
+                gpg_key: gpg,
+                timestamp,
+            };
+            let result = match path {
+                Some(path) => crate::cli::commands::seal::run(path, options),
+                None => crate::cli::commands::seal::run_batch(batch, options),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Sign { path, signature } => {
+            if let Err(e) = crate::cli::commands::sign::run(path, signature) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Verify {
+            path,
+            strict,
+            check_registry,
+            check_timestamp,
+            registry,
+            diagnose,
+            annotate,
+            skip_traces,
+            signature_only,
+            traces_only,
+            bundle,
+            fix,
+            yes,
+            explain,
+            staged_only,
+            since,
+            rev,
+            offline,
+            no_cache,
+            trust_root,
+            clock_skew,
+            chain,
+            report_file,
+            badge_json,
+            otlp_endpoint,
+        } => {
+            let otlp_endpoint = otlp_endpoint
+                .or_else(|| std::env::var(crate::telemetry::OTLP_ENDPOINT_ENV).ok())
+                .filter(|url| !url.trim().is_empty());
+            let telemetry = otlp_endpoint
+                .is_some()
+                .then(|| std::rc::Rc::new(crate::telemetry::Telemetry::new()));
+            let passes = if signature_only {
+                VerifyPasses::SIGNATURE_ONLY
+            } else if skip_traces {
+                VerifyPasses::SKIP_TRACES
+            } else if traces_only {
+                VerifyPasses::TRACES_ONLY
+            } else {
+                VerifyPasses::ALL
+            };
+            let options = crate::cli::commands::verify::VerifyOptions {
+                strict,
+                check_registry,
+                check_timestamp,
+                registry,
+                diagnose,
+                annotate,
+                passes,
+                bundle,
+                fix,
+                yes,
+                explain,
+                staged: staged_only,
+                revision: rev,
+                offline,
+                trust_root,
+                clock_skew,
+                chain,
+                pending_reviews: false,
+                no_cache,
+                telemetry: telemetry.clone(),
+            };
+            // A document written back to stdout keeps the report off stdout
+            let to_stderr = (annotate || fix) && path.as_deref().is_some_and(io::is_stdio);
+            let say = |message: String| {
+                if to_stderr {
+                    eprintln!("{}", message);
+                } else {
+                    println!("{}", message);
+                }
+            };
+            let mut record = VerificationReport::new("verify");
+            let mut shared_ids = std::collections::BTreeMap::new();
+            let paths = if staged_only {
+                match git::staged_documents() {
+                    Ok(paths) => paths.into_iter().map(Some).collect(),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
                     }
-                    if !report.errors.is_empty() {
-                        eprintln!("Errors found:");
-                        for err in &report.errors {
-                            eprintln!("  - {}", err);
+                }
+            } else if let Some(ref baseline) = since {
+                match crate::cli::commands::verify::since(baseline) {
+                    Ok(since) => {
+                        let crate::cli::commands::verify::Since {
+                            affected,
+                            cached,
+                            shared_ids: shared,
+                        } = since;
+                        shared_ids = shared;
+                        for doc in &cached {
+                            say(format!("{}: cached pass", doc.path.display()));
+                            record.documents.push(DocumentReport::cached(doc));
                         }
+                        if let Some(ref telemetry) = telemetry {
+                            let hits = cached.len() as u64;
+                            telemetry.add(
+                                "rhodi.cache.hits",
+                                hits,
+                                &[("rhodi.cache", "incremental")],
+                            );
+                        }
+                        affected.into_iter().map(Some).collect()
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
                         std::process::exit(1);
                     }
-                    if report.warnings.is_empty() && report.errors.is_empty() {
-                        println!("✓ Document verified successfully");
+                }
+            } else {
+                vec![path]
+            };
+            if paths.is_empty() {
+                say(match &since {
+                    Some(baseline) => format!("No documents affected since {}", baseline),
+                    None => "No staged documents to verify".to_string(),
+                });
+            }
+            let mut failed = false;
+            for path in paths {
+                if (staged_only || since.is_some())
+                    && let Some(ref path) = path
+                {
+                    say(format!("{}:", path.display()));
+                }
+                let shared = path.as_ref().and_then(|path| shared_ids.get(path).cloned());
+                match crate::cli::commands::verify::run_recorded(path, options.clone(), &mut record)
+                {
+                    Ok(report) => failed |= !print_report(&report, say),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        failed = true;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    std::process::exit(1);
+                if let Some(error) = shared {
+                    eprintln!("Error: {}", error);
+                    if let Some(entry) = record.documents.last_mut() {
+                        entry.add_error(error);
+                    }
+                    failed = true;
                 }
             }
+            if let Some(file) = report_file
+                && let Err(e) = record.write(&file)
+            {
+                eprintln!("Error: {}", e);
+                failed = true;
+            }
+            if let Some(file) = badge_json
+                && let Err(e) = Badge::for_report("rhodi", &record).write(&file)
+            {
+                eprintln!("Error: {}", e);
+                failed = true;
+            }
+            // An unreachable collector must not fail the verification
+            if let (Some(telemetry), Some(endpoint)) = (&telemetry, &otlp_endpoint)
+                && let Err(e) = telemetry.export(endpoint)
+            {
+                eprintln!("Warning: {}", e);
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Commands::Bundle {
+            paths,
+            workspace,
+            output,
+        } => {
+            if let Err(e) = crate::cli::commands::bundle::run(paths, workspace, output) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Stub { path, output } => {
+            if let Err(e) = crate::cli::commands::stub::run(path, output) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Attest {
+            path,
+            key,
+            ledger,
+            list,
+            report_file,
+        } => {
+            let result = if list {
+                crate::cli::commands::attest::list(path, ledger)
+            } else {
+                crate::cli::commands::attest::run(path, key, ledger, report_file)
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Review {
+            action,
+            path,
+            key,
+            ledger,
+        } => {
+            let result = match (action, path) {
+                (
+                    Some(ReviewAction::Add {
+                        path,
+                        comment,
+                        author,
+                        key,
+                        unsigned,
+                    }),
+                    _,
+                ) => crate::cli::commands::review::add(path, comment, author, key, unsigned),
+                (None, Some(path)) => crate::cli::commands::review::run(path, key, ledger),
+                (None, None) => Err(crate::error::RhodiError::Format(
+                    "No document to review".to_string(),
+                )),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Notarize {
+            path,
+            key,
+            name,
+            report_file,
+        } => {
+            if let Err(e) = crate::cli::commands::notarize::run(path, key, name, report_file) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Freeze {
+            dir,
+            key,
+            release,
+            output,
+            check,
+        } => {
+            let result = if check {
+                crate::cli::commands::freeze::check(dir, output)
+            } else {
+                crate::cli::commands::freeze::run(dir, key, release, output)
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Certify {
+            subject,
+            key,
+            name,
+            expires_in,
+            output,
+            attach,
+        } => {
+            if let Err(e) =
+                crate::cli::commands::certify::run(subject, key, name, expires_in, output, attach)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Hash {
+            path,
+            selector,
+            extractor,
+            transforms,
+            normalize,
+            compression,
+        } => {
+            if let Err(e) = crate::cli::commands::hash::run(
+                path,
+                selector,
+                extractor,
+                transforms,
+                normalize,
+                compression,
+            ) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Export {
+            path,
+            artifact,
+            render,
+            badges,
+            key,
+        } => {
+            if let Err(e) = crate::cli::commands::export::run(path, artifact, render, badges, key) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::VerifyArtifact { artifact, doc } => {
+            if let Err(e) = crate::cli::commands::verify_artifact::run(artifact, doc) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
         }
-        Commands::Update { path } => {
-            if let Err(e) = crate::cli::commands::update::run(path) {
+        Commands::Redact { path, output } => {
+            if let Err(e) = crate::cli::commands::redact::run(path, output) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
-            println!("Trace hashes updated successfully");
         }
-        Commands::Status { path } => {
-            if let Err(e) = crate::cli::commands::status::run(path) {
+        Commands::Serve { dir, addr, workers } => {
+            if let Err(e) = crate::cli::commands::serve::run(dir, addr, workers) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::SandboxExtract => {
+            if let Err(e) = crate::sandbox::serve(std::io::stdin().lock(), std::io::stdout().lock())
+            {
+                // Read by the verifier, which reports it as the trace's error
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Agent {
+            path,
+            endpoint,
+            model,
+            fill,
+        } => {
+            if let Err(e) = crate::cli::commands::agent::run(path, endpoint, model, fill) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Publish { path, registry } => {
+            if let Err(e) = crate::cli::commands::publish::run(path, registry) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Amend {
+            path,
+            title,
+            author,
+            set,
+            unset,
+            reason,
+            key,
+        } => {
+            let options = crate::cli::commands::amend::AmendOptions {
+                title,
+                author,
+                set,
+                unset,
+                reason,
+                key_name: key,
+            };
+            if let Err(e) = crate::cli::commands::amend::run(path, options) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::ResealAll {
+            compromised,
+            key,
+            reason,
+            dir,
+            dry_run,
+        } => {
+            if let Err(e) =
+                crate::cli::commands::reseal::run(dir, compromised, key, reason, dry_run)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Update { path, anchor, ids } => {
+            if let Err(e) = crate::cli::commands::update::run(path, anchor, ids) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::FixId { path, dry_run } => {
+            if let Err(e) = crate::cli::commands::fix_id::run(path, dry_run) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Supersede { old, new } => {
+            if let Err(e) = crate::cli::commands::supersede::run(old, new) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Migrate { path, to, dry_run } => {
+            if let Err(e) = crate::cli::commands::migrate::run(path, to, dry_run) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Status { path, deep } => {
+            if let Err(e) = crate::cli::commands::status::run(path, deep) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Search {
+            source,
+            author,
+            status,
+            text,
+            dir,
+            json,
+        } => {
+            let options = crate::cli::commands::search::SearchOptions {
+                source,
+                author,
+                status,
+                text,
+                json,
+            };
+            if let Err(e) = crate::cli::commands::search::run(dir, options) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Stats { dir, json } => {
+            if let Err(e) = crate::cli::commands::stats::run(dir, json) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Hooks { action } => {
+            let result = match action {
+                HooksAction::Install { force } => crate::cli::commands::hooks::install(force),
+                HooksAction::Uninstall => crate::cli::commands::hooks::uninstall(),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Keys { action } => {
+            let result = match action {
+                KeysAction::List => crate::cli::commands::keys::list(),
+                KeysAction::Fingerprint { key, expect } => {
+                    crate::cli::commands::keys::fingerprint(key, expect)
+                }
+                KeysAction::Backup {
+                    key,
+                    shares,
+                    threshold,
+                    out_dir,
+                } => crate::cli::commands::keys::backup(key, shares, threshold, out_dir),
+                KeysAction::Restore { shares, name } => {
+                    crate::cli::commands::keys::restore(shares, name)
+                }
+                KeysAction::Delete { name, yes, shred } => {
+                    crate::cli::commands::keys::delete(name, yes, shred)
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Author { action } => {
+            let result = match action {
+                AuthorAction::Add {
+                    profile,
+                    name,
+                    email,
+                    orcid,
+                    affiliation,
+                    key,
+                } => crate::cli::commands::author::add(AuthorProfile {
+                    profile,
+                    name,
+                    email,
+                    orcid,
+                    affiliation,
+                    key,
+                }),
+                AuthorAction::List => crate::cli::commands::author::list(),
+                AuthorAction::Remove { profile } => crate::cli::commands::author::remove(profile),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Config { action } => {
+            let result = match action {
+                ConfigAction::Get { key } => crate::cli::commands::config::get(key),
+                ConfigAction::Set { key, value } => crate::cli::commands::config::set(key, value),
+            };
+            if let Err(e) = result {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
@@ -129,3 +1259,43 @@ pub fn run() {
         }
     }
 }
+
+/// Print a verification report. Returns whether it has no errors.
+fn print_report(report: &CompilationReport, say: impl Fn(String)) -> bool {
+    if !report.warnings.is_empty() {
+        say("Warnings:".to_string());
+        for warning in &report.warnings {
+            say(format!("  - {}", warning));
+        }
+    }
+    if !report.unverified.is_empty() {
+        say("Unverified (offline):".to_string());
+        for check in &report.unverified {
+            say(format!("  - {}", check));
+        }
+    }
+    if !report.timed_out.is_empty() {
+        say("Timed out:".to_string());
+        for trace in &report.timed_out {
+            say(format!("  - {}", trace));
+        }
+    }
+    if !report.errors.is_empty() {
+        eprintln!("Errors found:");
+        for err in &report.errors {
+            eprintln!("  - {}", err);
+        }
+        return false;
+    }
+    if report.warnings.is_empty() {
+        if report.unverified.is_empty() {
+            say("✓ Document verified successfully".to_string());
+        } else {
+            say(format!(
+                "✓ Document verified, except {} check(s) that need the network",
+                report.unverified.len()
+            ));
+        }
+    }
+    true
+}
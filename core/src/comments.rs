@@ -0,0 +1,223 @@
+//! Reviewer comments: `review` blocks.
+//!
+//! A ```` ```review ```` block records a reviewer's finding where it
+//! belongs, in the body of the document:
+//!
+//! ````markdown
+//! ```review
+//! author: Grace Hopper
+//! timestamp: 2026-03-02T10:00:00Z
+//! comment: The Q3 revenue should cite the audited dataset.
+//! version_hash: 5be2…
+//! key: 9c1f…
+//! signature: 0a7d…
+//! ```
+//! ````
+//!
+//! From protocol 2.2, review blocks are kept in the body as written but
+//! never covered by the version hash, so reviewers can comment on a sealed
+//! document without breaking its seal. A signed comment names the
+//! reviewer's key and signs its author, time and text together with the
+//! document id and the version hash it was written against; `verify` warns
+//! about comments that do not parse, are unsigned, or are not signed by a
+//! listed contributor, and about signatures that do not verify.
+
+use crate::crypto::{KeyPair, parse_public_key, parse_signature};
+use crate::error::{Result, RhodiError};
+use crate::markdown::{FenceLine, Fences};
+use crate::models::TracedDocument;
+use crate::version::excludes_review_blocks;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+const REVIEW_FENCE: &str = "```review";
+
+/// Domain separator so review signatures can never be confused with seals,
+/// notarizations or attestations.
+const REVIEW_CONTEXT: &str = "rhodi-review-v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReviewComment {
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+    pub comment: String,
+    /// Hex version hash of the document the comment was written against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_hash: Option<String>,
+    /// Hex-encoded Ed25519 public key of the reviewer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Hex-encoded Ed25519 signature over the comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl ReviewComment {
+    /// An unsigned comment by `author`, timestamped now, on the version of
+    /// `doc` at hand.
+    pub fn new(doc: &TracedDocument, author: &str, comment: &str) -> Self {
+        Self {
+            author: author.to_string(),
+            timestamp: Utc::now(),
+            comment: comment.to_string(),
+            version_hash: doc.frontmatter.version_hash.map(hex::encode),
+            key: None,
+            signature: None,
+        }
+    }
+
+    /// Parse the YAML between the fences.
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let comment: Self = serde_norway::from_str(yaml)
+            .map_err(|e| RhodiError::Format(format!("Invalid review block: {}", e)))?;
+        if comment.key.is_some() != comment.signature.is_some() {
+            return Err(RhodiError::Format(
+                "Review block needs both 'key' and 'signature', or neither".to_string(),
+            ));
+        }
+        Ok(comment)
+    }
+
+    /// The exact bytes covered by the signature.
+    fn message(&self, doc: &TracedDocument) -> Vec<u8> {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            REVIEW_CONTEXT,
+            doc.frontmatter.id,
+            self.version_hash.as_deref().unwrap_or_default(),
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.key.as_deref().unwrap_or_default(),
+            self.author,
+            self.comment
+        )
+        .into_bytes()
+    }
+
+    /// Sign the comment with `keypair` as a comment on `doc`.
+    pub fn sign(mut self, doc: &TracedDocument, keypair: &KeyPair) -> Self {
+        self.key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let signature = keypair.sign(&self.message(doc));
+        self.signature = Some(hex::encode(signature.to_bytes()));
+        self
+    }
+
+    /// Check the signature of a signed comment on `doc`. Anyone can write
+    /// an unsigned comment, or sign one with a key of their own, so those
+    /// verify with a warning unless the key is a listed contributor's.
+    pub fn verify(&self, doc: &TracedDocument) -> Result<Option<String>> {
+        let (Some(key), Some(signature)) = (&self.key, &self.signature) else {
+            return Ok(Some(format!(
+                "Review comment by {} is unsigned",
+                self.author
+            )));
+        };
+        parse_public_key(key)?
+            .verify_strict(&self.message(doc), &parse_signature(signature)?)
+            .map_err(|_| {
+                RhodiError::Crypto(format!(
+                    "Review comment by {} has an invalid signature",
+                    self.author
+                ))
+            })?;
+        let listed = doc
+            .frontmatter
+            .contributors
+            .iter()
+            .flatten()
+            .filter_map(|c| c.key.as_deref())
+            .any(|k| k.eq_ignore_ascii_case(key));
+        Ok((!listed).then(|| {
+            format!(
+                "Review comment by {} is signed with a key no listed contributor has",
+                self.author
+            )
+        }))
+    }
+
+    /// The comment as a fenced block.
+    pub fn render(&self) -> Result<String> {
+        let yaml = serde_norway::to_string(self).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to render review block: {}", e))
+        })?;
+        Ok(format!("{}\n{}```\n", REVIEW_FENCE, yaml))
+    }
+}
+
+/// Every review block in `body`, in order, parsed.
+pub fn review_comments(body: &str) -> Vec<Result<ReviewComment>> {
+    let mut comments = Vec::new();
+    let mut fences = Fences::default();
+    let mut block: Option<String> = None;
+    for line in body.split_inclusive('\n') {
+        match (fences.line(line), &mut block) {
+            (FenceLine::Open("review"), None) => block = Some(String::new()),
+            (FenceLine::Close, Some(yaml)) => {
+                comments.push(ReviewComment::parse(yaml));
+                block = None;
+            }
+            (_, Some(yaml)) => yaml.push_str(line),
+            _ => {}
+        }
+    }
+    comments
+}
+
+/// `body` with `comment` appended as a review block, separated by a blank
+/// line.
+pub fn append_review(body: &str, comment: &ReviewComment) -> Result<String> {
+    let mut body = body.to_string();
+    if !body.is_empty() && !body.ends_with('\n') {
+        body.push('\n');
+    }
+    body.push('\n');
+    body.push_str(&comment.render()?);
+    Ok(body)
+}
+
+/// The body as the version hash of a `protocol_version` document covers
+/// it: from 2.2, without review blocks (see [`strip_reviews`]).
+pub(crate) fn hashed_body<'a>(body: &'a str, protocol_version: &str) -> Cow<'a, str> {
+    if excludes_review_blocks(protocol_version) {
+        strip_reviews(body)
+    } else {
+        Cow::Borrowed(body)
+    }
+}
+
+/// `body` with every review block removed, together with the blank line
+/// before it, so adding a comment with [`append_review`] leaves the hash as
+/// it was. A review fence that is never closed is not a block, and stays.
+pub(crate) fn strip_reviews(body: &str) -> Cow<'_, str> {
+    if !body.contains(REVIEW_FENCE) {
+        return Cow::Borrowed(body);
+    }
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    // The open review block, with the blank line before it
+    let mut review: Option<String> = None;
+    let mut last_line = 0;
+    for line in body.split_inclusive('\n') {
+        match (fences.line(line), &mut review) {
+            (FenceLine::Open("review"), None) => {
+                let mut block = String::new();
+                if last_line < out.len() && out[last_line..].trim().is_empty() {
+                    block = out.split_off(last_line);
+                }
+                block.push_str(line);
+                review = Some(block);
+            }
+            (FenceLine::Close, Some(_)) => review = None,
+            (_, Some(block)) => block.push_str(line),
+            (_, None) => {
+                last_line = out.len();
+                out.push_str(line);
+            }
+        }
+    }
+    if let Some(block) = review {
+        out.push_str(&block);
+    }
+    Cow::Owned(out)
+}
@@ -0,0 +1,261 @@
+use crate::error::{Result, RhodiError};
+use crate::models::Tolerance;
+
+/// Parse a human-formatted number such as `0.85`, `85%` or `-3.5e2`.
+/// Percentages are converted to fractions when `normalize_percent` is set.
+pub fn parse_number(value: &str, normalize_percent: bool) -> Option<f64> {
+    let value = value.trim();
+    let (digits, is_percent) = match value.strip_suffix('%') {
+        Some(stripped) => (stripped.trim_end(), true),
+        None => (value, false),
+    };
+
+    let number: f64 = digits.parse().ok()?;
+    if !number.is_finite() {
+        return None;
+    }
+
+    if is_percent && normalize_percent {
+        Some(number / 100.0)
+    } else {
+        Some(number)
+    }
+}
+
+/// Parse a number written in any common locale notation, such as
+/// `1,234.5`, `1.234,5`, `1 234,5` or `1'234.5`.
+///
+/// Spaces (including non-breaking ones), apostrophes and underscores group
+/// digits. When both `.` and `,` appear, the last one is the decimal
+/// separator; a separator repeated is a grouping one. A lone `.` is a
+/// decimal point, and so is a lone `,` unless exactly three digits follow
+/// it (`1,234` reads as 1234, `1,5` as 1.5).
+pub fn parse_locale_number(value: &str, normalize_percent: bool) -> Option<f64> {
+    let value: String = value
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '\'' | '\u{2019}' | '_'))
+        .collect();
+    let dots = value.matches('.').count();
+    let commas = value.matches(',').count();
+    let decimal = match (dots, commas) {
+        (0, 0) => None,
+        (1, 0) => Some('.'),
+        (0, 1) => {
+            let (whole, fraction) = value.split_once(',')?;
+            let fraction_digits = fraction.chars().take_while(char::is_ascii_digit).count();
+            let whole = whole.trim_start_matches(['-', '+']);
+            let grouping = fraction_digits == 3 && !whole.is_empty() && whole != "0";
+            (!grouping).then_some(',')
+        }
+        (_, 0) | (0, _) => None,
+        _ => {
+            let last = value.rfind(['.', ','])?;
+            let decimal = value[last..].chars().next()?;
+            if value.matches(decimal).count() > 1 {
+                return None;
+            }
+            Some(decimal)
+        }
+    };
+    let plain: String = value
+        .chars()
+        .filter_map(|c| match c {
+            '.' | ',' if Some(c) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    parse_number(&plain, normalize_percent)
+}
+
+/// Compare an extracted value against the expected one.
+///
+/// Without a tolerance this is the historical trimmed string comparison.
+/// With a tolerance both sides are parsed as numbers; values that are not
+/// numeric fall back to string comparison.
+pub fn values_match(expected: &str, actual: &str, tolerance: Option<&Tolerance>) -> bool {
+    values_match_with(expected, actual, tolerance, false)
+}
+
+/// Like [`values_match`], and with `numeric` (a trace's `numeric: true`)
+/// both sides are read with [`parse_locale_number`] and compared as
+/// numbers even without a tolerance.
+pub fn values_match_with(
+    expected: &str,
+    actual: &str,
+    tolerance: Option<&Tolerance>,
+    numeric: bool,
+) -> bool {
+    if expected.trim() == actual.trim() {
+        return true;
+    }
+
+    let exact = Tolerance::default();
+    let tolerance = match tolerance {
+        Some(tolerance) => tolerance,
+        None if numeric => &exact,
+        None => return false,
+    };
+
+    let parse = if numeric {
+        parse_locale_number
+    } else {
+        parse_number
+    };
+    let (Some(expected), Some(actual)) = (
+        parse(expected, tolerance.normalize_percent),
+        parse(actual, tolerance.normalize_percent),
+    ) else {
+        return false;
+    };
+
+    numbers_match(expected, actual, tolerance)
+}
+
+fn numbers_match(expected: f64, actual: f64, tolerance: &Tolerance) -> bool {
+    let diff = (expected - actual).abs();
+
+    if tolerance.absolute.is_none() && tolerance.relative.is_none() {
+        return diff <= f64::EPSILON * expected.abs().max(1.0);
+    }
+
+    let within_absolute = tolerance.absolute.is_some_and(|abs| diff <= abs);
+    let within_relative = tolerance
+        .relative
+        .is_some_and(|rel| diff <= rel * expected.abs());
+
+    within_absolute || within_relative
+}
+
+/// How a trace's `expected` value relates to the extracted one, named by
+/// its `compare` field. Without one a trace checks equality.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Comparison {
+    #[default]
+    Equals,
+    /// The extracted value contains `expected` as a substring
+    Contains,
+    /// `expected` is a regex the extracted value must match
+    Matches,
+    /// The extracted value is at least `expected`
+    Gte,
+    /// The extracted value is at most `expected`
+    Lte,
+    /// The extracted value lies within `expected`, written `[0.8, 0.9]`
+    Between,
+}
+
+impl Comparison {
+    /// Parse a trace's `compare` field.
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.trim().to_ascii_lowercase().as_str() {
+            "equals" | "eq" => Ok(Comparison::Equals),
+            "contains" => Ok(Comparison::Contains),
+            "matches" => Ok(Comparison::Matches),
+            "gte" | ">=" => Ok(Comparison::Gte),
+            "lte" | "<=" => Ok(Comparison::Lte),
+            "between" => Ok(Comparison::Between),
+            _ => Err(RhodiError::Format(format!(
+                "Unknown comparison: {} (expected equals, contains, matches, gte, lte or between)",
+                spec
+            ))),
+        }
+    }
+
+    /// Check `actual` against `expected`. Ordering comparisons read both
+    /// sides as numbers in any locale notation, and a tolerance widens
+    /// their bounds. An `expected` this comparison cannot read is an error.
+    pub fn check(
+        &self,
+        expected: &str,
+        actual: &str,
+        tolerance: Option<&Tolerance>,
+        numeric: bool,
+    ) -> Result<bool> {
+        match self {
+            Comparison::Equals => Ok(values_match_with(expected, actual, tolerance, numeric)),
+            Comparison::Contains => Ok(actual.contains(expected.trim())),
+            Comparison::Matches => {
+                let pattern = regex::Regex::new(expected).map_err(|e| {
+                    RhodiError::Format(format!("Invalid pattern in expected '{}': {}", expected, e))
+                })?;
+                Ok(pattern.is_match(actual.trim()))
+            }
+            Comparison::Gte | Comparison::Lte | Comparison::Between => {
+                let exact = Tolerance::default();
+                let tolerance = tolerance.unwrap_or(&exact);
+                let number = |value: &str| parse_locale_number(value, tolerance.normalize_percent);
+                let bound = |value: &str| {
+                    number(value).ok_or_else(|| {
+                        RhodiError::Format(format!(
+                            "Expected '{}' is not a number to compare against",
+                            value
+                        ))
+                    })
+                };
+                let (low, high) = match self {
+                    Comparison::Gte => (Some(bound(expected)?), None),
+                    Comparison::Lte => (None, Some(bound(expected)?)),
+                    _ => {
+                        let (low, high) = split_range(expected)?;
+                        let (low, high) = (bound(low)?, bound(high)?);
+                        if low > high {
+                            return Err(RhodiError::Format(format!(
+                                "Range '{}' starts above its end",
+                                expected
+                            )));
+                        }
+                        (Some(low), Some(high))
+                    }
+                };
+                let Some(actual) = number(actual) else {
+                    return Ok(false);
+                };
+                let above =
+                    low.is_none_or(|low| actual >= low || numbers_match(low, actual, tolerance));
+                let below = high
+                    .is_none_or(|high| actual <= high || numbers_match(high, actual, tolerance));
+                Ok(above && below)
+            }
+        }
+    }
+
+    /// The claim `expected` makes under this comparison, for messages:
+    /// `'0.85'`, `at least '80%'`, `a value within '[0.8, 0.9]'`.
+    pub fn describe(&self, expected: &str) -> String {
+        match self {
+            Comparison::Equals => format!("'{}'", expected),
+            Comparison::Contains => format!("a value containing '{}'", expected),
+            Comparison::Matches => format!("a value matching '{}'", expected),
+            Comparison::Gte => format!("at least '{}'", expected),
+            Comparison::Lte => format!("at most '{}'", expected),
+            Comparison::Between => format!("a value within '{}'", expected),
+        }
+    }
+}
+
+/// Split a range such as `[0.8, 0.9]`, `0.8..0.9` or `[0,8; 0,9]` (for
+/// decimal commas) into its bounds.
+fn split_range(range: &str) -> Result<(&str, &str)> {
+    let inner = range.trim();
+    let inner = inner
+        .strip_prefix('[')
+        .and_then(|r| r.strip_suffix(']'))
+        .unwrap_or(inner);
+    let bounds = if inner.contains(';') {
+        inner.split_once(';')
+    } else if inner.contains("..") {
+        inner.split_once("..")
+    } else if inner.matches(',').count() == 1 {
+        inner.split_once(',')
+    } else {
+        None
+    };
+    bounds.ok_or_else(|| {
+        RhodiError::Format(format!(
+            "Expected '{}' is not a range like [0.8, 0.9]",
+            range
+        ))
+    })
+}
@@ -1,36 +1,538 @@
-use crate::crypto::KeyPair;
+use crate::blocks::BlockHandler;
+use crate::certificate::{CertificateRef, KeyCertificate};
+use crate::comparison::Comparison;
+use crate::crypto::{KeyPair, hash_bytes, parse_public_key};
 use crate::error::{Result, RhodiError, SecurityError};
-use crate::markdown::{parse_tmd_sections, Section};
-use crate::models::{DocStatus, TraceBlock, TracedDocument};
-use crate::resolver::SourceResolver;
+use crate::extraction::ExecExtractor;
+use crate::index::DocumentLocator;
+use crate::fingerprint::KeyFingerprint;
+use crate::markdown::{
+    Section, parse_include_block, parse_tmd, parse_tmd_sections, parse_tmd_sections_with,
+    trace_anchors,
+};
+use crate::models::{
+    DocStatus, Environment, IncludeBlock, IncludeStub, QuoteBlock, TraceBlock, TraceMethod,
+    TracePreset, TraceResult, TracedDocument,
+};
+use crate::resolver::{ResolvedSource, SourceResolver, is_network_source};
+use crate::revocation::{RevocationCheck, own_revocation};
+use crate::telemetry::{Span, Telemetry};
+use crate::version::VersionRegistry;
 use ed25519_dalek::VerifyingKey;
-use serde::Deserialize;
-use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
 pub const MAX_INCLUDE_DEPTH: usize = 5;
+pub const MAX_INCLUDES: usize = 100;
+pub const MAX_TRACES: usize = 1000;
+pub const MAX_RESOLVED_BYTES: u64 = 256 * 1024 * 1024;
+pub const TRACE_TIMEOUT: Duration = Duration::from_secs(60);
+pub const DOCUMENT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A body section, its findings, and the time it took to check.
+type SectionReport = (Section, CompilationReport, Duration);
 
 pub struct Compiler<'a, R: SourceResolver> {
     resolver: &'a R,
+    handlers: Vec<Box<dyn BlockHandler + 'a>>,
+    passes: VerifyPasses,
+    exec: Option<ExecExtractor>,
+    trust_root: Option<VerifyingKey>,
+    offline: bool,
+    pending_reviews: bool,
+    locator: Option<DocumentLocator>,
+    revocation_checks: Vec<Box<dyn RevocationCheck + 'a>>,
+    clock_skew: chrono::Duration,
+    presets: BTreeMap<String, TracePreset>,
+    versions: VersionRegistry,
+    limits: CompilerLimits,
+    /// Includes entered and source bytes read by the current verification
+    includes_seen: Cell<usize>,
+    resolved_bytes: Cell<u64>,
+    /// When the current verification and trace run out of time
+    document_deadline: Cell<Option<Instant>>,
+    trace_deadline: Cell<Option<Instant>>,
+    cancellation: CancellationToken,
+    telemetry: Option<&'a Telemetry>,
+}
+
+/// Stops verifications in progress, e.g. from a signal handler or a UI.
+/// The compiler checks it between sources, mirrors and extraction steps,
+/// so a check already running finishes first.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Bounds on the work one verification may do, so a hostile or runaway
+/// document cannot exhaust the verifier. Each limit has its own
+/// [`SecurityError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompilerLimits {
+    /// Levels of nested includes
+    pub max_include_depth: usize,
+    /// Includes across the whole tree, counting repeats
+    pub max_includes: usize,
+    /// Trace blocks in any one document
+    pub max_traces: usize,
+    /// Bytes of trace sources and schemas read across the whole tree,
+    /// decompressed sources counted as decompressed
+    pub max_resolved_bytes: u64,
+    /// Bytes any one compressed source may decompress to
+    pub max_decompressed_bytes: u64,
+    /// Time one trace may take, mirrors included
+    pub trace_timeout: Duration,
+    /// Time one verification may take, includes included; traces left
+    /// when it is spent time out
+    pub document_timeout: Duration,
+}
+
+impl Default for CompilerLimits {
+    fn default() -> Self {
+        Self {
+            max_include_depth: MAX_INCLUDE_DEPTH,
+            max_includes: MAX_INCLUDES,
+            max_traces: MAX_TRACES,
+            max_resolved_bytes: MAX_RESOLVED_BYTES,
+            max_decompressed_bytes: crate::compression::MAX_DECOMPRESSED_BYTES,
+            trace_timeout: TRACE_TIMEOUT,
+            document_timeout: DOCUMENT_TIMEOUT,
+        }
+    }
+}
+
+/// The passes [`Compiler::verify`] runs. Integrity checks are cheap; traces
+/// read and hash every source, so CI may want to run them less often.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyPasses {
+    /// Version hash, signature and key URI of each sealed document, and the
+    /// plausibility of every document's timestamps
+    pub signature: bool,
+    /// Traces, quotes and custom blocks against their sources
+    pub traces: bool,
+    /// Descend into included documents
+    pub includes: bool,
+}
+
+impl VerifyPasses {
+    pub const ALL: Self = Self {
+        signature: true,
+        traces: true,
+        includes: true,
+    };
+
+    /// Integrity of the document and everything it includes, no sources read.
+    pub const SKIP_TRACES: Self = Self {
+        traces: false,
+        ..Self::ALL
+    };
+
+    /// Integrity of this document alone.
+    pub const SIGNATURE_ONLY: Self = Self {
+        signature: true,
+        traces: false,
+        includes: false,
+    };
+
+    /// Evidence of the document and its includes, without signatures.
+    pub const TRACES_ONLY: Self = Self {
+        signature: false,
+        ..Self::ALL
+    };
+}
+
+impl Default for VerifyPasses {
+    fn default() -> Self {
+        Self::ALL
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct CompilationReport {
     pub errors: Vec<RhodiError>,
     pub warnings: Vec<String>,
+    /// Checks skipped in offline mode because they need the network
+    pub unverified: Vec<String>,
+    /// Traces that ran out of time, also reported as errors or warnings
+    pub timed_out: Vec<String>,
+}
+
+impl CompilationReport {
+    /// Append everything `other` found.
+    pub fn merge(&mut self, other: CompilationReport) {
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+        self.unverified.extend(other.unverified);
+        self.timed_out.extend(other.timed_out);
+    }
+}
+
+/// Result of [`Compiler::verify_detailed`].
+#[derive(Debug)]
+pub struct VerifiedDocument {
+    /// Findings about the document as a whole: seal, signature,
+    /// notarizations, key certificate and environment
+    pub report: CompilationReport,
+    /// Every body section, in order, with its own findings
+    pub sections: Vec<VerifiedSection>,
+}
+
+impl VerifiedDocument {
+    /// All findings, as [`Compiler::verify`] reports them.
+    pub fn into_report(self) -> CompilationReport {
+        let mut report = self.report;
+        for section in self.sections {
+            report.merge(section.report);
+        }
+        report
+    }
+
+    /// How the document fared as a whole: the worst of its own outcome and
+    /// every section's.
+    pub fn outcome(&self) -> SectionOutcome {
+        self.sections
+            .iter()
+            .map(|section| section.outcome)
+            .fold(SectionOutcome::of(&self.report), SectionOutcome::worse)
+    }
+}
+
+#[derive(Debug)]
+pub struct VerifiedSection {
+    pub section: Section,
+    pub outcome: SectionOutcome,
+    pub report: CompilationReport,
+    /// Time spent checking the section, included documents and all
+    pub elapsed: Duration,
+}
+
+/// How one section fared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionOutcome {
+    /// Prose, or a block the enabled passes do not check
+    NotChecked,
+    Passed,
+    /// Passed, but with warnings (e.g. a failing trace in a draft)
+    Warning,
+    Failed,
+    /// Skipped offline because checking it needs the network
+    Unverified,
+    /// A trace ran out of time before it could be checked
+    TimedOut,
+}
+
+impl SectionOutcome {
+    /// The outcome of `report`, ranked as [`Self::worse`] ranks them. A
+    /// trace that ran out of time is also an error; only other errors make
+    /// the outcome `Failed`.
+    pub(crate) fn of(report: &CompilationReport) -> Self {
+        let timed_out = |e: &RhodiError| {
+            matches!(
+                e,
+                RhodiError::Security(
+                    SecurityError::TraceTimeout { .. } | SecurityError::DocumentTimeout { .. }
+                )
+            )
+        };
+        if report.errors.iter().any(|e| !timed_out(e)) {
+            Self::Failed
+        } else if !report.timed_out.is_empty() {
+            Self::TimedOut
+        } else if !report.errors.is_empty() {
+            Self::Failed
+        } else if !report.unverified.is_empty() {
+            Self::Unverified
+        } else if !report.warnings.is_empty() {
+            Self::Warning
+        } else {
+            Self::Passed
+        }
+    }
+
+    /// The outcome's name, as in JSON reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NotChecked => "not_checked",
+            Self::Passed => "passed",
+            Self::Warning => "warning",
+            Self::Failed => "failed",
+            Self::Unverified => "unverified",
+            Self::TimedOut => "timed_out",
+        }
+    }
+
+    /// The more severe of two outcomes, e.g. for a summary of several.
+    pub fn worse(self, other: Self) -> Self {
+        let severity = |outcome: Self| match outcome {
+            Self::NotChecked => 0,
+            Self::Passed => 1,
+            Self::Warning => 2,
+            Self::Unverified => 3,
+            Self::TimedOut => 4,
+            Self::Failed => 5,
+        };
+        if severity(other) > severity(self) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Lines of source shown on each side of a match site.
+const SNIPPET_CONTEXT: usize = 2;
+
+/// Why a trace failed, from [`Compiler::explain_trace`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceExplanation {
+    /// The trace's `id`, if it has one
+    pub id: Option<String>,
+    pub source: String,
+    pub error: String,
+    pub expected: String,
+    /// The value extracted now, when the selector still matches
+    pub actual: Option<String>,
+    /// Unified diff of `expected` against `actual`
+    pub diff: Option<String>,
+    pub snippet: Option<SourceSnippet>,
 }
 
-#[derive(Deserialize)]
-struct IncludeBlock {
-    path: String,
-    #[allow(dead_code)]
-    integrity: Option<String>,
+impl TraceExplanation {
+    fn error(trace: &TraceBlock, error: RhodiError) -> Self {
+        Self {
+            id: trace.id.clone(),
+            source: trace.source.clone(),
+            error: error.to_string(),
+            expected: trace.expected.clone(),
+            actual: None,
+            diff: None,
+            snippet: None,
+        }
+    }
+}
+
+impl std::fmt::Display for TraceExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.id {
+            Some(id) => writeln!(f, "Trace {} ({}): {}", id, self.source, self.error)?,
+            None => writeln!(f, "Trace {}: {}", self.source, self.error)?,
+        }
+        if let Some(ref diff) = self.diff {
+            for line in diff.lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        if let Some(ref snippet) = self.snippet {
+            writeln!(f, "  Source around the match:")?;
+            write!(f, "{}", snippet)?;
+        }
+        Ok(())
+    }
+}
+
+/// Numbered source lines around a match site.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceSnippet {
+    /// 1-based number of the first line shown
+    pub first_line: usize,
+    /// 1-based number of the line holding the match
+    pub match_line: usize,
+    pub lines: Vec<String>,
+}
+
+impl SourceSnippet {
+    /// The lines within `context` of the one containing byte `offset`.
+    pub fn around(text: &str, offset: usize, context: usize) -> Self {
+        let match_line = text[..offset.min(text.len())].matches('\n').count() + 1;
+        let first_line = match_line.saturating_sub(context).max(1);
+        let lines = text
+            .lines()
+            .skip(first_line - 1)
+            .take(match_line - first_line + context + 1)
+            .map(str::to_string)
+            .collect();
+        Self {
+            first_line,
+            match_line,
+            lines,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceSnippet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = (self.first_line + self.lines.len()).to_string().len();
+        for (i, line) in self.lines.iter().enumerate() {
+            let number = self.first_line + i;
+            let marker = if number == self.match_line { '>' } else { ' ' };
+            writeln!(
+                f,
+                "  {} {:>width$} | {}",
+                marker,
+                number,
+                line,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A refresh for a trace whose source content changed, from [`Compiler::propose_fix`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFix {
+    /// Resolved source location
+    pub source: String,
+    /// Frontmatter source whose declared hash should be refreshed instead of the trace's
+    pub shared_source: Option<String>,
+    pub old_hash: Option<String>,
+    pub new_hash: String,
+    pub old_expected: String,
+    pub new_expected: String,
 }
 
 impl<'a, R: SourceResolver> Compiler<'a, R> {
     pub fn new(resolver: &'a R) -> Self {
-        Self { resolver }
+        Self {
+            resolver,
+            handlers: Vec::new(),
+            passes: VerifyPasses::ALL,
+            exec: None,
+            trust_root: None,
+            offline: false,
+            pending_reviews: false,
+            locator: None,
+            revocation_checks: Vec::new(),
+            clock_skew: chrono::Duration::minutes(5),
+            presets: BTreeMap::new(),
+            versions: VersionRegistry::default(),
+            limits: CompilerLimits::default(),
+            includes_seen: Cell::new(0),
+            resolved_bytes: Cell::new(0),
+            document_deadline: Cell::new(None),
+            trace_deadline: Cell::new(None),
+            cancellation: CancellationToken::new(),
+            telemetry: None,
+        }
+    }
+
+    /// Bound include depth, include count, traces per document, bytes
+    /// read and time spent, e.g. from the workspace's `[limits]`.
+    pub fn with_limits(mut self, limits: CompilerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Stop verifications when `token` is cancelled, with
+    /// [`SecurityError::Cancelled`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Record spans and metrics of every verification in `telemetry`, for
+    /// export to an OpenTelemetry collector.
+    pub fn with_telemetry(mut self, telemetry: Option<&'a Telemetry>) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// Restrict which passes [`Self::verify`] runs.
+    pub fn with_passes(mut self, passes: VerifyPasses) -> Self {
+        self.passes = passes;
+        self
+    }
+
+    /// Run `extractor: exec` traces with `exec`. Without one (`None`, the
+    /// default) such traces fail, since they would run local commands.
+    pub fn with_exec_extractor(mut self, exec: Option<ExecExtractor>) -> Self {
+        self.exec = exec;
+        self
+    }
+
+    /// Expand `preset:` references of traces from `presets`, the workspace's
+    /// `[presets]`. A trace naming a preset not in it fails.
+    pub fn with_presets(mut self, presets: BTreeMap<String, TracePreset>) -> Self {
+        self.presets = presets;
+        self
+    }
+
+    /// Know the protocol versions of `versions`, the workspace's, rather
+    /// than the built-in ones only.
+    pub fn with_version_registry(mut self, versions: VersionRegistry) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Accept only sealed documents whose `key_certificate` chains to
+    /// `root`, including every included document.
+    pub fn with_trust_root(mut self, root: Option<VerifyingKey>) -> Self {
+        self.trust_root = root;
+        self
+    }
+
+    /// Skip everything that needs the network: remote trace sources, includes
+    /// and quotes, and `https://` key URIs are listed in
+    /// [`CompilationReport::unverified`] instead of being checked. Hashes and
+    /// signatures against embedded keys are still verified.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Report reviewer signatures that `policy.required_reviewers` still
+    /// lacks as warnings rather than errors, e.g. while a reviewer verifies
+    /// the document before notarizing it.
+    pub fn with_pending_reviews(mut self, pending: bool) -> Self {
+        self.pending_reviews = pending;
+        self
+    }
+
+    /// How far in the future a frontmatter or trace timestamp may lie before
+    /// it is reported, to allow for clocks that are slightly off. Default
+    /// five minutes.
+    pub fn with_clock_skew(mut self, skew: chrono::Duration) -> Self {
+        self.clock_skew = skew;
+        self
+    }
+
+    /// Resolve includes by `id` through `locator`. Without one, such
+    /// includes fail to resolve.
+    pub fn with_document_locator(mut self, locator: Option<DocumentLocator>) -> Self {
+        self.locator = locator;
+        self
+    }
+
+    /// Also ask `check` whether included and quoted versions are revoked,
+    /// besides their own status and the workspace of the document locator.
+    pub fn with_revocation_check(mut self, check: impl RevocationCheck + 'a) -> Self {
+        self.revocation_checks.push(Box::new(check));
+        self
+    }
+
+    /// Register a handler for a custom fenced block type.
+    pub fn with_handler(mut self, handler: impl BlockHandler + 'a) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
     }
 
     pub fn create(&self, title: &str, content: &str) -> TracedDocument {
@@ -53,7 +555,8 @@ impl<'a, R: SourceResolver> Compiler<'a, R> {
         Ok(doc)
     }
 
-    pub fn publish(&self, doc: TracedDocument, keypair: &KeyPair) -> Result<TracedDocument> {
+    pub fn publish(&self, mut doc: TracedDocument, keypair: &KeyPair) -> Result<TracedDocument> {
+        doc.expand_presets(&self.presets)?;
         let report = self.verify(&doc)?;
         if !report.errors.is_empty() {
             return Err(RhodiError::Verification(format!(
@@ -61,20 +564,111 @@ impl<'a, R: SourceResolver> Compiler<'a, R> {
                 report.errors
             )));
         }
-        Ok(doc.seal(keypair))
+        doc.seal(keypair)
     }
 
     pub fn revoke(&self, mut doc: TracedDocument, keypair: &KeyPair) -> Result<TracedDocument> {
         doc.frontmatter.doc_status = DocStatus::Revoked;
         doc = doc.update_modified_time();
-        Ok(doc.seal(keypair))
+        doc.seal(keypair)
     }
 
     pub fn verify(&self, doc: &TracedDocument) -> Result<CompilationReport> {
+        Ok(self.verify_detailed(doc)?.into_report())
+    }
+
+    /// Verify `doc` like [`Self::verify`], but keep each section's outcome
+    /// apart, so a renderer or editor can mark individual blocks. Failures
+    /// inside an included document are attributed to its include block.
+    pub fn verify_detailed(&self, doc: &TracedDocument) -> Result<VerifiedDocument> {
+        // Includes are tracked by resolved location and by document ID, so a
+        // module that includes its parent is caught however it is spelled.
         let mut seen = HashSet::new();
-        // We don't have a reliable unique ID for the initial doc if it's not saved,
-        // but recursion guard will handle it.
-        self.verify_recursive(doc, 0, &mut seen)
+        seen.insert(document_identity(doc));
+        let span = self.span("rhodi.verify");
+        span.set_attribute("rhodi.document.id", doc.frontmatter.id.to_string());
+        span.set_attribute("rhodi.document.title", doc.frontmatter.title.as_str());
+        let started = Instant::now();
+        self.includes_seen.set(0);
+        self.resolved_bytes.set(0);
+        self.document_deadline
+            .set(Some(Instant::now() + self.limits.document_timeout));
+        let verified = self.verify_sections(doc, 0, &mut seen);
+        self.document_deadline.set(None);
+        let (mut report, sections) = verified.inspect_err(|e| span.set_error(e))?;
+
+        if self.passes.signature {
+            match &self.locator {
+                Some(locator) => report.merge(crate::supersession::check_links(
+                    doc,
+                    locator,
+                    &self.versions,
+                )),
+                None if doc.frontmatter.supersedes.is_some()
+                    || doc.frontmatter.superseded_by.is_some() =>
+                {
+                    report.unverified.push(
+                        "Supersession links: unverified, no workspace to look versions up in"
+                            .to_string(),
+                    )
+                }
+                None => {}
+            }
+        }
+
+        // Environment differences matter only for what extractors return
+        if self.passes.traces
+            && let Some(ref sealed) = doc.frontmatter.environment
+        {
+            let current = Environment::current(&doc.frontmatter.protocol_version);
+            for diff in sealed.differences(&current) {
+                report
+                    .warnings
+                    .push(format!("Environment warning: verifying with {}", diff));
+            }
+        }
+        let sections = sections
+            .into_iter()
+            .map(|(section, report, elapsed)| VerifiedSection {
+                outcome: if self.checks(&section) {
+                    SectionOutcome::of(&report)
+                } else {
+                    SectionOutcome::NotChecked
+                },
+                section,
+                report,
+                elapsed,
+            })
+            .collect();
+        let verified = VerifiedDocument { report, sections };
+        let outcome = verified.outcome().name();
+        span.set_attribute("rhodi.outcome", outcome);
+        if outcome == SectionOutcome::Failed.name() {
+            span.set_error("verification failed");
+        }
+        if let Some(telemetry) = self.telemetry {
+            telemetry.add("rhodi.documents", 1, &[("rhodi.outcome", outcome)]);
+            telemetry.record_latency("rhodi.verify.duration", started.elapsed(), &[]);
+        }
+        Ok(verified)
+    }
+
+    /// A span named `name`, recording nothing without telemetry.
+    fn span(&self, name: &str) -> Span<'a> {
+        self.telemetry
+            .map_or_else(Span::disabled, |telemetry| telemetry.start_span(name))
+    }
+
+    /// Whether the enabled passes check `section` at all.
+    fn checks(&self, section: &Section) -> bool {
+        match section {
+            Section::Paragraph(_) => false,
+            Section::Trace(_) | Section::Quote(_) => self.passes.traces,
+            Section::Custom { kind, .. } => {
+                self.passes.traces && self.handlers.iter().any(|h| h.kind() == kind)
+            }
+            Section::Include(_) => self.passes.includes,
+        }
     }
 
     fn verify_recursive(
@@ -83,143 +677,1324 @@ impl<'a, R: SourceResolver> Compiler<'a, R> {
         depth: usize,
         seen: &mut HashSet<String>,
     ) -> Result<CompilationReport> {
+        let span = self.span("rhodi.include");
+        span.set_attribute("rhodi.document.id", doc.frontmatter.id.to_string());
+        span.set_attribute("rhodi.include.depth", depth);
+        let (mut report, sections) = self
+            .verify_sections(doc, depth, seen)
+            .inspect_err(|e| span.set_error(e))?;
+        for (_, section_report, _) in sections {
+            report.merge(section_report);
+        }
+        Ok(report)
+    }
+
+    /// The document-level report (seal, notarizations, certificate) and
+    /// one report per body section, in order, with the time it took.
+    fn verify_sections(
+        &self,
+        doc: &TracedDocument,
+        depth: usize,
+        seen: &mut HashSet<String>,
+    ) -> Result<(CompilationReport, Vec<SectionReport>)> {
         let mut report = CompilationReport::default();
 
-        if depth > MAX_INCLUDE_DEPTH {
+        if depth > self.limits.max_include_depth {
             return Err(RhodiError::Security(SecurityError::MaxRecursionDepth {
-                depth: MAX_INCLUDE_DEPTH,
+                depth: self.limits.max_include_depth,
             }));
         }
 
         // 1. Verify integrity/signature
-        if doc.frontmatter.doc_status == DocStatus::Published
-            || doc.frontmatter.doc_status == DocStatus::Revoked
+        if self.passes.signature
+            && (doc.frontmatter.doc_status == DocStatus::Published
+                || doc.frontmatter.doc_status == DocStatus::Revoked)
         {
-            if let Some(pk_hex) = &doc.frontmatter.public_key {
-                if let Ok(pk_bytes) = hex::decode(pk_hex) {
-                    if let Ok(pk) = VerifyingKey::from_bytes(
-                        &pk_bytes
-                            .try_into()
-                            .map_err(|_| RhodiError::Crypto("Invalid key len".to_string()))?,
-                    ) {
-                        if let Err(e) = doc.verify(&pk) {
-                            report.errors.push(e);
+            self.verify_signature(doc, &mut report);
+            if let Err(e) = crate::notary::verify_chain(doc) {
+                report.errors.push(e);
+            }
+            match crate::notary::check_reviews(doc) {
+                Err(e) if self.pending_reviews => report.warnings.push(e.to_string()),
+                Err(e) => report.errors.push(e),
+                Ok(()) => {}
+            }
+            if let Some(root) = &self.trust_root
+                && let Err(e) = self.check_certificate(doc, root)
+            {
+                report.errors.push(e);
+            }
+        }
+        let now = chrono::Utc::now();
+        if self.passes.signature {
+            for anomaly in doc.frontmatter.timestamp_anomalies(now, self.clock_skew) {
+                report
+                    .warnings
+                    .push(format!("Timestamp anomaly: {}", anomaly));
+            }
+            if let Some(notice) = self
+                .versions
+                .deprecation_notice(&doc.frontmatter.protocol_version)
+            {
+                report.warnings.push(notice);
+            }
+            if crate::version::excludes_review_blocks(&doc.frontmatter.protocol_version) {
+                for comment in crate::comments::review_comments(&doc.body) {
+                    match comment.and_then(|comment| comment.verify(doc)) {
+                        Ok(None) => {}
+                        Ok(Some(warning)) => {
+                            report.warnings.push(format!("Review warning: {}", warning))
                         }
-                    } else {
-                        report
-                            .errors
-                            .push(RhodiError::Crypto("Invalid public key format".to_string()));
+                        Err(e) => report.warnings.push(format!("Review warning: {}", e)),
                     }
-                } else {
-                    report.errors.push(RhodiError::Crypto(
-                        "Public key is not valid hex".to_string(),
-                    ));
                 }
-            } else {
-                report.warnings.push(
-                    "No public key found in metadata, skipping signature verification".to_string(),
-                );
             }
         }
 
         // 2. Recursive verification
-        let sections = parse_tmd_sections(&doc.body);
+        let kinds: Vec<&str> = self.handlers.iter().map(|h| h.kind()).collect();
+        let sections = parse_tmd_sections_with(&doc.body, &kinds);
+        let traces = sections
+            .iter()
+            .filter(|s| matches!(s, Section::Trace(_)))
+            .count();
+        if self.passes.traces && traces > self.limits.max_traces {
+            return Err(RhodiError::Security(SecurityError::TooManyTraces {
+                limit: self.limits.max_traces,
+            }));
+        }
+        let mut anchors = trace_anchors(&sections).into_iter();
+        let mut trace_ids = HashSet::new();
+        let mut results = Vec::with_capacity(sections.len());
         for section in sections {
-            match section {
-                Section::Trace(trace) => {
-                    if let Err(e) = self.verify_trace(&trace) {
-                        if doc.frontmatter.doc_status == DocStatus::Published {
-                            report.errors.push(e);
+            let mut report = CompilationReport::default();
+            let entry = section.clone();
+            let started = Instant::now();
+            'section: {
+                match section {
+                    Section::Trace(_) | Section::Quote(_) | Section::Custom { .. }
+                        if !self.passes.traces => {}
+                    Section::Include(_) if !self.passes.includes => {}
+                    Section::Trace(trace) => {
+                        let span = self.span("rhodi.trace");
+                        span.set_attribute("rhodi.trace.source", trace.source.as_str());
+                        if let Some(ref id) = trace.id {
+                            span.set_attribute("rhodi.trace.id", id.as_str());
+                        }
+                        let position = anchors.next().unwrap_or_default();
+                        let remote = self
+                            .resolve_trace(doc, &trace)
+                            .ok()
+                            .filter(|resolved| {
+                                self.offline
+                                    && is_network_source(&resolved.source)
+                                    && resolved.mirrors.iter().all(|m| is_network_source(m))
+                            })
+                            .map(|resolved| resolved.source);
+                        let result = if let Some(source) = remote {
+                            report.unverified.push(format!(
+                                "Trace {}: unverified (offline), {} needs the network",
+                                trace.label(),
+                                source
+                            ));
+                            Ok(())
                         } else {
-                            report.warnings.push(format!("Trace warning: {}", e));
+                            trace
+                                .anchor
+                                .as_deref()
+                                .map_or(Ok(()), |anchor| position.check(anchor))
+                                .and_then(|_| self.resolve_trace(doc, &trace))
+                                .and_then(|trace| {
+                                    let mirror =
+                                        self.within_trace_budget(|| self.verify_trace(&trace))?;
+                                    if let Some(mirror) = mirror {
+                                        report.warnings.push(format!(
+                                            "Trace {}: {}",
+                                            trace.label(),
+                                            mirror
+                                        ));
+                                    }
+                                    trace.check_freshness(&doc.frontmatter.policy, now)
+                                })
+                        };
+                        if let Err(
+                            e @ RhodiError::Security(
+                                SecurityError::ResolvedBytesExceeded { .. }
+                                | SecurityError::Cancelled,
+                            ),
+                        ) = result
+                        {
+                            return Err(e);
+                        }
+                        if let Err(
+                            ref e @ RhodiError::Security(
+                                SecurityError::TraceTimeout { .. }
+                                | SecurityError::DocumentTimeout { .. },
+                            ),
+                        ) = result
+                        {
+                            report
+                                .timed_out
+                                .push(format!("Trace {}: {}", trace.label(), e));
+                        }
+                        if let Err(e) = result {
+                            span.set_error(&e);
+                            let e = for_trace(e, &trace);
+                            if doc.frontmatter.doc_status == DocStatus::Published {
+                                report.errors.push(e);
+                            } else {
+                                report.warnings.push(format!("Trace warning: {}", e));
+                            }
+                        }
+                        if trace.timestamp_in_future(now, self.clock_skew) {
+                            report.warnings.push(format!(
+                                "Timestamp anomaly: trace {} has timestamp {} in the future",
+                                trace.label(),
+                                trace.timestamp.unwrap_or(now).to_rfc3339()
+                            ));
+                        }
+                        if let Some(id) = trace.id
+                            && !trace_ids.insert(id.clone())
+                        {
+                            report.errors.push(RhodiError::Format(format!(
+                                "Trace id '{}' is used more than once",
+                                id
+                            )));
+                        }
+                        let outcome = SectionOutcome::of(&report).name();
+                        span.set_attribute("rhodi.outcome", outcome);
+                        if let Some(telemetry) = self.telemetry {
+                            telemetry.add("rhodi.traces", 1, &[("rhodi.outcome", outcome)]);
+                            telemetry.record_latency(
+                                "rhodi.trace.duration",
+                                started.elapsed(),
+                                &[],
+                            );
                         }
                     }
-                }
-                Section::Include(content) => {
-                    let yaml_content = content
-                        .trim()
-                        .trim_start_matches("```include")
-                        .trim_end_matches("```")
-                        .trim();
-                    match serde_norway::from_str::<IncludeBlock>(yaml_content) {
-                        Ok(include) => {
-                            // Cycle detection
-                            if seen.contains(&include.path) {
-                                return Err(RhodiError::Security(SecurityError::CircularInclude {
-                                    path: PathBuf::from(&include.path),
-                                }));
+                    Section::Quote(quote)
+                        if self.offline && is_network_source(&quote.source_doc) =>
+                    {
+                        report.unverified.push(format!(
+                            "Quote of {}: unverified (offline)",
+                            quote.source_doc
+                        ));
+                    }
+                    Section::Quote(quote) => match self.verify_quote(&quote) {
+                        Ok(source) => self.check_revocation(
+                            doc,
+                            &source,
+                            &format!("quoted document {}", quote.source_doc),
+                            &mut report,
+                        ),
+                        Err(e) => {
+                            if doc.frontmatter.doc_status == DocStatus::Published {
+                                report.errors.push(e);
+                            } else {
+                                report.warnings.push(format!("Quote warning: {}", e));
                             }
-                            seen.insert(include.path.clone());
-
-                            match self.resolver.resolve_document(&include.path) {
-                                Ok(included_doc) => {
-                                    if !included_doc.frontmatter.policy.allow_include {
-                                        report.errors.push(RhodiError::Verification(format!(
-                                            "Document {} does not allow inclusion",
-                                            include.path
+                        }
+                    },
+                    Section::Include(content) => {
+                        match parse_include_block(&content) {
+                            Ok(include) if self.offline && is_network_source(&include.path) => {
+                                report.unverified.push(format!(
+                                    "Include {}: unverified (offline)",
+                                    include.path
+                                ));
+                            }
+                            Ok(include) => {
+                                let count = self.includes_seen.get() + 1;
+                                if count > self.limits.max_includes {
+                                    return Err(RhodiError::Security(
+                                        SecurityError::TooManyIncludes {
+                                            limit: self.limits.max_includes,
+                                        },
+                                    ));
+                                }
+                                self.includes_seen.set(count);
+                                let target = include.target();
+                                let circular = || {
+                                    RhodiError::Security(SecurityError::CircularInclude {
+                                        path: PathBuf::from(&target),
+                                    })
+                                };
+
+                                // Cycle detection
+                                let location = match include.id {
+                                    Some(_) => Ok(target.clone()),
+                                    None => self.resolver.identity(&include.path),
+                                };
+                                let location = match location {
+                                    Ok(location) => location,
+                                    Err(e) => {
+                                        report.errors.push(RhodiError::Resolution(format!(
+                                            "Failed to resolve include {}: {}",
+                                            target, e
                                         )));
+                                        break 'section;
                                     }
-
-                                    let sub_report =
-                                        self.verify_recursive(&included_doc, depth + 1, seen)?;
-                                    report.errors.extend(sub_report.errors);
-                                    report.warnings.extend(sub_report.warnings);
+                                };
+                                if !seen.insert(location.clone()) {
+                                    return Err(circular());
                                 }
-                                Err(e) => {
-                                    report.errors.push(RhodiError::Resolution(format!(
-                                        "Failed to resolve include {}: {}",
-                                        include.path, e
-                                    )));
+
+                                let resolved = match include.id {
+                                    Some(id) => self.resolve_by_id(id),
+                                    None => self.resolver.resolve_document(&include.path),
+                                };
+                                match resolved {
+                                    Ok(included_doc) => {
+                                        let doc_identity = document_identity(&included_doc);
+                                        if !seen.insert(doc_identity.clone()) {
+                                            return Err(circular());
+                                        }
+
+                                        if !included_doc.frontmatter.policy.allow_include {
+                                            report.errors.push(RhodiError::Verification(format!(
+                                                "Document {} does not allow inclusion",
+                                                target
+                                            )));
+                                        }
+
+                                        self.check_revocation(
+                                            doc,
+                                            &included_doc,
+                                            &format!("included document {}", target),
+                                            &mut report,
+                                        );
+
+                                        if let Err(e) = check_pinned(
+                                            doc,
+                                            &target,
+                                            included_doc.frontmatter.version_hash.map(hex::encode),
+                                        ) {
+                                            report.errors.push(e);
+                                        }
+
+                                        let sub_report =
+                                            self.verify_recursive(&included_doc, depth + 1, seen)?;
+                                        report.merge(sub_report);
+                                        seen.remove(&doc_identity);
+                                    }
+                                    Err(e) => match self.include_stub(&include) {
+                                        Some(Ok(stub)) => match check_pinned(
+                                            doc,
+                                            &target,
+                                            Some(stub.version_hash.clone()),
+                                        ) {
+                                            Ok(()) => report.warnings.push(format!(
+                                                "Include warning: {} could not be resolved ({}); accepted stub for {} at version {}, signed by {}",
+                                                target, e, stub.id, stub.version_hash, stub.public_key
+                                            )),
+                                            Err(e) => report.errors.push(e),
+                                        },
+                                        Some(Err(stub_error)) => report.errors.push(stub_error),
+                                        None => {
+                                            report.errors.push(RhodiError::Resolution(format!(
+                                                "Failed to resolve include {}: {}",
+                                                target, e
+                                            )));
+                                        }
+                                    },
                                 }
+                                seen.remove(&location);
+                            }
+                            Err(e) => {
+                                report.errors.push(e);
                             }
-                            seen.remove(&include.path);
                         }
-                        Err(e) => {
-                            report
-                                .errors
-                                .push(RhodiError::Format(format!("Invalid include block: {}", e)));
+                    }
+                    Section::Custom { kind, content } => {
+                        let handler = self.handlers.iter().find(|h| h.kind() == kind);
+                        if let Some(handler) = handler
+                            && let Err(e) = handler.verify(&content, doc, self.resolver)
+                        {
+                            if doc.frontmatter.doc_status == DocStatus::Published {
+                                report.errors.push(e);
+                            } else {
+                                report.warnings.push(format!("{} warning: {}", kind, e));
+                            }
                         }
                     }
+                    _ => {}
                 }
-                _ => {}
             }
+            results.push((entry, report, started.elapsed()));
         }
 
-        Ok(report)
+        Ok((report, results))
+    }
+
+    /// The version hash of every sealed document `doc` includes, keyed by
+    /// include target, for the `includes` manifest `seal` records. An
+    /// unresolvable include is pinned through its stub; unsealed includes
+    /// are left out.
+    pub fn include_manifest(&self, doc: &TracedDocument) -> Result<BTreeMap<String, String>> {
+        let mut manifest = BTreeMap::new();
+        for section in parse_tmd_sections(&doc.body) {
+            let Section::Include(content) = section else {
+                continue;
+            };
+            let include = parse_include_block(&content)?;
+            let resolved = match include.id {
+                Some(id) => self.resolve_by_id(id),
+                None => self.resolver.resolve_document(&include.path),
+            };
+            let hash = match resolved {
+                Ok(included) => included.frontmatter.version_hash.map(hex::encode),
+                Err(e) => match self.include_stub(&include) {
+                    Some(stub) => Some(stub?.version_hash),
+                    None => return Err(e),
+                },
+            };
+            if let Some(hash) = hash {
+                manifest.insert(include.target(), hash);
+            }
+        }
+        Ok(manifest)
     }
 
-    fn verify_trace(&self, trace: &TraceBlock) -> Result<()> {
-        let content = self.resolver.resolve_bytes(&trace.source)?;
+    /// Check a quote against the quoted document as the resolver serves
+    /// it: sealed, at the pinned version, and containing the excerpt.
+    pub fn check_quote(&self, quote: &QuoteBlock) -> Result<()> {
+        self.verify_quote(quote).map(|_| ())
+    }
 
-        // 1. Verify hash if present
-        if let Some(expected_hash) = &trace.hash {
-            let mut hasher = Sha256::new();
-            hasher.update(&content);
-            let result = hasher.finalize();
-            let computed_hash = format!("sha256:{}", hex::encode(result));
+    /// The stub standing in for an unresolvable include, if one exists. An
+    /// include by id looks for `<uuid>.stub.json`, and the stub must be for
+    /// the document with that id.
+    fn include_stub(&self, include: &IncludeBlock) -> Option<Result<IncludeStub>> {
+        let name = include
+            .id
+            .map_or_else(|| include.path.clone(), |id| id.to_string());
+        let bytes = self
+            .resolver
+            .resolve_bytes(&IncludeStub::path_for(&name))
+            .ok()?;
+        Some(
+            IncludeStub::from_json(&bytes).and_then(|stub| match include.id {
+                Some(id) if stub.id != id => Err(RhodiError::Verification(format!(
+                    "Include stub for id:{} is for a different document ({})",
+                    id, stub.id
+                ))),
+                _ => Ok(stub),
+            }),
+        )
+    }
 
-            if &computed_hash != expected_hash {
-                return Err(RhodiError::Verification(format!(
-                    "Hash mismatch for {}. Expected {}, got {}",
-                    trace.source, expected_hash, computed_hash
+    /// The workspace document with `id`, for includes by id.
+    fn resolve_by_id(&self, id: Uuid) -> Result<TracedDocument> {
+        self.locator
+            .as_ref()
+            .ok_or_else(|| {
+                RhodiError::Resolution(format!(
+                    "Include by id {} needs a workspace to look documents up in",
+                    id
+                ))
+            })?
+            .resolve_document(id)
+    }
+
+    /// Load `doc`'s key certificate and check it against the trust root.
+    fn check_certificate(&self, doc: &TracedDocument, root: &VerifyingKey) -> Result<()> {
+        let certificate = match &doc.frontmatter.key_certificate {
+            Some(CertificateRef::Inline(certificate)) => certificate.clone(),
+            Some(CertificateRef::Path(path)) => {
+                let bytes = self.resolver.resolve_bytes(path)?;
+                KeyCertificate::from_json(&String::from_utf8_lossy(&bytes))?
+            }
+            None => {
+                return Err(RhodiError::Verification(
+                    "Document has no key_certificate to check against the trust root".into(),
+                ));
+            }
+        };
+        crate::certificate::check_trust_root(doc, &certificate, root, &self.versions)
+    }
+
+    fn verify_signature(&self, doc: &TracedDocument, report: &mut CompilationReport) {
+        if let Err(e) = doc.signature_scheme().ensure_supported() {
+            report.errors.push(e);
+            return;
+        }
+        let fm = &doc.frontmatter;
+        match (&fm.public_key, &fm.key_uri) {
+            (Some(pk_hex), key_uri) => {
+                let pk = match parse_public_key(pk_hex) {
+                    Ok(pk) => pk,
+                    Err(e) => {
+                        report.errors.push(e);
+                        return;
+                    }
+                };
+                if let Err(e) = doc.verify_in(pk.as_bytes(), &self.versions) {
+                    report.errors.push(e);
+                }
+                match key_uri {
+                    Some(uri) if self.offline && !uri.starts_with("did:key:") => report
+                        .unverified
+                        .push(format!("Key URI {}: unverified (offline)", uri)),
+                    Some(uri) => {
+                        if let Err(e) = crate::discovery::check_key_uri(uri, &pk) {
+                            report.errors.push(e);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            (None, Some(uri)) if self.offline && !uri.starts_with("did:key:") => {
+                report.unverified.push(format!(
+                    "Signature: unverified (offline), the key must be fetched from {}",
+                    uri
+                ));
+            }
+            (None, Some(uri)) => match crate::discovery::resolve_key_uri(uri) {
+                Ok(keys) => {
+                    let mut last_error = None;
+                    for key in &keys {
+                        match doc.verify_in(key.as_bytes(), &self.versions) {
+                            Ok(()) => return,
+                            Err(e) => last_error = Some(e),
+                        }
+                    }
+                    report.errors.push(last_error.unwrap_or_else(|| {
+                        RhodiError::Crypto(format!("No keys published at {}", uri))
+                    }));
+                }
+                Err(e) => report.errors.push(e),
+            },
+            (None, None) => {
+                report.warnings.push(
+                    "No public key found in metadata, skipping signature verification".to_string(),
+                );
+            }
+        }
+    }
+
+    /// A trace of `doc` as it is checked: its preset expanded and a `$name`
+    /// source resolved against the document's declared sources.
+    fn resolve_trace(&self, doc: &TracedDocument, trace: &TraceBlock) -> Result<TraceBlock> {
+        doc.frontmatter
+            .resolve_trace(&trace.expand_preset(&self.presets)?)
+    }
+
+    /// Check a single trace and describe the outcome, for annotations.
+    pub fn check_trace(&self, trace: &TraceBlock) -> TraceResult {
+        match trace
+            .expand_preset(&self.presets)
+            .and_then(|trace| self.within_trace_budget(|| self.evaluate_trace(&trace)))
+        {
+            Ok(actual) => TraceResult {
+                passed: true,
+                actual,
+                error: None,
+            },
+            Err(e) => TraceResult {
+                passed: false,
+                actual: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// [`Self::check_trace`] for a trace of `doc`, resolving a `$name`
+    /// source against the document's declared sources first.
+    pub fn check_document_trace(&self, doc: &TracedDocument, trace: &TraceBlock) -> TraceResult {
+        match self.resolve_trace(doc, trace) {
+            Ok(trace) => self.check_trace(&trace),
+            Err(e) => TraceResult {
+                passed: false,
+                actual: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// Re-run every `method: automatic` trace of `doc` that has a selector and
+    /// return those that fail, with the reason. `seal` refuses to publish a
+    /// document whose `expected` values have gone stale.
+    pub fn failing_automatic_traces(&self, doc: &TracedDocument) -> Vec<(TraceBlock, String)> {
+        parse_tmd_sections(&doc.body)
+            .into_iter()
+            .filter_map(|section| match section {
+                Section::Trace(trace)
+                    if trace.method == TraceMethod::Automatic
+                        && trace
+                            .expand_preset(&self.presets)
+                            .map_or(true, |t| t.selector.is_some()) =>
+                {
+                    let result = self.check_document_trace(doc, &trace);
+                    (!result.passed).then(|| (trace, result.error.unwrap_or_default()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Verify a trace. When its source fails and one of its `mirrors`
+    /// passes, returns a note naming the mirror and why the source failed.
+    fn verify_trace(&self, trace: &TraceBlock) -> Result<Option<String>> {
+        self.evaluate_mirrored(trace).map(|(_, mirror)| mirror)
+    }
+
+    /// Run `check` with a fresh trace time budget, checked (cooperatively)
+    /// by [`Self::check_budget`].
+    fn within_trace_budget<T>(&self, check: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.trace_deadline
+            .set(Some(Instant::now() + self.limits.trace_timeout));
+        let result = check();
+        self.trace_deadline.set(None);
+        result
+    }
+
+    /// Fail when verification was cancelled or the trace or document ran
+    /// out of time. Called between the steps of checking a trace.
+    fn check_budget(&self) -> Result<()> {
+        if self.cancellation.is_cancelled() {
+            return Err(RhodiError::Security(SecurityError::Cancelled));
+        }
+        let now = Instant::now();
+        if self.document_deadline.get().is_some_and(|d| now > d) {
+            return Err(RhodiError::Security(SecurityError::DocumentTimeout {
+                limit: self.limits.document_timeout,
+            }));
+        }
+        if self.trace_deadline.get().is_some_and(|d| now > d) {
+            return Err(RhodiError::Security(SecurityError::TraceTimeout {
+                limit: self.limits.trace_timeout,
+            }));
+        }
+        Ok(())
+    }
+
+    /// When the current trace or document runs out of time, whichever
+    /// comes first.
+    fn deadline(&self) -> Option<Instant> {
+        [self.document_deadline.get(), self.trace_deadline.get()]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// `result`, unless it failed because the budget ran out while it was
+    /// computed: then the timeout, so it is reported as one.
+    fn within_budget<T>(&self, result: Result<T>) -> Result<T> {
+        if result.is_err() {
+            self.check_budget()?;
+        }
+        result
+    }
+
+    /// Resolve `uri`, counting its bytes against `max_resolved_bytes`.
+    fn resolve_counted(&self, uri: &str) -> Result<ResolvedSource> {
+        let span = self.span("rhodi.resolve");
+        span.set_attribute("rhodi.source", uri);
+        let started = Instant::now();
+        let resolved = self.resolver.resolve_until(uri, self.deadline());
+        if let Some(telemetry) = self.telemetry {
+            let scheme = uri.split_once("://").map_or("file", |(scheme, _)| scheme);
+            let result = if resolved.is_ok() { "ok" } else { "error" };
+            let series = [("rhodi.source.scheme", scheme), ("rhodi.result", result)];
+            telemetry.add("rhodi.resolver.calls", 1, &series);
+            telemetry.record_latency("rhodi.resolve.duration", started.elapsed(), &series);
+        }
+        let source = self.within_budget(resolved.inspect_err(|e| span.set_error(e)))?;
+        span.set_attribute("rhodi.source.bytes", source.bytes.len());
+        if let Some(telemetry) = self.telemetry {
+            telemetry.add("rhodi.resolver.bytes", source.bytes.len() as u64, &[]);
+            if source.metadata.from_cache {
+                telemetry.add("rhodi.cache.hits", 1, &[("rhodi.cache", "http")]);
+            }
+        }
+        let total = self.resolved_bytes.get() + source.bytes.len() as u64;
+        if total > self.limits.max_resolved_bytes {
+            return Err(RhodiError::Security(SecurityError::ResolvedBytesExceeded {
+                limit: self.limits.max_resolved_bytes,
+            }));
+        }
+        self.resolved_bytes.set(total);
+        Ok(source)
+    }
+
+    /// Check `trace` against `source` and, when `verify`, its resolved
+    /// `schema` (see [`check_resolved`]). Decompressed bytes count against
+    /// `max_resolved_bytes` like the bytes read.
+    fn check_counted(
+        &self,
+        trace: &TraceBlock,
+        source: &ResolvedSource,
+        schema: Option<&ResolvedSource>,
+        verify: bool,
+    ) -> Result<SourceFindings> {
+        let remaining = self
+            .limits
+            .max_resolved_bytes
+            .saturating_sub(self.resolved_bytes.get());
+        let limit = self.limits.max_decompressed_bytes.min(remaining);
+        let check = SourceCheck {
+            schema: schema.map(|schema| schema.bytes.clone()),
+            max_decompressed: limit,
+            verify,
+            ..SourceCheck::new(trace.clone(), source)
+        };
+        let findings = check_resolved(&check, source, self.exec.as_ref(), self.deadline(), &|| {
+            self.check_budget()
+        });
+        let findings = self.within_budget(findings).map_err(|e| match e {
+            RhodiError::Security(SecurityError::DecompressedSizeExceeded { .. })
+                if limit < self.limits.max_decompressed_bytes =>
+            {
+                RhodiError::Security(SecurityError::ResolvedBytesExceeded {
+                    limit: self.limits.max_resolved_bytes,
+                })
+            }
+            e => e,
+        })?;
+        self.resolved_bytes
+            .set(self.resolved_bytes.get() + findings.decompressed);
+        Ok(findings)
+    }
+
+    /// Verify a trace, returning the extracted value when it has a selector.
+    fn evaluate_trace(&self, trace: &TraceBlock) -> Result<Option<String>> {
+        self.evaluate_mirrored(trace).map(|(value, _)| value)
+    }
+
+    /// Verify a trace at its source, then at each of its mirrors in turn
+    /// until one passes. Returns the extracted value and, when a mirror
+    /// passed, a note naming it. Offline, network locations are skipped.
+    fn evaluate_mirrored(&self, trace: &TraceBlock) -> Result<(Option<String>, Option<String>)> {
+        let mut failures: Vec<(&str, RhodiError)> = Vec::new();
+        for location in std::iter::once(&trace.source).chain(&trace.mirrors) {
+            if self.offline && is_network_source(location) && !trace.mirrors.is_empty() {
+                continue;
+            }
+            self.check_budget()?;
+            let mut candidate = trace.clone();
+            candidate.source = location.clone();
+            match self.evaluate_location(&candidate) {
+                Ok(value) => {
+                    let note = failures.first().map(|(failed, e)| {
+                        format!("satisfied by mirror {} ({}: {})", location, failed, e)
+                    });
+                    return Ok((value, note));
+                }
+                Err(e @ RhodiError::Security(_)) => return Err(e),
+                Err(e) => failures.push((location, e)),
+            }
+        }
+        let mut failures = failures.into_iter();
+        let Some((_, first)) = failures.next() else {
+            return Err(RhodiError::Resolution(format!(
+                "No location of {} can be checked offline",
+                trace.source
+            )));
+        };
+        let mirrors: Vec<String> = failures
+            .map(|(location, e)| format!("{}: {}", location, e))
+            .collect();
+        if mirrors.is_empty() {
+            return Err(first);
+        }
+        Err(RhodiError::Verification(format!(
+            "{} (mirrors failed too: {})",
+            first,
+            mirrors.join("; ")
+        )))
+    }
+
+    /// Verify a trace at its `source` alone.
+    fn evaluate_location(&self, trace: &TraceBlock) -> Result<Option<String>> {
+        let source = self.resolve_counted(&trace.source)?;
+        self.check_budget()?;
+        let schema = trace
+            .schema
+            .as_deref()
+            .map(|schema| self.resolve_counted(schema))
+            .transpose()?;
+        let findings = self.check_counted(trace, &source, schema.as_ref(), true)?;
+        Ok(findings.value)
+    }
+
+    /// Propose a refresh for a failing trace of `doc` whose source changed:
+    /// the source's current hash and, for traces with a selector, the value
+    /// extracted now. Returns `None` when the trace passes. Unreadable
+    /// sources and selectors that no longer match are errors, since they
+    /// need a human to repair.
+    pub fn propose_fix(
+        &self,
+        doc: &TracedDocument,
+        trace: &TraceBlock,
+    ) -> Result<Option<TraceFix>> {
+        let resolved = self.resolve_trace(doc, trace)?;
+        if self.evaluate_trace(&resolved).is_ok() {
+            return Ok(None);
+        }
+
+        let source = self.resolver.resolve(&resolved.source)?;
+        let findings = self.check_counted(&resolved, &source, None, false)?;
+        // Only an equality claim is restated from the value found now; a
+        // bound or pattern is the author's to revise, so a value it rejects
+        // gets no fix
+        let new_expected = match findings.value {
+            Some(actual) => {
+                if resolved.comparison()? == Comparison::Equals {
+                    actual
+                } else if resolved.accepts(&actual)? {
+                    resolved.expected.clone()
+                } else {
+                    return Err(RhodiError::Verification(format!(
+                        "The value found now, '{}', fails the claim '{}'; revise it by hand",
+                        actual, resolved.expected
+                    )));
+                }
+            }
+            None => resolved.expected.clone(),
+        };
+        // A `$name` trace without its own hash uses the declared one, which
+        // covers the raw content; a normalizing or decompressing trace pins
+        // its own
+        let shared_source = trace
+            .source_ref()
+            .filter(|_| {
+                trace.hash.is_none()
+                    && resolved.normalize.is_none()
+                    && resolved.compression.is_none()
+            })
+            .map(str::to_string);
+
+        Ok(Some(TraceFix {
+            source: resolved.source,
+            shared_source,
+            old_hash: resolved.hash,
+            new_hash: findings.hash,
+            old_expected: resolved.expected,
+            new_expected,
+        }))
+    }
+
+    /// Explain why a trace of `doc` fails: the error, a unified diff of the
+    /// expected and extracted values, and the source lines around the match
+    /// site. Returns `None` when the trace passes.
+    pub fn explain_trace(
+        &self,
+        doc: &TracedDocument,
+        trace: &TraceBlock,
+    ) -> Option<TraceExplanation> {
+        let resolved = match self.resolve_trace(doc, trace) {
+            Ok(resolved) => resolved,
+            Err(e) => return Some(TraceExplanation::error(trace, e)),
+        };
+        let error = self.evaluate_trace(&resolved).err()?;
+        let mut explanation = TraceExplanation::error(&resolved, error);
+
+        let Ok(source) = self.resolver.resolve(&resolved.source) else {
+            return Some(explanation);
+        };
+        if let Ok(SourceFindings {
+            value: Some(actual),
+            ..
+        }) = self.check_counted(&resolved, &source, None, false)
+        {
+            explanation.diff = Some(
+                similar::TextDiff::from_lines(
+                    &format!("{}\n", resolved.expected),
+                    &format!("{}\n", actual),
+                )
+                .unified_diff()
+                .header("expected", &format!("actual ({})", resolved.source))
+                .to_string(),
+            );
+            explanation.actual = Some(actual);
+        }
+        explanation.snippet = match_site(
+            &resolved,
+            &source,
+            explanation.actual.as_deref(),
+            self.limits.max_decompressed_bytes,
+        );
+        Some(explanation)
+    }
+
+    /// The source lines around where a trace of `doc` matches, given the
+    /// value extracted for it (see [`match_site`]). `None` when the source
+    /// cannot be read or the match site cannot be found.
+    pub fn source_snippet(
+        &self,
+        doc: &TracedDocument,
+        trace: &TraceBlock,
+        actual: Option<&str>,
+    ) -> Option<SourceSnippet> {
+        let resolved = self.resolve_trace(doc, trace).ok()?;
+        let source = self.resolver.resolve(&resolved.source).ok()?;
+        match_site(
+            &resolved,
+            &source,
+            actual,
+            self.limits.max_decompressed_bytes,
+        )
+    }
+
+    /// Report that `doc` relies on revoked material when the version of
+    /// `source`, a document it includes or quotes, is revoked: an error in a
+    /// published document, a warning otherwise.
+    fn check_revocation(
+        &self,
+        doc: &TracedDocument,
+        source: &TracedDocument,
+        what: &str,
+        report: &mut CompilationReport,
+    ) {
+        match self.revocation(source) {
+            Ok(None) => {}
+            Ok(Some(reason)) if doc.frontmatter.doc_status == DocStatus::Published => {
+                report.errors.push(RhodiError::Verification(format!(
+                    "Relies on revoked material: {} {}",
+                    what, reason
                 )));
             }
+            Ok(Some(reason)) => report.warnings.push(format!(
+                "Revocation warning: relies on revoked material: {} {}",
+                what, reason
+            )),
+            Err(e) => report.errors.push(RhodiError::Verification(format!(
+                "Cannot check whether {} is revoked: {}",
+                what, e
+            ))),
         }
+    }
 
-        // 2. Truth extraction if selector is present
-        if let Some(selector) = &trace.selector {
-            let extractor_method = trace.extractor.as_deref().unwrap_or("regex");
-            let extractor = crate::extraction::get_extractor(extractor_method)?;
-            let extracted_value = extractor.extract(&content, selector)?;
+    /// Why the version of `doc` is revoked, if it is: by its own status,
+    /// the workspace, or one of the revocation checks.
+    fn revocation(&self, doc: &TracedDocument) -> Result<Option<String>> {
+        if let Some(reason) = own_revocation(doc) {
+            return Ok(Some(reason));
+        }
+        let checks = self
+            .locator
+            .iter()
+            .map(|locator| locator as &dyn RevocationCheck)
+            .chain(self.revocation_checks.iter().map(|check| check.as_ref()));
+        for check in checks {
+            if let Some(reason) = check.revocation(doc)? {
+                return Ok(Some(reason));
+            }
+        }
+        Ok(None)
+    }
 
-            if extracted_value.trim() != trace.expected.trim() {
+    /// Check a quote against the document it quotes, returning that
+    /// document.
+    fn verify_quote(&self, quote: &QuoteBlock) -> Result<TracedDocument> {
+        let source = self.resolver.resolve_document(&quote.source_doc)?;
+
+        if !source.frontmatter.policy.allow_quote {
+            return Err(RhodiError::Verification(format!(
+                "Document {} does not allow quotation",
+                quote.source_doc
+            )));
+        }
+
+        // 1. The quoted document must be the sealed version the quote pins
+        let stored_hash = source.frontmatter.version_hash.ok_or_else(|| {
+            RhodiError::Verification(format!(
+                "Quoted document {} is not sealed",
+                quote.source_doc
+            ))
+        })?;
+        let stored_hex = hex::encode(stored_hash);
+        if stored_hex != quote.version_hash.trim().to_lowercase() {
+            return Err(RhodiError::Verification(format!(
+                "Quote pins version {} of {}, but the resolved document is version {}",
+                quote.version_hash, quote.source_doc, stored_hex
+            )));
+        }
+
+        let pk_hex = source.frontmatter.public_key.as_deref().ok_or_else(|| {
+            RhodiError::Verification(format!(
+                "Quoted document {} has no public key",
+                quote.source_doc
+            ))
+        })?;
+        source.verify_in(parse_public_key(pk_hex)?.as_bytes(), &self.versions)?;
+
+        // 2. The excerpt must match its recorded hash
+        if let Some(expected_hash) = &quote.excerpt_hash {
+            let computed_hash = quote.compute_excerpt_hash();
+            if &computed_hash != expected_hash {
                 return Err(RhodiError::Verification(format!(
-                    "Truth verification failed for {}. Expected '{}', got '{}'",
-                    trace.source, trace.expected, extracted_value
+                    "Excerpt hash mismatch for quote of {}. Expected {}, got {}",
+                    quote.source_doc, expected_hash, computed_hash
                 )));
             }
         }
 
-        Ok(())
+        // 3. The excerpt must appear verbatim in the quoted body
+        let version = &source.frontmatter.protocol_version;
+        let body = crate::markdown::canonicalize_text_for(&source.body, version);
+        let excerpt = crate::markdown::canonicalize_text_for(quote.excerpt.trim(), version);
+        if !body.contains(excerpt.trim_end_matches('\n')) {
+            return Err(RhodiError::Verification(format!(
+                "Excerpt not found verbatim in {} (version {})",
+                quote.source_doc, stored_hex
+            )));
+        }
+
+        Ok(source)
     }
 }
+
+/// Parse and verify a `.tmd` document in one call, reading its sources
+/// through `resolver`: the entry point for embedders that need neither the
+/// CLI nor a workspace. With `trusted_keys`, the document must also be
+/// signed by one of them; an empty list accepts any signer.
+pub fn verify_document<R: SourceResolver>(
+    content: &str,
+    trusted_keys: &[VerifyingKey],
+    resolver: &R,
+) -> Result<CompilationReport> {
+    let doc = parse_tmd(content)?;
+    let mut report = Compiler::new(resolver).verify(&doc)?;
+    check_trusted_signer(&doc, trusted_keys, &mut report)?;
+    Ok(report)
+}
+
+/// Record an error in `report` unless `doc` is signed by one of
+/// `trusted_keys`. An empty list accepts any signer.
+pub fn check_trusted_signer(
+    doc: &TracedDocument,
+    trusted_keys: &[VerifyingKey],
+    report: &mut CompilationReport,
+) -> Result<()> {
+    if trusted_keys.is_empty() {
+        return Ok(());
+    }
+    let signer = doc
+        .frontmatter
+        .public_key
+        .as_deref()
+        .filter(|_| doc.frontmatter.signature.is_some())
+        .map(parse_public_key)
+        .transpose()?;
+    match signer {
+        Some(key) if trusted_keys.contains(&key) => {}
+        Some(key) => report.errors.push(RhodiError::Verification(format!(
+            "Document is signed by an untrusted key ({})",
+            KeyFingerprint::of(&key)
+        ))),
+        None => report.errors.push(RhodiError::Verification(
+            "Document is not signed, but trusted keys were given".into(),
+        )),
+    }
+    Ok(())
+}
+
+/// Identity of a document by its ID, independent of where it was loaded from.
+fn document_identity(doc: &TracedDocument) -> String {
+    format!("doc:{}", doc.frontmatter.id)
+}
+
+/// Name the trace in an error about it, when it has an `id`.
+fn for_trace(error: RhodiError, trace: &TraceBlock) -> RhodiError {
+    let Some(id) = &trace.id else {
+        return error;
+    };
+    let tag = |message: String| format!("trace {}: {}", id, message);
+    match error {
+        RhodiError::Verification(m) => RhodiError::Verification(tag(m)),
+        RhodiError::Extraction(m) => RhodiError::Extraction(tag(m)),
+        RhodiError::Resolution(m) => RhodiError::Resolution(tag(m)),
+        RhodiError::Format(m) => RhodiError::Format(tag(m)),
+        other => other,
+    }
+}
+
+/// Check an included document's version hash against the one `doc`'s
+/// `includes` manifest pinned at seal time, if any.
+fn check_pinned(doc: &TracedDocument, target: &str, version_hash: Option<String>) -> Result<()> {
+    let Some(pinned) = doc
+        .frontmatter
+        .includes
+        .as_ref()
+        .and_then(|includes| includes.get(target))
+    else {
+        return Ok(());
+    };
+    if version_hash.as_ref() == Some(pinned) {
+        return Ok(());
+    }
+    Err(RhodiError::Verification(format!(
+        "Included document {} is at version hash {}, but the seal pins {}",
+        target,
+        version_hash.as_deref().unwrap_or("(unsealed)"),
+        pinned
+    )))
+}
+
+/// The lines around a resolved trace's match site in `source`: where a
+/// regex selector matches, else where the extracted (or expected) value
+/// first appears. A compressed source is decompressed up to
+/// `max_decompressed` bytes, unless it is sandboxed: only the sandbox's
+/// helper decompresses those, so their raw bytes are searched.
+fn match_site(
+    trace: &TraceBlock,
+    source: &ResolvedSource,
+    actual: Option<&str>,
+    max_decompressed: u64,
+) -> Option<SourceSnippet> {
+    let content = match source.metadata.sandbox {
+        Some(_) => None,
+        None => trace
+            .normalized_source_within(&source.bytes, max_decompressed)
+            .ok(),
+    }
+    .map_or(
+        std::borrow::Cow::Borrowed(&source.bytes[..]),
+        |(content, _)| content,
+    );
+    let text = String::from_utf8_lossy(&content);
+    let regex_site = trace
+        .selector
+        .as_deref()
+        .filter(|selector| {
+            crate::extraction::select_extractor(
+                trace.extractor.as_deref(),
+                trace.source_content_type(source.metadata.content_type.as_deref()),
+                selector,
+            )
+            .eq_ignore_ascii_case("regex")
+        })
+        .and_then(|selector| regex::Regex::new(selector).ok())
+        .and_then(|re| re.find(&text).map(|m| m.start()));
+    let site = regex_site.or_else(|| {
+        [actual, Some(trace.expected.as_str())]
+            .into_iter()
+            .flatten()
+            .filter(|needle| !needle.trim().is_empty())
+            .find_map(|needle| text.find(needle.trim()))
+    });
+    site.map(|offset| SourceSnippet::around(&text, offset, SNIPPET_CONTEXT))
+}
+
+/// Run the trace's extractor and transforms over `content`, the source
+/// after the trace's `compression` and `normalize` steps. A trace that
+/// names no extractor gets one for `content_type` (see
+/// [`crate::extraction::select_extractor`]). `exec` serves
+/// `extractor: exec`, which is disabled without one. Commands are killed
+/// once `deadline` passes.
+pub(crate) fn extract_value(
+    trace: &TraceBlock,
+    selector: &str,
+    content: &[u8],
+    content_type: Option<&str>,
+    exec: Option<&ExecExtractor>,
+    deadline: Option<Instant>,
+) -> Result<String> {
+    let content_type = trace.source_content_type(content_type);
+    let method =
+        crate::extraction::select_extractor(trace.extractor.as_deref(), content_type, selector);
+    let selector = crate::selector::Selector::parse(method, selector)?;
+    let value = match exec {
+        Some(exec) if method.eq_ignore_ascii_case("exec") => {
+            exec.extract_until(content, &selector.to_string(), deadline)?
+        }
+        _ => crate::extraction::get_extractor(method)?.extract_selector(content, &selector)?,
+    };
+    crate::extraction::apply_transforms(&value, &trace.transforms)
+}
+
+/// A trace to check against the source it resolved to, with what the
+/// check needs besides the source; see [`check_source`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SourceCheck {
+    /// The trace, resolved
+    pub trace: TraceBlock,
+    /// Content type the resolver reported for the source
+    pub content_type: Option<String>,
+    /// The trace's resolved `schema`, checked when `verify`
+    pub schema: Option<Vec<u8>>,
+    /// Cap on the bytes decompressing the source may produce
+    pub max_decompressed: u64,
+    /// Check the hash, schema and claim, rather than only report the hash
+    /// and value found
+    pub verify: bool,
+}
+
+impl SourceCheck {
+    /// Report the hash and value `trace` finds in `source`, decompressing
+    /// up to [`crate::compression::MAX_DECOMPRESSED_BYTES`].
+    pub fn new(trace: TraceBlock, source: &ResolvedSource) -> Self {
+        Self {
+            trace,
+            content_type: source.metadata.content_type.clone(),
+            schema: None,
+            max_decompressed: crate::compression::MAX_DECOMPRESSED_BYTES,
+            verify: false,
+        }
+    }
+}
+
+/// What [`check_source`] found in a source.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SourceFindings {
+    /// Hash of the source after the trace's `compression` and `normalize`
+    pub hash: String,
+    /// Bytes decompressing the source produced
+    pub decompressed: u64,
+    /// The extracted value, when the trace has a selector
+    pub value: Option<String>,
+}
+
+/// [`check_source`] on a resolved `source`: in its sandbox's helper when
+/// its resolver does not trust it, so the verifier never decompresses,
+/// parses or extracts from it, else here. The helper's answer is not taken
+/// on trust: the source is hashed here, from the raw bytes or the
+/// normalized ones the helper sends back, and the hash and claim are
+/// checked here against what it found. `exec` serves `extractor: exec`
+/// traces; commands and the helper are killed once `deadline` passes.
+pub(crate) fn check_resolved(
+    check: &SourceCheck,
+    source: &ResolvedSource,
+    exec: Option<&ExecExtractor>,
+    deadline: Option<Instant>,
+    budget: &dyn Fn() -> Result<()>,
+) -> Result<SourceFindings> {
+    if let Some(sandbox) = &source.metadata.sandbox {
+        let extracted = sandbox.check(check, &source.bytes, exec, deadline)?;
+        let plain = check.trace.compression.is_none() && check.trace.normalize.is_none();
+        let content = match extracted.normalized.as_deref() {
+            Some(normalized) if !plain => normalized,
+            _ => &source.bytes,
+        };
+        let hash = hash_bytes(content);
+        check_hash(check, &hash)?;
+        let value = match (&check.trace.selector, extracted.value) {
+            (Some(_), Some(value)) => {
+                check_claim(check, &value)?;
+                Some(value)
+            }
+            (Some(_), None) => {
+                return Err(RhodiError::Extraction(
+                    "Sandboxed extraction failed: the helper extracted no value".into(),
+                ));
+            }
+            (None, _) => None,
+        };
+        return Ok(SourceFindings {
+            hash,
+            decompressed: extracted.decompressed,
+            value,
+        });
+    }
+    let extract = |selector: &str, content: &[u8]| {
+        extract_value(
+            &check.trace,
+            selector,
+            content,
+            check.content_type.as_deref(),
+            exec,
+            deadline,
+        )
+    };
+    check_source(check, &source.bytes, &extract, budget).map(|(findings, _)| findings)
+}
+
+/// Fail when `check.verify` and the trace pins a hash other than `hash`.
+fn check_hash(check: &SourceCheck, hash: &str) -> Result<()> {
+    let trace = &check.trace;
+    match &trace.hash {
+        Some(expected_hash) if check.verify && expected_hash != hash => {
+            Err(RhodiError::Verification(format!(
+                "Hash mismatch for {}. Expected {}, got {}",
+                trace.source, expected_hash, hash
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Fail when `check.verify` and `value` does not meet the trace's claim.
+fn check_claim(check: &SourceCheck, value: &str) -> Result<()> {
+    let trace = &check.trace;
+    if check.verify && !trace.accepts(value)? {
+        return Err(RhodiError::Verification(format!(
+            "Truth verification failed for {}. Expected {}, got '{}'",
+            trace.source,
+            trace.comparison()?.describe(&trace.expected),
+            value
+        )));
+    }
+    Ok(())
+}
+
+/// Check `check.trace` against `raw`, the bytes its source resolved to:
+/// decompress and normalize them, hash them and, when the trace has a
+/// selector, `extract` its value from the result. When `check.verify`, the
+/// hash, schema, claim and context must hold too, checked in that order.
+/// `budget` is called between steps and fails once time runs out. Also
+/// returns the bytes hashed.
+pub(crate) fn check_source<'a>(
+    check: &SourceCheck,
+    raw: &'a [u8],
+    extract: &dyn Fn(&str, &[u8]) -> Result<String>,
+    budget: &dyn Fn() -> Result<()>,
+) -> Result<(SourceFindings, std::borrow::Cow<'a, [u8]>)> {
+    let trace = &check.trace;
+    let (content, decompressed) = trace.normalized_source_within(raw, check.max_decompressed)?;
+    let hash = hash_bytes(&content);
+
+    // 1. Verify hash if present
+    check_hash(check, &hash)?;
+
+    // 2. Structure of the source, if it declares a schema
+    if check.verify
+        && let Some(schema) = &check.schema
+    {
+        crate::extraction::check_json_schema(&content, schema)?;
+        budget()?;
+    }
+
+    // 3. Truth extraction if selector is present
+    let Some(selector) = &trace.selector else {
+        let findings = SourceFindings {
+            hash,
+            decompressed,
+            value: None,
+        };
+        return Ok((findings, content));
+    };
+    let extracted_value = extract(selector, &content)?;
+    budget()?;
+
+    check_claim(check, &extracted_value)?;
+    if check.verify
+        && let Some(lines) = trace.context_lines
+    {
+        let extractor_method = crate::extraction::select_extractor(
+            trace.extractor.as_deref(),
+            trace.source_content_type(check.content_type.as_deref()),
+            selector,
+        );
+        if extractor_method.to_lowercase() != "regex" {
+            return Err(RhodiError::Verification(format!(
+                "context_lines only applies to regex traces, not '{}'",
+                extractor_method
+            )));
+        }
+        let context = trace.context.as_deref().ok_or_else(|| {
+            RhodiError::Verification(format!(
+                "Trace for {} sets context_lines but has no context",
+                trace.source
+            ))
+        })?;
+        crate::extraction::check_context(&content, selector, context, lines)?;
+    }
+
+    let findings = SourceFindings {
+        hash,
+        decompressed,
+        value: Some(extracted_value),
+    };
+    Ok((findings, content))
+}
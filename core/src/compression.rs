@@ -0,0 +1,151 @@
+//! Compressed trace sources. A trace with `compression: gzip`, `zstd` or
+//! `zip` is hashed and extracted from the decompressed content, so archived
+//! datasets can be cited without unpacking them next to the document. A
+//! zip source names the entry it cites after `!`
+//! (`data/runs.zip!2024/q3.csv`), or holds a single file.
+
+use crate::error::{Result, RhodiError, SecurityError};
+use std::io::{Cursor, Read};
+
+/// Decompressed sources larger than this are refused by default, so a
+/// small archive cannot expand into an unbounded amount of memory. The
+/// verifier's limit comes from `max_decompressed_bytes` in `[limits]`.
+pub const MAX_DECOMPRESSED_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// How a trace source is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    /// A zip archive; the entry comes from the source (`archive.zip!entry`)
+    Zip,
+}
+
+impl Compression {
+    /// Parse a trace's `compression` field.
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            "zip" => Ok(Compression::Zip),
+            _ => Err(RhodiError::Extraction(format!(
+                "Unknown compression: {} (expected gzip, zstd or zip)",
+                spec
+            ))),
+        }
+    }
+
+    /// The compression a source's extension suggests, for tools writing
+    /// new traces.
+    pub fn for_source(source: &str) -> Option<Self> {
+        let (archive, _) = split_archive_entry(source);
+        let extension = archive.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "gz" | "tgz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "zip" => Some(Compression::Zip),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Zip => "zip",
+        }
+    }
+
+    /// Decompress `content`, the source `source` resolved to, refusing to
+    /// produce more than `limit` bytes. A zip archive yields the entry
+    /// named after `!` in `source`, or its only file.
+    pub fn decompress(&self, content: &[u8], source: &str, limit: u64) -> Result<Vec<u8>> {
+        let (_, entry) = split_archive_entry(source);
+        if entry.is_some() && *self != Compression::Zip {
+            return Err(RhodiError::Extraction(format!(
+                "{} names an archive entry, but the trace's compression is {}",
+                source,
+                self.name()
+            )));
+        }
+        let invalid = |e: &dyn std::fmt::Display| {
+            RhodiError::Extraction(format!(
+                "Cannot decompress {} as {}: {}",
+                source,
+                self.name(),
+                e
+            ))
+        };
+        match self {
+            Compression::Gzip => {
+                read_capped(flate2::read::MultiGzDecoder::new(content), limit, invalid)
+            }
+            Compression::Zstd => {
+                let decoder =
+                    ruzstd::decoding::StreamingDecoder::new(content).map_err(|e| invalid(&e))?;
+                read_capped(decoder, limit, invalid)
+            }
+            Compression::Zip => {
+                let mut archive =
+                    zip::ZipArchive::new(Cursor::new(content)).map_err(|e| invalid(&e))?;
+                let name = match entry {
+                    Some(entry) => entry.to_string(),
+                    None => {
+                        let files: Vec<&str> =
+                            archive.file_names().filter(|n| !n.ends_with('/')).collect();
+                        match files.as_slice() {
+                            [only] => only.to_string(),
+                            _ => {
+                                return Err(RhodiError::Extraction(format!(
+                                    "{} holds {} files; name one as {}!<entry>",
+                                    source,
+                                    files.len(),
+                                    source
+                                )));
+                            }
+                        }
+                    }
+                };
+                let file = archive.by_name(&name).map_err(|e| {
+                    RhodiError::Extraction(format!("No entry {} in {}: {}", name, source, e))
+                })?;
+                if file.size() > limit {
+                    return Err(too_large(limit));
+                }
+                read_capped(file, limit, invalid)
+            }
+        }
+    }
+}
+
+/// Split `archive.zip!entry` into the archive and the entry. Other sources
+/// have no entry.
+pub fn split_archive_entry(source: &str) -> (&str, Option<&str>) {
+    let lower = source.to_ascii_lowercase();
+    match lower.find(".zip!") {
+        Some(at) if at + 5 < source.len() => (&source[..at + 4], Some(&source[at + 5..])),
+        _ => (source, None),
+    }
+}
+
+fn too_large(limit: u64) -> RhodiError {
+    RhodiError::Security(SecurityError::DecompressedSizeExceeded { limit })
+}
+
+/// Read `reader` to the end, failing past `limit` bytes. Read errors are
+/// corrupt data, reported through `invalid`.
+pub(crate) fn read_capped(
+    reader: impl Read,
+    limit: u64,
+    invalid: impl Fn(&dyn std::fmt::Display) -> RhodiError,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(|e| invalid(&e))?;
+    if bytes.len() as u64 > limit {
+        return Err(too_large(limit));
+    }
+    Ok(bytes)
+}
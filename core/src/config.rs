@@ -0,0 +1,494 @@
+//! Workspace configuration loaded from `rhodi.toml`.
+
+use crate::cache::{DEFAULT_CACHE_MAX_BYTES, HttpCache};
+use crate::compiler::CompilerLimits;
+use crate::error::{Result, RhodiError};
+use crate::extraction::ExecExtractor;
+use crate::index::DocumentLocator;
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, write_atomic};
+use crate::models::TracePreset;
+use crate::resolver::{FileResolver, HttpResolver, ResolverRegistry};
+use crate::sandbox::Sandbox;
+use crate::version::{VersionEntry, VersionRegistry};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// File name of the workspace configuration.
+pub const CONFIG_FILE_NAME: &str = "rhodi.toml";
+
+/// Directory under the workspace root holding rhodi's own state.
+pub const STATE_DIR_NAME: &str = ".rhodi";
+
+/// Typed contents of `rhodi.toml`. Every section is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    pub keys: KeysConfig,
+    pub resolver: ResolverConfig,
+    pub canonicalization: CanonicalizationConfig,
+    pub protocol: ProtocolConfig,
+    pub registry: RegistryConfig,
+    pub timestamp: TimestampConfig,
+    pub verify: VerifyConfig,
+    pub agent: AgentConfig,
+    pub extractors: ExtractorsConfig,
+    pub limits: LimitsConfig,
+    /// Named defaults for trace blocks, referenced with `preset:`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, TracePreset>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    /// Key name used by `seal` when `--key` is not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResolverConfig {
+    /// Workspace-relative directories trace sources may be read from (empty = anywhere in the workspace)
+    pub allow: Vec<String>,
+    /// Policy for `https://` sources
+    pub https: HttpsResolverConfig,
+    /// Extract values from workspace files in a sandbox
+    pub sandbox: SandboxConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpsResolverConfig {
+    /// Fetch `https://` sources at all (off by default)
+    pub enabled: bool,
+    /// Hosts sources may be fetched from (empty = any host)
+    pub allow_hosts: Vec<String>,
+    /// Largest source accepted, in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+    /// Keep fetched sources in `.rhodi/cache/http` and revalidate them with
+    /// ETag/Last-Modified instead of downloading them again (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<bool>,
+    /// Largest total size of the cache, in bytes (default: 1 GiB)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_max_bytes: Option<u64>,
+    /// Extract values from fetched sources in a sandbox
+    pub sandbox: SandboxConfig,
+}
+
+/// Confinement of extraction from one resolver's sources; see
+/// [`crate::sandbox`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Extract in a confined helper process (off by default)
+    pub enabled: bool,
+    /// Memory the helper may use, in MiB (default: 512)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// Seconds one extraction may take (default: 30)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Paths, absolute or workspace-relative, the helper and `exec`
+    /// commands may read besides the system directories
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow_read: Vec<String>,
+}
+
+impl SandboxConfig {
+    /// The sandbox for a workspace at `root`, if enabled.
+    pub fn sandbox(&self, root: &Path) -> Option<Sandbox> {
+        self.enabled.then(|| {
+            let mut sandbox = Sandbox::new(Sandbox::default_helper())
+                .with_allowed_reads(self.allow_read.iter().map(|path| root.join(path)));
+            if let Some(mb) = self.memory_mb {
+                sandbox = sandbox.with_memory_limit(mb * 1024 * 1024);
+            }
+            if let Some(secs) = self.timeout_secs {
+                sandbox = sandbox.with_timeout(Duration::from_secs(secs));
+            }
+            sandbox
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CanonicalizationConfig {
+    /// Protocol version (and thus canonicalization rules) for new documents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProtocolConfig {
+    /// Workspace-relative TOML file of `[[versions]]` entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Protocol versions to add, or built-in ones to deprecate
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<VersionEntry>,
+}
+
+/// Contents of a `[protocol] registry` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VersionRegistryFile {
+    #[serde(default)]
+    versions: Vec<VersionEntry>,
+}
+
+impl ProtocolConfig {
+    /// The entries of the registry file of a workspace at `root`, followed
+    /// by `versions`, which thus take precedence.
+    pub fn entries(&self, root: &Path) -> Result<Vec<VersionEntry>> {
+        let mut entries = match &self.registry {
+            Some(path) => {
+                let path = root.join(path);
+                let file: VersionRegistryFile = toml::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|e| {
+                        RhodiError::Format(format!("Invalid {}: {}", path.display(), e))
+                    })?;
+                file.versions
+            }
+            None => Vec::new(),
+        };
+        entries.extend(self.versions.iter().cloned());
+        Ok(entries)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Registry URL used by `publish` and `verify --check-registry`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimestampConfig {
+    /// OpenTimestamps calendars used by `seal --timestamp` (empty = the public calendars)
+    pub calendars: Vec<String>,
+    /// Esplora API used to look up Bitcoin blocks (default: blockstream.info)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerifyConfig {
+    /// Treat verification errors as fatal, like `verify --strict`
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Chat completions URL used by `rhodi agent`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Model name sent to the endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtractorsConfig {
+    /// Commands available to `extractor: exec` traces
+    pub exec: ExecExtractorConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecExtractorConfig {
+    /// Run `extractor: exec` traces at all (off by default)
+    pub enabled: bool,
+    /// Seconds a command may run before it is killed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Command name (the trace's selector) to program and arguments
+    pub commands: BTreeMap<String, Vec<String>>,
+    /// Commands that take further arguments from the trace's selector
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow_args: Vec<String>,
+}
+
+/// Overrides of the verifier's resource limits; unset limits keep the
+/// defaults of [`CompilerLimits`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Levels of nested includes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_include_depth: Option<usize>,
+    /// Includes in one verification, nested ones included
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_includes: Option<usize>,
+    /// Trace blocks in any one document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_traces: Option<usize>,
+    /// Bytes of sources read in one verification, decompressed sources
+    /// counted as decompressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_resolved_bytes: Option<u64>,
+    /// Bytes any one compressed source may decompress to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_decompressed_bytes: Option<u64>,
+    /// Seconds one trace may take
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_timeout_secs: Option<u64>,
+    /// Seconds one verification may take, includes included
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_timeout_secs: Option<u64>,
+}
+
+impl LimitsConfig {
+    pub fn compiler_limits(&self) -> CompilerLimits {
+        let defaults = CompilerLimits::default();
+        CompilerLimits {
+            max_include_depth: self.max_include_depth.unwrap_or(defaults.max_include_depth),
+            max_includes: self.max_includes.unwrap_or(defaults.max_includes),
+            max_traces: self.max_traces.unwrap_or(defaults.max_traces),
+            max_resolved_bytes: self
+                .max_resolved_bytes
+                .unwrap_or(defaults.max_resolved_bytes),
+            max_decompressed_bytes: self
+                .max_decompressed_bytes
+                .unwrap_or(defaults.max_decompressed_bytes),
+            trace_timeout: self
+                .trace_timeout_secs
+                .map_or(defaults.trace_timeout, Duration::from_secs),
+            document_timeout: self
+                .document_timeout_secs
+                .map_or(defaults.document_timeout, Duration::from_secs),
+        }
+    }
+}
+
+impl WorkspaceConfig {
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content)
+            .map_err(|e| RhodiError::Format(format!("Invalid {}: {}", CONFIG_FILE_NAME, e)))
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to serialize {}: {}", CONFIG_FILE_NAME, e))
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Read a value by dotted key, e.g. `keys.default`.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let table = toml::Table::try_from(self)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to read config: {}", e)))?;
+
+        let mut current = toml::Value::Table(table);
+        for part in key.split('.') {
+            match current {
+                toml::Value::Table(mut t) => match t.remove(part) {
+                    Some(v) => current = v,
+                    None => return Ok(None),
+                },
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(match current {
+            toml::Value::String(s) => s,
+            other => other.to_string(),
+        }))
+    }
+
+    /// Set a value by dotted key. The value is parsed as a TOML literal
+    /// (`true`, `["data"]`) when that fits the key's type, and is otherwise
+    /// taken as a string.
+    pub fn set(&self, key: &str, raw: &str) -> Result<Self> {
+        let literal = format!("v = {}", raw)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut t| t.remove("v"));
+
+        match literal {
+            Some(value) if !value.is_str() => self
+                .set_value(key, value)
+                .or_else(|_| self.set_value(key, toml::Value::String(raw.to_string()))),
+            _ => self.set_value(key, toml::Value::String(raw.to_string())),
+        }
+    }
+
+    fn set_value(&self, key: &str, value: toml::Value) -> Result<Self> {
+        let mut table = toml::Table::try_from(self)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to read config: {}", e)))?;
+
+        let parts: Vec<&str> = key.split('.').collect();
+        let (last, parents) = parts
+            .split_last()
+            .filter(|(last, _)| !last.is_empty())
+            .ok_or_else(|| RhodiError::Format("Empty config key".into()))?;
+
+        let mut current = &mut table;
+        for part in parents {
+            current = current
+                .entry(part.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| RhodiError::Format(format!("'{}' is not a section", part)))?;
+        }
+        current.insert(last.to_string(), value);
+
+        // Round-trip through the typed config to reject unknown keys and bad types
+        let content = toml::to_string(&table)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to write config: {}", e)))?;
+        Self::from_toml(&content)
+    }
+}
+
+/// A workspace: the directory holding `rhodi.toml` and its configuration.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub config: WorkspaceConfig,
+    /// Path of `rhodi.toml`, if one was found
+    pub config_path: Option<PathBuf>,
+}
+
+impl Workspace {
+    /// Find the nearest `rhodi.toml` in `start` or its ancestors.
+    /// Without one, `start` itself is the workspace root with default settings.
+    pub fn discover(start: &Path) -> Result<Self> {
+        let start = start.canonicalize()?;
+        for dir in start.ancestors() {
+            let candidate = dir.join(CONFIG_FILE_NAME);
+            if candidate.is_file() {
+                return Ok(Self {
+                    root: dir.to_path_buf(),
+                    config: WorkspaceConfig::load(&candidate)?,
+                    config_path: Some(candidate),
+                });
+            }
+        }
+
+        Ok(Self {
+            root: start,
+            config: WorkspaceConfig::default(),
+            config_path: None,
+        })
+    }
+
+    /// Discover the workspace containing a document.
+    pub fn for_document(path: &Path) -> Result<Self> {
+        match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            Some(dir) => Self::discover(dir),
+            None => Self::discover(&std::env::current_dir()?),
+        }
+    }
+
+    /// Path where `rhodi.toml` lives or would be created.
+    pub fn config_file(&self) -> PathBuf {
+        self.config_path
+            .clone()
+            .unwrap_or_else(|| self.root.join(CONFIG_FILE_NAME))
+    }
+
+    /// The workspace ledger, an append-only JSON-lines log of attestations.
+    pub fn ledger_path(&self) -> PathBuf {
+        self.root.join(STATE_DIR_NAME).join("ledger.jsonl")
+    }
+
+    /// The history store, keeping every sealed version of each document
+    /// for `verify --chain`.
+    pub fn history_dir(&self) -> PathBuf {
+        self.root.join(STATE_DIR_NAME).join("history")
+    }
+
+    /// The protocol versions the workspace knows: the built-in ones and
+    /// those of `[protocol]`.
+    pub fn version_registry(&self) -> Result<VersionRegistry> {
+        VersionRegistry::new(&self.config.protocol.entries(&self.root)?)
+    }
+
+    /// The `exec` extractor configured in `[extractors.exec]`, if enabled.
+    /// Commands run in the workspace root.
+    pub fn exec_extractor(&self) -> Option<ExecExtractor> {
+        let config = &self.config.extractors.exec;
+        config.enabled.then(|| {
+            let exec = ExecExtractor::new(config.commands.clone(), &self.root)
+                .with_args_allowed(config.allow_args.iter().cloned());
+            match config.timeout_secs {
+                Some(secs) => exec.with_timeout(Duration::from_secs(secs)),
+                None => exec,
+            }
+        })
+    }
+
+    /// The cache of sources fetched over `https://`.
+    pub fn http_cache_dir(&self) -> PathBuf {
+        self.root.join(STATE_DIR_NAME).join("cache").join("http")
+    }
+
+    /// Finds documents anywhere in the workspace by id, for includes by id.
+    pub fn document_locator(&self) -> DocumentLocator {
+        DocumentLocator::new(&self.root)
+    }
+
+    /// Resolvers for the workspace: files under `base`, restricted by
+    /// `resolver.allow`, plus any remote schemes enabled in `[resolver.*]`.
+    pub fn resolver(&self, base: &Path) -> Result<ResolverRegistry> {
+        let config = &self.config.resolver;
+        let files = FileResolver::new(base)?
+            .with_allowed_dirs(config.allow.iter().map(|dir| self.root.join(dir)))
+            .with_sandbox(config.sandbox.sandbox(&self.root));
+        let mut registry = ResolverRegistry::new(files);
+
+        if config.https.enabled {
+            let mut http = HttpResolver::new()
+                .with_allowed_hosts(config.https.allow_hosts.clone())
+                .with_sandbox(config.https.sandbox.sandbox(&self.root));
+            if let Some(max_bytes) = config.https.max_bytes {
+                http = http.with_max_bytes(max_bytes);
+            }
+            if config.https.cache.unwrap_or(true) {
+                let cache = HttpCache::new(self.http_cache_dir()).with_max_bytes(
+                    config
+                        .https
+                        .cache_max_bytes
+                        .unwrap_or(DEFAULT_CACHE_MAX_BYTES),
+                );
+                http = http.with_cache(Some(cache));
+            }
+            registry = registry.with_scheme("https", http);
+        }
+        Ok(registry)
+    }
+}
+
+/// Update `rhodi.toml` under an advisory lock, re-reading it inside the lock
+/// so concurrent writers never lose each other's changes.
+pub fn update_config<F>(path: &Path, update: F) -> Result<WorkspaceConfig>
+where
+    F: FnOnce(&WorkspaceConfig) -> Result<WorkspaceConfig>,
+{
+    let _lock = FileLock::acquire(path, DEFAULT_LOCK_TIMEOUT)?;
+
+    let current = if path.exists() {
+        WorkspaceConfig::load(path)?
+    } else {
+        WorkspaceConfig::default()
+    };
+    let updated = update(&current)?;
+    write_atomic(path, &updated.to_toml()?)?;
+    Ok(updated)
+}
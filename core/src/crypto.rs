@@ -1,5 +1,101 @@
+use crate::error::{Result, RhodiError};
 use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
 use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Signature algorithm a document is sealed with, recorded in its
+/// `signature_algorithm` frontmatter field. Documents without the field
+/// predate it and are Ed25519.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    #[default]
+    #[serde(rename = "ed25519")]
+    Ed25519,
+    /// A binary OpenPGP signature made with an Ed25519 key, e.g. by GnuPG;
+    /// see [`crate::openpgp`]
+    #[serde(rename = "openpgp-ed25519")]
+    OpenPgpEd25519,
+    /// Reserved; not implemented yet
+    #[serde(rename = "ed448")]
+    Ed448,
+    /// Reserved; not implemented yet
+    #[serde(rename = "ecdsa-p256")]
+    EcdsaP256,
+}
+
+impl SignatureScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::OpenPgpEd25519 => "openpgp-ed25519",
+            Self::Ed448 => "ed448",
+            Self::EcdsaP256 => "ecdsa-p256",
+        }
+    }
+
+    pub fn is_supported(self) -> bool {
+        matches!(self, Self::Ed25519 | Self::OpenPgpEd25519)
+    }
+
+    /// Fail with a clear error for algorithms this build cannot verify.
+    pub fn ensure_supported(self) -> Result<()> {
+        if self.is_supported() {
+            Ok(())
+        } else {
+            Err(RhodiError::Crypto(format!(
+                "Signature algorithm '{}' is not supported by this version of rhodi",
+                self
+            )))
+        }
+    }
+
+    /// Verify `signature` over `message` with a raw public key.
+    pub fn verify(self, public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+        match self {
+            Self::Ed25519 => {
+                let public_key: [u8; 32] = public_key.try_into().map_err(|_| {
+                    RhodiError::Crypto("Invalid public key length: expected 32 bytes".into())
+                })?;
+                let public_key = VerifyingKey::from_bytes(&public_key)
+                    .map_err(|e| RhodiError::Crypto(format!("Invalid public key format: {}", e)))?;
+                let signature = Signature::from_slice(signature).map_err(|_| {
+                    RhodiError::Crypto("Invalid signature length: expected 64 bytes".into())
+                })?;
+                public_key
+                    .verify_strict(message, &signature)
+                    .map_err(|e| RhodiError::Crypto(format!("Authenticity check failed: {}", e)))
+            }
+            Self::OpenPgpEd25519 => crate::openpgp::verify(public_key, message, signature),
+            Self::Ed448 | Self::EcdsaP256 => self.ensure_supported(),
+        }
+    }
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SignatureScheme {
+    type Err = RhodiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ed25519" => Ok(Self::Ed25519),
+            "openpgp-ed25519" | "openpgp" | "gpg" => Ok(Self::OpenPgpEd25519),
+            "ed448" => Ok(Self::Ed448),
+            "ecdsa-p256" | "p256" | "es256" => Ok(Self::EcdsaP256),
+            other => Err(RhodiError::Crypto(format!(
+                "Unknown signature algorithm '{}' (expected ed25519, openpgp-ed25519, ed448 or ecdsa-p256)",
+                other
+            ))),
+        }
+    }
+}
 
 pub struct KeyPair {
     pub signing_key: SigningKey,
@@ -7,6 +103,9 @@ pub struct KeyPair {
 }
 
 impl KeyPair {
+    /// Algorithm of the signatures this keypair produces
+    pub const SCHEME: SignatureScheme = SignatureScheme::Ed25519;
+
     /// Generate a new random Ed25519 keypair
     pub fn generate() -> Self {
         let mut csprng = OsRng;
@@ -23,3 +122,31 @@ impl KeyPair {
         self.signing_key.sign(message)
     }
 }
+
+/// Hash content into the `sha256:<hex>` form used by trace and quote blocks
+pub fn hash_bytes(content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("sha256:{}", hex::encode(hasher.finalize()))
+}
+
+/// Parse a hex-encoded Ed25519 public key
+pub fn parse_public_key(hex_str: &str) -> Result<VerifyingKey> {
+    let bytes = hex::decode(hex_str.trim())
+        .map_err(|e| RhodiError::Crypto(format!("Public key is not valid hex: {}", e)))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| RhodiError::Crypto("Invalid public key length: expected 32 bytes".into()))?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| RhodiError::Crypto(format!("Invalid public key format: {}", e)))
+}
+
+/// Parse a hex-encoded Ed25519 signature
+pub fn parse_signature(hex_str: &str) -> Result<Signature> {
+    let bytes = hex::decode(hex_str.trim())
+        .map_err(|e| RhodiError::Crypto(format!("Signature is not valid hex: {}", e)))?;
+    let bytes: [u8; 64] = bytes
+        .try_into()
+        .map_err(|_| RhodiError::Crypto("Invalid signature length: expected 64 bytes".into()))?;
+    Ok(Signature::from_bytes(&bytes))
+}
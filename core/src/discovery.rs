@@ -0,0 +1,158 @@
+//! Public key discovery from `did:key:` identifiers and well-known URLs.
+
+use crate::crypto::parse_public_key;
+use crate::error::{Result, RhodiError};
+use ed25519_dalek::VerifyingKey;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Multicodec prefix for an Ed25519 public key (varint 0xed).
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Document served at `https://<host>/.well-known/rhodi-keys.json`.
+#[derive(Debug, Deserialize)]
+pub struct WellKnownKeys {
+    pub keys: Vec<WellKnownKey>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WellKnownKey {
+    pub id: String,
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+}
+
+/// Encode an Ed25519 public key as a `did:key:` identifier.
+pub fn did_key_from_public_key(key: &VerifyingKey) -> String {
+    let mut bytes = ED25519_MULTICODEC.to_vec();
+    bytes.extend_from_slice(key.as_bytes());
+    format!("did:key:z{}", base58_encode(&bytes))
+}
+
+/// Decode the Ed25519 public key embedded in a `did:key:` identifier.
+pub fn public_key_from_did_key(did: &str) -> Result<VerifyingKey> {
+    let encoded = did.strip_prefix("did:key:z").ok_or_else(|| {
+        RhodiError::Crypto(format!("Unsupported DID (expected did:key:z…): {}", did))
+    })?;
+    let bytes = base58_decode(encoded)?;
+    let key = bytes
+        .strip_prefix(&ED25519_MULTICODEC)
+        .ok_or_else(|| RhodiError::Crypto("did:key is not an Ed25519 key".into()))?;
+    parse_public_key(&hex::encode(key))
+}
+
+/// Parse a public key given either as hex or as a `did:key:` identifier.
+pub fn parse_key(key: &str) -> Result<VerifyingKey> {
+    if key.starts_with("did:key:") {
+        public_key_from_did_key(key)
+    } else {
+        parse_public_key(key)
+    }
+}
+
+/// Resolve every public key a key URI vouches for.
+///
+/// `did:key:` URIs are decoded locally. `https://` URIs are fetched and must
+/// serve a [`WellKnownKeys`] document; a `#fragment` selects a single key id.
+pub fn resolve_key_uri(uri: &str) -> Result<Vec<VerifyingKey>> {
+    if uri.starts_with("did:key:") {
+        return Ok(vec![public_key_from_did_key(uri)?]);
+    }
+    if !uri.starts_with("https://") {
+        return Err(RhodiError::Resolution(format!(
+            "Unsupported key URI (expected did:key: or https://): {}",
+            uri
+        )));
+    }
+
+    let (url, fragment) = match uri.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (uri, None),
+    };
+
+    let body = ureq::AgentBuilder::new()
+        .timeout(DISCOVERY_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| RhodiError::Resolution(format!("Failed to fetch {}: {}", url, e)))?
+        .into_string()
+        .map_err(|e| RhodiError::Resolution(format!("Failed to read {}: {}", url, e)))?;
+    let published: WellKnownKeys = serde_json::from_str(&body)
+        .map_err(|e| RhodiError::Format(format!("Invalid key document at {}: {}", url, e)))?;
+
+    published
+        .keys
+        .iter()
+        .filter(|k| fragment.is_none_or(|f| k.id == f))
+        .map(|k| parse_public_key(&k.public_key))
+        .collect()
+}
+
+/// Check that the key URI serves the given public key.
+pub fn check_key_uri(uri: &str, public_key: &VerifyingKey) -> Result<()> {
+    let keys = resolve_key_uri(uri)?;
+    if keys.iter().any(|k| k == public_key) {
+        Ok(())
+    } else {
+        Err(RhodiError::Verification(format!(
+            "Embedded public key does not match the key published at {}",
+            uri
+        )))
+    }
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &bytes[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    std::iter::repeat_n('1', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|d| BASE58_ALPHABET[*d as usize] as char),
+        )
+        .collect()
+}
+
+fn base58_decode(encoded: &str) -> Result<Vec<u8>> {
+    let zeros = encoded.chars().take_while(|c| *c == '1').count();
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in encoded.chars().skip(zeros) {
+        let value = BASE58_ALPHABET
+            .iter()
+            .position(|a| *a as char == c)
+            .ok_or_else(|| RhodiError::Crypto(format!("Invalid base58 character '{}'", c)))?;
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut result = vec![0u8; zeros];
+    result.extend(bytes.iter().rev());
+    Ok(result)
+}
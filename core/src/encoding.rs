@@ -0,0 +1,96 @@
+//! Binary encodings of sealed documents.
+//!
+//! [`to_cose`] wraps a sealed `.tmd` in a COSE_Sign1 structure (RFC 9052)
+//! signed with EdDSA by the document's own key, so CBOR/COSE stacks can
+//! verify it without knowing the trace protocol. The payload is the rendered
+//! document, which still carries its rhodi `version_hash` and signature.
+
+use crate::crypto::{KeyPair, parse_public_key};
+use crate::error::{Result, RhodiError};
+use crate::markdown::{parse_tmd, render_tmd};
+use crate::models::TracedDocument;
+use coset::{
+    CborSerializable, CoseSign1, CoseSign1Builder, HeaderBuilder, TaggedCborSerializable, iana,
+};
+use ed25519_dalek::{Signature, Verifier};
+
+/// Content type of the COSE payload.
+pub const TMD_CONTENT_TYPE: &str = "text/markdown; variant=tmd";
+
+/// Encode a sealed document as a tagged COSE_Sign1 message. `keypair` must
+/// be the key the document was sealed with; its public key is the `kid`.
+pub fn to_cose(doc: &TracedDocument, keypair: &KeyPair) -> Result<Vec<u8>> {
+    if doc.frontmatter.signature.is_none() {
+        return Err(RhodiError::Crypto(
+            "Only sealed documents can be encoded as COSE".into(),
+        ));
+    }
+    let public_key = keypair.verifying_key.as_bytes();
+    if doc.frontmatter.public_key.as_deref() != Some(hex::encode(public_key).as_str()) {
+        return Err(RhodiError::Crypto(
+            "COSE signing key does not match the document's public_key".into(),
+        ));
+    }
+
+    let protected = HeaderBuilder::new()
+        .algorithm(iana::Algorithm::EdDSA)
+        .key_id(public_key.to_vec())
+        .content_type(TMD_CONTENT_TYPE.to_string())
+        .build();
+    let message = CoseSign1Builder::new()
+        .protected(protected)
+        .payload(render_tmd(doc)?.into_bytes())
+        .create_signature(&[], |data| keypair.sign(data).to_bytes().to_vec())
+        .build();
+
+    message
+        .to_tagged_vec()
+        .map_err(|e| RhodiError::Serialization(format!("Failed to encode COSE: {}", e)))
+}
+
+/// Decode a COSE_Sign1 message produced by [`to_cose`]. The COSE signature
+/// is checked against the document's embedded `public_key`, and the
+/// document's own seal is verified as well.
+pub fn from_cose(bytes: &[u8]) -> Result<TracedDocument> {
+    let message = CoseSign1::from_tagged_slice(bytes)
+        .or_else(|_| CoseSign1::from_slice(bytes))
+        .map_err(|e| RhodiError::Format(format!("Invalid COSE_Sign1: {}", e)))?;
+
+    let alg = message.protected.header.alg.as_ref();
+    if alg != Some(&coset::Algorithm::Assigned(iana::Algorithm::EdDSA)) {
+        return Err(RhodiError::Crypto(format!(
+            "Unsupported COSE algorithm {:?} (expected EdDSA)",
+            alg
+        )));
+    }
+
+    let payload = message
+        .payload
+        .as_deref()
+        .ok_or_else(|| RhodiError::Format("COSE message has no payload".into()))?;
+    let content = std::str::from_utf8(payload)
+        .map_err(|e| RhodiError::Format(format!("Invalid UTF-8 in COSE payload: {}", e)))?;
+    let doc = parse_tmd(content)?;
+
+    let pk_hex = doc.frontmatter.public_key.as_deref().ok_or_else(|| {
+        RhodiError::Crypto("COSE payload has no public_key to verify against".into())
+    })?;
+    let public_key = parse_public_key(pk_hex)?;
+    let kid = &message.protected.header.key_id;
+    if !kid.is_empty() && kid.as_slice() != public_key.as_bytes() {
+        return Err(RhodiError::Crypto(
+            "COSE kid does not match the document's public_key".into(),
+        ));
+    }
+
+    message.verify_signature(&[], |signature, data| {
+        let signature = Signature::from_slice(signature)
+            .map_err(|e| RhodiError::Crypto(format!("Invalid COSE signature: {}", e)))?;
+        public_key
+            .verify(data, &signature)
+            .map_err(|_| RhodiError::Crypto("COSE signature verification failed".into()))
+    })?;
+    doc.verify(&public_key)?;
+
+    Ok(doc)
+}
@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -26,6 +27,12 @@ pub enum RhodiError {
 
     #[error("Resolution error: {0}")]
     Resolution(String),
+
+    #[error("Registry error: {0}")]
+    Registry(String),
+
+    #[error("Agent error: {0}")]
+    Agent(String),
 }
 
 #[derive(Error, Debug)]
@@ -38,6 +45,36 @@ pub enum SecurityError {
 
     #[error("Circular include detected: {path}")]
     CircularInclude { path: PathBuf },
+
+    #[error("Source {path} is not in an allowed directory")]
+    SourceNotAllowed { path: PathBuf },
+
+    #[error("Too many includes: verification stops after {limit}")]
+    TooManyIncludes { limit: usize },
+
+    #[error("Too many trace blocks: a document may have at most {limit}")]
+    TooManyTraces { limit: usize },
+
+    #[error("Resolved sources exceed {limit} bytes")]
+    ResolvedBytesExceeded { limit: u64 },
+
+    #[error("Unpacked bundle exceeds {limit} bytes")]
+    BundleSizeExceeded { limit: u64 },
+
+    #[error("Decompressed source exceeds {limit} bytes")]
+    DecompressedSizeExceeded { limit: u64 },
+
+    #[error("Timed out: the trace took longer than {}s", limit.as_secs_f64())]
+    TraceTimeout { limit: Duration },
+
+    #[error("Timed out: the document's time budget of {}s is spent", limit.as_secs_f64())]
+    DocumentTimeout { limit: Duration },
+
+    #[error("Cannot sandbox extraction: {reason}")]
+    SandboxUnavailable { reason: String },
+
+    #[error("Verification cancelled")]
+    Cancelled,
 }
 
 pub type Result<T> = std::result::Result<T, RhodiError>;
@@ -1,10 +1,34 @@
 use crate::error::{Result, RhodiError};
+use crate::selector::{ByteEncoding, Selector};
 use jsonpath_rust::JsonPathFinder;
 use regex::Regex;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
 pub trait Extractor {
     fn extract(&self, source: &[u8], selector: &str) -> Result<String>;
+
+    /// Extract with a selector already parsed by [`Selector::parse`]. By
+    /// default the selector is written back in compact form for
+    /// [`Extractor::extract`].
+    fn extract_selector(&self, source: &[u8], selector: &Selector) -> Result<String> {
+        self.extract(source, &selector.to_string())
+    }
+}
+
+/// The error for a selector handed to an extractor that cannot read it.
+fn wrong_selector(extractor: &str, selector: &Selector) -> RhodiError {
+    RhodiError::Extraction(format!(
+        "The {} extractor cannot read the {} selector '{}'",
+        extractor,
+        selector.extractor(),
+        selector
+    ))
 }
 
 pub struct RegexExtractor;
@@ -63,6 +87,275 @@ impl Extractor for JsonPathExtractor {
     }
 }
 
+/// Check that the first match of `selector` lies within `lines` lines of an
+/// occurrence of the `context` snippet, so a regex that now matches some
+/// unrelated spot in a changed source is caught.
+pub fn check_context(source: &[u8], selector: &str, context: &str, lines: usize) -> Result<()> {
+    let text = String::from_utf8_lossy(source);
+    let re = Regex::new(selector)
+        .map_err(|e| RhodiError::Extraction(format!("Invalid regex '{}': {}", selector, e)))?;
+    let found = re
+        .find(&text)
+        .ok_or_else(|| RhodiError::Extraction(format!("Regex '{}' found no matches", selector)))?;
+    let line_of = |offset: usize| text[..offset].matches('\n').count();
+    let match_line = line_of(found.start());
+
+    let snippet = context.trim();
+    if snippet.is_empty() {
+        return Err(RhodiError::Verification("Context snippet is empty".into()));
+    }
+    let snippet_lines = snippet.matches('\n').count();
+
+    let mut nearest: Option<(usize, usize)> = None;
+    for (offset, _) in text.match_indices(snippet) {
+        let start = line_of(offset);
+        let end = start + snippet_lines;
+        if start.saturating_sub(lines) <= match_line && match_line <= end + lines {
+            return Ok(());
+        }
+        nearest.get_or_insert((start, end));
+    }
+
+    match nearest {
+        None => Err(RhodiError::Verification(
+            "Context snippet no longer appears in the source".into(),
+        )),
+        Some((start, end)) => Err(RhodiError::Verification(format!(
+            "Regex matched on line {}, outside the context at lines {}-{} (±{})",
+            match_line + 1,
+            start + 1,
+            end + 1,
+            lines
+        ))),
+    }
+}
+
+/// Extracts from YAML documents using a dotted path selector (`.metrics.accuracy`).
+pub struct YamlExtractor;
+
+impl Extractor for YamlExtractor {
+    fn extract(&self, source: &[u8], selector: &str) -> Result<String> {
+        let value: Value = serde_norway::from_slice(source)
+            .map_err(|e| RhodiError::Extraction(format!("Invalid YAML for extraction: {}", e)))?;
+        select_path(&value, selector).map(value_to_string)
+    }
+}
+
+/// Extracts from TOML documents using a dotted path selector (`.metrics.accuracy`).
+pub struct TomlExtractor;
+
+impl Extractor for TomlExtractor {
+    fn extract(&self, source: &[u8], selector: &str) -> Result<String> {
+        let text = std::str::from_utf8(source)
+            .map_err(|e| RhodiError::Extraction(format!("Invalid UTF-8 in TOML source: {}", e)))?;
+        let table: toml::Table = toml::from_str(text)
+            .map_err(|e| RhodiError::Extraction(format!("Invalid TOML for extraction: {}", e)))?;
+        let value = serde_json::to_value(table)
+            .map_err(|e| RhodiError::Extraction(format!("Unsupported TOML value: {}", e)))?;
+        select_path(&value, selector).map(value_to_string)
+    }
+}
+
+/// Extracts a byte range of any file with a `bytes:OFFSET..LEN[:hex|:utf8]`
+/// selector, e.g. `bytes:0..4` for a magic number. Offsets and lengths are
+/// decimal or `0x` hex; the value is lowercase hex unless `:utf8` is given.
+pub struct BytesExtractor;
+
+impl Extractor for BytesExtractor {
+    fn extract(&self, source: &[u8], selector: &str) -> Result<String> {
+        self.extract_selector(source, &Selector::parse("bytes", selector)?)
+    }
+
+    fn extract_selector(&self, source: &[u8], selector: &Selector) -> Result<String> {
+        let &Selector::Bytes {
+            offset,
+            len,
+            encoding,
+        } = selector
+        else {
+            return Err(wrong_selector("bytes", selector));
+        };
+
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| source.get(offset..end))
+            .ok_or_else(|| {
+                RhodiError::Extraction(format!(
+                    "Bytes {}..{} lie outside the {}-byte source",
+                    offset,
+                    offset.saturating_add(len),
+                    source.len()
+                ))
+            })?;
+        match encoding {
+            ByteEncoding::Hex => Ok(hex::encode(bytes)),
+            ByteEncoding::Utf8 => std::str::from_utf8(bytes).map(str::to_string).map_err(|e| {
+                RhodiError::Extraction(format!("Byte range is not valid UTF-8: {}", e))
+            }),
+        }
+    }
+}
+
+/// Extracts a range of lines of a text file with a `lines:FROM..TO`
+/// selector (counted from 1, both inclusive), joined with newlines.
+pub struct LinesExtractor;
+
+impl Extractor for LinesExtractor {
+    fn extract(&self, source: &[u8], selector: &str) -> Result<String> {
+        self.extract_selector(source, &Selector::parse("lines", selector)?)
+    }
+
+    fn extract_selector(&self, source: &[u8], selector: &Selector) -> Result<String> {
+        let &Selector::Lines { from, to } = selector else {
+            return Err(wrong_selector("lines", selector));
+        };
+        let text = String::from_utf8_lossy(source);
+        let lines: Vec<&str> = text.lines().collect();
+        if to > lines.len() {
+            return Err(RhodiError::Extraction(format!(
+                "Lines {}..{} lie outside the {}-line source",
+                from,
+                to,
+                lines.len()
+            )));
+        }
+        Ok(lines[from - 1..to].join("\n"))
+    }
+}
+
+/// Extracts one cell of a CSV table with a `cell:A1` selector, e.g.
+/// `cell:B3` for the second column of the third row.
+pub struct CellExtractor;
+
+impl Extractor for CellExtractor {
+    fn extract(&self, source: &[u8], selector: &str) -> Result<String> {
+        self.extract_selector(source, &Selector::parse("cell", selector)?)
+    }
+
+    fn extract_selector(&self, source: &[u8], selector: &Selector) -> Result<String> {
+        let Selector::Cell { sheet, row, col } = selector else {
+            return Err(wrong_selector("cell", selector));
+        };
+        if let Some(sheet) = sheet {
+            return Err(RhodiError::Extraction(format!(
+                "CSV sources have no sheets, but the selector names sheet '{}'",
+                sheet
+            )));
+        }
+        let text = std::str::from_utf8(source)
+            .map_err(|e| RhodiError::Extraction(format!("Invalid UTF-8 in CSV source: {}", e)))?;
+        csv_records(text)
+            .get(*row)
+            .and_then(|record| record.get(*col))
+            .cloned()
+            .ok_or_else(|| {
+                RhodiError::Extraction(format!("Cell '{}' lies outside the table", selector))
+            })
+    }
+}
+
+/// Split CSV text into records of fields. Fields may be quoted, with `""`
+/// for a quote and newlines kept inside quotes.
+pub(crate) fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Validate a JSON `source` against the JSON Schema in `schema`. The error
+/// lists the first few violations with where in the source they occur.
+pub fn check_json_schema(source: &[u8], schema: &[u8]) -> Result<()> {
+    let schema: Value = serde_json::from_slice(schema)
+        .map_err(|e| RhodiError::Extraction(format!("Invalid JSON in schema: {}", e)))?;
+    let validator = jsonschema::validator_for(&schema)
+        .map_err(|e| RhodiError::Extraction(format!("Invalid JSON Schema: {}", e)))?;
+    let instance: Value = serde_json::from_slice(source).map_err(|e| {
+        RhodiError::Verification(format!(
+            "Source is not JSON, cannot check its schema: {}",
+            e
+        ))
+    })?;
+
+    let violations: Vec<String> = validator
+        .iter_errors(&instance)
+        .map(|e| match e.instance_path().as_str() {
+            "" => e.to_string(),
+            path => format!("at {}: {}", path, e),
+        })
+        .collect();
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let shown = violations.len().min(MAX_SCHEMA_VIOLATIONS);
+    let mut message = format!(
+        "Source does not match its schema: {}",
+        violations[..shown].join("; ")
+    );
+    if violations.len() > shown {
+        message.push_str(&format!(" (and {} more)", violations.len() - shown));
+    }
+    Err(RhodiError::Verification(message))
+}
+
+/// How many schema violations an error lists before summarizing the rest.
+const MAX_SCHEMA_VIOLATIONS: usize = 5;
+
+/// Walk a dotted path such as `.metrics.accuracy` or `.runs[0].loss`.
+/// The leading dot is optional; `.` alone selects the whole document.
+fn select_path<'v>(root: &'v Value, selector: &str) -> Result<&'v Value> {
+    let not_found = || RhodiError::Extraction(format!("Path '{}' found no matches", selector));
+    let path = selector.trim();
+    let path = path.strip_prefix('.').unwrap_or(path);
+
+    let mut current = root;
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let (key, indices) = match segment.find('[') {
+            Some(i) => segment.split_at(i),
+            None => (segment, ""),
+        };
+
+        if !key.is_empty() {
+            current = current.get(key).ok_or_else(not_found)?;
+        }
+
+        for index in indices.split_terminator(']') {
+            let index: usize = index
+                .strip_prefix('[')
+                .and_then(|i| i.trim().parse().ok())
+                .ok_or_else(|| {
+                    RhodiError::Extraction(format!("Invalid index in path '{}'", selector))
+                })?;
+            current = current.get(index).ok_or_else(not_found)?;
+        }
+    }
+
+    Ok(current)
+}
+
 fn value_to_string(v: &Value) -> String {
     match v {
         Value::String(s) => s.clone(),
@@ -72,10 +365,559 @@ fn value_to_string(v: &Value) -> String {
     }
 }
 
+/// Default time an `exec` command may run before it is killed.
+pub const DEFAULT_EXEC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest output accepted from an `exec` command, in bytes.
+pub const MAX_EXEC_OUTPUT: usize = 1024 * 1024;
+
+/// Runs a workspace-configured command (`extractor: exec`). The selector
+/// names the command, optionally followed by whitespace-separated arguments
+/// appended to it; the source is written to its stdin and its trimmed stdout
+/// is the extracted value.
+///
+/// Documents can only pick among the commands configured in `rhodi.toml`,
+/// never name a program themselves. Commands run without a shell, in the
+/// workspace root, with an empty environment apart from `PATH`, and are
+/// killed after a timeout.
+#[derive(Debug, Clone)]
+pub struct ExecExtractor {
+    commands: BTreeMap<String, Vec<String>>,
+    allow_args: BTreeSet<String>,
+    dir: PathBuf,
+    timeout: Duration,
+}
+
+impl ExecExtractor {
+    /// `commands` maps a selector name to the program and its arguments.
+    pub fn new(commands: BTreeMap<String, Vec<String>>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            commands,
+            allow_args: BTreeSet::new(),
+            dir: dir.into(),
+            timeout: DEFAULT_EXEC_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Let the named commands take arguments from the selector. Other
+    /// commands accept only their name, so a trace cannot pass them flags.
+    pub fn with_args_allowed(mut self, commands: impl IntoIterator<Item = String>) -> Self {
+        self.allow_args.extend(commands);
+        self
+    }
+}
+
+impl ExecExtractor {
+    /// The program and arguments `selector` names, with the selector's
+    /// own arguments appended if the command allows them. A relative
+    /// program path such as `scripts/extract` is taken from the workspace
+    /// root.
+    pub(crate) fn command(&self, selector: &str) -> Result<Vec<String>> {
+        let mut words = selector.split_whitespace().peekable();
+        let name = words.next().unwrap_or_default();
+        let argv = self
+            .commands
+            .get(name)
+            .filter(|argv| !argv.is_empty())
+            .ok_or_else(|| {
+                RhodiError::Extraction(format!(
+                    "No exec command '{}' is configured in [extractors.exec.commands]",
+                    name
+                ))
+            })?;
+        if words.peek().is_some() && !self.allow_args.contains(name) {
+            return Err(RhodiError::Extraction(format!(
+                "Exec command '{}' takes no arguments unless [extractors.exec] allow_args lists it",
+                name
+            )));
+        }
+        let program = match Path::new(&argv[0]) {
+            path if path.is_relative() && path.components().count() > 1 => {
+                self.dir.join(path).display().to_string()
+            }
+            _ => argv[0].clone(),
+        };
+        Ok(std::iter::once(program)
+            .chain(argv[1..].iter().cloned())
+            .chain(words.map(str::to_string))
+            .collect())
+    }
+}
+
+impl ExecExtractor {
+    /// [`Extractor::extract`], killing the command once `deadline` passes
+    /// if that comes before the timeout.
+    pub(crate) fn extract_until(
+        &self,
+        source: &[u8],
+        selector: &str,
+        deadline: Option<Instant>,
+    ) -> Result<String> {
+        let argv = self.command(selector)?;
+        let name = selector.split_whitespace().next().unwrap_or_default();
+        let mut command = Command::new(&argv[0]);
+        command.args(&argv[1..]).current_dir(&self.dir);
+        run_command(
+            &format!("Exec command '{}'", name),
+            command,
+            source,
+            self.timeout,
+            deadline,
+        )
+    }
+}
+
+impl Extractor for ExecExtractor {
+    fn extract(&self, source: &[u8], selector: &str) -> Result<String> {
+        self.extract_until(source, selector, None)
+    }
+}
+
+/// Run `command` with `input` on its stdin, an empty environment apart
+/// from `PATH`, and a timeout. It is also killed once `deadline` passes.
+/// Returns its trimmed stdout. Errors name the command as `label`, e.g.
+/// `Exec command 'wc'`.
+pub(crate) fn run_command(
+    label: &str,
+    command: Command,
+    input: &[u8],
+    timeout: Duration,
+    deadline: Option<Instant>,
+) -> Result<String> {
+    let out = run_command_output(label, command, input, timeout, deadline, MAX_EXEC_OUTPUT)?;
+    let value = String::from_utf8(out)
+        .map_err(|e| RhodiError::Extraction(format!("{} printed invalid UTF-8: {}", label, e)))?;
+    Ok(value.trim().to_string())
+}
+
+/// As [`run_command`], returning stdout as printed, which may be at most
+/// `max_output` bytes.
+pub(crate) fn run_command_output(
+    label: &str,
+    mut command: Command,
+    input: &[u8],
+    timeout: Duration,
+    deadline: Option<Instant>,
+    max_output: usize,
+) -> Result<Vec<u8>> {
+    let failed = |e: std::io::Error| RhodiError::Extraction(format!("{} failed: {}", label, e));
+    let unpiped = || RhodiError::Extraction(format!("{} failed: its pipes were not set up", label));
+    let reader_panicked =
+        || RhodiError::Extraction(format!("{} failed: reading its output panicked", label));
+
+    let mut child = command
+        .env_clear()
+        .envs(std::env::var_os("PATH").map(|path| ("PATH", path)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(failed)?;
+
+    // Feed stdin and drain the pipes on their own threads, so a command
+    // that writes before reading all its input cannot deadlock us
+    let (Some(mut stdin), Some(stdout), Some(stderr)) =
+        (child.stdin.take(), child.stdout.take(), child.stderr.take())
+    else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(unpiped());
+    };
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let reader = std::thread::spawn(move || {
+        let mut out = Vec::new();
+        stdout
+            .take(max_output as u64 + 1)
+            .read_to_end(&mut out)
+            .map(|_| out)
+    });
+    let errors = std::thread::spawn(move || {
+        let mut err = Vec::new();
+        stderr.take(4096).read_to_end(&mut err).map(|_| err)
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(failed)? {
+            break status;
+        }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(RhodiError::Extraction(format!(
+                "{} timed out after {:?}",
+                label, timeout
+            )));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(RhodiError::Extraction(format!(
+                "{} was stopped when verification ran out of time",
+                label
+            )));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    // A command may exit without reading its input; that is not an error
+    let _ = writer.join();
+    let out = reader
+        .join()
+        .map_err(|_| reader_panicked())?
+        .map_err(failed)?;
+    let err = errors
+        .join()
+        .map_err(|_| reader_panicked())?
+        .unwrap_or_default();
+    if !status.success() {
+        return Err(RhodiError::Extraction(format!(
+            "{} exited with {}: {}",
+            label,
+            status,
+            String::from_utf8_lossy(&err).trim()
+        )));
+    }
+    if out.len() > max_output {
+        return Err(RhodiError::Extraction(format!(
+            "{} printed more than {} bytes",
+            label, max_output
+        )));
+    }
+    Ok(out)
+}
+
+/// Most digits `round:N` may keep: an `f64` carries no more than 17
+/// significant ones.
+pub const MAX_ROUND_DIGITS: usize = 17;
+
+/// A post-processing step applied to an extracted value before comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    Trim,
+    Lowercase,
+    Uppercase,
+    StripCommas,
+    StripPercent,
+    CollapseWhitespace,
+    Round(usize),
+}
+
+impl Transform {
+    /// Parse a transform from its trace-block form, e.g. `trim` or `round:2`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (spec, None),
+        };
+
+        match (name.to_lowercase().as_str(), arg) {
+            ("trim", None) => Ok(Transform::Trim),
+            ("lowercase", None) => Ok(Transform::Lowercase),
+            ("uppercase", None) => Ok(Transform::Uppercase),
+            ("strip_commas", None) => Ok(Transform::StripCommas),
+            ("strip_percent", None) => Ok(Transform::StripPercent),
+            ("collapse_whitespace", None) => Ok(Transform::CollapseWhitespace),
+            ("round", Some(digits)) => digits
+                .parse()
+                .ok()
+                .filter(|digits| *digits <= MAX_ROUND_DIGITS)
+                .map(Transform::Round)
+                .ok_or_else(|| {
+                    RhodiError::Extraction(format!(
+                        "Invalid digit count in transform '{}': expected 0 to {}",
+                        spec, MAX_ROUND_DIGITS
+                    ))
+                }),
+            _ => Err(RhodiError::Extraction(format!(
+                "Unknown transform: {}",
+                spec
+            ))),
+        }
+    }
+
+    pub fn apply(&self, value: &str) -> Result<String> {
+        match self {
+            Transform::Trim => Ok(value.trim().to_string()),
+            Transform::Lowercase => Ok(value.to_lowercase()),
+            Transform::Uppercase => Ok(value.to_uppercase()),
+            Transform::StripCommas => Ok(value.replace(',', "")),
+            Transform::StripPercent => {
+                Ok(value.trim().trim_end_matches('%').trim_end().to_string())
+            }
+            Transform::CollapseWhitespace => {
+                Ok(value.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            Transform::Round(digits) => {
+                let number: f64 = value.trim().parse().map_err(|_| {
+                    RhodiError::Extraction(format!("Cannot round non-numeric value '{}'", value))
+                })?;
+                Ok(format!("{:.*}", digits, number))
+            }
+        }
+    }
+}
+
+/// Apply a chain of transforms, in order, to an extracted value.
+pub fn apply_transforms(value: &str, transforms: &[String]) -> Result<String> {
+    transforms.iter().try_fold(value.to_string(), |acc, spec| {
+        Transform::parse(spec)?.apply(&acc)
+    })
+}
+
+/// A step applied to a source before it is hashed and extracted from, so
+/// that churn irrelevant to the claim does not break the trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// Reduce HTML to its visible text: see [`html_to_text`]
+    Html,
+}
+
+impl Normalization {
+    /// Parse a trace's `normalize` field, e.g. `html`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "html" => Ok(Normalization::Html),
+            _ => Err(RhodiError::Extraction(format!(
+                "Unknown normalization: {}",
+                spec
+            ))),
+        }
+    }
+
+    pub fn apply(&self, content: &[u8]) -> Vec<u8> {
+        match self {
+            Normalization::Html => html_to_text(&String::from_utf8_lossy(content)).into_bytes(),
+        }
+    }
+}
+
+/// `content` after the normalization `spec` names, or unchanged without one.
+pub fn normalize_source<'a>(content: &'a [u8], spec: Option<&str>) -> Result<Cow<'a, [u8]>> {
+    match spec {
+        Some(spec) => Ok(Cow::Owned(Normalization::parse(spec)?.apply(content))),
+        None => Ok(Cow::Borrowed(content)),
+    }
+}
+
+/// Elements that start a new line of text.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Elements whose content is not visible text.
+const HIDDEN_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+
+/// The visible text of an HTML page. Tags, comments and the content of
+/// `script` and `style` elements are dropped, so attributes such as
+/// tracking parameters and inline scripts do not affect the result; entities
+/// are decoded, block elements start a new line, table cells are separated
+/// by a space, and other whitespace is collapsed with blank lines removed.
+pub fn html_to_text(html: &str) -> String {
+    // ASCII lowercasing keeps byte offsets, so `lower` indexes like `html`
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::with_capacity(html.len());
+    let mut rest = 0;
+
+    while let Some(offset) = html[rest..].find('<') {
+        let start = rest + offset;
+        push_text(&mut text, &html[rest..start]);
+        let tag = &lower[start + 1..];
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+
+        let end = if tag.starts_with("!--") {
+            tag.find("-->").map(|i| start + 1 + i + 3)
+        } else if tag.starts_with('!') || tag.starts_with('?') || !name.is_empty() {
+            let close = tag.find('>').map(|i| start + 1 + i + 1);
+            if HIDDEN_ELEMENTS.contains(&name.as_str()) && !tag.starts_with('/') {
+                close.map(|close| {
+                    lower[close..]
+                        .find(&format!("</{}", name))
+                        .and_then(|i| lower[close + i..].find('>').map(|j| close + i + j + 1))
+                        .unwrap_or(html.len())
+                })
+            } else {
+                close
+            }
+        } else {
+            // A bare `<`, as in `a < b`, is text
+            text.push('<');
+            rest = start + 1;
+            continue;
+        };
+
+        let Some(end) = end else {
+            // An unterminated tag runs to the end of the page
+            rest = html.len();
+            break;
+        };
+        if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push('\n');
+        } else if name == "td" || name == "th" {
+            text.push(' ');
+        }
+        rest = end;
+    }
+    push_text(&mut text, &html[rest..]);
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Append a run of markup text. Line breaks in the markup are whitespace
+/// like any other; only elements break lines.
+fn push_text(text: &mut String, markup: &str) {
+    text.extend(
+        decode_entities(markup)
+            .chars()
+            .map(|c| if c.is_whitespace() { ' ' } else { c }),
+    );
+}
+
+/// Decode the named entities common in text, and numeric ones. Anything
+/// else is kept as written.
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| (&rest[1..end + 1], end + 2));
+        let decoded = entity.and_then(|(name, len)| {
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| name.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, len))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// The extractor a trace uses: the one it names, or for a trace that names
+/// none (or `auto`) one chosen from the source's content type. A structured
+/// extractor is only chosen when the selector is written for it (`$...` for
+/// JSONPath, a dotted path for YAML and TOML); anything else stays regex, so
+/// regex traces over JSON or YAML files keep working. A `bytes:` selector
+/// reads a byte range whatever the content type. `lines:` and `cell:`
+/// selectors pick their extractor the same way, but only when they parse
+/// in full, since text like `lines: (\d+)` is a regex.
+pub fn select_extractor<'a>(
+    named: Option<&'a str>,
+    content_type: Option<&str>,
+    selector: &str,
+) -> &'a str {
+    if let Some(named) = named.filter(|n| !n.eq_ignore_ascii_case("auto")) {
+        return named;
+    }
+    if selector.trim_start().starts_with("bytes:") {
+        return "bytes";
+    }
+    for prefixed in ["lines", "cell"] {
+        if Selector::parse(prefixed, selector).is_ok() {
+            return prefixed;
+        }
+    }
+    let is_path = selector.starts_with('.')
+        && selector
+            .chars()
+            .all(|c| c.is_alphanumeric() || "._-[]".contains(c));
+    let structured = match content_type.map(|t| t.to_ascii_lowercase()).as_deref() {
+        Some(t) if t == "application/json" || t.ends_with("+json") => "jsonpath",
+        Some("application/yaml" | "application/x-yaml" | "text/yaml") => "yaml",
+        Some("application/toml") => "toml",
+        _ => "regex",
+    };
+    match structured {
+        "jsonpath" if selector.starts_with('$') => "jsonpath",
+        "yaml" | "toml" if is_path => structured,
+        _ => "regex",
+    }
+}
+
 pub fn get_extractor(method: &str) -> Result<Box<dyn Extractor>> {
     match method.to_lowercase().as_str() {
         "regex" => Ok(Box::new(RegexExtractor)),
         "jsonpath" => Ok(Box::new(JsonPathExtractor)),
+        "yaml" => Ok(Box::new(YamlExtractor)),
+        "toml" => Ok(Box::new(TomlExtractor)),
+        "bytes" => Ok(Box::new(BytesExtractor)),
+        "lines" => Ok(Box::new(LinesExtractor)),
+        "cell" => Ok(Box::new(CellExtractor)),
+        "exec" => Err(RhodiError::Extraction(
+            "The exec extractor is disabled; enable it in [extractors.exec] of rhodi.toml".into(),
+        )),
         _ => Err(RhodiError::Extraction(format!(
             "Unknown extraction method: {}",
             method
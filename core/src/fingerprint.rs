@@ -0,0 +1,101 @@
+//! Short, human-comparable fingerprints of public keys.
+//!
+//! A [`KeyFingerprint`] is the first 8 bytes of the SHA-256 of a public key,
+//! shown as four hex groups and as eight words, one per byte, so two people
+//! can compare keys over a phone call instead of reading out 64 hex
+//! characters. 64 bits are plenty to catch a wrong or swapped key; against
+//! an attacker able to grind keys for a matching fingerprint, compare the
+//! full key.
+
+use ed25519_dalek::VerifyingKey;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Bytes of the key hash a fingerprint keeps.
+pub const FINGERPRINT_BYTES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFingerprint([u8; FINGERPRINT_BYTES]);
+
+impl KeyFingerprint {
+    pub fn of(key: &VerifyingKey) -> Self {
+        let digest = Sha256::digest(key.as_bytes());
+        let mut bytes = [0u8; FINGERPRINT_BYTES];
+        bytes.copy_from_slice(&digest[..FINGERPRINT_BYTES]);
+        Self(bytes)
+    }
+
+    /// The fingerprint as hex in groups of four, e.g. `3f2a 91c0 7e44 d1b8`.
+    pub fn hex(&self) -> String {
+        self.0
+            .chunks(2)
+            .map(hex::encode)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The fingerprint as one word per byte.
+    pub fn words(&self) -> String {
+        self.0
+            .iter()
+            .map(|&b| WORDS[b as usize])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Whether `text` spells this fingerprint, in hex or in words. Case,
+    /// spaces, colons and dashes are ignored.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_ascii_lowercase();
+        let words: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == '-' || c == ',')
+            .filter(|w| !w.is_empty())
+            .collect();
+        if words.len() == FINGERPRINT_BYTES && words.iter().all(|w| WORDS.contains(w)) {
+            return words.join(" ") == self.words();
+        }
+        let digits: String = text
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ':' && *c != '-')
+            .collect();
+        digits == hex::encode(self.0)
+    }
+}
+
+impl fmt::Display for KeyFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.hex(), self.words())
+    }
+}
+
+/// One word per byte value, sorted, short and distinct when spoken.
+const WORDS: [&str; 256] = [
+    "acorn", "actor", "adobe", "agent", "alarm", "album", "alley", "amber", "angel", "ankle",
+    "apple", "apron", "arena", "arrow", "atlas", "attic", "autumn", "award", "bacon", "badge",
+    "bagel", "baker", "bamboo", "banjo", "barn", "basin", "basket", "beach", "beacon", "beaver",
+    "berry", "bison", "blade", "blanket", "bobcat", "bonnet", "border", "bottle", "bounty",
+    "bracket", "branch", "brass", "bread", "breeze", "brick", "bridge", "bronze", "brook",
+    "bucket", "bugle", "bundle", "butter", "button", "cabin", "cactus", "camel", "camera", "canal",
+    "candle", "canoe", "canyon", "carbon", "carpet", "castle", "cedar", "cellar", "cement",
+    "chalk", "channel", "cherry", "chess", "cider", "circus", "citrus", "clover", "cobalt",
+    "cocoa", "comet", "copper", "coral", "cotton", "cougar", "coyote", "crane", "crater", "crayon",
+    "cricket", "crystal", "dagger", "daisy", "dancer", "delta", "denim", "desert", "diamond",
+    "dinner", "dolphin", "domino", "donkey", "dragon", "drum", "eagle", "easel", "echo", "eclipse",
+    "elbow", "ember", "emerald", "engine", "falcon", "feather", "fiddle", "finch", "flag", "flute",
+    "forest", "fossil", "fox", "galaxy", "garden", "garlic", "gazelle", "ginger", "glacier",
+    "goblet", "gopher", "granite", "gravel", "guitar", "hammer", "harbor", "harvest", "hazel",
+    "helmet", "heron", "hickory", "honey", "hornet", "igloo", "island", "ivory", "jacket",
+    "jaguar", "jelly", "jigsaw", "jungle", "kayak", "kernel", "kettle", "kitten", "koala",
+    "ladder", "lagoon", "lantern", "laurel", "lemon", "lentil", "lilac", "linen", "lizard",
+    "lobster", "locket", "lotus", "magnet", "mango", "maple", "marble", "meadow", "melon",
+    "meteor", "mitten", "monkey", "mosaic", "muffin", "mustard", "napkin", "nectar", "needle",
+    "nickel", "noodle", "nutmeg", "oasis", "ocean", "olive", "onion", "orbit", "orchid", "otter",
+    "oyster", "paddle", "panda", "panther", "parrot", "pebble", "pepper", "pigeon", "pillow",
+    "pilot", "pine", "planet", "plum", "pocket", "pony", "poppy", "puzzle", "quail", "quartz",
+    "quill", "rabbit", "radar", "radish", "raven", "ribbon", "river", "robin", "rocket", "saddle",
+    "salmon", "satin", "scarf", "shadow", "shovel", "silver", "sketch", "sparrow", "spider",
+    "spruce", "squid", "stable", "summit", "sunset", "swan", "tablet", "tango", "teapot", "tiger",
+    "timber", "toast", "tomato", "topaz", "tulip", "tundra", "turnip", "velvet", "violin",
+    "walnut", "walrus", "whale", "willow", "window", "wizard", "yogurt", "zebra", "zephyr",
+    "zigzag", "zipper",
+];
@@ -0,0 +1,212 @@
+//! Signed manifests of a whole workspace release.
+//!
+//! `rhodi freeze` records the version hash of every sealed document in the
+//! workspace in one [`WorkspaceManifest`], signed with the releaser's key,
+//! so a documentation set can be attested as a unit. `rhodi freeze --check`
+//! later confirms that every listed document is still at the frozen version
+//! and that its seal verifies.
+
+use crate::compiler::CompilationReport;
+use crate::crypto::{KeyPair, parse_public_key, parse_signature};
+use crate::error::{Result, RhodiError};
+use crate::index::WorkspaceIndex;
+use crate::lock::write_atomic;
+use crate::markdown::parse_tmd;
+use crate::models::DocStatus;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Domain separator so manifest signatures can never be confused with seals.
+const FREEZE_CONTEXT: &str = "rhodi-freeze-v1";
+
+/// Default manifest file, at the workspace root.
+pub const FREEZE_FILE_NAME: &str = "rhodi.freeze.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkspaceManifest {
+    /// Name of the release, e.g. `v2.1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>,
+    pub frozen_at: DateTime<Utc>,
+    /// Hex-encoded public key that signed the manifest
+    pub signer: String,
+    /// Every sealed document, by workspace-relative path
+    pub documents: Vec<FrozenDocument>,
+    /// Hex-encoded Ed25519 signature over the manifest
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FrozenDocument {
+    /// Path relative to the workspace root, with `/` separators
+    pub path: String,
+    pub id: Uuid,
+    pub doc_version: u32,
+    /// Hex-encoded version hash of the frozen version
+    pub version_hash: String,
+}
+
+impl WorkspaceManifest {
+    /// Record and sign the current version of every Published document
+    /// under `root`. A document whose body no longer matches its seal is
+    /// an error: it has to be resealed before the release can be frozen.
+    pub fn create(root: &Path, release: Option<String>, keypair: &KeyPair) -> Result<Self> {
+        let index = WorkspaceIndex::build(root)?;
+        let mut documents = Vec::new();
+        for indexed in &index.documents {
+            let Some(version_hash) = indexed
+                .version_hash
+                .clone()
+                .filter(|_| indexed.status == DocStatus::Published)
+            else {
+                continue;
+            };
+            let doc = parse_tmd(&fs::read_to_string(root.join(&indexed.path))?)?;
+            if hex::encode(doc.current_version_hash()?) != version_hash {
+                return Err(RhodiError::Verification(format!(
+                    "{} has changed since it was sealed; reseal it before freezing",
+                    indexed.path.display()
+                )));
+            }
+            documents.push(FrozenDocument {
+                path: manifest_path(&indexed.path),
+                id: indexed.id,
+                doc_version: indexed.doc_version,
+                version_hash,
+            });
+        }
+        if documents.is_empty() {
+            return Err(RhodiError::Verification(
+                "No sealed documents in the workspace to freeze".into(),
+            ));
+        }
+
+        let mut manifest = Self {
+            release,
+            frozen_at: Utc::now(),
+            signer: hex::encode(keypair.verifying_key.as_bytes()),
+            documents,
+            signature: String::new(),
+        };
+        manifest.signature = hex::encode(keypair.sign(&manifest.message()).to_bytes());
+        Ok(manifest)
+    }
+
+    /// The exact bytes covered by the signature.
+    fn message(&self) -> Vec<u8> {
+        let mut message = format!(
+            "{}\n{}\n{}\n{}",
+            FREEZE_CONTEXT,
+            self.release.as_deref().unwrap_or_default(),
+            self.frozen_at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.signer
+        );
+        for document in &self.documents {
+            message.push('\n');
+            message.push_str(&serde_json::to_string(document).unwrap_or_default());
+        }
+        message.into_bytes()
+    }
+
+    /// Check the manifest's signature against its embedded signer key.
+    pub fn verify(&self) -> Result<()> {
+        let public_key = parse_public_key(&self.signer)?;
+        let signature = parse_signature(&self.signature)?;
+        public_key
+            .verify_strict(&self.message(), &signature)
+            .map_err(|e| RhodiError::Crypto(format!("Manifest signature invalid: {}", e)))
+    }
+
+    /// Check the workspace at `root` against the manifest: its signature,
+    /// and that every listed document is still at the frozen version with a
+    /// valid seal. Sealed documents the manifest does not list are warnings.
+    pub fn check(&self, root: &Path) -> Result<CompilationReport> {
+        let mut report = CompilationReport::default();
+        if let Err(e) = self.verify() {
+            report.errors.push(e);
+        }
+
+        for frozen in &self.documents {
+            if let Err(e) = check_document(root, frozen) {
+                report.errors.push(e);
+            }
+        }
+
+        let index = WorkspaceIndex::build(root)?;
+        for indexed in &index.documents {
+            let path = manifest_path(&indexed.path);
+            if indexed.status == DocStatus::Published
+                && !self.documents.iter().any(|d| d.path == path)
+            {
+                report
+                    .warnings
+                    .push(format!("{} is sealed but not part of the release", path));
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| RhodiError::Format(format!("Invalid manifest {}: {}", path.display(), e)))
+    }
+
+    /// Write the manifest as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to serialize manifest: {}", e))
+        })?;
+        write_atomic(path, &format!("{}\n", json))
+    }
+}
+
+/// One frozen document: still there, same id, same version hash, and its
+/// seal verifies.
+fn check_document(root: &Path, frozen: &FrozenDocument) -> Result<()> {
+    let content = fs::read_to_string(root.join(&frozen.path)).map_err(|e| {
+        RhodiError::Resolution(format!(
+            "{}: cannot read frozen document: {}",
+            frozen.path, e
+        ))
+    })?;
+    let doc = parse_tmd(&content)?;
+    let fm = &doc.frontmatter;
+    if fm.id != frozen.id {
+        return Err(RhodiError::Verification(format!(
+            "{}: document id is {}, the release froze {}",
+            frozen.path, fm.id, frozen.id
+        )));
+    }
+    if fm.version_hash.map(hex::encode).as_deref() != Some(frozen.version_hash.as_str()) {
+        return Err(RhodiError::Verification(format!(
+            "{}: now at v{}, the release froze v{} ({})",
+            frozen.path, fm.doc_version, frozen.doc_version, frozen.version_hash
+        )));
+    }
+    if let Some(pk) = &fm.public_key {
+        return doc.verify(&parse_public_key(pk)?).map_err(|e| match e {
+            RhodiError::Verification(msg) => {
+                RhodiError::Verification(format!("{}: {}", frozen.path, msg))
+            }
+            other => other,
+        });
+    }
+    if hex::encode(doc.current_version_hash()?) != frozen.version_hash {
+        return Err(RhodiError::Verification(format!(
+            "{}: content no longer matches its version hash",
+            frozen.path
+        )));
+    }
+    Ok(())
+}
+
+/// A workspace-relative path as the manifest records it.
+fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
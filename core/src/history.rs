@@ -0,0 +1,184 @@
+//! Earlier sealed versions of documents, and the `prev_version_hash` chain
+//! linking them.
+//!
+//! Sealing a document (`seal`, `sign`, `reseal-all`) keeps a copy of the new
+//! version in the workspace history store, `.rhodi/history/<id>/`.
+//! [`walk_chain`] follows `prev_version_hash` back from a document through
+//! that store and the document's sibling files, checking that each earlier
+//! version still matches its hash and signature, until it reaches the first
+//! version or the chain breaks.
+
+use crate::compiler::CompilationReport;
+use crate::crypto::parse_public_key;
+use crate::error::{Result, RhodiError};
+use crate::lock::write_atomic;
+use crate::markdown::parse_tmd;
+use crate::models::{TracedDocument, check_version_sequence};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One version on a document's chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainLink {
+    pub doc_version: u32,
+    /// Hex-encoded version hash
+    pub version_hash: String,
+    /// Where this version was found; `None` for the document being verified
+    pub path: Option<PathBuf>,
+}
+
+/// The versions reached from a document, newest first, and what went wrong
+/// on the way. The chain is complete when it ends at a version without
+/// `prev_version_hash` and the report has no errors.
+#[derive(Debug, Default)]
+pub struct VersionChain {
+    pub links: Vec<ChainLink>,
+    pub report: CompilationReport,
+}
+
+impl VersionChain {
+    pub fn is_complete(&self) -> bool {
+        self.report.errors.is_empty()
+    }
+}
+
+/// Keep a copy of the sealed `doc` (its file content is `content`) in the
+/// history store at `dir`. Returns where it was written.
+pub fn archive(dir: &Path, doc: &TracedDocument, content: &str) -> Result<PathBuf> {
+    let version_hash = doc.frontmatter.version_hash.ok_or_else(|| {
+        RhodiError::Verification("Only sealed versions are kept in the history".into())
+    })?;
+    let path = dir.join(doc.frontmatter.id.to_string()).join(format!(
+        "v{}-{}.tmd",
+        doc.frontmatter.doc_version,
+        &hex::encode(version_hash)[..16]
+    ));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, content)?;
+    Ok(path)
+}
+
+/// Follow `doc`'s `prev_version_hash` links back through the `.tmd` files
+/// in `search` (the history store and the document's directory), checking
+/// each earlier version's hash and signature.
+pub fn walk_chain(doc: &TracedDocument, search: &[PathBuf]) -> VersionChain {
+    let known = earlier_versions(doc, search);
+    let mut chain = VersionChain::default();
+    let mut versions = vec![doc.clone()];
+    chain.links.push(ChainLink {
+        doc_version: doc.frontmatter.doc_version,
+        version_hash: doc
+            .frontmatter
+            .version_hash
+            .map(hex::encode)
+            .unwrap_or_default(),
+        path: None,
+    });
+
+    let mut current = doc;
+    while let Some(prev_hash) = current.frontmatter.prev_version_hash {
+        let after = current.frontmatter.doc_version;
+        let Some((path, prev)) = known.get(&prev_hash) else {
+            chain.report.errors.push(RhodiError::Verification(format!(
+                "Version chain breaks after v{}: previous version {} not found in the history or next to the document",
+                after,
+                hex::encode(prev_hash)
+            )));
+            break;
+        };
+        if versions
+            .iter()
+            .any(|v| v.frontmatter.version_hash == Some(prev_hash))
+        {
+            chain.report.errors.push(RhodiError::Verification(format!(
+                "Version chain loops back to {} after v{}",
+                hex::encode(prev_hash),
+                after
+            )));
+            break;
+        }
+        chain.links.push(ChainLink {
+            doc_version: prev.frontmatter.doc_version,
+            version_hash: hex::encode(prev_hash),
+            path: Some(path.clone()),
+        });
+        if let Err(e) = check_link(prev) {
+            let reason = match e {
+                RhodiError::Verification(m) | RhodiError::Crypto(m) => m,
+                other => other.to_string(),
+            };
+            chain.report.errors.push(RhodiError::Verification(format!(
+                "Version chain breaks at v{} ({}): {}",
+                prev.frontmatter.doc_version,
+                path.display(),
+                reason
+            )));
+            break;
+        }
+        if prev.frontmatter.public_key.is_none() {
+            chain.report.warnings.push(format!(
+                "v{} ({}) carries no public key; only its hash was checked",
+                prev.frontmatter.doc_version,
+                path.display()
+            ));
+        }
+        versions.push(prev.clone());
+        current = prev;
+    }
+
+    versions.reverse();
+    for anomaly in check_version_sequence(&versions) {
+        chain.report.errors.push(RhodiError::Verification(format!(
+            "Version chain: {}",
+            anomaly
+        )));
+    }
+    chain
+}
+
+/// An earlier version is intact: its content matches its recorded hash and,
+/// when it names its key, its signature verifies.
+fn check_link(doc: &TracedDocument) -> Result<()> {
+    match &doc.frontmatter.public_key {
+        Some(pk) => doc.verify(&parse_public_key(pk)?),
+        None if doc.frontmatter.version_hash == Some(doc.current_version_hash()?) => Ok(()),
+        None => Err(RhodiError::Verification(
+            "content no longer matches its version hash".into(),
+        )),
+    }
+}
+
+/// Sealed versions of `doc` (same id) among the `.tmd` files directly in
+/// each of `dirs`, by recorded version hash. Unreadable files are skipped.
+fn earlier_versions(
+    doc: &TracedDocument,
+    dirs: &[PathBuf],
+) -> HashMap<[u8; 32], (PathBuf, TracedDocument)> {
+    let mut versions = HashMap::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "tmd") {
+                continue;
+            }
+            let Ok(version) = fs::read_to_string(&path)
+                .map_err(RhodiError::from)
+                .and_then(|content| parse_tmd(&content))
+            else {
+                continue;
+            };
+            if version.frontmatter.id != doc.frontmatter.id {
+                continue;
+            }
+            if let Some(hash) = version.frontmatter.version_hash {
+                versions.entry(hash).or_insert((path, version));
+            }
+        }
+    }
+    versions
+}
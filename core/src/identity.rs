@@ -0,0 +1,174 @@
+//! Author identity profiles.
+//!
+//! A profile stores who an author is (name, email, ORCID iD, affiliation)
+//! and which key they sign with, so `rhodi init --author-profile` and
+//! `rhodi seal --author-profile` can embed a structured
+//! [`Author`] in the frontmatter instead of a free-form string. Profiles
+//! are kept one JSON file each in the user's configuration directory, next
+//! to the keys.
+
+use crate::error::{Result, RhodiError};
+use crate::models::Author;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(feature = "keys-store")]
+const PROFILE_DIR_NAME: &str = "profiles";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorProfile {
+    /// Name the profile is selected by, e.g. `ada`
+    pub profile: String,
+    /// Display name written to the frontmatter
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orcid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affiliation: Option<String>,
+    /// Key `seal` signs with under this profile, unless `--key` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl AuthorProfile {
+    /// The frontmatter `author` for this profile.
+    pub fn author(&self) -> Author {
+        Author {
+            name: self.name.clone(),
+            email: self.email.clone(),
+            orcid: self.orcid.clone(),
+            affiliation: self.affiliation.clone(),
+        }
+    }
+
+    /// Check the fields that have a fixed format.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(RhodiError::Format("Author profile needs a name".into()));
+        }
+        if let Some(email) = &self.email
+            && !email.contains('@')
+        {
+            return Err(RhodiError::Format(format!("Invalid email '{}'", email)));
+        }
+        if let Some(orcid) = &self.orcid {
+            validate_orcid(orcid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check an ORCID iD: four groups of four digits, the last character
+/// possibly `X`, with a valid ISO 7064 MOD 11-2 check digit.
+pub fn validate_orcid(orcid: &str) -> Result<()> {
+    let invalid =
+        |reason: &str| RhodiError::Format(format!("Invalid ORCID iD '{}': {}", orcid, reason));
+    let digits: Vec<char> = orcid.chars().filter(|c| *c != '-').collect();
+    let grouped = orcid.len() == 19 && orcid.split('-').all(|group| group.len() == 4);
+    if !grouped || digits.len() != 16 {
+        return Err(invalid("expected 0000-0000-0000-0000"));
+    }
+    let (body, check) = digits.split_at(15);
+    let mut total = 0;
+    for c in body {
+        let digit = c.to_digit(10).ok_or_else(|| invalid("not a digit"))?;
+        total = (total + digit) * 2;
+    }
+    let expected = match (12 - total % 11) % 11 {
+        10 => Some('X'),
+        n => char::from_digit(n, 10),
+    };
+    if Some(check[0].to_ascii_uppercase()) != expected {
+        return Err(invalid("check digit does not match"));
+    }
+    Ok(())
+}
+
+pub struct ProfileStore {
+    dir: PathBuf,
+}
+
+impl ProfileStore {
+    #[cfg(feature = "keys-store")]
+    pub fn new() -> Result<Self> {
+        let proj_dirs = directories::ProjectDirs::from("com", "rhodi", "rhodi")
+            .ok_or_else(|| RhodiError::Resolution("Could not determine config directory".into()))?;
+        Self::at(proj_dirs.config_dir().join(PROFILE_DIR_NAME))
+    }
+
+    /// A profile store over an explicit directory.
+    pub fn at(dir: PathBuf) -> Result<Self> {
+        if !dir.exists() {
+            fs::create_dir_all(&dir)?;
+        }
+        Ok(Self { dir })
+    }
+
+    fn path(&self, profile: &str) -> Result<PathBuf> {
+        if profile.is_empty()
+            || !profile
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-_.".contains(c))
+            || profile.starts_with('.')
+        {
+            return Err(RhodiError::Format(format!(
+                "Invalid profile name '{}': use letters, digits, '-', '_' and '.'",
+                profile
+            )));
+        }
+        Ok(self.dir.join(format!("{}.json", profile)))
+    }
+
+    /// Store `profile`, replacing any profile of the same name.
+    pub fn save(&self, profile: &AuthorProfile) -> Result<()> {
+        profile.validate()?;
+        let json = serde_json::to_string_pretty(profile).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to serialize profile: {}", e))
+        })?;
+        fs::write(self.path(&profile.profile)?, json)?;
+        Ok(())
+    }
+
+    pub fn get(&self, profile: &str) -> Result<AuthorProfile> {
+        let path = self.path(profile)?;
+        let json = fs::read_to_string(&path).map_err(|_| {
+            RhodiError::Resolution(format!(
+                "Author profile '{}' not found. Create it with 'rhodi author add {}'.",
+                profile, profile
+            ))
+        })?;
+        serde_json::from_str(&json)
+            .map_err(|e| RhodiError::Format(format!("Invalid author profile '{}': {}", profile, e)))
+    }
+
+    /// Every stored profile, sorted by name.
+    pub fn list(&self) -> Result<Vec<AuthorProfile>> {
+        let mut profiles = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json")
+                && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            {
+                profiles.push(self.get(stem)?);
+            }
+        }
+        profiles.sort_by(|a, b| a.profile.cmp(&b.profile));
+        Ok(profiles)
+    }
+
+    pub fn remove(&self, profile: &str) -> Result<()> {
+        let path = self.path(profile)?;
+        if !path.exists() {
+            return Err(RhodiError::Resolution(format!(
+                "Author profile '{}' not found",
+                profile
+            )));
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! Incremental verification, for `rhodi verify --since`.
+//!
+//! Given the files changed since a baseline (a git revision or a freeze
+//! manifest), a [`ChangeSet`] splits the workspace's documents into those
+//! that must be verified again and those whose last result still stands. A
+//! document is affected when it changed, when a source it cites changed,
+//! or when a document it includes is affected. Remote sources cannot be
+//! diffed, so documents citing them are always verified.
+
+use crate::freeze::WorkspaceManifest;
+use crate::index::{IndexedDocument, WorkspaceIndex};
+use crate::resolver::{is_network_source, normalize};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct ChangeSet<'a> {
+    /// Documents to verify again
+    pub affected: Vec<&'a IndexedDocument>,
+    /// Documents nothing they depend on changed in, reported as a cached pass
+    pub cached: Vec<&'a IndexedDocument>,
+}
+
+impl<'a> ChangeSet<'a> {
+    /// Split `index`'s documents by `changed`, a list of paths relative to
+    /// the workspace root.
+    pub fn new(index: &'a WorkspaceIndex, changed: &[PathBuf]) -> Self {
+        let changed: HashSet<PathBuf> = changed.iter().map(|p| normalize(p)).collect();
+        let mut affected: HashSet<&Path> = index
+            .documents
+            .iter()
+            .filter(|doc| {
+                changed.contains(&doc.path)
+                    || doc.claims.iter().any(|claim| {
+                        is_network_source(&claim.source)
+                            || changed.contains(Path::new(&claim.source))
+                    })
+                    // An include by an id no document has cannot be traced
+                    || doc.includes.iter().any(|include| {
+                        include.starts_with("id:") || changed.contains(Path::new(include))
+                    })
+            })
+            .map(|doc| doc.path.as_path())
+            .collect();
+
+        // Includers of an affected document are affected in turn
+        loop {
+            let before = affected.len();
+            for doc in &index.documents {
+                if doc
+                    .includes
+                    .iter()
+                    .any(|include| affected.contains(Path::new(include)))
+                {
+                    affected.insert(&doc.path);
+                }
+            }
+            if affected.len() == before {
+                break;
+            }
+        }
+
+        let (affected, cached) = index
+            .documents
+            .iter()
+            .partition(|doc| affected.contains(doc.path.as_path()));
+        Self { affected, cached }
+    }
+}
+
+/// Files changed since `manifest` was frozen, relative to the workspace
+/// root: documents not at the version the manifest lists (or not listed at
+/// all), and documents and local sources modified after `frozen_at`.
+pub fn changed_since_manifest(
+    index: &WorkspaceIndex,
+    manifest: &WorkspaceManifest,
+) -> Vec<PathBuf> {
+    let modified = |path: &Path| modified_after(&index.root.join(path), manifest.frozen_at);
+    let mut changed = Vec::new();
+    for doc in &index.documents {
+        let frozen = manifest
+            .documents
+            .iter()
+            .find(|frozen| Path::new(&frozen.path) == doc.path);
+        if frozen.is_none_or(|frozen| Some(&frozen.version_hash) != doc.version_hash.as_ref())
+            || modified(&doc.path)
+        {
+            changed.push(doc.path.clone());
+        }
+        for source in doc.sources() {
+            let source = PathBuf::from(source);
+            if !is_network_source(&source.to_string_lossy()) && modified(&source) {
+                changed.push(source);
+            }
+        }
+    }
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+/// Whether `path` was modified after `time`. A missing file counts as
+/// modified, since it was deleted.
+fn modified_after(path: &Path, time: DateTime<Utc>) -> bool {
+    match fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => DateTime::<Utc>::from(modified) > time,
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,401 @@
+//! Workspace index of documents, the sources they cite and the claims they make.
+
+use crate::error::{Result, RhodiError};
+use crate::markdown::{Section, parse_include_block, parse_tmd, parse_tmd_sections};
+use crate::models::{DocStatus, DocumentLink, TracedDocument};
+use crate::resolver::{FileResolver, SourceResolver, normalize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Directories never scanned for documents.
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// A value a document claims to find in a source.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct IndexedClaim {
+    /// Source as resolved against the workspace root
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    pub expected: String,
+}
+
+impl IndexedClaim {
+    /// A query matches the cited source exactly, or by its trailing path
+    /// components (`data.csv` matches `results/data.csv`).
+    pub fn cites(&self, source: &str) -> bool {
+        if is_remote(&self.source) || is_remote(source) {
+            return self.source == source;
+        }
+        Path::new(&self.source).ends_with(normalize(Path::new(source)))
+    }
+}
+
+/// Metadata of one `.tmd` file in the workspace.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexedDocument {
+    /// Path relative to the workspace root
+    pub path: PathBuf,
+    pub id: Uuid,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub status: DocStatus,
+    pub doc_version: u32,
+    /// Hex-encoded version hash recorded by the last seal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_hash: Option<String>,
+    /// Hex-encoded key the current version claims to be signed with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    pub claims: Vec<IndexedClaim>,
+    /// Included documents, relative to the workspace root. Includes by id
+    /// are listed as `id:<uuid>` unless exactly one indexed document has that id.
+    pub includes: Vec<String>,
+    /// The sealed version this document replaces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<DocumentLink>,
+}
+
+impl IndexedDocument {
+    /// Distinct sources cited by the document's traces.
+    pub fn sources(&self) -> Vec<&str> {
+        let mut sources: Vec<&str> = self.claims.iter().map(|c| c.source.as_str()).collect();
+        sources.sort_unstable();
+        sources.dedup();
+        sources
+    }
+}
+
+/// Filters for [`WorkspaceIndex::search`]. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Documents citing this source (workspace-relative path or URL)
+    pub source: Option<String>,
+    /// Case-insensitive author match
+    pub author: Option<String>,
+    pub status: Option<DocStatus>,
+    /// Case-insensitive substring of the title or any claimed value
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceIndex {
+    pub root: PathBuf,
+    pub documents: Vec<IndexedDocument>,
+    /// Files that could not be parsed, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl WorkspaceIndex {
+    /// Scan `root` recursively for `.tmd` files. Hidden directories and
+    /// build output are skipped.
+    pub fn build(root: &Path) -> Result<Self> {
+        let mut index = Self {
+            root: root.to_path_buf(),
+            documents: Vec::new(),
+            skipped: Vec::new(),
+        };
+        let mut files = Vec::new();
+        collect_documents(root, &mut files)?;
+        files.sort();
+
+        for file in files {
+            let relative = file.strip_prefix(root).unwrap_or(&file).to_path_buf();
+            match index_document(&file, &relative) {
+                Ok(doc) => index.documents.push(doc),
+                Err(e) => index.skipped.push((relative, e.to_string())),
+            }
+        }
+
+        let paths: Vec<(String, String)> = index
+            .documents
+            .iter()
+            .map(|doc| {
+                (
+                    format!("id:{}", doc.id),
+                    doc.path.to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+        // An id shared by several documents names none of them
+        for doc in &mut index.documents {
+            for include in &mut doc.includes {
+                let mut matches = paths.iter().filter(|(id, _)| id == include);
+                if let (Some((_, path)), None) = (matches.next(), matches.next()) {
+                    *include = path.clone();
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    /// The indexed document with `id`. Two files sharing an id are an error,
+    /// since an include by id could mean either.
+    pub fn find(&self, id: Uuid) -> Result<Option<&IndexedDocument>> {
+        let mut matches = self.documents.iter().filter(|doc| doc.id == id);
+        let found = matches.next();
+        if let (Some(first), Some(second)) = (found, matches.next()) {
+            return Err(RhodiError::Resolution(format!(
+                "Document id {} is used by both {} and {}",
+                id,
+                first.path.display(),
+                second.path.display()
+            )));
+        }
+        Ok(found)
+    }
+
+    /// Ids used by more than one document, with the documents using each
+    /// in path order. A copied document or template keeps the original's
+    /// id until `rhodi fix-id` gives it one of its own.
+    pub fn duplicate_ids(&self) -> BTreeMap<Uuid, Vec<&IndexedDocument>> {
+        let mut by_id: BTreeMap<Uuid, Vec<&IndexedDocument>> = BTreeMap::new();
+        for doc in &self.documents {
+            by_id.entry(doc.id).or_default().push(doc);
+        }
+        by_id.retain(|_, docs| docs.len() > 1);
+        by_id
+    }
+
+    /// Read and parse the document with `id`, checking it still carries
+    /// that id.
+    pub fn resolve_document(&self, id: Uuid) -> Result<TracedDocument> {
+        let indexed = self.find(id)?.ok_or_else(|| {
+            RhodiError::Resolution(format!("No document with id {} in the workspace", id))
+        })?;
+        let doc =
+            FileResolver::new(&self.root)?.resolve_document(&indexed.path.to_string_lossy())?;
+        if doc.frontmatter.id != id {
+            return Err(RhodiError::Resolution(format!(
+                "{} no longer has id {}",
+                indexed.path.display(),
+                id
+            )));
+        }
+        Ok(doc)
+    }
+
+    pub fn search(&self, query: &Query) -> Vec<&IndexedDocument> {
+        let author = query.author.as_deref().map(str::to_lowercase);
+        let text = query.text.as_deref().map(str::to_lowercase);
+
+        self.documents
+            .iter()
+            .filter(|doc| query.status.as_ref().is_none_or(|s| &doc.status == s))
+            .filter(|doc| {
+                author.as_deref().is_none_or(|a| {
+                    doc.author
+                        .as_deref()
+                        .is_some_and(|da| da.to_lowercase().contains(a))
+                })
+            })
+            .filter(|doc| {
+                query
+                    .source
+                    .as_deref()
+                    .is_none_or(|s| doc.claims.iter().any(|c| c.cites(s)))
+            })
+            .filter(|doc| {
+                text.as_deref().is_none_or(|t| {
+                    doc.title.to_lowercase().contains(t)
+                        || doc
+                            .claims
+                            .iter()
+                            .any(|c| c.expected.to_lowercase().contains(t))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Documents under `root` with `id`, relative to `root`, in path order.
+/// Only their frontmatter is read, so this is far cheaper than
+/// [`WorkspaceIndex::build`]; files without a readable id are skipped.
+pub fn documents_with_id(root: &Path, id: Uuid) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_documents(root, &mut files)?;
+    files.sort();
+    Ok(files
+        .into_iter()
+        .filter(|file| {
+            fs::read_to_string(file)
+                .map_err(RhodiError::from)
+                .and_then(|content| crate::markdown::frontmatter_id(&content))
+                .is_ok_and(|found| found == id)
+        })
+        .map(|file| file.strip_prefix(root).unwrap_or(&file).to_path_buf())
+        .collect())
+}
+
+/// Finds workspace documents by id for includes, indexing the workspace
+/// the first time a document is looked up.
+#[derive(Debug)]
+pub struct DocumentLocator {
+    root: PathBuf,
+    index: OnceLock<WorkspaceIndex>,
+    /// Documents found by id before the workspace is consulted
+    documents: BTreeMap<Uuid, TracedDocument>,
+}
+
+impl DocumentLocator {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            index: OnceLock::new(),
+            documents: BTreeMap::new(),
+        }
+    }
+
+    /// Serve `doc` for its id instead of the version in the workspace, as
+    /// for a document sealed but not yet written.
+    pub fn with_document(mut self, doc: TracedDocument) -> Self {
+        self.documents.insert(doc.frontmatter.id, doc);
+        self
+    }
+
+    pub fn index(&self) -> Result<&WorkspaceIndex> {
+        if let Some(index) = self.index.get() {
+            return Ok(index);
+        }
+        let index = WorkspaceIndex::build(&self.root)?;
+        Ok(self.index.get_or_init(|| index))
+    }
+
+    pub fn resolve_document(&self, id: Uuid) -> Result<TracedDocument> {
+        if let Some(doc) = self.documents.get(&id) {
+            return Ok(doc.clone());
+        }
+        self.index()?.resolve_document(id)
+    }
+
+    /// The sealed version `link` names and where it was found: a workspace
+    /// document still at that version, or the copy kept in the history
+    /// store when it was sealed.
+    pub fn find_version(&self, link: &DocumentLink) -> Result<Option<(PathBuf, TracedDocument)>> {
+        let current = self.index()?.documents.iter().filter(|indexed| {
+            indexed.id == link.id && indexed.version_hash.as_ref() == Some(&link.version_hash)
+        });
+        let history = self
+            .root
+            .join(crate::config::STATE_DIR_NAME)
+            .join("history")
+            .join(link.id.to_string());
+        let archived = fs::read_dir(&history)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tmd"));
+        let candidates = current
+            .map(|indexed| self.root.join(&indexed.path))
+            .chain(archived);
+        for path in candidates {
+            let Ok(doc) = fs::read_to_string(&path)
+                .map_err(RhodiError::from)
+                .and_then(|content| parse_tmd(&content))
+            else {
+                continue;
+            };
+            if link.names(&doc) {
+                let path = path.strip_prefix(&self.root).unwrap_or(&path).to_path_buf();
+                return Ok(Some((path, doc)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Workspace documents that name `link` in `supersedes`.
+    pub fn superseding(&self, link: &DocumentLink) -> Result<Vec<&IndexedDocument>> {
+        Ok(self
+            .index()?
+            .documents
+            .iter()
+            .filter(|indexed| indexed.supersedes.as_ref() == Some(link))
+            .collect())
+    }
+}
+
+fn is_remote(source: &str) -> bool {
+    source.contains("://")
+}
+
+fn collect_documents(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if entry.file_type()?.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                collect_documents(&path, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "tmd") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn index_document(file: &Path, relative: &Path) -> Result<IndexedDocument> {
+    let doc = parse_tmd(&fs::read_to_string(file)?)?;
+    let doc_dir = relative.parent().unwrap_or(Path::new(""));
+    // Sources and includes are relative to the document; store them
+    // relative to the workspace root so queries can compare them. An
+    // archive entry keeps its `!entry` after the rebased archive
+    let rebase = |path: &str| {
+        if is_remote(path) {
+            return path.to_string();
+        }
+        let (archive, entry) = crate::compression::split_archive_entry(path);
+        let rebased = normalize(&doc_dir.join(archive))
+            .to_string_lossy()
+            .into_owned();
+        match entry {
+            Some(entry) => format!("{}!{}", rebased, entry),
+            None => rebased,
+        }
+    };
+
+    let mut claims = Vec::new();
+    let mut includes = Vec::new();
+    for section in parse_tmd_sections(&doc.body) {
+        match section {
+            Section::Trace(trace) => {
+                let trace = doc.frontmatter.resolve_trace(&trace).unwrap_or(trace);
+                claims.push(IndexedClaim {
+                    source: rebase(&trace.source),
+                    selector: trace.selector,
+                    expected: trace.expected,
+                })
+            }
+            Section::Include(block) => {
+                if let Ok(include) = parse_include_block(&block) {
+                    includes.push(match include.id {
+                        Some(_) => include.target(),
+                        None => rebase(&include.path),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let fm = doc.frontmatter;
+    Ok(IndexedDocument {
+        path: relative.to_path_buf(),
+        id: fm.id,
+        title: fm.title,
+        author: fm.author.map(|author| author.name),
+        status: fm.doc_status,
+        doc_version: fm.doc_version,
+        version_hash: fm.version_hash.map(hex::encode),
+        public_key: fm.public_key.filter(|_| fm.signature.is_some()),
+        claims,
+        includes,
+        supersedes: fm.supersedes,
+    })
+}
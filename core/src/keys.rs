@@ -0,0 +1,395 @@
+//! The user's signing keys, one JSON file each.
+//!
+//! Keys live in the OS configuration directory (`keys-store` feature) or in
+//! any directory given to [`KeyManager::at`].
+
+use crate::error::{Result, RhodiError};
+use crate::shamir::{self, Share};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::SigningKey;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "keys-store")]
+const KEY_DIR_NAME: &str = "keys";
+
+#[derive(Serialize, Deserialize)]
+pub struct KeyFile {
+    pub name: String,
+    pub public_key: String,
+    signing_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// What `rhodi keys list` shows about a stored key.
+#[derive(Debug, Clone)]
+pub struct KeyInfo {
+    pub name: String,
+    pub public_key: String,
+    /// Recorded at generation; older key files fall back to the file's timestamps
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// One share of a key backup, written as a short text file a custodian
+/// can print and later type back in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyShare {
+    /// Name the key was stored under
+    pub name: String,
+    /// Hex-encoded public key of the shared key
+    pub public_key: String,
+    /// Random id of the backup, so shares of different backups are not mixed
+    pub backup: String,
+    pub threshold: u8,
+    /// Number of shares in the backup
+    pub shares: u8,
+    pub share: Share,
+}
+
+impl KeyShare {
+    /// The share as printable text. The `check` line catches typos in the
+    /// share data when it is typed back in.
+    pub fn to_text(&self) -> String {
+        let data = hex::encode(&self.share.data);
+        let groups: Vec<&str> = data
+            .as_bytes()
+            .chunks(4)
+            .map(|group| std::str::from_utf8(group).unwrap_or_default())
+            .collect();
+        format!(
+            "# Rhodi signing key share {index} of {shares}. Any {threshold} shares of backup\n\
+             # {backup} restore the key with 'rhodi keys restore'; fewer reveal nothing.\n\
+             # Keep this share apart from the others.\n\
+             key: {name}\n\
+             public_key: {public_key}\n\
+             backup: {backup}\n\
+             threshold: {threshold}\n\
+             share: {index} of {shares}\n\
+             data: {data}\n\
+             check: {check}\n",
+            index = self.share.index,
+            shares = self.shares,
+            threshold = self.threshold,
+            backup = self.backup,
+            name = self.name,
+            public_key = self.public_key,
+            data = groups.join(" "),
+            check = self.check(),
+        )
+    }
+
+    /// Parse a share written by [`KeyShare::to_text`]. Comment lines and
+    /// spaces inside the data are ignored.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = |reason: String| RhodiError::Format(format!("Invalid key share: {}", reason));
+        let mut fields = BTreeMap::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("unexpected line '{}'", line)))?;
+            fields.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .cloned()
+                .ok_or_else(|| invalid(format!("missing {}", key)))
+        };
+        let number = |key: &str, value: &str| {
+            value
+                .trim()
+                .parse::<u8>()
+                .map_err(|_| invalid(format!("{} '{}' is not a number up to 255", key, value)))
+        };
+
+        let share = field("share")?;
+        let (index, shares) = share
+            .split_once(" of ")
+            .ok_or_else(|| invalid(format!("share '{}' is not written 'N of M'", share)))?;
+        let data: String = field("data")?.split_whitespace().collect();
+        let data = hex::decode(&data).map_err(|e| invalid(format!("data is not hex: {}", e)))?;
+        let share = KeyShare {
+            name: field("key")?,
+            public_key: field("public_key")?,
+            backup: field("backup")?,
+            threshold: number("threshold", &field("threshold")?)?,
+            shares: number("share count", shares)?,
+            share: Share {
+                index: number("share index", index)?,
+                data,
+            },
+        };
+        if share.check() != field("check")?.to_ascii_lowercase() {
+            return Err(invalid(format!(
+                "share {} of backup {} does not match its check; look for a typo in data",
+                share.share.index, share.backup
+            )));
+        }
+        Ok(share)
+    }
+
+    fn check(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.backup.as_bytes());
+        hasher.update([self.share.index]);
+        hasher.update(&self.share.data);
+        hex::encode(&hasher.finalize()[..4])
+    }
+}
+
+pub struct KeyManager {
+    keys_dir: PathBuf,
+}
+
+impl KeyManager {
+    #[cfg(feature = "keys-store")]
+    pub fn new() -> Result<Self> {
+        let proj_dirs = directories::ProjectDirs::from("com", "rhodi", "rhodi")
+            .ok_or_else(|| RhodiError::Resolution("Could not determine config directory".into()))?;
+
+        Self::at(proj_dirs.config_dir().join(KEY_DIR_NAME))
+    }
+
+    /// A key manager over an explicit directory.
+    pub fn at(keys_dir: PathBuf) -> Result<Self> {
+        if !keys_dir.exists() {
+            fs::create_dir_all(&keys_dir)?;
+        }
+
+        Ok(Self { keys_dir })
+    }
+
+    fn key_path(&self, name: &str) -> PathBuf {
+        self.keys_dir.join(format!("{}.json", name))
+    }
+
+    pub fn get_key(&self, name: &str) -> Result<SigningKey> {
+        let key_path = self.keys_dir.join(format!("{}.json", name));
+
+        if !key_path.exists() {
+            return Err(RhodiError::Resolution(format!(
+                "Key '{}' not found. Run 'rhodi keygen --name {}' to create it.",
+                name, name
+            )));
+        }
+
+        let content = fs::read_to_string(&key_path)?;
+        let key_file: KeyFile = serde_json::from_str(&content)
+            .map_err(|e| RhodiError::Format(format!("Invalid key file: {}", e)))?;
+
+        let sk_bytes = hex::decode(&key_file.signing_key)
+            .map_err(|e| RhodiError::Crypto(format!("Invalid hex in key file: {}", e)))?;
+
+        let sk_bytes: [u8; 32] = sk_bytes
+            .try_into()
+            .map_err(|_| RhodiError::Crypto("Invalid key length".into()))?;
+
+        Ok(SigningKey::from_bytes(&sk_bytes))
+    }
+
+    pub fn get_public_key_hex(&self, name: &str) -> Result<String> {
+        let key_path = self.keys_dir.join(format!("{}.json", name));
+
+        if !key_path.exists() {
+            return Err(RhodiError::Resolution(format!("Key '{}' not found", name)));
+        }
+
+        let content = fs::read_to_string(&key_path)?;
+        let key_file: KeyFile = serde_json::from_str(&content)
+            .map_err(|e| RhodiError::Format(format!("Invalid key file: {}", e)))?;
+
+        Ok(key_file.public_key)
+    }
+
+    /// Generate and store a new key under `name`, which must not exist yet.
+    pub fn generate(&self, name: &str) -> Result<KeyFile> {
+        let key_path = self.key_path(name);
+        if key_path.exists() {
+            return Err(RhodiError::Resolution(format!(
+                "Key '{}' already exists. Use a different name or delete the existing key.",
+                name
+            )));
+        }
+
+        let mut csprng = rand::rngs::OsRng;
+        let signing_key = SigningKey::generate(&mut csprng);
+        self.store(&key_path, name, &signing_key)
+    }
+
+    /// Split the key `name` into `shares` printable shares, any `threshold`
+    /// of which restore it with [`KeyManager::restore`].
+    pub fn backup(&self, name: &str, shares: u8, threshold: u8) -> Result<Vec<KeyShare>> {
+        let signing_key = self.get_key(name)?;
+        let mut backup = [0u8; 8];
+        rand::rngs::OsRng.fill_bytes(&mut backup);
+        let backup = hex::encode(backup);
+        let public_key = hex::encode(signing_key.verifying_key().as_bytes());
+
+        Ok(shamir::split(&signing_key.to_bytes(), threshold, shares)?
+            .into_iter()
+            .map(|share| KeyShare {
+                name: name.to_string(),
+                public_key: public_key.clone(),
+                backup: backup.clone(),
+                threshold,
+                shares,
+                share,
+            })
+            .collect())
+    }
+
+    /// Rebuild a key from shares of one backup and store it under `name`,
+    /// or the name it was backed up under. The rebuilt key must match the
+    /// public key the shares record.
+    pub fn restore(&self, shares: &[KeyShare], name: Option<&str>) -> Result<KeyFile> {
+        let Some(first) = shares.first() else {
+            return Err(RhodiError::Crypto("No shares given".into()));
+        };
+        if let Some(other) = shares
+            .iter()
+            .find(|s| s.backup != first.backup || s.public_key != first.public_key)
+        {
+            return Err(RhodiError::Crypto(format!(
+                "Share {} belongs to backup {}, not {}; use shares of one backup",
+                other.share.index, other.backup, first.backup
+            )));
+        }
+        if shares.len() < first.threshold as usize {
+            return Err(RhodiError::Crypto(format!(
+                "{} share(s) given; backup {} needs {}",
+                shares.len(),
+                first.backup,
+                first.threshold
+            )));
+        }
+
+        let parts: Vec<Share> = shares.iter().map(|s| s.share.clone()).collect();
+        let secret: [u8; 32] = shamir::combine(&parts)?
+            .try_into()
+            .map_err(|_| RhodiError::Crypto("Invalid key length".into()))?;
+        let signing_key = SigningKey::from_bytes(&secret);
+        if hex::encode(signing_key.verifying_key().as_bytes()) != first.public_key {
+            return Err(RhodiError::Crypto(format!(
+                "The shares do not rebuild key {}",
+                first.public_key
+            )));
+        }
+
+        let name = name.unwrap_or(&first.name);
+        let key_path = self.key_path(name);
+        if key_path.exists() {
+            return Err(RhodiError::Resolution(format!(
+                "Key '{}' already exists. Restore it under another name with --name.",
+                name
+            )));
+        }
+        self.store(&key_path, name, &signing_key)
+    }
+
+    fn store(&self, key_path: &PathBuf, name: &str, signing_key: &SigningKey) -> Result<KeyFile> {
+        let key_file = KeyFile {
+            name: name.to_string(),
+            public_key: hex::encode(signing_key.verifying_key().as_bytes()),
+            signing_key: hex::encode(signing_key.to_bytes()),
+            created_at: Some(Utc::now()),
+        };
+
+        let content = serde_json::to_string_pretty(&key_file)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to serialize key: {}", e)))?;
+        fs::write(key_path, content)?;
+        Self::set_key_permissions(key_path)?;
+        Ok(key_file)
+    }
+
+    pub fn list_keys(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+
+        for entry in fs::read_dir(&self.keys_dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("json")
+                && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            {
+                keys.push(stem.to_string());
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Every stored key with its public key and creation date, sorted by name.
+    pub fn key_infos(&self) -> Result<Vec<KeyInfo>> {
+        let mut infos = Vec::new();
+        for name in self.list_keys()? {
+            let path = self.key_path(&name);
+            let content = fs::read_to_string(&path)?;
+            let key_file: KeyFile = serde_json::from_str(&content)
+                .map_err(|e| RhodiError::Format(format!("Invalid key file {}: {}", name, e)))?;
+
+            let created_at = key_file.created_at.or_else(|| {
+                let metadata = fs::metadata(&path).ok()?;
+                let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+                Some(time.into())
+            });
+
+            infos.push(KeyInfo {
+                name,
+                public_key: key_file.public_key,
+                created_at,
+            });
+        }
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(infos)
+    }
+
+    /// Remove a key. With `shred`, the file is first overwritten with random
+    /// bytes and synced, so the secret does not linger in the freed blocks.
+    /// (Copy-on-write and journaling filesystems may still keep old copies.)
+    pub fn delete_key(&self, name: &str, shred: bool) -> Result<()> {
+        if name.contains(['/', '\\']) || name.contains("..") {
+            return Err(RhodiError::Resolution(format!(
+                "Invalid key name '{}'",
+                name
+            )));
+        }
+        let path = self.key_path(name);
+        if !path.exists() {
+            return Err(RhodiError::Resolution(format!("Key '{}' not found", name)));
+        }
+
+        if shred {
+            shred_file(&path)?;
+        }
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    pub fn set_key_permissions(path: &PathBuf) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+fn shred_file(path: &Path) -> Result<()> {
+    let len = fs::metadata(path)?.len() as usize;
+    let mut noise = vec![0u8; len];
+    rand::rngs::OsRng.fill_bytes(&mut noise);
+
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    file.write_all(&noise)?;
+    file.sync_all()?;
+    Ok(())
+}
@@ -2,16 +2,60 @@
 //!
 //! This library provides the fundamental structures and functionalities for creating and managing traced documents.
 
+pub mod agent;
+pub mod artifact;
+pub mod attestation;
+pub mod badge;
+pub mod blocks;
+pub mod bundle;
+pub mod cache;
+pub mod canonical;
+pub mod certificate;
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod comments;
+pub mod comparison;
 pub mod compiler;
+pub mod compression;
+pub mod config;
 pub mod crypto;
+pub mod discovery;
+pub mod encoding;
 pub mod error;
 pub mod extraction;
+pub mod fingerprint;
+pub mod freeze;
+pub mod history;
+pub mod identity;
+pub mod incremental;
+pub mod index;
+pub mod keys;
+pub mod lock;
+pub mod lsp;
 pub mod markdown;
+pub mod merkle;
 pub mod models;
+pub mod notary;
+pub mod openpgp;
+pub mod opentimestamps;
+pub mod redaction;
+pub mod registry;
+pub mod report;
 pub mod resolver;
+pub mod review;
+pub mod revocation;
+pub mod sandbox;
+pub mod selector;
+pub mod server;
+pub mod shamir;
+pub mod stats;
+pub mod suggest;
+pub mod supersession;
+pub mod telemetry;
+pub mod testkit;
 pub mod version;
 
+pub use compiler::{CompilationReport, verify_document};
 pub use crypto::KeyPair;
 pub use error::{Result, RhodiError};
 pub use markdown::{parse_tmd, parse_trace_block};
@@ -22,6 +66,13 @@ pub use version::{get_version_status, get_latest_version, is_version_known, Vers
 mod tests {
     use super::*;
     use crate::models::{DocStatus, TraceBlock};
+
+    /// Create an empty, uniquely named directory for a test workspace.
+    fn temp_workspace(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rhodi-{}-{}", name, uuid::Uuid::now_v7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
     #[test]
     fn create_traced_document() {
         let doc = TracedDocument::new("Test Title", "This is the body of the document.")
@@ -88,7 +139,7 @@ agent_metadata:
             .unwrap();
 
         // 2. Seal the document
-        doc = doc.seal(&keypair);
+        doc = doc.seal(&keypair).unwrap();
 
         assert_eq!(doc.frontmatter.doc_status, DocStatus::Published);
         assert!(doc.frontmatter.version_hash.is_some());
@@ -120,6 +171,7 @@ agent_metadata:
             context: None,
             confidence: None,
             agent_metadata: None,
+            ..Default::default()
         };
 
         let yaml = serde_norway::to_string(&trace).unwrap();
@@ -245,9 +297,9 @@ agent_metadata:
 
         let doc_no_extra = TracedDocument::new("Real Title", "body");
 
-        let hash1 = doc1.compute_version_hash();
-        let hash2 = doc2.compute_version_hash();
-        let hash_no_extra = doc_no_extra.compute_version_hash();
+        let hash1 = doc1.compute_version_hash().unwrap();
+        let hash2 = doc2.compute_version_hash().unwrap();
+        let hash_no_extra = doc_no_extra.compute_version_hash().unwrap();
 
         // Extra fields with the same standard field name should still produce
         // different hashes from each other (they are distinct extra values)
@@ -263,7 +315,7 @@ agent_metadata:
         // can be parsed back correctly
         let fm = FrontMatter {
             title: "Roundtrip Test".to_string(),
-            author: Some("Author".to_string()),
+            author: Some("Author".into()),
             doc_status: DocStatus::Draft,
             ..Default::default()
         };
@@ -277,4 +329,5415 @@ agent_metadata:
         assert_eq!(doc.frontmatter.doc_status, DocStatus::Draft);
         assert_eq!(doc.body, "Body text here.");
     }
+
+    #[test]
+    fn test_external_seal_roundtrip() {
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("External", "Signed by a remote service.");
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+
+        let pending = doc.prepare_seal().unwrap();
+        let hash = pending.frontmatter.version_hash.unwrap();
+        assert!(pending.frontmatter.signature.is_none());
+
+        // A signature from the wrong key is rejected
+        let other = KeyPair::generate();
+        assert!(pending.clone().complete_seal(other.sign(&hash)).is_err());
+
+        let sealed = pending.complete_seal(keypair.sign(&hash)).unwrap();
+        assert_eq!(sealed.frontmatter.doc_status, DocStatus::Published);
+        sealed.verify(&keypair.verifying_key).unwrap();
+    }
+
+    #[test]
+    fn test_quote_block_provenance() {
+        use crate::compiler::Compiler;
+        use crate::markdown::{Section, parse_tmd_sections, render_tmd};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("quote");
+        let keypair = KeyPair::generate();
+        let mut source = TracedDocument::new("Source", "Revenue grew by 12% in Q3.\nOther text.");
+        source.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let source = source.seal(&keypair).unwrap();
+        std::fs::write(dir.join("source.tmd"), render_tmd(&source).unwrap()).unwrap();
+        let version = hex::encode(source.frontmatter.version_hash.unwrap());
+
+        let quoting = |excerpt: &str, version: &str| {
+            let mut doc = TracedDocument::new(
+                "Citing",
+                &format!(
+                    "As reported:\n\n```quote\nsource_doc: source.tmd\nversion_hash: {}\nexcerpt: \"{}\"\n```\n",
+                    version, excerpt
+                ),
+            );
+            doc.update_all_traces(&dir).unwrap();
+            doc
+        };
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+
+        let doc = quoting("Revenue grew by 12% in Q3.", &version);
+        let quote = parse_tmd_sections(&doc.body)
+            .into_iter()
+            .find_map(|s| match s {
+                Section::Quote(q) => Some(q),
+                _ => None,
+            })
+            .unwrap();
+        assert!(quote.excerpt_hash.is_some());
+        let report = compiler.verify(&doc).unwrap();
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let doc = quoting("Revenue grew by 15% in Q3.", &version).set_status(DocStatus::Published);
+        let report = compiler.verify(&doc).unwrap();
+        assert!(!report.errors.is_empty());
+
+        let doc = quoting("Revenue grew by 12% in Q3.", &"00".repeat(32));
+        let report = compiler.verify(&doc).unwrap();
+        assert_eq!(report.warnings.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_numeric_tolerance_matching() {
+        use crate::comparison::values_match;
+        use crate::models::Tolerance;
+
+        // Exact string comparison stays the default
+        assert!(values_match("0.85", " 0.85 ", None));
+        assert!(!values_match("0.85", "0.8500001", None));
+
+        let abs = Tolerance {
+            absolute: Some(1e-4),
+            ..Default::default()
+        };
+        assert!(values_match("0.85", "0.8500001", Some(&abs)));
+        assert!(!values_match("0.85", "0.86", Some(&abs)));
+
+        let rel = Tolerance {
+            relative: Some(0.01),
+            ..Default::default()
+        };
+        assert!(values_match("1000", "1009", Some(&rel)));
+        assert!(!values_match("1000", "1011", Some(&rel)));
+
+        let pct = Tolerance {
+            normalize_percent: true,
+            ..Default::default()
+        };
+        assert!(values_match("85%", "0.85", Some(&pct)));
+        assert!(!values_match("85%", "0.85", Some(&abs)));
+        assert!(!values_match("high", "0.85", Some(&pct)));
+
+        let trace = parse_trace_block(
+            "```trace\nsource: a.csv\nexpected: \"85%\"\ntolerance:\n  absolute: 0.001\n  normalize_percent: true\n```",
+        )
+        .unwrap();
+        assert_eq!(trace.tolerance.unwrap().absolute, Some(0.001));
+    }
+
+    #[test]
+    fn test_registry_publish_and_anchor_check() {
+        use crate::registry::{RegistryClient, RegistryRecord};
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        // Minimal in-memory registry: stores the last POSTed record and serves it back.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut stored: Option<String> = None;
+            for _ in 0..3 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(v) = header.to_lowercase().strip_prefix("content-length:") {
+                        content_length = v.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let response = if request_line.starts_with("POST") {
+                    stored = Some(String::from_utf8(body).unwrap());
+                    "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else {
+                    match &stored {
+                        Some(record) if !request_line.contains("/versions/00") => format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                            record.len(),
+                            record
+                        ),
+                        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                    }
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("Anchored", "Body");
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let doc = doc.seal(&keypair).unwrap();
+
+        let client = RegistryClient::new(&base);
+        client
+            .publish(&RegistryRecord::from_document(&doc).unwrap())
+            .unwrap();
+        client.verify_anchored(&doc).unwrap();
+        assert!(
+            client
+                .lookup(&doc.frontmatter.id, &"00".repeat(32))
+                .unwrap()
+                .is_none()
+        );
+
+        server.join().unwrap();
+
+        // Unsealed documents cannot be published
+        assert!(RegistryRecord::from_document(&TracedDocument::new("Draft", "x")).is_err());
+    }
+
+    #[test]
+    fn test_extraction_transforms() {
+        use crate::extraction::{Transform, apply_transforms};
+
+        let chain = vec![
+            "trim".to_string(),
+            "strip_commas".to_string(),
+            "round:2".to_string(),
+        ];
+        assert_eq!(
+            apply_transforms("  1,234.5678 ", &chain).unwrap(),
+            "1234.57"
+        );
+        assert_eq!(
+            apply_transforms(
+                "Hello   World",
+                &["collapse_whitespace".into(), "lowercase".into()]
+            )
+            .unwrap(),
+            "hello world"
+        );
+        assert_eq!(
+            apply_transforms("85 %", &["strip_percent".into()]).unwrap(),
+            "85"
+        );
+        assert_eq!(Transform::parse("round:3").unwrap(), Transform::Round(3));
+        assert!(Transform::parse("round:4000000000").is_err());
+        assert!(Transform::parse("explode").is_err());
+        assert!(apply_transforms("abc", &["round:1".into()]).is_err());
+
+        let trace = parse_trace_block(
+            "```trace\nsource: a.txt\nexpected: \"42.00\"\nselector: \"value: ([0-9.]+)\"\ntransforms: [trim, \"round:2\"]\n```",
+        )
+        .unwrap();
+        assert_eq!(trace.transforms, vec!["trim", "round:2"]);
+    }
+
+    #[test]
+    fn test_include_block_parsing() {
+        use crate::markdown::parse_include_block;
+
+        let include = parse_include_block("```include\npath: ./sections/intro.tmd\n```").unwrap();
+        assert_eq!(include.path, "./sections/intro.tmd");
+        assert!(include.integrity.is_none());
+
+        assert!(parse_include_block("```include\nnot: [valid\n```").is_err());
+        assert!(parse_include_block("```trace\npath: a.tmd\n```").is_err());
+    }
+
+    #[test]
+    fn test_key_discovery_did_key() {
+        use crate::compiler::Compiler;
+        use crate::discovery::{check_key_uri, did_key_from_public_key, public_key_from_did_key};
+        use crate::resolver::FileResolver;
+
+        let keypair = KeyPair::generate();
+        let did = did_key_from_public_key(&keypair.verifying_key);
+        assert!(did.starts_with("did:key:z6Mk"));
+        assert_eq!(
+            public_key_from_did_key(&did).unwrap(),
+            keypair.verifying_key
+        );
+        assert!(public_key_from_did_key("did:web:example.org").is_err());
+
+        let other = KeyPair::generate();
+        assert!(check_key_uri(&did, &keypair.verifying_key).is_ok());
+        assert!(check_key_uri(&did, &other.verifying_key).is_err());
+
+        let dir = temp_workspace("did");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+
+        // Key discovered from the URI alone
+        let mut doc = TracedDocument::new("DID", "Body");
+        doc.frontmatter.key_uri = Some(did.clone());
+        let doc = doc.seal(&keypair).unwrap();
+        let report = compiler.verify(&doc).unwrap();
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+
+        // Embedded key that the URI does not vouch for
+        let mut doc = TracedDocument::new("DID", "Body");
+        doc.frontmatter.public_key = Some(hex::encode(other.verifying_key.as_bytes()));
+        doc.frontmatter.key_uri = Some(did);
+        let doc = doc.seal(&other).unwrap();
+        let report = compiler.verify(&doc).unwrap();
+        assert_eq!(report.errors.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_workspace_config() {
+        use crate::config::{CONFIG_FILE_NAME, Workspace, WorkspaceConfig, update_config};
+        use crate::resolver::{FileResolver, SourceResolver};
+
+        let root = temp_workspace("config");
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::create_dir_all(root.join("private")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("data/a.txt"), "ok").unwrap();
+        std::fs::write(root.join("private/b.txt"), "secret").unwrap();
+
+        let config_path = root.join(CONFIG_FILE_NAME);
+        update_config(&config_path, |c| c.set("keys.default", "team")).unwrap();
+        update_config(&config_path, |c| c.set("verify.strict", "true")).unwrap();
+        update_config(&config_path, |c| c.set("resolver.allow", r#"["data"]"#)).unwrap();
+        update_config(&config_path, |c| c.set("canonicalization.profile", "1.0")).unwrap();
+        assert!(WorkspaceConfig::default().set("keys.unknown", "x").is_err());
+        assert!(
+            WorkspaceConfig::default()
+                .set("verify.strict", "maybe")
+                .is_err()
+        );
+
+        // Discovery walks up from nested directories
+        let workspace = Workspace::discover(&root.join("docs")).unwrap();
+        assert_eq!(workspace.config.keys.default.as_deref(), Some("team"));
+        assert!(workspace.config.verify.strict);
+        assert_eq!(
+            workspace
+                .config
+                .get("canonicalization.profile")
+                .unwrap()
+                .as_deref(),
+            Some("1.0")
+        );
+
+        let resolver = workspace.resolver(&root).unwrap();
+        assert!(resolver.resolve_bytes("data/a.txt").is_ok());
+        assert!(matches!(
+            resolver.resolve_bytes("private/b.txt"),
+            Err(RhodiError::Security(_))
+        ));
+        assert!(
+            FileResolver::new(&root)
+                .unwrap()
+                .resolve_bytes("private/b.txt")
+                .is_ok()
+        );
+        assert!(!crate::lock::FileLock::lock_path(&config_path).exists());
+    }
+
+    #[test]
+    fn test_file_lock_and_atomic_write() {
+        use crate::lock::{FileLock, write_atomic};
+        use std::time::Duration;
+
+        let dir = temp_workspace("file-lock");
+        let doc = dir.join("doc.tmd");
+        std::fs::write(&doc, "old").unwrap();
+        let leftovers = || {
+            std::fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .filter(|name| name.contains(".tmp"))
+                .collect::<Vec<_>>()
+        };
+
+        // A second writer waits for the lock, then gives up
+        let held = FileLock::acquire(&doc, Duration::from_millis(100)).unwrap();
+        let error = FileLock::acquire(&doc, Duration::from_millis(100)).unwrap_err();
+        assert!(error.to_string().contains("is locked by another process"));
+        drop(held);
+        assert!(!FileLock::lock_path(&doc).exists());
+        assert!(FileLock::acquire(&doc, Duration::from_millis(100)).is_ok());
+
+        // Writes replace the file whole and leave no temp file behind,
+        // whether they succeed or not
+        write_atomic(&doc, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&doc).unwrap(), "new");
+        assert!(leftovers().is_empty(), "{:?}", leftovers());
+        let occupied = dir.join("occupied");
+        std::fs::create_dir_all(occupied.join("inside")).unwrap();
+        assert!(write_atomic(&occupied, "new").is_err());
+        assert!(occupied.is_dir());
+        assert!(leftovers().is_empty(), "{:?}", leftovers());
+    }
+
+    #[test]
+    fn test_verification_attestation() {
+        use crate::attestation::{self, Attestation};
+
+        let keypair = KeyPair::generate();
+        let doc = TracedDocument::new("Attested", "Body")
+            .seal(&keypair)
+            .unwrap();
+
+        let auditor = KeyPair::generate();
+        let attestation = Attestation::create(&doc, &auditor, vec![]).unwrap();
+        assert!(attestation.verify().is_ok());
+        assert!(attestation.covers(&doc));
+
+        let mut forged = attestation.clone();
+        forged.verified_at += chrono::Duration::days(1);
+        assert!(forged.verify().is_err());
+
+        let log = temp_workspace("attest").join("doc.tmd.attestations");
+        attestation::append(&log, &attestation).unwrap();
+        attestation::append(&log, &attestation).unwrap();
+        assert_eq!(
+            attestation::read_all(&log).unwrap(),
+            vec![attestation.clone(), attestation]
+        );
+
+        let draft = TracedDocument::new("Draft", "Body");
+        assert!(Attestation::create(&draft, &auditor, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_yaml_and_toml_extractors() {
+        use crate::extraction::get_extractor;
+
+        let yaml = b"metrics:\n  accuracy: 0.93\nruns:\n  - loss: 1.5\n  - loss: 0.7\n";
+        let yaml_extractor = get_extractor("yaml").unwrap();
+        assert_eq!(
+            yaml_extractor.extract(yaml, ".metrics.accuracy").unwrap(),
+            "0.93"
+        );
+        assert_eq!(yaml_extractor.extract(yaml, "runs[1].loss").unwrap(), "0.7");
+        assert!(yaml_extractor.extract(yaml, ".metrics.recall").is_err());
+
+        let toml = b"[metrics]\naccuracy = 0.93\nname = \"baseline\"\n";
+        let toml_extractor = get_extractor("toml").unwrap();
+        assert_eq!(
+            toml_extractor.extract(toml, ".metrics.accuracy").unwrap(),
+            "0.93"
+        );
+        assert_eq!(
+            toml_extractor.extract(toml, ".metrics.name").unwrap(),
+            "baseline"
+        );
+        assert!(toml_extractor.extract(b"not = [toml", ".x").is_err());
+    }
+
+    #[test]
+    fn test_custom_block_handler() {
+        use crate::blocks::BlockHandler;
+        use crate::compiler::Compiler;
+        use crate::markdown::parse_fenced_yaml;
+        use crate::resolver::{FileResolver, SourceResolver};
+
+        #[derive(serde::Deserialize)]
+        struct Approval {
+            approved_by: String,
+        }
+
+        struct ApprovalHandler;
+
+        impl BlockHandler for ApprovalHandler {
+            fn kind(&self) -> &str {
+                "approval"
+            }
+
+            fn verify(
+                &self,
+                block: &str,
+                _doc: &TracedDocument,
+                _resolver: &dyn SourceResolver,
+            ) -> Result<()> {
+                let approval: Approval = parse_fenced_yaml(block, "approval")?;
+                if approval.approved_by.is_empty() {
+                    return Err(RhodiError::Verification("Approval is unsigned".into()));
+                }
+                Ok(())
+            }
+        }
+
+        let body =
+            "Intro\n\n```approval\napproved_by: \"\"\n```\n\n```approvals\nignored: true\n```\n";
+        let doc = TracedDocument::new("Custom", body);
+        let resolver = FileResolver::new(std::env::temp_dir()).unwrap();
+
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(report.warnings.is_empty());
+
+        let compiler = Compiler::new(&resolver).with_handler(ApprovalHandler);
+        let report = compiler.verify(&doc).unwrap();
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].starts_with("approval warning"));
+    }
+
+    #[test]
+    fn test_tamper_diagnosis() {
+        let keypair = KeyPair::generate();
+        let doc = TracedDocument::new("Original", "Body text.")
+            .author("Alice")
+            .seal(&keypair)
+            .unwrap();
+        assert_eq!(doc.diagnose_tamper(), Some(vec![]));
+
+        let mut tampered = doc.clone();
+        tampered.frontmatter.author = Some("Mallory".into());
+        tampered.body = "Body text, edited.".into();
+        assert!(tampered.verify(&keypair.verifying_key).is_err());
+        assert_eq!(
+            tampered.diagnose_tamper(),
+            Some(vec!["body".to_string(), "author".to_string()])
+        );
+
+        // The manifest survives a round trip through the file format
+        let reparsed = parse_tmd(&crate::markdown::render_tmd(&doc).unwrap()).unwrap();
+        assert!(reparsed.verify(&keypair.verifying_key).is_ok());
+        assert_eq!(reparsed.diagnose_tamper(), Some(vec![]));
+
+        let unsealed = TracedDocument::new("Draft", "Body");
+        assert_eq!(unsealed.diagnose_tamper(), None);
+    }
+
+    #[test]
+    fn test_protocol_migration() {
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("Old", "Line one   \r\nLine two");
+        doc.frontmatter.protocol_version = "1.0".into();
+        let doc = doc.seal(&keypair).unwrap();
+        let sealed_hash = doc.frontmatter.version_hash;
+        assert_eq!(doc.frontmatter.protocol_version, "1.0");
+
+        let (migrated, changes) = doc.clone().migrate("2.0").unwrap();
+        assert_eq!(migrated.frontmatter.protocol_version, "2.0");
+        assert_eq!(migrated.body, "Line one\nLine two");
+        assert_eq!(migrated.frontmatter.version_hash, None);
+        assert_eq!(migrated.frontmatter.prev_version_hash, sealed_hash);
+        assert_eq!(migrated.frontmatter.doc_status, DocStatus::Draft);
+        assert!(changes.iter().any(|c| c == "body: re-canonicalized"));
+
+        let resealed = migrated.seal(&keypair).unwrap();
+        assert!(resealed.verify(&keypair.verifying_key).is_ok());
+
+        let (_, changes) = resealed.migrate("2.0").unwrap();
+        assert!(changes.is_empty());
+        assert!(doc.clone().migrate("9.9").is_err());
+        let (v2, _) = doc.migrate("2.0").unwrap();
+        assert!(v2.migrate("1.0").is_err());
+    }
+
+    #[test]
+    fn test_trace_annotations_excluded_from_hash() {
+        use crate::compiler::Compiler;
+        use crate::markdown::{Section, annotate_trace_blocks, parse_tmd_sections};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("annotate");
+        std::fs::write(dir.join("data.txt"), "value: 42").unwrap();
+
+        let body = "Intro\n\n```trace\nsource: data.txt\nexpected: \"42\"\nselector: \"value: ([0-9]+)\"\n```\n\nOutro\n";
+        let keypair = KeyPair::generate();
+        let doc = TracedDocument::new("Annotated", body)
+            .seal(&keypair)
+            .unwrap();
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let now = chrono::Utc::now();
+
+        let mut annotated = doc.clone();
+        annotated.body =
+            annotate_trace_blocks(&doc.body, now, |t| compiler.check_trace(t)).unwrap();
+        assert_ne!(annotated.body, doc.body);
+        assert!(annotated.verify(&keypair.verifying_key).is_ok());
+
+        let trace = parse_tmd_sections(&annotated.body)
+            .into_iter()
+            .find_map(|s| match s {
+                Section::Trace(t) => Some(t),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(trace.verified_at, Some(now));
+        let result = trace.last_result.unwrap();
+        assert!(result.passed);
+        assert_eq!(result.actual.as_deref(), Some("42"));
+
+        // Re-annotating replaces the previous result rather than appending
+        std::fs::write(dir.join("data.txt"), "value: 7").unwrap();
+        let again =
+            annotate_trace_blocks(&annotated.body, now, |t| compiler.check_trace(t)).unwrap();
+        assert_eq!(again.matches("last_result:").count(), 1);
+        assert!(again.contains("passed: false"));
+        assert!(again.ends_with("Outro"));
+
+        // Before 2.3 annotations are body text: a 1.0 document written with
+        // them keeps the hash it was sealed with
+        let tmd = "---\nid: 01900000-0000-7000-8000-000000000001\ntitle: Annotated Report\ncreated_at: 2025-01-01T00:00:00Z\ndoc_status: draft\ndoc_version: 1\nprotocol_version: '1.0'\n---\n\nSales reached 1,204 units.\n\n```trace\nsource: data/sales.csv\nselector: '/Total: ([\\d,]+)/'\nexpected: \"1,204\"\nverified_at: 2025-06-01T00:00:00Z\nlast_result:\n  passed: true\n```\n";
+        let old = parse_tmd(tmd).unwrap();
+        assert_eq!(
+            hex::encode(old.compute_version_hash().unwrap()),
+            "3189640c95a43dbb6ddabd2878d69993bdc6721d07b45788837a5e882ca9e6f9"
+        );
+        let mut stripped = old.clone();
+        stripped.body = crate::markdown::strip_trace_annotations(&old.body).into_owned();
+        assert_ne!(
+            stripped.compute_version_hash().unwrap(),
+            old.compute_version_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_include_cycle_detected_across_spellings() {
+        use crate::compiler::Compiler;
+        use crate::error::SecurityError;
+        use crate::markdown::render_tmd;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("include-cycle");
+        let write = |name: &str, doc: &TracedDocument| {
+            std::fs::write(dir.join(name), render_tmd(doc).unwrap()).unwrap();
+        };
+        let is_circular = |res: Result<crate::compiler::CompilationReport>| {
+            matches!(
+                res,
+                Err(RhodiError::Security(SecurityError::CircularInclude { .. }))
+            )
+        };
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+
+        // b.tmd includes itself as ./b.tmd
+        write(
+            "b.tmd",
+            &TracedDocument::new("B", "```include\npath: ./b.tmd\n```"),
+        );
+        let root = TracedDocument::new("Root", "```include\npath: b.tmd\n```");
+        assert!(is_circular(compiler.verify(&root)));
+
+        // c.tmd includes the root document back, detected by document ID
+        let root = TracedDocument::new("Root", "```include\npath: c.tmd\n```");
+        write("root.tmd", &root);
+        write(
+            "c.tmd",
+            &TracedDocument::new("C", "```include\npath: ./root.tmd\n```"),
+        );
+        assert!(is_circular(compiler.verify(&root)));
+
+        // Including the same module twice side by side is not a cycle
+        write("leaf.tmd", &TracedDocument::new("Leaf", "Leaf body"));
+        let root = TracedDocument::new(
+            "Root",
+            "```include\npath: leaf.tmd\n```\n\n```include\npath: ./leaf.tmd\n```",
+        );
+        assert!(compiler.verify(&root).unwrap().errors.is_empty());
+    }
+
+    #[test]
+    fn test_canonical_json_hashing() {
+        use crate::canonical::to_jcs;
+
+        // RFC 8785 ordering, escaping and number formatting
+        let value = serde_json::json!({
+            "b": [1, 2.0, true, null],
+            "a": "line\nquote\"é",
+            "\u{20ac}": {"z": 1, "aa": 2},
+            "1": 0
+        });
+        assert_eq!(
+            to_jcs(&value).unwrap(),
+            "{\"1\":0,\"a\":\"line\\nquote\\\"é\",\"b\":[1,2,true,null],\"€\":{\"aa\":2,\"z\":1}}"
+        );
+
+        let keypair = KeyPair::generate();
+        let doc = TracedDocument::new("New", "Body").seal(&keypair).unwrap();
+        assert_eq!(doc.frontmatter.protocol_version, get_latest_version());
+        assert!(doc.verify(&keypair.verifying_key).is_ok());
+
+        // An extra field named like a standard field cannot collide with it
+        let plain = TracedDocument::new("New", "Body");
+        let shadowed = plain.clone().extra_info("title", "Other");
+        assert_eq!(
+            plain.component_digests().unwrap().get("title"),
+            shadowed.component_digests().unwrap().get("title")
+        );
+        assert!(
+            shadowed
+                .component_digests()
+                .unwrap()
+                .contains_key("extra.title")
+        );
+
+        // 1.x documents still verify with the legacy encoding
+        let mut legacy = TracedDocument::new("Legacy", "Body");
+        legacy.frontmatter.protocol_version = "1.0".into();
+        let legacy = legacy.seal(&keypair).unwrap();
+        assert!(legacy.verify(&keypair.verifying_key).is_ok());
+        let mut relabeled = legacy.clone();
+        relabeled.frontmatter.protocol_version = "2.0".into();
+        assert_ne!(
+            relabeled.compute_version_hash().unwrap(),
+            legacy.compute_version_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_manager_list_and_delete() {
+        use crate::keys::KeyManager;
+
+        let dir = temp_workspace("keys");
+        let manager = KeyManager::at(dir.clone()).unwrap();
+        let key_json = |name: &str| {
+            format!(
+                "{{\"name\":\"{}\",\"public_key\":\"{}\",\"signing_key\":\"{}\"}}",
+                name,
+                "ab".repeat(32),
+                "01".repeat(32)
+            )
+        };
+        std::fs::write(dir.join("beta.json"), key_json("beta")).unwrap();
+        std::fs::write(dir.join("alpha.json"), key_json("alpha")).unwrap();
+
+        let infos = manager.key_infos().unwrap();
+        let names: Vec<&str> = infos.iter().map(|k| k.name.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta"]);
+        assert!(infos.iter().all(|k| k.created_at.is_some()));
+
+        manager.delete_key("alpha", true).unwrap();
+        assert!(!dir.join("alpha.json").exists());
+        assert!(manager.delete_key("alpha", false).is_err());
+        assert!(manager.delete_key("../beta", false).is_err());
+        assert_eq!(manager.list_keys().unwrap(), ["beta"]);
+    }
+
+    #[test]
+    fn test_key_backup_shares() {
+        use crate::keys::{KeyManager, KeyShare};
+        use crate::shamir::{combine, split};
+
+        // Every 3 of 5 shares rebuild the secret; 2 do not
+        let secret = b"0123456789abcdef0123456789abcdef";
+        let shares = split(secret, 3, 5).unwrap();
+        for a in 0..5 {
+            for b in a + 1..5 {
+                for c in b + 1..5 {
+                    let picked = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+                    assert_eq!(combine(&picked).unwrap(), secret);
+                }
+                assert_ne!(combine(&[shares[a].clone(), shares[b].clone()]).unwrap(), secret);
+            }
+        }
+        assert!(split(secret, 1, 5).is_err());
+        assert!(split(secret, 6, 5).is_err());
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+
+        let dir = temp_workspace("key-backup");
+        let manager = KeyManager::at(dir.clone()).unwrap();
+        let key = manager.generate("lab").unwrap();
+        let backup = manager.backup("lab", 5, 3).unwrap();
+        let printed: Vec<String> = backup.iter().map(KeyShare::to_text).collect();
+        let parsed: Vec<KeyShare> = printed.iter().map(|t| KeyShare::parse(t).unwrap()).collect();
+        assert_eq!(parsed, backup);
+
+        // Restored under a new name, with the same public key
+        let restored = manager.restore(&parsed[2..], Some("lab-restored")).unwrap();
+        assert_eq!(restored.public_key, key.public_key);
+        assert_eq!(
+            manager.get_key("lab-restored").unwrap().to_bytes(),
+            manager.get_key("lab").unwrap().to_bytes()
+        );
+        assert!(manager.restore(&parsed[2..], None).is_err());
+        assert!(manager.restore(&parsed[..2], Some("too-few")).is_err());
+
+        // Shares of another backup of the same key do not mix
+        let other = manager.backup("lab", 5, 3).unwrap();
+        let mixed = [parsed[0].clone(), parsed[1].clone(), other[2].clone()];
+        assert!(manager.restore(&mixed, Some("mixed")).is_err());
+
+        // A typo in the data is caught by the check line
+        let data = hex::encode(&backup[0].share.data[..1]);
+        let swapped = format!("{}{}", &data[1..], &data[..1]);
+        let typo = printed[0].replacen(&format!("data: {}", data), &format!("data: {}", swapped), 1);
+        assert_eq!(KeyShare::parse(&typo).is_err(), data != swapped);
+    }
+
+    #[test]
+    fn test_regex_context_assertion() {
+        use crate::extraction::check_context;
+
+        let source = b"Header\n\nRevenue summary\nTotal: 42\nFooter\n";
+        let context = "Revenue summary\nTotal: 42";
+        assert!(check_context(source, r"Total: (\d+)", context, 0).is_ok());
+
+        // The regex now hits an unrelated table far above the stored context
+        let moved = b"Costs\nTotal: 42\n\n\n\n\nRevenue summary\nTotal: 42\n";
+        assert!(check_context(moved, r"Total: (\d+)", context, 1).is_err());
+        assert!(check_context(moved, r"Total: (\d+)", context, 6).is_ok());
+
+        let rewritten = b"Revenue overview\nTotal: 42\n";
+        assert!(check_context(rewritten, r"Total: (\d+)", context, 5).is_err());
+    }
+
+    #[test]
+    fn test_trace_freshness() {
+        use crate::models::{Policy, parse_duration};
+        use chrono::{Duration, Utc};
+
+        assert_eq!(parse_duration("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_duration("12h").unwrap(), Duration::hours(12));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("5y").is_err());
+
+        let now = Utc::now();
+        let mut trace = TraceBlock {
+            source: "data.csv".to_string(),
+            expected: "42".to_string(),
+            timestamp: Some(now - Duration::days(10)),
+            ..Default::default()
+        };
+        let policy = Policy::default();
+        assert!(trace.check_freshness(&policy, now).is_ok());
+
+        trace.max_age = Some("7d".to_string());
+        assert!(trace.check_freshness(&policy, now).is_err());
+
+        // The document policy applies only when the trace has no limit
+        let policy = Policy {
+            max_trace_age: Some("30d".to_string()),
+            ..Default::default()
+        };
+        assert!(trace.check_freshness(&policy, now).is_err());
+        trace.max_age = None;
+        assert!(trace.check_freshness(&policy, now).is_ok());
+
+        trace.timestamp = None;
+        assert!(trace.check_freshness(&policy, now).is_err());
+    }
+
+    #[test]
+    fn test_workspace_index_search() {
+        use crate::index::{Query, WorkspaceIndex};
+        use crate::markdown::render_tmd;
+
+        let dir = temp_workspace("index");
+        std::fs::create_dir_all(dir.join("reports")).unwrap();
+        std::fs::create_dir_all(dir.join(".rhodi")).unwrap();
+        let trace = |source: &str| format!("```trace\nsource: {}\nexpected: \"42\"\n```", source);
+
+        let q1 = TracedDocument::new("Q1 Results", &trace("../data/sales.csv"))
+            .author("Ada Lovelace")
+            .set_status(DocStatus::Published);
+        let notes = TracedDocument::new("Notes", &trace("other.csv"));
+        std::fs::write(dir.join("reports/q1.tmd"), render_tmd(&q1).unwrap()).unwrap();
+        std::fs::write(dir.join("notes.tmd"), render_tmd(&notes).unwrap()).unwrap();
+        std::fs::write(dir.join("broken.tmd"), "not a document").unwrap();
+        std::fs::write(dir.join(".rhodi/hidden.tmd"), render_tmd(&notes).unwrap()).unwrap();
+
+        let index = WorkspaceIndex::build(&dir).unwrap();
+        assert_eq!(index.documents.len(), 2);
+        assert_eq!(index.skipped.len(), 1);
+
+        let by_source = |source: &str| {
+            index
+                .search(&Query {
+                    source: Some(source.to_string()),
+                    ..Default::default()
+                })
+                .len()
+        };
+        // Sources are rebased onto the workspace root
+        assert_eq!(by_source("data/sales.csv"), 1);
+        assert_eq!(by_source("sales.csv"), 1);
+        assert_eq!(by_source("ales.csv"), 0);
+
+        let published = index.search(&Query {
+            status: Some(DocStatus::Published),
+            author: Some("ada".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].title, "Q1 Results");
+    }
+
+    #[test]
+    fn test_lsp_diagnostics_hover_and_definition() {
+        use crate::lsp::{definition, diagnostics, hover};
+        use crate::markdown::render_tmd;
+
+        let dir = temp_workspace("lsp");
+        std::fs::write(dir.join("data.txt"), "Total: 42").unwrap();
+        std::fs::write(
+            dir.join("part.tmd"),
+            render_tmd(&TracedDocument::new("Part", "Body")).unwrap(),
+        )
+        .unwrap();
+
+        let body = "```trace\nsource: data.txt\nselector: \"Total: (\\\\d+)\"\nexpected: \"41\"\n```\n\n```trace\nsource: data.txt\nextractor: xpath\nselector: //a\nexpected: x\n```\n\n```include\npath: part.tmd\n```\n\n```trace\nsource: [unclosed\n```\n";
+        let mut doc = TracedDocument::new("Doc", body);
+        doc.frontmatter.version_hash = Some(doc.compute_version_hash().unwrap());
+        let text = render_tmd(&doc).unwrap();
+
+        // Without running traces, only structural problems are reported
+        let found = diagnostics(&text, &dir, false);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert!(found.iter().any(|d| d.message.contains("xpath")));
+
+        let found = diagnostics(&text, &dir, true);
+        assert_eq!(found.len(), 3, "{:?}", found);
+
+        let line_of =
+            |prefix: &str| text.lines().position(|l| l.starts_with(prefix)).unwrap() as u32;
+        let info = hover(&text, line_of("version_hash:")).unwrap();
+        assert!(info.contains("matches the seal"));
+        assert!(hover(&text, line_of("title:")).is_none());
+
+        assert_eq!(
+            definition(&text, line_of("path: part.tmd"), &dir),
+            Some(dir.join("part.tmd"))
+        );
+        assert!(definition(&text, line_of("expected: x"), &dir).is_none());
+    }
+
+    #[test]
+    fn test_lsp_survives_malformed_messages() {
+        use lsp_server::{Connection, Message, Notification, Request, RequestId};
+
+        let (server, client) = Connection::memory();
+        let serving = std::thread::spawn(move || crate::lsp::serve(server));
+        let request = |id: i32, method: &str, params: serde_json::Value| {
+            Message::Request(Request::new(RequestId::from(id), method.to_string(), params))
+        };
+
+        // A bad request gets an error response, a bad notification is ignored
+        client
+            .sender
+            .send(request(1, "textDocument/hover", serde_json::json!({"bogus": 1})))
+            .unwrap();
+        let Message::Response(response) = client.receiver.recv().unwrap() else {
+            panic!("expected a response");
+        };
+        assert_eq!(
+            response.error.unwrap().code,
+            lsp_server::ErrorCode::InvalidParams as i32
+        );
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                "textDocument/didOpen".to_string(),
+                serde_json::json!({"textDocument": 3}),
+            )))
+            .unwrap();
+
+        // The server still answers, and shuts down when asked
+        client
+            .sender
+            .send(request(2, "textDocument/unknown", serde_json::Value::Null))
+            .unwrap();
+        let Message::Response(response) = client.receiver.recv().unwrap() else {
+            panic!("expected a response");
+        };
+        assert!(response.error.is_none());
+        client
+            .sender
+            .send(request(3, "shutdown", serde_json::Value::Null))
+            .unwrap();
+        client.receiver.recv().unwrap();
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                "exit".to_string(),
+                serde_json::Value::Null,
+            )))
+            .unwrap();
+        serving.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_environment_recorded_and_compared() {
+        use crate::compiler::Compiler;
+        use crate::models::Environment;
+        use crate::resolver::FileResolver;
+
+        let sealed = TracedDocument::new("Env", "Body").prepare_seal().unwrap();
+        let recorded = sealed.frontmatter.environment.clone().unwrap();
+        assert_eq!(recorded.tool_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(recorded.canonicalization, "jcs-rfc8785+nfc");
+        assert!(recorded.differences(&recorded).is_empty());
+
+        // Patch releases and locale encodings are not material
+        let mut other = recorded.clone();
+        let (minor, _) = recorded.tool_version.rsplit_once('.').unwrap();
+        other.tool_version = format!("{}.999", minor);
+        other.locale = recorded
+            .locale
+            .as_ref()
+            .map(|l| format!("{}.ISO-8859-1", l));
+        assert!(recorded.differences(&other).is_empty());
+        other.locale = Some("de_DE.UTF-8".to_string());
+        other.os = "plan9".to_string();
+        assert_eq!(recorded.differences(&other).len(), 2);
+
+        // Verification warns when run somewhere else
+        let mut doc = TracedDocument::new("Env", "Body");
+        doc.frontmatter.environment = Some(Environment {
+            os: "plan9".to_string(),
+            ..recorded
+        });
+        let resolver = FileResolver::new(".").unwrap();
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Environment warning") && w.contains("plan9"))
+        );
+    }
+
+    #[test]
+    fn test_reseal_after_key_compromise() {
+        let old_key = KeyPair::generate();
+        let new_key = KeyPair::generate();
+
+        let mut doc = TracedDocument::new("Compromised", "Body");
+        doc.frontmatter.public_key = Some(hex::encode(old_key.verifying_key.as_bytes()));
+        let sealed = doc.seal(&old_key).unwrap();
+        let old_hash = sealed.frontmatter.version_hash;
+
+        // A version edited since its seal is not laundered into a signed one
+        let mut tampered = sealed.clone();
+        tampered.body = "Edited body".to_string();
+        assert!(tampered.clone().reseal(&new_key, "laptop stolen").is_err());
+        let mut rehashed = tampered;
+        rehashed.frontmatter.version_hash = Some(rehashed.compute_version_hash().unwrap());
+        assert!(rehashed.reseal(&new_key, "laptop stolen").is_err());
+        assert!(
+            TracedDocument::new("Unsigned", "Body")
+                .reseal(&new_key, "laptop stolen")
+                .is_err()
+        );
+
+        let resealed = sealed.reseal(&new_key, "laptop stolen").unwrap();
+        assert!(resealed.verify(&new_key.verifying_key).is_ok());
+        assert!(resealed.verify(&old_key.verifying_key).is_err());
+        assert_eq!(resealed.frontmatter.prev_version_hash, old_hash);
+        assert_eq!(resealed.frontmatter.doc_version, 2);
+
+        let extra = resealed.frontmatter.extra.as_ref().unwrap();
+        assert_eq!(
+            extra.get("resealed_from"),
+            Some(&hex::encode(old_key.verifying_key.as_bytes()))
+        );
+        assert_eq!(extra.get("reseal_reason").unwrap(), "laptop stolen");
+    }
+
+    #[test]
+    fn test_agent_extraction_records_metadata() {
+        use crate::agent::{AgentClient, build_prompt};
+        use crate::markdown::update_trace_blocks;
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        // Minimal chat completions endpoint that always answers "42"
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(v) = header.to_lowercase().strip_prefix("content-length:") {
+                    content_length = v.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+            let reply = serde_json::json!({
+                "model": format!("{}-2024", request["model"].as_str().unwrap()),
+                "choices": [{ "message": { "role": "assistant", "content": " 42\n" } }],
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                reply.len(),
+                reply
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            request
+        });
+
+        let body = "Intro\n\n```trace\nsource: report.txt\nexpected: \"\"\nmethod: agent\ncontext: Total revenue\n```\n";
+        let trace = crate::parse_trace_block(&body[body.find("```trace").unwrap()..]).unwrap();
+        let source = b"Total revenue was 42 million.";
+
+        let answer = AgentClient::new(&url, "small")
+            .extract(&trace, source)
+            .unwrap();
+        let request = server.join().unwrap();
+        assert_eq!(
+            request["messages"][0]["content"],
+            build_prompt(&trace, "Total revenue was 42 million.")
+        );
+
+        assert_eq!(answer.value, "42");
+        assert_eq!(answer.metadata.model, "small-2024");
+        assert_eq!(
+            answer.metadata.prompt_hash,
+            Some(crate::crypto::hash_bytes(
+                build_prompt(&trace, "Total revenue was 42 million.").as_bytes()
+            ))
+        );
+
+        // Recording the answer rewrites only the agent's fields
+        let updated = update_trace_blocks(body, |_| {
+            let mut fields = serde_norway::Mapping::new();
+            fields.insert(
+                "agent_metadata".into(),
+                serde_norway::to_value(&answer.metadata).unwrap(),
+            );
+            fields.insert("expected".into(), answer.value.clone().into());
+            Ok(Some(serde_norway::Value::Mapping(fields)))
+        })
+        .unwrap();
+        assert!(updated.starts_with("Intro\n\n```trace\nsource: report.txt\n"));
+        let recorded =
+            crate::parse_trace_block(&updated[updated.find("```trace").unwrap()..]).unwrap();
+        assert_eq!(recorded.expected, "42");
+        assert_eq!(recorded.agent_metadata, Some(answer.metadata));
+        assert_eq!(recorded.context.as_deref(), Some("Total revenue"));
+    }
+
+    #[test]
+    fn test_cose_round_trip() {
+        use crate::encoding::{from_cose, to_cose};
+
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("Binary", "Body with a claim.");
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let sealed = doc.seal(&keypair).unwrap();
+
+        let bytes = to_cose(&sealed, &keypair).unwrap();
+        // Tagged COSE_Sign1 (CBOR tag 18)
+        assert_eq!(bytes[0], 0xd2);
+        let decoded = from_cose(&bytes).unwrap();
+        assert_eq!(
+            decoded.frontmatter.version_hash,
+            sealed.frontmatter.version_hash
+        );
+        assert_eq!(decoded.body, sealed.body);
+
+        // A flipped payload byte breaks the COSE signature
+        let mut tampered = bytes.clone();
+        let at = tampered.windows(5).position(|w| w == b"claim").unwrap();
+        tampered[at] = b'C';
+        assert!(from_cose(&tampered).is_err());
+
+        // Only the document's own key may wrap it, and only once sealed
+        assert!(to_cose(&sealed, &KeyPair::generate()).is_err());
+        assert!(to_cose(&TracedDocument::new("Draft", "x"), &keypair).is_err());
+    }
+
+    #[test]
+    fn test_verify_passes() {
+        use crate::compiler::{Compiler, VerifyPasses};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("passes");
+        std::fs::write(dir.join("data.txt"), "Total: 42").unwrap();
+        let keypair = KeyPair::generate();
+        let body = "```trace\nsource: data.txt\nselector: \"Total: (\\\\d+)\"\nexpected: \"41\"\n```\n\n```include\npath: missing.tmd\n```\n";
+        let mut doc = TracedDocument::new("Passes", body);
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let mut doc = doc.seal(&keypair).unwrap();
+        doc.frontmatter.title = "Tampered".to_string();
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let errors = |passes: VerifyPasses| {
+            let report = Compiler::new(&resolver)
+                .with_passes(passes)
+                .verify(&doc)
+                .unwrap();
+            report
+                .errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+        };
+        let mentions = |errors: &[String], what: &str| errors.iter().any(|e| e.contains(what));
+
+        let all = errors(VerifyPasses::ALL);
+        assert_eq!(all.len(), 3, "{:?}", all);
+
+        let signature_only = errors(VerifyPasses::SIGNATURE_ONLY);
+        assert_eq!(signature_only.len(), 1);
+        assert!(!mentions(&signature_only, "missing.tmd"));
+
+        let skip_traces = errors(VerifyPasses::SKIP_TRACES);
+        assert_eq!(skip_traces.len(), 2);
+        assert!(mentions(&skip_traces, "missing.tmd"));
+        assert!(!mentions(&skip_traces, "Expected '41'"));
+
+        let traces_only = errors(VerifyPasses::TRACES_ONLY);
+        assert_eq!(traces_only.len(), 2);
+        assert!(mentions(&traces_only, "Expected '41'"));
+    }
+
+    #[test]
+    fn test_resolver_registry_dispatch() {
+        use crate::config::WorkspaceConfig;
+        use crate::resolver::{FileResolver, HttpResolver, ResolverRegistry, SourceResolver};
+
+        struct Memory;
+        impl SourceResolver for Memory {
+            fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+                Ok(source.split_once(':').unwrap().1.as_bytes().to_vec())
+            }
+            fn resolve_document(&self, _source: &str) -> Result<TracedDocument> {
+                Err(RhodiError::Resolution("not a document".into()))
+            }
+        }
+
+        let dir = temp_workspace("registry");
+        std::fs::write(dir.join("a.txt"), "local").unwrap();
+        let registry = ResolverRegistry::new(FileResolver::new(&dir).unwrap())
+            .with_scheme("mem", Memory)
+            .with_scheme(
+                "https",
+                HttpResolver::new().with_allowed_hosts(["data.example.org"]),
+            );
+
+        assert_eq!(registry.resolve_bytes("a.txt").unwrap(), b"local");
+        assert_eq!(registry.resolve_bytes("file:a.txt").unwrap(), b"local");
+        assert_eq!(registry.resolve_bytes("MEM:hello").unwrap(), b"hello");
+        assert!(registry.resolve_bytes("file:../escape.txt").is_err());
+
+        // Unregistered schemes and disallowed hosts fail before any I/O
+        let err = registry.resolve_bytes("s3:bucket/key").unwrap_err();
+        assert!(err.to_string().contains("'s3:'"), "{}", err);
+        assert!(!err.to_string().contains("[resolver.s3]"));
+        assert!(matches!(
+            registry.resolve_bytes("https://evil.example.com/data.csv"),
+            Err(RhodiError::Security(_))
+        ));
+        assert!(
+            HttpResolver::new()
+                .resolve_bytes("http://data.example.org/x")
+                .is_err()
+        );
+
+        // Remote sources stay disabled until the workspace opts in
+        let config = WorkspaceConfig::from_toml(
+            "[resolver.https]\nenabled = true\nallow_hosts = [\"data.example.org\"]\n",
+        )
+        .unwrap();
+        assert!(config.resolver.https.enabled);
+        assert!(
+            WorkspaceConfig::default()
+                .resolver
+                .https
+                .allow_hosts
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_trace_merkle_inclusion() {
+        use crate::merkle::{decode, trace_leaf, verify_inclusion};
+
+        let traces: String = (1..=5)
+            .map(|i| {
+                format!(
+                    "```trace\nsource: data{}.csv\nexpected: \"{}\"\n```\n\n",
+                    i, i
+                )
+            })
+            .collect();
+        let key = KeyPair::generate();
+        let mut doc = TracedDocument::new("Merkle", &traces);
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let sealed = doc.seal(&key).unwrap();
+        assert!(sealed.verify(&key.verifying_key).is_ok());
+        assert_eq!(sealed.frontmatter.trace_tree.as_ref().unwrap().len(), 5);
+
+        let root = decode(sealed.frontmatter.trace_root.as_deref().unwrap()).unwrap();
+        let third = TraceBlock {
+            source: "data3.csv".to_string(),
+            expected: "3".to_string(),
+            ..Default::default()
+        };
+        let proof = sealed.prove_trace(2).unwrap();
+        assert!(verify_inclusion(&trace_leaf(&third).unwrap(), &proof, &root).unwrap());
+
+        // A different trace, or the same trace at another index, does not prove
+        let forged = TraceBlock {
+            expected: "4".to_string(),
+            ..third.clone()
+        };
+        assert!(!verify_inclusion(&trace_leaf(&forged).unwrap(), &proof, &root).unwrap());
+        let other = sealed.prove_trace(3).unwrap();
+        assert!(!verify_inclusion(&trace_leaf(&third).unwrap(), &other, &root).unwrap());
+        assert!(sealed.prove_trace(5).is_err());
+
+        // The root is part of the version hash
+        let mut tampered = sealed.clone();
+        tampered.frontmatter.trace_root = Some(crate::merkle::encode(&[0u8; 32]));
+        assert!(tampered.verify(&key.verifying_key).is_err());
+
+        // The leaf list is not hashed, so it is checked against the body
+        let mut tampered = sealed.clone();
+        tampered.frontmatter.trace_tree.as_mut().unwrap()[2] = crate::merkle::encode(&[0u8; 32]);
+        assert!(tampered.verify(&key.verifying_key).is_err());
+        let mut trimmed = sealed.clone();
+        trimmed.frontmatter.trace_tree.as_mut().unwrap().pop();
+        assert!(trimmed.verify(&key.verifying_key).is_err());
+    }
+
+    #[test]
+    fn test_suggest_trace_stubs() {
+        use crate::markdown::{Section, markdown_title, parse_tmd_sections, suggest_trace_stubs};
+
+        let md = "# Q4 Report\n\nRevenue grew 12.5% to $1,200,000.\nStaff: 40 in 2024.\n\n```text\npi = 3.14\n```\n";
+        assert_eq!(markdown_title(md).as_deref(), Some("Q4 Report"));
+
+        let body = suggest_trace_stubs(md);
+        let traces: Vec<TraceBlock> = parse_tmd_sections(&body)
+            .into_iter()
+            .filter_map(|s| match s {
+                Section::Trace(t) => Some(t),
+                _ => None,
+            })
+            .collect();
+        let expected: Vec<&str> = traces.iter().map(|t| t.expected.as_str()).collect();
+        assert_eq!(expected, ["12.5%", "$1,200,000"]);
+        assert!(traces.iter().all(|t| t.source == "TODO"));
+
+        // Stubs follow the paragraph rather than splitting it
+        assert!(body.contains("Staff: 40 in 2024.\n\n```trace"));
+        assert!(body.contains("pi = 3.14"));
+    }
+
+    #[test]
+    fn test_bundle_resolver_is_hermetic() {
+        use crate::bundle::{BundleResolver, write_bundle};
+        use crate::compiler::Compiler;
+        use crate::markdown::render_tmd;
+        use crate::resolver::SourceResolver;
+
+        let dir = temp_workspace("bundle");
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        std::fs::write(dir.join("data/r.txt"), "Revenue: 42").unwrap();
+        let doc = TracedDocument::new(
+            "Bundled",
+            "```trace\nsource: ./data/r.txt\nselector: \"Revenue: (\\\\d+)\"\nexpected: \"42\"\n```",
+        );
+        std::fs::write(dir.join("doc.tmd"), render_tmd(&doc).unwrap()).unwrap();
+
+        let mut archive = Vec::new();
+        let manifest = write_bundle(&dir.join("doc.tmd"), &mut archive).unwrap();
+        assert_eq!(manifest.document, "doc.tmd");
+        assert_eq!(manifest.files.len(), 2);
+
+        // The original layout is gone; the bundle alone verifies the document
+        std::fs::remove_dir_all(&dir).unwrap();
+        let bundle = BundleResolver::from_bytes(archive.clone()).unwrap();
+        assert_eq!(bundle.resolve_bytes("data/r.txt").unwrap(), b"Revenue: 42");
+        assert!(bundle.resolve_bytes("../secret.txt").is_err());
+        let bundled = bundle.document().unwrap();
+        let report = Compiler::new(&bundle).verify(&bundled).unwrap();
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+
+        // A file that no longer matches its manifest digest is rejected
+        let at = archive
+            .windows(11)
+            .position(|w| w == b"Revenue: 42")
+            .unwrap();
+        archive[at + 9] = b'7';
+        assert!(BundleResolver::from_bytes(archive.clone()).is_err());
+
+        // Unpacking stops at the size limit, compressed or not
+        let mut gzipped = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        std::io::Write::write_all(&mut gzipped, &archive).unwrap();
+        let gzipped = gzipped.finish().unwrap();
+        for bytes in [archive, gzipped] {
+            // An unbounded limit is no limit, not an overflow
+            assert!(!matches!(
+                BundleResolver::from_bytes_with_limit(bytes.clone(), u64::MAX),
+                Err(RhodiError::Security(_))
+            ));
+            assert!(matches!(
+                BundleResolver::from_bytes_with_limit(bytes, 16),
+                Err(RhodiError::Security(
+                    crate::error::SecurityError::BundleSizeExceeded { limit: 16 }
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_signature_algorithm_recorded() {
+        use crate::crypto::SignatureScheme;
+        use crate::markdown::render_tmd;
+
+        let key = KeyPair::generate();
+        let mut doc = TracedDocument::new("Agile", "Body");
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let sealed = doc.seal(&key).unwrap();
+        assert_eq!(sealed.frontmatter.signature_algorithm, None);
+        assert_eq!(sealed.signature_scheme(), SignatureScheme::Ed25519);
+        assert!(sealed.verify(&key.verifying_key).is_ok());
+
+        let parsed = parse_tmd(&render_tmd(&sealed).unwrap()).unwrap();
+        assert_eq!(parsed.signature_scheme(), SignatureScheme::Ed25519);
+        assert!(parsed.verify(&key.verifying_key).is_ok());
+
+        // The algorithm is covered by the version hash
+        let mut swapped = sealed.clone();
+        swapped.frontmatter.signature_algorithm = Some(SignatureScheme::Ed25519);
+        assert!(swapped.verify(&key.verifying_key).is_err());
+
+        // Reserved algorithms are named, but rejected until implemented
+        assert_eq!(
+            "ecdsa-p256".parse::<SignatureScheme>().unwrap(),
+            SignatureScheme::EcdsaP256
+        );
+        assert!("rsa".parse::<SignatureScheme>().is_err());
+        let err = SignatureScheme::Ed448
+            .verify(key.verifying_key.as_bytes(), b"msg", &[0u8; 114])
+            .unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+    }
+
+    #[test]
+    fn test_shared_sources() {
+        use crate::compiler::Compiler;
+        use crate::models::SourceDecl;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("shared-sources");
+        std::fs::write(dir.join("sales.csv"), "q1,120\nq2,135\n").unwrap();
+        let body = "```trace\nsource: $sales\nselector: \"q1,(\\\\d+)\"\nexpected: \"120\"\n```\n\n\
+                    ```trace\nsource: $sales\nselector: \"q2,(\\\\d+)\"\nexpected: \"135\"\n```\n";
+        let mut doc = TracedDocument::new("Shared", body);
+        let mut sources = std::collections::BTreeMap::new();
+        sources.insert(
+            "sales".to_string(),
+            SourceDecl {
+                path: "sales.csv".to_string(),
+                ..Default::default()
+            },
+        );
+        doc.frontmatter.sources = Some(sources);
+
+        // The hash is recorded once, on the declaration
+        doc.update_all_traces(&dir).unwrap();
+        let decl = &doc.frontmatter.sources.as_ref().unwrap()["sales"];
+        assert!(decl.hash.as_deref().unwrap().starts_with("sha256:"));
+        assert!(!doc.body.contains("hash: sha256"));
+
+        let key = KeyPair::generate();
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let sealed = doc.seal(&key).unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        assert!(compiler.verify(&sealed).unwrap().errors.is_empty());
+
+        // Both traces fail once the shared source changes
+        std::fs::write(dir.join("sales.csv"), "q1,120\nq2,136\n").unwrap();
+        assert_eq!(compiler.verify(&sealed).unwrap().errors.len(), 2);
+
+        // Declarations are covered by the version hash
+        let mut moved = sealed.clone();
+        moved
+            .frontmatter
+            .sources
+            .as_mut()
+            .unwrap()
+            .get_mut("sales")
+            .unwrap()
+            .path = "other.csv".to_string();
+        assert!(moved.verify(&key.verifying_key).is_err());
+
+        let undeclared = TraceBlock {
+            source: "$missing".to_string(),
+            ..Default::default()
+        };
+        assert!(sealed.frontmatter.resolve_trace(&undeclared).is_err());
+    }
+
+    #[test]
+    fn test_propose_fix_for_changed_source() {
+        use crate::compiler::Compiler;
+        use crate::crypto::hash_bytes;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("fix");
+        std::fs::write(dir.join("r.txt"), "Revenue: 42").unwrap();
+        let mut trace = TraceBlock {
+            source: "r.txt".to_string(),
+            selector: Some("Revenue: (\\d+)".to_string()),
+            expected: "42".to_string(),
+            hash: Some(hash_bytes(b"Revenue: 42")),
+            ..Default::default()
+        };
+        let doc = TracedDocument::new("Fix", "");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        assert_eq!(compiler.propose_fix(&doc, &trace).unwrap(), None);
+
+        std::fs::write(dir.join("r.txt"), "Revenue: 45").unwrap();
+        let fix = compiler.propose_fix(&doc, &trace).unwrap().unwrap();
+        assert_eq!(fix.old_expected, "42");
+        assert_eq!(fix.new_expected, "45");
+        assert_eq!(fix.new_hash, hash_bytes(b"Revenue: 45"));
+        assert_eq!(fix.shared_source, None);
+
+        // A selector that no longer matches needs a human
+        std::fs::write(dir.join("r.txt"), "Turnover: 45").unwrap();
+        assert!(compiler.propose_fix(&doc, &trace).is_err());
+
+        trace.selector = None;
+        let fix = compiler.propose_fix(&doc, &trace).unwrap().unwrap();
+        assert_eq!(fix.new_expected, "42");
+    }
+
+    #[test]
+    fn test_notarization_chain() {
+        use crate::markdown::render_tmd;
+        use crate::notary::{notarize, verify_chain};
+        use crate::version::VersionRegistry;
+
+        let versions = VersionRegistry::default();
+        let author = KeyPair::generate();
+        let mut doc = TracedDocument::new("Notarized", "Body");
+        doc.frontmatter.public_key = Some(hex::encode(author.verifying_key.as_bytes()));
+        let sealed = doc.seal(&author).unwrap();
+
+        // Unsigned documents cannot be notarized
+        let draft = TracedDocument::new("Draft", "Body");
+        assert!(notarize(draft, &KeyPair::generate(), None, &versions).is_err());
+
+        let first = notarize(
+            sealed.clone(),
+            &KeyPair::generate(),
+            Some("Notary A"),
+            &versions,
+        )
+        .unwrap();
+        let both = notarize(first, &KeyPair::generate(), None, &versions).unwrap();
+        let parsed = parse_tmd(&render_tmd(&both).unwrap()).unwrap();
+        assert_eq!(parsed.frontmatter.notarizations.as_ref().unwrap().len(), 2);
+        assert!(verify_chain(&parsed).is_ok());
+        // Notarizations do not disturb the author's seal
+        assert!(parsed.verify(&author.verifying_key).is_ok());
+
+        // Dropping the first entry breaks the chain of the second
+        let mut broken = parsed.clone();
+        broken.frontmatter.notarizations.as_mut().unwrap().remove(0);
+        assert!(verify_chain(&broken).is_err());
+
+        // A notarization is bound to the seal it was made over
+        let mut tampered = parsed.clone();
+        tampered.frontmatter.signature = sealed.frontmatter.signature.map(|mut s| {
+            s[0] ^= 1;
+            s
+        });
+        assert!(verify_chain(&tampered).is_err());
+        assert!(
+            parsed
+                .seal(&author)
+                .unwrap()
+                .frontmatter
+                .notarizations
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_trace_anchors() {
+        use crate::markdown::{Section, parse_tmd_sections, trace_anchors};
+
+        let trace = "```trace\nsource: data.csv\nexpected: \"42\"\n```\n";
+        let body = format!(
+            "# Report\n\n## Results\n\nSetup. Revenue grew to 42.\n\n{trace}\n## Appendix\n\n{trace}"
+        );
+        let anchors = trace_anchors(&parse_tmd_sections(&body));
+        assert_eq!(anchors.len(), 2);
+        assert_eq!(anchors[0].heading_path, vec!["Report", "Results"]);
+        assert_eq!(anchors[0].sentence.as_deref(), Some("Revenue grew to 42."));
+        assert_eq!(anchors[1].sentence, None);
+
+        let hash = anchors[0].sentence_hash().unwrap();
+        assert!(anchors[0].check(&hash).is_ok());
+        assert!(anchors[0].check(&hash[..15]).is_ok());
+        assert!(anchors[0].check("Results").is_ok());
+        assert!(anchors[0].check("# Report > ## Results").is_ok());
+        // Moved under another heading, the anchor no longer resolves
+        assert!(anchors[1].check(&hash).is_err());
+        assert!(anchors[1].check("Results").is_err());
+
+        let mut doc = TracedDocument::new("Anchored", &body);
+        assert_eq!(doc.anchor_traces().unwrap(), 2);
+        let anchors: Vec<_> = parse_tmd_sections(&doc.body)
+            .into_iter()
+            .filter_map(|s| match s {
+                Section::Trace(t) => t.anchor,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(anchors, vec![hash, "Report > Appendix".to_string()]);
+        assert_eq!(doc.anchor_traces().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_server_endpoints() {
+        use crate::config::Workspace;
+        use crate::markdown::render_tmd;
+        use crate::server::Service;
+
+        let dir = temp_workspace("serve");
+        std::fs::write(dir.join("data.json"), r#"{"revenue": 42}"#).unwrap();
+        let mut doc = TracedDocument::new(
+            "Served",
+            "Revenue was 42.\n\n```trace\nsource: data.json\nselector: $.revenue\nextractor: jsonpath\nexpected: \"42\"\n```\n",
+        );
+        doc.frontmatter.doc_status = DocStatus::Draft;
+        std::fs::write(dir.join("doc.tmd"), render_tmd(&doc).unwrap()).unwrap();
+        let service = Service::new(Workspace::discover(&dir).unwrap());
+
+        let (status, body) = service.handle("POST", "/verify", br#"{"path": "doc.tmd"}"#);
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["valid"], true);
+
+        let (status, body) = service.handle("POST", "/status", br#"{"path": "doc.tmd"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(body["title"], "Served");
+        assert_eq!(body["traces"], 1);
+
+        let request =
+            br#"{"source": "data.json", "selector": "$.revenue", "extractor": "jsonpath"}"#;
+        let (status, body) = service.handle("POST", "/extract", request);
+        assert_eq!(status, 200);
+        assert_eq!(body["value"], "42");
+
+        // Sources outside the workspace are refused
+        let (status, _) = service.handle(
+            "POST",
+            "/extract",
+            br#"{"source": "../secret", "selector": "x"}"#,
+        );
+        assert_eq!(status, 403);
+        assert_eq!(service.handle("GET", "/verify", b"").0, 405);
+        assert_eq!(service.handle("POST", "/verify", b"{}").0, 400);
+        assert_eq!(service.handle("GET", "/nope", b"").0, 404);
+    }
+
+    #[test]
+    fn test_testkit_fixtures_are_deterministic() {
+        use crate::testkit::{
+            DocumentBuilder, TraceBuilder, check_golden_vectors, fixture_keypair,
+        };
+
+        check_golden_vectors().unwrap();
+
+        let build = || {
+            DocumentBuilder::new("Fixture")
+                .paragraph("Revenue was 42.")
+                .trace(
+                    TraceBuilder::new("data.json", "42")
+                        .selector("$.revenue")
+                        .extractor("jsonpath")
+                        .build(),
+                )
+                .sealed(&fixture_keypair())
+        };
+        let (a, b) = (build(), build());
+        assert_eq!(a.frontmatter.version_hash, b.frontmatter.version_hash);
+        assert_eq!(a.frontmatter.signature, b.frontmatter.signature);
+        assert!(a.verify(&fixture_keypair().verifying_key).is_ok());
+        assert_eq!(crate::markdown::parse_tmd_sections(&a.body).len(), 2);
+    }
+
+    #[test]
+    fn test_windows_traversal_vectors() {
+        use crate::resolver::{FileResolver, SourceResolver};
+
+        let dir = temp_workspace("winpaths");
+        std::fs::write(dir.join("data.csv"), "a,b").unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        assert!(resolver.resolve_bytes("data.csv").is_ok());
+
+        for source in [
+            r"C:\Windows\win.ini",
+            "C:secret.txt",
+            r"\\server\share\data.csv",
+            r"\\?\C:\Windows\win.ini",
+            r"\\.\PhysicalDrive0",
+            "//server/share/data.csv",
+            r"\Windows\win.ini",
+            r"..\secret.txt",
+            r"sub\..\..\secret.txt",
+            r"sub/..\..\secret.txt",
+        ] {
+            let res = resolver.resolve_bytes(source);
+            assert!(
+                matches!(res, Err(RhodiError::Security(_))),
+                "{} was not rejected",
+                source
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_workspace_paths() {
+        use crate::resolver::{FileResolver, SourceResolver};
+        use std::os::unix::fs::symlink;
+
+        let outside = temp_workspace("outside");
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        let real = temp_workspace("real-root");
+        std::fs::write(real.join("data.csv"), "a,b").unwrap();
+        symlink(&outside, real.join("escape")).unwrap();
+
+        // A workspace reached through a symlink resolves as usual
+        let linked = std::env::temp_dir().join(format!("rhodi-link-{}", uuid::Uuid::now_v7()));
+        symlink(&real, &linked).unwrap();
+        let resolver = FileResolver::new(&linked).unwrap();
+        assert!(resolver.resolve_bytes("data.csv").is_ok());
+
+        // Links leaving the root are refused, whether or not the target exists
+        for source in ["escape/secret.txt", "escape/missing.txt"] {
+            let res = resolver.resolve_bytes(source);
+            assert!(matches!(res, Err(RhodiError::Security(_))), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_explain_trace_mismatch() {
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("explain");
+        std::fs::write(
+            dir.join("report.txt"),
+            "Intro\nQ3\nRevenue: 40 units\nOutro\n",
+        )
+        .unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let doc = TracedDocument::new("Explained", "");
+
+        let trace = TraceBlock {
+            source: "report.txt".into(),
+            selector: Some(r"Revenue: (\d+)".into()),
+            expected: "42".into(),
+            ..Default::default()
+        };
+        let explanation = compiler.explain_trace(&doc, &trace).unwrap();
+        assert_eq!(explanation.actual.as_deref(), Some("40"));
+        let diff = explanation.diff.as_deref().unwrap();
+        assert!(diff.contains("-42") && diff.contains("+40"));
+        let snippet = explanation.snippet.as_ref().unwrap();
+        assert_eq!((snippet.first_line, snippet.match_line), (1, 3));
+        assert_eq!(snippet.lines.len(), 4);
+        assert!(explanation.to_string().contains("> 3 | Revenue: 40 units"));
+
+        let passing = TraceBlock {
+            expected: "40".into(),
+            ..trace
+        };
+        assert!(compiler.explain_trace(&doc, &passing).is_none());
+    }
+
+    #[test]
+    fn test_artifact_records() {
+        use crate::artifact::{ArtifactRecord, render_html, verify_artifact};
+
+        let author = KeyPair::generate();
+        let mut doc = TracedDocument::new("Exported", "# Report\n\nBody");
+        doc.frontmatter.public_key = Some(hex::encode(author.verifying_key.as_bytes()));
+        let v1 = doc.seal(&author).unwrap();
+
+        let html = render_html(&v1);
+        assert!(html.contains("<h1>Report</h1>"));
+        let record = ArtifactRecord::create(&v1, "report.html", html.as_bytes(), &author).unwrap();
+        let records = vec![record];
+        assert!(verify_artifact(&v1, html.as_bytes(), &records).is_ok());
+
+        // A modified artifact has no record
+        let edited = html.replace("Body", "Edited");
+        assert!(verify_artifact(&v1, edited.as_bytes(), &records).is_err());
+
+        // Only the sealing key may record exports
+        assert!(ArtifactRecord::create(&v1, "x.html", b"x", &KeyPair::generate()).is_err());
+
+        // The record is bound to the version it was exported from
+        let mut v2 = v1.clone();
+        v2.body.push_str("\n\nAddendum");
+        let v2 = v2.seal(&author).unwrap();
+        assert!(verify_artifact(&v2, html.as_bytes(), &records).is_err());
+
+        let mut tampered = records.clone();
+        tampered[0].artifact = "other.html".to_string();
+        assert!(verify_artifact(&v1, html.as_bytes(), &tampered).is_err());
+
+        // A bad record is a finding; a valid one still proves the export
+        let mut mixed = tampered.clone();
+        mixed.extend(records.clone());
+        let check = verify_artifact(&v1, html.as_bytes(), &mixed).unwrap();
+        assert_eq!(check.record, &records[0]);
+        assert_eq!(check.findings.len(), 1);
+        assert!(check.findings[0].contains("signature invalid"));
+        let err = verify_artifact(&v2, html.as_bytes(), &mixed)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("signature invalid") && err.contains("not the current"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_include_stubs() {
+        use crate::compiler::Compiler;
+        use crate::models::IncludeStub;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("include-stub");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let root = TracedDocument::new("Root", "```include\npath: private.tmd\n```")
+            .set_status(DocStatus::Published);
+        assert_eq!(compiler.verify(&root).unwrap().errors.len(), 1);
+
+        // Drafts cannot be stubbed
+        assert!(IncludeStub::from_document(&TracedDocument::new("Draft", "")).is_err());
+
+        let key = KeyPair::generate();
+        let mut private = TracedDocument::new("Private", "Restricted");
+        private.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let stub = IncludeStub::from_document(&private.seal(&key).unwrap()).unwrap();
+        let stub_path = dir.join(IncludeStub::path_for("private.tmd"));
+        std::fs::write(&stub_path, serde_json::to_vec(&stub).unwrap()).unwrap();
+        let report = compiler.verify(&root).unwrap();
+        assert!(report.errors.is_empty());
+        assert!(report.warnings.iter().any(|w| w.contains("accepted stub")));
+
+        // A malformed stub is an error, not a pass
+        std::fs::write(&stub_path, r#"{"id": "not-a-uuid"}"#).unwrap();
+        assert_eq!(compiler.verify(&root).unwrap().errors.len(), 1);
+
+        // An include by id only accepts a stub for that document
+        let other = uuid::Uuid::now_v7();
+        let by_id = TracedDocument::new("Root", &format!("```include\nid: {}\n```", other))
+            .set_status(DocStatus::Published);
+        let stub_path = dir.join(IncludeStub::path_for(&other.to_string()));
+        std::fs::write(&stub_path, serde_json::to_vec(&stub).unwrap()).unwrap();
+        let errors = compiler.verify(&by_id).unwrap().errors;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("different document"));
+        let mut renamed = stub.clone();
+        renamed.id = other;
+        std::fs::write(&stub_path, serde_json::to_vec(&renamed).unwrap()).unwrap();
+        assert!(compiler.verify(&by_id).unwrap().errors.is_empty());
+    }
+
+    #[test]
+    fn test_failing_automatic_traces() {
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("seal-reextract");
+        std::fs::write(dir.join("data.txt"), "Total: 42").unwrap();
+        let trace = |expected: &str, method: &str| {
+            format!(
+                "```trace\nsource: data.txt\nselector: \"Total: (\\\\d+)\"\nexpected: \"{}\"\nmethod: {}\n```\n",
+                expected, method
+            )
+        };
+        let body = [
+            trace("42", "automatic"),
+            trace("41", "automatic"),
+            trace("41", "manual"),
+        ]
+        .join("\n");
+        let mut doc = TracedDocument::new("Stale", &body);
+        doc.update_all_traces(&dir).unwrap();
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let failing = Compiler::new(&resolver).failing_automatic_traces(&doc);
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].0.expected, "41");
+        assert!(failing[0].1.contains("got '42'"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_extractor() {
+        use crate::compiler::Compiler;
+        use crate::config::{Workspace, WorkspaceConfig};
+        use crate::extraction::Extractor;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("exec-extractor");
+        std::fs::write(dir.join("data.bin"), "total=42\n").unwrap();
+        let config = WorkspaceConfig::from_toml(
+            r#"
+            [extractors.exec]
+            enabled = true
+            timeout_secs = 1
+            allow_args = ["field"]
+            [extractors.exec.commands]
+            field = ["sed", "-n"]
+            fail = ["sh", "-c", "echo broken >&2; exit 3"]
+            slow = ["sleep", "5"]
+            "#,
+        )
+        .unwrap();
+        let workspace = Workspace {
+            root: dir.clone(),
+            config,
+            config_path: None,
+        };
+        let exec = workspace.exec_extractor().unwrap();
+
+        // Arguments after the command name are appended to it, but only
+        // for commands that allow them
+        assert_eq!(
+            exec.extract(b"total=42\n", "field s/total=//p").unwrap(),
+            "42"
+        );
+        let err = exec.extract(b"", "fail --help").unwrap_err().to_string();
+        assert!(err.contains("takes no arguments"), "{}", err);
+        let err = exec.extract(b"", "fail").unwrap_err().to_string();
+        assert!(err.contains("broken"));
+        let err = exec.extract(b"", "slow").unwrap_err().to_string();
+        assert!(err.contains("timed out"));
+        assert!(exec.extract(b"", "rm -rf /").is_err());
+
+        let doc = TracedDocument::new(
+            "Exec",
+            "```trace\nsource: data.bin\nextractor: exec\nselector: field s/total=//p\nexpected: \"42\"\n```\n",
+        );
+        let resolver = FileResolver::new(&dir).unwrap();
+        // Disabled unless the workspace opts in
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(report.warnings.iter().any(|w| w.contains("disabled")));
+        let report = Compiler::new(&resolver)
+            .with_exec_extractor(Some(exec))
+            .verify(&doc)
+            .unwrap();
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sandboxed_extraction() {
+        use crate::compiler::Compiler;
+        use crate::config::{Workspace, WorkspaceConfig};
+        use crate::crypto::hash_bytes;
+        use crate::resolver::{FileResolver, SourceResolver};
+        use crate::sandbox::Sandbox;
+
+        let dir = temp_workspace("sandboxed-extraction");
+        std::fs::write(dir.join("data.txt"), "total=42\n").unwrap();
+        let config = WorkspaceConfig::from_toml(
+            r#"
+            [resolver.sandbox]
+            enabled = true
+            memory_mb = 64
+            allow_read = ["tools"]
+            "#,
+        )
+        .unwrap();
+        let workspace = Workspace {
+            root: dir.clone(),
+            config,
+            config_path: None,
+        };
+        let source = workspace
+            .resolver(&dir)
+            .unwrap()
+            .resolve("data.txt")
+            .unwrap();
+        assert_eq!(
+            source.metadata.sandbox,
+            Some(
+                Sandbox::new(Sandbox::default_helper())
+                    .with_memory_limit(64 * 1024 * 1024)
+                    .with_allowed_reads([dir.join("tools")])
+            )
+        );
+        let source = FileResolver::new(&dir)
+            .unwrap()
+            .resolve("data.txt")
+            .unwrap();
+        assert_eq!(source.metadata.sandbox, None);
+
+        let trace = TraceBlock::builder()
+            .source("data.txt")
+            .selector(r"total=(\d+)")
+            .extractor("regex")
+            .expected("7")
+            .build()
+            .unwrap();
+        let doc = TracedDocument::new("Sandboxed", "Body");
+        let check = |helper: &[&str]| {
+            let sandbox = Sandbox::new(helper.iter().map(|arg| arg.to_string()).collect());
+            let resolver = FileResolver::new(&dir).unwrap().with_sandbox(Some(sandbox));
+            Compiler::new(&resolver).check_document_trace(&doc, &trace)
+        };
+        // The helper's answer is what it found in the source, or why the
+        // check failed
+        let result = check(&[
+            "sh",
+            "-c",
+            r#"cat >/dev/null; printf '{"Ok":{"decompressed":0,"value":"7","normalized":false}}'"#,
+        ]);
+        assert!(result.passed, "{:?}", result.error);
+        // but the verdict is the verifier's: a value that misses the claim
+        // fails, and the hash is taken of the source itself, so neither a
+        // lying helper nor bytes it passes off as normalized can forge it
+        let result = check(&[
+            "sh",
+            "-c",
+            r#"cat >/dev/null; printf '{"Ok":{"decompressed":0,"value":"8","normalized":false}}'"#,
+        ]);
+        let error = result.error.unwrap();
+        assert!(error.contains("got '8'"), "{}", error);
+        let pinned = TraceBlock {
+            hash: Some(hash_bytes(b"forged")),
+            ..trace.clone()
+        };
+        let sandbox = Sandbox::new(vec![
+            "sh".into(),
+            "-c".into(),
+            r#"cat >/dev/null; printf '{"Ok":{"decompressed":0,"value":"7","normalized":true}}\nforged'"#.into(),
+        ]);
+        let resolver = FileResolver::new(&dir).unwrap().with_sandbox(Some(sandbox));
+        let compiler = Compiler::new(&resolver);
+        let result = compiler.check_document_trace(&doc, &pinned);
+        assert!(result.error.unwrap().contains("Hash mismatch"));
+        let pinned = TraceBlock {
+            hash: Some(hash_bytes(b"total=42\n")),
+            ..trace.clone()
+        };
+        let result = compiler.check_document_trace(&doc, &pinned);
+        assert!(result.passed, "{:?}", result.error);
+        let result = check(&[
+            "sh",
+            "-c",
+            r#"cat >/dev/null; printf '{"Err":{"Verification":"not 7"}}'"#,
+        ]);
+        assert_eq!(result.error.unwrap(), "Verification failed: not 7");
+        let result = check(&["sh", "-c", "cat >/dev/null; printf 7"]);
+        assert!(result.error.unwrap().contains("unreadable answer"));
+        // A helper that cannot run fails the trace rather than extracting here
+        let result = check(&["/nonexistent/rhodi", "sandbox-extract"]);
+        let error = result.error.unwrap();
+        assert!(error.contains("Sandboxed extraction failed"), "{}", error);
+        let result = check(&["sh", "-c", "cat >/dev/null; echo 'no Landlock' >&2; exit 1"]);
+        assert!(result.error.unwrap().contains("no Landlock"));
+    }
+
+    #[test]
+    fn test_render_preserves_frontmatter() {
+        use crate::markdown::render_tmd_preserving;
+
+        let original = "---\n# Owned by finance\ntitle: \"Q3\"   # working title\nid: 01a14607-b0c8-7972-8dbb-18b4d0e6af13\ndoc_status: draft\ncreated_at: 2025-01-01T00:00:00Z\ndoc_version: 0\n\npolicy:\n  allow_include: true\n---\n\nBody";
+        let mut doc = parse_tmd(original).unwrap();
+
+        // Nothing changed: the file is reproduced as written
+        assert_eq!(render_tmd_preserving(original, &doc).unwrap(), original);
+
+        doc.frontmatter.doc_version = 2;
+        doc.frontmatter.author = Some("Ada".into());
+        let rendered = render_tmd_preserving(original, &doc).unwrap();
+        assert!(rendered.starts_with("---\n# Owned by finance\ntitle: \"Q3\"   # working title\n"));
+        assert!(rendered.contains("doc_status: draft\ncreated_at: 2025-01-01T00:00:00Z\ndoc_version: 2\n\npolicy:\n  allow_include: true\nauthor: Ada\n---"));
+        assert_eq!(
+            parse_tmd(&rendered)
+                .unwrap()
+                .compute_version_hash()
+                .unwrap(),
+            doc.compute_version_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_trace_ids() {
+        use crate::compiler::Compiler;
+        use crate::markdown::{Section, parse_tmd_sections};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("trace-ids");
+        std::fs::write(dir.join("data.txt"), "Total: 42").unwrap();
+        let trace = |id: &str, expected: &str| {
+            format!(
+                "```trace\n{}source: data.txt\nselector: \"Total: (\\\\d+)\"\nexpected: \"{}\"\n```\n",
+                id, expected
+            )
+        };
+        let body = [trace("", "42"), trace("id: t-004\n", "41"), trace("", "42")].join("\n");
+        let mut doc = TracedDocument::new("Ids", &body);
+
+        // Numbering continues after the highest existing id
+        assert_eq!(doc.assign_trace_ids().unwrap(), 2);
+        let ids: Vec<_> = parse_tmd_sections(&doc.body)
+            .into_iter()
+            .filter_map(|s| match s {
+                Section::Trace(t) => t.id,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids, ["t-005", "t-004", "t-006"]);
+        assert_eq!(doc.assign_trace_ids().unwrap(), 0);
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let report = compiler.verify(&doc).unwrap();
+        assert!(report.errors.is_empty());
+        assert!(report.warnings[0].contains("trace t-004: "));
+
+        doc.body = doc.body.replace("t-006", "t-005");
+        let report = compiler.verify(&doc).unwrap();
+        let error = report.errors[0].to_string();
+        assert!(error.contains("'t-005' is used more than once"));
+    }
+
+    #[test]
+    fn test_key_certificates() {
+        use crate::certificate::{CertificateRef, KeyCertificate};
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+        use crate::version::VersionRegistry;
+
+        let dir = temp_workspace("key-certificate");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let versions = VersionRegistry::default();
+        let org = KeyPair::generate();
+        let author = KeyPair::generate();
+        let author_hex = hex::encode(author.verifying_key.as_bytes());
+        let compiler = Compiler::new(&resolver).with_trust_root(Some(org.verifying_key));
+
+        let mut doc = TracedDocument::new("Report", "Body");
+        doc.frontmatter.public_key = Some(author_hex.clone());
+        let mut doc = doc.seal(&author).unwrap();
+        // A valid seal alone is not enough under a trust root
+        assert_eq!(compiler.verify(&doc).unwrap().errors.len(), 1);
+
+        let certificate = KeyCertificate::issue(&org, &author_hex, Some("Alice"), None).unwrap();
+        doc.frontmatter.key_certificate = Some(CertificateRef::Inline(certificate.clone()));
+        assert!(compiler.verify(&doc).unwrap().errors.is_empty());
+        // Attaching the certificate does not disturb the seal
+        assert!(doc.verify(&author.verifying_key).is_ok());
+
+        // Referenced by path, and round-tripped through the frontmatter
+        std::fs::write(
+            dir.join("alice.json"),
+            serde_json::to_vec(&certificate).unwrap(),
+        )
+        .unwrap();
+        doc.frontmatter.key_certificate = Some(CertificateRef::Path("alice.json".into()));
+        let doc = parse_tmd(&crate::markdown::render_tmd(&doc).unwrap()).unwrap();
+        assert!(compiler.verify(&doc).unwrap().errors.is_empty());
+
+        // Another root, a tampered name and an expired certificate are refused
+        let other =
+            Compiler::new(&resolver).with_trust_root(Some(KeyPair::generate().verifying_key));
+        assert_eq!(other.verify(&doc).unwrap().errors.len(), 1);
+        let mut tampered = certificate.clone();
+        tampered.name = Some("Mallory".into());
+        assert!(
+            tampered
+                .verify(&org.verifying_key, chrono::Utc::now())
+                .is_err()
+        );
+        let expired = KeyCertificate::issue(
+            &org,
+            &author_hex,
+            None,
+            Some(chrono::Utc::now() - chrono::Duration::days(1)),
+        )
+        .unwrap();
+        assert!(
+            crate::certificate::check_trust_root(&doc, &expired, &org.verifying_key, &versions)
+                .is_err()
+        );
+        // A certificate for someone else's key does not cover this document
+        let stranger = hex::encode(KeyPair::generate().verifying_key.as_bytes());
+        let misissued = KeyCertificate::issue(&org, &stranger, None, None).unwrap();
+        assert!(
+            crate::certificate::check_trust_root(&doc, &misissued, &org.verifying_key, &versions)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_offline_verification() {
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("offline");
+        std::fs::write(dir.join("local.txt"), "Total: 42").unwrap();
+        let body = format!(
+            "```trace\nsource: local.txt\nhash: {}\nselector: \"Total: (\\\\d+)\"\nexpected: \"42\"\n```\n\n```trace\nid: t-002\nsource: https://example.com/data.csv\nselector: \"Total: (\\\\d+)\"\nexpected: \"7\"\n```\n\n```include\npath: https://example.com/part.tmd\n```",
+            crate::crypto::hash_bytes(b"Total: 42")
+        );
+        let key = KeyPair::generate();
+        let mut doc = TracedDocument::new("Offline", &body).set_status(DocStatus::Published);
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        doc.frontmatter.key_uri = Some("https://example.com/.well-known/rhodi-keys.json".into());
+        let doc = doc.seal(&key).unwrap();
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let online = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(!online.errors.is_empty());
+
+        let offline = Compiler::new(&resolver).with_offline(true);
+        let report = offline.verify(&doc).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.unverified.len(), 3);
+        assert!(
+            report
+                .unverified
+                .iter()
+                .any(|u| u.starts_with("Trace t-002"))
+        );
+
+        // The seal and local traces are still checked
+        let mut tampered = doc.clone();
+        tampered.body = tampered.body.replace("\"42\"", "\"43\"");
+        assert!(!offline.verify(&tampered).unwrap().errors.is_empty());
+    }
+
+    #[test]
+    fn test_include_by_id() {
+        use crate::compiler::Compiler;
+        use crate::index::{DocumentLocator, WorkspaceIndex};
+        use crate::markdown::{parse_include_block, render_tmd};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("include-by-id");
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::create_dir_all(dir.join("reports")).unwrap();
+        let key = KeyPair::generate();
+        let mut part = TracedDocument::new("Part", "Shared section");
+        part.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let part = part.seal(&key).unwrap();
+        let part_id = part.frontmatter.id;
+        std::fs::write(dir.join("parts/part.tmd"), render_tmd(&part).unwrap()).unwrap();
+
+        let block = format!("```include\nid: {}\n```", part_id);
+        assert_eq!(
+            parse_include_block(&block).unwrap().target(),
+            format!("id:{}", part_id)
+        );
+        assert!(parse_include_block("```include\nintegrity: x\n```").is_err());
+        assert!(
+            parse_include_block(&format!("```include\npath: a.tmd\nid: {}\n```", part_id)).is_err()
+        );
+
+        let main = TracedDocument::new("Main", &block).set_status(DocStatus::Published);
+        std::fs::write(dir.join("reports/main.tmd"), render_tmd(&main).unwrap()).unwrap();
+        let index = WorkspaceIndex::build(&dir).unwrap();
+        let indexed = index
+            .documents
+            .iter()
+            .find(|d| d.id == main.frontmatter.id)
+            .unwrap();
+        assert_eq!(indexed.includes, vec!["parts/part.tmd".to_string()]);
+
+        // Resolved from another directory, and again after the part moves
+        let resolver = FileResolver::new(dir.join("reports")).unwrap();
+        let verify = |dir: &std::path::Path| {
+            Compiler::new(&resolver)
+                .with_document_locator(Some(DocumentLocator::new(dir)))
+                .verify(&main)
+                .unwrap()
+        };
+        assert!(verify(&dir).errors.is_empty());
+        std::fs::rename(dir.join("parts/part.tmd"), dir.join("part.tmd")).unwrap();
+        assert!(verify(&dir).errors.is_empty());
+
+        // Without a locator, or with two files sharing the id, it fails
+        assert_eq!(
+            Compiler::new(&resolver).verify(&main).unwrap().errors.len(),
+            1
+        );
+        std::fs::copy(dir.join("part.tmd"), dir.join("parts/copy.tmd")).unwrap();
+        assert_eq!(verify(&dir).errors.len(), 1);
+
+        // The index names the shared id, and an include by it names neither copy
+        let index = WorkspaceIndex::build(&dir).unwrap();
+        let duplicates = index.duplicate_ids();
+        let paths: Vec<_> = duplicates[&part_id].iter().map(|d| d.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                std::path::PathBuf::from("part.tmd"),
+                std::path::PathBuf::from("parts/copy.tmd")
+            ]
+        );
+        assert_eq!(duplicates.len(), 1);
+        let indexed = index.find(main.frontmatter.id).unwrap().unwrap();
+        assert_eq!(indexed.includes, vec![format!("id:{}", part_id)]);
+
+        // Retargeting touches only the id line of includes of that id
+        let fresh = uuid::Uuid::now_v7();
+        let body = format!("{}\n\n```include\npath: other.tmd\n```\n", block);
+        let retargeted = crate::markdown::retarget_include_blocks(&body, part_id, fresh);
+        assert_eq!(retargeted, body.replace(&part_id.to_string(), &fresh.to_string()));
+        assert_eq!(
+            crate::markdown::retarget_include_blocks(&body, fresh, part_id),
+            body
+        );
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_fix_id() {
+        use crate::markdown::{parse_tmd, render_tmd};
+
+        let dir = temp_workspace("fix-id");
+        std::fs::write(dir.join("rhodi.toml"), "").unwrap();
+        let key = KeyPair::generate();
+        let sealed = |title: &str, id: Option<uuid::Uuid>| {
+            let mut doc = TracedDocument::new(title, "Shared section");
+            doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+            doc.frontmatter.id = id.unwrap_or(doc.frontmatter.id);
+            doc.seal(&key).unwrap()
+        };
+        let part = sealed("Part", None);
+        let id = part.frontmatter.id;
+        // A copy edited and sealed again keeps the original's id
+        let copy = sealed("Copy", Some(id));
+        std::fs::write(dir.join("part.tmd"), render_tmd(&part).unwrap()).unwrap();
+        std::fs::write(dir.join("copy.tmd"), render_tmd(&copy).unwrap()).unwrap();
+
+        let block = format!("```include\nid: {}\n```", id);
+        let includer = |title: &str, pin: &TracedDocument| {
+            let mut doc = TracedDocument::new(title, &block);
+            doc.frontmatter.includes = Some(
+                [(
+                    format!("id:{}", id),
+                    hex::encode(pin.frontmatter.version_hash.unwrap()),
+                )]
+                .into(),
+            );
+            render_tmd(&doc).unwrap()
+        };
+        let of_part = includer("Of part", &part);
+        std::fs::write(dir.join("of-part.tmd"), &of_part).unwrap();
+        std::fs::write(dir.join("of-copy.tmd"), includer("Of copy", &copy)).unwrap();
+
+        crate::cli::commands::fix_id::run(dir.join("copy.tmd"), false).unwrap();
+        let read =
+            |name: &str| parse_tmd(&std::fs::read_to_string(dir.join(name)).unwrap()).unwrap();
+        let fresh = read("copy.tmd").frontmatter.id;
+        assert_ne!(fresh, id);
+        assert_eq!(read("part.tmd").frontmatter.id, id);
+
+        // Only the include pinned to the copy follows it to the new id
+        let rewritten = read("of-copy.tmd");
+        assert_eq!(
+            rewritten.body,
+            block.replace(&id.to_string(), &fresh.to_string())
+        );
+        let pins = rewritten.frontmatter.includes.unwrap();
+        assert_eq!(
+            pins.get(&format!("id:{}", fresh)),
+            Some(&hex::encode(copy.frontmatter.version_hash.unwrap()))
+        );
+        assert!(!pins.contains_key(&format!("id:{}", id)));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("of-part.tmd")).unwrap(),
+            of_part
+        );
+        assert_eq!(
+            crate::index::documents_with_id(&dir, id).unwrap(),
+            vec![std::path::PathBuf::from("part.tmd")]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_staged_documents() {
+        use crate::cli::git::staged_documents_in;
+        use std::path::PathBuf;
+
+        let dir = temp_workspace("staged");
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for file in ["kept.tmd", "deleted.tmd", "renamed.tmd"] {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+        git(&["add", "."]);
+        git(&["commit", "-qm", "base"]);
+
+        // Only added, modified and renamed .tmd files count
+        std::fs::write(dir.join("kept.tmd"), "changed").unwrap();
+        std::fs::write(dir.join("sub/new.tmd"), "new").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a document").unwrap();
+        std::fs::write(dir.join("unstaged.tmd"), "not staged").unwrap();
+        git(&["add", "kept.tmd", "sub/new.tmd", "notes.txt"]);
+        git(&["rm", "-q", "deleted.tmd"]);
+        git(&["mv", "renamed.tmd", "moved.tmd"]);
+
+        let mut staged = staged_documents_in(&dir).unwrap();
+        staged.sort();
+        assert_eq!(
+            staged,
+            ["kept.tmd", "moved.tmd", "sub/new.tmd"].map(PathBuf::from)
+        );
+
+        // From a subdirectory, paths are relative to it and limited to it
+        assert_eq!(
+            staged_documents_in(&dir.join("sub")).unwrap(),
+            [PathBuf::from("new.tmd")]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_stdio_documents() {
+        use crate::cli::io::{STDIO_PATH, is_stdio, read_document_from, write_document_to};
+
+        let dir = temp_workspace("stdio");
+        let stdio = std::path::Path::new(STDIO_PATH);
+        assert!(is_stdio(stdio));
+        // Only the bare `-` is stdio; a file named `-` is reached by path
+        assert!(!is_stdio(&dir.join("-")));
+
+        // `-` reads stdin and writes stdout, newline-terminated
+        assert_eq!(
+            read_document_from(stdio, &b"from stdin"[..]).unwrap(),
+            "from stdin"
+        );
+        let mut stdout = Vec::new();
+        write_document_to(stdio, "no newline", &mut stdout).unwrap();
+        write_document_to(stdio, "newline\n", &mut stdout).unwrap();
+        assert_eq!(stdout, b"no newline\nnewline\n");
+
+        // Any other path is a file, written as given
+        let file = dir.join("doc.tmd");
+        let mut stdout = Vec::new();
+        write_document_to(&file, "no newline", &mut stdout).unwrap();
+        assert!(stdout.is_empty());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "no newline");
+        assert_eq!(
+            read_document_from(&file, &b"from stdin"[..]).unwrap(),
+            "no newline"
+        );
+    }
+
+    #[test]
+    fn test_verify_detailed() {
+        use crate::compiler::{Compiler, SectionOutcome, VerifyPasses};
+        use crate::markdown::Section;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("verify-detailed");
+        std::fs::write(dir.join("data.txt"), "Total: 42").unwrap();
+        let hash = crate::crypto::hash_bytes(b"Total: 42");
+        let trace = |expected: &str| {
+            format!(
+                "```trace\nsource: data.txt\nhash: {}\nselector: \"Total: (\\\\d+)\"\nexpected: \"{}\"\n```",
+                hash, expected
+            )
+        };
+        let body = format!(
+            "# Results\n\n{}\n\n{}\n\n```include\npath: missing.tmd\n```",
+            trace("42"),
+            trace("41")
+        );
+        let doc = TracedDocument::new("Detailed", &body);
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+
+        let detailed = compiler.verify_detailed(&doc).unwrap();
+        let outcomes: Vec<SectionOutcome> = detailed.sections.iter().map(|s| s.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                SectionOutcome::NotChecked,
+                SectionOutcome::Passed,
+                SectionOutcome::Warning,
+                SectionOutcome::Failed,
+            ]
+        );
+        assert!(matches!(detailed.sections[3].section, Section::Include(_)));
+
+        // The per-section findings add up to the flat report
+        let flat = compiler.verify(&doc).unwrap();
+        let merged = detailed.into_report();
+        assert_eq!(flat.errors.len(), merged.errors.len());
+        assert_eq!(flat.warnings, merged.warnings);
+
+        let no_includes = VerifyPasses {
+            includes: false,
+            ..Default::default()
+        };
+        let detailed = compiler
+            .with_passes(no_includes)
+            .verify_detailed(&doc)
+            .unwrap();
+        assert_eq!(detailed.sections[3].outcome, SectionOutcome::NotChecked);
+    }
+
+    #[test]
+    fn test_redact_blocks() {
+        use crate::redaction::{self, RedactBlock};
+
+        let keypair = KeyPair::generate();
+        let body = "Intro\n\n```redact\nlabel: Salary\ncontent: |\n  120,000 EUR\n```\n\nOutro\n";
+        let doc = TracedDocument::new("Report", body).seal(&keypair).unwrap();
+        // Sealing salts the block
+        assert!(doc.body.contains("salt: "));
+
+        let mut shared = doc.clone();
+        shared.body = redaction::redact(&doc.body).unwrap();
+        assert!(!shared.body.contains("120,000"));
+        assert_eq!(redaction::count(&shared.body), (1, 1));
+        // Both renditions verify under the same seal
+        assert!(doc.verify(&keypair.verifying_key).is_ok());
+        assert!(shared.verify(&keypair.verifying_key).is_ok());
+
+        // Changing the plaintext breaks the commitment
+        let mut tampered = doc.clone();
+        tampered.body = doc.body.replace("120,000", "130,000");
+        assert!(tampered.verify(&keypair.verifying_key).is_err());
+
+        // Unsalted blocks cannot be redacted, and a block needs exactly one form
+        assert!(redaction::redact(body).is_err());
+        assert!(RedactBlock::parse("content: a\ncommitment: sha256:00\n").is_err());
+
+        // The hash covers the canonical JSON of the label and commitment,
+        // however the block's YAML is written
+        let reworded = shared.body.replace("label: Salary", "label: \"Salary\"");
+        assert_ne!(reworded, shared.body);
+        assert_eq!(
+            redaction::commit_redactions(&reworded),
+            redaction::commit_redactions(&shared.body)
+        );
+        let committed = redaction::commit_redactions(&doc.body);
+        assert_eq!(committed, redaction::commit_redactions(&shared.body));
+        assert!(committed.contains("```redact\n{\"commitment\":\"sha256:"));
+        assert!(committed.contains("\",\"label\":\"Salary\"}\n```"));
+
+        // Before 2.4 redact blocks are hashed as written: sealing leaves
+        // them unsalted, so they cannot be redacted under the seal
+        let mut old = TracedDocument::new("Report", body);
+        old.frontmatter.protocol_version = "2.3".to_string();
+        let old = old.seal(&keypair).unwrap();
+        assert!(!old.body.contains("salt: "));
+        let mut tampered = old.clone();
+        tampered.body = body.replace("120,000", "130,000");
+        assert!(tampered.verify(&keypair.verifying_key).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_anomalies() {
+        use crate::compiler::Compiler;
+        use crate::models::check_version_sequence;
+        use crate::resolver::FileResolver;
+
+        let now = chrono::Utc::now();
+        let skew = chrono::Duration::minutes(5);
+        let mut doc = TracedDocument::new("Report", "Body");
+        assert!(doc.frontmatter.timestamp_anomalies(now, skew).is_empty());
+
+        // Slightly ahead is tolerated, an hour ahead is not
+        doc.frontmatter.created_at = now + chrono::Duration::minutes(2);
+        assert!(doc.frontmatter.timestamp_anomalies(now, skew).is_empty());
+        doc.frontmatter.created_at = now + chrono::Duration::hours(1);
+        doc.frontmatter.modified_at = Some(now);
+        let anomalies = doc.frontmatter.timestamp_anomalies(now, skew);
+        assert_eq!(anomalies.len(), 2, "{:?}", anomalies);
+
+        // verify reports them as warnings, so --strict fails
+        let dir = temp_workspace("timestamps");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(report.errors.is_empty());
+        assert_eq!(report.warnings.len(), 2);
+        let report = Compiler::new(&resolver)
+            .with_clock_skew(chrono::Duration::days(1))
+            .verify(&doc)
+            .unwrap();
+        assert_eq!(report.warnings.len(), 1);
+
+        // Versions must move forward
+        let keypair = KeyPair::generate();
+        let v1 = TracedDocument::new("Report", "Body")
+            .seal(&keypair)
+            .unwrap();
+        let v2 = v1.clone().seal(&keypair).unwrap();
+        assert!(check_version_sequence(&[v1.clone(), v2.clone()]).is_empty());
+        let anomalies = check_version_sequence(&[v2, v1]);
+        assert_eq!(anomalies.len(), 2, "{:?}", anomalies);
+    }
+
+    #[test]
+    fn test_content_type_extractor_selection() {
+        use crate::compiler::Compiler;
+        use crate::extraction::select_extractor;
+        use crate::resolver::{FileResolver, SourceResolver};
+
+        let dir = temp_workspace("content-type");
+        std::fs::write(dir.join("metrics.json"), r#"{"total": 42}"#).unwrap();
+        std::fs::write(dir.join("run.yaml"), "loss: 0.25\n").unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+
+        let resolved = resolver.resolve("metrics.json").unwrap();
+        assert_eq!(
+            resolved.metadata.content_type.as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(resolved.metadata.size, 13);
+        assert!(resolved.metadata.modified.is_some());
+
+        // Structured extractors need a selector written for them
+        let json = Some("application/json");
+        assert_eq!(select_extractor(None, json, "$.total"), "jsonpath");
+        assert_eq!(select_extractor(Some("auto"), json, "$.total"), "jsonpath");
+        assert_eq!(select_extractor(None, json, r#""total": (\d+)"#), "regex");
+        assert_eq!(select_extractor(Some("regex"), json, "$.total"), "regex");
+        assert_eq!(
+            select_extractor(None, Some("application/yaml"), ".loss"),
+            "yaml"
+        );
+        assert_eq!(select_extractor(None, None, ".loss"), "regex");
+
+        let compiler = Compiler::new(&resolver);
+        let doc = TracedDocument::new("Report", "Body");
+        for (source, selector, expected) in [
+            ("metrics.json", "$.total", "42"),
+            ("metrics.json", r#""total": (\d+)"#, "42"),
+            ("run.yaml", ".loss", "0.25"),
+        ] {
+            let trace = TraceBlock {
+                source: source.into(),
+                selector: Some(selector.into()),
+                expected: expected.into(),
+                ..Default::default()
+            };
+            let result = compiler.check_document_trace(&doc, &trace);
+            assert!(result.passed, "{}: {:?}", selector, result.error);
+        }
+    }
+
+    #[test]
+    fn test_include_manifest() {
+        use crate::compiler::Compiler;
+        use crate::markdown::render_tmd;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("include-manifest");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let keypair = KeyPair::generate();
+
+        let sub = TracedDocument::new("Sub", "Sub body")
+            .seal(&keypair)
+            .unwrap();
+        std::fs::write(dir.join("sub.tmd"), render_tmd(&sub).unwrap()).unwrap();
+
+        let mut main = TracedDocument::new("Main", "```include\npath: sub.tmd\n```\n");
+        let manifest = compiler.include_manifest(&main).unwrap();
+        assert_eq!(
+            manifest.get("sub.tmd"),
+            sub.frontmatter.version_hash.map(hex::encode).as_ref()
+        );
+        main.frontmatter.includes = Some(manifest);
+        let main = main.seal(&keypair).unwrap();
+        assert!(compiler.verify(&main).unwrap().errors.is_empty());
+
+        // A different sealed version of the included file is caught
+        let mut resealed = sub.clone();
+        resealed.frontmatter.doc_status = DocStatus::Draft;
+        let resealed = resealed.seal(&keypair).unwrap();
+        std::fs::write(dir.join("sub.tmd"), render_tmd(&resealed).unwrap()).unwrap();
+        let report = compiler.verify(&main).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].to_string().contains("pins"));
+
+        // The manifest itself is covered by the seal
+        let mut tampered = main.clone();
+        tampered.frontmatter.includes = Some(std::collections::BTreeMap::from([(
+            "sub.tmd".to_string(),
+            hex::encode(resealed.frontmatter.version_hash.unwrap()),
+        )]));
+        assert!(tampered.verify(&keypair.verifying_key).is_err());
+    }
+
+    #[test]
+    fn test_author_profiles() {
+        use crate::identity::{AuthorProfile, ProfileStore, validate_orcid};
+        use crate::markdown::render_tmd;
+        use crate::models::Author;
+
+        assert!(validate_orcid("0000-0002-1825-0097").is_ok());
+        assert!(validate_orcid("0000-0002-1694-233X").is_ok());
+        assert!(validate_orcid("0000-0002-1825-0098").is_err());
+        assert!(validate_orcid("0000000218250097").is_err());
+
+        let store = ProfileStore::at(temp_workspace("profiles")).unwrap();
+        let profile = AuthorProfile {
+            profile: "ada".into(),
+            name: "Ada Lovelace".into(),
+            orcid: Some("0000-0002-1825-0097".into()),
+            key: Some("ada-key".into()),
+            ..Default::default()
+        };
+        store.save(&profile).unwrap();
+        assert_eq!(store.get("ada").unwrap(), profile);
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert!(store.get("../ada").is_err());
+
+        // A plain author keeps its string form and the hash it had before
+        // profiles existed
+        let golden = crate::testkit::golden_document("1.0");
+        assert_eq!(
+            hex::encode(golden.compute_version_hash().unwrap()),
+            "9e525529707e0e97ecead65374e35f16f22f9a402fd890f4a7c6e937f054cc07"
+        );
+        let plain = TracedDocument::new("Report", "Body").author("Ada Lovelace");
+        assert!(
+            render_tmd(&plain)
+                .unwrap()
+                .contains("author: Ada Lovelace\n")
+        );
+
+        // A structured author round-trips and is covered by the hash
+        let keypair = KeyPair::generate();
+        let mut doc = plain.clone();
+        doc.frontmatter.author = Some(profile.author());
+        let doc = doc.seal(&keypair).unwrap();
+        assert_ne!(
+            doc.compute_version_hash().unwrap(),
+            plain.compute_version_hash().unwrap()
+        );
+        let parsed = parse_tmd(&render_tmd(&doc).unwrap()).unwrap();
+        assert_eq!(parsed.frontmatter.author, Some(profile.author()));
+        assert!(parsed.verify(&keypair.verifying_key).is_ok());
+
+        let mut tampered = parsed.clone();
+        tampered.frontmatter.author.as_mut().unwrap().orcid = None;
+        assert!(tampered.verify(&keypair.verifying_key).is_err());
+
+        // In 1.x, a name spelled like a profile does not hash like one
+        let mut profiled = plain.clone();
+        profiled.frontmatter.protocol_version = "1.0".into();
+        profiled.frontmatter.author = Some(profile.author());
+        let mut spoofed = profiled.clone();
+        spoofed.frontmatter.author = Some(Author::from(
+            crate::canonical::to_jcs(&serde_json::to_value(profile.author()).unwrap()).unwrap(),
+        ));
+        assert_ne!(
+            profiled.compute_version_hash().unwrap(),
+            spoofed.compute_version_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_html_normalization() {
+        use crate::compiler::Compiler;
+        use crate::extraction::html_to_text;
+        use crate::resolver::FileResolver;
+
+        let page = |tracking: &str, generated: &str| {
+            format!(
+                "<html><head><script>var generated = \"{}\";</script>\n<style>p {{ color: red }}</style></head>\n<body><!-- build {} -->\n<h1>Q3   Report</h1><p>Revenue:\n  <b>1,204</b> &amp; rising</p>\n<a href=\"/more?utm_source={}\">More</a><p>1 &lt; 2 &#x2013; a < b</p></body></html>",
+                generated, generated, tracking
+            )
+        };
+        assert_eq!(
+            html_to_text(&page("mail", "09:00")),
+            "Q3 Report\nRevenue: 1,204 & rising\nMore\n1 < 2 \u{2013} a < b"
+        );
+
+        let dir = temp_workspace("html-normalize");
+        std::fs::write(dir.join("page.html"), page("mail", "09:00")).unwrap();
+        let mut trace = TraceBlock {
+            source: "page.html".into(),
+            selector: Some(r"Revenue: ([\d,]+)".into()),
+            expected: "1,204".into(),
+            normalize: Some("html".into()),
+            ..Default::default()
+        };
+        trace.update_hash(&dir).unwrap();
+        assert_eq!(
+            trace.hash.as_deref(),
+            Some(
+                crate::crypto::hash_bytes(html_to_text(&page("mail", "09:00")).as_bytes()).as_str()
+            )
+        );
+
+        // Tracking parameters and scripts change; the visible text does not
+        std::fs::write(dir.join("page.html"), page("feed", "17:45")).unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let doc = TracedDocument::new("Report", "Body");
+        let result = compiler.check_document_trace(&doc, &trace);
+        assert!(result.passed, "{:?}", result.error);
+
+        // Without normalization the raw page no longer matches its hash
+        let raw = TraceBlock {
+            normalize: None,
+            ..trace.clone()
+        };
+        assert!(!compiler.check_document_trace(&doc, &raw).passed);
+
+        let unknown = TraceBlock {
+            normalize: Some("pdf".into()),
+            ..trace
+        };
+        let result = compiler.check_document_trace(&doc, &unknown);
+        assert!(result.error.unwrap().contains("Unknown normalization"));
+    }
+
+    #[test]
+    fn test_compressed_sources() {
+        use crate::compiler::{Compiler, CompilerLimits};
+        use crate::resolver::{FileResolver, guess_content_type};
+        use std::io::Write;
+
+        let csv = "quarter,revenue\nQ3,1204\n";
+        let dir = temp_workspace("compressed-sources");
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(csv.as_bytes()).unwrap();
+        std::fs::write(dir.join("runs.csv.gz"), gzip.finish().unwrap()).unwrap();
+        std::fs::write(
+            dir.join("runs.csv.zst"),
+            ruzstd::encoding::compress_to_vec(
+                csv.as_bytes(),
+                ruzstd::encoding::CompressionLevel::Fastest,
+            ),
+        )
+        .unwrap();
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in [("2024/q3.csv", csv), ("README", "Quarterly runs")] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        std::fs::write(dir.join("runs.zip"), zip.finish().unwrap().into_inner()).unwrap();
+        assert_eq!(guess_content_type("runs.csv.gz"), Some("text/csv"));
+        assert_eq!(guess_content_type("runs.zip!2024/q3.csv"), Some("text/csv"));
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let doc = TracedDocument::new("Report", "Body");
+        let expected_hash = crate::crypto::hash_bytes(csv.as_bytes());
+        for (source, compression) in [
+            ("runs.csv.gz", "gzip"),
+            ("runs.csv.zst", "zstd"),
+            ("runs.zip!2024/q3.csv", "zip"),
+        ] {
+            let mut trace = TraceBlock::builder()
+                .source(source)
+                .compression(compression)
+                .selector("cell:B2")
+                .extractor("cell")
+                .expected("1204")
+                .build()
+                .unwrap();
+            trace.update_hash(&dir).unwrap();
+            // The hash covers the content, not the archive around it
+            assert_eq!(trace.hash.as_deref(), Some(expected_hash.as_str()));
+            let result = compiler.check_document_trace(&doc, &trace);
+            assert!(result.passed, "{}: {:?}", source, result.error);
+        }
+
+        // Decompressed bytes count against the limits
+        let zstd = TraceBlock::builder()
+            .source("runs.csv.zst")
+            .compression("zstd")
+            .selector("cell:B2")
+            .extractor("cell")
+            .expected("1204")
+            .build()
+            .unwrap();
+        let limited = |limits: CompilerLimits| {
+            Compiler::new(&resolver)
+                .with_limits(limits)
+                .check_document_trace(&doc, &zstd)
+                .error
+                .unwrap()
+        };
+        let error = limited(CompilerLimits {
+            max_decompressed_bytes: 10,
+            ..Default::default()
+        });
+        assert!(
+            error.contains("Decompressed source exceeds 10 bytes"),
+            "{}",
+            error
+        );
+        let stored = std::fs::metadata(dir.join("runs.csv.zst")).unwrap().len();
+        let error = limited(CompilerLimits {
+            max_resolved_bytes: stored + csv.len() as u64 - 1,
+            ..Default::default()
+        });
+        assert!(error.contains("Resolved sources exceed"), "{}", error);
+        let config =
+            crate::config::WorkspaceConfig::from_toml("[limits]\nmax_decompressed_bytes = 10\n")
+                .unwrap();
+        assert_eq!(config.limits.compiler_limits().max_decompressed_bytes, 10);
+
+        // A zip of several files needs an entry; a missing entry is named
+        let whole = TraceBlock {
+            source: "runs.zip".into(),
+            compression: Some("zip".into()),
+            expected: "1204".into(),
+            selector: Some("1204".into()),
+            ..Default::default()
+        };
+        let error = compiler.check_document_trace(&doc, &whole).error.unwrap();
+        assert!(error.contains("holds 2 files"), "{}", error);
+        let missing = TraceBlock {
+            source: "runs.zip!2024/q4.csv".into(),
+            ..whole.clone()
+        };
+        let error = compiler.check_document_trace(&doc, &missing).error.unwrap();
+        assert!(error.contains("No entry 2024/q4.csv"), "{}", error);
+
+        // An entry without `compression: zip`, or a corrupt stream, fails
+        let undeclared = TraceBlock {
+            compression: None,
+            ..missing
+        };
+        assert!(!compiler.check_document_trace(&doc, &undeclared).passed);
+        std::fs::write(dir.join("runs.csv.gz"), b"not gzip").unwrap();
+        let corrupt = TraceBlock {
+            source: "runs.csv.gz".into(),
+            compression: Some("gzip".into()),
+            ..whole
+        };
+        let error = compiler.check_document_trace(&doc, &corrupt).error.unwrap();
+        assert!(error.contains("Cannot decompress"), "{}", error);
+        assert!(
+            TraceBlock::builder()
+                .source("runs.csv.xz")
+                .compression("xz")
+                .expected("1")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_verification_report() {
+        use crate::compiler::{Compiler, SectionOutcome};
+        use crate::report::{DocumentReport, REPORT_SCHEMA_VERSION, VerificationReport};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("report");
+        std::fs::write(dir.join("data.txt"), "Total: 42").unwrap();
+        let body = "```trace\nid: t-001\nsource: data.txt\nselector: \"Total: (\\\\d+)\"\nexpected: \"41\"\n```\n\n```include\npath: missing.tmd\n```\n";
+        let doc = TracedDocument::new("Report", body);
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+
+        let verified = compiler.verify_detailed(&doc).unwrap();
+        let entry = DocumentReport::new(Some("report.tmd".into()), &doc, &verified);
+        let report = verified.into_report();
+        let mut record = VerificationReport::new("verify");
+        record
+            .documents
+            .push(entry.finish(&report, std::time::Duration::from_millis(3)));
+        record.documents.push(DocumentReport::unverifiable(
+            Some("broken.tmd".into()),
+            &RhodiError::Format("No frontmatter".into()),
+            std::time::Duration::ZERO,
+        ));
+
+        let path = dir.join("report.json");
+        record.write(&path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], REPORT_SCHEMA_VERSION);
+        let document = &json["documents"][0];
+        assert_eq!(document["outcome"], "failed");
+        assert_eq!(document["duration_ms"], 3.0);
+        assert_eq!(document["traces"][0]["id"], "t-001");
+        assert_eq!(document["traces"][0]["outcome"], "warning");
+        assert_eq!(document["includes"][0]["target"], "missing.tmd");
+        assert_eq!(document["includes"][0]["outcome"], "failed");
+        assert_eq!(json["documents"][1]["id"], serde_json::Value::Null);
+        assert_eq!(
+            json["documents"][1]["errors"][0],
+            "Format error: No frontmatter"
+        );
+
+        // Every field the published schema requires is present
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../../specs/report.schema.json")).unwrap();
+        let required = |value: &serde_json::Value| -> Vec<String> {
+            let mut keys: Vec<String> = Vec::new();
+            for part in value["allOf"]
+                .as_array()
+                .cloned()
+                .unwrap_or_else(|| vec![value.clone()])
+            {
+                let part = match part["$ref"].as_str() {
+                    Some(reference) => schema["definitions"]
+                        [reference.trim_start_matches("#/definitions/")]
+                    .clone(),
+                    None => part,
+                };
+                for key in part["required"].as_array().into_iter().flatten() {
+                    keys.push(key.as_str().unwrap().to_string());
+                }
+            }
+            keys
+        };
+        let present = |value: &serde_json::Value, keys: Vec<String>| {
+            for key in keys {
+                assert!(value.get(&key).is_some(), "missing '{}'", key);
+            }
+        };
+        present(&json, required(&schema));
+        let definitions = &schema["definitions"];
+        for document in json["documents"].as_array().unwrap() {
+            present(document, required(&definitions["DocumentReport"]));
+            for trace in document["traces"].as_array().unwrap() {
+                present(trace, required(&definitions["TraceReport"]));
+            }
+            for include in document["includes"].as_array().unwrap() {
+                present(include, required(&definitions["IncludeReport"]));
+            }
+        }
+        let parsed: VerificationReport = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            parsed.documents[0].traces[0].outcome,
+            SectionOutcome::Warning
+        );
+    }
+
+    #[test]
+    fn test_review_session() {
+        use crate::attestation::{Attestation, Verdict};
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+        use crate::review::ReviewSession;
+
+        let dir = temp_workspace("review");
+        std::fs::write(
+            dir.join("data.txt"),
+            "Header\nUnits: 12\nTotal: 42\nFooter\n",
+        )
+        .unwrap();
+        let trace = |expected: &str| {
+            format!(
+                "```trace\nsource: data.txt\nselector: \"Total: (\\\\d+)\"\nexpected: \"{}\"\n```",
+                expected
+            )
+        };
+        let body = format!(
+            "# Results\n\n## Sales\n\nWe sold 42 units in total.\n\n{}\n\nA later restatement said 41.\n\n{}\n",
+            trace("42"),
+            trace("41")
+        );
+        let keypair = KeyPair::generate();
+        let doc = TracedDocument::new("Review", &body).seal(&keypair).unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        let mut session = ReviewSession::new(&Compiler::new(&resolver), &doc);
+
+        assert_eq!(session.items.len(), 2);
+        let first = session.current().unwrap();
+        assert_eq!(first.heading_path, vec!["Results", "Sales"]);
+        assert_eq!(first.claim.as_deref(), Some("We sold 42 units in total."));
+        assert!(first.result.passed);
+        let snippet = first.snippet.as_ref().unwrap();
+        assert_eq!(snippet.match_line, 3);
+        assert_eq!(
+            snippet.lines[snippet.match_line - snippet.first_line],
+            "Total: 42"
+        );
+        assert!(!session.items[1].result.passed);
+
+        session.approve();
+        assert_eq!(session.current, 1);
+        assert!(!session.is_complete());
+        session.flag(Some("Source says 42".into()));
+        assert!(session.is_complete());
+        session.previous();
+        assert_eq!(session.current, 0);
+
+        let reviews = session.reviews();
+        assert_eq!(reviews[0].verdict, Verdict::Approved);
+        assert_eq!(reviews[1].verdict, Verdict::Flagged);
+        assert!(!reviews[1].passed);
+        assert_eq!(reviews[1].note.as_deref(), Some("Source says 42"));
+
+        // The verdicts are covered by the attestation's signature
+        let attestation =
+            Attestation::create_with_reviews(&doc, &keypair, Vec::new(), reviews).unwrap();
+        attestation.verify().unwrap();
+        let mut tampered = attestation.clone();
+        tampered.reviews[1].verdict = Verdict::Approved;
+        assert!(tampered.verify().is_err());
+        let json = serde_json::to_string(&attestation).unwrap();
+        let parsed: Attestation = serde_json::from_str(&json).unwrap();
+        parsed.verify().unwrap();
+    }
+
+    #[test]
+    fn test_nested_fences() {
+        use crate::markdown::{Section, markdown_title, parse_tmd_sections};
+
+        let body = "Intro.\n\n````markdown\n```trace\nsource: \"a.txt\"\nexpected: \"1\"\n```\n````\n\n```yaml\nkey: value\n```\n\n~~~trace\nsource: \"b.txt\"\nexpected: \"2\"\n~~~\n\n````trace\nsource: \"c.txt\"\nexpected: \"```\"\n````\n";
+        let sections = parse_tmd_sections(body);
+        let traces: Vec<&TraceBlock> = sections
+            .iter()
+            .filter_map(|s| match s {
+                Section::Trace(t) => Some(t),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].source, "b.txt");
+        assert_eq!(traces[1].source, "c.txt");
+        assert_eq!(traces[1].expected, "```");
+        // The example trace and the yaml block stay in the paragraph
+        let Section::Paragraph(text) = &sections[0] else {
+            panic!("expected a paragraph first");
+        };
+        assert!(text.contains("source: \"a.txt\""));
+        assert!(text.contains("key: value"));
+
+        // Inline code in an info string doesn't open a fence
+        assert_eq!(
+            markdown_title("```` ```x ````\n# Title\n").as_deref(),
+            Some("Title")
+        );
+        assert_eq!(markdown_title("~~~\n# Not a title\n~~~\n"), None);
+    }
+
+    #[test]
+    fn test_trace_presets() {
+        use crate::compiler::Compiler;
+        use crate::config::WorkspaceConfig;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("presets");
+        std::fs::write(
+            dir.join("metrics.json"),
+            r#"{"runs": {"a": {"accuracy": 0.9121}, "b": {"accuracy": 0.8}}}"#,
+        )
+        .unwrap();
+        let config = WorkspaceConfig::from_toml(
+            "[presets.experiment-metric]\nextractor = \"jsonpath\"\ntransforms = [\"round:2\"]\nmax_age = \"90d\"\n\n[presets.experiment-metric.tolerance]\nabsolute = 0.01\n",
+        )
+        .unwrap();
+        let preset = &config.presets["experiment-metric"];
+        assert_eq!(preset.transforms, vec!["round:2".to_string()]);
+        assert_eq!(
+            WorkspaceConfig::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let doc = TracedDocument::new(
+            "Presets",
+            &format!(
+                "```trace\nsource: metrics.json\npreset: experiment-metric\nselector: $.runs.a.accuracy\nexpected: \"0.905\"\ntimestamp: {now}\n```\n\n```trace\nsource: metrics.json\npreset: experiment-metric\nselector: $.runs.b.accuracy\nexpected: \"0.8\"\ntimestamp: {now}\ntolerance:\n  absolute: 0.0\n```\n"
+            ),
+        );
+        let resolver = FileResolver::new(&dir).unwrap();
+        // A compiler without the workspace's presets can't expand them
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(report.warnings.iter().any(|w| w.contains("unknown preset")));
+
+        let compiler = Compiler::new(&resolver).with_presets(config.presets.clone());
+        let report = compiler.verify(&doc).unwrap();
+        assert!(
+            report.errors.is_empty() && report.warnings.is_empty(),
+            "{:?}",
+            report
+        );
+
+        // The trace's own fields win over the preset's
+        let trace = TraceBlock {
+            preset: Some("experiment-metric".into()),
+            extractor: Some("regex".into()),
+            ..Default::default()
+        };
+        let expanded = trace.expand_preset(&config.presets).unwrap();
+        assert_eq!(expanded.extractor.as_deref(), Some("regex"));
+        assert_eq!(expanded.max_age.as_deref(), Some("90d"));
+
+        // Publishing writes the presets into the traces, so the sealed
+        // document checks the same without the workspace's presets
+        let keypair = KeyPair::generate();
+        let mut doc = doc;
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let published = compiler.publish(doc, &keypair).unwrap();
+        assert!(!published.body.contains("preset:"));
+        assert!(published.body.contains("extractor: jsonpath"));
+        assert!(published.body.contains("absolute: 0.0\n"));
+        assert!(published.verify(&keypair.verifying_key).is_ok());
+        let report = Compiler::new(&resolver).verify(&published).unwrap();
+        assert!(
+            report.errors.is_empty() && report.warnings.is_empty(),
+            "{:?}",
+            report
+        );
+    }
+
+    #[test]
+    fn test_byte_range_selectors() {
+        use crate::extraction::{BytesExtractor, Extractor, select_extractor};
+
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDRcamera=X100";
+        let bytes = BytesExtractor;
+        assert_eq!(bytes.extract(png, "bytes:0..4").unwrap(), "89504e47");
+        assert_eq!(bytes.extract(png, "bytes:0x0c..4:utf8").unwrap(), "IHDR");
+        assert_eq!(
+            bytes.extract(png, "bytes:16..11:utf8").unwrap(),
+            "camera=X100"
+        );
+        assert!(bytes.extract(png, "bytes:0..4:utf8").is_err());
+        assert!(bytes.extract(png, "bytes:20..100").is_err());
+        assert!(bytes.extract(png, "bytes:0-4").is_err());
+        assert!(bytes.extract(png, "bytes:0..4:base64").is_err());
+
+        // Chosen from the selector, whatever the content type
+        assert_eq!(
+            select_extractor(None, Some("application/json"), "bytes:0..4"),
+            "bytes"
+        );
+    }
+
+    #[test]
+    fn test_freeze_workspace() {
+        use crate::freeze::WorkspaceManifest;
+        use crate::markdown::render_tmd;
+
+        let dir = temp_workspace("freeze");
+        let keypair = KeyPair::generate();
+        let write = |name: &str, doc: &TracedDocument| {
+            std::fs::write(dir.join(name), render_tmd(doc).unwrap()).unwrap()
+        };
+        let a = TracedDocument::new("A", "First.").seal(&keypair).unwrap();
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        write("docs/a.tmd", &a);
+        write(
+            "b.tmd",
+            &TracedDocument::new("B", "Second.").seal(&keypair).unwrap(),
+        );
+        // Drafts are not part of a release
+        write("draft.tmd", &TracedDocument::new("Draft", "Wip."));
+
+        let manifest = WorkspaceManifest::create(&dir, Some("v1".into()), &keypair).unwrap();
+        let paths: Vec<&str> = manifest.documents.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["b.tmd", "docs/a.tmd"]);
+        manifest.verify().unwrap();
+        let path = dir.join("rhodi.freeze.json");
+        manifest.write(&path).unwrap();
+        let read = WorkspaceManifest::read(&path).unwrap();
+        assert_eq!(read, manifest);
+        let report = read.check(&dir).unwrap();
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+
+        // Tampering with the manifest breaks its signature
+        let mut forged = manifest.clone();
+        forged.documents.pop();
+        assert!(forged.verify().is_err());
+
+        // A resealed document no longer matches the release, and a newly
+        // sealed one is not part of it
+        let mut revised = a.clone();
+        revised.body = "First, revised.".into();
+        write("docs/a.tmd", &revised.seal(&keypair).unwrap());
+        write(
+            "c.tmd",
+            &TracedDocument::new("C", "Third.").seal(&keypair).unwrap(),
+        );
+        let report = manifest.check(&dir).unwrap();
+        assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+        assert!(report.errors[0].to_string().contains("docs/a.tmd"));
+        assert!(report.warnings.iter().any(|w| w.contains("c.tmd")));
+    }
+
+    #[test]
+    fn test_version_chain() {
+        use crate::history::{archive, walk_chain};
+        use crate::markdown::render_tmd;
+
+        let dir = temp_workspace("chain");
+        let history = dir.join(".rhodi/history");
+        let keypair = KeyPair::generate();
+        let v1 = TracedDocument::new("Report", "Body")
+            .seal(&keypair)
+            .unwrap();
+        let mut v2 = v1.clone();
+        v2.body = "Body, revised".into();
+        let v2 = v2.seal(&keypair).unwrap();
+        let mut v3 = v2.clone();
+        v3.body = "Body, revised again".into();
+        let v3 = v3.seal(&keypair).unwrap();
+        for v in [&v1, &v2] {
+            archive(&history, v, &render_tmd(v).unwrap()).unwrap();
+        }
+        let search = vec![history.join(v1.frontmatter.id.to_string())];
+
+        let chain = walk_chain(&v3, &search);
+        assert!(chain.is_complete(), "{:?}", chain.report.errors);
+        let versions: Vec<u32> = chain.links.iter().map(|l| l.doc_version).collect();
+        assert_eq!(versions, vec![3, 2, 1]);
+
+        // A missing version breaks the chain there
+        let v1_path = chain.links[2].path.clone().unwrap();
+        std::fs::remove_file(&v1_path).unwrap();
+        let chain = walk_chain(&v3, &search);
+        assert_eq!(chain.links.len(), 2);
+        assert!(chain.report.errors[0].to_string().contains("after v2"));
+
+        // So does a tampered one
+        let mut forged = v1.clone();
+        forged.body = "Forged".into();
+        std::fs::write(&v1_path, render_tmd(&forged).unwrap()).unwrap();
+        let chain = walk_chain(&v3, &search);
+        assert!(!chain.is_complete());
+        assert!(chain.report.errors[0].to_string().contains("breaks at v1"));
+    }
+
+    #[test]
+    fn test_trace_schema() {
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("schema");
+        std::fs::write(
+dir.join("metrics.schema.json"),
+r#"{"type": "object", "required": ["accuracy", "runs"], "properties": {"accuracy": {"type": "number"}, "runs": {"type": "integer"}}}"#,
+)
+.unwrap();
+        std::fs::write(dir.join("metrics.json"), r#"{"accuracy": 0.91, "runs": 3}"#).unwrap();
+        let doc = TracedDocument::new(
+            "Schema",
+            "```trace\nsource: metrics.json\nschema: metrics.schema.json\nselector: $.accuracy\nexpected: \"0.91\"\n```\n",
+        );
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let report = compiler.verify(&doc).unwrap();
+        assert!(
+            report.errors.is_empty() && report.warnings.is_empty(),
+            "{:?}",
+            report
+        );
+
+        // The value still matches, but the upstream structure drifted
+        std::fs::write(
+            dir.join("metrics.json"),
+            r#"{"accuracy": 0.91, "runs": "three"}"#,
+        )
+        .unwrap();
+        let report = compiler.verify(&doc).unwrap();
+        // Drafts report failed traces as warnings
+        let warnings = &report.warnings;
+        assert!(
+            warnings
+                .iter()
+                .any(|e| e.contains("does not match its schema") && e.contains("/runs")),
+            "{:?}",
+            warnings
+        );
+
+        assert!(crate::extraction::check_json_schema(b"{}", b"{\"type\": 3}").is_err());
+    }
+
+    #[test]
+    fn test_compiler_limits() {
+        use crate::compiler::{Compiler, CompilerLimits};
+        use crate::config::WorkspaceConfig;
+        use crate::error::SecurityError;
+        use crate::markdown::render_tmd;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("limits");
+        std::fs::write(dir.join("data.txt"), "0123456789").unwrap();
+        std::fs::write(
+            dir.join("leaf.tmd"),
+            render_tmd(&TracedDocument::new("Leaf", "Leaf.")).unwrap(),
+        )
+        .unwrap();
+        let trace = "```trace\nsource: data.txt\nexpected: \"x\"\n```\n\n";
+        let include = "```include\npath: leaf.tmd\n```\n\n";
+        let doc = TracedDocument::new(
+            "Limits",
+            &format!("{}{}", trace.repeat(3), include.repeat(3)),
+        );
+        let resolver = FileResolver::new(&dir).unwrap();
+        let verify =
+            |limits: CompilerLimits| Compiler::new(&resolver).with_limits(limits).verify(&doc);
+
+        assert!(verify(CompilerLimits::default()).is_ok());
+        assert!(matches!(
+            verify(CompilerLimits {
+                max_traces: 2,
+                ..Default::default()
+            }),
+            Err(RhodiError::Security(SecurityError::TooManyTraces {
+                limit: 2
+            }))
+        ));
+        assert!(matches!(
+            verify(CompilerLimits {
+                max_includes: 2,
+                ..Default::default()
+            }),
+            Err(RhodiError::Security(SecurityError::TooManyIncludes {
+                limit: 2
+            }))
+        ));
+        assert!(matches!(
+            verify(CompilerLimits {
+                max_include_depth: 0,
+                ..Default::default()
+            }),
+            Err(RhodiError::Security(SecurityError::MaxRecursionDepth {
+                depth: 0
+            }))
+        ));
+        // Three reads of a 10-byte source
+        assert!(
+            verify(CompilerLimits {
+                max_resolved_bytes: 30,
+                ..Default::default()
+            })
+            .is_ok()
+        );
+        assert!(matches!(
+            verify(CompilerLimits {
+                max_resolved_bytes: 25,
+                ..Default::default()
+            }),
+            Err(RhodiError::Security(SecurityError::ResolvedBytesExceeded {
+                limit: 25
+            }))
+        ));
+
+        let config = WorkspaceConfig::from_toml("[limits]\nmax_traces = 2\n").unwrap();
+        let limits = config.limits.compiler_limits();
+        assert_eq!(limits.max_traces, 2);
+        assert_eq!(limits.max_includes, CompilerLimits::default().max_includes);
+        assert!(WorkspaceConfig::from_toml("[limits]\nmax_trace = 2\n").is_err());
+    }
+
+    #[test]
+    fn test_incremental_change_set() {
+        use crate::incremental::ChangeSet;
+        use crate::index::WorkspaceIndex;
+        use crate::markdown::render_tmd;
+        use std::path::PathBuf;
+
+        let dir = temp_workspace("incremental");
+        let write = |name: &str, body: &str| {
+            let doc = TracedDocument::new(name, body);
+            std::fs::write(dir.join(name), render_tmd(&doc).unwrap()).unwrap();
+        };
+        let trace = |source: &str| format!("```trace\nsource: {}\nexpected: \"x\"\n```\n", source);
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        write("a.tmd", &trace("data/a.txt"));
+        write("b.tmd", &trace("data/b.txt"));
+        write("c.tmd", "```include\npath: b.tmd\n```\n");
+        write("d.tmd", "```include\npath: c.tmd\n```\n");
+        write("remote.tmd", &trace("https://example.org/data.json"));
+
+        let index = WorkspaceIndex::build(&dir).unwrap();
+        let paths = |docs: &[&crate::index::IndexedDocument]| -> Vec<String> {
+            docs.iter()
+                .map(|d| d.path.to_string_lossy().into_owned())
+                .collect()
+        };
+        let changes = ChangeSet::new(&index, &[PathBuf::from("data/b.txt")]);
+        // Includers are affected transitively; remote sources always are
+        assert_eq!(
+            paths(&changes.affected),
+            vec!["b.tmd", "c.tmd", "d.tmd", "remote.tmd"]
+        );
+        assert_eq!(paths(&changes.cached), vec!["a.tmd"]);
+
+        let changes = ChangeSet::new(&index, &[PathBuf::from("a.tmd")]);
+        assert_eq!(paths(&changes.affected), vec!["a.tmd", "remote.tmd"]);
+    }
+
+    #[test]
+    fn test_key_fingerprint() {
+        use crate::crypto::parse_public_key;
+        use crate::fingerprint::KeyFingerprint;
+
+        let key =
+            parse_public_key("4b209a9d7824767f101c29ba475a2f3e8152d7122e0e0ae0e5f369844532fbc3")
+                .unwrap();
+        let fingerprint = KeyFingerprint::of(&key);
+        assert_eq!(fingerprint.hex(), "5021 5b28 164e 070f");
+        assert_eq!(
+            fingerprint.words(),
+            "cotton blanket delta branch bamboo copper amber attic"
+        );
+
+        assert!(fingerprint.matches("50215B28164E070F"));
+        assert!(fingerprint.matches("50:21:5b:28:16:4e:07:0f"));
+        assert!(fingerprint.matches("Cotton-Blanket-Delta-Branch-Bamboo-Copper-Amber-Attic"));
+        assert!(!fingerprint.matches("cotton blanket delta branch bamboo copper amber acorn"));
+        assert!(!fingerprint.matches("5021 5b28"));
+        assert_ne!(
+            KeyFingerprint::of(&KeyPair::generate().verifying_key),
+            fingerprint
+        );
+    }
+
+    #[test]
+    fn test_locale_numeric_comparison() {
+        use crate::comparison::{parse_locale_number, values_match_with};
+
+        for value in [
+            "1,234.5",
+            "1 234,5",
+            "1234.5",
+            "1.234,5",
+            "1'234.5",
+            "1\u{a0}234,5",
+        ] {
+            assert_eq!(parse_locale_number(value, false), Some(1234.5), "{}", value);
+        }
+        assert_eq!(parse_locale_number("1,234,567", false), Some(1234567.0));
+        assert_eq!(parse_locale_number("1.234.567", false), Some(1234567.0));
+        assert_eq!(parse_locale_number("1,5", false), Some(1.5));
+        assert_eq!(parse_locale_number("0,123", false), Some(0.123));
+        assert_eq!(parse_locale_number("4.125", false), Some(4.125));
+        assert_eq!(parse_locale_number("-12,5%", true), Some(-0.125));
+        assert_eq!(parse_locale_number("1.234,5,6", false), None);
+        assert_eq!(parse_locale_number("n/a", false), None);
+
+        // Only traces that ask for it compare across locales
+        assert!(values_match_with("1,234.5", "1 234,5", None, true));
+        assert!(!values_match_with("1,234.5", "1 234,5", None, false));
+        assert!(!values_match_with("1,234.5", "1 234,6", None, true));
+
+        let trace: TraceBlock = serde_norway::from_str(
+            "source: data.csv\nselector: x\nexpected: \"1,234.5\"\nnumeric: true\n",
+        )
+        .unwrap();
+        assert!(trace.numeric);
+    }
+
+    #[test]
+    fn test_trace_comparisons() {
+        use crate::comparison::Comparison;
+        use crate::compiler::Compiler;
+        use crate::models::Tolerance;
+        use crate::resolver::FileResolver;
+
+        let check = |comparison: &str, expected: &str, actual: &str| {
+            Comparison::parse(comparison)
+                .unwrap()
+                .check(expected, actual, None, false)
+                .unwrap()
+        };
+        assert!(check("gte", "80%", "82.5%"));
+        assert!(!check("gte", "80%", "79.9%"));
+        assert!(check("lte", "0.05", "0.049"));
+        assert!(check("between", "[0.8, 0.9]", "0.85"));
+        assert!(check("between", "0.8..0.9", "0.9"));
+        assert!(check("between", "[0,8; 0,9]", "0,81"));
+        assert!(!check("between", "[0.8, 0.9]", "0.91"));
+        assert!(!check("between", "[0.8, 0.9]", "n/a"));
+        assert!(check("contains", "p < 0.05", "significant (p < 0.05)"));
+        assert!(check("matches", r"^v\d+\.\d+$", "v2.1"));
+        assert!(!check("matches", r"^v\d+\.\d+$", "version 2.1"));
+
+        // A tolerance widens the bounds; percentages read as fractions
+        let slack = Tolerance {
+            absolute: Some(0.01),
+            normalize_percent: true,
+            ..Default::default()
+        };
+        let gte = Comparison::Gte;
+        assert!(gte.check("80%", "0.795", Some(&slack), false).unwrap());
+        assert!(!gte.check("80%", "0.785", Some(&slack), false).unwrap());
+
+        // An expected value the comparison cannot read is an error
+        assert!(Comparison::Gte.check("high", "0.9", None, false).is_err());
+        let reversed = Comparison::Between.check("[0.9, 0.8]", "0.85", None, false);
+        assert!(reversed.is_err());
+        assert!(Comparison::Matches.check("(", "x", None, false).is_err());
+        assert!(Comparison::parse("approx").is_err());
+        assert!(
+            TraceBlock::builder()
+                .source("data.csv")
+                .expected("1")
+                .compare("approx")
+                .build()
+                .is_err()
+        );
+
+        let dir = temp_workspace("trace-comparisons");
+        std::fs::write(dir.join("results.csv"), "metric,value\naccuracy,0.87\n").unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let doc = TracedDocument::new("Results", "Body");
+        let trace = |compare: &str, expected: &str| {
+            let mut trace = TraceBlock::builder()
+                .source("results.csv")
+                .selector("cell:B2")
+                .extractor("cell")
+                .expected(expected)
+                .compare(compare)
+                .build()
+                .unwrap();
+            trace.update_hash(&dir).unwrap();
+            trace
+        };
+        let result = compiler.check_document_trace(&doc, &trace("between", "[0.8, 0.9]"));
+        assert!(result.passed, "{:?}", result.error);
+        let result = compiler.check_document_trace(&doc, &trace("gte", "0.9"));
+        let error = result.error.unwrap();
+        assert!(
+            error.contains("Expected at least '0.9', got '0.87'"),
+            "{}",
+            error
+        );
+
+        // A fix refreshes the hash but leaves the claim to the author
+        let stale = trace("gte", "0.8");
+        std::fs::write(dir.join("results.csv"), "metric,value\naccuracy,0.88\n").unwrap();
+        let fix = compiler.propose_fix(&doc, &stale).unwrap().unwrap();
+        assert_eq!(fix.new_expected, "0.8");
+        // and proposes nothing once the claim itself fails
+        std::fs::write(dir.join("results.csv"), "metric,value\naccuracy,0.79\n").unwrap();
+        let error = compiler.propose_fix(&doc, &stale).unwrap_err().to_string();
+        assert!(error.contains("revise it by hand"), "{}", error);
+    }
+
+    #[test]
+    fn test_verify_document_entrypoint() {
+        use crate::markdown::render_tmd;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("entrypoint");
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("Embedded", "Body.");
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let content = render_tmd(&doc.seal(&keypair).unwrap()).unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+
+        let report = crate::verify_document(&content, &[keypair.verifying_key], &resolver).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report);
+        let report = crate::verify_document(&content, &[], &resolver).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report);
+
+        let other = KeyPair::generate();
+        let report = crate::verify_document(&content, &[other.verifying_key], &resolver).unwrap();
+        assert!(
+            report
+                .errors
+                .iter()
+                .any(|e| e.to_string().contains("untrusted key"))
+        );
+
+        let draft = render_tmd(&TracedDocument::new("Draft", "Body.")).unwrap();
+        let report = crate::verify_document(&draft, &[keypair.verifying_key], &resolver).unwrap();
+        assert!(
+            report
+                .errors
+                .iter()
+                .any(|e| e.to_string().contains("not signed"))
+        );
+    }
+
+    #[test]
+    fn test_status_badges() {
+        use crate::badge::{self, Badge};
+        use crate::compiler::{Compiler, SectionOutcome};
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("badges");
+        std::fs::write(dir.join("data.txt"), "revenue: 42\n").unwrap();
+        let doc = TracedDocument::new(
+            "Badges",
+            "Revenue was 42.\n\n```trace\nid: t-1\nsource: data.txt\nselector: \"revenue: (\\\\d+)\"\nexpected: \"42\"\n```\n\nAnd 7.\n\n```trace\nsource: data.txt\nselector: \"revenue: (\\\\d+)\"\nexpected: \"7\"\n```\n",
+        );
+        let resolver = FileResolver::new(&dir).unwrap();
+        let verified = Compiler::new(&resolver).verify_detailed(&doc).unwrap();
+        // Drafts report failed traces as warnings
+        assert_eq!(verified.outcome(), SectionOutcome::Warning);
+
+        let markdown = badge::render_markdown_with_badges(&doc, &verified);
+        assert!(
+            markdown.starts_with(
+                "![rhodi: warning](https://img.shields.io/badge/rhodi-warning-yellow)"
+            )
+        );
+        assert!(markdown.contains(
+            "```\n\n![t-1: verified](https://img.shields.io/badge/t--1-verified-brightgreen)\n"
+        ));
+        assert!(markdown.contains("![trace: warning]"));
+
+        let html = badge::render_html_with_badges(&doc, &verified);
+        assert_eq!(html.matches("<svg").count(), 3);
+        assert!(!html.contains('\u{E000}'));
+        assert!(html.contains("aria-label=\"t-1: verified\""));
+
+        let json =
+            serde_json::to_value(Badge::for_outcome("docs", SectionOutcome::Failed)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"schemaVersion": 1, "label": "docs", "message": "failed", "color": "red"})
+        );
+        assert_eq!(
+            Badge::new("a_b c", "x", "red").shields_url(),
+            "https://img.shields.io/badge/a__b_c-x-red"
+        );
+    }
+
+    #[test]
+    fn test_revoked_includes() {
+        use crate::compiler::Compiler;
+        use crate::index::DocumentLocator;
+        use crate::markdown::render_tmd;
+        use crate::resolver::FileResolver;
+        use crate::revocation::RevocationCheck;
+
+        let dir = temp_workspace("revoked-includes");
+        std::fs::create_dir_all(dir.join("archive")).unwrap();
+        let key = KeyPair::generate();
+        let mut part = TracedDocument::new("Part", "Shared section");
+        part.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let part = part.seal(&key).unwrap();
+        std::fs::write(dir.join("archive/part-v1.tmd"), render_tmd(&part).unwrap()).unwrap();
+
+        let body = "```include\npath: archive/part-v1.tmd\n```";
+        let main = TracedDocument::new("Main", body).set_status(DocStatus::Published);
+        let resolver = FileResolver::new(&dir).unwrap();
+        let verify = |doc: &TracedDocument| {
+            Compiler::new(&resolver)
+                .with_document_locator(Some(DocumentLocator::new(&dir)))
+                .verify(doc)
+                .unwrap()
+        };
+        assert!(verify(&main).errors.is_empty(), "{:?}", verify(&main));
+
+        // A revocation signed with another key is not honored
+        let forged = Compiler::new(&resolver)
+            .revoke(part.clone(), &KeyPair::generate())
+            .unwrap();
+        std::fs::write(dir.join("part.tmd"), render_tmd(&forged).unwrap()).unwrap();
+        assert!(verify(&main).errors.is_empty(), "{:?}", verify(&main));
+
+        // Revoking the part in the workspace revokes the version main pins
+        let revoked = Compiler::new(&resolver).revoke(part, &key).unwrap();
+        std::fs::write(dir.join("part.tmd"), render_tmd(&revoked).unwrap()).unwrap();
+        let errors: Vec<String> = verify(&main).errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains(
+            "Relies on revoked material: included document archive/part-v1.tmd revoked by version"
+        ));
+        assert!(
+            Compiler::new(&resolver)
+                .verify(&main)
+                .unwrap()
+                .errors
+                .is_empty()
+        );
+
+        // Including the revoking version itself, or from a draft
+        let direct = TracedDocument::new("Direct", "```include\npath: part.tmd\n```")
+            .set_status(DocStatus::Published);
+        assert!(
+            verify(&direct).errors[0]
+                .to_string()
+                .contains("part.tmd is revoked")
+        );
+        let draft = TracedDocument::new("Draft", body);
+        let report = verify(&draft);
+        assert!(report.errors.is_empty());
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Revocation warning"))
+        );
+
+        // Other sources of revocations, e.g. a registry
+        struct Everything;
+        impl RevocationCheck for Everything {
+            fn revocation(&self, _: &TracedDocument) -> Result<Option<String>> {
+                Ok(Some("revoked by fiat".to_string()))
+            }
+        }
+        std::fs::remove_file(dir.join("part.tmd")).unwrap();
+        let report = Compiler::new(&resolver)
+            .with_revocation_check(Everything)
+            .verify(&main)
+            .unwrap();
+        assert!(report.errors[0].to_string().contains("revoked by fiat"));
+    }
+
+    #[test]
+    fn test_supersession_links() {
+        use crate::compiler::Compiler;
+        use crate::history::archive;
+        use crate::index::DocumentLocator;
+        use crate::markdown::render_tmd;
+        use crate::models::DocumentLink;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("supersession");
+        let key = KeyPair::generate();
+        let write = |name: &str, doc: &TracedDocument| {
+            std::fs::write(dir.join(name), render_tmd(doc).unwrap()).unwrap();
+        };
+        let resolver = FileResolver::new(&dir).unwrap();
+        let verify = |doc: &TracedDocument| {
+            Compiler::new(&resolver)
+                .with_document_locator(Some(DocumentLocator::new(&dir)))
+                .verify(doc)
+                .unwrap()
+        };
+
+        let draft = |title: &str, body: &str| {
+            let mut doc = TracedDocument::new(title, body);
+            doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+            doc
+        };
+        let old = draft("Q3 report", "Revenue was 1,204.").seal(&key).unwrap();
+        write("old.tmd", &old);
+        let mut new = draft("Q3 report, revised", "Revenue was 1,240.");
+        new.frontmatter.supersedes = DocumentLink::to(&old);
+        let new = new.seal(&key).unwrap();
+        write("new.tmd", &new);
+        let mut unlinked = new.clone();
+        unlinked.frontmatter.supersedes = None;
+        assert_ne!(
+            unlinked.compute_version_hash().unwrap(),
+            new.compute_version_hash().unwrap()
+        );
+
+        // One-sided until the old version links back
+        let report = verify(&new);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings[0].contains("does not link back"));
+        let report = verify(&old);
+        assert!(report.warnings[0].contains("Superseded by 'Q3 report, revised'"));
+
+        // The back link is not hashed, so the old seal still holds
+        let mut linked = old.clone();
+        linked.frontmatter.superseded_by = DocumentLink::to(&new);
+        linked.verify(&key.verifying_key).unwrap();
+        write("old.tmd", &linked);
+        assert!(verify(&new).warnings.is_empty(), "{:?}", verify(&new));
+        let report = verify(&linked);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(
+            report.warnings,
+            vec!["Superseded by 'Q3 report, revised' v1 (new.tmd)".to_string()]
+        );
+
+        // A third version is named as the current one
+        let mut newest = draft("Q3 report, final", "Revenue was 1,250.");
+        newest.frontmatter.supersedes = DocumentLink::to(&new);
+        let newest = newest.seal(&key).unwrap();
+        write("newest.tmd", &newest);
+        let mut new_linked = new.clone();
+        new_linked.frontmatter.superseded_by = DocumentLink::to(&newest);
+        write("new.tmd", &new_linked);
+        assert!(
+            verify(&linked).warnings[0]
+                .ends_with("the current version is 'Q3 report, final' v1 (newest.tmd)")
+        );
+
+        // The old version is found in the history once its file moves on
+        let history = dir.join(".rhodi/history");
+        archive(&history, &old, &render_tmd(&old).unwrap()).unwrap();
+        let mut revised = linked.clone();
+        revised.body = "Revenue was 1,204 (unaudited).".into();
+        write("old.tmd", &revised.seal(&key).unwrap());
+        assert!(verify(&new_linked).errors.is_empty());
+        std::fs::remove_dir_all(&history).unwrap();
+        let errors = verify(&new_linked).errors;
+        assert!(
+            errors[0].to_string().contains("not a sealed version"),
+            "{:?}",
+            errors
+        );
+
+        // A successor that does not name this version is an error
+        let mut forged = old.clone();
+        forged.frontmatter.superseded_by = DocumentLink::to(&newest);
+        let errors = verify(&forged).errors;
+        assert!(
+            errors[0].to_string().contains("does not name this version"),
+            "{:?}",
+            errors
+        );
+        // Resealing drops the link, which was bound to the old version
+        assert!(
+            forged
+                .seal(&key)
+                .unwrap()
+                .frontmatter
+                .superseded_by
+                .is_none()
+        );
+
+        // A successor signed by another key is not believed
+        let other = KeyPair::generate();
+        let mut impostor = TracedDocument::new("Q3 report, hijacked", "Revenue was 9,999.");
+        impostor.frontmatter.public_key = Some(hex::encode(other.verifying_key.as_bytes()));
+        impostor.frontmatter.supersedes = DocumentLink::to(&old);
+        let impostor = impostor.seal(&other).unwrap();
+        write("impostor.tmd", &impostor);
+        let mut claimed = old.clone();
+        claimed.frontmatter.superseded_by = DocumentLink::to(&impostor);
+        let report = verify(&claimed);
+        assert!(
+            report
+                .warnings
+                .iter()
+                .all(|w| !w.starts_with("Superseded by")),
+            "{:?}",
+            report.warnings
+        );
+        assert!(
+            report.errors[0]
+                .to_string()
+                .contains("not signed by this version's key"),
+            "{:?}",
+            report.errors
+        );
+        let link = DocumentLink {
+            id: old.frontmatter.id,
+            version_hash: "é".repeat(20),
+        };
+        assert!(link.to_string().ends_with(&"é".repeat(16)));
+    }
+
+    #[test]
+    fn test_file_hash_seal() {
+        use crate::markdown::{render_sealed, render_tmd};
+        use crate::models::SealMode;
+
+        let key = KeyPair::generate();
+        let mut doc = TracedDocument::new("File hash", "Body text.");
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        doc.frontmatter.seal_mode = Some(SealMode::FileHash);
+        let original = render_tmd(&doc).unwrap();
+        let (sealed_doc, sealed) = render_sealed(&original, doc.seal(&key).unwrap(), &key).unwrap();
+        sealed_doc.verify(&key.verifying_key).unwrap();
+        assert!(sealed.contains("seal_mode: file-hash"));
+
+        let parsed = parse_tmd(&sealed).unwrap();
+        parsed.verify(&key.verifying_key).unwrap();
+        assert_ne!(
+            parsed.frontmatter.version_hash,
+            Some(parsed.compute_version_hash().unwrap())
+        );
+        parse_tmd(&sealed.replace('\n', "\r\n"))
+            .unwrap()
+            .verify(&key.verifying_key)
+            .unwrap();
+
+        // Masked fields may be attached later; any other byte is covered, even
+        // when it parses to the same document
+        let notarized = sealed.replacen("\n---\n", "\nnotarizations: []\n---\n", 1);
+        parse_tmd(&notarized)
+            .unwrap()
+            .verify(&key.verifying_key)
+            .unwrap();
+        let reformatted = parse_tmd(&sealed.replacen("title: ", "title:  ", 1)).unwrap();
+        assert_eq!(reformatted.frontmatter.title, "File hash");
+        assert!(reformatted.verify(&key.verifying_key).is_err());
+
+        let mut in_memory = parsed.clone();
+        in_memory.raw = None;
+        assert!(in_memory.verify(&key.verifying_key).is_err());
+
+        // Sealing in the library renders the text the hash covers
+        let mut built =
+            TracedDocument::new("Dashes---in the title", "Body---text.\n\n---\n\nMore.");
+        built.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        built.frontmatter.seal_mode = Some(SealMode::FileHash);
+        let built = built.seal(&key).unwrap();
+        built.verify(&key.verifying_key).unwrap();
+        let raw = built.raw.as_deref().unwrap();
+        let reparsed = parse_tmd(raw).unwrap();
+        assert_eq!(reparsed.frontmatter.title, "Dashes---in the title");
+        assert_eq!(reparsed.body, "Body---text.\n\n---\n\nMore.");
+        reparsed.verify(&key.verifying_key).unwrap();
+        let resealed = reparsed.seal(&key).unwrap();
+        resealed.verify(&key.verifying_key).unwrap();
+    }
+
+    #[test]
+    fn test_workspace_stats() {
+        use crate::attestation::{Attestation, TraceReview, Verdict};
+        use crate::index::WorkspaceIndex;
+        use crate::markdown::render_tmd;
+        use crate::stats::WorkspaceStats;
+
+        let dir = temp_workspace("stats");
+        let key = KeyPair::generate();
+        let traced = TracedDocument::new(
+            "Traced",
+            "```trace\nsource: metrics.json\nselector: $.accuracy\nexpected: \"0.9\"\ntimestamp: 2026-01-01T00:00:00Z\n```\n\n```trace\nsource: log.txt\nselector: \"runs: (\\\\d+)\"\nexpected: \"3\"\ntimestamp: 2026-01-11T00:00:00Z\n```\n\n```trace\nsource: notes.pdf\nexpected: \"seen\"\nmethod: manual\n```",
+        )
+        .seal(&key)
+        .unwrap();
+        let notes = TracedDocument::new("Notes", "No evidence yet.");
+        std::fs::write(dir.join("traced.tmd"), render_tmd(&traced).unwrap()).unwrap();
+        std::fs::write(dir.join("notes.tmd"), render_tmd(&notes).unwrap()).unwrap();
+
+        let review = |passed, verdict| TraceReview {
+            trace: "metrics.json".to_string(),
+            passed,
+            verdict,
+            note: None,
+        };
+        let mut earlier = Attestation::create(&traced, &key, vec!["stale".to_string()]).unwrap();
+        earlier.verified_at = "2025-12-15T00:00:00Z".parse().unwrap();
+        let reviewed = Attestation::create_with_reviews(
+            &traced,
+            &key,
+            Vec::new(),
+            vec![
+                review(true, Verdict::Approved),
+                review(false, Verdict::Flagged),
+            ],
+        )
+        .unwrap();
+
+        let index = WorkspaceIndex::build(&dir).unwrap();
+        let now = "2026-01-21T00:00:00Z".parse().unwrap();
+        let stats = WorkspaceStats::collect(
+            &index,
+            &Default::default(),
+            &[reviewed.clone(), earlier],
+            now,
+        )
+        .unwrap();
+        assert_eq!((stats.documents, stats.sealed, stats.traces), (2, 1, 3));
+        assert_eq!(stats.by_status["published"], 1);
+        assert_eq!(stats.traces_per_document[&0], 1);
+        assert_eq!(stats.mean_traces_per_document(), 1.5);
+        let extractors: Vec<(&str, usize)> = stats
+            .extractors
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        assert_eq!(
+            extractors,
+            vec![("jsonpath", 1), ("manual", 1), ("regex", 1)]
+        );
+        assert_eq!(stats.average_evidence_age_days, Some(15.0));
+
+        assert_eq!(stats.attestations, 2);
+        assert_eq!(stats.ledger.len(), 2);
+        assert_eq!(stats.ledger[0].month, "2025-12");
+        assert_eq!(stats.ledger[0].with_warnings, 1);
+        assert_eq!(stats.ledger[0].failure_rate(), None);
+        let latest = &stats.ledger[1];
+        assert_eq!(
+            latest.month,
+            reviewed.verified_at.format("%Y-%m").to_string()
+        );
+        assert_eq!(
+            (
+                latest.reviewed_traces,
+                latest.failed_traces,
+                latest.flagged_traces
+            ),
+            (2, 1, 1)
+        );
+        assert_eq!(latest.failure_rate(), Some(0.5));
+
+        // A document gone since indexing is counted as unreadable
+        std::fs::remove_file(dir.join("notes.tmd")).unwrap();
+        let stats = WorkspaceStats::collect(&index, &Default::default(), &[], now).unwrap();
+        assert_eq!((stats.documents, stats.unreadable, stats.traces), (2, 1, 3));
+        assert_eq!(stats.mean_traces_per_document(), 3.0);
+    }
+
+    #[test]
+    fn test_openpgp_signatures() {
+        use crate::crypto::SignatureScheme;
+        use crate::openpgp::{SignaturePacket, ed25519_keys, verify};
+
+        // Made with GnuPG 2.2: `gpg --quick-gen-key ... ed25519`, then a
+        // detached signature over 32 bytes of 0x01 and `gpg --export`
+        let export = hex::decode(
+            "9833046ad2879516092b06010401da470f01010740b9ebd582b822c6d6459de4b9f10b5dac8cb461d0b67b3e33e7dec48b16019723b4244578616d706c6520496e73746974757465203c7365616c406578616d706c652e6f72673e88900413160800381621042808a89ce244ef7ab4d5c1851070d2001171ff1805026ad28795021b03050b0908070206150a09080b020416020301021e01021780000a09101070d2001171ff187ae100fc09d973241db8a87e8862941562110e0f948c0f1f0355270032d0ea258051d5d801008579e08fd725476e0ff59e4c51728d0908c46d18643d9e3f657f3598fdd5e10d",
+        )
+        .unwrap();
+        let signature = hex::decode(
+            "888704001608002f1621042808a89ce244ef7ab4d5c1851070d2001171ff1805026ad28795111c7365616c406578616d706c652e6f7267000a09101070d2001171ff185f1200ff5fecb1407d4c729663fb6af46cc2d94106f811f96ce35bc823c8e198647b893200fe3aba96698949b114c8c2d475e3d18c6de14824a58928da5c1411269bc0718b01",
+        )
+        .unwrap();
+        let message = [1u8; 32];
+
+        let keys = ed25519_keys(&export).unwrap();
+        assert_eq!(keys.len(), 1);
+        let key = keys[0];
+        assert_eq!(
+            hex::encode(key),
+            "b9ebd582b822c6d6459de4b9f10b5dac8cb461d0b67b3e33e7dec48b16019723"
+        );
+        assert_eq!(
+            SignaturePacket::parse(&signature)
+                .unwrap()
+                .issuer_fingerprint
+                .as_deref(),
+            Some("2808A89CE244EF7AB4D5C1851070D2001171FF18")
+        );
+
+        verify(&key, &message, &signature).unwrap();
+        assert!(verify(&key, &[2u8; 32], &signature).is_err());
+        assert!(
+            verify(
+                KeyPair::generate().verifying_key.as_bytes(),
+                &message,
+                &signature
+            )
+            .is_err()
+        );
+        let mut tampered = signature.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(verify(&key, &message, &tampered).is_err());
+        assert!(verify(&key, &message, &signature[..40]).is_err());
+
+        // Documents select the OpenPGP verifier through signature_algorithm
+        let scheme: SignatureScheme = "openpgp-ed25519".parse().unwrap();
+        assert_eq!(scheme, SignatureScheme::OpenPgpEd25519);
+        scheme.verify(&key, &message, &signature).unwrap();
+
+        // A document sealed with a rhodi key and relabelled as OpenPGP fails,
+        // and sealing with a rhodi key drops the OpenPGP label
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("OpenPGP", "Signed under an institutional key.");
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        doc.frontmatter.signature_algorithm = Some(SignatureScheme::OpenPgpEd25519);
+        let sealed = doc.seal(&keypair).unwrap();
+        assert_eq!(sealed.frontmatter.signature_algorithm, None);
+        sealed.verify(&keypair.verifying_key).unwrap();
+        let mut relabelled = sealed.clone();
+        relabelled.frontmatter.signature_algorithm = Some(SignatureScheme::OpenPgpEd25519);
+        assert!(relabelled.verify(&keypair.verifying_key).is_err());
+    }
+
+    #[test]
+    fn test_contributor_review_policy() {
+        use crate::compiler::Compiler;
+        use crate::markdown::render_tmd;
+        use crate::models::{Contributor, ContributorRole};
+        use crate::notary::{check_reviews, notarize, reviewer_signatures};
+        use crate::resolver::FileResolver;
+        use crate::version::VersionRegistry;
+
+        let dir = temp_workspace("contributor_review");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let versions = VersionRegistry::default();
+        let author = KeyPair::generate();
+        let reviewer = KeyPair::generate();
+        let contributor = |name: &str, role, key: Option<&KeyPair>| Contributor {
+            name: name.to_string(),
+            role,
+            key: key.map(|k| hex::encode(k.verifying_key.as_bytes())),
+            orcid: None,
+        };
+
+        let mut doc = TracedDocument::new("Reviewed", "Findings.");
+        doc.frontmatter.public_key = Some(hex::encode(author.verifying_key.as_bytes()));
+        doc.frontmatter.contributors = Some(vec![
+            contributor("Ada", ContributorRole::Author, Some(&author)),
+            // The signer listed as a reviewer does not review their own work
+            contributor("Ada", ContributorRole::Reviewer, Some(&author)),
+            contributor("Grace", ContributorRole::Reviewer, Some(&reviewer)),
+            contributor("Edsger", ContributorRole::Editor, None),
+        ]);
+        doc.frontmatter.policy.required_reviewers = Some(1);
+        let sealed = doc.seal(&author).unwrap();
+
+        // Contributors and the policy round-trip and are covered by the seal
+        let parsed = parse_tmd(&render_tmd(&sealed).unwrap()).unwrap();
+        assert_eq!(
+            parsed.frontmatter.contributors,
+            sealed.frontmatter.contributors
+        );
+        parsed.verify(&author.verifying_key).unwrap();
+        let mut tampered = parsed.clone();
+        tampered.frontmatter.contributors.as_mut().unwrap()[2].role = ContributorRole::Editor;
+        assert!(tampered.verify(&author.verifying_key).is_err());
+        let mut relaxed = parsed.clone();
+        relaxed.frontmatter.policy.required_reviewers = None;
+        assert!(relaxed.verify(&author.verifying_key).is_err());
+
+        // Published without a reviewer's sign-off fails, unless pending
+        assert!(check_reviews(&parsed).is_err());
+        let report = Compiler::new(&resolver).verify(&parsed).unwrap();
+        assert!(
+            report
+                .errors
+                .iter()
+                .any(|e| e.to_string().contains("Review policy"))
+        );
+        let report = Compiler::new(&resolver)
+            .with_pending_reviews(true)
+            .verify(&parsed)
+            .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.iter().any(|w| w.contains("Review policy")));
+
+        // The author and an unlisted notary do not count
+        let self_signed = notarize(parsed.clone(), &author, None, &versions).unwrap();
+        let outsider = notarize(self_signed, &KeyPair::generate(), None, &versions).unwrap();
+        assert!(reviewer_signatures(&outsider).is_empty());
+        assert!(check_reviews(&outsider).is_err());
+
+        let reviewed = notarize(outsider, &reviewer, Some("Grace"), &versions).unwrap();
+        assert_eq!(reviewer_signatures(&reviewed).len(), 1);
+        let report = Compiler::new(&resolver).verify(&reviewed).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+        // Drafts are not held to the policy
+        let mut draft = TracedDocument::new("Draft", "Body");
+        draft.frontmatter.policy.required_reviewers = Some(2);
+        assert!(check_reviews(&draft).is_ok());
+
+        let invalid = Contributor {
+            orcid: Some("0000-0002-1825-0098".to_string()),
+            ..contributor("Grace", ContributorRole::Reviewer, Some(&reviewer))
+        };
+        assert!(invalid.validate().is_err());
+        let unkeyed = Contributor {
+            key: Some("not hex".to_string()),
+            ..contributor("Grace", ContributorRole::Reviewer, None)
+        };
+        assert!(unkeyed.validate().is_err());
+        assert!(
+            contributor("Grace", ContributorRole::Reviewer, Some(&reviewer))
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_source_mirrors() {
+        use crate::compiler::Compiler;
+        use crate::crypto::hash_bytes;
+        use crate::markdown::parse_trace_block;
+        use crate::merkle::trace_leaf;
+        use crate::resolver::FileResolver;
+
+        let dir = temp_workspace("source_mirrors");
+        let evidence = "Total: 42\n";
+        std::fs::write(dir.join("mirror.txt"), evidence).unwrap();
+        std::fs::write(dir.join("snapshot.txt"), evidence).unwrap();
+        std::fs::write(dir.join("moved.txt"), "Total: 41\n").unwrap();
+        let hash = hash_bytes(evidence.as_bytes());
+
+        // A source list is the source followed by its mirrors
+        let listed = parse_trace_block(&format!(
+            "```trace\nsource: [primary.txt, mirror.txt]\nmirrors: [snapshot.txt]\nhash: {}\nselector: 'Total: (\\d+)'\nexpected: \"42\"\n```",
+            hash
+        ))
+        .unwrap();
+        assert_eq!(listed.source, "primary.txt");
+        assert_eq!(listed.mirrors, vec!["mirror.txt", "snapshot.txt"]);
+        let spelled_out = parse_trace_block(&format!(
+            "```trace\nsource: primary.txt\nmirrors: [mirror.txt, snapshot.txt]\nhash: {}\nselector: 'Total: (\\d+)'\nexpected: \"42\"\n```",
+            hash
+        ))
+        .unwrap();
+        assert_eq!(
+            trace_leaf(&listed).unwrap(),
+            trace_leaf(&spelled_out).unwrap()
+        );
+        assert!(parse_trace_block("```trace\nsource: []\nexpected: \"42\"\n```").is_err());
+
+        let resolver = FileResolver::new(&dir).unwrap();
+        let compiler = Compiler::new(&resolver);
+        let verify = |source: &str| {
+            let body = format!(
+                "Revenue was 42.\n\n```trace\nsource: {}\nhash: {}\nselector: 'Total: (\\d+)'\nexpected: \"42\"\n```\n",
+                source, hash
+            );
+            let mut doc = TracedDocument::new("Mirrored", &body);
+            doc.frontmatter.doc_status = DocStatus::Published;
+            compiler.verify(&doc).unwrap()
+        };
+
+        // The primary source is gone: the first mirror satisfies the hash
+        let report = verify("[primary.txt, mirror.txt, snapshot.txt]");
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| w.contains("satisfied by mirror mirror.txt") && w.contains("primary.txt"))
+        );
+
+        // A source whose content changed falls through to the snapshot
+        let report = verify("[moved.txt, snapshot.txt]");
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.iter().any(|w| w.contains("snapshot.txt")));
+
+        // A passing source needs no mirror and reports nothing
+        let report = verify("[mirror.txt, primary.txt]");
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(!report.warnings.iter().any(|w| w.contains("mirror")));
+
+        // Every location failing is reported with each reason
+        let report = verify("[moved.txt, primary.txt]");
+        let error = report.errors[0].to_string();
+        assert!(error.contains("mirrors failed too") && error.contains("primary.txt"));
+    }
+
+    #[test]
+    fn test_bundle_deduplicates_shared_evidence() {
+        use crate::bundle::{BundleResolver, write_documents_bundle};
+        use crate::compiler::Compiler;
+        use crate::markdown::render_tmd;
+        use crate::resolver::SourceResolver;
+
+        let dir = temp_workspace("bundle-dedup");
+        let dataset = "year,revenue\n".to_string() + &"2024,42\n".repeat(5000);
+        let mut paths = Vec::new();
+        for (name, expected) in [("a", "42"), ("b", "2024")] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(dir.join(name).join("data.csv"), &dataset).unwrap();
+            let doc = TracedDocument::new(
+                name,
+                &format!(
+                    "```trace\nsource: ./data.csv\nselector: \"(\\\\d+),42\"\nexpected: \"{}\"\n```",
+                    expected
+                ),
+            );
+            std::fs::write(dir.join(name).join("doc.tmd"), render_tmd(&doc).unwrap()).unwrap();
+            paths.push(dir.join(name).join("doc.tmd"));
+        }
+
+        let mut archive = Vec::new();
+        let manifest = write_documents_bundle(&paths, &mut archive).unwrap();
+        assert_eq!(manifest.document, "a/doc.tmd");
+        assert_eq!(
+            manifest.documents.keys().collect::<Vec<_>>(),
+            ["a/doc.tmd", "b/doc.tmd"]
+        );
+        // Four files listed, the dataset stored once
+        assert_eq!(manifest.file_counts(), (4, 3));
+        assert!(archive.len() < 2 * dataset.len());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        let bundle = BundleResolver::from_bytes(archive).unwrap();
+        assert!(bundle.has_document("./b/doc.tmd"));
+        assert!(!bundle.has_document("c/doc.tmd"));
+        assert_eq!(
+            bundle.resolve_bytes("data.csv").unwrap(),
+            dataset.as_bytes()
+        );
+
+        // Each document verifies against its own view of the bundle
+        let a = bundle.document().unwrap();
+        let report = Compiler::new(&bundle).verify(&a).unwrap();
+        assert!(report.errors.is_empty());
+        let bundle = bundle.select("b/doc.tmd").unwrap();
+        let b = bundle.document().unwrap();
+        assert_eq!(b.frontmatter.title, "b");
+        let report = Compiler::new(&bundle).verify(&b).unwrap();
+        assert!(report.errors.is_empty());
+        assert!(bundle.select("c/doc.tmd").is_err());
+    }
+
+    #[test]
+    fn test_trace_block_builder() {
+        use crate::models::{AgentMetadata, TraceMethod};
+
+        let trace = TraceBlock::builder()
+            .id("t-001")
+            .source("data.csv")
+            .selector("Revenue: (\\d+)")
+            .extractor("auto")
+            .expected("42")
+            .transform("trim")
+            .max_age("90d")
+            .build()
+            .unwrap();
+        assert_eq!(trace.label(), "t-001 (data.csv)");
+        assert_eq!(trace.method, TraceMethod::Automatic);
+        assert_eq!(trace.transforms, ["trim"]);
+
+        // A selector needs an extractor
+        let unextracted = TraceBlock::builder()
+            .source("data.csv")
+            .selector("Revenue: (\\d+)")
+            .expected("42");
+        assert!(unextracted.clone().build().is_err());
+        assert!(unextracted.extractor("regex").build().is_ok());
+
+        // Agent metadata needs the agent method
+        let metadata = AgentMetadata {
+            model: "summarizer".into(),
+            prompt_hash: None,
+        };
+        let agent = TraceBlock::builder()
+            .source("data.csv")
+            .agent_metadata(metadata);
+        assert!(agent.clone().build().is_err());
+        let trace = agent.method(TraceMethod::Agent).build().unwrap();
+        assert_eq!(trace.method, TraceMethod::Agent);
+
+        assert!(TraceBlock::builder().expected("42").build().is_err());
+        assert!(
+            TraceBlock::builder()
+                .source("data.csv")
+                .max_age("soon")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_nfc_canonicalization_profile() {
+        let composed =
+            TracedDocument::new("Caf\u{e9}", "Cr\u{e8}me br\u{fb}l\u{e9}e costs 4 \u{20ac}.");
+        let mut decomposed = composed.clone();
+        decomposed.frontmatter.title = "Cafe\u{301}".to_string();
+        decomposed.body = "Cre\u{300}me bru\u{302}le\u{301}e costs 4 \u{20ac}.".to_string();
+        assert_ne!(composed.body, decomposed.body);
+        let hashes_match = |version: &str| {
+            let (mut a, mut b) = (composed.clone(), decomposed.clone());
+            a.frontmatter.protocol_version = version.to_string();
+            b.frontmatter.protocol_version = version.to_string();
+            a.compute_version_hash().unwrap() == b.compute_version_hash().unwrap()
+        };
+
+        // From 2.1 on, visually identical text hashes identically; earlier
+        // versions keep hashing the text as written
+        assert!(hashes_match("2.1"));
+        assert!(!hashes_match("2.0"));
+        assert!(!hashes_match("1.1"));
+
+        // Migrating to 2.1 stores the body in NFC
+        let mut old = decomposed.clone();
+        old.frontmatter.protocol_version = "2.0".to_string();
+        let (migrated, changes) = old.migrate("2.1").unwrap();
+        assert_eq!(migrated.body, composed.body);
+        assert!(changes.contains(&"body: re-canonicalized".to_string()));
+
+        // Extra keys that only differ in normalization cannot both be hashed
+        let mut colliding = composed
+            .clone()
+            .extra_info("caf\u{e9}", "composed")
+            .extra_info("cafe\u{301}", "decomposed");
+        colliding.frontmatter.protocol_version = "2.0".to_string();
+        assert!(colliding.compute_version_hash().is_ok());
+        colliding.frontmatter.protocol_version = "2.1".to_string();
+        assert!(matches!(
+            colliding.compute_version_hash(),
+            Err(RhodiError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn test_configured_version_registry() {
+        use crate::compiler::Compiler;
+        use crate::config::{Workspace, WorkspaceConfig};
+        use crate::resolver::FileResolver;
+        use crate::version::{
+            CanonicalizationProfile, HashingScheme, VersionEntry, VersionRegistry, VersionStatus,
+            canonicalization_profile, hashing_scheme,
+        };
+        use chrono::NaiveDate;
+
+        // Dates raise the configured status once reached
+        let day = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let mut entry = VersionEntry::new("2.0", VersionStatus::Current);
+        entry.deprecated_on = Some(day("2025-01-01"));
+        entry.obsolete_on = Some(day("2026-01-01"));
+        assert_eq!(entry.status_on(day("2024-12-31")), VersionStatus::Current);
+        assert_eq!(
+            entry.status_on(day("2025-06-01")),
+            VersionStatus::Deprecated
+        );
+        assert_eq!(entry.status_on(day("2026-01-01")), VersionStatus::Obsolete);
+
+        // Forks need a built-in base their name extends, and built-in
+        // versions keep their rules
+        let mut fork = VersionEntry::new("2.0-registry-test", VersionStatus::Current);
+        let mut bad = VersionEntry::new("2.1", VersionStatus::Current);
+        bad.based_on = Some("2.0".to_string());
+        assert!(VersionRegistry::new(&[fork.clone(), bad]).is_err());
+        fork.based_on = Some("2.0-other".to_string());
+        assert!(VersionRegistry::new(&[fork.clone()]).is_err());
+        fork.based_on = Some("1.1".to_string());
+        assert!(VersionRegistry::new(&[fork.clone()]).is_err());
+
+        // A fork is known only to a registry that has it, and hashes by its
+        // base's rules whichever registry verifies it
+        fork.based_on = Some("2.0".to_string());
+        let versions = VersionRegistry::new(&[fork]).unwrap();
+        assert!(versions.is_known("2.0-registry-test"));
+        assert!(!VersionRegistry::default().is_known("2.0-registry-test"));
+        assert_eq!(
+            hashing_scheme("2.0-registry-test"),
+            HashingScheme::CanonicalJson
+        );
+        assert_eq!(
+            canonicalization_profile("2.0-registry-test"),
+            CanonicalizationProfile::Basic
+        );
+        assert_eq!(
+            canonicalization_profile("2.1+registry-test"),
+            CanonicalizationProfile::Nfc
+        );
+        assert_eq!(versions.deprecation_notice("2.0-registry-test"), None);
+
+        // Entries come from rhodi.toml and its registry file, dates bare or quoted
+        let dir = temp_workspace("version_registry");
+        std::fs::write(
+            dir.join("versions.toml"),
+            "[[versions]]\nversion = \"1.1-registry-test\"\nbased_on = \"1.1\"\nstatus = \"obsolete\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("rhodi.toml"),
+            r#"[protocol]
+registry = "versions.toml"
+
+[[protocol.versions]]
+version = "2.0-registry-test"
+based_on = "2.0"
+deprecated_on = 2025-03-01
+note = "Migrate to 2.1."
+"#,
+        )
+        .unwrap();
+        let workspace = Workspace::discover(&dir).unwrap();
+        assert_eq!(workspace.config.protocol.versions.len(), 1);
+        let versions = workspace.version_registry().unwrap();
+        assert_eq!(
+            versions.status("2.0-registry-test"),
+            VersionStatus::Deprecated
+        );
+        assert_eq!(
+            versions.deprecation_notice("2.0-registry-test").unwrap(),
+            "Protocol version 2.0-registry-test is deprecated since 2025-03-01. Migrate to 2.1."
+        );
+        assert_eq!(
+            versions.status("1.1-registry-test"),
+            VersionStatus::Obsolete
+        );
+        // Discovering the workspace changed nothing for anyone else
+        assert!(!crate::version::is_version_known("2.0-registry-test"));
+        assert!(
+            WorkspaceConfig::from_toml(
+                "[[protocol.versions]]\nversion = \"3.0\"\nstatus = \"retired\"\n"
+            )
+            .is_err()
+        );
+
+        // Verification with the registry warns about deprecated versions,
+        // accepts their signatures and refuses obsolete ones
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("Registry", "Body.");
+        doc.frontmatter.protocol_version = "2.0-registry-test".to_string();
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let doc = doc.seal(&keypair).unwrap();
+        let resolver = FileResolver::new(&dir).unwrap();
+        let report = Compiler::new(&resolver)
+            .with_version_registry(versions.clone())
+            .verify(&doc)
+            .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| w.contains("is deprecated since 2025-03-01"))
+        );
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(
+            report.errors[0]
+                .to_string()
+                .contains("Unknown protocol version")
+        );
+        // The same holds for a key found through key_uri
+        let mut doc = TracedDocument::new("Registry", "Body.");
+        doc.frontmatter.protocol_version = "2.0-registry-test".to_string();
+        doc.frontmatter.key_uri = Some(crate::discovery::did_key_from_public_key(
+            &keypair.verifying_key,
+        ));
+        let doc = doc.seal(&keypair).unwrap();
+        assert_eq!(doc.frontmatter.public_key, None);
+        let report = Compiler::new(&resolver)
+            .with_version_registry(versions.clone())
+            .verify(&doc)
+            .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        let report = Compiler::new(&resolver).verify(&doc).unwrap();
+        assert!(
+            report.errors[0]
+                .to_string()
+                .contains("Unknown protocol version")
+        );
+        let mut doc = TracedDocument::new("Registry", "Body.");
+        doc.frontmatter.protocol_version = "1.1-registry-test".to_string();
+        let sealed = doc.seal(&keypair).unwrap();
+        assert!(
+            sealed
+                .verify_in(keypair.verifying_key.as_bytes(), &versions)
+                .unwrap_err()
+                .to_string()
+                .contains("obsolete")
+        );
+    }
+
+    #[test]
+    fn test_trace_and_document_time_budgets() {
+        use crate::compiler::{CancellationToken, Compiler, CompilerLimits, SectionOutcome};
+        use crate::error::SecurityError;
+        use crate::resolver::SourceResolver;
+        use std::time::Duration;
+
+        // Sources named `slow-…` take 100ms to read
+        struct Slow;
+        impl SourceResolver for Slow {
+            fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+                if source.starts_with("slow") {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Ok(b"Revenue: 42".to_vec())
+            }
+            fn resolve_document(&self, _source: &str) -> Result<TracedDocument> {
+                Err(RhodiError::Resolution("not a document".into()))
+            }
+        }
+        let trace = |source: &str| {
+            format!(
+                "```trace\nsource: {}\nselector: \"Revenue: (\\\\d+)\"\nexpected: \"42\"\n```\n\n",
+                source
+            )
+        };
+        let limits = CompilerLimits {
+            trace_timeout: Duration::from_millis(50),
+            ..CompilerLimits::default()
+        };
+
+        // A trace over its budget times out; the others still pass
+        let doc = TracedDocument::new("Budgets", &(trace("fast.txt") + &trace("slow-a.txt")));
+        let compiler = Compiler::new(&Slow).with_limits(limits);
+        let verified = compiler.verify_detailed(&doc).unwrap();
+        let outcomes: Vec<SectionOutcome> = verified.sections.iter().map(|s| s.outcome).collect();
+        assert!(outcomes.contains(&SectionOutcome::Passed));
+        assert!(outcomes.contains(&SectionOutcome::TimedOut));
+        assert_eq!(verified.outcome(), SectionOutcome::TimedOut);
+        let report = verified.into_report();
+        assert_eq!(report.timed_out.len(), 1);
+        assert!(report.timed_out[0].contains("slow-a.txt"));
+        let slow = TraceBlock::builder()
+            .source("slow-b.txt")
+            .selector("Revenue: (\\d+)")
+            .extractor("regex")
+            .expected("42")
+            .build()
+            .unwrap();
+        let result = compiler.check_trace(&slow);
+        assert!(result.error.unwrap().contains("Timed out"));
+
+        // Once the document's budget is spent, the remaining traces time out
+        let limits = CompilerLimits {
+            document_timeout: Duration::from_millis(150),
+            ..CompilerLimits::default()
+        };
+        let doc = TracedDocument::new(
+            "Budgets",
+            &(trace("slow-a.txt") + &trace("slow-b.txt") + &trace("fast.txt")),
+        );
+        let report = Compiler::new(&Slow)
+            .with_limits(limits)
+            .verify(&doc)
+            .unwrap();
+        assert_eq!(report.timed_out.len(), 2);
+        assert!(report.timed_out[1].contains("fast.txt"));
+
+        // A failure outranks a timeout, in a section as in a summary
+        let mut mixed = report;
+        mixed
+            .errors
+            .push(RhodiError::Verification("Hash mismatch".into()));
+        assert_eq!(SectionOutcome::of(&mixed), SectionOutcome::Failed);
+        assert_eq!(
+            SectionOutcome::TimedOut.worse(SectionOutcome::Failed),
+            SectionOutcome::Failed
+        );
+
+        // Exec commands are stopped at the deadline rather than their own
+        // timeout
+        let exec = crate::extraction::ExecExtractor::new(
+            [(
+                "stall".to_string(),
+                vec!["sleep".to_string(), "5".to_string()],
+            )]
+            .into(),
+            std::env::temp_dir(),
+        );
+        let stalled = TraceBlock::builder()
+            .source("fast.txt")
+            .selector("stall")
+            .extractor("exec")
+            .expected("42")
+            .build()
+            .unwrap();
+        let limits = CompilerLimits {
+            trace_timeout: Duration::from_millis(100),
+            ..CompilerLimits::default()
+        };
+        let started = std::time::Instant::now();
+        let result = Compiler::new(&Slow)
+            .with_limits(limits)
+            .with_exec_extractor(Some(exec))
+            .check_trace(&stalled);
+        assert!(result.error.unwrap().contains("Timed out"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        // Cancellation stops verification altogether
+        let token = CancellationToken::new();
+        token.cancel();
+        let compiler = Compiler::new(&Slow).with_cancellation(token);
+        assert!(matches!(
+            compiler.verify(&doc),
+            Err(RhodiError::Security(SecurityError::Cancelled))
+        ));
+    }
+
+    #[test]
+    fn test_review_comments_outside_version_hash() {
+        use crate::comments::{ReviewComment, append_review, review_comments};
+        use crate::compiler::Compiler;
+        use crate::resolver::FileResolver;
+
+        use crate::models::{Contributor, ContributorRole};
+
+        let key = KeyPair::generate();
+        let reviewer = KeyPair::generate();
+        let mut doc = TracedDocument::new("Reviewed", "# Results\n\nRevenue grew.\n\n\nOutlook.");
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        doc.frontmatter.contributors = Some(vec![Contributor {
+            name: "Grace".to_string(),
+            role: ContributorRole::Reviewer,
+            key: Some(hex::encode(reviewer.verifying_key.as_bytes())),
+            orcid: None,
+        }]);
+        let sealed = doc.seal(&key).unwrap();
+        let version_hash = sealed.frontmatter.version_hash;
+
+        let signed = ReviewComment::new(&sealed, "Grace", "Cite the audited figures")
+            .sign(&sealed, &reviewer);
+        let unsigned = ReviewComment::new(&sealed, "Ada", "Typo in the outlook");
+        let mut reviewed = sealed.clone();
+        reviewed.body = append_review(&reviewed.body, &signed).unwrap();
+        reviewed.body = append_review(&reviewed.body, &unsigned).unwrap();
+        // A comment written by hand between paragraphs is excluded as well
+        reviewed.body = reviewed.body.replacen(
+            "Revenue grew.\n\n",
+            &format!("Revenue grew.\n\n{}\n", unsigned.render().unwrap()),
+            1,
+        );
+
+        let comments: Vec<ReviewComment> = review_comments(&reviewed.body)
+            .into_iter()
+            .map(|c| c.unwrap())
+            .collect();
+        assert_eq!(comments.len(), 3);
+        assert_eq!(comments[1], signed);
+        assert_eq!(comments[1].verify(&reviewed).unwrap(), None);
+        assert_eq!(comments[1].version_hash, version_hash.map(hex::encode));
+
+        // The seal still holds; only the unsigned comments are warned about
+        assert_eq!(
+            reviewed.compute_version_hash().unwrap(),
+            version_hash.unwrap()
+        );
+        let dir = temp_workspace("review-comments");
+        let resolver = FileResolver::new(&dir).unwrap();
+        let report = Compiler::new(&resolver).verify(&reviewed).unwrap();
+        assert!(report.errors.is_empty());
+        assert_eq!(report.warnings.len(), 2);
+        assert!(
+            report
+                .warnings
+                .iter()
+                .all(|w| w.contains("Ada is unsigned"))
+        );
+
+        // So is a comment signed with a key no contributor has
+        let outsider = ReviewComment::new(&sealed, "Mallory", "Looks fine")
+            .sign(&sealed, &KeyPair::generate());
+        assert!(
+            outsider
+                .verify(&sealed)
+                .unwrap()
+                .unwrap()
+                .contains("no listed contributor")
+        );
+
+        // An altered signed comment is reported, without failing the seal
+        reviewed.body = reviewed.body.replace("audited", "unaudited");
+        let report = Compiler::new(&resolver).verify(&reviewed).unwrap();
+        assert!(report.errors.is_empty());
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| w.contains("invalid signature"))
+        );
+
+        // A review fence that is never closed is body text, and hashed
+        let mut unclosed = sealed.clone();
+        unclosed.body.push_str("\n\n```review\nauthor: Eve\n");
+        assert_ne!(
+            unclosed.compute_version_hash().unwrap(),
+            version_hash.unwrap()
+        );
+
+        // Before 2.2 review blocks are hashed like any other text
+        let mut older = sealed.clone();
+        older.frontmatter.protocol_version = "2.1".to_string();
+        let hash = older.compute_version_hash().unwrap();
+        older.body = append_review(&older.body, &unsigned).unwrap();
+        assert_ne!(older.compute_version_hash().unwrap(), hash);
+
+        // Editing the text outside review blocks still breaks the seal
+        reviewed.body = reviewed.body.replace("Outlook", "Forecast");
+        assert_ne!(
+            reviewed.compute_version_hash().unwrap(),
+            version_hash.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_telemetry_spans_and_otlp_export() {
+        use crate::compiler::Compiler;
+        use crate::resolver::SourceResolver;
+        use crate::telemetry::Telemetry;
+
+        struct Sources;
+        impl SourceResolver for Sources {
+            fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+                match source {
+                    "data.txt" => Ok(b"Revenue: 42".to_vec()),
+                    _ => Err(RhodiError::Resolution(format!("{} not found", source))),
+                }
+            }
+            fn resolve_document(&self, _source: &str) -> Result<TracedDocument> {
+                Err(RhodiError::Resolution("not a document".into()))
+            }
+        }
+        let trace = |source: &str| {
+            format!(
+                "```trace\nid: {}\nsource: {}\nselector: \"Revenue: (\\\\d+)\"\nexpected: \"42\"\n```\n\n",
+                source.trim_end_matches(".txt"),
+                source
+            )
+        };
+        let doc = TracedDocument::new("Telemetry", &(trace("data.txt") + &trace("missing.txt")));
+        let telemetry = Telemetry::new();
+        Compiler::new(&Sources)
+            .with_telemetry(Some(&telemetry))
+            .verify(&doc)
+            .unwrap();
+
+        // One span per document, trace and resolver call, nested in that order
+        let spans = telemetry.spans();
+        let root = spans.iter().find(|s| s.name == "rhodi.verify").unwrap();
+        assert_eq!(root.parent_span_id, None);
+        let traces: Vec<_> = spans.iter().filter(|s| s.name == "rhodi.trace").collect();
+        assert_eq!(traces.len(), 2);
+        assert!(
+            traces
+                .iter()
+                .all(|s| s.parent_span_id == Some(root.span_id))
+        );
+        assert!(traces[0].error.is_none());
+        assert!(traces[1].error.as_ref().unwrap().contains("missing.txt"));
+        let resolves: Vec<_> = spans.iter().filter(|s| s.name == "rhodi.resolve").collect();
+        assert!(!resolves.is_empty());
+        assert!(
+            resolves
+                .iter()
+                .all(|s| traces.iter().any(|t| s.parent_span_id == Some(t.span_id)))
+        );
+        assert!(spans.iter().all(|s| s.end.is_some()));
+        assert_eq!(telemetry.counter("rhodi.traces"), 2);
+        assert_eq!(telemetry.counter("rhodi.documents"), 1);
+        assert_eq!(telemetry.counter("rhodi.resolver.bytes"), 11);
+
+        // Exported as OTLP/JSON to the collector's traces and metrics endpoints
+        let collector = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", collector.server_addr().to_ip().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            for _ in 0..2 {
+                let mut request = collector.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                received.push((request.url().to_string(), body));
+                request.respond(tiny_http::Response::empty(200)).unwrap();
+            }
+            received
+        });
+        telemetry.export(&format!("{}/", endpoint)).unwrap();
+        let received = server.join().unwrap();
+        assert_eq!(received[0].0, "/v1/traces");
+        assert_eq!(received[1].0, "/v1/metrics");
+
+        let traces: serde_json::Value = serde_json::from_str(&received[0].1).unwrap();
+        let resource_spans = &traces["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["value"]["stringValue"],
+            "rhodi"
+        );
+        let exported = resource_spans["scopeSpans"][0]["spans"].as_array().unwrap();
+        assert_eq!(exported.len(), spans.len());
+        assert!(
+            exported
+                .iter()
+                .all(|s| s["traceId"].as_str().unwrap().len() == 32)
+        );
+        assert!(exported.iter().any(|s| s["status"]["code"] == 2));
+
+        let metrics: serde_json::Value = serde_json::from_str(&received[1].1).unwrap();
+        let metrics = metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap();
+        let duration = metrics
+            .iter()
+            .find(|m| m["name"] == "rhodi.trace.duration")
+            .unwrap();
+        assert_eq!(duration["histogram"]["dataPoints"][0]["count"], "2");
+    }
+
+    #[test]
+    fn test_amend_metadata_reseals() {
+        use crate::compiler::Compiler;
+        use crate::models::{Amendment, Author};
+        use crate::resolver::FileResolver;
+
+        let key = KeyPair::generate();
+        let mut doc = TracedDocument::new("Quartely Report", "Revenue grew.\n");
+        doc.frontmatter.public_key = Some(hex::encode(key.verifying_key.as_bytes()));
+        let doc = doc.seal(&key).unwrap();
+        let sealed_hash = doc.frontmatter.version_hash;
+
+        let amendment = Amendment {
+            title: Some("Quarterly Report".to_string()),
+            author: Some(Author {
+                name: "Ada Lovelace".to_string(),
+                ..Author::default()
+            }),
+            extra: [("department".to_string(), Some("Finance".to_string()))].into(),
+            reason: "typo in the title".to_string(),
+        };
+        let amended = doc.clone().amend(&amendment, &key).unwrap();
+        let fm = &amended.frontmatter;
+        assert_eq!(fm.title, "Quarterly Report");
+        assert_eq!(fm.doc_version, doc.frontmatter.doc_version + 1);
+        assert_eq!(fm.prev_version_hash, sealed_hash);
+        assert_eq!(amended.body, doc.body);
+        let extra = fm.extra.as_ref().unwrap();
+        assert_eq!(extra["amendment_reason"], "typo in the title");
+        assert_eq!(extra["amended_fields"], "title, author, extra.department");
+        assert_eq!(extra["department"], "Finance");
+        let resolver = FileResolver::new(std::env::temp_dir()).unwrap();
+        let report = Compiler::new(&resolver).verify(&amended).unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+        // Only intact published versions, with their own key and a real change
+        let no_change = Amendment {
+            title: Some("Quartely Report".to_string()),
+            reason: "none".to_string(),
+            ..Amendment::default()
+        };
+        assert!(doc.clone().amend(&no_change, &key).is_err());
+        assert!(doc.clone().amend(&amendment, &KeyPair::generate()).is_err());
+        let mut tampered = doc.clone();
+        tampered.body = "Revenue fell.\n".to_string();
+        assert!(tampered.amend(&amendment, &key).is_err());
+        let mut forged = doc.clone();
+        forged.frontmatter.signature = Some(vec![0; 64]);
+        assert!(forged.amend(&amendment, &key).is_err());
+        let draft = TracedDocument::new("Draft", "Text\n");
+        assert!(draft.amend(&amendment, &key).is_err());
+    }
+
+    #[test]
+    fn test_http_cache_revalidates_with_etag() {
+        use crate::cache::HttpCache;
+        use crate::resolver::HttpResolver;
+        use std::sync::{Arc, Mutex};
+
+        // An origin serving `/data.csv` with an ETag and answering matching
+        // conditional requests with 304; the requests it saw are recorded
+        let origin = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/data.csv", origin.server_addr().to_ip().unwrap());
+        let content = Arc::new(Mutex::new(("v1", "revenue,42\n")));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (served, log) = (content.clone(), seen.clone());
+        std::thread::spawn(move || {
+            for request in origin.incoming_requests() {
+                let (etag, body) = *served.lock().unwrap();
+                let condition = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("If-None-Match"))
+                    .map(|h| h.value.to_string());
+                let fresh = condition.as_deref() == Some(etag);
+                log.lock().unwrap().push(condition);
+                let etag = tiny_http::Header::from_bytes("ETag", etag).unwrap();
+                let response = if fresh {
+                    tiny_http::Response::from_string("").with_status_code(304)
+                } else {
+                    tiny_http::Response::from_string(body)
+                };
+                let _ = request.respond(response.with_header(etag));
+            }
+        });
+
+        let dir = temp_workspace("http-cache").join("cache");
+        let resolver = HttpResolver::new().with_cache(Some(HttpCache::new(&dir)));
+        let first = resolver.fetch(&url, None).unwrap();
+        assert_eq!(first.bytes, b"revenue,42\n");
+        assert!(!first.metadata.from_cache);
+
+        // Unchanged: revalidated and served from the cache
+        let second = resolver.fetch(&url, None).unwrap();
+        assert_eq!(second.bytes, b"revenue,42\n");
+        assert!(second.metadata.from_cache);
+
+        // Changed at the origin: downloaded again and re-cached
+        *content.lock().unwrap() = ("v2", "revenue,43\n");
+        let third = resolver.fetch(&url, None).unwrap();
+        assert_eq!(third.bytes, b"revenue,43\n");
+        assert!(!third.metadata.from_cache);
+        assert_eq!(
+            HttpCache::new(&dir).lookup(&url).unwrap().etag.unwrap(),
+            "v2"
+        );
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![None, Some("v1".to_string()), Some("v1".to_string())]
+        );
+
+        // A corrupt cached copy fails the fetch once and is dropped
+        let cached = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|file| file.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "bin"))
+            .unwrap();
+        std::fs::write(&cached, "revenue,44\n").unwrap();
+        let error = resolver.fetch(&url, None).unwrap_err();
+        assert!(error.to_string().contains("corrupt"), "{}", error);
+        assert!(HttpCache::new(&dir).lookup(&url).is_none());
+        assert_eq!(resolver.fetch(&url, None).unwrap().bytes, b"revenue,43\n");
+
+        // Without a cache, every fetch is unconditional
+        HttpResolver::new().fetch(&url, None).unwrap();
+        assert_eq!(seen.lock().unwrap().last().unwrap(), &None);
+
+        // Redirects are followed only to hosts the resolver allows
+        let redirector = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let moved = format!("http://{}/moved", redirector.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            for request in redirector.incoming_requests() {
+                let location =
+                    tiny_http::Header::from_bytes("Location", "https://evil.example.com/x")
+                        .unwrap();
+                let _ = request.respond(
+                    tiny_http::Response::from_string("")
+                        .with_status_code(302)
+                        .with_header(location),
+                );
+            }
+        });
+        assert!(matches!(
+            HttpResolver::new()
+                .with_allowed_hosts(["127.0.0.1"])
+                .fetch(&moved, None),
+            Err(RhodiError::Security(_))
+        ));
+
+        // Over the size limit, the least recently used entries are evicted
+        let small = HttpCache::new(dir.join("small")).with_max_bytes(20);
+        small
+            .store("https://a.example/1", Some("a"), None, None, b"0123456789")
+            .unwrap();
+        small
+            .store("https://a.example/2", Some("b"), None, None, b"0123456789")
+            .unwrap();
+        small
+            .store("https://a.example/3", Some("c"), None, None, b"0123456789")
+            .unwrap();
+        assert!(small.lookup("https://a.example/1").is_none());
+        assert!(small.lookup("https://a.example/3").is_some());
+        assert_eq!(small.size(), 20);
+        // Sources without validators, or larger than the cache, are not kept
+        small
+            .store("https://a.example/4", None, None, None, b"x")
+            .unwrap();
+        small
+            .store("https://a.example/5", Some("e"), None, None, &[0; 21])
+            .unwrap();
+        assert!(small.lookup("https://a.example/4").is_none());
+        assert!(small.lookup("https://a.example/5").is_none());
+    }
+
+    #[test]
+    fn test_opentimestamps_stamp_upgrade_and_verify() {
+        use crate::opentimestamps::{
+            Attestation, BlockHeaders, Calendars, DetachedTimestamp, Op, Timestamp,
+            TimestampStatus, verify,
+        };
+        use chrono::{DateTime, Utc};
+        use sha2::{Digest, Sha256};
+        use std::sync::{Arc, Mutex};
+
+        // A calendar committing to digests at once and, once asked for the
+        // timestamp, completing it with a Bitcoin attestation
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let calendar = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let leaf = |op: Op, attestation: Attestation| Timestamp {
+            attestations: Vec::new(),
+            ops: vec![(
+                op,
+                Timestamp {
+                    attestations: Vec::new(),
+                    ops: vec![(
+                        Op::Sha256,
+                        Timestamp {
+                            attestations: vec![attestation],
+                            ops: Vec::new(),
+                        },
+                    )],
+                },
+            )],
+        };
+        let commitments = Arc::new(Mutex::new(Vec::new()));
+        let (uri, seen) = (calendar.clone(), commitments.clone());
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let body = if request.url() == "/digest" {
+                    let mut commitment = Vec::new();
+                    request.as_reader().read_to_end(&mut commitment).unwrap();
+                    seen.lock().unwrap().push(commitment);
+                    leaf(
+                        Op::Append(b"cal".to_vec()),
+                        Attestation::Pending { uri: uri.clone() },
+                    )
+                } else {
+                    leaf(
+                        Op::Prepend(b"blk".to_vec()),
+                        Attestation::Bitcoin { height: 800_000 },
+                    )
+                };
+                let _ = request.respond(tiny_http::Response::from_data(body.to_bytes()));
+            }
+        });
+
+        struct Headers([u8; 32], DateTime<Utc>);
+        impl BlockHeaders for Headers {
+            fn block(&self, height: u64) -> Result<([u8; 32], DateTime<Utc>)> {
+                assert_eq!(height, 800_000);
+                Ok((self.0, self.1))
+            }
+        }
+
+        let version_hash: [u8; 32] = Sha256::digest(b"version").into();
+        let calendars = Calendars::new(std::slice::from_ref(&calendar));
+        let mut proof = calendars.stamp(version_hash).unwrap();
+        assert_eq!(DetachedTimestamp::parse(&proof.to_bytes()).unwrap(), proof);
+        let headers = Headers([0; 32], DateTime::from_timestamp(1_690_000_000, 0).unwrap());
+        assert_eq!(
+            verify(&proof, &version_hash, &headers).unwrap(),
+            TimestampStatus::Pending {
+                calendars: vec![calendar.clone()]
+            }
+        );
+
+        // The calendar saw only the nonce-blinded commitment
+        let commitment = commitments.lock().unwrap()[0].clone();
+        assert_eq!(commitment.len(), 32);
+        assert_ne!(commitment, version_hash);
+
+        assert!(calendars.upgrade(&mut proof).unwrap());
+        assert!(proof.is_complete().unwrap());
+        let proof = DetachedTimestamp::parse(&proof.to_bytes()).unwrap();
+        let pending: Vec<u8> = Sha256::digest([commitment.as_slice(), b"cal"].concat()).to_vec();
+        let root: [u8; 32] = Sha256::digest([b"blk".as_slice(), &pending].concat()).into();
+        assert_eq!(
+            verify(&proof, &version_hash, &Headers(root, headers.1)).unwrap(),
+            TimestampStatus::Confirmed {
+                height: 800_000,
+                time: headers.1
+            }
+        );
+
+        // A different block or a different version does not verify
+        assert!(verify(&proof, &version_hash, &headers).is_err());
+        let other: [u8; 32] = Sha256::digest(b"other").into();
+        assert!(verify(&proof, &other, &Headers(root, headers.1)).is_err());
+        assert!(DetachedTimestamp::parse(b"not a proof").is_err());
+    }
+
+    #[test]
+    fn test_overlay_resolver_serves_batch_versions() {
+        use crate::compiler::Compiler;
+        use crate::markdown::{Section, parse_tmd_sections, render_tmd, repin_quote_blocks};
+        use crate::resolver::{FileResolver, OverlayResolver, SourceResolver};
+        use std::collections::BTreeMap;
+
+        let dir = temp_workspace("overlay");
+        let keypair = KeyPair::generate();
+        let seal = |body: &str| {
+            let mut doc = TracedDocument::new("Source", body);
+            doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+            doc.seal(&keypair).unwrap()
+        };
+        let old = seal("Revenue grew by 12% in Q3.\n");
+        std::fs::write(dir.join("source.tmd"), render_tmd(&old).unwrap()).unwrap();
+        let new = seal("Revenue grew by 12% in Q3.\nCosts fell.\n");
+        let new_hash = hex::encode(new.frontmatter.version_hash.unwrap());
+
+        let body = format!(
+            "As reported:\n\n```quote\nsource_doc: source.tmd\nversion_hash: {}\nexcerpt: \"Revenue grew by 12% in Q3.\"\n```\n\nDone.\n",
+            hex::encode(old.frontmatter.version_hash.unwrap())
+        );
+        let repinned = repin_quote_blocks(&body, |quote| {
+            (quote.source_doc == "source.tmd").then(|| new_hash.clone())
+        });
+        assert!(repinned.contains(&format!("version_hash: {}\n", new_hash)));
+        assert!(repinned.ends_with("```\n\nDone.\n"));
+        assert_eq!(repin_quote_blocks(&body, |_| None), body);
+        let quote = parse_tmd_sections(&repinned)
+            .into_iter()
+            .find_map(|s| match s {
+                Section::Quote(q) => Some(q),
+                _ => None,
+            })
+            .unwrap();
+
+        // On disk the old version is still there; the overlay serves the new one
+        let files = FileResolver::new(&dir).unwrap();
+        assert!(Compiler::new(&files).check_quote(&quote).is_err());
+        let mut documents = BTreeMap::new();
+        documents.insert(
+            files.identity("source.tmd").unwrap(),
+            render_tmd(&new).unwrap(),
+        );
+        let overlay = OverlayResolver::new(&files, &documents);
+        Compiler::new(&overlay).check_quote(&quote).unwrap();
+        assert_eq!(
+            overlay.resolve_document("./source.tmd").unwrap().frontmatter.version_hash,
+            new.frontmatter.version_hash
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_typed_selectors() {
+        use crate::extraction::{CellExtractor, Extractor, LinesExtractor, select_extractor};
+        use crate::selector::{ByteEncoding, Selector};
+
+        // Parsed per extractor, written back in compact form
+        assert_eq!(
+            Selector::parse("bytes", "bytes:0x10..4:utf8").unwrap(),
+            Selector::Bytes {
+                offset: 16,
+                len: 4,
+                encoding: ByteEncoding::Utf8
+            }
+        );
+        let cell = Selector::parse("auto", "cell:Results!AB12").unwrap();
+        assert_eq!(
+            cell,
+            Selector::Cell {
+                sheet: Some("Results".into()),
+                row: 11,
+                col: 27
+            }
+        );
+        assert_eq!(cell.to_string(), "cell:Results!AB12");
+        assert_eq!(Selector::parse("lines", "lines:3").unwrap().to_string(), "lines:3");
+        assert_eq!(
+            Selector::parse("auto", "$.metrics.accuracy").unwrap(),
+            Selector::JsonPath("$.metrics.accuracy".into())
+        );
+        for (extractor, spec) in [
+            ("regex", r"Revenue: (\d+)"),
+            ("yaml", ".runs[0].loss"),
+            ("exec", "pdf-total --page 3"),
+            ("bytes", "bytes:0..4"),
+            ("lines", "lines:2..5"),
+        ] {
+            assert_eq!(Selector::parse(extractor, spec).unwrap().to_string(), spec);
+        }
+
+        // Malformed selectors fail before any source is read
+        for (extractor, spec) in [
+            ("regex", "Revenue: (\\d+"),
+            ("jsonpath", "$.[["),
+            ("toml", ".runs[x]"),
+            ("bytes", "bytes:4"),
+            ("cell", "cell:12"),
+            ("cell", "cell:A0"),
+            ("lines", "lines:5..2"),
+            ("sql", "SELECT 1"),
+        ] {
+            assert!(Selector::parse(extractor, spec).is_err(), "{}", spec);
+        }
+        let error = TraceBlock::builder()
+            .source("data.csv")
+            .selector("lines:0")
+            .extractor("lines")
+            .expected("x")
+            .build()
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("lines are numbered from 1"),
+            "{}",
+            error
+        );
+        // Agent selectors are hints, not parsed
+        let mut hint = parse_trace_block(
+            "```trace\nsource: a.pdf\nselector: \"table 3 (\"\nexpected: \"1\"\nmethod: agent\n```",
+        )
+        .unwrap();
+        assert_eq!(hint.typed_selector().unwrap(), None);
+        hint.method = crate::models::TraceMethod::Automatic;
+        assert!(hint.typed_selector().is_err());
+
+        // Line and cell extractors
+        let text = b"one\ntwo\nthree\n";
+        assert_eq!(LinesExtractor.extract(text, "lines:2..3").unwrap(), "two\nthree");
+        assert!(LinesExtractor.extract(text, "lines:3..4").is_err());
+        let csv = b"name,total\n\"Widgets, large\",\"1,204\"\r\n\"Say \"\"hi\"\"\",7\n";
+        assert_eq!(CellExtractor.extract(csv, "cell:A2").unwrap(), "Widgets, large");
+        assert_eq!(CellExtractor.extract(csv, "cell:B2").unwrap(), "1,204");
+        assert_eq!(CellExtractor.extract(csv, "cell:A3").unwrap(), "Say \"hi\"");
+        assert!(CellExtractor.extract(csv, "cell:C1").is_err());
+        assert!(CellExtractor.extract(csv, "cell:Sheet1!A1").is_err());
+        assert_eq!(select_extractor(None, Some("text/csv"), "cell:B2"), "cell");
+        assert_eq!(select_extractor(Some("auto"), None, "lines:1"), "lines");
+        // Only a selector that parses in full picks them
+        assert_eq!(select_extractor(None, None, r"lines: (\d+)"), "regex");
+        assert_eq!(
+            select_extractor(None, Some("text/csv"), r"cell: (\w+)"),
+            "regex"
+        );
+        assert_eq!(
+            Selector::parse("auto", r"lines: (\d+)").unwrap(),
+            Selector::Regex(r"lines: (\d+)".into())
+        );
+        assert_eq!(
+            LinesExtractor
+                .extract_selector(text, &Selector::Lines { from: 1, to: 2 })
+                .unwrap(),
+            "one\ntwo"
+        );
+        assert!(
+            LinesExtractor
+                .extract_selector(text, &Selector::Regex("one".into()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_trace_suggestions_from_evidence() {
+        use crate::suggest::{EvidenceFile, insert_traces, suggest, untraced_claims};
+
+        let body = "# Results\n\nRevenue reached $1,204 in Q3,\nup 12.5%.\n\nAccuracy was 0.87.\n\n```trace\nsource: eval.json\nexpected: \"0.87\"\n```\n\n```text\nNot prose: 99.5\n```\n";
+        let claims = untraced_claims(body);
+        let figures: Vec<&str> = claims.iter().map(|c| c.figure.as_str()).collect();
+        assert_eq!(figures, vec!["$1,204", "12.5%"]);
+        assert_eq!(claims[0].value, 1204.0);
+        assert_eq!((claims[0].line_index, claims[0].paragraph_end), (2, 3));
+
+        let file = |source: &str, content: &str| EvidenceFile {
+            source: source.to_string(),
+            content: content.as_bytes().to_vec(),
+        };
+        let files = vec![
+            file("image.bin", "1204\0"),
+            file("notes.txt", "Draft total 1203\nFinal revenue: 1,204 USD\n"),
+            file("sales.csv", "region,revenue\nEU,1204\n"),
+            file("stats.json", "{\"q3\": {\"growth %\": 12.5}}"),
+        ];
+        let found = suggest(&claims[0], &files, 3);
+        let selectors: Vec<&str> = found.iter().map(|s| s.selector.as_str()).collect();
+        assert_eq!(
+            selectors,
+            vec![r"Final\s+revenue:\s+(-?\d[\d,.]*\d%?|\d%?)", "cell:B2"]
+        );
+        assert_eq!(found[0].expected, "1,204");
+        assert_eq!(found[1].expected, "1204");
+        let growth = suggest(&claims[1], &files, 3);
+        assert_eq!(growth[0].selector, "$.q3['growth %']");
+
+        // The suggested block parses back into a working trace
+        let block = found[1].to_block();
+        let trace = parse_trace_block(&block).unwrap();
+        assert_eq!(trace.selector.as_deref(), Some("cell:B2"));
+        assert_eq!(trace.hash, Some(found[1].hash.clone()));
+        let updated = insert_traces(body, &[(claims[0].paragraph_end, block)]);
+        assert!(updated.contains("up 12.5%.\n\n```trace\nsource: \"sales.csv\""));
+        let figures: Vec<String> = untraced_claims(&updated)
+            .into_iter()
+            .map(|c| c.figure)
+            .collect();
+        assert_eq!(figures, vec!["12.5%"]);
+    }
 }
@@ -0,0 +1,83 @@
+//! Advisory lock files guarding read-modify-write cycles on workspace files.
+
+use crate::error::{Result, RhodiError};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a contended lock before giving up.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(25);
+
+/// An exclusive advisory lock, held for as long as the value lives.
+///
+/// The lock is a sibling file named `<target>.lock`, created atomically with
+/// `create_new` and removed on drop. It only protects against other rhodi
+/// processes that take the same lock.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Path of the lock file guarding `target`.
+    pub fn lock_path(target: &Path) -> PathBuf {
+        let mut name = target.as_os_str().to_owned();
+        name.push(".lock");
+        PathBuf::from(name)
+    }
+
+    /// Acquire the lock for `target`, waiting up to `timeout` if it is held.
+    pub fn acquire(target: &Path, timeout: Duration) -> Result<Self> {
+        let path = Self::lock_path(target);
+        let started = Instant::now();
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // Record the owner to help diagnose stale locks
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if started.elapsed() >= timeout {
+                        return Err(RhodiError::Resolution(format!(
+                            "'{}' is locked by another process. If no rhodi process is running, remove '{}'.",
+                            target.display(),
+                            path.display()
+                        )));
+                    }
+                    thread::sleep(RETRY_INTERVAL);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Replace `target` with `content` atomically (write a temp file, then rename).
+pub fn write_atomic(target: &Path, content: &str) -> Result<()> {
+    write_atomic_bytes(target, content.as_bytes())
+}
+
+/// [`write_atomic`] for binary content.
+pub fn write_atomic_bytes(target: &Path, content: &[u8]) -> Result<()> {
+    let mut tmp_name = target.as_os_str().to_owned();
+    tmp_name.push(format!(".tmp-{}", std::process::id()));
+    let tmp = PathBuf::from(tmp_name);
+
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, target).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok(())
+}
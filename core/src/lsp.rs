@@ -0,0 +1,538 @@
+//! Language server for `.tmd` files.
+//!
+//! Publishes diagnostics (invalid block YAML, unknown extractors, failing
+//! traces, unresolvable includes), shows version hash details on hover and
+//! jumps to included documents and trace sources.
+
+use crate::compiler::Compiler;
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError};
+use crate::markdown::{
+    FenceLine, Fences, parse_include_block, parse_quote_block, parse_tmd, parse_tmd_sections,
+    parse_trace_block, trace_anchors,
+};
+use crate::models::DocStatus;
+use crate::resolver::{FileResolver, ResolverRegistry, SourceResolver};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const SOURCE: &str = "rhodi";
+
+/// A fenced rhodi block located in the document text.
+struct FencedBlock {
+    kind: &'static str,
+    /// Line of the opening fence
+    start: u32,
+    /// Line of the closing fence (the last line if unterminated)
+    end: u32,
+    /// Block text including both fences
+    content: String,
+}
+
+impl FencedBlock {
+    fn range(&self, text: &str) -> Range {
+        line_range(text, self.start, self.end)
+    }
+
+    /// The first line inside the block starting with `key:`.
+    fn key_line(&self, key: &str) -> Option<u32> {
+        let prefix = format!("{}:", key);
+        self.content
+            .lines()
+            .position(|l| l.trim_start().starts_with(&prefix))
+            .map(|i| self.start + i as u32)
+    }
+}
+
+fn fenced_blocks(text: &str) -> Vec<FencedBlock> {
+    let mut blocks = Vec::new();
+    let mut fences = Fences::default();
+    let mut open: Option<FencedBlock> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let fence = fences.line(line);
+        match open.as_mut() {
+            Some(block) => {
+                block.content.push_str(line);
+                block.content.push('\n');
+                block.end = i as u32;
+                if fence == FenceLine::Close {
+                    blocks.extend(open.take());
+                }
+            }
+            None => {
+                let kind = ["trace", "include", "quote"]
+                    .into_iter()
+                    .find(|k| fence == FenceLine::Open(k));
+                if let Some(kind) = kind {
+                    open = Some(FencedBlock {
+                        kind,
+                        start: i as u32,
+                        end: i as u32,
+                        content: format!("{}\n", line),
+                    });
+                }
+            }
+        }
+    }
+    blocks.extend(open);
+    blocks
+}
+
+/// Lines spanned by the frontmatter, including the `---` delimiters.
+fn frontmatter_lines(text: &str) -> Option<(u32, u32)> {
+    let mut lines = text.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+    lines
+        .position(|l| l.trim_end() == "---")
+        .map(|i| (0, i as u32 + 1))
+}
+
+fn line_range(text: &str, start: u32, end: u32) -> Range {
+    let end_len = text
+        .lines()
+        .nth(end as usize)
+        .map(|l| l.encode_utf16().count() as u32)
+        .unwrap_or(0);
+    Range::new(Position::new(start, 0), Position::new(end, end_len))
+}
+
+fn diagnostic(range: Range, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some(SOURCE.to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Diagnose a document. Block syntax, extractors and include paths are
+/// always checked; traces are only run against their sources when
+/// `run_traces` is set, since that reads and hashes every source file.
+pub fn diagnostics(text: &str, base_dir: &Path, run_traces: bool) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+
+    let doc = parse_tmd(text);
+    // Anchor positions are only known when the document parses
+    let mut anchors = doc
+        .as_ref()
+        .map(|doc| trace_anchors(&parse_tmd_sections(&doc.body)))
+        .unwrap_or_default()
+        .into_iter();
+    let frontmatter = match doc {
+        Ok(doc) => Some(doc.frontmatter),
+        Err(e) => {
+            let (start, end) = frontmatter_lines(text).unwrap_or((0, 0));
+            out.push(diagnostic(
+                line_range(text, start, end),
+                DiagnosticSeverity::ERROR,
+                e.to_string(),
+            ));
+            None
+        }
+    };
+    // Failing traces only block Published documents, as in `rhodi verify`
+    let failure_severity = match frontmatter.as_ref().map(|fm| &fm.doc_status) {
+        Some(DocStatus::Published) => DiagnosticSeverity::ERROR,
+        _ => DiagnosticSeverity::WARNING,
+    };
+    let policy = frontmatter
+        .as_ref()
+        .map(|fm| fm.policy.clone())
+        .unwrap_or_default();
+
+    let workspace = Workspace::discover(base_dir).ok();
+    let resolver = workspace
+        .as_ref()
+        .and_then(|ws| ws.resolver(base_dir).ok())
+        .or_else(|| FileResolver::new(base_dir).map(ResolverRegistry::new).ok());
+    let exec = workspace.as_ref().and_then(Workspace::exec_extractor);
+    let presets = workspace
+        .as_ref()
+        .map(|ws| ws.config.presets.clone())
+        .unwrap_or_default();
+    let limits = workspace
+        .as_ref()
+        .map(|ws| ws.config.limits.compiler_limits())
+        .unwrap_or_default();
+    let compiler = resolver.as_ref().map(|r| {
+        Compiler::new(r)
+            .with_exec_extractor(exec.clone())
+            .with_presets(presets.clone())
+            .with_limits(limits)
+    });
+
+    for block in fenced_blocks(text) {
+        let range = block.range(text);
+        match block.kind {
+            "trace" => {
+                let parsed = parse_trace_block(&block.content);
+                let position = parsed.as_ref().ok().and_then(|_| anchors.next());
+                let parsed = parsed
+                    .and_then(|trace| trace.expand_preset(&presets))
+                    .and_then(|trace| match frontmatter {
+                        Some(ref fm) => fm.resolve_trace(&trace),
+                        None => Ok(trace),
+                    });
+                let trace = match parsed {
+                    Ok(trace) => trace,
+                    Err(e) => {
+                        out.push(diagnostic(range, DiagnosticSeverity::ERROR, e.to_string()));
+                        continue;
+                    }
+                };
+                if let Some(ref extractor) = trace.extractor
+                    && !(exec.is_some() && extractor.eq_ignore_ascii_case("exec"))
+                    && !extractor.eq_ignore_ascii_case("auto")
+                    && let Err(e) = crate::extraction::get_extractor(extractor)
+                {
+                    let line = block.key_line("extractor").unwrap_or(block.start);
+                    out.push(diagnostic(
+                        line_range(text, line, line),
+                        DiagnosticSeverity::ERROR,
+                        e.to_string(),
+                    ));
+                    continue;
+                }
+                if let Err(e) = trace.typed_selector() {
+                    let line = block.key_line("selector").unwrap_or(block.start);
+                    out.push(diagnostic(
+                        line_range(text, line, line),
+                        DiagnosticSeverity::ERROR,
+                        e.to_string(),
+                    ));
+                    continue;
+                }
+                if let (Some(anchor), Some(position)) = (&trace.anchor, &position)
+                    && let Err(e) = position.check(anchor)
+                {
+                    let line = block.key_line("anchor").unwrap_or(block.start);
+                    out.push(diagnostic(
+                        line_range(text, line, line),
+                        failure_severity,
+                        e.to_string(),
+                    ));
+                }
+                if let Err(e) = trace.check_freshness(&policy, chrono::Utc::now()) {
+                    out.push(diagnostic(range, failure_severity, e.to_string()));
+                }
+                if run_traces && let Some(ref compiler) = compiler {
+                    let result = compiler.check_trace(&trace);
+                    if let Some(error) = result.error {
+                        out.push(diagnostic(range, failure_severity, error));
+                    }
+                }
+            }
+            "include" => match parse_include_block(&block.content) {
+                Ok(include) => {
+                    // Includes by id are only resolved by `rhodi verify`
+                    if let Some(ref resolver) = resolver
+                        && include.id.is_none()
+                        && let Err(e) = resolver.resolve_document(&include.path)
+                    {
+                        let line = block.key_line("path").unwrap_or(block.start);
+                        out.push(diagnostic(
+                            line_range(text, line, line),
+                            DiagnosticSeverity::ERROR,
+                            e.to_string(),
+                        ));
+                    }
+                }
+                Err(e) => out.push(diagnostic(range, DiagnosticSeverity::ERROR, e.to_string())),
+            },
+            _ => {
+                if let Err(e) = parse_quote_block(&block.content) {
+                    out.push(diagnostic(range, DiagnosticSeverity::ERROR, e.to_string()));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Markdown shown when hovering `line`: details of the recorded version
+/// hash, or of the previous version's hash.
+pub fn hover(text: &str, line: u32) -> Option<String> {
+    let (start, end) = frontmatter_lines(text)?;
+    if line <= start || line >= end {
+        return None;
+    }
+    let key = text.lines().nth(line as usize)?.split(':').next()?.trim();
+    let doc = parse_tmd(text).ok()?;
+    let fm = &doc.frontmatter;
+
+    match key {
+        "version_hash" => {
+            let recorded = fm.version_hash?;
+            let current = doc.current_version_hash().ok()?;
+            let mut message = format!(
+                "**Version hash** of v{} (protocol {})\n\nRecorded: `{}`\n\nCurrent:  `{}`\n\n",
+                fm.doc_version,
+                fm.protocol_version,
+                hex::encode(recorded),
+                hex::encode(current)
+            );
+            if recorded == current {
+                message.push_str("✓ Content matches the seal");
+            } else {
+                message.push_str("✗ Content changed since sealing");
+                if let Some(changed) = doc.diagnose_tamper().filter(|c| !c.is_empty()) {
+                    message.push_str(&format!(": {}", changed.join(", ")));
+                }
+            }
+            if fm.signature.is_none() {
+                message.push_str("\n\nNot signed");
+            }
+            Some(message)
+        }
+        "prev_version_hash" => {
+            let prev = fm.prev_version_hash?;
+            Some(format!(
+                "**Previous version hash** (v{})\n\n`{}`",
+                fm.doc_version.saturating_sub(1),
+                hex::encode(prev)
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// The file an include `path:`, a local trace `source:` or a quote
+/// `source_doc:` on `line` points to.
+pub fn definition(text: &str, line: u32, base_dir: &Path) -> Option<PathBuf> {
+    let block = fenced_blocks(text)
+        .into_iter()
+        .find(|b| b.start < line && line < b.end)?;
+    let key = match block.kind {
+        "include" => "path",
+        "trace" => "source",
+        "quote" => "source_doc",
+        _ => return None,
+    };
+    if block.key_line(key)? != line {
+        return None;
+    }
+    let target = match block.kind {
+        "include" => parse_include_block(&block.content).ok()?.path,
+        "trace" => {
+            let trace = parse_trace_block(&block.content).ok()?;
+            match parse_tmd(text) {
+                Ok(doc) => doc.frontmatter.resolve_trace(&trace).ok()?.source,
+                Err(_) => trace.source,
+            }
+        }
+        _ => parse_quote_block(&block.content).ok()?.source_doc,
+    };
+    if target.contains("://") {
+        return None;
+    }
+    let path = base_dir.join(target);
+    path.is_file().then_some(path)
+}
+
+/// Serve the language server protocol over stdin/stdout until the client exits.
+pub fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    let capabilities =
+        serde_json::to_value(capabilities).map_err(|e| RhodiError::Serialization(e.to_string()))?;
+    connection
+        .initialize(capabilities)
+        .map_err(|e| RhodiError::Io(std::io::Error::other(e.to_string())))?;
+
+    serve(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Answer messages on an initialized `connection` until the client shuts
+/// the server down.
+pub(crate) fn serve(connection: Connection) -> Result<()> {
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.main_loop()
+}
+
+struct Server {
+    connection: Connection,
+    /// Open documents by URI, with full-text sync
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    fn main_loop(&mut self) -> Result<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    let shutdown = self
+                        .connection
+                        .handle_shutdown(&request)
+                        .map_err(|e| RhodiError::Io(std::io::Error::other(e.to_string())))?;
+                    if shutdown {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer `request`; a request the server cannot read gets an error
+    /// response rather than ending the session.
+    fn handle_request(&mut self, request: Request) -> Result<()> {
+        let response = match self.answer(&request.method, request.params) {
+            Ok(result) => Response::new_ok(request.id, result.unwrap_or(serde_json::Value::Null)),
+            Err(RhodiError::Format(message)) => {
+                Response::new_err(request.id, ErrorCode::InvalidParams as i32, message)
+            }
+            Err(e) => Response::new_err(request.id, ErrorCode::InternalError as i32, e.to_string()),
+        };
+        self.send(Message::Response(response))
+    }
+
+    fn answer(&self, method: &str, params: serde_json::Value) -> Result<Option<serde_json::Value>> {
+        Ok(match method {
+            HoverRequest::METHOD => {
+                let params: lsp_types::HoverParams = parse_params(params)?;
+                let position = params.text_document_position_params;
+                self.documents
+                    .get(&position.text_document.uri)
+                    .and_then(|text| hover(text, position.position.line))
+                    .map(|value| Hover {
+                        contents: HoverContents::Markup(MarkupContent {
+                            kind: MarkupKind::Markdown,
+                            value,
+                        }),
+                        range: None,
+                    })
+                    .map(to_json)
+                    .transpose()?
+            }
+            GotoDefinition::METHOD => {
+                let params: lsp_types::GotoDefinitionParams = parse_params(params)?;
+                let position = params.text_document_position_params;
+                let uri = &position.text_document.uri;
+                self.documents
+                    .get(uri)
+                    .and_then(|text| definition(text, position.position.line, &base_dir(uri)))
+                    .and_then(|path| Url::from_file_path(path).ok())
+                    .map(|uri| GotoDefinitionResponse::Scalar(Location::new(uri, Range::default())))
+                    .map(to_json)
+                    .transpose()?
+            }
+            _ => None,
+        })
+    }
+
+    /// Act on `notification`. One the server cannot read is reported on
+    /// stderr and ignored, as notifications get no response.
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        let method = notification.method.clone();
+        match self.notify(notification) {
+            Err(RhodiError::Format(message)) => {
+                eprintln!(
+                    "rhodi lsp: ignoring invalid {} notification: {}",
+                    method, message
+                );
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    fn notify(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    parse_params(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                self.publish(uri, true)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    parse_params(notification.params)?;
+                let uri = params.text_document.uri;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                self.publish(uri, false)
+            }
+            DidSaveTextDocument::METHOD => {
+                let params: lsp_types::DidSaveTextDocumentParams =
+                    parse_params(notification.params)?;
+                self.publish(params.text_document.uri, true)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    parse_params(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn publish(&self, uri: Url, run_traces: bool) -> Result<()> {
+        let Some(text) = self.documents.get(&uri) else {
+            return Ok(());
+        };
+        let diagnostics = diagnostics(text, &base_dir(&uri), run_traces);
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))
+    }
+
+    fn send(&self, message: Message) -> Result<()> {
+        self.connection
+            .sender
+            .send(message)
+            .map_err(|e| RhodiError::Io(std::io::Error::other(e.to_string())))
+    }
+}
+
+/// Directory that relative sources in the document resolve against.
+fn base_dir(uri: &Url) -> PathBuf {
+    uri.to_file_path()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: serde_json::Value) -> Result<T> {
+    serde_json::from_value(params).map_err(|e| RhodiError::Format(e.to_string()))
+}
+
+fn to_json<T: serde::Serialize>(value: T) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| RhodiError::Serialization(e.to_string()))
+}
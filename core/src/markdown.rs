@@ -1,9 +1,26 @@
 use crate::error::{Result, RhodiError};
-use crate::models::{FrontMatter, TraceBlock, TracedDocument};
+use crate::crypto::KeyPair;
+use crate::models::{
+    FrontMatter, IncludeBlock, QuoteBlock, SealMode, TraceBlock, TraceResult, TracedDocument,
+};
+use crate::version::{
+    CanonicalizationProfile, DEFAULT_PROTOCOL_VERSION, canonicalization_profile,
+    excludes_trace_annotations,
+};
+use sha2::{Digest, Sha256};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use serde_norway;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
 
 // ... (rest of the file stays similar but using Result)
 
+// Sections are short-lived parse results, so boxing the trace variant isn't worth it
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Section {
     /// This is a regular markdown paragraph
@@ -12,23 +29,48 @@ pub enum Section {
     Trace(TraceBlock),
     /// This is an include block for modular composition
     Include(String),
+    /// This is a quote block citing an excerpt of another sealed document
+    Quote(QuoteBlock),
+    /// A fenced block owned by a registered [`crate::blocks::BlockHandler`]
+    Custom { kind: String, content: String },
 }
 
 /// A function to parse the markdown body, separating paragraphs, traces, and includes.
 pub fn parse_tmd_sections(body: &str) -> Vec<Section> {
+    parse_tmd_sections_with(body, &[])
+}
+
+/// Like [`parse_tmd_sections`], but also recognizes fenced blocks whose info
+/// string is one of `custom_kinds`, returning them as [`Section::Custom`].
+///
+/// Fences follow CommonMark: a block opened by three or more backticks or
+/// tildes is closed only by a run of the same character at least as long.
+/// Other fenced blocks stay part of the surrounding paragraph, so a
+/// ```` ```trace ```` example inside a ```` ````markdown ```` block is not a
+/// trace.
+pub fn parse_tmd_sections_with(body: &str, custom_kinds: &[&str]) -> Vec<Section> {
     let mut sections = Vec::new();
-    let mut in_block = false;
-    let mut block_type = ""; // "trace", "include", or "tmd"
+    let mut fences = Fences::default();
+    let mut block_type: Option<&str> = None; // "trace", "include", "quote", or a custom kind
     let mut current = String::new();
 
     for line in body.lines() {
-        let s = line.trim_start();
-
-        if in_block {
-            current.push_str(line);
-            current.push('\n');
-            if s.starts_with("```") {
-                match block_type {
+        match fences.line(line) {
+            FenceLine::Open(info) => {
+                if let Some(kind) = block_kind(info, custom_kinds) {
+                    if !current.trim().is_empty() {
+                        sections.push(Section::Paragraph(current.clone()));
+                    }
+                    current.clear();
+                    block_type = Some(kind);
+                }
+                current.push_str(line);
+                current.push('\n');
+            }
+            FenceLine::Close if block_type.is_some() => {
+                current.push_str(line);
+                current.push('\n');
+                match block_type.take().unwrap_or_default() {
                     "trace" => {
                         if let Ok(trace) = parse_trace_block(&current) {
                             sections.push(Section::Trace(trace));
@@ -40,35 +82,26 @@ pub fn parse_tmd_sections(body: &str) -> Vec<Section> {
                     "include" => {
                         sections.push(Section::Include(current.clone()));
                     }
-                    _ => {
-                        sections.push(Section::Paragraph(current.clone()));
+                    "quote" => {
+                        if let Ok(quote) = parse_quote_block(&current) {
+                            sections.push(Section::Quote(quote));
+                        } else {
+                            sections.push(Section::Paragraph(current.clone()));
+                        }
+                    }
+                    kind => {
+                        sections.push(Section::Custom {
+                            kind: kind.to_string(),
+                            content: current.clone(),
+                        });
                     }
                 }
                 current.clear();
-                in_block = false;
-                block_type = "";
-            }
-        } else if s.starts_with("```trace") {
-            if !current.trim().is_empty() {
-                sections.push(Section::Paragraph(current.clone()));
-            }
-            current.clear();
-            current.push_str(line);
-            current.push('\n');
-            in_block = true;
-            block_type = "trace";
-        } else if s.starts_with("```include") {
-            if !current.trim().is_empty() {
-                sections.push(Section::Paragraph(current.clone()));
-            }
-            current.clear();
-            current.push_str(line);
-            current.push('\n');
-            in_block = true;
-            block_type = "include";
-        } else {
-            current.push_str(line);
-            current.push('\n');
+            }
+            _ => {
+                current.push_str(line);
+                current.push('\n');
+            }
         }
     }
 
@@ -79,6 +112,99 @@ pub fn parse_tmd_sections(body: &str) -> Vec<Section> {
     sections
 }
 
+/// The block kind named by a fence's info string, if rhodi (or a registered
+/// handler) owns it.
+fn block_kind<'k>(info: &str, custom_kinds: &[&'k str]) -> Option<&'k str> {
+    ["trace", "include", "quote"]
+        .into_iter()
+        .chain(custom_kinds.iter().copied())
+        .find(|kind| *kind == info)
+}
+
+/// The opening fence of a fenced code block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Fence {
+    ch: u8,
+    len: usize,
+}
+
+impl Fence {
+    /// The fence opened by `line`, with the first word of its info string.
+    fn open(line: &str) -> Option<(Fence, &str)> {
+        let s = line.trim_start();
+        let ch = *s.as_bytes().first()?;
+        if ch != b'`' && ch != b'~' {
+            return None;
+        }
+        let len = s.bytes().take_while(|b| *b == ch).count();
+        let info = &s[len..];
+        // Backtick fences can't have backticks in the info string: that's inline code
+        if len < 3 || (ch == b'`' && info.contains('`')) {
+            return None;
+        }
+        Some((
+            Fence { ch, len },
+            info.split_whitespace().next().unwrap_or(""),
+        ))
+    }
+
+    /// Whether `line` closes this fence: the same character, at least as
+    /// many times, and nothing else.
+    fn closes(&self, line: &str) -> bool {
+        let s = line.trim();
+        s.len() >= self.len && s.bytes().all(|b| b == self.ch)
+    }
+}
+
+/// What a line is with respect to fenced code blocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FenceLine<'a> {
+    /// Outside any fenced block
+    Text,
+    /// Opens a block; carries the first word of the info string
+    Open(&'a str),
+    /// Inside a block
+    Content,
+    /// Closes the open block
+    Close,
+}
+
+/// Follows fenced code blocks line by line, so that fence-like lines inside
+/// a block are seen as content.
+#[derive(Debug, Default)]
+pub(crate) struct Fences {
+    open: Option<Fence>,
+}
+
+impl Fences {
+    pub(crate) fn line<'a>(&mut self, line: &'a str) -> FenceLine<'a> {
+        match self.open {
+            Some(fence) if fence.closes(line) => {
+                self.open = None;
+                FenceLine::Close
+            }
+            Some(_) => FenceLine::Content,
+            None => match Fence::open(line) {
+                Some((fence, info)) => {
+                    self.open = Some(fence);
+                    FenceLine::Open(info)
+                }
+                None => FenceLine::Text,
+            },
+        }
+    }
+}
+
+/// Canonicalize a string as `protocol_version` prescribes: with
+/// [`canonicalize_text`], followed by Unicode NFC from 2.1 on.
+pub fn canonicalize_text_for(text: &str, protocol_version: &str) -> String {
+    let canonical = canonicalize_text(text);
+    match canonicalization_profile(protocol_version) {
+        CanonicalizationProfile::Basic => canonical,
+        CanonicalizationProfile::Nfc => canonical.nfc().collect(),
+    }
+}
+
 /// Canonicalize a string by:
 /// 1. Normalizing line endings to LF
 /// 2. Stripping trailing whitespace
@@ -133,49 +259,371 @@ pub fn canonicalize_text(text: &str) -> String {
     result
 }
 
+/// Top-level keys that `rhodi verify --annotate` writes into trace blocks.
+const TRACE_ANNOTATION_KEYS: &[&str] = &["verified_at", "last_result"];
+
+fn is_key_line(line: &str, keys: &[&str]) -> bool {
+    keys.iter().any(|key| {
+        line.strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with(':'))
+    })
+}
+
+/// Remove verification annotations from every trace block, so that
+/// annotating a document never changes its version hash (from protocol
+/// 2.3; see [`excludes_trace_annotations`]).
+pub fn strip_trace_annotations(body: &str) -> Cow<'_, str> {
+    strip_trace_keys(body, TRACE_ANNOTATION_KEYS)
+}
+
+/// Remove the top-level `keys`, with their nested lines, from every trace block.
+fn strip_trace_keys<'b>(body: &'b str, keys: &[&str]) -> Cow<'b, str> {
+    if !body.lines().any(|line| is_key_line(line, keys)) {
+        return Cow::Borrowed(body);
+    }
+
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    let mut in_trace = false;
+    let mut skipping = false;
+
+    for line in body.split_inclusive('\n') {
+        match fences.line(line) {
+            FenceLine::Open(info) => in_trace = info == "trace",
+            FenceLine::Close => {
+                in_trace = false;
+                skipping = false;
+            }
+            FenceLine::Content if in_trace => {
+                // Nested lines of a skipped key are indented
+                if skipping && line.starts_with([' ', '\t']) {
+                    continue;
+                }
+                skipping = is_key_line(line, keys);
+                if skipping {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push_str(line);
+    }
+
+    Cow::Owned(out)
+}
+
+#[derive(Serialize)]
+struct TraceAnnotation<'a> {
+    verified_at: DateTime<Utc>,
+    last_result: &'a TraceResult,
+}
+
+/// Rewrite every trace block with the result of `check`, replacing any
+/// earlier annotation. Everything else in the body is left byte-for-byte.
+pub fn annotate_trace_blocks<F>(
+    body: &str,
+    verified_at: DateTime<Utc>,
+    mut check: F,
+) -> Result<String>
+where
+    F: FnMut(&TraceBlock) -> TraceResult,
+{
+    update_trace_blocks(body, |trace| {
+        let result = check(trace);
+        let annotation = serde_norway::to_value(TraceAnnotation {
+            verified_at,
+            last_result: &result,
+        })
+        .map_err(|e| RhodiError::Serialization(format!("Failed to serialize annotation: {}", e)))?;
+        Ok(Some(annotation))
+    })
+}
+
+/// `body` with `after(n)` inserted, in a paragraph of its own, after the
+/// closing fence of the `n`th trace block.
+pub(crate) fn after_trace_blocks(body: &str, mut after: impl FnMut(usize) -> String) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    let mut in_trace = false;
+    let mut count = 0;
+    for line in body.split_inclusive('\n') {
+        out.push_str(line);
+        match fences.line(line) {
+            FenceLine::Open(info) => in_trace = info == "trace",
+            FenceLine::Close if in_trace => {
+                in_trace = false;
+                if !line.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push('\n');
+                out.push_str(&after(count));
+                out.push_str("\n\n");
+                count += 1;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Set fields on trace blocks. `update` returns a YAML mapping of the
+/// top-level keys to write, or `None` to leave the block alone; existing
+/// values of those keys are replaced, and keys mapped to null removed.
+/// Other lines keep their formatting.
+pub fn update_trace_blocks<F>(body: &str, mut update: F) -> Result<String>
+where
+    F: FnMut(&TraceBlock) -> Result<Option<serde_norway::Value>>,
+{
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    let mut block: Option<String> = None;
+
+    for line in body.split_inclusive('\n') {
+        let fence = fences.line(line);
+        match block.as_mut() {
+            None => {
+                if fence == FenceLine::Open("trace") {
+                    block = Some(line.to_string());
+                } else {
+                    out.push_str(line);
+                }
+            }
+            Some(current) if fence != FenceLine::Close => current.push_str(line),
+            Some(current) => {
+                let fields = match parse_trace_block(&format!("{}{}", current, line)) {
+                    Ok(trace) => update(&trace)?,
+                    Err(_) => None,
+                };
+                let Some(fields) = fields else {
+                    out.push_str(current);
+                    out.push_str(line);
+                    block = None;
+                    continue;
+                };
+
+                let keys: Vec<&str> = fields
+                    .as_mapping()
+                    .map(|m| m.keys().filter_map(|k| k.as_str()).collect())
+                    .unwrap_or_default();
+                let mut written = fields.as_mapping().cloned().unwrap_or_default();
+                written.retain(|_, value| !value.is_null());
+                let yaml = match written.is_empty() {
+                    true => String::new(),
+                    false => serde_norway::to_string(&written).map_err(|e| {
+                        RhodiError::Serialization(format!(
+                            "Failed to serialize trace fields: {}",
+                            e
+                        ))
+                    })?,
+                };
+
+                let stripped = strip_trace_keys(current, &keys);
+                out.push_str(&stripped);
+                if !stripped.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str(&yaml);
+                out.push_str(line);
+                block = None;
+            }
+        }
+    }
+
+    // An unterminated block is not a trace block; keep it as written
+    if let Some(current) = block {
+        out.push_str(&current);
+    }
+
+    Ok(out)
+}
+
+/// Point quote blocks at other versions of the documents they quote.
+/// `repin` returns the hex version hash a quote should pin, or `None` to
+/// leave it alone; only the `version_hash` line of the block changes.
+pub fn repin_quote_blocks<F>(body: &str, mut repin: F) -> String
+where
+    F: FnMut(&QuoteBlock) -> Option<String>,
+{
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    let mut block: Option<String> = None;
+
+    for line in body.split_inclusive('\n') {
+        let fence = fences.line(line);
+        match block.as_mut() {
+            None if fence == FenceLine::Open("quote") => block = Some(line.to_string()),
+            None => out.push_str(line),
+            Some(current) if fence != FenceLine::Close => current.push_str(line),
+            Some(current) => {
+                let hash = parse_quote_block(&format!("{}{}", current, line))
+                    .ok()
+                    .and_then(|quote| repin(&quote));
+                for block_line in current.split_inclusive('\n') {
+                    match &hash {
+                        Some(hash) if is_key_line(block_line, &["version_hash"]) => {
+                            out.push_str(&format!("version_hash: {}\n", hash));
+                        }
+                        _ => out.push_str(block_line),
+                    }
+                }
+                out.push_str(line);
+                block = None;
+            }
+        }
+    }
+
+    // An unterminated block is not a quote block; keep it as written
+    if let Some(current) = block {
+        out.push_str(&current);
+    }
+    out
+}
+
+/// Point include blocks that include the document `from` by id at the
+/// document `to`; only the `id` line of each such block changes.
+pub fn retarget_include_blocks(body: &str, from: uuid::Uuid, to: uuid::Uuid) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    let mut block: Option<String> = None;
+
+    for line in body.split_inclusive('\n') {
+        let fence = fences.line(line);
+        match block.as_mut() {
+            None if fence == FenceLine::Open("include") => block = Some(line.to_string()),
+            None => out.push_str(line),
+            Some(current) if fence != FenceLine::Close => current.push_str(line),
+            Some(current) => {
+                let retarget = parse_include_block(&format!("{}{}", current, line))
+                    .is_ok_and(|include| include.id == Some(from));
+                for block_line in current.split_inclusive('\n') {
+                    if retarget && is_key_line(block_line, &["id"]) {
+                        out.push_str(&format!("id: {}\n", to));
+                    } else {
+                        out.push_str(block_line);
+                    }
+                }
+                out.push_str(line);
+                block = None;
+            }
+        }
+    }
+
+    // An unterminated block is not an include block; keep it as written
+    if let Some(current) = block {
+        out.push_str(&current);
+    }
+    out
+}
+
 /// Parse a **trace** block and extract the metadata.
 /// The block should include the triple backticks and the "trace" identifier.
 pub fn parse_trace_block(block: &str) -> Result<TraceBlock> {
+    use serde_norway::Value;
+
+    let mut fields: Value = parse_fenced_yaml(block, "trace")?;
+    // A `source` list names equivalent locations: the source, then its mirrors
+    if let Some(map) = fields.as_mapping_mut()
+        && let Some(Value::Sequence(locations)) = map.get("source").cloned()
+    {
+        let mut locations = locations.into_iter();
+        let source = locations.next().ok_or_else(|| {
+            RhodiError::Format("Failed to parse trace metadata: source list is empty".into())
+        })?;
+        let mut mirrors: Vec<Value> = locations.collect();
+        if let Some(Value::Sequence(listed)) = map.get("mirrors") {
+            mirrors.extend(listed.iter().cloned());
+        }
+        map.insert("source".into(), source);
+        map.insert("mirrors".into(), Value::Sequence(mirrors));
+    }
+    serde_norway::from_value(fields)
+        .map_err(|e| RhodiError::Format(format!("Failed to parse trace metadata: {}", e)))
+}
+
+/// Parse an **include** block and extract the metadata.
+/// The block should include the triple backticks and the "include" identifier.
+pub fn parse_include_block(block: &str) -> Result<IncludeBlock> {
+    let include: IncludeBlock = parse_fenced_yaml(block, "include")?;
+    match (include.path.is_empty(), include.id) {
+        (true, None) => Err(RhodiError::Format(
+            "Invalid include block: needs a path or an id".to_string(),
+        )),
+        (false, Some(_)) => Err(RhodiError::Format(
+            "Invalid include block: give either a path or an id, not both".to_string(),
+        )),
+        _ => Ok(include),
+    }
+}
+
+/// Parse a **quote** block and extract the citation metadata.
+/// The block should include the triple backticks and the "quote" identifier.
+pub fn parse_quote_block(block: &str) -> Result<QuoteBlock> {
+    parse_fenced_yaml(block, "quote")
+}
+
+/// Deserialize the YAML content of a fenced block whose info string is `kind`.
+/// Custom block handlers can use this for YAML-bodied blocks.
+pub fn parse_fenced_yaml<T: DeserializeOwned>(block: &str, kind: &str) -> Result<T> {
     let lines: Vec<&str> = block.lines().collect();
     if lines.len() < 2 {
-        return Err(RhodiError::Format(
-            "Invalid trace block: too short".to_string(),
-        ));
+        return Err(RhodiError::Format(format!(
+            "Invalid {} block: too short",
+            kind
+        )));
     }
 
-    // Ensure it starts with ```trace and ends with ```
-    if !lines[0].trim().starts_with("```trace") {
-        return Err(RhodiError::Format(
-            "Invalid trace block: missing opening fence".to_string(),
-        ));
-    }
-    if !lines.last().unwrap().trim().starts_with("```") {
-        return Err(RhodiError::Format(
-            "Invalid trace block: missing closing fence".to_string(),
-        ));
+    // Ensure it starts with ```<kind> and ends with a matching closing fence
+    let Some((fence, _)) = Fence::open(lines[0]).filter(|(_, info)| *info == kind) else {
+        return Err(RhodiError::Format(format!(
+            "Invalid {} block: missing opening fence",
+            kind
+        )));
+    };
+    if !fence.closes(lines[lines.len() - 1]) {
+        return Err(RhodiError::Format(format!(
+            "Invalid {} block: missing closing fence",
+            kind
+        )));
     }
 
     // Extract the YAML content between the fences
     let yaml_content = lines[1..lines.len() - 1].join("\n");
 
-    let trace: TraceBlock = serde_norway::from_str(&yaml_content)
-        .map_err(|e| RhodiError::Format(format!("Failed to parse trace metadata: {}", e)))?;
+    serde_norway::from_str(&yaml_content)
+        .map_err(|e| RhodiError::Format(format!("Failed to parse {} metadata: {}", kind, e)))
+}
 
-    Ok(trace)
+/// Split `content` at its first two `---` delimiter lines into the text
+/// before the frontmatter, the frontmatter and the body. A `---` inside a
+/// line is not a delimiter. The frontmatter and body keep the line breaks
+/// that end the delimiters.
+fn split_frontmatter(content: &str) -> Option<(&str, &str, &str)> {
+    let mut delimiters = content
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line))
+        })
+        .filter(|(_, line)| line.trim_end_matches(['\n', '\r']) == "---")
+        .map(|(start, _)| start);
+    let open = delimiters.next()?;
+    let close = delimiters.next()?;
+    Some((
+        &content[..open],
+        &content[open + 3..close],
+        &content[close + 3..],
+    ))
 }
 
 /// Parse a TMD (Traced Markdown Document) content into a TracedDocument struct.
 pub fn parse_tmd(content: &str) -> Result<TracedDocument> {
-    let parts: Vec<&str> = content.splitn(3, "---").collect();
-
-    if parts.len() < 3 {
+    let Some((_, yaml_str, body)) = split_frontmatter(content) else {
         return Err(RhodiError::Format(
             "Invalid TMD format: Missing frontmatter delimiters".to_string(),
         ));
-    }
-
-    let yaml_str = parts[1];
-    let body = parts[2].trim();
+    };
+    let body = body.trim();
 
     let frontmatter: FrontMatter = serde_norway::from_str(yaml_str)
         .map_err(|e| RhodiError::Format(format!("Failed to parse frontmatter: {}", e)))?;
@@ -183,5 +631,443 @@ pub fn parse_tmd(content: &str) -> Result<TracedDocument> {
     Ok(TracedDocument {
         frontmatter,
         body: body.to_string(),
+        raw: Some(content.into()),
     })
 }
+
+/// The `id` in the frontmatter of `content`, read without parsing the
+/// rest of the document.
+pub fn frontmatter_id(content: &str) -> Result<uuid::Uuid> {
+    #[derive(serde::Deserialize)]
+    struct Id {
+        id: uuid::Uuid,
+    }
+    let Some((_, yaml_str, _)) = split_frontmatter(content) else {
+        return Err(RhodiError::Format(
+            "Invalid TMD format: Missing frontmatter delimiters".to_string(),
+        ));
+    };
+    let Id { id } = serde_norway::from_str(yaml_str)
+        .map_err(|e| RhodiError::Format(format!("Failed to parse frontmatter: {}", e)))?;
+    Ok(id)
+}
+
+/// The text of the first level-one heading, used as a converted document's title.
+pub fn markdown_title(markdown: &str) -> Option<String> {
+    let mut fences = Fences::default();
+    for line in markdown.lines() {
+        if fences.line(line) == FenceLine::Text
+            && let Some(title) = line.strip_prefix("# ")
+        {
+            let title = title.trim().trim_end_matches('#').trim();
+            if !title.is_empty() {
+                return Some(title.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Figures worth backing with evidence: percentages, currency amounts, and
+/// numbers with decimals or thousands separators. Bare integers (years,
+/// list numbers) are too noisy to suggest.
+pub(crate) fn figure_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"[$€£]\s?\d[\d,]*(?:\.\d+)?|\b\d+(?:[.,]\d+)*\s?%|\b\d{1,3}(?:,\d{3})+(?:\.\d+)?\b|\b\d+\.\d+\b",
+        )
+        .expect("figure pattern is valid")
+    })
+}
+
+/// Insert a trace block stub after each paragraph that states a figure, one
+/// stub per figure. Stubs have `source: TODO` and fail verification until
+/// the author points them at the evidence. Headings and fenced blocks are
+/// left alone.
+pub fn suggest_trace_stubs(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut pending: Vec<(String, String)> = Vec::new();
+    let mut fences = Fences::default();
+
+    let flush = |out: &mut String, pending: &mut Vec<(String, String)>| {
+        for (figure, line) in pending.drain(..) {
+            let excerpt: String = line.chars().take(80).collect();
+            out.push_str(&format!(
+                "\n```trace\n# TODO: cite the evidence for: {}\nsource: TODO\nexpected: {}\n```\n",
+                excerpt,
+                serde_json::Value::String(figure)
+            ));
+        }
+    };
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        match fences.line(line) {
+            FenceLine::Open(_) => flush(&mut out, &mut pending),
+            FenceLine::Text if trimmed.is_empty() => flush(&mut out, &mut pending),
+            FenceLine::Text if !trimmed.starts_with('#') => {
+                for figure in figure_pattern().find_iter(line) {
+                    pending.push((figure.as_str().to_string(), trimmed.to_string()));
+                }
+            }
+            _ => {}
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    flush(&mut out, &mut pending);
+    out
+}
+
+/// Where a trace block sits in the body: the headings enclosing it and the
+/// last sentence of prose before it (since the nearest heading).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TraceAnchor {
+    pub heading_path: Vec<String>,
+    pub sentence: Option<String>,
+}
+
+impl TraceAnchor {
+    /// `sha256:` digest of the preceding sentence, whitespace-normalized.
+    pub fn sentence_hash(&self) -> Option<String> {
+        self.sentence
+            .as_deref()
+            .map(|s| crate::crypto::hash_bytes(s.as_bytes()))
+    }
+
+    /// The anchor to record for a trace here: the sentence hash when there
+    /// is prose before it, else the heading path.
+    pub fn suggest(&self) -> Option<String> {
+        self.sentence_hash()
+            .or_else(|| (!self.heading_path.is_empty()).then(|| self.heading_path.join(" > ")))
+    }
+
+    /// Check a trace's `anchor` against this position. `sha256:` anchors
+    /// must match the sentence hash (a prefix of at least 8 hex digits is
+    /// enough); anything else is a heading path such as `Results > Revenue`,
+    /// which must match the innermost enclosing headings.
+    pub fn check(&self, anchor: &str) -> Result<()> {
+        let resolves = match anchor.strip_prefix("sha256:") {
+            Some(prefix) => {
+                prefix.len() >= 8
+                    && self
+                        .sentence_hash()
+                        .is_some_and(|h| h["sha256:".len()..].starts_with(prefix))
+            }
+            None => {
+                let wanted: Vec<&str> = anchor
+                    .split('>')
+                    .map(|part| part.trim().trim_start_matches('#').trim())
+                    .collect();
+                wanted.len() <= self.heading_path.len()
+                    && self.heading_path[self.heading_path.len() - wanted.len()..]
+                        .iter()
+                        .zip(&wanted)
+                        .all(|(have, want)| have == want)
+            }
+        };
+        if resolves {
+            return Ok(());
+        }
+        let location = if self.heading_path.is_empty() {
+            "outside any heading".to_string()
+        } else {
+            format!("under '{}'", self.heading_path.join(" > "))
+        };
+        Err(RhodiError::Verification(format!(
+            "Trace anchor '{}' no longer resolves; the trace now sits {}",
+            anchor, location
+        )))
+    }
+}
+
+/// The anchor position of every trace in `sections`, in order.
+pub fn trace_anchors(sections: &[Section]) -> Vec<TraceAnchor> {
+    let mut anchors = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut prose = String::new();
+
+    for section in sections {
+        match section {
+            Section::Paragraph(text) => {
+                let mut fences = Fences::default();
+                for line in text.lines() {
+                    if fences.line(line) != FenceLine::Text {
+                        continue;
+                    }
+                    let trimmed = line.trim_start();
+                    if let Some((level, title)) = heading(trimmed) {
+                        headings.retain(|(l, _)| *l < level);
+                        headings.push((level, title));
+                        prose.clear();
+                    } else {
+                        prose.push_str(trimmed);
+                        prose.push(' ');
+                    }
+                }
+            }
+            Section::Trace(_) => anchors.push(TraceAnchor {
+                heading_path: headings.iter().map(|(_, t)| t.clone()).collect(),
+                sentence: last_sentence(&prose),
+            }),
+            _ => {}
+        }
+    }
+    anchors
+}
+
+/// An ATX heading's level and text.
+fn heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim().to_string()))
+}
+
+fn last_sentence(prose: &str) -> Option<String> {
+    let text = prose.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    // Ignore the closing punctuation of the last sentence itself
+    let last = text.char_indices().last().map_or(0, |(i, _)| i);
+    let search = &text[..last];
+    let start = [". ", "! ", "? "]
+        .iter()
+        .filter_map(|end| search.rfind(end).map(|i| i + end.len()))
+        .max()
+        .unwrap_or(0);
+    Some(text[start..].to_string())
+}
+
+/// Serialize a TracedDocument back into TMD content (frontmatter + body).
+pub fn render_tmd(doc: &TracedDocument) -> Result<String> {
+    let fm_yaml = serde_norway::to_string(&doc.frontmatter).map_err(|e| {
+        RhodiError::Serialization(format!("Failed to serialize frontmatter: {}", e))
+    })?;
+    Ok(format!("---\n{}\n---\n\n{}", fm_yaml.trim(), doc.body))
+}
+
+/// [`render_tmd`] for a document that was read from `original`: every
+/// top-level frontmatter field whose value did not change keeps its original
+/// text, comments, quoting and position. Changed fields are rewritten in
+/// place, removed ones dropped and new ones appended, so rewriting a document
+/// only touches the lines that actually changed. Values are compared after
+/// parsing, so a field left at its default is not spelled out. Falls back to
+/// [`render_tmd`] when `original` is not a valid TMD document.
+pub fn render_tmd_preserving(original: &str, doc: &TracedDocument) -> Result<String> {
+    let Ok(previous) = parse_tmd(original) else {
+        return render_tmd(doc);
+    };
+    let previous = frontmatter_mapping(&previous.frontmatter)?;
+    let mut fields = frontmatter_mapping(&doc.frontmatter)?;
+    let yaml = split_frontmatter(original).map_or("", |(_, yaml, _)| yaml);
+
+    let mut out = String::new();
+    for segment in frontmatter_segments(yaml) {
+        match segment {
+            FrontmatterSegment::Trivia(text) => out.push_str(&text),
+            FrontmatterSegment::Field { key, text } => {
+                let key = serde_norway::Value::String(key);
+                // Fields the document no longer serializes are dropped
+                let Some(value) = fields.shift_remove(&key) else {
+                    continue;
+                };
+                if previous.get(&key) == Some(&value) {
+                    out.push_str(&text);
+                } else {
+                    out.push_str(&render_field(&key, value)?);
+                }
+            }
+        }
+    }
+    for (key, value) in fields {
+        if previous.get(&key) != Some(&value) {
+            out.push_str(&render_field(&key, value)?);
+        }
+    }
+
+    Ok(format!(
+        "---\n{}\n---\n\n{}",
+        out.trim_matches('\n'),
+        doc.body
+    ))
+}
+
+/// Frontmatter fields a file-hash seal leaves out: those written while or
+/// after the hash is computed, and those attached to a sealed version later.
+pub const FILE_HASH_MASKED_FIELDS: &[&str] = &[
+    "version_hash",
+    "signature",
+    "hash_manifest",
+    "environment",
+    "notarizations",
+    "key_certificate",
+    "superseded_by",
+];
+
+/// Domain separator so a file hash can never equal a semantic version hash.
+const FILE_HASH_CONTEXT: &str = "rhodi-file-hash-v1\n";
+
+/// Version hash of a file-hash seal: SHA-256 of the `.tmd` text exactly as
+/// written, except that a byte order mark is dropped, line endings are
+/// normalized to `\n`, trailing whitespace is trimmed, the
+/// [`FILE_HASH_MASKED_FIELDS`] are removed from the frontmatter with their
+/// nested lines, and review blocks (from protocol 2.2) and trace result
+/// annotations (from 2.3) are stripped from the body. The frontmatter is only split
+/// into top-level fields, never parsed; only `protocol_version` is read.
+pub fn file_hash(content: &str) -> Result<[u8; 32]> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let content = content.replace("\r\n", "\n");
+    let Some((preamble, yaml, body)) = split_frontmatter(&content) else {
+        return Err(RhodiError::Format(
+            "Invalid TMD format: Missing frontmatter delimiters".to_string(),
+        ));
+    };
+
+    let mut canonical = format!("{}{}---", FILE_HASH_CONTEXT, preamble);
+    let mut protocol_version = DEFAULT_PROTOCOL_VERSION.to_string();
+    for segment in frontmatter_segments(yaml) {
+        match segment {
+            FrontmatterSegment::Field { key, .. }
+                if FILE_HASH_MASKED_FIELDS.contains(&key.as_str()) => {}
+            FrontmatterSegment::Field { key, text } => {
+                if key == "protocol_version" {
+                    protocol_version = field_string(&text)?;
+                }
+                canonical.push_str(&text)
+            }
+            FrontmatterSegment::Trivia(text) => canonical.push_str(&text),
+        }
+    }
+    canonical.push_str("---");
+    let body = crate::comments::hashed_body(body, &protocol_version);
+    if excludes_trace_annotations(&protocol_version) {
+        canonical.push_str(strip_trace_annotations(&body).trim_end());
+    } else {
+        canonical.push_str(body.trim_end());
+    }
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest(canonical.as_bytes()));
+    Ok(hash)
+}
+
+/// Render `doc`, just sealed with `keypair`, from `original` as
+/// [`render_tmd_preserving`] does. Under a file-hash seal mode the version
+/// hash is replaced by the [`file_hash`] of the rendered text and signed
+/// again; the returned document carries that text as `raw`.
+pub fn render_sealed(
+    original: &str,
+    doc: TracedDocument,
+    keypair: &KeyPair,
+) -> Result<(TracedDocument, String)> {
+    let mut doc = doc;
+    let rendered = render_tmd_preserving(original, &doc)?;
+    if doc.frontmatter.seal_mode != Some(SealMode::FileHash) {
+        return Ok((doc, rendered));
+    }
+
+    let hash = file_hash(&rendered)?;
+    doc.frontmatter.version_hash = Some(hash);
+    doc.frontmatter.signature = Some(keypair.sign(&hash).to_vec());
+    let sealed = render_tmd_preserving(original, &doc)?;
+    // Only masked fields changed, so the hash still covers the final text
+    if file_hash(&sealed)? != hash {
+        return Err(RhodiError::Serialization(
+            "Rendering the sealed document changed what its file hash covers".to_string(),
+        ));
+    }
+    doc.raw = Some(sealed.as_str().into());
+    Ok((doc, sealed))
+}
+
+fn frontmatter_mapping(frontmatter: &FrontMatter) -> Result<serde_norway::Mapping> {
+    match serde_norway::to_value(frontmatter) {
+        Ok(serde_norway::Value::Mapping(mapping)) => Ok(mapping),
+        Ok(_) => Err(RhodiError::Serialization(
+            "Frontmatter did not serialize to a mapping".into(),
+        )),
+        Err(e) => Err(RhodiError::Serialization(format!(
+            "Failed to serialize frontmatter: {}",
+            e
+        ))),
+    }
+}
+
+fn render_field(key: &serde_norway::Value, value: serde_norway::Value) -> Result<String> {
+    let mut single = serde_norway::Mapping::new();
+    single.insert(key.clone(), value);
+    serde_norway::to_string(&single)
+        .map_err(|e| RhodiError::Serialization(format!("Failed to serialize frontmatter: {}", e)))
+}
+
+/// A run of frontmatter lines: one top-level field with everything nested
+/// under it, or comments and blank lines between fields.
+enum FrontmatterSegment {
+    Field { key: String, text: String },
+    Trivia(String),
+}
+
+/// The value of a top-level field holding a string, from the text
+/// [`frontmatter_segments`] split out for it.
+fn field_string(text: &str) -> Result<String> {
+    let field: std::collections::BTreeMap<String, String> = serde_norway::from_str(text)
+        .map_err(|e| RhodiError::Format(format!("Failed to parse frontmatter: {}", e)))?;
+    field
+        .into_values()
+        .next()
+        .ok_or_else(|| RhodiError::Format(format!("Frontmatter field has no value: {}", text)))
+}
+
+/// Split frontmatter YAML into top-level fields, keeping every line.
+fn frontmatter_segments(yaml: &str) -> Vec<FrontmatterSegment> {
+    let mut segments = Vec::new();
+    // Blank and comment lines are held back until the next line shows
+    // whether they sit inside a field (e.g. a block scalar) or between fields
+    let mut pending = String::new();
+    for line in yaml.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            pending.push_str(line);
+            continue;
+        }
+        let continues =
+            line.starts_with([' ', '\t']) || trimmed == "-" || trimmed.starts_with("- ");
+        match segments.last_mut() {
+            Some(FrontmatterSegment::Field { text, .. }) if continues => {
+                text.push_str(&pending);
+                text.push_str(line);
+            }
+            _ => {
+                if !pending.is_empty() {
+                    segments.push(FrontmatterSegment::Trivia(std::mem::take(&mut pending)));
+                }
+                let key = trimmed
+                    .split_once(':')
+                    .map_or(trimmed, |(key, _)| key)
+                    .trim()
+                    .trim_matches(['\'', '"'])
+                    .to_string();
+                segments.push(FrontmatterSegment::Field {
+                    key,
+                    text: line.to_string(),
+                });
+            }
+        }
+        pending.clear();
+    }
+    if !pending.is_empty() {
+        segments.push(FrontmatterSegment::Trivia(pending));
+    }
+    // The last field may lack a newline; appended fields must start on their own line
+    if let Some(FrontmatterSegment::Field { text, .. }) = segments.last_mut()
+        && !text.ends_with('\n')
+    {
+        text.push('\n');
+    }
+    segments
+}
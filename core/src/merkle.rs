@@ -0,0 +1,158 @@
+//! Merkle tree over trace blocks (RFC 6962 hashing).
+//!
+//! A sealed document records the root in `trace_root`, which the version
+//! hash covers. An [`InclusionProof`] then shows that one trace was part of
+//! the sealed version without disclosing the other traces.
+
+use crate::error::{Result, RhodiError};
+use crate::models::TraceBlock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Trace keys written by `verify --annotate`; they never affect a leaf.
+const ANNOTATION_KEYS: &[&str] = &["verified_at", "last_result"];
+
+pub type Hash = [u8; 32];
+
+/// Audit path proving that a leaf is part of a tree of `tree_size` leaves.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InclusionProof {
+    pub index: usize,
+    pub tree_size: usize,
+    /// Sibling hashes from the leaf up to the root, `sha256:`-prefixed hex
+    pub path: Vec<String>,
+}
+
+/// Leaf hash of a trace: RFC 8785 canonical JSON of its fields, without
+/// verification annotations.
+pub fn trace_leaf(trace: &TraceBlock) -> Result<Hash> {
+    let mut value = serde_json::to_value(trace)
+        .map_err(|e| RhodiError::Serialization(format!("Failed to serialize trace: {}", e)))?;
+    if let Some(map) = value.as_object_mut() {
+        for key in ANNOTATION_KEYS {
+            map.remove(*key);
+        }
+    }
+    let canonical = crate::canonical::to_jcs(&value)?;
+    Ok(leaf_hash(canonical.as_bytes()))
+}
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Largest power of two strictly less than `n` (n > 1).
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Root of the tree over `leaves`. An empty tree hashes the empty string.
+pub fn root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+        }
+    }
+}
+
+/// Audit path for the leaf at `index`.
+pub fn inclusion_proof(leaves: &[Hash], index: usize) -> Result<InclusionProof> {
+    if index >= leaves.len() {
+        return Err(RhodiError::Verification(format!(
+            "Trace index {} is out of range ({} traces)",
+            index,
+            leaves.len()
+        )));
+    }
+    let mut path = Vec::new();
+    audit_path(leaves, index, &mut path);
+    Ok(InclusionProof {
+        index,
+        tree_size: leaves.len(),
+        path: path.iter().map(encode).collect(),
+    })
+}
+
+fn audit_path(leaves: &[Hash], index: usize, path: &mut Vec<Hash>) {
+    if leaves.len() <= 1 {
+        return;
+    }
+    let k = split_point(leaves.len());
+    if index < k {
+        audit_path(&leaves[..k], index, path);
+        path.push(root(&leaves[k..]));
+    } else {
+        audit_path(&leaves[k..], index - k, path);
+        path.push(root(&leaves[..k]));
+    }
+}
+
+/// Check that `leaf` sits at `proof.index` in the tree with root `expected`.
+pub fn verify_inclusion(leaf: &Hash, proof: &InclusionProof, expected: &Hash) -> Result<bool> {
+    if proof.index >= proof.tree_size {
+        return Ok(false);
+    }
+    let path = proof
+        .path
+        .iter()
+        .map(|h| decode(h))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Walk back down the same splits `audit_path` took, deepest sibling first
+    let mut splits = Vec::new();
+    let (mut index, mut size) = (proof.index, proof.tree_size);
+    while size > 1 {
+        let k = split_point(size);
+        if index < k {
+            splits.push(true);
+            size = k;
+        } else {
+            splits.push(false);
+            index -= k;
+            size -= k;
+        }
+    }
+    if splits.len() != path.len() {
+        return Ok(false);
+    }
+
+    let mut hash = *leaf;
+    for (sibling, leaf_on_left) in path.iter().zip(splits.iter().rev()) {
+        hash = if *leaf_on_left {
+            node_hash(&hash, sibling)
+        } else {
+            node_hash(sibling, &hash)
+        };
+    }
+    Ok(&hash == expected)
+}
+
+pub fn encode(hash: &Hash) -> String {
+    format!("sha256:{}", hex::encode(hash))
+}
+
+pub fn decode(s: &str) -> Result<Hash> {
+    let hex_str = s.strip_prefix("sha256:").unwrap_or(s);
+    hex::decode(hex_str)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| RhodiError::Format(format!("Invalid sha256 digest: {}", s)))
+}
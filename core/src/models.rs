@@ -1,6 +1,10 @@
+use crate::comparison::Comparison;
+use crate::crypto::SignatureScheme;
 use crate::error::{Result, RhodiError};
+use crate::selector::Selector;
 use crate::version::{
-    get_version_status, is_version_known, VersionStatus, DEFAULT_PROTOCOL_VERSION,
+    CanonicalizationProfile, DEFAULT_PROTOCOL_VERSION, HashingScheme, Migration, VersionRegistry,
+    canonicalization_profile, get_latest_version, hashing_scheme, migration_path,
 };
 use chrono::{DateTime, Utc};
 use ed25519_dalek::Signature;
@@ -9,6 +13,8 @@ use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
 use uuid::Uuid;
 
 fn serialize_hex<S>(bytes: &Option<[u8; 32]>, serializer: S) -> std::result::Result<S::Ok, S::Error>
@@ -22,14 +28,14 @@ where
 }
 
 fn serialize_signature<S>(
-    sig: &Option<Signature>,
+    sig: &Option<Vec<u8>>,
     serializer: S,
 ) -> std::result::Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     match sig {
-        Some(s) => serializer.serialize_str(&hex::encode(s.to_bytes())),
+        Some(s) => serializer.serialize_str(&hex::encode(s)),
         None => serializer.serialize_none(),
     }
 }
@@ -52,9 +58,9 @@ where
     }
 }
 
-fn deserialize_signature<'de, D>(
-    deserializer: D,
-) -> std::result::Result<Option<Signature>, D::Error>
+/// Signatures are stored as raw bytes; their length depends on the
+/// document's `signature_algorithm` and is checked at verification.
+fn deserialize_signature<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<u8>>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -63,10 +69,10 @@ where
         Some(hex_str) => {
             let bytes =
                 hex::decode(&hex_str).map_err(|e| serde::de::Error::custom(e.to_string()))?;
-            let arr: [u8; 64] = bytes.try_into().map_err(|_| {
-                serde::de::Error::custom("Invalid signature length: expected 64 bytes")
-            })?;
-            Ok(Some(Signature::from_bytes(&arr)))
+            if bytes.is_empty() {
+                return Err(serde::de::Error::custom("Signature is empty"));
+            }
+            Ok(Some(bytes))
         }
         None => Ok(None),
     }
@@ -82,6 +88,20 @@ pub enum DocStatus {
     Revoked,   // Document has been revoked
 }
 
+/// What a seal's version hash covers, recorded in the `seal_mode`
+/// frontmatter field. Documents without the field are sealed semantically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SealMode {
+    /// The parsed frontmatter fields and canonicalized body
+    #[default]
+    Semantic,
+    /// The `.tmd` file as written, less a few masked frontmatter fields
+    /// (see [`crate::markdown::file_hash`]), so verifying does not depend
+    /// on how the document is parsed
+    FileHash,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrontMatter {
     pub id: Uuid,
@@ -92,15 +112,27 @@ pub struct FrontMatter {
     )]
     pub version_hash: Option<[u8; 32]>,
     pub title: String,
-    pub author: Option<String>,
+    pub author: Option<Author>,
+    /// Everyone who contributed, with their role and signing key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contributors: Option<Vec<Contributor>>,
     /// Hex-encoded Ed25519 public key of the author
     pub public_key: Option<String>,
+    /// Where the author's public key can be discovered (`did:key:` or `https://` URL)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_uri: Option<String>,
     #[serde(
         serialize_with = "serialize_signature",
         deserialize_with = "deserialize_signature",
         default
     )]
-    pub signature: Option<Signature>,
+    pub signature: Option<Vec<u8>>,
+    /// Algorithm of `signature`; absent means Ed25519
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_algorithm: Option<SignatureScheme>,
+    /// What the version hash covers; absent means semantic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal_mode: Option<SealMode>,
     pub created_at: DateTime<Utc>,
     pub modified_at: Option<DateTime<Utc>>,
     pub doc_status: DocStatus,
@@ -117,6 +149,385 @@ pub struct FrontMatter {
     )]
     pub prev_version_hash: Option<[u8; 32]>,
     pub extra: Option<BTreeMap<String, String>>,
+    /// Sources declared once and referenced from traces as `source: $name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources: Option<BTreeMap<String, SourceDecl>>,
+    /// Digest of each hashed component at seal time, used only to diagnose
+    /// a `version_hash` mismatch. Not covered by the version hash itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_manifest: Option<BTreeMap<String, String>>,
+    /// Where the document was sealed, so verification elsewhere can warn
+    /// about differences that may change extraction results. Not hashed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    /// Merkle root over the document's trace blocks (`sha256:` hex). Covered
+    /// by the version hash, so a sealed version commits to every trace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_root: Option<String>,
+    /// Leaf hashes of the trace Merkle tree, in body order. Derivable from
+    /// the body; kept so inclusion proofs can be produced from the header.
+    /// Not hashed, so verification checks it against the body and
+    /// `trace_root`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_tree: Option<Vec<String>>,
+    /// Hex-encoded version hash of each included document at seal time,
+    /// keyed by include target (`path` or `id:<uuid>`). Covered by the
+    /// version hash, so swapping an included file for another sealed version
+    /// is detected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub includes: Option<BTreeMap<String, String>>,
+    /// The sealed version this document replaces. Covered by the version
+    /// hash, so a sealed version commits to what it supersedes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<DocumentLink>,
+    /// The sealed version that replaces this one, so readers of an old
+    /// version find the current one. Not hashed; it is attached after
+    /// sealing and holds only when the replacement `supersedes` this version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<DocumentLink>,
+    /// Counter-signatures by third parties, in the order they were added.
+    /// Not hashed; each entry is bound to the seal it notarizes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notarizations: Option<Vec<crate::notary::Notarization>>,
+    /// Certificate binding `public_key` to an organization root key.
+    /// Not hashed, so it can be attached or renewed without resealing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_certificate: Option<crate::certificate::CertificateRef>,
+}
+
+/// A sealed version of a document, named by id and version hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DocumentLink {
+    pub id: Uuid,
+    /// Hex-encoded version hash
+    pub version_hash: String,
+}
+
+impl DocumentLink {
+    /// The link to the sealed version `doc` is, if it is sealed.
+    pub fn to(doc: &TracedDocument) -> Option<Self> {
+        Some(Self {
+            id: doc.frontmatter.id,
+            version_hash: hex::encode(doc.frontmatter.version_hash?),
+        })
+    }
+
+    /// Whether `doc` is the version this link names.
+    pub fn names(&self, doc: &TracedDocument) -> bool {
+        Self::to(doc).as_ref() == Some(self)
+    }
+}
+
+impl std::fmt::Display for DocumentLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let short: String = self.version_hash.chars().take(16).collect();
+        write!(f, "{} at {}", self.id, short)
+    }
+}
+
+/// The document's author: a plain name, or a structured profile with
+/// contact details and identifiers. An author with only a name is written,
+/// and hashed, as a plain string, so free-form authors are unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "AuthorRepr", into = "AuthorRepr")]
+pub struct Author {
+    pub name: String,
+    pub email: Option<String>,
+    /// ORCID iD, e.g. `0000-0002-1825-0097`
+    pub orcid: Option<String>,
+    pub affiliation: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum AuthorRepr {
+    Name(String),
+    Profile {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        email: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        orcid: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        affiliation: Option<String>,
+    },
+}
+
+impl From<AuthorRepr> for Author {
+    fn from(repr: AuthorRepr) -> Self {
+        match repr {
+            AuthorRepr::Name(name) => name.into(),
+            AuthorRepr::Profile {
+                name,
+                email,
+                orcid,
+                affiliation,
+            } => Self {
+                name,
+                email,
+                orcid,
+                affiliation,
+            },
+        }
+    }
+}
+
+impl From<Author> for AuthorRepr {
+    fn from(author: Author) -> Self {
+        if author.is_plain() {
+            return Self::Name(author.name);
+        }
+        Self::Profile {
+            name: author.name,
+            email: author.email,
+            orcid: author.orcid,
+            affiliation: author.affiliation,
+        }
+    }
+}
+
+impl From<String> for Author {
+    fn from(name: String) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for Author {
+    fn from(name: &str) -> Self {
+        name.to_string().into()
+    }
+}
+
+/// Code that only needs the author's name can treat an author as a string.
+impl std::ops::Deref for Author {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl std::fmt::Display for Author {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl Author {
+    /// Whether the author is only a name.
+    pub fn is_plain(&self) -> bool {
+        self.email.is_none() && self.orcid.is_none() && self.affiliation.is_none()
+    }
+
+    /// The author as covered by the version hash: the name for a plain
+    /// author, else the profile object.
+    fn hashed_value(&self) -> Result<serde_json::Value> {
+        serde_json::to_value(self).map_err(unencodable)
+    }
+}
+
+/// Someone who contributed to the document, in one role. A reviewer with a
+/// `key` signs off on a sealed version by notarizing it with that key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contributor {
+    pub name: String,
+    pub role: ContributorRole,
+    /// Hex-encoded Ed25519 public key the contributor signs with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// ORCID iD, e.g. `0000-0002-1825-0097`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orcid: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContributorRole {
+    Author,
+    Reviewer,
+    Editor,
+}
+
+impl Contributor {
+    /// Check the fields that have a fixed format.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(RhodiError::Format("Contributor needs a name".into()));
+        }
+        if let Some(key) = &self.key {
+            crate::crypto::parse_public_key(key).map_err(|e| {
+                RhodiError::Format(format!(
+                    "Invalid key for contributor '{}': {}",
+                    self.name, e
+                ))
+            })?;
+        }
+        if let Some(orcid) = &self.orcid {
+            crate::identity::validate_orcid(orcid)?;
+        }
+        Ok(())
+    }
+}
+
+/// A source shared by several traces, declared in the frontmatter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SourceDecl {
+    pub path: String,
+    /// `sha256:` digest of the content, used by traces without their own hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// URI scheme of the resolver serving `path` (e.g. `https`), when the
+    /// path does not carry one itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver: Option<String>,
+}
+
+impl SourceDecl {
+    /// The source string handed to the resolver.
+    pub fn location(&self) -> String {
+        match self.resolver {
+            Some(ref scheme) if !self.path.contains(':') => format!("{}://{}", scheme, self.path),
+            _ => self.path.clone(),
+        }
+    }
+}
+
+impl FrontMatter {
+    /// Timestamps that cannot be right: `created_at` after `modified_at`,
+    /// a notarization before the seal, or any of them later than `now`
+    /// plus the tolerated clock `skew`.
+    pub fn timestamp_anomalies(&self, now: DateTime<Utc>, skew: chrono::Duration) -> Vec<String> {
+        let mut anomalies = Vec::new();
+        let mut future = |field: &str, at: DateTime<Utc>| {
+            if at > now + skew {
+                anomalies.push(format!("{} {} is in the future", field, at.to_rfc3339()));
+            }
+        };
+        future("created_at", self.created_at);
+        if let Some(modified_at) = self.modified_at {
+            future("modified_at", modified_at);
+        }
+        for notarization in self.notarizations.iter().flatten() {
+            future("notarized_at", notarization.notarized_at);
+        }
+
+        if let Some(modified_at) = self.modified_at
+            && modified_at < self.created_at
+        {
+            anomalies.push(format!(
+                "modified_at {} is before created_at {}",
+                modified_at.to_rfc3339(),
+                self.created_at.to_rfc3339()
+            ));
+        }
+        let sealed_at = self.modified_at.unwrap_or(self.created_at);
+        for notarization in self.notarizations.iter().flatten() {
+            if notarization.notarized_at < sealed_at {
+                anomalies.push(format!(
+                    "notarized_at {} is before the document was sealed ({})",
+                    notarization.notarized_at.to_rfc3339(),
+                    sealed_at.to_rfc3339()
+                ));
+            }
+        }
+        anomalies
+    }
+
+    /// The trace with a `$name` source replaced by the declared location,
+    /// inheriting the declared hash when the trace has none. Other traces
+    /// are returned unchanged.
+    pub fn resolve_trace(&self, trace: &TraceBlock) -> Result<TraceBlock> {
+        let Some(name) = trace.source_ref() else {
+            return Ok(trace.clone());
+        };
+        let decl = self
+            .sources
+            .as_ref()
+            .and_then(|sources| sources.get(name))
+            .ok_or_else(|| {
+                RhodiError::Resolution(format!(
+                    "Trace references undeclared source '{}'; declare it under `sources:` in the frontmatter",
+                    trace.source
+                ))
+            })?;
+        let mut resolved = trace.clone();
+        resolved.source = decl.location();
+        if resolved.hash.is_none() {
+            resolved.hash = decl.hash.clone();
+        }
+        Ok(resolved)
+    }
+}
+
+/// The tooling and platform a document was sealed with.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Environment {
+    /// Version of rhodi that sealed the document
+    pub tool_version: String,
+    pub os: String,
+    /// Locale from `LC_ALL`, `LC_NUMERIC` or `LANG`, if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Hashing scheme of the document's protocol version
+    pub canonicalization: String,
+}
+
+impl Environment {
+    /// Describe the running process, for a document at `protocol_version`.
+    pub fn current(protocol_version: &str) -> Self {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|v| !v.is_empty());
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            locale,
+            canonicalization: format!(
+                "{}{}",
+                hashing_scheme(protocol_version).name(),
+                canonicalization_profile(protocol_version).suffix()
+            ),
+        }
+    }
+
+    /// Differences from `other` that could affect extraction or hashing.
+    /// Patch releases and locale encodings (`.UTF-8`) are not material.
+    pub fn differences(&self, other: &Environment) -> Vec<String> {
+        let minor = |v: &str| v.split('.').take(2).collect::<Vec<_>>().join(".");
+        let language = |l: &Option<String>| {
+            l.as_deref()
+                .map(|l| l.split(['.', '@']).next().unwrap_or(l).to_string())
+        };
+
+        let mut diffs = Vec::new();
+        if minor(&self.tool_version) != minor(&other.tool_version) {
+            diffs.push(format!(
+                "tool version {} (sealed with {})",
+                other.tool_version, self.tool_version
+            ));
+        }
+        if self.os != other.os {
+            diffs.push(format!("OS {} (sealed on {})", other.os, self.os));
+        }
+        if language(&self.locale) != language(&other.locale) {
+            diffs.push(format!(
+                "locale {} (sealed with {})",
+                other.locale.as_deref().unwrap_or("unset"),
+                self.locale.as_deref().unwrap_or("unset")
+            ));
+        }
+        if self.canonicalization != other.canonicalization {
+            diffs.push(format!(
+                "canonicalization {} (sealed with {})",
+                other.canonicalization, self.canonicalization
+            ));
+        }
+        diffs
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -127,6 +538,13 @@ pub struct Policy {
     pub allow_quote: bool,
     #[serde(default = "default_false")]
     pub require_attribution: bool,
+    /// Default freshness limit for traces without their own `max_age`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_trace_age: Option<String>,
+    /// Published versions need notarizations by at least this many listed
+    /// reviewers other than the signer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_reviewers: Option<u32>,
 }
 
 impl Default for Policy {
@@ -135,6 +553,8 @@ impl Default for Policy {
             allow_include: true,
             allow_quote: true,
             require_attribution: false,
+            max_trace_age: None,
+            required_reviewers: None,
         }
     }
 }
@@ -155,17 +575,92 @@ pub struct AgentMetadata {
     pub prompt_hash: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum TraceMethod {
+    #[default]
     Automatic,
     Manual,
     Agent,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Numeric comparison settings for a trace's `expected` value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Tolerance {
+    /// Maximum absolute difference between expected and extracted values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absolute: Option<f64>,
+    /// Maximum difference relative to the expected value (0.01 = 1%)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relative: Option<f64>,
+    /// Treat percentages as fractions, so `85%` equals `0.85`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub normalize_percent: bool,
+}
+
+/// Defaults for traces that name it with `preset:`, declared under
+/// `[presets.<name>]` in `rhodi.toml`. A trace's own fields win.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TracePreset {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extractor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance: Option<Tolerance>,
+    /// Used when the trace has no transforms of its own
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalize: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub numeric: bool,
+}
+
+impl TracePreset {
+    /// Fill the fields `trace` leaves unset.
+    pub fn apply(&self, trace: &mut TraceBlock) {
+        fn fill<T: Clone>(field: &mut Option<T>, default: &Option<T>) {
+            if field.is_none() {
+                field.clone_from(default);
+            }
+        }
+        fill(&mut trace.selector, &self.selector);
+        fill(&mut trace.extractor, &self.extractor);
+        fill(&mut trace.context, &self.context);
+        fill(&mut trace.context_lines, &self.context_lines);
+        fill(&mut trace.tolerance, &self.tolerance);
+        fill(&mut trace.normalize, &self.normalize);
+        fill(&mut trace.max_age, &self.max_age);
+        fill(&mut trace.schema, &self.schema);
+        trace.numeric |= self.numeric;
+        if trace.transforms.is_empty() {
+            trace.transforms.clone_from(&self.transforms);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct TraceBlock {
+    /// Stable identifier, e.g. `t-004`, so reports and tooling can name the
+    /// trace across versions regardless of its source or position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub source: String,
+    /// Equivalent locations of `source`, e.g. an HTTPS mirror or an
+    /// archive.org snapshot, tried in order when it fails. Written as a
+    /// `source` list, whose first entry is `source`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
     pub hash: Option<String>,
     pub selector: Option<String>,
     pub expected: String,
@@ -176,13 +671,147 @@ pub struct TraceBlock {
     pub context: Option<String>,
     pub confidence: Option<f64>,
     pub agent_metadata: Option<AgentMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance: Option<Tolerance>,
+    /// Compare expected and extracted values as numbers in whatever locale
+    /// notation each uses, so `1,234.5` equals `1 234,5`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub numeric: bool,
+    /// How `expected` relates to the extracted value: `equals` (the
+    /// default), `contains`, `matches`, `gte`, `lte` or `between`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare: Option<String>,
+    /// Post-processing steps applied to the extracted value, e.g. `round:2`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<String>,
+    /// How the source is compressed (`gzip`, `zstd` or `zip`). The hash and
+    /// extraction then cover the decompressed content; a zip source names
+    /// its entry as `archive.zip!entry`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Normalize the source before hashing and extraction, e.g. `html` to
+    /// reduce a web page to its visible text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize: Option<String>,
+    /// Require the regex match to lie within this many lines of `context`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_lines: Option<usize>,
+    /// How old `timestamp` may be before the trace is stale, e.g. `90d`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// JSON Schema (a path or URI, resolved like `source`) the JSON source
+    /// must validate against, on top of the selector check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Workspace preset (see [`TracePreset`]) supplying the fields this
+    /// trace leaves unset, e.g. `experiment-metric`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// The claim this trace backs: a heading path (`Results > Revenue`) or
+    /// the `sha256:` hash of the sentence before the block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// When `rhodi verify --annotate` last checked this trace (not hashed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<DateTime<Utc>>,
+    /// Outcome of the last annotated verification (not hashed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_result: Option<TraceResult>,
+}
+
+/// Outcome of checking one trace against its source.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceResult {
+    pub passed: bool,
+    /// The value extracted from the source, when extraction succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+    /// Why the check failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 impl TraceBlock {
+    /// Start building a trace from library code; see [`TraceBlockBuilder`].
+    pub fn builder() -> TraceBlockBuilder {
+        TraceBlockBuilder::default()
+    }
+
+    /// How reports name this trace: `t-004 (data.csv)` with an id, else the
+    /// source.
+    pub fn label(&self) -> String {
+        match &self.id {
+            Some(id) => format!("{} ({})", id, self.source),
+            None => self.source.clone(),
+        }
+    }
+
+    /// The comparison named by `compare`, equality when unset.
+    pub fn comparison(&self) -> Result<Comparison> {
+        self.compare
+            .as_deref()
+            .map_or(Ok(Comparison::Equals), Comparison::parse)
+    }
+
+    /// Check an extracted value against `expected` under the trace's
+    /// comparison, tolerance and `numeric` setting.
+    pub fn accepts(&self, actual: &str) -> Result<bool> {
+        self.comparison()?
+            .check(&self.expected, actual, self.tolerance.as_ref(), self.numeric)
+    }
+
+    /// Name of the frontmatter source this trace references (`source: $name`).
+    pub fn source_ref(&self) -> Option<&str> {
+        self.source.strip_prefix('$')
+    }
+
+    /// The selector parsed for the trace's extractor (inferred as for
+    /// `auto` when none is named). Selectors of manual and agent traces
+    /// are free-form location hints and are not parsed.
+    pub fn typed_selector(&self) -> Result<Option<Selector>> {
+        let Some(selector) = &self.selector else {
+            return Ok(None);
+        };
+        if self.method != TraceMethod::Automatic {
+            return Ok(None);
+        }
+        Selector::parse(self.extractor.as_deref().unwrap_or("auto"), selector)
+            .map(Some)
+            .map_err(|e| match e {
+                RhodiError::Extraction(message) => {
+                    RhodiError::Format(format!("Trace for {}: {}", self.label(), message))
+                }
+                e => e,
+            })
+    }
+
+    /// Whether `timestamp` is later than `now` plus the tolerated clock `skew`.
+    pub fn timestamp_in_future(&self, now: DateTime<Utc>, skew: chrono::Duration) -> bool {
+        self.timestamp
+            .is_some_and(|timestamp| timestamp > now + skew)
+    }
+
+    /// The trace with its `preset`, if any, expanded from `presets`. Traces
+    /// without a preset are returned unchanged.
+    pub fn expand_preset(&self, presets: &BTreeMap<String, TracePreset>) -> Result<TraceBlock> {
+        let mut expanded = self.clone();
+        if let Some(name) = &self.preset {
+            let preset = presets.get(name).ok_or_else(|| {
+                RhodiError::Resolution(format!(
+                    "Trace uses unknown preset '{}'; define it under [presets.{}] in rhodi.toml",
+                    name, name
+                ))
+            })?;
+            preset.apply(&mut expanded);
+        }
+        Ok(expanded)
+    }
+
     /// Update the hash of the source file.
     /// Currently supports local files.
     pub fn update_hash(&mut self, base_path: &Path) -> Result<()> {
-        let path = base_path.join(&self.source);
+        let (file, _) = crate::compression::split_archive_entry(&self.source);
+        let path = base_path.join(file);
         if !path.exists() {
             return Err(RhodiError::Resolution(format!(
                 "Source file not found: {:?}",
@@ -192,15 +821,447 @@ impl TraceBlock {
 
         let content = fs::read(&path)?;
 
-        let mut hasher = Sha256::new();
-        hasher.update(&content);
-        let result = hasher.finalize();
+        self.hash = Some(crate::crypto::hash_bytes(
+            &self.normalized_source(&content)?,
+        ));
+        Ok(())
+    }
 
-        self.hash = Some(format!("sha256:{}", hex::encode(result)));
+    /// The content type to extract the normalized source as, given the
+    /// type its resolver reported. A compressed source's is that of the
+    /// decompressed content, guessed from the source name.
+    pub fn source_content_type<'a>(&self, resolved: Option<&'a str>) -> Option<&'a str> {
+        if self.compression.is_some() {
+            crate::resolver::guess_content_type(&self.source)
+        } else {
+            resolved
+        }
+    }
+
+    /// The source as the trace hashes and extracts from it: `content`
+    /// decompressed as the trace's `compression` says, then after its
+    /// `normalize` step, if any.
+    pub fn normalized_source<'a>(&self, content: &'a [u8]) -> Result<std::borrow::Cow<'a, [u8]>> {
+        self.normalized_source_within(content, crate::compression::MAX_DECOMPRESSED_BYTES)
+            .map(|(normalized, _)| normalized)
+    }
+
+    /// As [`Self::normalized_source`], decompressing at most
+    /// `max_decompressed` bytes. Also returns how many bytes were
+    /// decompressed (0 for an uncompressed source), for callers that count
+    /// them against a budget.
+    pub fn normalized_source_within<'a>(
+        &self,
+        content: &'a [u8],
+        max_decompressed: u64,
+    ) -> Result<(std::borrow::Cow<'a, [u8]>, u64)> {
+        use std::borrow::Cow;
+
+        let compression = match self.compression.as_deref() {
+            Some(spec) => Some(crate::compression::Compression::parse(spec)?),
+            None if crate::compression::split_archive_entry(&self.source).1.is_some() => {
+                return Err(RhodiError::Extraction(format!(
+                    "{} names an archive entry; the trace needs `compression: zip`",
+                    self.source
+                )));
+            }
+            None => None,
+        };
+        let Some(compression) = compression else {
+            return crate::extraction::normalize_source(content, self.normalize.as_deref())
+                .map(|normalized| (normalized, 0));
+        };
+        let content = compression.decompress(content, &self.source, max_decompressed)?;
+        Ok((
+            Cow::Owned(
+                crate::extraction::normalize_source(&content, self.normalize.as_deref())?
+                    .into_owned(),
+            ),
+            content.len() as u64,
+        ))
+    }
+
+    /// Check `timestamp` against the trace's `max_age`, falling back to the
+    /// document policy's `max_trace_age`. Traces without a limit are fresh.
+    pub fn check_freshness(&self, policy: &Policy, now: DateTime<Utc>) -> Result<()> {
+        let Some(limit) = self.max_age.as_deref().or(policy.max_trace_age.as_deref()) else {
+            return Ok(());
+        };
+        let max_age = parse_duration(limit)?;
+        let timestamp = self.timestamp.ok_or_else(|| {
+            RhodiError::Verification(format!(
+                "Trace for {} has a max_age of {} but no timestamp",
+                self.source, limit
+            ))
+        })?;
+        if now.signed_duration_since(timestamp) > max_age {
+            return Err(RhodiError::Verification(format!(
+                "Trace for {} is stale: timestamp {} is older than {}",
+                self.source,
+                timestamp.to_rfc3339(),
+                limit
+            )));
+        }
         Ok(())
     }
 }
 
+/// Builds a [`TraceBlock`] field by field, checking on [`Self::build`] that
+/// the fields make sense together.
+#[derive(Debug, Clone, Default)]
+pub struct TraceBlockBuilder {
+    trace: TraceBlock,
+}
+
+impl TraceBlockBuilder {
+    pub fn id(mut self, id: &str) -> Self {
+        self.trace.id = Some(id.to_string());
+        self
+    }
+
+    pub fn source(mut self, source: &str) -> Self {
+        self.trace.source = source.to_string();
+        self
+    }
+
+    /// Add a location tried when `source` and earlier mirrors fail.
+    pub fn mirror(mut self, mirror: &str) -> Self {
+        self.trace.mirrors.push(mirror.to_string());
+        self
+    }
+
+    /// Pin the source hash (`sha256:` hex).
+    pub fn hash(mut self, hash: &str) -> Self {
+        self.trace.hash = Some(hash.to_string());
+        self
+    }
+
+    /// Set the selector, which needs an [`Self::extractor`] (`auto` picks
+    /// one by source type).
+    pub fn selector(mut self, selector: &str) -> Self {
+        self.trace.selector = Some(selector.to_string());
+        self
+    }
+
+    pub fn extractor(mut self, extractor: &str) -> Self {
+        self.trace.extractor = Some(extractor.to_string());
+        self
+    }
+
+    pub fn expected(mut self, expected: &str) -> Self {
+        self.trace.expected = expected.to_string();
+        self
+    }
+
+    pub fn method(mut self, method: TraceMethod) -> Self {
+        self.trace.method = method;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.trace.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn context(mut self, context: &str) -> Self {
+        self.trace.context = Some(context.to_string());
+        self
+    }
+
+    /// Require the match to lie within `lines` lines of the context.
+    pub fn context_lines(mut self, lines: usize) -> Self {
+        self.trace.context_lines = Some(lines);
+        self
+    }
+
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.trace.confidence = Some(confidence);
+        self
+    }
+
+    /// Record the agent that produced the trace, which needs
+    /// [`TraceMethod::Agent`].
+    pub fn agent_metadata(mut self, metadata: AgentMetadata) -> Self {
+        self.trace.agent_metadata = Some(metadata);
+        self
+    }
+
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.trace.tolerance = Some(tolerance);
+        self
+    }
+
+    pub fn numeric(mut self, numeric: bool) -> Self {
+        self.trace.numeric = numeric;
+        self
+    }
+
+    /// How `expected` relates to the extracted value, e.g. `gte`.
+    pub fn compare(mut self, compare: &str) -> Self {
+        self.trace.compare = Some(compare.to_string());
+        self
+    }
+
+    /// Append a post-processing step, e.g. `round:2`.
+    pub fn transform(mut self, step: &str) -> Self {
+        self.trace.transforms.push(step.to_string());
+        self
+    }
+
+    /// How the source is compressed: `gzip`, `zstd` or `zip`.
+    pub fn compression(mut self, compression: &str) -> Self {
+        self.trace.compression = Some(compression.to_string());
+        self
+    }
+
+    pub fn normalize(mut self, normalize: &str) -> Self {
+        self.trace.normalize = Some(normalize.to_string());
+        self
+    }
+
+    /// How old the timestamp may be, e.g. `90d`.
+    pub fn max_age(mut self, max_age: &str) -> Self {
+        self.trace.max_age = Some(max_age.to_string());
+        self
+    }
+
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.trace.schema = Some(schema.to_string());
+        self
+    }
+
+    pub fn preset(mut self, preset: &str) -> Self {
+        self.trace.preset = Some(preset.to_string());
+        self
+    }
+
+    pub fn anchor(mut self, anchor: &str) -> Self {
+        self.trace.anchor = Some(anchor.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<TraceBlock> {
+        let trace = self.trace;
+        if trace.source.trim().is_empty() {
+            return Err(RhodiError::Format("Trace needs a source".into()));
+        }
+        if trace.selector.is_some() && trace.extractor.is_none() {
+            return Err(RhodiError::Format(format!(
+                "Trace for {} has a selector but no extractor (use `auto` to pick one by source type)",
+                trace.source
+            )));
+        }
+        if trace.agent_metadata.is_some() && trace.method != TraceMethod::Agent {
+            return Err(RhodiError::Format(format!(
+                "Trace for {} has agent metadata but its method is not `agent`",
+                trace.source
+            )));
+        }
+        if let Some(max_age) = &trace.max_age {
+            parse_duration(max_age)?;
+        }
+        if let Some(compression) = &trace.compression {
+            crate::compression::Compression::parse(compression)?;
+        }
+        trace.comparison()?;
+        trace.typed_selector()?;
+        Ok(trace)
+    }
+}
+
+/// Check that `versions` (oldest first) form a monotonic sequence of one
+/// document: the same id and `created_at`, strictly increasing
+/// `doc_version`, and `modified_at` never going backwards. Returns one
+/// message per anomaly.
+pub fn check_version_sequence(versions: &[TracedDocument]) -> Vec<String> {
+    let mut anomalies = Vec::new();
+    for pair in versions.windows(2) {
+        let (older, newer) = (&pair[0].frontmatter, &pair[1].frontmatter);
+        if newer.id != older.id {
+            anomalies.push(format!(
+                "Version {} belongs to document {}, not {}",
+                newer.doc_version, newer.id, older.id
+            ));
+            continue;
+        }
+        if newer.doc_version <= older.doc_version {
+            anomalies.push(format!(
+                "doc_version goes from {} to {}",
+                older.doc_version, newer.doc_version
+            ));
+        }
+        if newer.created_at != older.created_at {
+            anomalies.push(format!(
+                "created_at changes between versions {} and {}",
+                older.doc_version, newer.doc_version
+            ));
+        }
+        let older_at = older.modified_at.unwrap_or(older.created_at);
+        let newer_at = newer.modified_at.unwrap_or(newer.created_at);
+        if newer_at < older_at {
+            anomalies.push(format!(
+                "Version {} was modified at {}, before version {} ({})",
+                newer.doc_version,
+                newer_at.to_rfc3339(),
+                older.doc_version,
+                older_at.to_rfc3339()
+            ));
+        }
+    }
+    anomalies
+}
+
+/// Parse a duration such as `30d`, `12h`, `2w`, `45m` or `90s`.
+pub fn parse_duration(s: &str) -> Result<chrono::Duration> {
+    let s = s.trim();
+    let invalid = || RhodiError::Format(format!("Invalid duration '{}'", s));
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)
+}
+
+/// Metadata of an include block used for modular composition. The
+/// included document is named by exactly one of `path` (relative to the
+/// including document) or `id`, which is looked up in the workspace index so
+/// the include survives moving files around.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IncludeBlock {
+    #[serde(default)]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub integrity: Option<String>,
+}
+
+impl IncludeBlock {
+    /// How reports name the included document: its path, or `id:<uuid>`.
+    pub fn target(&self) -> String {
+        match self.id {
+            Some(id) => format!("id:{}", id),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Local stand-in for an included document that cannot be resolved
+/// (offline, or access-restricted). Stored as JSON next to where the include
+/// would be, at `<path>.stub.json`. A stub lets verification continue with a
+/// warning; it vouches for nothing, since the document itself is never read.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct IncludeStub {
+    pub id: Uuid,
+    /// Hex-encoded version hash of the included document
+    pub version_hash: String,
+    /// Hex-encoded public key the included document is sealed with
+    pub public_key: String,
+}
+
+impl IncludeStub {
+    /// Path of the stub standing in for the include at `path`.
+    pub fn path_for(path: &str) -> String {
+        format!("{}.stub.json", path)
+    }
+
+    /// Stub for a sealed document, for distribution to readers without
+    /// access to it.
+    pub fn from_document(doc: &TracedDocument) -> Result<Self> {
+        let fm = &doc.frontmatter;
+        match (fm.version_hash, &fm.public_key) {
+            (Some(version_hash), Some(public_key)) => Ok(Self {
+                id: fm.id,
+                version_hash: hex::encode(version_hash),
+                public_key: public_key.clone(),
+            }),
+            _ => Err(RhodiError::Verification(
+                "Only sealed documents with a public_key can be stubbed".into(),
+            )),
+        }
+    }
+
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        let stub: Self = serde_json::from_slice(bytes)
+            .map_err(|e| RhodiError::Format(format!("Invalid include stub: {}", e)))?;
+        crate::crypto::parse_public_key(&stub.public_key)?;
+        if !hex::decode(&stub.version_hash).is_ok_and(|h| h.len() == 32) {
+            return Err(RhodiError::Format(format!(
+                "Invalid include stub: version_hash '{}' is not a sha256 hex digest",
+                stub.version_hash
+            )));
+        }
+        Ok(stub)
+    }
+}
+
+/// A verbatim citation of another sealed document.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuoteBlock {
+    /// Location of the quoted document, resolved like an include path
+    pub source_doc: String,
+    /// Hex-encoded version hash of the quoted document version
+    pub version_hash: String,
+    /// The quoted text, which must appear verbatim in the source body
+    pub excerpt: String,
+    /// `sha256:` hash of the excerpt, filled in when traces are updated
+    pub excerpt_hash: Option<String>,
+}
+
+impl QuoteBlock {
+    /// Compute the `sha256:` hash of the trimmed excerpt.
+    pub fn compute_excerpt_hash(&self) -> String {
+        crate::crypto::hash_bytes(self.excerpt.trim().as_bytes())
+    }
+}
+
+fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// `value` with every string and object key in Unicode NFC. Keys that
+/// differ only in normalization would hash as one, so they are an error.
+fn nfc_value(value: serde_json::Value) -> Result<serde_json::Value> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::String(text) => Value::String(nfc(&text)),
+        Value::Array(items) => {
+            Value::Array(items.into_iter().map(nfc_value).collect::<Result<_>>()?)
+        }
+        Value::Object(members) => Value::Object(nfc_members(members)?),
+        other => other,
+    })
+}
+
+fn nfc_members(
+    members: serde_json::Map<String, serde_json::Value>,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let mut normalized = serde_json::Map::new();
+    for (key, value) in members {
+        let key = nfc(&key);
+        if normalized.contains_key(&key) {
+            return Err(RhodiError::Format(format!(
+                "Frontmatter has two keys that are both '{}' in Unicode NFC",
+                key
+            )));
+        }
+        normalized.insert(key, nfc_value(value)?);
+    }
+    Ok(normalized)
+}
+
+/// A hashed frontmatter field that could not be encoded for the hash.
+fn unencodable(e: serde_json::Error) -> RhodiError {
+    RhodiError::Serialization(format!("Failed to encode hashed frontmatter: {}", e))
+}
+
 fn default_trace_method() -> TraceMethod {
     TraceMethod::Automatic
 }
@@ -212,24 +1273,72 @@ impl Default for FrontMatter {
             version_hash: None,
             title: "Untitled".to_string(),
             author: None,
+            contributors: None,
             public_key: None,
+            key_uri: None,
             signature: None,
+            signature_algorithm: None,
+            seal_mode: None,
             created_at: Utc::now(),
             modified_at: None,
             doc_status: DocStatus::Notes,
             policy: Policy::default(),
-            protocol_version: DEFAULT_PROTOCOL_VERSION.to_string(),
+            protocol_version: get_latest_version().to_string(),
             doc_version: 0,
             prev_version_hash: None,
             extra: None,
+            sources: None,
+            hash_manifest: None,
+            environment: None,
+            trace_root: None,
+            trace_tree: None,
+            includes: None,
+            supersedes: None,
+            superseded_by: None,
+            notarizations: None,
+            key_certificate: None,
         }
     }
 }
 
+/// Metadata changes for [`TracedDocument::amend`], and why they are made.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Amendment {
+    pub title: Option<String>,
+    pub author: Option<Author>,
+    /// `extra` entries to set, or with `None` to remove
+    pub extra: BTreeMap<String, Option<String>>,
+    /// Recorded as `extra.amendment_reason`
+    pub reason: String,
+}
+
+impl Amendment {
+    /// The fields the amendment actually changes in `fm`.
+    fn changed_fields(&self, fm: &FrontMatter) -> Vec<String> {
+        let mut changed = Vec::new();
+        if self.title.as_ref().is_some_and(|title| *title != fm.title) {
+            changed.push("title".to_string());
+        }
+        if self.author.is_some() && self.author != fm.author {
+            changed.push("author".to_string());
+        }
+        for (key, value) in &self.extra {
+            let current = fm.extra.as_ref().and_then(|extra| extra.get(key));
+            if value.as_ref() != current {
+                changed.push(format!("extra.{}", key));
+            }
+        }
+        changed
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TracedDocument {
     pub frontmatter: FrontMatter,
     pub body: String,
+    /// The text the document was parsed from, which a file-hash seal
+    /// covers. `None` for documents built in memory.
+    pub raw: Option<Arc<str>>,
 }
 
 impl TracedDocument {
@@ -240,6 +1349,7 @@ impl TracedDocument {
                 ..FrontMatter::default()
             },
             body: content.trim().to_string(),
+            raw: None,
         }
     }
 
@@ -254,11 +1364,12 @@ impl TracedDocument {
                 ..FrontMatter::default()
             },
             body: content.trim().to_string(),
+            raw: None,
         }
     }
 
     pub fn author(mut self, author: &str) -> Self {
-        self.frontmatter.author = Some(author.to_string());
+        self.frontmatter.author = Some(author.into());
         self
     }
 
@@ -289,30 +1400,106 @@ impl TracedDocument {
     }
 
     pub fn set_signature(mut self, signature: Signature) -> Self {
-        self.frontmatter.signature = Some(signature);
+        self.frontmatter.signature = Some(signature.to_vec());
         self
     }
 
     /// Compute the SHA-256 hash of the document for integrity.
-    /// This hashes the canonicalized body and the frontmatter (excluding version_hash and signature).
-    pub fn compute_version_hash(&self) -> [u8; 32] {
+    /// This hashes the canonicalized body and the frontmatter (excluding version_hash and signature),
+    /// encoded according to the document's protocol version.
+    pub fn compute_version_hash(&self) -> Result<[u8; 32]> {
         let mut hasher = Sha256::new();
 
+        if hashing_scheme(&self.frontmatter.protocol_version) == HashingScheme::CanonicalJson {
+            let mut document = serde_json::Map::new();
+            document.insert("body".into(), self.canonical_body().into());
+            document.insert("frontmatter".into(), self.hashed_object()?.into());
+            let jcs = crate::canonical::to_jcs(&document.into())?;
+            hasher.update(jcs.as_bytes());
+
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&hasher.finalize());
+            return Ok(hash);
+        }
+
         // 1. Hash the canonicalized body
-        let canonical_body = crate::markdown::canonicalize_text(&self.body);
-        hasher.update(canonical_body.as_bytes());
+        hasher.update(self.canonical_body().as_bytes());
 
         // 2. Hash the frontmatter (excluding version_hash and signature)
-        // We use a temporary BTreeMap to ensure sorted keys for deterministic hashing
+        let fm_json = serde_json::to_string(&self.hashed_fields()?).map_err(unencodable)?;
+        hasher.update(fm_json.as_bytes());
+
+        let result = hasher.finalize();
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&result);
+        Ok(hash)
+    }
+
+    /// The version hash the document's content hashes to under its seal
+    /// mode. A file-hash seal needs the text the document was parsed from.
+    pub fn current_version_hash(&self) -> Result<[u8; 32]> {
+        match self.frontmatter.seal_mode.unwrap_or_default() {
+            SealMode::Semantic => self.compute_version_hash(),
+            SealMode::FileHash => {
+                let raw = self.raw.as_deref().ok_or_else(|| {
+                    RhodiError::Verification(
+                        "Document has a file-hash seal but was not read from a file".to_string(),
+                    )
+                })?;
+                crate::markdown::file_hash(raw)
+            }
+        }
+    }
+
+    /// The body as covered by the version hash: canonicalized, with review
+    /// comments (from 2.2) and trace result annotations (from 2.3) removed
+    /// and (from 2.4) redact blocks reduced to their commitments.
+    fn canonical_body(&self) -> String {
+        let version = &self.frontmatter.protocol_version;
+        let body = crate::comments::hashed_body(&self.body, version);
+        let body = if crate::version::excludes_trace_annotations(version) {
+            crate::markdown::strip_trace_annotations(&body)
+        } else {
+            std::borrow::Cow::Borrowed(body.as_ref())
+        };
+        let body = if crate::version::commits_redactions(version) {
+            crate::redaction::commit_redactions(&body)
+        } else {
+            std::borrow::Cow::Borrowed(body.as_ref())
+        };
+        crate::markdown::canonicalize_text_for(&body, version)
+    }
+
+    /// The frontmatter fields covered by the version hash, as canonical strings.
+    fn hashed_fields(&self) -> Result<BTreeMap<String, String>> {
+        // We use a BTreeMap to ensure sorted keys for deterministic hashing
         let mut fm_map: BTreeMap<String, String> = BTreeMap::new();
         fm_map.insert("id".into(), self.frontmatter.id.to_string());
         fm_map.insert("title".into(), self.frontmatter.title.clone());
+        // A profile gets its own key, so no plain name can hash like one
         if let Some(ref author) = self.frontmatter.author {
-            fm_map.insert("author".into(), author.clone());
+            let (key, value) = match author.hashed_value()? {
+                serde_json::Value::String(name) => ("author", name),
+                profile => ("author_profile", crate::canonical::to_jcs(&profile)?),
+            };
+            fm_map.insert(key.into(), value);
+        }
+        if let Some(ref contributors) = self.frontmatter.contributors {
+            let contributors = serde_json::to_value(contributors).map_err(unencodable)?;
+            fm_map.insert(
+                "contributors".into(),
+                crate::canonical::to_jcs(&contributors)?,
+            );
         }
         if let Some(ref pk) = self.frontmatter.public_key {
             fm_map.insert("public_key".into(), pk.clone());
         }
+        if let Some(ref uri) = self.frontmatter.key_uri {
+            fm_map.insert("key_uri".into(), uri.clone());
+        }
+        if let Some(scheme) = self.frontmatter.signature_algorithm {
+            fm_map.insert("signature_algorithm".into(), scheme.name().into());
+        }
 
         // Policy fields
         fm_map.insert(
@@ -327,6 +1514,12 @@ impl TracedDocument {
             "policy_require_attribution".into(),
             self.frontmatter.policy.require_attribution.to_string(),
         );
+        if let Some(ref max_age) = self.frontmatter.policy.max_trace_age {
+            fm_map.insert("policy_max_trace_age".into(), max_age.clone());
+        }
+        if let Some(reviewers) = self.frontmatter.policy.required_reviewers {
+            fm_map.insert("policy_required_reviewers".into(), reviewers.to_string());
+        }
 
         fm_map.insert("created_at".into(), self.frontmatter.created_at.to_rfc3339());
         if let Some(ref modified_at) = self.frontmatter.modified_at {
@@ -346,6 +1539,25 @@ impl TracedDocument {
         if let Some(ref prev_hash) = self.frontmatter.prev_version_hash {
             fm_map.insert("prev_version_hash".into(), hex::encode(prev_hash));
         }
+        if let Some(ref root) = self.frontmatter.trace_root {
+            fm_map.insert("trace_root".into(), root.clone());
+        }
+        if let Some(ref includes) = self.frontmatter.includes {
+            for (target, hash) in includes {
+                fm_map.insert(format!("includes.{}", target), hash.clone());
+            }
+        }
+        if let Some(ref link) = self.frontmatter.supersedes {
+            fm_map.insert("supersedes.id".into(), link.id.to_string());
+            fm_map.insert("supersedes.version_hash".into(), link.version_hash.clone());
+        }
+
+        if let Some(ref sources) = self.frontmatter.sources {
+            for (name, decl) in sources {
+                let decl = serde_json::to_string(decl).map_err(unencodable)?;
+                fm_map.insert(format!("sources.{}", name), decl);
+            }
+        }
 
         // Extra fields are namespaced with "extra." prefix to prevent
         // collisions with standard frontmatter fields in the hash.
@@ -355,18 +1567,256 @@ impl TracedDocument {
             }
         }
 
-        let fm_json = serde_json::to_string(&fm_map).unwrap();
-        hasher.update(fm_json.as_bytes());
+        Ok(fm_map)
+    }
 
-        let result = hasher.finalize();
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&result);
-        hash
+    /// The frontmatter covered by a 2.x version hash, with typed values.
+    /// Extra fields live in their own `extra` object, so they can never
+    /// shadow a standard field.
+    fn hashed_object(&self) -> Result<serde_json::Map<String, serde_json::Value>> {
+        use serde_json::{Value, json};
+
+        let fm = &self.frontmatter;
+        let mut map = serde_json::Map::new();
+        map.insert("id".into(), fm.id.to_string().into());
+        map.insert("title".into(), fm.title.clone().into());
+        if let Some(ref author) = fm.author {
+            map.insert("author".into(), author.hashed_value()?);
+        }
+        if let Some(ref contributors) = fm.contributors {
+            let contributors = serde_json::to_value(contributors).map_err(unencodable)?;
+            map.insert("contributors".into(), contributors);
+        }
+        if let Some(ref pk) = fm.public_key {
+            map.insert("public_key".into(), pk.clone().into());
+        }
+        if let Some(ref uri) = fm.key_uri {
+            map.insert("key_uri".into(), uri.clone().into());
+        }
+        if let Some(scheme) = fm.signature_algorithm {
+            map.insert("signature_algorithm".into(), scheme.name().into());
+        }
+        let mut policy = json!({
+            "allow_include": fm.policy.allow_include,
+            "allow_quote": fm.policy.allow_quote,
+            "require_attribution": fm.policy.require_attribution,
+        });
+        if let Some(ref max_age) = fm.policy.max_trace_age {
+            policy["max_trace_age"] = max_age.clone().into();
+        }
+        if let Some(reviewers) = fm.policy.required_reviewers {
+            policy["required_reviewers"] = reviewers.into();
+        }
+        map.insert("policy".into(), policy);
+        map.insert("created_at".into(), fm.created_at.to_rfc3339().into());
+        if let Some(ref modified_at) = fm.modified_at {
+            map.insert("modified_at".into(), modified_at.to_rfc3339().into());
+        }
+        map.insert(
+            "doc_status".into(),
+            format!("{:?}", fm.doc_status).to_lowercase().into(),
+        );
+        map.insert(
+            "protocol_version".into(),
+            fm.protocol_version.clone().into(),
+        );
+        map.insert("doc_version".into(), fm.doc_version.into());
+        if let Some(ref prev_hash) = fm.prev_version_hash {
+            map.insert("prev_version_hash".into(), hex::encode(prev_hash).into());
+        }
+        if let Some(ref root) = fm.trace_root {
+            map.insert("trace_root".into(), root.clone().into());
+        }
+        if let Some(ref includes) = fm.includes {
+            let includes = serde_json::to_value(includes).map_err(unencodable)?;
+            map.insert("includes".into(), includes);
+        }
+        if let Some(ref link) = fm.supersedes {
+            let link = serde_json::to_value(link).map_err(unencodable)?;
+            map.insert("supersedes".into(), link);
+        }
+        if let Some(ref sources) = fm.sources {
+            let sources = serde_json::to_value(sources).map_err(unencodable)?;
+            map.insert("sources".into(), sources);
+        }
+        if let Some(ref extra) = fm.extra {
+            let extra: serde_json::Map<String, Value> = extra
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect();
+            map.insert("extra".into(), extra.into());
+        }
+        if canonicalization_profile(&fm.protocol_version) == CanonicalizationProfile::Nfc {
+            return nfc_members(map);
+        }
+        Ok(map)
+    }
+
+    /// Digest of each component that feeds the version hash: `body`, then
+    /// one entry per hashed frontmatter field.
+    pub fn component_digests(&self) -> Result<BTreeMap<String, String>> {
+        let mut digests = BTreeMap::new();
+        digests.insert(
+            "body".to_string(),
+            crate::crypto::hash_bytes(self.canonical_body().as_bytes()),
+        );
+
+        if hashing_scheme(&self.frontmatter.protocol_version) == HashingScheme::CanonicalJson {
+            let mut insert = |field: String, value: &serde_json::Value| {
+                let jcs = crate::canonical::to_jcs(value).unwrap_or_default();
+                digests.insert(field, crate::crypto::hash_bytes(jcs.as_bytes()));
+            };
+            // Nested objects (policy, extra) are reported per member
+            for (field, value) in self.hashed_object()? {
+                match value.as_object() {
+                    Some(members) => {
+                        for (member, v) in members {
+                            insert(format!("{}.{}", field, member), v);
+                        }
+                    }
+                    None => insert(field, &value),
+                }
+            }
+            return Ok(digests);
+        }
+
+        for (field, value) in self.hashed_fields()? {
+            digests.insert(field, crate::crypto::hash_bytes(value.as_bytes()));
+        }
+        Ok(digests)
+    }
+
+    /// Compare the current components against the manifest recorded at seal
+    /// time. Returns the components that changed, body first, or `None` when
+    /// the document has no manifest to compare against.
+    pub fn diagnose_tamper(&self) -> Option<Vec<String>> {
+        let recorded = self.frontmatter.hash_manifest.as_ref()?;
+        let current = self.component_digests().ok()?;
+
+        let mut changed: Vec<String> = recorded
+            .keys()
+            .chain(current.keys())
+            .filter(|k| recorded.get(*k) != current.get(*k))
+            .cloned()
+            .collect();
+        changed.sort_by_key(|k| (k != "body", k.clone()));
+        changed.dedup();
+        Some(changed)
     }
 
     /// Seal the document by computing the version hash and signing it.
     /// This sets the status to Published unless it is already Revoked.
-    pub fn seal(mut self, keypair: &crate::crypto::KeyPair) -> Self {
+    /// Under a file-hash seal mode the hash covers the text rendered from
+    /// `raw` (or from the document, if it was built in memory), which the
+    /// sealed document then carries as `raw`.
+    pub fn seal(mut self, keypair: &crate::crypto::KeyPair) -> Result<Self> {
+        // Ed25519 is not recorded, so such documents hash as they always did
+        self.frontmatter.signature_algorithm = None;
+        let original = match (self.frontmatter.seal_mode, &self.raw) {
+            (Some(SealMode::FileHash), Some(raw)) => Some(raw.to_string()),
+            (Some(SealMode::FileHash), None) => Some(crate::markdown::render_tmd(&self)?),
+            _ => None,
+        };
+        let mut doc = self.prepare_seal()?;
+        if let Some(hash) = doc.frontmatter.version_hash {
+            doc.frontmatter.signature = Some(keypair.sign(&hash).to_vec());
+        }
+        match original {
+            Some(original) => Ok(crate::markdown::render_sealed(&original, doc, keypair)?.0),
+            None => Ok(doc),
+        }
+    }
+
+    /// Seal a new version with `keypair` after the previous signing key was
+    /// compromised. The old public key and `reason` are recorded in `extra`
+    /// (`resealed_from`, `reseal_reason`) and the version chain continues.
+    /// The document must still verify under the old key, so that a version
+    /// tampered with since its seal is not signed anew.
+    pub fn reseal(self, keypair: &crate::crypto::KeyPair, reason: &str) -> Result<Self> {
+        let old_key = self.frontmatter.public_key.clone().ok_or_else(|| {
+            RhodiError::Verification(
+                "Document has no public key; only a signed document can be re-sealed".to_string(),
+            )
+        })?;
+        let old_key_bytes = hex::decode(&old_key)
+            .map_err(|e| RhodiError::Crypto(format!("Invalid public key: {}", e)))?;
+        self.verify_with_key(&old_key_bytes).map_err(|e| {
+            RhodiError::Verification(format!(
+                "Document does not verify under its current key, so it is not re-sealed: {}",
+                e
+            ))
+        })?;
+        let mut doc = self
+            .extra_info("resealed_from", &old_key)
+            .extra_info("reseal_reason", reason);
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        doc.seal(keypair)
+    }
+
+    /// Seal a new version that changes only metadata (title, author and
+    /// `extra` entries) of a published document, without a draft cycle. The
+    /// body, traces and sources stay as sealed, so the document must still
+    /// verify under `keypair`, the key it is sealed with. The changed fields and the reason are recorded in `extra`
+    /// (`amended_fields`, `amendment_reason`) and the version chain continues.
+    pub fn amend(self, amendment: &Amendment, keypair: &crate::crypto::KeyPair) -> Result<Self> {
+        let fm = &self.frontmatter;
+        if fm.doc_status != DocStatus::Published {
+            return Err(RhodiError::Verification(
+                "Only a published document can be amended; edit the draft and seal it instead"
+                    .to_string(),
+            ));
+        }
+        if fm.version_hash.is_none() || self.current_version_hash().ok() != fm.version_hash {
+            return Err(RhodiError::Verification(
+                "Document does not match its version hash; only an intact sealed version can be amended"
+                    .to_string(),
+            ));
+        }
+        if fm.public_key.as_deref() != Some(hex::encode(keypair.verifying_key.as_bytes()).as_str())
+        {
+            return Err(RhodiError::Crypto(
+                "Document is sealed with a different key; amend it with that key, or move it to a new one with reseal-all"
+                    .to_string(),
+            ));
+        }
+        // Only a version that was validly signed may be signed anew
+        self.verify(&keypair.verifying_key)?;
+        if amendment.reason.trim().is_empty() {
+            return Err(RhodiError::Format(
+                "An amendment needs a reason".to_string(),
+            ));
+        }
+        let changed = amendment.changed_fields(fm);
+        if changed.is_empty() {
+            return Err(RhodiError::Verification(
+                "The amendment changes nothing".to_string(),
+            ));
+        }
+
+        let mut doc = self;
+        if let Some(ref title) = amendment.title {
+            doc.frontmatter.title = title.clone();
+        }
+        if let Some(ref author) = amendment.author {
+            doc.frontmatter.author = Some(author.clone());
+        }
+        let mut extra = doc.frontmatter.extra.take().unwrap_or_default();
+        for (key, value) in &amendment.extra {
+            match value {
+                Some(value) => extra.insert(key.clone(), value.clone()),
+                None => extra.remove(key),
+            };
+        }
+        doc.frontmatter.extra = (!extra.is_empty()).then_some(extra);
+        doc.extra_info("amended_fields", &changed.join(", "))
+            .extra_info("amendment_reason", amendment.reason.trim())
+            .seal(keypair)
+    }
+
+    /// Perform every sealing step except signing: set the status, chain the
+    /// previous hash, bump the version and store the new `version_hash`.
+    /// The result can be signed elsewhere and finished with [`Self::complete_seal`].
+    pub fn prepare_seal(mut self) -> Result<Self> {
         if self.frontmatter.doc_status != DocStatus::Revoked {
             self.frontmatter.doc_status = DocStatus::Published;
         }
@@ -380,59 +1830,197 @@ impl TracedDocument {
         // Increment document version
         self.frontmatter.doc_version += 1;
 
-        let hash = self.compute_version_hash();
-        let signature = keypair.sign(&hash);
+        if crate::version::commits_redactions(&self.frontmatter.protocol_version) {
+            self.body = crate::redaction::salt_redactions(&self.body).into_owned();
+        }
+        self.frontmatter.signature = None;
+        self.frontmatter.notarizations = None;
+        self.frontmatter.superseded_by = None;
+        let leaves = self.trace_leaves()?;
+        self.frontmatter.trace_root = Some(crate::merkle::encode(&crate::merkle::root(&leaves)));
+        self.frontmatter.trace_tree = Some(leaves.iter().map(crate::merkle::encode).collect());
+        self.frontmatter.version_hash = Some(self.compute_version_hash()?);
+        self.frontmatter.hash_manifest = Some(self.component_digests()?);
+        self.frontmatter.environment =
+            Some(Environment::current(&self.frontmatter.protocol_version));
+        Ok(self)
+    }
+
+    /// Attach an externally produced signature to a document prepared with
+    /// [`Self::prepare_seal`]. The signature is checked against the embedded
+    /// `public_key` before it is accepted.
+    pub fn complete_seal(self, signature: Signature) -> Result<Self> {
+        self.attach_signature(signature.to_vec())
+    }
+
+    /// Like [`Self::complete_seal`], for a signature in any supported
+    /// `signature_algorithm`, such as an OpenPGP signature made by gpg.
+    pub fn attach_signature(mut self, signature: Vec<u8>) -> Result<Self> {
+        let pk_hex = self.frontmatter.public_key.as_deref().ok_or_else(|| {
+            RhodiError::Verification("Pending seal has no public_key to check against".to_string())
+        })?;
+        let public_key = crate::crypto::parse_public_key(pk_hex)?;
 
-        self.frontmatter.version_hash = Some(hash);
         self.frontmatter.signature = Some(signature);
-        self
+        self.verify(&public_key)?;
+        Ok(self)
     }
 
     /// Verify the document's integrity and authenticity.
     pub fn verify(&self, public_key: &ed25519_dalek::VerifyingKey) -> Result<()> {
-        // 1. Check protocol version status
-        let version = &self.frontmatter.protocol_version;
-        if !is_version_known(version) {
-            return Err(RhodiError::Verification(format!(
-                "Unknown protocol version: {}. Document may be from a future or obsolete version.",
-                version
-            )));
-        }
+        self.verify_with_key(public_key.as_bytes())
+    }
 
-        let status = get_version_status(version);
-        if status == VersionStatus::Obsolete {
-            return Err(RhodiError::Verification(format!(
-                "Protocol version {} is obsolete and no longer supported.",
-                version
-            )));
-        }
+    /// The algorithm the document is signed with.
+    pub fn signature_scheme(&self) -> SignatureScheme {
+        self.frontmatter.signature_algorithm.unwrap_or_default()
+    }
+
+    /// Verify the document against a raw public key of its
+    /// `signature_algorithm`. Only built-in protocol versions are accepted;
+    /// see [`Self::verify_in`].
+    pub fn verify_with_key(&self, public_key: &[u8]) -> Result<()> {
+        self.verify_in(public_key, &VersionRegistry::default())
+    }
+
+    /// [`Self::verify_with_key`], accepting the protocol versions
+    /// `versions` knows, such as a workspace's.
+    pub fn verify_in(&self, public_key: &[u8], versions: &VersionRegistry) -> Result<()> {
+        // 1. Check protocol version status
+        versions.check_supported(&self.frontmatter.protocol_version)?;
 
         // 2. Check if the document is sealed
         let stored_hash = self.frontmatter.version_hash.ok_or_else(|| {
             RhodiError::Verification("Document is not sealed (missing version_hash)".to_string())
         })?;
-        let signature = self.frontmatter.signature.ok_or_else(|| {
+        let signature = self.frontmatter.signature.as_deref().ok_or_else(|| {
             RhodiError::Verification("Document is not signed (missing signature)".to_string())
         })?;
 
         // 3. Re-compute the hash and compare
-        let computed_hash = self.compute_version_hash();
+        let computed_hash = self.current_version_hash()?;
         if computed_hash != stored_hash {
             return Err(RhodiError::Verification(
                 "Integrity check failed: version_hash mismatch".to_string(),
             ));
         }
 
-        // 4. Verify the signature
-        public_key
-            .verify_strict(&computed_hash, &signature)
-            .map_err(|e| RhodiError::Crypto(format!("Authenticity check failed: {}", e)))?;
+        // 4. Verify the signature with the recorded algorithm
+        self.signature_scheme()
+            .verify(public_key, &computed_hash, signature)?;
+
+        // 5. The committed trace root, and the unhashed leaf list kept
+        // beside it, must match the traces in the body
+        if self.frontmatter.trace_root.is_none() && self.frontmatter.trace_tree.is_none() {
+            return Ok(());
+        }
+        let leaves = self.trace_leaves()?;
+        if let Some(ref recorded) = self.frontmatter.trace_root {
+            let computed = crate::merkle::encode(&crate::merkle::root(&leaves));
+            if &computed != recorded {
+                return Err(RhodiError::Verification(
+                    "Integrity check failed: trace_root does not match the document's traces"
+                        .to_string(),
+                ));
+            }
+        }
+        if let Some(ref tree) = self.frontmatter.trace_tree {
+            let computed: Vec<String> = leaves.iter().map(crate::merkle::encode).collect();
+            if self.frontmatter.trace_root.is_none() || tree != &computed {
+                return Err(RhodiError::Verification(
+                    "Integrity check failed: trace_tree does not rebuild the document's trace_root"
+                        .to_string(),
+                ));
+            }
+        }
 
         Ok(())
     }
 
+    /// Merkle leaf hash of every trace block, in body order. Fails if a
+    /// trace cannot be hashed, rather than leave it out of the tree.
+    pub fn trace_leaves(&self) -> Result<Vec<crate::merkle::Hash>> {
+        crate::markdown::parse_tmd_sections(&self.body)
+            .iter()
+            .filter_map(|section| match section {
+                crate::markdown::Section::Trace(trace) => Some(crate::merkle::trace_leaf(trace)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Prove that the trace at `index` (in body order) is part of this
+    /// version's `trace_root`, without revealing the other traces.
+    pub fn prove_trace(&self, index: usize) -> Result<crate::merkle::InclusionProof> {
+        crate::merkle::inclusion_proof(&self.trace_leaves()?, index)
+    }
+
+    /// Upgrade the document to protocol version `to`, applying each migration
+    /// step in order. Returns the migrated document and a description of
+    /// every change. A sealed document loses its seal and must be re-sealed;
+    /// its old version hash is kept as `prev_version_hash`.
+    pub fn migrate(mut self, to: &str) -> Result<(Self, Vec<String>)> {
+        let from = self.frontmatter.protocol_version.clone();
+        let steps = migration_path(&from, to)?;
+        let mut changes = Vec::new();
+        if steps.is_empty() {
+            return Ok((self, changes));
+        }
+
+        for step in &steps {
+            changes.push(format!("{} -> {}: {}", step.from, step.to, step.summary));
+            self.apply_migration(step, &mut changes);
+        }
+        self.frontmatter.protocol_version = to.to_string();
+        changes.push(format!("protocol_version: {} -> {}", from, to));
+
+        if let Some(old_hash) = self.frontmatter.version_hash.take() {
+            self.frontmatter.prev_version_hash = Some(old_hash);
+            self.frontmatter.signature = None;
+            self.frontmatter.hash_manifest = None;
+            self.frontmatter.environment = None;
+            self.frontmatter.trace_root = None;
+            self.frontmatter.trace_tree = None;
+            self.frontmatter.notarizations = None;
+            self.frontmatter.superseded_by = None;
+            changes.push("version_hash, signature: removed; re-seal required".to_string());
+        }
+        if self.frontmatter.doc_status == DocStatus::Published {
+            self.frontmatter.doc_status = DocStatus::Draft;
+            changes.push("doc_status: published -> draft".to_string());
+        }
+
+        Ok((self, changes))
+    }
+
+    fn apply_migration(&mut self, step: &Migration, changes: &mut Vec<String>) {
+        // Additive steps have nothing to rewrite
+        if matches!((step.from, step.to), ("1.1", "2.0") | ("2.0", "2.1")) {
+            let canonical = crate::markdown::canonicalize_text_for(&self.body, step.to);
+            let canonical = canonical.trim_end_matches('\n');
+            if canonical != self.body {
+                self.body = canonical.to_string();
+                changes.push("body: re-canonicalized".to_string());
+            }
+        }
+    }
+
     /// Update all trace blocks in the document body with current source hashes.
     pub fn update_all_traces(&mut self, base_path: &Path) -> Result<()> {
+        // Shared sources are hashed once, in the frontmatter
+        if let Some(ref mut sources) = self.frontmatter.sources {
+            for decl in sources.values_mut() {
+                if decl.resolver.is_none() && !decl.path.contains("://") {
+                    let mut probe = TraceBlock {
+                        source: decl.path.clone(),
+                        ..Default::default()
+                    };
+                    probe.update_hash(base_path)?;
+                    decl.hash = probe.hash;
+                }
+            }
+        }
+
         let sections = crate::markdown::parse_tmd_sections(&self.body);
         let mut new_body = String::new();
 
@@ -442,7 +2030,17 @@ impl TracedDocument {
                     new_body.push_str(&p);
                 }
                 crate::markdown::Section::Trace(mut t) => {
-                    t.update_hash(base_path)?;
+                    if t.source_ref().is_none() {
+                        t.update_hash(base_path)?;
+                    } else if t.normalize.is_some() || t.compression.is_some() {
+                        // A shared source's digest covers the raw content, so a
+                        // normalizing or decompressing trace pins its own
+                        let mut resolved = self.frontmatter.resolve_trace(&t)?;
+                        if !resolved.source.contains("://") {
+                            resolved.update_hash(base_path)?;
+                            t.hash = resolved.hash;
+                        }
+                    }
                     new_body.push_str("```trace\n");
                     let yaml = serde_norway::to_string(&t).map_err(|e| {
                         RhodiError::Serialization(format!("Failed to serialize trace: {}", e))
@@ -450,13 +2048,102 @@ impl TracedDocument {
                     new_body.push_str(&yaml);
                     new_body.push_str("```\n");
                 }
-                crate::markdown::Section::Include(i) => {
+                crate::markdown::Section::Include(i)
+                | crate::markdown::Section::Custom { content: i, .. } => {
                     new_body.push_str(&i);
                 }
+                crate::markdown::Section::Quote(mut q) => {
+                    if q.excerpt_hash.is_none() {
+                        q.excerpt_hash = Some(q.compute_excerpt_hash());
+                    }
+                    new_body.push_str("```quote\n");
+                    let yaml = serde_norway::to_string(&q).map_err(|e| {
+                        RhodiError::Serialization(format!("Failed to serialize quote: {}", e))
+                    })?;
+                    new_body.push_str(&yaml);
+                    new_body.push_str("```\n");
+                }
             }
         }
 
         self.body = new_body;
         Ok(())
     }
+
+    /// Give every trace without an `id` the next free `t-NNN` id, in
+    /// document order. Returns how many traces were numbered.
+    pub fn assign_trace_ids(&mut self) -> Result<usize> {
+        let mut next = crate::markdown::parse_tmd_sections(&self.body)
+            .iter()
+            .filter_map(|section| match section {
+                crate::markdown::Section::Trace(trace) => trace.id.as_deref(),
+                _ => None,
+            })
+            .filter_map(|id| id.strip_prefix("t-")?.parse::<usize>().ok())
+            .max()
+            .unwrap_or(0);
+        let mut assigned = 0;
+        self.body = crate::markdown::update_trace_blocks(&self.body, |trace| {
+            if trace.id.is_some() {
+                return Ok(None);
+            }
+            next += 1;
+            assigned += 1;
+            let mut fields = serde_norway::Mapping::new();
+            fields.insert("id".into(), format!("t-{:03}", next).into());
+            Ok(Some(serde_norway::Value::Mapping(fields)))
+        })?;
+        Ok(assigned)
+    }
+
+    /// Write each trace's preset into the trace: the fields it takes from
+    /// the preset are set on the block and `preset` is removed, so the
+    /// sealed trace no longer depends on the workspace's `rhodi.toml`.
+    /// Returns how many traces were expanded.
+    pub fn expand_presets(&mut self, presets: &BTreeMap<String, TracePreset>) -> Result<usize> {
+        let to_yaml = |trace: &TraceBlock| {
+            serde_norway::to_value(trace)
+                .map_err(|e| RhodiError::Serialization(format!("Failed to serialize trace: {}", e)))
+        };
+        let mut expanded = 0;
+        self.body = crate::markdown::update_trace_blocks(&self.body, |trace| {
+            if trace.preset.is_none() {
+                return Ok(None);
+            }
+            let before = to_yaml(trace)?;
+            let after = to_yaml(&trace.expand_preset(presets)?)?;
+            let mut fields = serde_norway::Mapping::new();
+            for (key, value) in after.as_mapping().into_iter().flatten() {
+                if before.get(key) != Some(value) {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+            fields.insert("preset".into(), serde_norway::Value::Null);
+            expanded += 1;
+            Ok(Some(serde_norway::Value::Mapping(fields)))
+        })?;
+        Ok(expanded)
+    }
+
+    /// Record an `anchor` on every trace that has none, binding it to the
+    /// sentence (or heading) it currently follows. Returns how many traces
+    /// were anchored.
+    pub fn anchor_traces(&mut self) -> Result<usize> {
+        let sections = crate::markdown::parse_tmd_sections(&self.body);
+        let mut anchors = crate::markdown::trace_anchors(&sections).into_iter();
+        let mut anchored = 0;
+        self.body = crate::markdown::update_trace_blocks(&self.body, |trace| {
+            let suggestion = anchors.next().and_then(|position| position.suggest());
+            match suggestion {
+                Some(anchor) if trace.anchor.is_none() => {
+                    anchored += 1;
+                    let mut fields = serde_norway::Mapping::new();
+                    fields.insert("anchor".into(), anchor.into());
+                    Ok(Some(serde_norway::Value::Mapping(fields)))
+                }
+                _ => Ok(None),
+            }
+        })?;
+        Ok(anchored)
+    }
 }
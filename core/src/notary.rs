@@ -0,0 +1,182 @@
+//! Third-party counter-signatures on sealed documents.
+//!
+//! A notary verifies a sealed document and appends a [`Notarization`] to its
+//! `notarizations` frontmatter list. Each notarization signs the document's
+//! version hash, the author's signature and the previous notarization's
+//! signature, so the list forms a chain: altering or removing an entry breaks
+//! every entry after it. Notarizations are not covered by the version hash,
+//! so adding one does not disturb the author's seal.
+//!
+//! A listed `reviewer` contributor signs off on a version by notarizing it
+//! with their key; `policy.required_reviewers` makes a number of such
+//! sign-offs a condition of a valid published document.
+
+use crate::crypto::{KeyPair, parse_public_key, parse_signature};
+use crate::error::{Result, RhodiError};
+use crate::models::{Contributor, ContributorRole, DocStatus, TracedDocument};
+use crate::version::VersionRegistry;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator so notary signatures can never be confused with seals
+/// or attestations.
+const NOTARIZATION_CONTEXT: &str = "rhodi-notarization-v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Notarization {
+    /// Hex-encoded Ed25519 public key of the notary
+    pub notary: String,
+    /// Display name of the notary, covered by the signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub notarized_at: DateTime<Utc>,
+    /// Hex-encoded Ed25519 signature over the chained statement
+    pub signature: String,
+}
+
+impl Notarization {
+    /// The exact bytes covered by the signature. `previous` is the signature
+    /// of the preceding notarization, empty for the first one.
+    fn message(&self, doc: &TracedDocument, previous: &str) -> Result<Vec<u8>> {
+        let fm = &doc.frontmatter;
+        let (Some(version_hash), Some(signature)) = (fm.version_hash, &fm.signature) else {
+            return Err(RhodiError::Verification(
+                "Only sealed and signed documents can be notarized".into(),
+            ));
+        };
+        Ok(format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            NOTARIZATION_CONTEXT,
+            fm.id,
+            hex::encode(version_hash),
+            hex::encode(signature),
+            previous,
+            self.notarized_at
+                .to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.notary,
+            self.name.as_deref().unwrap_or_default()
+        )
+        .into_bytes())
+    }
+}
+
+/// Append a notarization by `keypair` to `doc`. The document's own seal is
+/// checked first, under the protocol versions in `versions`; a notary never
+/// vouches for a document that fails it.
+pub fn notarize(
+    mut doc: TracedDocument,
+    keypair: &KeyPair,
+    name: Option<&str>,
+    versions: &VersionRegistry,
+) -> Result<TracedDocument> {
+    let pk_hex = doc.frontmatter.public_key.as_deref().ok_or_else(|| {
+        RhodiError::Verification("Document has no public_key to verify before notarizing".into())
+    })?;
+    doc.verify_in(parse_public_key(pk_hex)?.as_bytes(), versions)?;
+    verify_chain(&doc)?;
+
+    let previous = doc
+        .frontmatter
+        .notarizations
+        .as_ref()
+        .and_then(|list| list.last())
+        .map(|n| n.signature.clone())
+        .unwrap_or_default();
+    let mut notarization = Notarization {
+        notary: hex::encode(keypair.verifying_key.as_bytes()),
+        name: name.map(str::to_string),
+        notarized_at: Utc::now(),
+        signature: String::new(),
+    };
+    let message = notarization.message(&doc, &previous)?;
+    notarization.signature = hex::encode(keypair.sign(&message).to_bytes());
+
+    doc.frontmatter
+        .notarizations
+        .get_or_insert_with(Vec::new)
+        .push(notarization);
+    Ok(doc)
+}
+
+/// Check every notarization in order. A document without notarizations
+/// passes.
+pub fn verify_chain(doc: &TracedDocument) -> Result<()> {
+    let Some(list) = &doc.frontmatter.notarizations else {
+        return Ok(());
+    };
+    let mut previous = String::new();
+    for (i, notarization) in list.iter().enumerate() {
+        let public_key = parse_public_key(&notarization.notary)?;
+        let signature = parse_signature(&notarization.signature)?;
+        public_key
+            .verify_strict(&notarization.message(doc, &previous)?, &signature)
+            .map_err(|e| {
+                RhodiError::Crypto(format!(
+                    "Notarization {} by {} is invalid: {}",
+                    i + 1,
+                    notarization
+                        .name
+                        .as_deref()
+                        .unwrap_or(notarization.notary.as_str()),
+                    e
+                ))
+            })?;
+        previous = notarization.signature.clone();
+    }
+    Ok(())
+}
+
+/// Listed reviewers who can sign off on `doc`: those with a key, other
+/// than the key that sealed it.
+pub fn eligible_reviewers(doc: &TracedDocument) -> Vec<&Contributor> {
+    let signer = doc.frontmatter.public_key.as_deref().unwrap_or_default();
+    let mut reviewers: Vec<&Contributor> = Vec::new();
+    for contributor in doc.frontmatter.contributors.iter().flatten() {
+        if contributor.role == ContributorRole::Reviewer
+            && let Some(key) = contributor.key.as_deref()
+            && !key.eq_ignore_ascii_case(signer)
+            && !reviewers.iter().any(|r| {
+                r.key
+                    .as_deref()
+                    .is_some_and(|k| k.eq_ignore_ascii_case(key))
+            })
+        {
+            reviewers.push(contributor);
+        }
+    }
+    reviewers
+}
+
+/// Eligible reviewers who have notarized `doc`.
+pub fn reviewer_signatures(doc: &TracedDocument) -> Vec<&Contributor> {
+    let notarizations = doc.frontmatter.notarizations.as_deref().unwrap_or_default();
+    eligible_reviewers(doc)
+        .into_iter()
+        .filter(|reviewer| {
+            let key = reviewer.key.as_deref().unwrap_or_default();
+            notarizations
+                .iter()
+                .any(|n| n.notary.eq_ignore_ascii_case(key))
+        })
+        .collect()
+}
+
+/// Check `policy.required_reviewers`: a published document needs that many
+/// listed reviewers to have notarized it. The notarizations themselves are
+/// checked by [`verify_chain`].
+pub fn check_reviews(doc: &TracedDocument) -> Result<()> {
+    let Some(required) = doc.frontmatter.policy.required_reviewers else {
+        return Ok(());
+    };
+    if doc.frontmatter.doc_status != DocStatus::Published {
+        return Ok(());
+    }
+    let signed = reviewer_signatures(doc).len();
+    if signed < required as usize {
+        return Err(RhodiError::Verification(format!(
+            "Review policy requires {} reviewer signature(s), found {}; listed reviewers sign off with 'rhodi notarize'",
+            required, signed
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,373 @@
+//! OpenPGP signatures, for sealing under an existing GnuPG key.
+//!
+//! Institutions with an established PGP identity can seal documents with
+//! it: `rhodi seal --gpg <key>` has `gpg` sign the version hash with the
+//! key, which must be Ed25519, and stores the binary OpenPGP signature with
+//! `signature_algorithm: openpgp-ed25519`. The key's raw Ed25519 point is
+//! recorded as `public_key` like any other, so verifying needs neither gpg
+//! nor a keyring: [`verify`] checks the v4 signature packet (RFC 4880
+//! §5.2.4) against it. Both the legacy EdDSA and the RFC 9580 Ed25519 key
+//! formats are understood.
+
+use crate::error::{Result, RhodiError};
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Environment variable naming the gpg executable, `gpg` by default.
+pub const GPG_PROGRAM_ENV: &str = "RHODI_GPG";
+
+const TAG_SIGNATURE: u8 = 2;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+
+/// Public-key algorithms: EdDSA with a curve OID, and native Ed25519
+const ALGO_EDDSA_LEGACY: u8 = 22;
+const ALGO_ED25519: u8 = 27;
+
+/// OID of the Ed25519 curve in legacy EdDSA keys
+const ED25519_OID: &[u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0xDA, 0x47, 0x0F, 0x01];
+
+/// Signature over a binary document
+const SIG_BINARY: u8 = 0x00;
+
+/// Issuer fingerprint subpacket
+const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+
+fn invalid(reason: &str) -> RhodiError {
+    RhodiError::Crypto(format!("Invalid OpenPGP data: {}", reason))
+}
+
+/// Split OpenPGP data into its packets, as (tag, body) pairs.
+fn packets(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut packets = Vec::new();
+    while let Some((&ctb, rest)) = data.split_first() {
+        if ctb & 0x80 == 0 {
+            return Err(invalid("not a packet header"));
+        }
+        let (tag, len, rest) = if ctb & 0x40 != 0 {
+            let (len, rest) = match rest {
+                [a, rest @ ..] if *a < 192 => (*a as usize, rest),
+                [a, b, rest @ ..] if *a < 224 => {
+                    (((*a as usize - 192) << 8) + *b as usize + 192, rest)
+                }
+                [255, a, b, c, d, rest @ ..] => {
+                    (u32::from_be_bytes([*a, *b, *c, *d]) as usize, rest)
+                }
+                _ => return Err(invalid("unsupported packet length")),
+            };
+            (ctb & 0x3F, len, rest)
+        } else {
+            let (len, rest) = match (ctb & 0x03, rest) {
+                (0, [a, rest @ ..]) => (*a as usize, rest),
+                (1, [a, b, rest @ ..]) => (u16::from_be_bytes([*a, *b]) as usize, rest),
+                (2, [a, b, c, d, rest @ ..]) => {
+                    (u32::from_be_bytes([*a, *b, *c, *d]) as usize, rest)
+                }
+                _ => return Err(invalid("unsupported packet length")),
+            };
+            ((ctb >> 2) & 0x0F, len, rest)
+        };
+        if rest.len() < len {
+            return Err(invalid("truncated packet"));
+        }
+        packets.push((tag, &rest[..len]));
+        data = &rest[len..];
+    }
+    Ok(packets)
+}
+
+/// Take `n` bytes off the front of `data`.
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if data.len() < n {
+        return Err(invalid("truncated packet"));
+    }
+    let (head, rest) = data.split_at(n);
+    *data = rest;
+    Ok(head)
+}
+
+fn take_u16(data: &mut &[u8]) -> Result<usize> {
+    let bytes = take(data, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+/// A multiprecision integer, without its bit count.
+fn take_mpi<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let bits = take_u16(data)?;
+    take(data, bits.div_ceil(8))
+}
+
+/// Left-pad a big-endian integer to 32 bytes.
+fn pad32(bytes: &[u8]) -> Result<[u8; 32]> {
+    let mut out = [0u8; 32];
+    let start = 32usize
+        .checked_sub(bytes.len())
+        .ok_or_else(|| invalid("integer longer than 32 bytes"))?;
+    out[start..].copy_from_slice(bytes);
+    Ok(out)
+}
+
+/// A v4 signature packet over a binary document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignaturePacket {
+    hash_algorithm: u8,
+    /// Version through hashed subpackets: the part the signature covers
+    hashed: Vec<u8>,
+    /// First two bytes of the signed digest
+    digest_prefix: [u8; 2],
+    signature: [u8; 64],
+    /// Hex fingerprint of the signing key, if the packet names it
+    pub issuer_fingerprint: Option<String>,
+}
+
+impl SignaturePacket {
+    /// Parse the single Ed25519 signature packet of `data`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let packets = packets(data)?;
+        let [(TAG_SIGNATURE, body)] = packets.as_slice() else {
+            return Err(invalid("expected a single signature packet"));
+        };
+        let mut rest = *body;
+        let header = take(&mut rest, 4)?;
+        let (version, sig_type, algorithm, hash_algorithm) =
+            (header[0], header[1], header[2], header[3]);
+        if version != 4 {
+            return Err(invalid(&format!(
+                "unsupported signature version {}",
+                version
+            )));
+        }
+        if sig_type != SIG_BINARY {
+            return Err(invalid("not a signature over a binary document"));
+        }
+        let hashed_len = take_u16(&mut rest)?;
+        let subpackets = take(&mut rest, hashed_len)?;
+        let hashed = body[..6 + hashed_len].to_vec();
+        let unhashed_len = take_u16(&mut rest)?;
+        take(&mut rest, unhashed_len)?;
+        let prefix = take(&mut rest, 2)?;
+        let signature = match algorithm {
+            ALGO_EDDSA_LEGACY => {
+                let r = pad32(take_mpi(&mut rest)?)?;
+                let s = pad32(take_mpi(&mut rest)?)?;
+                let mut signature = [0u8; 64];
+                signature[..32].copy_from_slice(&r);
+                signature[32..].copy_from_slice(&s);
+                signature
+            }
+            ALGO_ED25519 => take(&mut rest, 64)?
+                .try_into()
+                .map_err(|_| invalid("truncated Ed25519 signature"))?,
+            other => {
+                return Err(RhodiError::Crypto(format!(
+                    "OpenPGP signature uses public-key algorithm {}, not Ed25519",
+                    other
+                )));
+            }
+        };
+
+        Ok(Self {
+            hash_algorithm,
+            hashed,
+            digest_prefix: [prefix[0], prefix[1]],
+            signature,
+            issuer_fingerprint: issuer_fingerprint(subpackets),
+        })
+    }
+
+    /// The digest the signature signs: `message` followed by the hashed
+    /// part of the packet and its trailer.
+    fn digest(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut trailer = vec![0x04, 0xFF];
+        trailer.extend_from_slice(&(self.hashed.len() as u32).to_be_bytes());
+        let parts = [message, &self.hashed, &trailer];
+        Ok(match self.hash_algorithm {
+            8 => hash::<Sha256>(&parts),
+            9 => hash::<Sha384>(&parts),
+            10 => hash::<Sha512>(&parts),
+            11 => hash::<Sha224>(&parts),
+            other => {
+                return Err(RhodiError::Crypto(format!(
+                    "Unsupported OpenPGP hash algorithm {}",
+                    other
+                )));
+            }
+        })
+    }
+}
+
+fn hash<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = D::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// The issuer fingerprint among hashed signature subpackets.
+fn issuer_fingerprint(mut subpackets: &[u8]) -> Option<String> {
+    while let Some((&first, rest)) = subpackets.split_first() {
+        let (len, rest) = match (first, rest) {
+            (a, rest) if a < 192 => (a as usize, rest),
+            (a, [b, rest @ ..]) if a < 255 => (((a as usize - 192) << 8) + *b as usize + 192, rest),
+            (255, [a, b, c, d, rest @ ..]) => (u32::from_be_bytes([*a, *b, *c, *d]) as usize, rest),
+            _ => return None,
+        };
+        let subpacket = rest.get(..len)?;
+        if let [kind, _version, fingerprint @ ..] = subpacket
+            && kind & 0x7F == SUBPACKET_ISSUER_FINGERPRINT
+        {
+            return Some(hex::encode_upper(fingerprint));
+        }
+        subpackets = &rest[len..];
+    }
+    None
+}
+
+/// Verify the binary OpenPGP signature `signature` over `message` with a
+/// raw Ed25519 public key.
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    let public_key: [u8; 32] = public_key
+        .try_into()
+        .map_err(|_| RhodiError::Crypto("Invalid public key length: expected 32 bytes".into()))?;
+    let public_key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| RhodiError::Crypto(format!("Invalid public key format: {}", e)))?;
+    let packet = SignaturePacket::parse(signature)?;
+    let digest = packet.digest(message)?;
+    if digest[..2] != packet.digest_prefix {
+        return Err(RhodiError::Crypto(
+            "Authenticity check failed: OpenPGP signature is not over this message".into(),
+        ));
+    }
+    public_key
+        .verify_strict(&digest, &Signature::from_bytes(&packet.signature))
+        .map_err(|e| RhodiError::Crypto(format!("Authenticity check failed: {}", e)))
+}
+
+/// The Ed25519 keys and subkeys of an exported (binary) OpenPGP key, as
+/// raw public keys.
+pub fn ed25519_keys(export: &[u8]) -> Result<Vec<[u8; 32]>> {
+    let mut keys = Vec::new();
+    for (tag, body) in packets(export)? {
+        if tag != TAG_PUBLIC_KEY && tag != TAG_PUBLIC_SUBKEY {
+            continue;
+        }
+        let mut rest = body;
+        let header = take(&mut rest, 6)?;
+        if header[0] != 4 {
+            continue;
+        }
+        match header[5] {
+            ALGO_EDDSA_LEGACY => {
+                let oid_len = take(&mut rest, 1)?[0] as usize;
+                if take(&mut rest, oid_len)? != ED25519_OID {
+                    continue;
+                }
+                // The point is prefixed with 0x40 for its native encoding
+                if let [0x40, point @ ..] = take_mpi(&mut rest)? {
+                    keys.push(pad32(point)?);
+                }
+            }
+            ALGO_ED25519 => keys.push(
+                take(&mut rest, 32)?
+                    .try_into()
+                    .map_err(|_| invalid("truncated Ed25519 key"))?,
+            ),
+            _ => {}
+        }
+    }
+    Ok(keys)
+}
+
+/// Signs with a key in the user's GnuPG keyring by running `gpg`.
+pub struct GpgSigner {
+    program: String,
+    key: String,
+}
+
+impl GpgSigner {
+    /// Sign with `key`, a key ID, fingerprint or user ID gpg knows.
+    pub fn new(key: &str) -> Self {
+        Self {
+            program: "gpg".to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    /// Sign with `key`, running the gpg named by `RHODI_GPG` if it is set.
+    pub fn from_env(key: &str) -> Self {
+        let signer = Self::new(key);
+        match std::env::var(GPG_PROGRAM_ENV) {
+            Ok(program) if !program.trim().is_empty() => signer.with_program(&program),
+            _ => signer,
+        }
+    }
+
+    /// Run gpg with `args`, feeding it `input`, and return what it printed.
+    fn run(&self, args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+        let failed = |e: std::io::Error| {
+            RhodiError::Crypto(format!("Running '{}' failed: {}", self.program, e))
+        };
+        let mut child = Command::new(&self.program)
+            .arg("--batch")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+        let mut stdin = child.stdin.take().ok_or_else(|| {
+            RhodiError::Crypto(format!("Running '{}' failed: no stdin", self.program))
+        })?;
+        stdin.write_all(input).map_err(failed)?;
+        drop(stdin);
+        let output = child.wait_with_output().map_err(failed)?;
+        if !output.status.success() {
+            return Err(RhodiError::Crypto(format!(
+                "gpg failed for key '{}': {}",
+                self.key,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+
+    /// A binary detached signature over `message`.
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        self.run(
+            &["--no-armor", "--local-user", &self.key, "--detach-sign"],
+            message,
+        )
+    }
+
+    /// The raw public key gpg signs with for this key. A key may have
+    /// several Ed25519 subkeys, so a probe message is signed and checked
+    /// against each of them.
+    pub fn public_key(&self) -> Result<[u8; 32]> {
+        let export = self.run(&["--export", &self.key], &[])?;
+        let keys = ed25519_keys(&export)?;
+        if keys.is_empty() {
+            return Err(RhodiError::Crypto(format!(
+                "gpg key '{}' has no Ed25519 key to sign with",
+                self.key
+            )));
+        }
+        let probe = b"rhodi: which key signs";
+        let signature = self.sign(probe)?;
+        keys.into_iter()
+            .find(|key| verify(key, probe, &signature).is_ok())
+            .ok_or_else(|| {
+                RhodiError::Crypto(format!(
+                    "gpg signs for '{}' with a key that is not Ed25519",
+                    self.key
+                ))
+            })
+    }
+}
@@ -0,0 +1,631 @@
+//! OpenTimestamps proofs of a version's existence.
+//!
+//! `rhodi seal --timestamp` submits the version hash to OpenTimestamps
+//! calendars and writes the detached proof they return next to the
+//! document, as `<document>.ots` in the format of the `ots` client
+//! (<https://github.com/opentimestamps/python-opentimestamps>). A fresh
+//! proof is *pending*: the calendars commit to the hash at once but anchor
+//! it in a Bitcoin block only after a few hours. `rhodi verify
+//! --check-timestamp` asks the calendars for the completed proof, replays
+//! its operations from the version hash to the Bitcoin attestation and
+//! compares the result with the merkle root of the attested block, read
+//! from a block explorer. No server of one's own is involved, and the proof
+//! shows the version existed no later than that block.
+
+use crate::error::{Result, RhodiError};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Public calendars used when the workspace names none.
+pub const DEFAULT_CALENDARS: [&str; 3] = [
+    "https://a.pool.opentimestamps.org",
+    "https://b.pool.opentimestamps.org",
+    "https://a.pool.eternitywall.com",
+];
+
+/// Esplora API used to look up Bitcoin block headers by default.
+pub const DEFAULT_EXPLORER: &str = "https://blockstream.info/api";
+
+const HEADER_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const MAJOR_VERSION: u64 = 1;
+
+const TAG_ATTESTATION: u8 = 0x00;
+const TAG_FORK: u8 = 0xff;
+const OP_SHA256: u8 = 0x08;
+const OP_APPEND: u8 = 0xf0;
+const OP_PREPEND: u8 = 0xf1;
+const OP_REVERSE: u8 = 0xf2;
+const OP_HEXLIFY: u8 = 0xf3;
+
+const PENDING_TAG: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+const BITCOIN_TAG: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+
+/// Bounds on what a proof may contain, as in the reference client
+const MAX_MESSAGE_LENGTH: usize = 4096;
+const MAX_DEPTH: usize = 256;
+
+const CALENDAR_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn invalid(reason: &str) -> RhodiError {
+    RhodiError::Format(format!("Invalid OpenTimestamps proof: {}", reason))
+}
+
+/// Path of the proof written for the document at `path`.
+pub fn proof_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".ots");
+    PathBuf::from(name)
+}
+
+/// An operation on the message, from the digest towards an attestation.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Op {
+    Sha256,
+    Append(Vec<u8>),
+    Prepend(Vec<u8>),
+    Reverse,
+    Hexlify,
+}
+
+impl Op {
+    fn apply(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        let result = match self {
+            Self::Sha256 => Sha256::digest(msg).to_vec(),
+            Self::Append(suffix) => [msg, suffix].concat(),
+            Self::Prepend(prefix) => [prefix, msg].concat(),
+            Self::Reverse => msg.iter().rev().copied().collect(),
+            Self::Hexlify => hex::encode(msg).into_bytes(),
+        };
+        if result.len() > MAX_MESSAGE_LENGTH {
+            return Err(invalid("message too long"));
+        }
+        Ok(result)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Sha256 => out.push(OP_SHA256),
+            Self::Append(arg) => {
+                out.push(OP_APPEND);
+                write_bytes(out, arg);
+            }
+            Self::Prepend(arg) => {
+                out.push(OP_PREPEND);
+                write_bytes(out, arg);
+            }
+            Self::Reverse => out.push(OP_REVERSE),
+            Self::Hexlify => out.push(OP_HEXLIFY),
+        }
+    }
+}
+
+/// What a calendar or the Bitcoin blockchain says about a message.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Attestation {
+    /// The calendar at `uri` will anchor the message in a later block
+    Pending {
+        uri: String,
+    },
+    /// The message is the merkle root of the Bitcoin block at `height`
+    Bitcoin {
+        height: u64,
+    },
+    Unknown {
+        tag: [u8; 8],
+        payload: Vec<u8>,
+    },
+}
+
+impl Attestation {
+    fn write(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        let tag = match self {
+            Self::Pending { uri } => {
+                write_bytes(&mut payload, uri.as_bytes());
+                PENDING_TAG
+            }
+            Self::Bitcoin { height } => {
+                write_varuint(&mut payload, *height);
+                BITCOIN_TAG
+            }
+            Self::Unknown { tag, payload: raw } => {
+                payload.extend_from_slice(raw);
+                *tag
+            }
+        };
+        out.extend_from_slice(&tag);
+        write_bytes(out, &payload);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let tag: [u8; 8] = reader
+            .take(8)?
+            .try_into()
+            .map_err(|_| invalid("truncated attestation tag"))?;
+        let payload = reader.bytes()?;
+        let mut inner = Reader(payload);
+        Ok(match tag {
+            PENDING_TAG => Self::Pending {
+                uri: String::from_utf8(inner.bytes()?.to_vec())
+                    .map_err(|_| invalid("calendar URI is not UTF-8"))?,
+            },
+            BITCOIN_TAG => Self::Bitcoin {
+                height: inner.varuint()?,
+            },
+            _ => Self::Unknown {
+                tag,
+                payload: payload.to_vec(),
+            },
+        })
+    }
+}
+
+/// A tree of operations on a message, each branch ending in attestations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timestamp {
+    pub attestations: Vec<Attestation>,
+    pub ops: Vec<(Op, Timestamp)>,
+}
+
+impl Timestamp {
+    /// Parse the serialized timestamp of `msg`.
+    pub fn parse(data: &[u8], msg: &[u8]) -> Result<Self> {
+        let mut reader = Reader(data);
+        let timestamp = Self::read(&mut reader, msg, 0)?;
+        if !reader.0.is_empty() {
+            return Err(invalid("trailing data"));
+        }
+        Ok(timestamp)
+    }
+
+    fn read(reader: &mut Reader, msg: &[u8], depth: usize) -> Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("nested too deeply"));
+        }
+        let mut timestamp = Self::default();
+        loop {
+            let tag = reader.byte()?;
+            let (tag, last) = match tag {
+                TAG_FORK => (reader.byte()?, false),
+                tag => (tag, true),
+            };
+            if tag == TAG_ATTESTATION {
+                timestamp.attestations.push(Attestation::read(reader)?);
+            } else {
+                let op = match tag {
+                    OP_SHA256 => Op::Sha256,
+                    OP_APPEND => Op::Append(reader.bytes()?.to_vec()),
+                    OP_PREPEND => Op::Prepend(reader.bytes()?.to_vec()),
+                    OP_REVERSE => Op::Reverse,
+                    OP_HEXLIFY => Op::Hexlify,
+                    other => {
+                        return Err(invalid(&format!("unsupported operation 0x{:02x}", other)));
+                    }
+                };
+                let result = op.apply(msg)?;
+                let child = Self::read(reader, &result, depth + 1)?;
+                timestamp.ops.push((op, child));
+            }
+            if last {
+                return Ok(timestamp);
+            }
+        }
+    }
+
+    /// The serialized timestamp, as calendars send it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let mut attestations = self.attestations.clone();
+        attestations.sort();
+        attestations.dedup();
+        let mut ops: Vec<&(Op, Timestamp)> = self.ops.iter().collect();
+        ops.sort_by(|a, b| a.0.cmp(&b.0));
+        let items = attestations.len() + ops.len();
+        let mut written = 0;
+        let mut fork = |out: &mut Vec<u8>| {
+            written += 1;
+            if written < items {
+                out.push(TAG_FORK);
+            }
+        };
+        for attestation in &attestations {
+            fork(out);
+            out.push(TAG_ATTESTATION);
+            attestation.write(out);
+        }
+        for (op, child) in ops {
+            fork(out);
+            op.write(out);
+            child.write(out);
+        }
+    }
+
+    /// Add the attestations and operations of `other`, a timestamp of the
+    /// same message.
+    pub fn merge(&mut self, other: Timestamp) {
+        for attestation in other.attestations {
+            if !self.attestations.contains(&attestation) {
+                self.attestations.push(attestation);
+            }
+        }
+        for (op, child) in other.ops {
+            match self.ops.iter_mut().find(|(existing, _)| *existing == op) {
+                Some((_, existing)) => existing.merge(child),
+                None => self.ops.push((op, child)),
+            }
+        }
+    }
+
+    /// Every attestation with the message it attests, starting from `msg`.
+    pub fn attestations(&self, msg: &[u8]) -> Result<Vec<(Vec<u8>, Attestation)>> {
+        let mut found: Vec<(Vec<u8>, Attestation)> = self
+            .attestations
+            .iter()
+            .map(|attestation| (msg.to_vec(), attestation.clone()))
+            .collect();
+        for (op, child) in &self.ops {
+            found.extend(child.attestations(&op.apply(msg)?)?);
+        }
+        Ok(found)
+    }
+
+    /// The node whose message is `target`, reached from `msg`.
+    fn node_mut(&mut self, msg: &[u8], target: &[u8]) -> Result<Option<&mut Timestamp>> {
+        if msg == target {
+            return Ok(Some(self));
+        }
+        for (op, child) in &mut self.ops {
+            let result = op.apply(msg)?;
+            if let Some(node) = child.node_mut(&result, target)? {
+                return Ok(Some(node));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A proof for a 32-byte SHA-256 digest, as in an `.ots` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetachedTimestamp {
+    pub digest: [u8; 32],
+    pub timestamp: Timestamp,
+}
+
+impl DetachedTimestamp {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let rest = data
+            .strip_prefix(HEADER_MAGIC)
+            .ok_or_else(|| invalid("not an OpenTimestamps proof file"))?;
+        let mut reader = Reader(rest);
+        let version = reader.varuint()?;
+        if version != MAJOR_VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+        if reader.byte()? != OP_SHA256 {
+            return Err(invalid("only SHA-256 file digests are supported"));
+        }
+        let digest: [u8; 32] = reader
+            .take(32)?
+            .try_into()
+            .map_err(|_| invalid("truncated file digest"))?;
+        Ok(Self {
+            digest,
+            timestamp: Timestamp::parse(reader.0, &digest)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = HEADER_MAGIC.to_vec();
+        write_varuint(&mut out, MAJOR_VERSION);
+        out.push(OP_SHA256);
+        out.extend_from_slice(&self.digest);
+        self.timestamp.write(&mut out);
+        out
+    }
+
+    /// Every attestation with the message it attests.
+    pub fn attestations(&self) -> Result<Vec<(Vec<u8>, Attestation)>> {
+        self.timestamp.attestations(&self.digest)
+    }
+
+    /// Whether any branch ends in a Bitcoin attestation.
+    pub fn is_complete(&self) -> Result<bool> {
+        Ok(self
+            .attestations()?
+            .iter()
+            .any(|(_, attestation)| matches!(attestation, Attestation::Bitcoin { .. })))
+    }
+}
+
+/// Talks to OpenTimestamps calendar servers.
+pub struct Calendars {
+    urls: Vec<String>,
+    agent: ureq::Agent,
+}
+
+impl Calendars {
+    /// Use the calendars at `urls`, or the public ones if there are none.
+    /// Pending attestations naming other calendars are never followed.
+    pub fn new(urls: &[String]) -> Self {
+        let urls = if urls.is_empty() {
+            DEFAULT_CALENDARS
+                .iter()
+                .map(|url| url.to_string())
+                .collect()
+        } else {
+            urls.iter()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .collect()
+        };
+        Self {
+            urls,
+            agent: ureq::AgentBuilder::new().timeout(CALENDAR_TIMEOUT).build(),
+        }
+    }
+
+    fn post(&self, calendar: &str, commitment: &[u8]) -> Result<Vec<u8>> {
+        let response = self
+            .agent
+            .post(&format!("{}/digest", calendar))
+            .set("Accept", "application/vnd.opentimestamps.v1")
+            .send_bytes(commitment)
+            .map_err(|e| {
+                RhodiError::Resolution(format!("Calendar {} did not stamp: {}", calendar, e))
+            })?;
+        read_body(response)
+    }
+
+    /// Submit `digest` to every calendar; at least one must answer. A
+    /// random nonce keeps the digest itself from the calendars.
+    pub fn stamp(&self, digest: [u8; 32]) -> Result<DetachedTimestamp> {
+        let nonce: [u8; 16] = rand::random();
+        let append = Op::Append(nonce.to_vec());
+        let commitment = Op::Sha256.apply(&append.apply(&digest)?)?;
+
+        let mut committed = Timestamp::default();
+        let mut failures = Vec::new();
+        for calendar in &self.urls {
+            match self
+                .post(calendar, &commitment)
+                .and_then(|body| Timestamp::parse(&body, &commitment))
+            {
+                Ok(timestamp) => committed.merge(timestamp),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+        if committed == Timestamp::default() {
+            return Err(RhodiError::Resolution(format!(
+                "No OpenTimestamps calendar accepted the digest: {}",
+                failures.join("; ")
+            )));
+        }
+        let hashed = Timestamp {
+            attestations: Vec::new(),
+            ops: vec![(Op::Sha256, committed)],
+        };
+        Ok(DetachedTimestamp {
+            digest,
+            timestamp: Timestamp {
+                attestations: Vec::new(),
+                ops: vec![(append, hashed)],
+            },
+        })
+    }
+
+    /// Ask the calendars of pending attestations for their completed
+    /// timestamps and merge them in. Returns whether anything was added.
+    pub fn upgrade(&self, proof: &mut DetachedTimestamp) -> Result<bool> {
+        let mut upgraded = false;
+        for (msg, attestation) in proof.attestations()? {
+            let Attestation::Pending { uri } = attestation else {
+                continue;
+            };
+            let calendar = uri.trim_end_matches('/');
+            if !self.urls.iter().any(|url| url == calendar) {
+                continue;
+            }
+            let url = format!("{}/timestamp/{}", calendar, hex::encode(&msg));
+            let body = match self
+                .agent
+                .get(&url)
+                .set("Accept", "application/vnd.opentimestamps.v1")
+                .call()
+            {
+                Ok(response) => read_body(response)?,
+                // Not anchored yet
+                Err(ureq::Error::Status(404, _)) => continue,
+                Err(e) => {
+                    return Err(RhodiError::Resolution(format!(
+                        "Calendar {} did not answer: {}",
+                        calendar, e
+                    )));
+                }
+            };
+            let timestamp = Timestamp::parse(&body, &msg)?;
+            let before = proof.clone();
+            if let Some(node) = proof.timestamp.node_mut(&proof.digest, &msg)? {
+                node.merge(timestamp);
+            }
+            upgraded |= *proof != before;
+        }
+        Ok(upgraded)
+    }
+}
+
+/// Looks up Bitcoin block headers.
+pub trait BlockHeaders {
+    /// The merkle root, in internal byte order, and the time of the block
+    /// at `height`.
+    fn block(&self, height: u64) -> Result<([u8; 32], DateTime<Utc>)>;
+}
+
+/// A block explorer with the Esplora HTTP API, such as blockstream.info.
+pub struct Esplora {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl Esplora {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(CALENDAR_TIMEOUT).build(),
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<String> {
+        let response = self
+            .agent
+            .get(&format!("{}/{}", self.base_url, path))
+            .call()
+            .map_err(|e| {
+                RhodiError::Resolution(format!("Block explorer {} failed: {}", self.base_url, e))
+            })?;
+        Ok(response.into_string()?)
+    }
+}
+
+impl BlockHeaders for Esplora {
+    fn block(&self, height: u64) -> Result<([u8; 32], DateTime<Utc>)> {
+        let hash = self.get(&format!("block-height/{}", height))?;
+        let block: serde_json::Value =
+            serde_json::from_str(&self.get(&format!("block/{}", hash.trim()))?).map_err(|e| {
+                RhodiError::Resolution(format!("Invalid block from explorer: {}", e))
+            })?;
+        let root = block["merkle_root"]
+            .as_str()
+            .and_then(|root| hex::decode(root).ok())
+            .and_then(|root| <[u8; 32]>::try_from(root).ok())
+            .ok_or_else(|| RhodiError::Resolution("Block has no merkle root".to_string()))?;
+        let time = block["timestamp"]
+            .as_i64()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .ok_or_else(|| RhodiError::Resolution("Block has no timestamp".to_string()))?;
+        // Explorers show the root byte-reversed, like block hashes
+        let mut internal = root;
+        internal.reverse();
+        Ok((internal, time))
+    }
+}
+
+/// What a proof establishes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimestampStatus {
+    /// Anchored in the Bitcoin block at `height`, mined at `time`
+    Confirmed { height: u64, time: DateTime<Utc> },
+    /// Only calendars vouch for it so far
+    Pending { calendars: Vec<String> },
+}
+
+/// Check that `proof` is for `version_hash` and find its earliest Bitcoin
+/// attestation that matches the block in `headers`.
+pub fn verify(
+    proof: &DetachedTimestamp,
+    version_hash: &[u8; 32],
+    headers: &dyn BlockHeaders,
+) -> Result<TimestampStatus> {
+    if &proof.digest != version_hash {
+        return Err(RhodiError::Verification(
+            "OpenTimestamps proof is for a different version hash".to_string(),
+        ));
+    }
+    let mut confirmed: Option<(u64, DateTime<Utc>)> = None;
+    let mut calendars = Vec::new();
+    for (msg, attestation) in proof.attestations()? {
+        match attestation {
+            Attestation::Bitcoin { height } => {
+                let (root, time) = headers.block(height)?;
+                if msg != root {
+                    return Err(RhodiError::Verification(format!(
+                        "OpenTimestamps proof does not match Bitcoin block {}",
+                        height
+                    )));
+                }
+                if confirmed.is_none_or(|(earliest, _)| height < earliest) {
+                    confirmed = Some((height, time));
+                }
+            }
+            Attestation::Pending { uri } => calendars.push(uri),
+            Attestation::Unknown { .. } => {}
+        }
+    }
+    match confirmed {
+        Some((height, time)) => Ok(TimestampStatus::Confirmed { height, time }),
+        None if !calendars.is_empty() => Ok(TimestampStatus::Pending { calendars }),
+        None => Err(RhodiError::Verification(
+            "OpenTimestamps proof has no attestation".to_string(),
+        )),
+    }
+}
+
+fn read_body(response: ureq::Response) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    std::io::Read::read_to_end(
+        &mut std::io::Read::take(response.into_reader(), MAX_MESSAGE_LENGTH as u64 * 64),
+        &mut body,
+    )?;
+    Ok(body)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid("truncated"));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// An unsigned LEB128 integer.
+    fn varuint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("integer too large"))
+    }
+
+    /// Length-prefixed bytes.
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.varuint()? as usize;
+        if len > MAX_MESSAGE_LENGTH {
+            return Err(invalid("field too long"));
+        }
+        self.take(len)
+    }
+}
+
+fn write_varuint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
@@ -0,0 +1,234 @@
+//! Selective disclosure: `redact` blocks.
+//!
+//! A ```` ```redact ```` block holds sensitive content together with a random
+//! salt. From protocol 2.4 the version hash never covers the plaintext
+//! directly, only the block's label and commitment, `sha256(salt "\n"
+//! content)`, as RFC 8785 canonical JSON. [`redact`] produces a
+//! rendition of the document in which every such block is replaced by its
+//! commitment, so a sealed document can be shared with sensitive sections
+//! removed and still verify under the original seal:
+//!
+//! ````markdown
+//! ```redact
+//! label: Salary figures
+//! salt: 9c1f…
+//! content: |
+//!   The base salary is 120,000 EUR.
+//! ```
+//! ````
+//!
+//! becomes
+//!
+//! ````markdown
+//! ```redact
+//! label: Salary figures
+//! commitment: sha256:5be2…
+//! ```
+//! ````
+//!
+//! Salts are assigned when the document is sealed. A block that does not
+//! parse is hashed as written, like any other text, and so is every block
+//! of a document before 2.4.
+
+use crate::crypto::hash_bytes;
+use crate::error::{Result, RhodiError};
+use crate::markdown::{FenceLine, Fences};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+const REDACT_FENCE: &str = "```redact";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RedactBlock {
+    /// Visible description of what was redacted, kept in both renditions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Hex-encoded random salt, assigned at seal time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// The plaintext, absent once redacted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// `sha256:` commitment to the salted plaintext, present once redacted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+}
+
+impl RedactBlock {
+    /// Parse the YAML between the fences. Exactly one of `content` or
+    /// `commitment` must be present.
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let block: Self = serde_norway::from_str(yaml)
+            .map_err(|e| RhodiError::Format(format!("Invalid redact block: {}", e)))?;
+        if block.content.is_some() == block.commitment.is_some() {
+            return Err(RhodiError::Format(
+                "Redact block needs exactly one of 'content' or 'commitment'".to_string(),
+            ));
+        }
+        Ok(block)
+    }
+
+    pub fn is_redacted(&self) -> bool {
+        self.content.is_none()
+    }
+
+    /// The commitment this block contributes to the version hash.
+    pub fn commitment(&self) -> String {
+        match (&self.content, &self.commitment) {
+            (Some(content), _) => hash_bytes(
+                format!("{}\n{}", self.salt.as_deref().unwrap_or_default(), content).as_bytes(),
+            ),
+            (None, commitment) => commitment.clone().unwrap_or_default(),
+        }
+    }
+
+    /// The redacted form of this block: its label and commitment only.
+    pub fn redacted(&self) -> Self {
+        Self {
+            label: self.label.clone(),
+            commitment: Some(self.commitment()),
+            ..Default::default()
+        }
+    }
+
+    /// The block as fenced markdown.
+    pub fn render(&self) -> Result<String> {
+        let yaml = serde_norway::to_string(self).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to render redact block: {}", e))
+        })?;
+        Ok(format!("{}\n{}```\n", REDACT_FENCE, yaml))
+    }
+}
+
+/// Replace every plaintext redact block in `body` with its commitment.
+/// Fails if a block has no salt yet, since an unsalted commitment to short
+/// content can be guessed; seal the document first.
+pub fn redact(body: &str) -> Result<String> {
+    let mut count = 0;
+    let out = rewrite_blocks(body, |text| {
+        let block = RedactBlock::parse(text)?;
+        if block.is_redacted() {
+            return Ok(None);
+        }
+        if block.salt.is_none() {
+            return Err(RhodiError::Verification(
+                "Redact block has no salt; seal the document before redacting it".to_string(),
+            ));
+        }
+        count += 1;
+        block.redacted().render().map(Some)
+    })?;
+    if count == 0 {
+        return Err(RhodiError::Verification(
+            "Document has no plaintext redact blocks".to_string(),
+        ));
+    }
+    Ok(out.into_owned())
+}
+
+/// Number of redact blocks in `body`, and how many of them are redacted.
+pub fn count(body: &str) -> (usize, usize) {
+    let mut total = 0;
+    let mut redacted = 0;
+    // The closure never fails, so neither does the rewrite
+    let _ = rewrite_blocks(body, |text| {
+        total += 1;
+        if RedactBlock::parse(text).is_ok_and(|b| b.is_redacted()) {
+            redacted += 1;
+        }
+        Ok(None)
+    });
+    (total, redacted)
+}
+
+/// The body as covered by the version hash: every well-formed redact block
+/// reduced to the canonical JSON of its label and commitment, so both
+/// renditions hash the same, whatever YAML either was written in.
+pub(crate) fn commit_redactions(body: &str) -> Cow<'_, str> {
+    rewrite_blocks(body, |text| {
+        let Ok(block) = RedactBlock::parse(text) else {
+            return Ok(None);
+        };
+        let value = serde_json::to_value(block.redacted()).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to encode redact block: {}", e))
+        })?;
+        Ok(Some(format!(
+            "{}\n{}\n```\n",
+            REDACT_FENCE,
+            crate::canonical::to_jcs(&value)?
+        )))
+    })
+    .unwrap_or(Cow::Borrowed(body))
+}
+
+/// Give every plaintext redact block without a salt a fresh random one.
+/// The salt is inserted as the block's first line, leaving the rest as
+/// written.
+pub(crate) fn salt_redactions(body: &str) -> Cow<'_, str> {
+    rewrite_blocks(body, |text| {
+        Ok(match RedactBlock::parse(text) {
+            Ok(block) if !block.is_redacted() && block.salt.is_none() => {
+                let mut salt = [0u8; 16];
+                rand::rngs::OsRng.fill_bytes(&mut salt);
+                Some(format!(
+                    "{}\nsalt: {}\n{}```\n",
+                    REDACT_FENCE,
+                    hex::encode(salt),
+                    text
+                ))
+            }
+            _ => None,
+        })
+    })
+    .unwrap_or(Cow::Borrowed(body))
+}
+
+/// Call `f` with the YAML of every redact block in `body`. Where it returns
+/// a replacement, the whole fenced block (both fences included) is replaced.
+fn rewrite_blocks<'b, F>(body: &'b str, mut f: F) -> Result<Cow<'b, str>>
+where
+    F: FnMut(&str) -> Result<Option<String>>,
+{
+    if !body.contains("redact") {
+        return Ok(Cow::Borrowed(body));
+    }
+
+    let mut out = String::with_capacity(body.len());
+    let mut fences = Fences::default();
+    let mut block: Option<String> = None;
+    let mut changed = false;
+
+    for line in body.split_inclusive('\n') {
+        let fence = fences.line(line);
+        match &mut block {
+            None if fence == FenceLine::Open("redact") => block = Some(line.to_string()),
+            None => out.push_str(line),
+            Some(raw) if fence == FenceLine::Close => {
+                raw.push_str(line);
+                let text = raw.split_inclusive('\n').skip(1).collect::<Vec<_>>();
+                let yaml = text[..text.len() - 1].concat();
+                match f(&yaml)? {
+                    Some(replacement) => {
+                        out.push_str(&replacement);
+                        changed = true;
+                    }
+                    None => out.push_str(raw),
+                }
+                block = None;
+            }
+            Some(raw) => raw.push_str(line),
+        }
+    }
+    // An unterminated block is left as written
+    if let Some(raw) = block {
+        out.push_str(&raw);
+    }
+
+    Ok(if changed {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(body)
+    })
+}
@@ -0,0 +1,145 @@
+use crate::error::{Result, RhodiError};
+use crate::models::{DocStatus, TracedDocument};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Environment variable consulted when no registry URL is given explicitly.
+pub const REGISTRY_URL_ENV: &str = "RHODI_REGISTRY_URL";
+
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The public record of a sealed document version anchored in a registry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryRecord {
+    pub id: Uuid,
+    pub version_hash: String,
+    pub signature: String,
+    pub public_key: String,
+    pub doc_version: u32,
+    /// The version is revoked: it is a revoking version itself, or an
+    /// earlier version the registry marked when the revoking one arrived
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub revoked: bool,
+}
+
+impl RegistryRecord {
+    /// Build the record for a sealed document.
+    pub fn from_document(doc: &TracedDocument) -> Result<Self> {
+        let fm = &doc.frontmatter;
+        let version_hash = fm.version_hash.ok_or_else(|| {
+            RhodiError::Registry("Document is not sealed (missing version_hash)".into())
+        })?;
+        let signature = fm.signature.as_ref().ok_or_else(|| {
+            RhodiError::Registry("Document is not signed (missing signature)".into())
+        })?;
+        let public_key = fm
+            .public_key
+            .clone()
+            .ok_or_else(|| RhodiError::Registry("Document has no public_key to publish".into()))?;
+
+        Ok(Self {
+            id: fm.id,
+            version_hash: hex::encode(version_hash),
+            signature: hex::encode(signature),
+            public_key,
+            doc_version: fm.doc_version,
+            revoked: fm.doc_status == DocStatus::Revoked,
+        })
+    }
+}
+
+/// HTTP client for a rhodi registry.
+///
+/// The registry exposes two endpoints:
+/// - `POST {base}/documents` with a [`RegistryRecord`] JSON body
+/// - `GET {base}/documents/{id}/versions/{version_hash}` returning the record, or 404
+///
+/// When a revoking version is published, the registry should set `revoked`
+/// on the records of the document's earlier versions.
+pub struct RegistryClient {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl RegistryClient {
+    pub fn new(base_url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(REGISTRY_TIMEOUT).build();
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent,
+        }
+    }
+
+    /// Resolve the registry URL from an explicit value, `RHODI_REGISTRY_URL`,
+    /// or the workspace's configured `registry.url`, in that order.
+    pub fn from_url_or_env(url: Option<String>, configured: Option<String>) -> Result<Self> {
+        let url = url
+            .or_else(|| std::env::var(REGISTRY_URL_ENV).ok())
+            .filter(|u| !u.trim().is_empty())
+            .or(configured)
+            .filter(|u| !u.trim().is_empty())
+            .ok_or_else(|| {
+                RhodiError::Registry(format!(
+                    "No registry URL configured. Pass --registry, set {}, or set registry.url in rhodi.toml.",
+                    REGISTRY_URL_ENV
+                ))
+            })?;
+        Ok(Self::new(&url))
+    }
+
+    /// Anchor a sealed document version in the registry.
+    pub fn publish(&self, record: &RegistryRecord) -> Result<()> {
+        let body = serde_json::to_string(record)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to encode record: {}", e)))?;
+
+        self.agent
+            .post(&format!("{}/documents", self.base_url))
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map_err(|e| RhodiError::Registry(format!("Failed to publish: {}", e)))?;
+        Ok(())
+    }
+
+    /// Look up an anchored version. Returns `None` if the registry does not know it.
+    pub fn lookup(&self, id: &Uuid, version_hash: &str) -> Result<Option<RegistryRecord>> {
+        let url = format!(
+            "{}/documents/{}/versions/{}",
+            self.base_url, id, version_hash
+        );
+        match self.agent.get(&url).call() {
+            Ok(response) => {
+                let body = response
+                    .into_string()
+                    .map_err(|e| RhodiError::Registry(format!("Failed to read response: {}", e)))?;
+                let record = serde_json::from_str(&body)
+                    .map_err(|e| RhodiError::Registry(format!("Invalid registry record: {}", e)))?;
+                Ok(Some(record))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(RhodiError::Registry(format!("Lookup failed: {}", e))),
+        }
+    }
+
+    /// Check that the document's exact sealed version is anchored in the registry.
+    pub fn verify_anchored(&self, doc: &TracedDocument) -> Result<()> {
+        let local = RegistryRecord::from_document(doc)?;
+        match self.lookup(&local.id, &local.version_hash)? {
+            Some(remote) if remote.revoked && !local.revoked => {
+                Err(RhodiError::Verification(format!(
+                    "Version {} of {} has been revoked in the registry",
+                    local.version_hash, local.id
+                )))
+            }
+            Some(remote) if remote == local => Ok(()),
+            Some(_) => Err(RhodiError::Verification(format!(
+                "Registry record for {} version {} does not match the document",
+                local.id, local.version_hash
+            ))),
+            None => Err(RhodiError::Verification(format!(
+                "Version {} of {} is not anchored in the registry",
+                local.version_hash, local.id
+            ))),
+        }
+    }
+}
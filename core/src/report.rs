@@ -0,0 +1,226 @@
+//! Machine-readable verification reports.
+//!
+//! `--report-file` on `verify`, `attest` and `notarize` writes a
+//! [`VerificationReport`]: one entry per document with its outcome, every
+//! finding, and the outcome and timing of each trace and include, so
+//! dashboards can ingest verification history. The format is described by
+//! `specs/report.schema.json` and versioned by [`REPORT_SCHEMA_VERSION`];
+//! fields may be added within a version, but not removed or changed.
+
+use crate::compiler::{CompilationReport, SectionOutcome, VerifiedDocument};
+use crate::error::{Result, RhodiError};
+use crate::index::IndexedDocument;
+use crate::lock::write_atomic;
+use crate::markdown::{Section, parse_include_block};
+use crate::models::{DocStatus, TracedDocument};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// Version of the report format, bumped on incompatible changes.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerificationReport {
+    pub schema_version: u32,
+    /// Version of rhodi that produced the report
+    pub tool_version: String,
+    /// The command that verified, e.g. `verify`
+    pub command: String,
+    pub generated_at: DateTime<Utc>,
+    pub documents: Vec<DocumentReport>,
+}
+
+impl VerificationReport {
+    pub fn new(command: &str) -> Self {
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            command: command.to_string(),
+            generated_at: Utc::now(),
+            documents: Vec::new(),
+        }
+    }
+
+    /// Write the report as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| RhodiError::Serialization(format!("Failed to serialize report: {}", e)))?;
+        write_atomic(path, &format!("{}\n", json))
+    }
+}
+
+/// Errors, warnings, checks skipped offline and traces that ran out of
+/// time, as display strings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Findings {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    pub unverified: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timed_out: Vec<String>,
+}
+
+impl From<&CompilationReport> for Findings {
+    fn from(report: &CompilationReport) -> Self {
+        Self {
+            errors: report.errors.iter().map(|e| e.to_string()).collect(),
+            warnings: report.warnings.clone(),
+            unverified: report.unverified.clone(),
+            timed_out: report.timed_out.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DocumentReport {
+    /// The document as named on the command line, if it was
+    pub path: Option<String>,
+    /// Absent when the document could not be read or parsed
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub doc_status: Option<DocStatus>,
+    pub doc_version: Option<u32>,
+    pub version_hash: Option<String>,
+    pub outcome: SectionOutcome,
+    /// Not verified again because nothing it depends on changed since the
+    /// `verify --since` baseline
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    pub duration_ms: f64,
+    #[serde(flatten)]
+    pub findings: Findings,
+    pub traces: Vec<TraceReport>,
+    pub includes: Vec<IncludeReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceReport {
+    pub id: Option<String>,
+    pub source: String,
+    pub expected: String,
+    pub outcome: SectionOutcome,
+    pub duration_ms: f64,
+    #[serde(flatten)]
+    pub findings: Findings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IncludeReport {
+    /// The included path, or `id:<uuid>` for an include by ID
+    pub target: String,
+    pub outcome: SectionOutcome,
+    pub duration_ms: f64,
+    #[serde(flatten)]
+    pub findings: Findings,
+}
+
+impl DocumentReport {
+    /// The entry for `doc`, with each trace and include of `verified`.
+    /// Document-level findings and the outcome are filled in by
+    /// [`Self::finish`].
+    pub fn new(path: Option<String>, doc: &TracedDocument, verified: &VerifiedDocument) -> Self {
+        let mut traces = Vec::new();
+        let mut includes = Vec::new();
+        for section in &verified.sections {
+            let findings = Findings::from(&section.report);
+            let duration_ms = millis(section.elapsed);
+            match &section.section {
+                Section::Trace(trace) => traces.push(TraceReport {
+                    id: trace.id.clone(),
+                    source: trace.source.clone(),
+                    expected: trace.expected.clone(),
+                    outcome: section.outcome,
+                    duration_ms,
+                    findings,
+                }),
+                Section::Include(content) => includes.push(IncludeReport {
+                    target: parse_include_block(content)
+                        .map(|include| include.target())
+                        .unwrap_or_else(|_| content.trim().to_string()),
+                    outcome: section.outcome,
+                    duration_ms,
+                    findings,
+                }),
+                _ => {}
+            }
+        }
+
+        let fm = &doc.frontmatter;
+        Self {
+            path,
+            id: Some(fm.id.to_string()),
+            title: Some(fm.title.clone()),
+            doc_status: Some(fm.doc_status.clone()),
+            doc_version: Some(fm.doc_version),
+            version_hash: fm.version_hash.map(hex::encode),
+            outcome: SectionOutcome::NotChecked,
+            cached: false,
+            duration_ms: 0.0,
+            findings: Findings::default(),
+            traces,
+            includes,
+        }
+    }
+
+    /// Record every finding of the finished verification and the time it
+    /// took.
+    pub fn finish(mut self, report: &CompilationReport, elapsed: Duration) -> Self {
+        self.outcome = SectionOutcome::of(report);
+        self.findings = Findings::from(report);
+        self.duration_ms = millis(elapsed);
+        self
+    }
+
+    /// Record an error found outside the document's own verification, such
+    /// as an id shared with another workspace document. The document fails.
+    pub fn add_error(&mut self, error: String) {
+        self.outcome = SectionOutcome::Failed;
+        self.findings.errors.push(error);
+    }
+
+    /// The entry for a document that could not be verified at all, e.g.
+    /// because it does not parse.
+    pub fn unverifiable(path: Option<String>, error: &RhodiError, elapsed: Duration) -> Self {
+        Self {
+            path,
+            id: None,
+            title: None,
+            doc_status: None,
+            doc_version: None,
+            version_hash: None,
+            outcome: SectionOutcome::Failed,
+            cached: false,
+            duration_ms: millis(elapsed),
+            findings: Findings {
+                errors: vec![error.to_string()],
+                ..Default::default()
+            },
+            traces: Vec::new(),
+            includes: Vec::new(),
+        }
+    }
+
+    /// The entry for a document `verify --since` passed from cache.
+    pub fn cached(indexed: &IndexedDocument) -> Self {
+        Self {
+            path: Some(indexed.path.to_string_lossy().into_owned()),
+            id: Some(indexed.id.to_string()),
+            title: Some(indexed.title.clone()),
+            doc_status: Some(indexed.status.clone()),
+            doc_version: Some(indexed.doc_version),
+            version_hash: indexed.version_hash.clone(),
+            outcome: SectionOutcome::Passed,
+            cached: true,
+            duration_ms: 0.0,
+            findings: Findings::default(),
+            traces: Vec::new(),
+            includes: Vec::new(),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -1,7 +1,13 @@
+use crate::cache::HttpCache;
 use crate::error::{Result, RhodiError, SecurityError};
 use crate::models::TracedDocument;
+use crate::sandbox::Sandbox;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 pub trait SourceResolver {
     /// Resolve a source (path or URL) to its content (bytes).
@@ -9,66 +15,242 @@ pub trait SourceResolver {
 
     /// Resolve a source to a parsed Document (for includes).
     fn resolve_document(&self, source: &str) -> Result<TracedDocument>;
+
+    /// Resolve a source to its content together with what is known about
+    /// it. By default the content type is guessed from the file extension.
+    fn resolve(&self, source: &str) -> Result<ResolvedSource> {
+        let bytes = self.resolve_bytes(source)?;
+        Ok(ResolvedSource {
+            metadata: SourceMetadata {
+                content_type: guess_content_type(source).map(str::to_string),
+                size: bytes.len() as u64,
+                modified: None,
+                from_cache: false,
+                sandbox: None,
+            },
+            bytes,
+        })
+    }
+
+    /// [`Self::resolve`], giving up once `deadline` passes. Resolvers that
+    /// can block, such as network fetches, honor it; by default it is
+    /// ignored.
+    fn resolve_until(&self, source: &str, _deadline: Option<Instant>) -> Result<ResolvedSource> {
+        self.resolve(source)
+    }
+
+    /// A stable identity for a source, so that different spellings of the
+    /// same location (`./a.tmd`, `a.tmd`) compare equal.
+    fn identity(&self, source: &str) -> Result<String> {
+        Ok(source.to_string())
+    }
+}
+
+/// What a resolver knows about a source besides its bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMetadata {
+    /// MIME type without parameters, e.g. `application/json`
+    pub content_type: Option<String>,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// The bytes came from a cache, revalidated with the origin
+    pub from_cache: bool,
+    /// Extract values from the source in this sandbox, as its resolver
+    /// does not trust it
+    pub sandbox: Option<Sandbox>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResolvedSource {
+    pub bytes: Vec<u8>,
+    pub metadata: SourceMetadata,
+}
+
+/// The MIME type implied by a source's file extension, if rhodi knows it.
+/// A compressed source has the type of its content: `runs.csv.gz` and
+/// `runs.zip!q3.csv` are CSV.
+pub fn guess_content_type(source: &str) -> Option<&'static str> {
+    let path = source.split(['?', '#']).next().unwrap_or(source);
+    let path = match crate::compression::split_archive_entry(path) {
+        (_, Some(entry)) => entry,
+        (path, None) => path,
+    };
+    let mut path = Path::new(path);
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gz") || e.eq_ignore_ascii_case("zst"))
+    {
+        path = Path::new(path.file_stem()?);
+    }
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "md" | "tmd" => "text/markdown",
+        "txt" | "log" => "text/plain",
+        _ => return None,
+    })
 }
 
 pub struct FileResolver {
     root: PathBuf,
+    allowed: Vec<PathBuf>,
+    sandbox: Option<Sandbox>,
 }
 
 impl FileResolver {
     pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
         let root = root.as_ref().canonicalize()?;
-        Ok(Self { root })
+        Ok(Self {
+            root,
+            allowed: Vec::new(),
+            sandbox: None,
+        })
+    }
+
+    /// Restrict sources to the given directories. An empty list allows the whole root.
+    pub fn with_allowed_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.allowed = dirs
+            .into_iter()
+            .map(|d| {
+                let d = d.as_ref();
+                d.canonicalize().unwrap_or_else(|_| normalize(d))
+            })
+            .collect();
+        self
+    }
+
+    /// Extract values from the files this resolves in `sandbox`.
+    pub fn with_sandbox(mut self, sandbox: Option<Sandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    fn check_allowed(&self, path: &Path) -> Result<()> {
+        if self.allowed.is_empty() || self.allowed.iter().any(|dir| path.starts_with(dir)) {
+            return Ok(());
+        }
+        Err(RhodiError::Security(SecurityError::SourceNotAllowed {
+            path: path.to_path_buf(),
+        }))
+    }
+
+    fn traversal(&self, path: &Path) -> RhodiError {
+        RhodiError::Security(SecurityError::PathTraversal {
+            path: path.to_path_buf(),
+            root: self.root.clone(),
+        })
     }
 
     fn validate_path(&self, source: &str) -> Result<PathBuf> {
+        // An archive entry resolves to the archive; decompression picks
+        // the entry out
+        let (source, _) = crate::compression::split_archive_entry(source);
         let path = Path::new(source);
 
-        // 1. Reject absolute paths
-        if path.is_absolute() {
-            return Err(RhodiError::Security(SecurityError::PathTraversal {
-                path: path.to_path_buf(),
-                root: self.root.clone(),
-            }));
+        // 1. Reject absolute paths, including Windows spellings on any OS
+        if path.is_absolute() || is_windows_rooted(source) {
+            return Err(self.traversal(path));
+        }
+        if cfg!(windows) && has_windows_special_component(source) {
+            return Err(self.traversal(path));
         }
 
-        // 2. Check for ".." components that go above root
+        // 2. Check for ".." components that go above root. Backslashes count
+        //    as separators too, as they do when the document is read on Windows.
         let mut depth: i32 = 0;
-        for component in path.components() {
+        for component in source.split(['/', '\\']) {
             match component {
-                std::path::Component::Normal(_) => depth += 1,
-                std::path::Component::ParentDir => {
+                "" | "." => {}
+                ".." => {
                     depth -= 1;
                     if depth < 0 {
-                        return Err(RhodiError::Security(SecurityError::PathTraversal {
-                            path: path.to_path_buf(),
-                            root: self.root.clone(),
-                        }));
+                        return Err(self.traversal(path));
                     }
                 }
-                _ => {}
+                _ => depth += 1,
             }
         }
 
         // 3. Join and canonicalize
         let full_path = self.root.join(path);
 
-        // For existing files, we also check canonical path as a second layer of defense (symlinks)
+        // For existing files, we also check canonical path as a second layer of
+        // defense (symlinks, and junctions on Windows). Canonical paths are
+        // verbatim (`\\?\C:\...`) on Windows, as is the canonical root.
         if full_path.exists() {
             let canonical = full_path.canonicalize()?;
             if !canonical.starts_with(&self.root) {
-                return Err(RhodiError::Security(SecurityError::PathTraversal {
-                    path: canonical,
-                    root: self.root.clone(),
-                }));
+                return Err(self.traversal(&canonical));
             }
+            self.check_allowed(&canonical)?;
             Ok(canonical)
         } else {
+            // A missing file must not sit under a link that leaves the root
+            let normalized = normalize(&full_path);
+            if let Some(ancestor) = normalized.ancestors().skip(1).find(|a| a.exists()) {
+                let canonical = ancestor.canonicalize()?;
+                if !canonical.starts_with(&self.root) {
+                    return Err(self.traversal(&canonical));
+                }
+            }
+            self.check_allowed(&normalized)?;
             Ok(full_path)
         }
     }
 }
 
+/// Whether `source` is absolute or drive-relative on Windows: drive letters
+/// (`C:\x`, `C:x`), UNC and device paths (`\\server\share`, `\\?\C:\`,
+/// `//server/share`) and paths rooted on the current drive (`\x`). These
+/// escape the root on Windows even where `Path::is_absolute` says otherwise.
+fn is_windows_rooted(source: &str) -> bool {
+    let bytes = source.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    drive || source.starts_with('\\') || source.starts_with('/')
+}
+
+/// Components Windows gives a special meaning: reserved device names
+/// (`CON`, `NUL`, `COM1`, with or without an extension) and alternate data
+/// streams (`file.txt:stream`).
+fn has_windows_special_component(source: &str) -> bool {
+    const DEVICES: &[&str] = &["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+    source.split(['/', '\\']).any(|component| {
+        let stem = component
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .trim_end()
+            .to_ascii_uppercase();
+        let numbered = (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit();
+        component.contains(':') || DEVICES.contains(&stem.as_str()) || numbered
+    })
+}
+
+/// Lexically resolve `.` and `..` components.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
 impl SourceResolver for FileResolver {
     fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
         let safe_path = self.validate_path(source)?;
@@ -81,4 +263,433 @@ impl SourceResolver for FileResolver {
             .map_err(|e| RhodiError::Format(format!("Invalid UTF-8 in document: {}", e)))?;
         crate::markdown::parse_tmd(&content)
     }
+
+    fn resolve(&self, source: &str) -> Result<ResolvedSource> {
+        let safe_path = self.validate_path(source)?;
+        let bytes = fs::read(&safe_path)?;
+        let modified = fs::metadata(&safe_path)?
+            .modified()
+            .ok()
+            .map(DateTime::from);
+        Ok(ResolvedSource {
+            metadata: SourceMetadata {
+                content_type: guess_content_type(source).map(str::to_string),
+                size: bytes.len() as u64,
+                modified,
+                from_cache: false,
+                sandbox: self.sandbox.clone(),
+            },
+            bytes,
+        })
+    }
+
+    fn identity(&self, source: &str) -> Result<String> {
+        let path = self.validate_path(source)?;
+        Ok(normalize(&path).display().to_string())
+    }
+}
+
+/// Default cap on the size of a remote source.
+pub const DEFAULT_MAX_REMOTE_BYTES: u64 = 50 * 1024 * 1024;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed for one source. Every hop must pass
+/// [`HttpResolver::check_url`].
+const MAX_REDIRECTS: usize = 5;
+
+/// Fetches `https://` sources. Plain `http://` is always rejected.
+pub struct HttpResolver {
+    agent: ureq::Agent,
+    allowed_hosts: Vec<String>,
+    max_bytes: u64,
+    cache: Option<HttpCache>,
+    sandbox: Option<Sandbox>,
+}
+
+impl Default for HttpResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpResolver {
+    pub fn new() -> Self {
+        Self {
+            // Redirects are followed in `fetch`, which checks every hop
+            agent: ureq::AgentBuilder::new()
+                .timeout(HTTP_TIMEOUT)
+                .redirects(0)
+                .build(),
+            allowed_hosts: Vec::new(),
+            max_bytes: DEFAULT_MAX_REMOTE_BYTES,
+            cache: None,
+            sandbox: None,
+        }
+    }
+
+    /// Only fetch from these hosts. An empty list allows any host.
+    pub fn with_allowed_hosts<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_hosts = hosts.into_iter().map(|h| h.into().to_lowercase()).collect();
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Keep fetched sources in `cache` and revalidate them with the server
+    /// instead of downloading them again. Without one (the default) every
+    /// source is fetched in full.
+    pub fn with_cache(mut self, cache: Option<HttpCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Extract values from fetched sources in `sandbox`.
+    pub fn with_sandbox(mut self, sandbox: Option<Sandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    fn check_url(&self, source: &str) -> Result<()> {
+        let rest = source.strip_prefix("https://").ok_or_else(|| {
+            RhodiError::Resolution(format!("Only https:// sources can be fetched: {}", source))
+        })?;
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = authority
+            .rsplit('@')
+            .next()
+            .unwrap_or_default()
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if self.allowed_hosts.is_empty() || self.allowed_hosts.contains(&host) {
+            return Ok(());
+        }
+        Err(RhodiError::Security(SecurityError::SourceNotAllowed {
+            path: PathBuf::from(source),
+        }))
+    }
+
+    /// Where a redirect from `url` to `location` leads, if
+    /// [`Self::check_url`] allows it.
+    fn redirect_target(&self, url: &str, location: &str) -> Result<String> {
+        let target = url::Url::parse(url)
+            .and_then(|base| base.join(location))
+            .map_err(|e| {
+                RhodiError::Resolution(format!(
+                    "{} redirects to an invalid location {}: {}",
+                    url, location, e
+                ))
+            })?
+            .to_string();
+        self.check_url(&target)?;
+        Ok(target)
+    }
+
+    /// Fetch `source`, which [`Self::check_url`] allowed, following up to
+    /// [`MAX_REDIRECTS`] redirects it also allows. A cached copy is
+    /// revalidated with a conditional request and served on `304 Not
+    /// Modified`; anything else is downloaded and cached anew. Requests
+    /// time out once `deadline` passes.
+    pub(crate) fn fetch(&self, source: &str, deadline: Option<Instant>) -> Result<ResolvedSource> {
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.lookup(source))
+            .filter(|entry| entry.revalidatable());
+        let mut url = source.to_string();
+        let mut redirects = 0;
+        let response = loop {
+            let mut request = self.agent.get(&url);
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(RhodiError::Resolution(format!(
+                        "Ran out of time fetching {}",
+                        source
+                    )));
+                }
+                request = request.timeout(remaining.min(HTTP_TIMEOUT));
+            }
+            if let Some(ref entry) = cached {
+                if let Some(ref etag) = entry.etag {
+                    request = request.set("If-None-Match", etag);
+                }
+                if let Some(ref date) = entry.last_modified {
+                    request = request.set("If-Modified-Since", date);
+                }
+            }
+            let response = request.call().map_err(|e| {
+                RhodiError::Resolution(format!("Failed to fetch {}: {}", source, e))
+            })?;
+            if !matches!(response.status(), 301 | 302 | 303 | 307 | 308) {
+                break response;
+            }
+            if redirects == MAX_REDIRECTS {
+                return Err(RhodiError::Resolution(format!(
+                    "{} redirects more than {} times",
+                    source, MAX_REDIRECTS
+                )));
+            }
+            let location = response.header("Location").ok_or_else(|| {
+                RhodiError::Resolution(format!("{} redirects without a Location", url))
+            })?;
+            url = self.redirect_target(&url, location)?;
+            redirects += 1;
+        };
+        if response.status() == 304
+            && let (Some(cache), Some(entry)) = (&self.cache, cached)
+        {
+            let bytes = cache.read(&entry)?;
+            return Ok(ResolvedSource {
+                metadata: SourceMetadata {
+                    content_type: entry.content_type,
+                    size: bytes.len() as u64,
+                    modified: entry.last_modified.as_deref().and_then(http_date),
+                    from_cache: true,
+                    sandbox: self.sandbox.clone(),
+                },
+                bytes,
+            });
+        }
+
+        // The server's word wins over the extension
+        let content_type = match response.header("Content-Type") {
+            Some(_) => Some(response.content_type().to_ascii_lowercase()),
+            None => guess_content_type(source).map(str::to_string),
+        };
+        let etag = response.header("ETag").map(str::to_string);
+        let last_modified = response.header("Last-Modified").map(str::to_string);
+
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(self.max_bytes + 1)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > self.max_bytes {
+            return Err(RhodiError::Resolution(format!(
+                "{} is larger than the {} byte limit",
+                source, self.max_bytes
+            )));
+        }
+        if let Some(ref cache) = self.cache {
+            // A cache that cannot be written only costs a download next time
+            let _ = cache.store(
+                source,
+                etag.as_deref(),
+                last_modified.as_deref(),
+                content_type.as_deref(),
+                &bytes,
+            );
+        }
+        Ok(ResolvedSource {
+            metadata: SourceMetadata {
+                content_type,
+                size: bytes.len() as u64,
+                modified: last_modified.as_deref().and_then(http_date),
+                from_cache: false,
+                sandbox: self.sandbox.clone(),
+            },
+            bytes,
+        })
+    }
+}
+
+/// An HTTP date, as in `Last-Modified`.
+fn http_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(date)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+impl SourceResolver for HttpResolver {
+    fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+        self.resolve(source).map(|resolved| resolved.bytes)
+    }
+
+    fn resolve(&self, source: &str) -> Result<ResolvedSource> {
+        self.resolve_until(source, None)
+    }
+
+    fn resolve_until(&self, source: &str, deadline: Option<Instant>) -> Result<ResolvedSource> {
+        let (source, _) = crate::compression::split_archive_entry(source);
+        self.check_url(source)?;
+        self.fetch(source, deadline)
+    }
+
+    fn resolve_document(&self, source: &str) -> Result<TracedDocument> {
+        let bytes = self.resolve_bytes(source)?;
+        let content = String::from_utf8(bytes)
+            .map_err(|e| RhodiError::Format(format!("Invalid UTF-8 in document: {}", e)))?;
+        crate::markdown::parse_tmd(&content)
+    }
+
+    fn identity(&self, source: &str) -> Result<String> {
+        let (source, _) = crate::compression::split_archive_entry(source);
+        self.check_url(source)?;
+        Ok(source.split('#').next().unwrap_or(source).to_string())
+    }
+}
+
+/// Dispatches each source to the resolver registered for its URI scheme
+/// (`https:`, `git:`, `s3:`, ...). Sources without a scheme, or with
+/// `file:`, go to the default resolver.
+pub struct ResolverRegistry {
+    default: Box<dyn SourceResolver>,
+    schemes: BTreeMap<String, Box<dyn SourceResolver>>,
+}
+
+impl ResolverRegistry {
+    pub fn new(default: impl SourceResolver + 'static) -> Self {
+        Self {
+            default: Box::new(default),
+            schemes: BTreeMap::new(),
+        }
+    }
+
+    /// Handle sources starting with `<scheme>:`. The resolver receives the
+    /// source unchanged, scheme included.
+    pub fn with_scheme(mut self, scheme: &str, resolver: impl SourceResolver + 'static) -> Self {
+        self.schemes
+            .insert(scheme.to_lowercase(), Box::new(resolver));
+        self
+    }
+
+    fn route<'s>(&self, source: &'s str) -> Result<(&dyn SourceResolver, &'s str)> {
+        let Some(scheme) = uri_scheme(source) else {
+            return Ok((self.default.as_ref(), source));
+        };
+        if scheme.eq_ignore_ascii_case("file") {
+            let path = &source[scheme.len() + 1..];
+            return Ok((
+                self.default.as_ref(),
+                path.strip_prefix("//").unwrap_or(path),
+            ));
+        }
+        match self.schemes.get(&scheme.to_lowercase()) {
+            Some(resolver) => Ok((resolver.as_ref(), source)),
+            None if scheme.eq_ignore_ascii_case("https") => Err(RhodiError::Resolution(
+                "No resolver for 'https:' sources; enable it under [resolver.https] in rhodi.toml"
+                    .to_string(),
+            )),
+            None => Err(RhodiError::Resolution(format!(
+                "No resolver is registered for '{}:' sources",
+                scheme
+            ))),
+        }
+    }
+}
+
+impl SourceResolver for ResolverRegistry {
+    fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+        let (resolver, source) = self.route(source)?;
+        resolver.resolve_bytes(source)
+    }
+
+    fn resolve_document(&self, source: &str) -> Result<TracedDocument> {
+        let (resolver, source) = self.route(source)?;
+        resolver.resolve_document(source)
+    }
+
+    fn resolve(&self, source: &str) -> Result<ResolvedSource> {
+        let (resolver, source) = self.route(source)?;
+        resolver.resolve(source)
+    }
+
+    fn resolve_until(&self, source: &str, deadline: Option<Instant>) -> Result<ResolvedSource> {
+        let (resolver, source) = self.route(source)?;
+        resolver.resolve_until(source, deadline)
+    }
+
+    fn identity(&self, source: &str) -> Result<String> {
+        let (resolver, source) = self.route(source)?;
+        resolver.identity(source)
+    }
+}
+
+/// Serves documents not yet written from memory and everything else from
+/// `inner`. `documents` maps the [`identity`](SourceResolver::identity) of
+/// each document's location to its new text, so that documents sealed
+/// together resolve each other at their new versions.
+pub struct OverlayResolver<'a, R: SourceResolver> {
+    inner: &'a R,
+    documents: &'a BTreeMap<String, String>,
+}
+
+impl<'a, R: SourceResolver> OverlayResolver<'a, R> {
+    pub fn new(inner: &'a R, documents: &'a BTreeMap<String, String>) -> Self {
+        Self { inner, documents }
+    }
+
+    fn overlaid(&self, source: &str) -> Option<&'a String> {
+        let identity = self.inner.identity(source).ok()?;
+        self.documents.get(&identity)
+    }
+}
+
+impl<R: SourceResolver> SourceResolver for OverlayResolver<'_, R> {
+    fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+        match self.overlaid(source) {
+            Some(content) => Ok(content.clone().into_bytes()),
+            None => self.inner.resolve_bytes(source),
+        }
+    }
+
+    fn resolve_document(&self, source: &str) -> Result<TracedDocument> {
+        match self.overlaid(source) {
+            Some(content) => crate::markdown::parse_tmd(content),
+            None => self.inner.resolve_document(source),
+        }
+    }
+
+    fn resolve(&self, source: &str) -> Result<ResolvedSource> {
+        match self.overlaid(source) {
+            Some(content) => Ok(ResolvedSource {
+                metadata: SourceMetadata {
+                    content_type: guess_content_type(source).map(str::to_string),
+                    size: content.len() as u64,
+                    ..SourceMetadata::default()
+                },
+                bytes: content.clone().into_bytes(),
+            }),
+            None => self.inner.resolve(source),
+        }
+    }
+
+    fn resolve_until(&self, source: &str, deadline: Option<Instant>) -> Result<ResolvedSource> {
+        match self.overlaid(source) {
+            Some(_) => self.resolve(source),
+            None => self.inner.resolve_until(source, deadline),
+        }
+    }
+
+    fn identity(&self, source: &str) -> Result<String> {
+        self.inner.identity(source)
+    }
+}
+
+/// Whether resolving `source` needs the network: it names a URI scheme
+/// other than `file:`.
+pub fn is_network_source(source: &str) -> bool {
+    uri_scheme(source).is_some_and(|scheme| !scheme.eq_ignore_ascii_case("file"))
+}
+
+/// The URI scheme of `source`, if it has one. Single letters are Windows
+/// drive prefixes, not schemes.
+fn uri_scheme(source: &str) -> Option<&str> {
+    let (scheme, _) = source.split_once(':')?;
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
 }
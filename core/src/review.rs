@@ -0,0 +1,122 @@
+//! State of an interactive trace review, for `rhodi review`.
+//!
+//! A [`ReviewSession`] holds every trace block of a document together with
+//! what an auditor needs to judge it: the claim it backs, its current
+//! verification result and the source lines around the match. The auditor
+//! approves or flags each trace; the verdicts become the `reviews` of a
+//! signed [`crate::attestation::Attestation`]. The terminal UI lives in the
+//! CLI; this module knows nothing about terminals.
+
+use crate::attestation::{TraceReview, Verdict};
+use crate::compiler::{Compiler, SourceSnippet};
+use crate::markdown::{Section, parse_tmd_sections, trace_anchors};
+use crate::models::{TraceBlock, TraceResult, TracedDocument};
+use crate::resolver::SourceResolver;
+
+/// One trace under review.
+#[derive(Debug, Clone)]
+pub struct ReviewItem {
+    pub trace: TraceBlock,
+    /// Headings enclosing the trace, outermost first
+    pub heading_path: Vec<String>,
+    /// The sentence right before the trace: the claim it backs
+    pub claim: Option<String>,
+    pub result: TraceResult,
+    pub snippet: Option<SourceSnippet>,
+    pub verdict: Option<Verdict>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReviewSession {
+    pub items: Vec<ReviewItem>,
+    /// Index of the trace being reviewed
+    pub current: usize,
+}
+
+impl ReviewSession {
+    /// Check every trace of `doc` and collect its context.
+    pub fn new<R: SourceResolver>(compiler: &Compiler<R>, doc: &TracedDocument) -> Self {
+        let sections = parse_tmd_sections(&doc.body);
+        let anchors = trace_anchors(&sections);
+        let traces = sections.into_iter().filter_map(|section| match section {
+            Section::Trace(trace) => Some(trace),
+            _ => None,
+        });
+        let items = traces
+            .zip(anchors)
+            .map(|(trace, anchor)| {
+                let result = compiler.check_document_trace(doc, &trace);
+                let snippet = compiler.source_snippet(doc, &trace, result.actual.as_deref());
+                ReviewItem {
+                    trace,
+                    heading_path: anchor.heading_path,
+                    claim: anchor.sentence,
+                    result,
+                    snippet,
+                    verdict: None,
+                    note: None,
+                }
+            })
+            .collect();
+        Self { items, current: 0 }
+    }
+
+    pub fn current(&self) -> Option<&ReviewItem> {
+        self.items.get(self.current)
+    }
+
+    pub fn next(&mut self) {
+        if self.current + 1 < self.items.len() {
+            self.current += 1;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+
+    /// Approve the current trace and move on.
+    pub fn approve(&mut self) {
+        self.decide(Verdict::Approved, None);
+    }
+
+    /// Flag the current trace, with an optional note, and move on.
+    pub fn flag(&mut self, note: Option<String>) {
+        self.decide(Verdict::Flagged, note.filter(|n| !n.trim().is_empty()));
+    }
+
+    fn decide(&mut self, verdict: Verdict, note: Option<String>) {
+        if let Some(item) = self.items.get_mut(self.current) {
+            item.verdict = Some(verdict);
+            item.note = note;
+        }
+        self.next();
+    }
+
+    /// Number of traces with a verdict.
+    pub fn decided(&self) -> usize {
+        self.items.iter().filter(|i| i.verdict.is_some()).count()
+    }
+
+    /// Whether every trace has a verdict.
+    pub fn is_complete(&self) -> bool {
+        self.decided() == self.items.len()
+    }
+
+    /// The verdicts, in document order, for an attestation. Undecided
+    /// traces are left out.
+    pub fn reviews(&self) -> Vec<TraceReview> {
+        self.items
+            .iter()
+            .filter_map(|item| {
+                Some(TraceReview {
+                    trace: item.trace.label(),
+                    passed: item.result.passed,
+                    verdict: item.verdict?,
+                    note: item.note.clone(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,90 @@
+//! Revocation of included and quoted documents.
+//!
+//! Revoking a document seals a new version with status `revoked`, which
+//! revokes every earlier version with it. A document that includes or
+//! quotes one of those versions relies on revoked material even though the
+//! version it pins still verifies. The compiler asks the included
+//! document's own status, the workspace (through its
+//! [`DocumentLocator`]) and every [`RevocationCheck`] it was given, such
+//! as a registry, whether the version it relies on is revoked.
+
+use crate::error::{Result, RhodiError};
+use crate::index::DocumentLocator;
+use crate::markdown::parse_tmd;
+use crate::models::{DocStatus, TracedDocument};
+use crate::registry::RegistryClient;
+use std::fs;
+
+/// Knows whether versions of documents are revoked.
+pub trait RevocationCheck {
+    /// Why the version of `doc` is revoked, or `None` if it is not known to
+    /// be.
+    fn revocation(&self, doc: &TracedDocument) -> Result<Option<String>>;
+}
+
+impl<T: RevocationCheck + ?Sized> RevocationCheck for &T {
+    fn revocation(&self, doc: &TracedDocument) -> Result<Option<String>> {
+        (**self).revocation(doc)
+    }
+}
+
+/// A workspace document revokes the earlier versions of its id, if it is
+/// signed with the key of the version it revokes. Anyone can write a
+/// document with a revoked status and a higher version; only the key
+/// holder can revoke.
+impl RevocationCheck for DocumentLocator {
+    fn revocation(&self, doc: &TracedDocument) -> Result<Option<String>> {
+        let fm = &doc.frontmatter;
+        let Some(key) = fm
+            .public_key
+            .as_deref()
+            .and_then(|key| hex::decode(key).ok())
+        else {
+            return Ok(None);
+        };
+        let index = self.index()?;
+        let candidates = index.documents.iter().filter(|indexed| {
+            indexed.id == fm.id
+                && indexed.status == DocStatus::Revoked
+                && indexed.doc_version > fm.doc_version
+        });
+        for indexed in candidates {
+            let Ok(revoking) = fs::read_to_string(index.root.join(&indexed.path))
+                .map_err(RhodiError::from)
+                .and_then(|content| parse_tmd(&content))
+            else {
+                continue;
+            };
+            if revoking.frontmatter.doc_status == DocStatus::Revoked
+                && revoking.frontmatter.doc_version > fm.doc_version
+                && revoking.verify_with_key(&key).is_ok()
+            {
+                return Ok(Some(format!(
+                    "revoked by version {} ({})",
+                    indexed.doc_version,
+                    indexed.path.display()
+                )));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The registry marks revoked versions in their records.
+impl RevocationCheck for RegistryClient {
+    fn revocation(&self, doc: &TracedDocument) -> Result<Option<String>> {
+        let Some(version_hash) = doc.frontmatter.version_hash else {
+            return Ok(None);
+        };
+        Ok(self
+            .lookup(&doc.frontmatter.id, &hex::encode(version_hash))?
+            .filter(|record| record.revoked)
+            .map(|_| "revoked in the registry".to_string()))
+    }
+}
+
+/// Why `doc` itself is revoked, from its own status.
+pub(crate) fn own_revocation(doc: &TracedDocument) -> Option<String> {
+    (doc.frontmatter.doc_status == DocStatus::Revoked)
+        .then(|| format!("is revoked (version {})", doc.frontmatter.doc_version))
+}
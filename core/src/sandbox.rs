@@ -0,0 +1,424 @@
+//! Checking untrusted sources in a confined child process.
+//!
+//! A resolver given a [`Sandbox`] tags the sources it resolves, and traces
+//! are then checked against them by a helper process (`rhodi
+//! sandbox-extract`) rather than by the verifier itself: the helper
+//! decompresses and normalizes the source, validates it against the
+//! trace's schema and extracts the value, and the verifier only ever holds
+//! the raw bytes and those the helper sends back. The verifier hashes the
+//! source and checks the hash and claim itself, so a helper subverted by
+//! its source cannot vouch for it. Before it reads the source the helper confines
+//! itself: its memory and CPU time are capped,
+//! it may read only the system directories programs load from (plus any
+//! the workspace allows) and write nowhere, and it cannot open sockets.
+//! `exec` commands run inside the same confinement. An evidence file crafted
+//! to exploit a decompressor or parser bug is thus contained in a process that reaches
+//! neither the workspace nor the network.
+//!
+//! Confinement needs Linux with Landlock. Where it cannot be set up,
+//! sandboxed extraction fails rather than run unconfined.
+
+use crate::compiler::{SourceCheck, check_source, extract_value};
+use crate::error::{Result, RhodiError, SecurityError};
+use crate::extraction::{ExecExtractor, MAX_EXEC_OUTPUT, run_command, run_command_output};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Default cap on the helper's address space, in bytes.
+pub const DEFAULT_SANDBOX_MEMORY: u64 = 512 * 1024 * 1024;
+
+/// Default time one sandboxed extraction may take.
+pub const DEFAULT_SANDBOX_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a confined helper may read (never write): the places programs and
+/// their libraries load from.
+pub const SYSTEM_READ_PATHS: &[&str] = &[
+    "/usr",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/bin",
+    "/sbin",
+    "/etc",
+    "/nix/store",
+    "/dev/null",
+    "/dev/urandom",
+];
+
+/// Time the helper gets on top of the extraction's own timeout to start
+/// and confine itself.
+const HELPER_STARTUP: Duration = Duration::from_secs(2);
+
+/// How values are extracted from sources of one resolver: in a helper
+/// process with these limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+    helper: Vec<String>,
+    memory_bytes: u64,
+    timeout: Duration,
+    allow_read: Vec<PathBuf>,
+}
+
+/// What [`Sandbox::check`] asks of the helper, sent as one JSON line
+/// ahead of the source.
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    memory_bytes: u64,
+    timeout_ms: u64,
+    allow_read: Vec<PathBuf>,
+    check: SourceCheck,
+    /// The `exec` command to run, for `extractor: exec` traces
+    command: Option<Vec<String>>,
+}
+
+/// What the helper found in a source, sent back as one JSON line ahead of
+/// the normalized source when normalizing changed it. It holds no hash:
+/// the verifier hashes the bytes itself.
+#[derive(Debug, Serialize, Deserialize)]
+struct Answer {
+    decompressed: u64,
+    value: Option<String>,
+    normalized: bool,
+}
+
+/// What [`Sandbox::check`] got back from the helper.
+#[derive(Debug)]
+pub(crate) struct Extracted {
+    /// The source after the trace's `compression` and `normalize`, when
+    /// they changed it
+    pub normalized: Option<Vec<u8>>,
+    /// Bytes decompressing the source produced
+    pub decompressed: u64,
+    /// The extracted value, when the trace has a selector
+    pub value: Option<String>,
+}
+
+/// How the helper's check failed, sent back so the verifier reports it as
+/// it would its own.
+#[derive(Debug, Serialize, Deserialize)]
+enum Failure {
+    Format(String),
+    Extraction(String),
+    Verification(String),
+    DecompressedSizeExceeded { limit: u64 },
+    Other(String),
+}
+
+impl From<RhodiError> for Failure {
+    fn from(error: RhodiError) -> Self {
+        match error {
+            RhodiError::Format(m) => Self::Format(m),
+            RhodiError::Extraction(m) => Self::Extraction(m),
+            RhodiError::Verification(m) => Self::Verification(m),
+            RhodiError::Security(SecurityError::DecompressedSizeExceeded { limit }) => {
+                Self::DecompressedSizeExceeded { limit }
+            }
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<Failure> for RhodiError {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Format(m) => Self::Format(m),
+            Failure::Extraction(m) => Self::Extraction(m),
+            Failure::Verification(m) => Self::Verification(m),
+            Failure::DecompressedSizeExceeded { limit } => {
+                Self::Security(SecurityError::DecompressedSizeExceeded { limit })
+            }
+            Failure::Other(m) => Self::Extraction(format!("Sandboxed extraction failed: {}", m)),
+        }
+    }
+}
+
+impl Sandbox {
+    /// A sandbox whose helper is run as `helper` (program and arguments);
+    /// see [`Sandbox::default_helper`].
+    pub fn new(helper: Vec<String>) -> Self {
+        Self {
+            helper,
+            memory_bytes: DEFAULT_SANDBOX_MEMORY,
+            timeout: DEFAULT_SANDBOX_TIMEOUT,
+            allow_read: Vec::new(),
+        }
+    }
+
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_bytes = bytes;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Let the helper, and `exec` commands, read these paths as well.
+    pub fn with_allowed_reads<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.allow_read = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// `rhodi sandbox-extract`, run by the `rhodi` binary that is running,
+    /// or else the one beside the running program, or else the one on
+    /// `PATH`.
+    pub fn default_helper() -> Vec<String> {
+        let name = format!("rhodi{}", std::env::consts::EXE_SUFFIX);
+        let program = std::env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                if exe.file_name()? == name.as_str() {
+                    Some(exe)
+                } else {
+                    Some(exe.with_file_name(&name)).filter(|sibling| sibling.is_file())
+                }
+            })
+            .map_or(name.clone(), |path| path.display().to_string());
+        vec![program, "sandbox-extract".to_string()]
+    }
+
+    /// Run [`check_source`] on `raw` in the helper, returning what it
+    /// extracted. `exec` supplies the commands of `extractor: exec` traces.
+    /// The helper is killed once `deadline` passes.
+    pub(crate) fn check(
+        &self,
+        check: &SourceCheck,
+        raw: &[u8],
+        exec: Option<&ExecExtractor>,
+        deadline: Option<Instant>,
+    ) -> Result<Extracted> {
+        let trace = &check.trace;
+        let command = match (exec, &trace.selector) {
+            (Some(exec), Some(selector))
+                if crate::extraction::select_extractor(
+                    trace.extractor.as_deref(),
+                    trace.source_content_type(check.content_type.as_deref()),
+                    selector,
+                )
+                .eq_ignore_ascii_case("exec") =>
+            {
+                Some(exec.command(selector)?)
+            }
+            _ => None,
+        };
+        let request = Request {
+            memory_bytes: self.memory_bytes,
+            timeout_ms: self.timeout.as_millis() as u64,
+            allow_read: self.allow_read.clone(),
+            check: check.clone(),
+            command,
+        };
+        let mut input = serde_json::to_vec(&request).map_err(|e| {
+            RhodiError::Serialization(format!("Failed to encode sandbox request: {}", e))
+        })?;
+        input.push(b'\n');
+        input.extend_from_slice(raw);
+
+        let (program, args) = self
+            .helper
+            .split_first()
+            .ok_or_else(|| unavailable("no helper program is configured".into()))?;
+        let mut helper = Command::new(program);
+        helper.args(args).current_dir("/");
+        // Normalizing at most triples a source (NFC's worst case)
+        let max_output = (raw.len() as u64)
+            .saturating_mul(3)
+            .saturating_add(check.max_decompressed)
+            .saturating_add(MAX_EXEC_OUTPUT as u64);
+        let response = run_command_output(
+            "Sandboxed extraction",
+            helper,
+            &input,
+            self.timeout + HELPER_STARTUP,
+            deadline,
+            usize::try_from(max_output).unwrap_or(usize::MAX),
+        )?;
+        let split = response
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or(response.len());
+        let unreadable = |reason: String| {
+            RhodiError::Extraction(format!(
+                "Sandboxed extraction failed: unreadable answer from the helper: {}",
+                reason
+            ))
+        };
+        let outcome: std::result::Result<Answer, Failure> =
+            serde_json::from_slice(&response[..split]).map_err(|e| unreadable(e.to_string()))?;
+        let answer = outcome?;
+        let rest = response.get(split + 1..).unwrap_or_default();
+        if !answer.normalized && !rest.is_empty() {
+            return Err(unreadable("trailing bytes after the findings".into()));
+        }
+        Ok(Extracted {
+            normalized: answer.normalized.then(|| rest.to_vec()),
+            decompressed: answer.decompressed,
+            value: answer.value,
+        })
+    }
+}
+
+/// The helper's side of [`Sandbox::check`]: read the request and the
+/// source from `input`, confine this process, check the source, and write
+/// the findings and the normalized source (or why the check failed) to
+/// `output`. `rhodi
+/// sandbox-extract` runs this; nothing else should, as the confinement
+/// cannot be undone.
+pub fn serve(mut input: impl Read, mut output: impl Write) -> Result<()> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let split = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| RhodiError::Format("Sandbox request is missing".into()))?;
+    let request: Request = serde_json::from_slice(&bytes[..split])
+        .map_err(|e| RhodiError::Format(format!("Invalid sandbox request: {}", e)))?;
+    let raw = &bytes[split + 1..];
+
+    let program = request
+        .command
+        .as_ref()
+        .and_then(|argv| argv.first())
+        .and_then(|program| find_program(program));
+    confine(&request, program.as_deref())?;
+
+    let trace = &request.check.trace;
+    let extract = |selector: &str, content: &[u8]| match &request.command {
+        Some(argv) => {
+            let name = selector.split_whitespace().next().unwrap_or_default();
+            let (program, args) = argv.split_first().ok_or_else(|| {
+                RhodiError::Extraction(format!("Exec command '{}' is empty", name))
+            })?;
+            let mut command = Command::new(program);
+            command.args(args).current_dir("/");
+            let value = run_command(
+                &format!("Exec command '{}'", name),
+                command,
+                content,
+                Duration::from_millis(request.timeout_ms),
+                None,
+            )?;
+            crate::extraction::apply_transforms(&value, &trace.transforms)
+        }
+        None => extract_value(
+            trace,
+            selector,
+            content,
+            request.check.content_type.as_deref(),
+            None,
+            None,
+        ),
+    };
+    let outcome = check_source(&request.check, raw, &extract, &|| Ok(()));
+    let (answer, normalized) = match outcome {
+        Ok((findings, content)) => {
+            let normalized = (*content != *raw).then_some(content);
+            let answer = Answer {
+                decompressed: findings.decompressed,
+                value: findings.value,
+                normalized: normalized.is_some(),
+            };
+            (Ok(answer), normalized)
+        }
+        Err(e) => (Err(Failure::from(e)), None),
+    };
+    serde_json::to_writer(&mut output, &answer).map_err(|e| {
+        RhodiError::Serialization(format!("Failed to encode sandbox answer: {}", e))
+    })?;
+    if let Some(normalized) = normalized {
+        output.write_all(b"\n")?;
+        output.write_all(&normalized)?;
+    }
+    Ok(())
+}
+
+/// Where `program` lives: itself when it is a path, else the first match
+/// on `PATH`.
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        return Some(PathBuf::from(program));
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+fn unavailable(reason: String) -> RhodiError {
+    RhodiError::Security(SecurityError::SandboxUnavailable { reason })
+}
+
+/// Cap this process's resources and restrict what it can reach, for good:
+/// read-only access to [`SYSTEM_READ_PATHS`], the request's allowed paths
+/// and `program`, and no sockets.
+#[cfg(all(target_os = "linux", feature = "cli"))]
+fn confine(request: &Request, program: Option<&Path>) -> Result<()> {
+    use landlock::{
+        ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+        path_beneath_rules,
+    };
+    use rlimit::Resource;
+    use seccompiler::{SeccompAction, SeccompFilter};
+
+    let cpu_secs = request.timeout_ms.div_ceil(1000).max(1);
+    for (resource, limit) in [
+        (Resource::AS, request.memory_bytes),
+        (Resource::CPU, cpu_secs),
+        (Resource::FSIZE, 0),
+        (Resource::CORE, 0),
+    ] {
+        rlimit::setrlimit(resource, limit, limit)
+            .map_err(|e| unavailable(format!("cannot limit {}: {}", resource.as_name(), e)))?;
+    }
+
+    let abi = ABI::V5;
+    let readable = SYSTEM_READ_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .chain(request.allow_read.iter().cloned())
+        .chain(program.map(Path::to_path_buf));
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))
+        .and_then(|ruleset| ruleset.create())
+        .and_then(|ruleset| {
+            ruleset.add_rules(path_beneath_rules(readable, AccessFs::from_read(abi)))
+        })
+        .and_then(|ruleset| ruleset.restrict_self())
+        .map_err(|e| unavailable(format!("Landlock: {}", e)))?;
+    if status.ruleset == RulesetStatus::NotEnforced {
+        return Err(unavailable("the kernel does not enforce Landlock".into()));
+    }
+
+    // io_uring could open sockets past the socket() filter
+    let arch = std::env::consts::ARCH
+        .try_into()
+        .map_err(|e| unavailable(format!("seccomp: {}", e)))?;
+    let filter: seccompiler::BpfProgram = SeccompFilter::new(
+        [
+            (libc::SYS_socket, vec![]),
+            (libc::SYS_io_uring_setup, vec![]),
+        ]
+        .into_iter()
+        .collect(),
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EACCES as u32),
+        arch,
+    )
+    .and_then(TryInto::try_into)
+    .map_err(|e| unavailable(format!("seccomp: {}", e)))?;
+    seccompiler::apply_filter(&filter).map_err(|e| unavailable(format!("seccomp: {}", e)))
+}
+
+#[cfg(not(all(target_os = "linux", feature = "cli")))]
+fn confine(_request: &Request, _program: Option<&Path>) -> Result<()> {
+    Err(unavailable(
+        "sandboxed extraction needs Linux with Landlock and the `cli` feature".into(),
+    ))
+}
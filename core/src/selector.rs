@@ -0,0 +1,296 @@
+//! Typed trace selectors. A trace's `selector` is written as a compact
+//! string whose meaning depends on the extractor; [`Selector::parse`] turns
+//! it into a [`Selector`], rejecting malformed selectors before any source
+//! is read, and [`Selector`]'s `Display` writes the compact form back.
+
+use crate::error::{Result, RhodiError};
+use jsonpath_rust::JsonPathInst;
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// How a byte-range selector renders the bytes it selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteEncoding {
+    /// Lowercase hex
+    #[default]
+    Hex,
+    Utf8,
+}
+
+/// Where in a source a trace's value is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// The first capture group, or the whole match, of a regular expression
+    Regex(String),
+    /// A JSONPath query, e.g. `$.metrics.accuracy`
+    JsonPath(String),
+    /// A dotted YAML or TOML path, e.g. `.runs[0].loss`
+    Path(String),
+    /// `bytes:OFFSET..LEN[:utf8]`, a byte range of any file
+    Bytes {
+        offset: usize,
+        len: usize,
+        encoding: ByteEncoding,
+    },
+    /// `cell:[SHEET!]A1`, a table cell; `row` and `col` count from 0
+    Cell {
+        sheet: Option<String>,
+        row: usize,
+        col: usize,
+    },
+    /// `lines:FROM..TO`, a range of lines counted from 1, both inclusive
+    Lines { from: usize, to: usize },
+    /// A command configured in `[extractors.exec.commands]` and its arguments
+    Exec { command: String, args: Vec<String> },
+}
+
+impl Selector {
+    /// Parse `spec` as a selector for the extractor named `extractor`. For
+    /// `auto`, the extractor is inferred as [`select_extractor`] would
+    /// without knowing the content type: `bytes:` selectors are recognized
+    /// by their prefix, `cell:` and `lines:` selectors when they parse in
+    /// full, and anything else must at least be a valid regex.
+    ///
+    /// [`select_extractor`]: crate::extraction::select_extractor
+    pub fn parse(extractor: &str, spec: &str) -> Result<Self> {
+        match extractor.trim().to_ascii_lowercase().as_str() {
+            "auto" => Self::parse_auto(spec),
+            "regex" => Self::parse_regex(spec),
+            "jsonpath" => Self::parse_json_path(spec),
+            "yaml" | "toml" => Self::parse_path(spec),
+            "bytes" => Self::parse_bytes(spec),
+            "cell" => Self::parse_cell(spec),
+            "lines" => Self::parse_lines(spec),
+            "exec" => Self::parse_exec(spec),
+            _ => Err(RhodiError::Extraction(format!(
+                "Unknown extraction method: {}",
+                extractor
+            ))),
+        }
+    }
+
+    /// The extractor that reads this selector.
+    pub fn extractor(&self) -> &'static str {
+        match self {
+            Selector::Regex(_) => "regex",
+            Selector::JsonPath(_) => "jsonpath",
+            Selector::Path(_) => "yaml",
+            Selector::Bytes { .. } => "bytes",
+            Selector::Cell { .. } => "cell",
+            Selector::Lines { .. } => "lines",
+            Selector::Exec { .. } => "exec",
+        }
+    }
+
+    fn parse_auto(spec: &str) -> Result<Self> {
+        let trimmed = spec.trim_start();
+        if trimmed.starts_with("bytes:") {
+            Self::parse_bytes(spec)
+        } else if let Ok(cell) = Self::parse_cell(spec) {
+            Ok(cell)
+        } else if let Ok(lines) = Self::parse_lines(spec) {
+            Ok(lines)
+        } else if spec.starts_with('$') && Self::parse_json_path(spec).is_ok() {
+            Ok(Selector::JsonPath(spec.to_string()))
+        } else {
+            Self::parse_regex(spec)
+        }
+    }
+
+    fn parse_regex(spec: &str) -> Result<Self> {
+        Regex::new(spec)
+            .map_err(|e| RhodiError::Extraction(format!("Invalid regex '{}': {}", spec, e)))?;
+        Ok(Selector::Regex(spec.to_string()))
+    }
+
+    fn parse_json_path(spec: &str) -> Result<Self> {
+        JsonPathInst::from_str(spec)
+            .map_err(|e| RhodiError::Extraction(format!("Invalid JSONPath '{}': {}", spec, e)))?;
+        Ok(Selector::JsonPath(spec.to_string()))
+    }
+
+    fn parse_path(spec: &str) -> Result<Self> {
+        let path = spec.trim();
+        let invalid =
+            |reason: &str| RhodiError::Extraction(format!("Invalid path '{}': {}", spec, reason));
+        let segments = path.strip_prefix('.').unwrap_or(path);
+        for segment in segments.split('.').filter(|s| !s.is_empty()) {
+            let (key, indices) = match segment.find('[') {
+                Some(i) => segment.split_at(i),
+                None => (segment, ""),
+            };
+            if key.contains(']') {
+                return Err(invalid("unmatched ']'"));
+            }
+            for index in indices.split_terminator(']') {
+                index
+                    .strip_prefix('[')
+                    .and_then(|i| i.trim().parse::<usize>().ok())
+                    .ok_or_else(|| invalid("indices are written [N]"))?;
+            }
+            if !indices.is_empty() && !indices.ends_with(']') {
+                return Err(invalid("unclosed '['"));
+            }
+        }
+        Ok(Selector::Path(path.to_string()))
+    }
+
+    fn parse_bytes(spec: &str) -> Result<Self> {
+        let invalid = || {
+            RhodiError::Extraction(format!(
+                "Invalid byte-range selector '{}': expected bytes:OFFSET..LEN[:hex|:utf8]",
+                spec
+            ))
+        };
+        let range = spec.trim().strip_prefix("bytes:").ok_or_else(invalid)?;
+        let (range, encoding) = range.split_once(':').unwrap_or((range, "hex"));
+        let (offset, len) = range.split_once("..").ok_or_else(invalid)?;
+        let encoding = match encoding.trim().to_ascii_lowercase().as_str() {
+            "hex" => ByteEncoding::Hex,
+            "utf8" => ByteEncoding::Utf8,
+            _ => return Err(invalid()),
+        };
+        Ok(Selector::Bytes {
+            offset: parse_count(offset).ok_or_else(invalid)?,
+            len: parse_count(len).ok_or_else(invalid)?,
+            encoding,
+        })
+    }
+
+    fn parse_cell(spec: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            RhodiError::Extraction(format!(
+                "Invalid cell selector '{}': {} (expected cell:[SHEET!]A1)",
+                spec, reason
+            ))
+        };
+        let cell = spec
+            .trim()
+            .strip_prefix("cell:")
+            .ok_or_else(|| invalid("missing cell: prefix"))?;
+        let (sheet, reference) = match cell.rsplit_once('!') {
+            Some((sheet, _)) if sheet.trim().is_empty() => {
+                return Err(invalid("empty sheet name"));
+            }
+            Some((sheet, reference)) => (Some(sheet.trim().to_string()), reference.trim()),
+            None => (None, cell.trim()),
+        };
+        let letters = reference
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(reference.len());
+        let (column, row) = reference.split_at(letters);
+        if column.is_empty() {
+            return Err(invalid("missing column letters"));
+        }
+        let col = column
+            .chars()
+            .try_fold(0usize, |col, c| {
+                col.checked_mul(26)?
+                    .checked_add((c.to_ascii_uppercase() as u8 - b'A') as usize + 1)
+            })
+            .ok_or_else(|| invalid("column out of range"))?;
+        let row: usize = row
+            .parse()
+            .ok()
+            .filter(|row| *row > 0)
+            .ok_or_else(|| invalid("rows are numbered from 1"))?;
+        Ok(Selector::Cell {
+            sheet,
+            row: row - 1,
+            col: col - 1,
+        })
+    }
+
+    fn parse_lines(spec: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            RhodiError::Extraction(format!(
+                "Invalid line selector '{}': {} (expected lines:FROM..TO)",
+                spec, reason
+            ))
+        };
+        let range = spec
+            .trim()
+            .strip_prefix("lines:")
+            .ok_or_else(|| invalid("missing lines: prefix"))?;
+        let (from, to) = range.split_once("..").unwrap_or((range, range));
+        let line = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| invalid("lines are numbered from 1"))
+        };
+        let (from, to) = (line(from)?, line(to)?);
+        if from > to {
+            return Err(invalid("the range ends before it starts"));
+        }
+        Ok(Selector::Lines { from, to })
+    }
+
+    fn parse_exec(spec: &str) -> Result<Self> {
+        let mut words = spec.split_whitespace().map(str::to_string);
+        let command = words.next().ok_or_else(|| {
+            RhodiError::Extraction("Exec selector must name a configured command".into())
+        })?;
+        Ok(Selector::Exec {
+            command,
+            args: words.collect(),
+        })
+    }
+}
+
+/// A decimal or `0x` hex count.
+fn parse_count(s: &str) -> Option<usize> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Column letters for a 0-based column index: 0 is `A`, 26 is `AA`.
+fn column_letters(col: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        letters.push(b'A' + ((n - 1) % 26) as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Regex(spec) | Selector::JsonPath(spec) | Selector::Path(spec) => {
+                f.write_str(spec)
+            }
+            Selector::Bytes {
+                offset,
+                len,
+                encoding,
+            } => {
+                write!(f, "bytes:{}..{}", offset, len)?;
+                match encoding {
+                    ByteEncoding::Hex => Ok(()),
+                    ByteEncoding::Utf8 => f.write_str(":utf8"),
+                }
+            }
+            Selector::Cell { sheet, row, col } => {
+                f.write_str("cell:")?;
+                if let Some(sheet) = sheet {
+                    write!(f, "{}!", sheet)?;
+                }
+                write!(f, "{}{}", column_letters(*col), row + 1)
+            }
+            Selector::Lines { from, to } if from == to => write!(f, "lines:{}", from),
+            Selector::Lines { from, to } => write!(f, "lines:{}..{}", from, to),
+            Selector::Exec { command, args } => {
+                f.write_str(command)?;
+                args.iter().try_for_each(|arg| write!(f, " {}", arg))
+            }
+        }
+    }
+}
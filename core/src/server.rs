@@ -0,0 +1,300 @@
+//! HTTP verification service for `rhodi serve`.
+//!
+//! Exposes verification, status and extraction as JSON endpoints so that a
+//! document management system can run rhodi as a sidecar instead of
+//! spawning the CLI for every document. Documents are posted inline or named
+//! by a path inside the workspace the server was started in; sources always
+//! resolve within that workspace, under its `rhodi.toml` resolver settings.
+//!
+//! | Method | Path       | Body              |
+//! | :----- | :--------- | :---------------- |
+//! | GET    | `/health`  |                   |
+//! | POST   | `/verify`  | [`VerifyRequest`] |
+//! | POST   | `/status`  | [`StatusRequest`] |
+//! | POST   | `/extract` | [`ExtractRequest`] |
+
+use crate::compiler::{Compiler, SourceCheck, VerifyPasses, check_resolved};
+use crate::config::Workspace;
+use crate::error::{Result, RhodiError, SecurityError};
+use crate::markdown::{Section, parse_tmd, parse_tmd_sections};
+use crate::models::{DocStatus, TraceBlock, TracedDocument};
+use crate::resolver::{FileResolver, SourceResolver};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Largest request body accepted, in bytes.
+pub const MAX_BODY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// A document to work on: inline TMD text, or a path in the workspace.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DocumentRef {
+    /// TMD content posted inline
+    #[serde(default)]
+    pub document: Option<String>,
+    /// Path of a `.tmd` file relative to the workspace root
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Directory, relative to the workspace root, that an inline document's
+    /// sources resolve against (default: the root). Ignored with `path`.
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VerifyRequest {
+    #[serde(flatten)]
+    pub doc: DocumentRef,
+    /// Check the seal only, like `rhodi verify --skip-traces`
+    #[serde(default)]
+    pub skip_traces: bool,
+    /// Fail on warnings, like `rhodi verify --strict`
+    #[serde(default)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerifyResponse {
+    pub valid: bool,
+    pub id: String,
+    pub doc_status: DocStatus,
+    pub version_hash: Option<String>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+pub type StatusRequest = DocumentRef;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatusResponse {
+    pub id: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub doc_status: DocStatus,
+    pub doc_version: u32,
+    pub protocol_version: String,
+    /// `Current`, `Deprecated` or `Obsolete`
+    pub protocol_status: String,
+    pub version_hash: Option<String>,
+    pub signed: bool,
+    pub traces: usize,
+    pub includes: usize,
+}
+
+/// One extraction, with the same fields a trace block would carry.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExtractRequest {
+    pub source: String,
+    pub selector: String,
+    #[serde(default)]
+    pub extractor: Option<String>,
+    #[serde(default)]
+    pub transforms: Vec<String>,
+    #[serde(default)]
+    pub normalize: Option<String>,
+    /// Directory the source resolves against, relative to the workspace root
+    #[serde(default)]
+    pub base: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtractResponse {
+    pub value: String,
+    /// `sha256:` digest of the (normalized) source, ready to pin in a
+    /// trace's `hash`
+    pub source_hash: String,
+}
+
+/// Request handling, independent of the HTTP transport.
+pub struct Service {
+    workspace: Workspace,
+}
+
+impl Service {
+    pub fn new(workspace: Workspace) -> Self {
+        Self { workspace }
+    }
+
+    /// Route a request and return the HTTP status and JSON response body.
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        let path = path.split('?').next().unwrap_or_default();
+        let result = match (method, path) {
+            ("GET", "/health") => Ok(json!({
+                "status": "ok",
+                "version": env!("CARGO_PKG_VERSION"),
+            })),
+            ("POST", "/verify") => parse_body(body).and_then(|r| to_json(self.verify(r)?)),
+            ("POST", "/status") => parse_body(body).and_then(|r| to_json(self.status(r)?)),
+            ("POST", "/extract") => parse_body(body).and_then(|r| to_json(self.extract(r)?)),
+            (_, "/health" | "/verify" | "/status" | "/extract") => {
+                return (
+                    405,
+                    json!({ "error": format!("{} not allowed on {}", method, path) }),
+                );
+            }
+            _ => return (404, json!({ "error": format!("No endpoint {}", path) })),
+        };
+        match result {
+            Ok(value) => (200, value),
+            Err(e) => {
+                let status = match e {
+                    RhodiError::Security(_) => 403,
+                    RhodiError::Io(_) | RhodiError::Resolution(_) => 404,
+                    _ => 400,
+                };
+                (status, json!({ "error": e.to_string() }))
+            }
+        }
+    }
+
+    pub fn verify(&self, request: VerifyRequest) -> Result<VerifyResponse> {
+        let (doc, base) = self.load(&request.doc)?;
+        let resolver = self.workspace.resolver(&base)?;
+        let passes = VerifyPasses {
+            traces: !request.skip_traces,
+            ..Default::default()
+        };
+        let report = Compiler::new(&resolver)
+            .with_passes(passes)
+            .with_exec_extractor(self.workspace.exec_extractor())
+            .with_presets(self.workspace.config.presets.clone())
+            .with_version_registry(self.workspace.version_registry()?)
+            .with_limits(self.workspace.config.limits.compiler_limits())
+            .with_document_locator(Some(self.workspace.document_locator()))
+            .verify(&doc)?;
+        let valid = report.errors.is_empty() && (!request.strict || report.warnings.is_empty());
+        Ok(VerifyResponse {
+            valid,
+            id: doc.frontmatter.id.to_string(),
+            doc_status: doc.frontmatter.doc_status.clone(),
+            version_hash: doc.frontmatter.version_hash.map(hex::encode),
+            errors: report.errors.iter().map(|e| e.to_string()).collect(),
+            warnings: report.warnings,
+        })
+    }
+
+    pub fn status(&self, request: StatusRequest) -> Result<StatusResponse> {
+        let (doc, _) = self.load(&request)?;
+        let versions = self.workspace.version_registry()?;
+        let sections = parse_tmd_sections(&doc.body);
+        let count = |f: fn(&Section) -> bool| sections.iter().filter(|s| f(s)).count();
+        let fm = doc.frontmatter;
+        Ok(StatusResponse {
+            id: fm.id.to_string(),
+            protocol_status: format!("{:?}", versions.status(&fm.protocol_version)),
+            title: fm.title,
+            author: fm.author.map(|author| author.name),
+            doc_status: fm.doc_status,
+            doc_version: fm.doc_version,
+            protocol_version: fm.protocol_version,
+            version_hash: fm.version_hash.map(hex::encode),
+            signed: fm.signature.is_some(),
+            traces: count(|s| matches!(s, Section::Trace(_))),
+            includes: count(|s| matches!(s, Section::Include(_))),
+        })
+    }
+
+    pub fn extract(&self, request: ExtractRequest) -> Result<ExtractResponse> {
+        let base = self.base_dir(request.base.as_deref())?;
+        let resolver = self.workspace.resolver(&base)?;
+        let source = resolver.resolve(&request.source)?;
+        let trace = TraceBlock {
+            source: request.source,
+            selector: Some(request.selector),
+            extractor: request.extractor,
+            transforms: request.transforms,
+            normalize: request.normalize,
+            ..Default::default()
+        };
+        let findings = check_resolved(
+            &SourceCheck::new(trace, &source),
+            &source,
+            self.workspace.exec_extractor().as_ref(),
+            None,
+            &|| Ok(()),
+        )?;
+        Ok(ExtractResponse {
+            value: findings.value.unwrap_or_default(),
+            source_hash: findings.hash,
+        })
+    }
+
+    /// The requested document and the directory its sources resolve against.
+    fn load(&self, doc: &DocumentRef) -> Result<(TracedDocument, PathBuf)> {
+        match (&doc.document, &doc.path) {
+            (Some(content), None) => Ok((parse_tmd(content)?, self.base_dir(doc.base.as_deref())?)),
+            (None, Some(path)) => {
+                let document = FileResolver::new(&self.workspace.root)?.resolve_document(path)?;
+                let parent = Path::new(path).parent().and_then(|p| p.to_str());
+                Ok((document, self.base_dir(parent)?))
+            }
+            _ => Err(RhodiError::Format(
+                "Request needs exactly one of 'document' or 'path'".to_string(),
+            )),
+        }
+    }
+
+    /// A directory inside the workspace root.
+    fn base_dir(&self, relative: Option<&str>) -> Result<PathBuf> {
+        let root = &self.workspace.root;
+        let dir = root.join(relative.unwrap_or_default()).canonicalize()?;
+        if !dir.starts_with(root) {
+            return Err(RhodiError::Security(SecurityError::PathTraversal {
+                path: dir,
+                root: root.clone(),
+            }));
+        }
+        Ok(dir)
+    }
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body)
+        .map_err(|e| RhodiError::Format(format!("Invalid request body: {}", e)))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| RhodiError::Serialization(e.to_string()))
+}
+
+/// Serve `service` on `addr` (e.g. `127.0.0.1:8750`) with `workers`
+/// threads. Runs until the process is stopped.
+pub fn run(service: Service, addr: &str, workers: usize) -> Result<()> {
+    let server = tiny_http::Server::http(addr).map_err(|e| {
+        RhodiError::Io(std::io::Error::other(format!(
+            "Cannot listen on {}: {}",
+            addr, e
+        )))
+    })?;
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .map_err(|()| RhodiError::Format("Invalid Content-Type header".into()))?;
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| {
+                while let Ok(mut request) = server.recv() {
+                    let mut body = Vec::new();
+                    let (status, value) = match request
+                        .as_reader()
+                        .take(MAX_BODY_BYTES + 1)
+                        .read_to_end(&mut body)
+                    {
+                        Ok(_) if body.len() as u64 > MAX_BODY_BYTES => (
+                            413,
+                            json!({ "error": format!("Request body exceeds {} bytes", MAX_BODY_BYTES) }),
+                        ),
+                        Ok(_) => service.handle(request.method().as_str(), request.url(), &body),
+                        Err(e) => (400, json!({ "error": e.to_string() })),
+                    };
+                    let response = tiny_http::Response::from_string(value.to_string())
+                        .with_status_code(status)
+                        .with_header(content_type.clone());
+                    // The client may have gone away; nothing to do about it here
+                    let _ = request.respond(response);
+                }
+            });
+        }
+    });
+    Ok(())
+}
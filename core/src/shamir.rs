@@ -0,0 +1,130 @@
+//! Shamir secret sharing over GF(256), for splitting a signing key among
+//! custodians: any `threshold` of the shares rebuild the secret, fewer
+//! reveal nothing about it.
+//!
+//! Each byte of the secret is the constant term of its own random
+//! polynomial of degree `threshold - 1`; share `x` holds every polynomial
+//! evaluated at `x`. The field uses the AES polynomial (x⁸ + x⁴ + x³ + x + 1)
+//! and table-free arithmetic, so no lookup depends on secret bytes.
+
+use crate::error::{Result, RhodiError};
+use rand::RngCore;
+
+/// One share of a split secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    /// Point the polynomials are evaluated at, from 1
+    pub index: u8,
+    /// One byte per byte of the secret
+    pub data: Vec<u8>,
+}
+
+/// Split `secret` into `shares` shares, any `threshold` of which rebuild it.
+pub fn split(secret: &[u8], threshold: u8, shares: u8) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > shares {
+        return Err(RhodiError::Crypto(format!(
+            "Threshold must be at least 2 and at most the number of shares ({}), not {}",
+            shares, threshold
+        )));
+    }
+    if secret.is_empty() {
+        return Err(RhodiError::Crypto("Cannot split an empty secret".into()));
+    }
+
+    let mut coefficients = vec![0u8; threshold as usize - 1];
+    let mut out: Vec<Share> = (1..=shares)
+        .map(|index| Share {
+            index,
+            data: Vec::with_capacity(secret.len()),
+        })
+        .collect();
+    for &byte in secret {
+        rand::rngs::OsRng.fill_bytes(&mut coefficients);
+        for share in &mut out {
+            // Horner's rule, highest coefficient first
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, &c| mul(acc, share.index) ^ c);
+            share.data.push(mul(y, share.index) ^ byte);
+        }
+    }
+    coefficients.fill(0);
+    Ok(out)
+}
+
+/// Rebuild a secret from shares of one split. Pass at least the threshold
+/// it was split with: fewer shares give a wrong secret, not an error, so
+/// callers check the result against something known, such as a public key.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>> {
+    let Some(first) = shares.first() else {
+        return Err(RhodiError::Crypto("No shares to combine".into()));
+    };
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0 {
+            return Err(RhodiError::Crypto("Share index 0 is not valid".into()));
+        }
+        if share.data.len() != first.data.len() {
+            return Err(RhodiError::Crypto(
+                "Shares have different lengths; they come from different splits".into(),
+            ));
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(RhodiError::Crypto(format!(
+                "Share {} is given twice",
+                share.index
+            )));
+        }
+    }
+
+    // Lagrange interpolation at x = 0
+    let weights: Vec<u8> = shares
+        .iter()
+        .map(|share| {
+            let (numerator, denominator) = shares
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold((1u8, 1u8), |(num, den), other| {
+                    (mul(num, other.index), mul(den, other.index ^ share.index))
+                });
+            mul(numerator, inverse(denominator))
+        })
+        .collect();
+    Ok((0..first.data.len())
+        .map(|i| {
+            shares
+                .iter()
+                .zip(&weights)
+                .fold(0u8, |acc, (share, &weight)| {
+                    acc ^ mul(share.data[i], weight)
+                })
+        })
+        .collect())
+}
+
+/// Multiplication in GF(256), without branches on the operands.
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(256): a²⁵⁴. Zero has none and maps to zero.
+fn inverse(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
@@ -0,0 +1,175 @@
+//! Workspace analytics, for `rhodi stats`.
+//!
+//! [`WorkspaceStats`] aggregates what research-integrity officers watch to
+//! follow adoption: how many documents are sealed, how densely they are
+//! traced, which extractors back the traces, how old the evidence is, and,
+//! from the ledger of attestations, how often verification turned up
+//! warnings and failing traces month by month.
+
+use crate::attestation::{Attestation, Verdict};
+use crate::error::Result;
+use crate::extraction::select_extractor;
+use crate::index::WorkspaceIndex;
+use crate::markdown::{Section, parse_tmd, parse_tmd_sections};
+use crate::models::{DocStatus, TraceBlock, TraceMethod, TracePreset};
+use crate::resolver::guess_content_type;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct WorkspaceStats {
+    pub documents: usize,
+    /// Documents by status (`notes`, `draft`, `published`, `revoked`)
+    pub by_status: BTreeMap<String, usize>,
+    /// Documents carrying a version hash
+    pub sealed: usize,
+    /// Indexed documents that could no longer be read or parsed; their
+    /// traces are not counted
+    pub unreadable: usize,
+    pub traces: usize,
+    /// Documents by their number of trace blocks
+    pub traces_per_document: BTreeMap<usize, usize>,
+    /// Traces by the extractor that checks them: `regex`, `jsonpath`, …,
+    /// `manual` for manual traces and `hash` for traces without a selector
+    pub extractors: BTreeMap<String, usize>,
+    /// Mean age in days of trace timestamps, when any trace has one
+    pub average_evidence_age_days: Option<f64>,
+    pub attestations: usize,
+    /// The ledger by calendar month, oldest first
+    pub ledger: Vec<LedgerPeriod>,
+}
+
+/// Attestations recorded in one calendar month.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct LedgerPeriod {
+    /// `YYYY-MM`
+    pub month: String,
+    pub attestations: usize,
+    /// Attestations whose verification reported warnings
+    pub with_warnings: usize,
+    /// Traces judged in `rhodi review`
+    pub reviewed_traces: usize,
+    /// Reviewed traces whose automatic check failed
+    pub failed_traces: usize,
+    /// Reviewed traces the reviewer flagged
+    pub flagged_traces: usize,
+}
+
+impl LedgerPeriod {
+    /// Share of reviewed traces that failed their automatic check.
+    pub fn failure_rate(&self) -> Option<f64> {
+        (self.reviewed_traces > 0).then(|| self.failed_traces as f64 / self.reviewed_traces as f64)
+    }
+}
+
+impl WorkspaceStats {
+    /// Aggregate the documents of `index` and the attestations of `ledger`,
+    /// expanding trace presets from `presets`. Evidence age is measured
+    /// from `now`.
+    pub fn collect(
+        index: &WorkspaceIndex,
+        presets: &BTreeMap<String, TracePreset>,
+        ledger: &[Attestation],
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        let mut stats = Self {
+            documents: index.documents.len(),
+            attestations: ledger.len(),
+            ..Self::default()
+        };
+        let mut ages = Vec::new();
+        for indexed in &index.documents {
+            let status = match indexed.status {
+                DocStatus::Notes => "notes",
+                DocStatus::Draft => "draft",
+                DocStatus::Published => "published",
+                DocStatus::Revoked => "revoked",
+            };
+            *stats.by_status.entry(status.to_string()).or_default() += 1;
+            if indexed.version_hash.is_some() {
+                stats.sealed += 1;
+            }
+
+            let Some(doc) = fs::read_to_string(index.root.join(&indexed.path))
+                .ok()
+                .and_then(|content| parse_tmd(&content).ok())
+            else {
+                stats.unreadable += 1;
+                continue;
+            };
+            let traces: Vec<TraceBlock> = parse_tmd_sections(&doc.body)
+                .into_iter()
+                .filter_map(|section| match section {
+                    Section::Trace(trace) => {
+                        let trace = trace.expand_preset(presets).unwrap_or(trace);
+                        Some(doc.frontmatter.resolve_trace(&trace).unwrap_or(trace))
+                    }
+                    _ => None,
+                })
+                .collect();
+            stats.traces += traces.len();
+            *stats.traces_per_document.entry(traces.len()).or_default() += 1;
+            for trace in &traces {
+                *stats.extractors.entry(extractor_of(trace)).or_default() += 1;
+                if let Some(timestamp) = trace.timestamp {
+                    ages.push((now - timestamp).num_seconds() as f64 / 86_400.0);
+                }
+            }
+        }
+        stats.average_evidence_age_days =
+            (!ages.is_empty()).then(|| ages.iter().sum::<f64>() / ages.len() as f64);
+
+        let mut months: BTreeMap<String, LedgerPeriod> = BTreeMap::new();
+        for attestation in ledger {
+            let month = attestation.verified_at.format("%Y-%m").to_string();
+            let period = months.entry(month.clone()).or_insert_with(|| LedgerPeriod {
+                month,
+                ..LedgerPeriod::default()
+            });
+            period.attestations += 1;
+            if !attestation.warnings.is_empty() {
+                period.with_warnings += 1;
+            }
+            for review in &attestation.reviews {
+                period.reviewed_traces += 1;
+                if !review.passed {
+                    period.failed_traces += 1;
+                }
+                if review.verdict == Verdict::Flagged {
+                    period.flagged_traces += 1;
+                }
+            }
+        }
+        stats.ledger = months.into_values().collect();
+        Ok(stats)
+    }
+
+    /// Mean number of traces per document.
+    pub fn mean_traces_per_document(&self) -> f64 {
+        let read = self.documents - self.unreadable;
+        if read == 0 {
+            0.0
+        } else {
+            self.traces as f64 / read as f64
+        }
+    }
+}
+
+/// The extractor that checks `trace`, as the compiler would pick it from
+/// the source's file extension.
+fn extractor_of(trace: &TraceBlock) -> String {
+    if trace.method == TraceMethod::Manual {
+        return "manual".to_string();
+    }
+    match &trace.selector {
+        Some(selector) => select_extractor(
+            trace.extractor.as_deref(),
+            guess_content_type(&trace.source),
+            selector,
+        )
+        .to_lowercase(),
+        None => "hash".to_string(),
+    }
+}
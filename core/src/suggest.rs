@@ -0,0 +1,356 @@
+//! Trace suggestions for authors: figures stated in prose that no trace
+//! backs, and where in the workspace's evidence files the same values
+//! appear. `rhodi suggest` turns accepted suggestions into trace blocks.
+
+use crate::comparison::parse_locale_number;
+use crate::crypto::hash_bytes;
+use crate::error::Result;
+use crate::extraction::{csv_records, get_extractor};
+use crate::markdown::{FenceLine, Fences, figure_pattern, parse_trace_block};
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Evidence files larger than this are not searched.
+pub const MAX_EVIDENCE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// A figure stated in the body that no trace after its paragraph backs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    /// The figure as written, e.g. `1,204` or `12%`
+    pub figure: String,
+    /// Its numeric value; `12%` is 12
+    pub value: f64,
+    /// The line stating it, trimmed
+    pub line: String,
+    /// Index of that line in the body, from 0
+    pub line_index: usize,
+    /// Index of the last line of its paragraph, after which a trace goes
+    pub paragraph_end: usize,
+}
+
+/// A file that may hold evidence, named as a trace of the document would
+/// name it.
+#[derive(Debug, Clone)]
+pub struct EvidenceFile {
+    /// Path relative to the document's directory, with `/` separators
+    pub source: String,
+    pub content: Vec<u8>,
+}
+
+/// A trace that would back a claim, already checked to extract `expected`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub source: String,
+    pub hash: String,
+    pub extractor: &'static str,
+    pub selector: String,
+    pub expected: String,
+}
+
+impl Suggestion {
+    /// The trace block to insert into the body.
+    pub fn to_block(&self) -> String {
+        format!(
+            "```trace\nsource: {}\nhash: {}\nselector: {}\nextractor: {}\nexpected: {}\n```\n",
+            yaml_string(&self.source),
+            self.hash,
+            yaml_string(&self.selector),
+            self.extractor,
+            yaml_string(&self.expected)
+        )
+    }
+}
+
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+/// The numeric value of a figure, ignoring currency signs and `%`.
+fn figure_value(figure: &str) -> Option<f64> {
+    let digits: String = figure
+        .chars()
+        .filter(|c| !matches!(c, '$' | '€' | '£' | '%') && !c.is_whitespace())
+        .collect();
+    parse_locale_number(&digits, false)
+}
+
+/// Figures in prose paragraphs of `body` that are not the `expected` value
+/// of a trace block following the paragraph. Headings and fenced blocks
+/// are not prose.
+pub fn untraced_claims(body: &str) -> Vec<Claim> {
+    let mut claims = Vec::new();
+    let mut pending: Vec<Claim> = Vec::new();
+    let mut fences = Fences::default();
+    let mut trace: Option<String> = None;
+    let mut in_paragraph = false;
+
+    for (index, line) in body.lines().enumerate() {
+        let trimmed = line.trim();
+        match fences.line(line) {
+            FenceLine::Open("trace") => {
+                in_paragraph = false;
+                trace = Some(format!("{}\n", line));
+            }
+            FenceLine::Open(_) => {
+                in_paragraph = false;
+                claims.append(&mut pending);
+            }
+            FenceLine::Content => {
+                if let Some(block) = trace.as_mut() {
+                    block.push_str(line);
+                    block.push('\n');
+                }
+            }
+            FenceLine::Close => {
+                let Some(mut block) = trace.take() else {
+                    continue;
+                };
+                block.push_str(line);
+                // A trace backs the figures whose value it expects
+                if let Ok(trace) = parse_trace_block(&block) {
+                    let expected = figure_value(&trace.expected);
+                    pending.retain(|claim| {
+                        claim.figure != trace.expected.trim() && expected != Some(claim.value)
+                    });
+                }
+            }
+            FenceLine::Text if trimmed.is_empty() => in_paragraph = false,
+            FenceLine::Text if trimmed.starts_with('#') => {
+                in_paragraph = false;
+                claims.append(&mut pending);
+            }
+            FenceLine::Text => {
+                if !in_paragraph {
+                    claims.append(&mut pending);
+                    in_paragraph = true;
+                }
+                for claim in &mut pending {
+                    claim.paragraph_end = index;
+                }
+                for figure in figure_pattern().find_iter(line) {
+                    let Some(value) = figure_value(figure.as_str()) else {
+                        continue;
+                    };
+                    pending.push(Claim {
+                        figure: figure.as_str().trim().to_string(),
+                        value,
+                        line: trimmed.to_string(),
+                        line_index: index,
+                        paragraph_end: index,
+                    });
+                }
+            }
+        }
+    }
+    claims.append(&mut pending);
+    claims
+}
+
+/// Every readable file under `dirs` that lies under `base`, skipping hidden
+/// directories, `target`, `.tmd` documents and files over
+/// [`MAX_EVIDENCE_BYTES`].
+pub fn collect_evidence(dirs: &[PathBuf], base: &Path) -> Result<Vec<EvidenceFile>> {
+    let base = base.canonicalize()?;
+    let mut files = Vec::new();
+    let mut stack: Vec<PathBuf> = dirs.iter().filter(|dir| dir.is_dir()).cloned().collect();
+    while let Some(dir) = stack.pop() {
+        let mut entries: Vec<_> = fs::read_dir(&dir)?.filter_map(|e| e.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(kind) = entry.file_type() else {
+                continue;
+            };
+            if kind.is_dir() {
+                if name != "target" {
+                    stack.push(path);
+                }
+                continue;
+            }
+            let too_large = entry
+                .metadata()
+                .map_or(true, |meta| meta.len() > MAX_EVIDENCE_BYTES);
+            if !kind.is_file() || too_large || name.ends_with(".tmd") {
+                continue;
+            }
+            let Ok(relative) = path
+                .canonicalize()
+                .map(|path| path.strip_prefix(&base).map(Path::to_path_buf))
+            else {
+                continue;
+            };
+            let Ok(relative) = relative else {
+                continue;
+            };
+            let source = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if files.iter().any(|f: &EvidenceFile| f.source == source) {
+                continue;
+            }
+            files.push(EvidenceFile {
+                source,
+                content: fs::read(&path)?,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.source.cmp(&b.source));
+    Ok(files)
+}
+
+/// Up to `limit` traces that would back `claim`, from the files holding its
+/// value: a JSONPath for JSON, a cell for CSV, and a regex anchored on the
+/// text before the value for anything else. Each is checked to extract the
+/// value before it is suggested.
+pub fn suggest(claim: &Claim, files: &[EvidenceFile], limit: usize) -> Vec<Suggestion> {
+    let mut suggestions = Vec::new();
+    for file in files {
+        if suggestions.len() >= limit {
+            break;
+        }
+        // Binary files hold no figures worth tracing
+        if file.content.contains(&0) {
+            continue;
+        }
+        let candidates = match extension(&file.source).as_str() {
+            "json" => json_candidates(file, claim.value),
+            "csv" => csv_candidates(file, claim.value),
+            _ => text_candidates(file, claim.value),
+        };
+        let checked = candidates.into_iter().find_map(|(extractor, selector)| {
+            let expected = get_extractor(extractor)
+                .ok()?
+                .extract(&file.content, &selector)
+                .ok()?;
+            (figure_value(&expected) == Some(claim.value)).then(|| Suggestion {
+                source: file.source.clone(),
+                hash: hash_bytes(&file.content),
+                extractor,
+                selector,
+                expected,
+            })
+        });
+        suggestions.extend(checked);
+    }
+    suggestions
+}
+
+fn extension(source: &str) -> String {
+    Path::new(source)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+fn json_candidates(file: &EvidenceFile, value: f64) -> Vec<(&'static str, String)> {
+    fn walk(node: &Value, path: String, value: f64, out: &mut Vec<(&'static str, String)>) {
+        match node {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    let simple = !key.is_empty()
+                        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    let path = if simple {
+                        format!("{}.{}", path, key)
+                    } else {
+                        format!("{}['{}']", path, key.replace('\'', "\\'"))
+                    };
+                    walk(child, path, value, out);
+                }
+            }
+            Value::Array(items) => {
+                for (i, child) in items.iter().enumerate() {
+                    walk(child, format!("{}[{}]", path, i), value, out);
+                }
+            }
+            Value::Number(n) if n.as_f64() == Some(value) => out.push(("jsonpath", path)),
+            Value::String(s) if figure_value(s) == Some(value) => out.push(("jsonpath", path)),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    if let Ok(json) = serde_json::from_slice::<Value>(&file.content) {
+        walk(&json, "$".to_string(), value, &mut out);
+    }
+    out
+}
+
+fn csv_candidates(file: &EvidenceFile, value: f64) -> Vec<(&'static str, String)> {
+    let Ok(text) = std::str::from_utf8(&file.content) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for (row, record) in csv_records(text).iter().enumerate() {
+        for (col, field) in record.iter().enumerate() {
+            if figure_value(field) == Some(value) {
+                let cell = crate::selector::Selector::Cell {
+                    sheet: None,
+                    row,
+                    col,
+                };
+                out.push(("cell", cell.to_string()));
+            }
+        }
+    }
+    out
+}
+
+/// Numbers as they appear in running text.
+fn number_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"-?\d[\d,.]*\d%?|\d%?").expect("number pattern is valid"))
+}
+
+fn text_candidates(file: &EvidenceFile, value: f64) -> Vec<(&'static str, String)> {
+    let text = String::from_utf8_lossy(&file.content);
+    let mut out = Vec::new();
+    for line in text.lines() {
+        for number in number_pattern().find_iter(line) {
+            if figure_value(number.as_str()) != Some(value) {
+                continue;
+            }
+            // Anchor on up to 40 characters of the line before the number,
+            // starting at a word
+            let before = &line[..number.start()];
+            let mut start = before.len().saturating_sub(40);
+            while !before.is_char_boundary(start) {
+                start += 1;
+            }
+            let context = &before[start..];
+            let context = match context.find(char::is_whitespace) {
+                Some(space) if start > 0 => &context[space..],
+                _ => context,
+            };
+            let context = context.trim_start();
+            let selector = format!(
+                r"{}(-?\d[\d,.]*\d%?|\d%?)",
+                regex::escape(context).replace(' ', r"\s+")
+            );
+            out.push(("regex", selector));
+        }
+    }
+    out
+}
+
+/// Insert trace blocks into `body`, each after the line index it is paired
+/// with (a claim's [`Claim::paragraph_end`]), separated by blank lines.
+pub fn insert_traces(body: &str, traces: &[(usize, String)]) -> String {
+    let mut out = String::with_capacity(body.len());
+    for (index, line) in body.lines().enumerate() {
+        out.push_str(line);
+        out.push('\n');
+        for (_, block) in traces.iter().filter(|(after, _)| *after == index) {
+            out.push('\n');
+            out.push_str(block);
+        }
+    }
+    out
+}
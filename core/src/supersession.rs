@@ -0,0 +1,195 @@
+//! Links from a document version to the version that replaces it.
+//!
+//! A new document names the sealed version it replaces in `supersedes`,
+//! which its version hash covers. The replaced version names its successor
+//! in `superseded_by`, attached after sealing and so not hashed: the link is
+//! believed only because the successor's `supersedes` points back at it,
+//! and the successor is signed by the same key. [`check_links`] looks both
+//! ends up in the workspace and its history store, so readers of an old
+//! version learn which one is current.
+
+use crate::compiler::CompilationReport;
+use crate::crypto::parse_public_key;
+use crate::error::RhodiError;
+use crate::index::DocumentLocator;
+use crate::models::{DocStatus, DocumentLink, TracedDocument};
+use crate::version::VersionRegistry;
+use ed25519_dalek::VerifyingKey;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Check `doc`'s `supersedes` and `superseded_by` links and report its
+/// successor. A broken link is an error in a published document and a
+/// warning otherwise; a link the other end does not return is a warning.
+/// Linked versions are verified under the protocol versions in `versions`.
+pub fn check_links(
+    doc: &TracedDocument,
+    locator: &DocumentLocator,
+    versions: &VersionRegistry,
+) -> CompilationReport {
+    let mut report = CompilationReport::default();
+    let published = doc.frontmatter.doc_status == DocStatus::Published;
+    let broken = |report: &mut CompilationReport, message: String| {
+        if published {
+            report.errors.push(RhodiError::Verification(message));
+        } else {
+            report.warnings.push(format!("Link warning: {}", message));
+        }
+    };
+    let this = DocumentLink::to(doc);
+    // Both ends of a link must be signed by this version's key
+    let signer = doc
+        .frontmatter
+        .public_key
+        .as_deref()
+        .and_then(|key| parse_public_key(key).ok());
+    let signer = signer.as_ref();
+
+    if let Some(old) = &doc.frontmatter.supersedes {
+        if this.as_ref() == Some(old) {
+            broken(&mut report, "Document supersedes itself".to_string());
+        } else {
+            match find_intact(locator, old, signer, versions) {
+                Err(message) => broken(&mut report, format!("Superseded version {}: {}", old, message)),
+                Ok((path, found)) => match (&found.frontmatter.superseded_by, &this) {
+                    (Some(back), Some(this)) if back != this => broken(
+                        &mut report,
+                        format!(
+                            "Superseded version {} ({}) names {} as its successor instead",
+                            old,
+                            path.display(),
+                            back
+                        ),
+                    ),
+                    (None, Some(_)) => report.warnings.push(format!(
+                        "Link warning: {} ({}) does not link back to this version; run 'rhodi supersede {} <this document>'",
+                        old,
+                        path.display(),
+                        path.display()
+                    )),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    let Some(this) = this else {
+        if doc.frontmatter.superseded_by.is_some() {
+            broken(
+                &mut report,
+                "Document sets superseded_by, but only a sealed version can be superseded"
+                    .to_string(),
+            );
+        }
+        return report;
+    };
+    match &doc.frontmatter.superseded_by {
+        Some(new) => match find_intact(locator, new, signer, versions) {
+            Err(message) => broken(&mut report, format!("Successor {}: {}", new, message)),
+            Ok((path, found)) if found.frontmatter.supersedes.as_ref() != Some(&this) => broken(
+                &mut report,
+                format!(
+                    "Successor {} ({}) does not name this version in supersedes",
+                    new,
+                    path.display()
+                ),
+            ),
+            Ok((path, found)) => report
+                .warnings
+                .push(superseded_notice(locator, &path, &found, signer, versions)),
+        },
+        // A successor may exist that this version was never linked to
+        None if !published => {}
+        None => match locator.superseding(&this) {
+            Ok(successors) => {
+                for successor in successors {
+                    report.warnings.push(format!(
+                        "Superseded by '{}' ({}), which this version does not link back to; run 'rhodi supersede <this document> {}'",
+                        successor.title,
+                        successor.path.display(),
+                        successor.path.display()
+                    ));
+                }
+            }
+            Err(e) => report.warnings.push(format!(
+                "Link warning: cannot look for successors of this version: {}",
+                e
+            )),
+        },
+    }
+    report
+}
+
+/// The version `link` names, checked to still match its version hash and
+/// to be signed by `signer`, the key of the version linking to it, so no
+/// one else's document can claim the link. The error explains why it
+/// cannot be relied on.
+fn find_intact(
+    locator: &DocumentLocator,
+    link: &DocumentLink,
+    signer: Option<&VerifyingKey>,
+    versions: &VersionRegistry,
+) -> std::result::Result<(PathBuf, TracedDocument), String> {
+    let (path, doc) = locator
+        .find_version(link)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "not a sealed version in the workspace or its history".to_string())?;
+    if doc.current_version_hash().ok() != doc.frontmatter.version_hash {
+        return Err(format!(
+            "{} no longer matches its version hash",
+            path.display()
+        ));
+    }
+    let signer = signer
+        .ok_or_else(|| "this version has no public_key to check its signature".to_string())?;
+    doc.verify_in(signer.as_bytes(), versions).map_err(|e| {
+        format!(
+            "{} is not signed by this version's key: {}",
+            path.display(),
+            e
+        )
+    })?;
+    Ok((path, doc))
+}
+
+/// "Superseded by ..." for the successor at `path`, naming the current
+/// version when the successor has itself been superseded.
+fn superseded_notice(
+    locator: &DocumentLocator,
+    path: &Path,
+    successor: &TracedDocument,
+    signer: Option<&VerifyingKey>,
+    versions: &VersionRegistry,
+) -> String {
+    let describe = |path: &Path, doc: &TracedDocument| {
+        format!(
+            "'{}' v{} ({})",
+            doc.frontmatter.title,
+            doc.frontmatter.doc_version,
+            path.display()
+        )
+    };
+    let mut notice = format!("Superseded by {}", describe(path, successor));
+
+    // Follow the successor's own links, as far as they hold
+    let mut seen = HashSet::new();
+    let mut current = None;
+    let mut doc = successor.clone();
+    while let Some(next) = doc.frontmatter.superseded_by.clone() {
+        if !seen.insert(next.clone()) {
+            break;
+        }
+        let Ok((next_path, next_doc)) = find_intact(locator, &next, signer, versions) else {
+            break;
+        };
+        if next_doc.frontmatter.supersedes.as_ref() != DocumentLink::to(&doc).as_ref() {
+            break;
+        }
+        current = Some(describe(&next_path, &next_doc));
+        doc = next_doc;
+    }
+    if let Some(current) = current {
+        notice.push_str(&format!("; the current version is {}", current));
+    }
+    notice
+}
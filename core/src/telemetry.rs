@@ -0,0 +1,419 @@
+//! OpenTelemetry export of verification runs.
+//!
+//! A [`Telemetry`] handed to the compiler with
+//! [`Compiler::with_telemetry`](crate::compiler::Compiler::with_telemetry)
+//! records a span for every document verified (`rhodi.verify`), include
+//! entered (`rhodi.include`), trace checked (`rhodi.trace`) and source
+//! resolved (`rhodi.resolve`), and keeps metrics alongside: traces by
+//! outcome, resolver calls and bytes, cache hits, and trace and resolver
+//! latency histograms.
+//!
+//! [`Telemetry::export`] sends both to an OpenTelemetry collector over
+//! OTLP/HTTP in its JSON encoding
+//! (<https://opentelemetry.io/docs/specs/otlp/#otlphttp>), so organizations
+//! running `rhodi verify` in CI can follow verification latency and failure
+//! trends in their existing observability stack. The endpoint is the
+//! collector's base URL, as in `OTEL_EXPORTER_OTLP_ENDPOINT`; spans go to
+//! `/v1/traces` and metrics to `/v1/metrics` under it.
+
+use crate::error::{Result, RhodiError};
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable with the collector's base URL.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Environment variable naming the service in exported resources, `rhodi`
+/// by default.
+pub const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bucket bounds of the latency histograms, in milliseconds.
+const LATENCY_BOUNDS_MS: [f64; 12] = [
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 60000.0,
+];
+
+/// `SPAN_KIND_INTERNAL`
+const SPAN_KIND_INTERNAL: u8 = 1;
+/// `STATUS_CODE_OK` and `STATUS_CODE_ERROR`
+const STATUS_OK: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+/// `AGGREGATION_TEMPORALITY_CUMULATIVE`
+const TEMPORALITY_CUMULATIVE: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl AttributeValue {
+    /// The OTLP `AnyValue`; 64-bit integers are strings in its JSON.
+    fn otlp(&self) -> Value {
+        match self {
+            Self::String(s) => json!({ "stringValue": s }),
+            Self::Int(i) => json!({ "intValue": i.to_string() }),
+            Self::Bool(b) => json!({ "boolValue": b }),
+        }
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<usize> for AttributeValue {
+    fn from(value: usize) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+/// A finished or still open span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanRecord {
+    pub name: String,
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub start: SystemTime,
+    pub end: Option<SystemTime>,
+    pub attributes: Vec<(String, AttributeValue)>,
+    /// Why the operation failed, if it did
+    pub error: Option<String>,
+}
+
+/// Attribute pairs identifying one series of a metric.
+type Series = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Histogram {
+    count: u64,
+    sum: f64,
+    /// One count per bound, plus the overflow bucket
+    buckets: Vec<u64>,
+}
+
+/// Spans and metrics recorded during one or more verifications, all in a
+/// single trace.
+#[derive(Debug)]
+pub struct Telemetry {
+    service_name: String,
+    trace_id: [u8; 16],
+    started: SystemTime,
+    spans: RefCell<Vec<SpanRecord>>,
+    /// Indices of the spans currently open, innermost last
+    open: RefCell<Vec<usize>>,
+    counters: RefCell<BTreeMap<(String, Series), u64>>,
+    histograms: RefCell<BTreeMap<(String, Series), Histogram>>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Telemetry {
+    /// Record for the service named by `OTEL_SERVICE_NAME`, or `rhodi`.
+    pub fn new() -> Self {
+        let service_name = std::env::var(SERVICE_NAME_ENV)
+            .ok()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "rhodi".to_string());
+        Self {
+            service_name,
+            trace_id: rand::random(),
+            started: SystemTime::now(),
+            spans: RefCell::new(Vec::new()),
+            open: RefCell::new(Vec::new()),
+            counters: RefCell::new(BTreeMap::new()),
+            histograms: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Open a span named `name`, a child of the innermost open span. It
+    /// ends when the returned [`Span`] is dropped.
+    pub fn start_span(&self, name: &str) -> Span<'_> {
+        let mut spans = self.spans.borrow_mut();
+        let mut open = self.open.borrow_mut();
+        let parent_span_id = open.last().map(|&parent| spans[parent].span_id);
+        spans.push(SpanRecord {
+            name: name.to_string(),
+            span_id: rand::random(),
+            parent_span_id,
+            start: SystemTime::now(),
+            end: None,
+            attributes: Vec::new(),
+            error: None,
+        });
+        open.push(spans.len() - 1);
+        Span(Some((self, spans.len() - 1)))
+    }
+
+    /// Add `value` to the counter `name` of the series `attributes`.
+    pub fn add(&self, name: &str, value: u64, attributes: &[(&str, &str)]) {
+        *self
+            .counters
+            .borrow_mut()
+            .entry((name.to_string(), series(attributes)))
+            .or_default() += value;
+    }
+
+    /// Record a duration in the latency histogram `name`, in milliseconds.
+    pub fn record_latency(&self, name: &str, elapsed: Duration, attributes: &[(&str, &str)]) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let mut histograms = self.histograms.borrow_mut();
+        let histogram = histograms
+            .entry((name.to_string(), series(attributes)))
+            .or_insert_with(|| Histogram {
+                buckets: vec![0; LATENCY_BOUNDS_MS.len() + 1],
+                ..Histogram::default()
+            });
+        histogram.count += 1;
+        histogram.sum += ms;
+        let bucket = LATENCY_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(LATENCY_BOUNDS_MS.len());
+        histogram.buckets[bucket] += 1;
+    }
+
+    /// The spans recorded so far, in the order they were opened.
+    pub fn spans(&self) -> Vec<SpanRecord> {
+        self.spans.borrow().clone()
+    }
+
+    /// The value of the counter `name`, summed over all its series.
+    pub fn counter(&self, name: &str) -> u64 {
+        self.counters
+            .borrow()
+            .iter()
+            .filter(|((counter, _), _)| counter == name)
+            .map(|(_, value)| value)
+            .sum()
+    }
+
+    fn resource(&self) -> Value {
+        json!({
+            "attributes": attributes(&[
+                ("service.name".to_string(), self.service_name.as_str().into()),
+                ("service.version".to_string(), env!("CARGO_PKG_VERSION").into()),
+            ])
+        })
+    }
+
+    fn scope() -> Value {
+        json!({ "name": "rhodi", "version": env!("CARGO_PKG_VERSION") })
+    }
+
+    /// The finished spans as an OTLP `ExportTraceServiceRequest` in JSON.
+    pub fn traces_json(&self) -> Value {
+        let trace_id = hex::encode(self.trace_id);
+        let spans: Vec<Value> = self
+            .spans
+            .borrow()
+            .iter()
+            .filter_map(|span| {
+                let end = span.end?;
+                let status = match &span.error {
+                    Some(message) => json!({ "code": STATUS_ERROR, "message": message }),
+                    None => json!({ "code": STATUS_OK }),
+                };
+                Some(json!({
+                    "traceId": trace_id,
+                    "spanId": hex::encode(span.span_id),
+                    "parentSpanId": span.parent_span_id.map(hex::encode).unwrap_or_default(),
+                    "name": span.name,
+                    "kind": SPAN_KIND_INTERNAL,
+                    "startTimeUnixNano": unix_nanos(span.start),
+                    "endTimeUnixNano": unix_nanos(end),
+                    "attributes": attributes(&span.attributes),
+                    "status": status,
+                }))
+            })
+            .collect();
+        json!({
+            "resourceSpans": [{
+                "resource": self.resource(),
+                "scopeSpans": [{ "scope": Self::scope(), "spans": spans }],
+            }]
+        })
+    }
+
+    /// The metrics as an OTLP `ExportMetricsServiceRequest` in JSON:
+    /// cumulative sums for the counters and explicit-bucket histograms for
+    /// the latencies.
+    pub fn metrics_json(&self) -> Value {
+        let (start, now) = (unix_nanos(self.started), unix_nanos(SystemTime::now()));
+        let point_attributes = |series: &Series| {
+            let pairs: Vec<(String, AttributeValue)> = series
+                .iter()
+                .map(|(key, value)| (key.clone(), value.as_str().into()))
+                .collect();
+            attributes(&pairs)
+        };
+
+        let mut sums: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+        let counters = self.counters.borrow();
+        for ((name, series), value) in counters.iter() {
+            sums.entry(name).or_default().push(json!({
+                "attributes": point_attributes(series),
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "asInt": value.to_string(),
+            }));
+        }
+        let mut histograms: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+        let recorded = self.histograms.borrow();
+        for ((name, series), histogram) in recorded.iter() {
+            histograms.entry(name).or_default().push(json!({
+                "attributes": point_attributes(series),
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "count": histogram.count.to_string(),
+                "sum": histogram.sum,
+                "bucketCounts": histogram.buckets.iter().map(u64::to_string).collect::<Vec<_>>(),
+                "explicitBounds": LATENCY_BOUNDS_MS,
+            }));
+        }
+
+        let metrics: Vec<Value> = sums
+            .into_iter()
+            .map(|(name, points)| {
+                json!({
+                    "name": name,
+                    "sum": {
+                        "dataPoints": points,
+                        "aggregationTemporality": TEMPORALITY_CUMULATIVE,
+                        "isMonotonic": true,
+                    }
+                })
+            })
+            .chain(histograms.into_iter().map(|(name, points)| {
+                json!({
+                    "name": name,
+                    "unit": "ms",
+                    "histogram": {
+                        "dataPoints": points,
+                        "aggregationTemporality": TEMPORALITY_CUMULATIVE,
+                    }
+                })
+            }))
+            .collect();
+        json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{ "scope": Self::scope(), "metrics": metrics }],
+            }]
+        })
+    }
+
+    /// Send the spans and metrics to the OTLP/HTTP collector at `endpoint`.
+    pub fn export(&self, endpoint: &str) -> Result<()> {
+        let agent = ureq::AgentBuilder::new().timeout(EXPORT_TIMEOUT).build();
+        let base = endpoint.trim_end_matches('/');
+        for (path, body) in [
+            ("v1/traces", self.traces_json()),
+            ("v1/metrics", self.metrics_json()),
+        ] {
+            let url = format!("{}/{}", base, path);
+            agent
+                .post(&url)
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())
+                .map_err(|e| {
+                    RhodiError::Io(std::io::Error::other(format!(
+                        "OTLP export to {} failed: {}",
+                        url, e
+                    )))
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// An open span, ended when dropped. A span of a compiler without
+/// telemetry records nothing.
+pub struct Span<'t>(Option<(&'t Telemetry, usize)>);
+
+impl Span<'_> {
+    /// A span that records nothing.
+    pub(crate) fn disabled() -> Self {
+        Self(None)
+    }
+
+    pub fn set_attribute(&self, key: &str, value: impl Into<AttributeValue>) {
+        if let Some((telemetry, index)) = self.0 {
+            telemetry.spans.borrow_mut()[index]
+                .attributes
+                .push((key.to_string(), value.into()));
+        }
+    }
+
+    /// Mark the operation as failed, with `message` as the status message.
+    pub fn set_error(&self, message: impl std::fmt::Display) {
+        if let Some((telemetry, index)) = self.0 {
+            telemetry.spans.borrow_mut()[index].error = Some(message.to_string());
+        }
+    }
+}
+
+impl Drop for Span<'_> {
+    fn drop(&mut self) {
+        if let Some((telemetry, index)) = self.0 {
+            telemetry.spans.borrow_mut()[index].end = Some(SystemTime::now());
+            let mut open = telemetry.open.borrow_mut();
+            if let Some(position) = open.iter().rposition(|&i| i == index) {
+                open.remove(position);
+            }
+        }
+    }
+}
+
+fn series(attributes: &[(&str, &str)]) -> Series {
+    let mut series: Series = attributes
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    series.sort();
+    series
+}
+
+fn attributes(pairs: &[(String, AttributeValue)]) -> Vec<Value> {
+    pairs
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": value.otlp() }))
+        .collect()
+}
+
+/// Nanoseconds since the Unix epoch, as the string OTLP JSON uses for
+/// 64-bit integers.
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
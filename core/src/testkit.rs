@@ -0,0 +1,250 @@
+//! Deterministic fixtures and golden hash vectors.
+//!
+//! Everything built here is fixed: ids, timestamps and keys come from
+//! constants, so a fixture hashes the same on every machine and every run.
+//! [`GOLDEN_VECTORS`] pins the version hash of a reference document for each
+//! protocol version. Integrations that wrap or re-implement hashing can call
+//! [`check_golden_vectors`] in their own tests to prove they still produce
+//! hashes existing documents verify against.
+
+use crate::crypto::KeyPair;
+use crate::error::{Result, RhodiError};
+use crate::models::{DocStatus, TraceBlock, TraceBlockBuilder, TraceMethod, TracedDocument};
+use chrono::{DateTime, TimeZone, Utc};
+use ed25519_dalek::SigningKey;
+use uuid::Uuid;
+
+/// Id given to every fixture document.
+pub const FIXTURE_ID: Uuid = Uuid::from_u128(0x0190_0000_0000_7000_8000_0000_0000_0001);
+
+/// Seed of [`fixture_keypair`]. Never use it for real documents.
+pub const FIXTURE_KEY_SEED: [u8; 32] = [7; 32];
+
+/// Timestamp used for `created_at` and `modified_at` of fixtures.
+pub fn fixture_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0)
+        .single()
+        .expect("fixture time is valid")
+}
+
+/// A keypair derived from [`FIXTURE_KEY_SEED`], so fixture signatures are
+/// reproducible.
+pub fn fixture_keypair() -> KeyPair {
+    let signing_key = SigningKey::from_bytes(&FIXTURE_KEY_SEED);
+    KeyPair {
+        verifying_key: signing_key.verifying_key(),
+        signing_key,
+    }
+}
+
+/// Builds an automatic trace block with [`TraceBlockBuilder`].
+#[derive(Debug, Clone)]
+pub struct TraceBuilder {
+    trace: TraceBlockBuilder,
+}
+
+impl TraceBuilder {
+    pub fn new(source: &str, expected: &str) -> Self {
+        Self {
+            trace: TraceBlock::builder()
+                .source(source)
+                .expected(expected)
+                .method(TraceMethod::Automatic),
+        }
+    }
+
+    pub fn selector(self, selector: &str) -> Self {
+        Self {
+            trace: self.trace.selector(selector),
+        }
+    }
+
+    pub fn extractor(self, extractor: &str) -> Self {
+        Self {
+            trace: self.trace.extractor(extractor),
+        }
+    }
+
+    /// Pin the source hash (`sha256:` hex).
+    pub fn hash(self, hash: &str) -> Self {
+        Self {
+            trace: self.trace.hash(hash),
+        }
+    }
+
+    pub fn transform(self, step: &str) -> Self {
+        Self {
+            trace: self.trace.transform(step),
+        }
+    }
+
+    /// Panics if the fields do not make sense together, as
+    /// [`TraceBlockBuilder::build`] checks them.
+    pub fn build(self) -> TraceBlock {
+        self.trace.build().expect("a fixture trace is valid")
+    }
+}
+
+/// Builds a document with fixed id and timestamps.
+#[derive(Debug, Clone)]
+pub struct DocumentBuilder {
+    doc: TracedDocument,
+}
+
+impl DocumentBuilder {
+    pub fn new(title: &str) -> Self {
+        let mut doc = TracedDocument::new(title, "");
+        doc.frontmatter.id = FIXTURE_ID;
+        doc.frontmatter.created_at = fixture_time();
+        Self { doc }
+    }
+
+    pub fn protocol_version(mut self, version: &str) -> Self {
+        self.doc.frontmatter.protocol_version = version.to_string();
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.doc.frontmatter.author = Some(author.into());
+        self
+    }
+
+    pub fn status(mut self, status: DocStatus) -> Self {
+        self.doc.frontmatter.doc_status = status;
+        self
+    }
+
+    pub fn extra(self, key: &str, value: &str) -> Self {
+        Self {
+            doc: self.doc.extra_info(key, value),
+        }
+    }
+
+    /// Append a paragraph, separated from what precedes it by a blank line.
+    pub fn paragraph(mut self, text: &str) -> Self {
+        self.separate();
+        self.doc.body.push_str(text);
+        self.doc.body.push('\n');
+        self
+    }
+
+    /// Append a fenced trace block.
+    pub fn trace(mut self, trace: TraceBlock) -> Self {
+        self.separate();
+        let yaml = serde_norway::to_string(&trace).expect("trace blocks serialize");
+        self.doc.body.push_str("```trace\n");
+        self.doc.body.push_str(&yaml);
+        self.doc.body.push_str("```\n");
+        self
+    }
+
+    fn separate(&mut self) {
+        if !self.doc.body.is_empty() {
+            self.doc.body.push('\n');
+        }
+    }
+
+    pub fn build(self) -> TracedDocument {
+        self.doc
+    }
+
+    /// Seal with `keypair`, fixing `modified_at` so the version hash and
+    /// signature are reproducible.
+    pub fn sealed(self, keypair: &KeyPair) -> TracedDocument {
+        let mut doc = self.doc;
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        seal_at_fixture_time(doc, keypair).expect("a fixture seals with its own key")
+    }
+}
+
+fn seal_at_fixture_time(doc: TracedDocument, keypair: &KeyPair) -> Result<TracedDocument> {
+    let mut doc = doc.prepare_seal()?;
+    doc.frontmatter.modified_at = Some(fixture_time());
+    doc.frontmatter.environment = None;
+    let hash = doc.compute_version_hash()?;
+    doc.frontmatter.version_hash = Some(hash);
+    doc.frontmatter.hash_manifest = Some(doc.component_digests()?);
+    doc.complete_seal(keypair.sign(&hash))
+}
+
+/// The pinned version hash of [`golden_document`] at one protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoldenVector {
+    pub protocol_version: &'static str,
+    /// Hex-encoded `compute_version_hash` of the golden document
+    pub version_hash: &'static str,
+}
+
+/// One vector per protocol version. These values must never change: a
+/// document sealed by any release has to keep verifying.
+pub const GOLDEN_VECTORS: &[GoldenVector] = &[
+    GoldenVector {
+        protocol_version: "1.0",
+        version_hash: "9e525529707e0e97ecead65374e35f16f22f9a402fd890f4a7c6e937f054cc07",
+    },
+    GoldenVector {
+        protocol_version: "1.1",
+        version_hash: "d63f9732ec2a45aa13b45dfbbb7c0e002c838dd115151c1c96453474a8817d89",
+    },
+    GoldenVector {
+        protocol_version: "2.0",
+        version_hash: "0852436f740865cb3b61ed03af99f208b979dc024f6a6509da6a4406c80e8f70",
+    },
+    GoldenVector {
+        protocol_version: "2.1",
+        version_hash: "af6d7d07277c90c2c0e8ac1ae9ccd6c91d7e7f306ea6bf5d65cc6e631557d49b",
+    },
+    GoldenVector {
+        protocol_version: "2.2",
+        version_hash: "249ac49a15a69aef1d1f135fcc188f279e7255c8f70d478a066c14fc2a2975b5",
+    },
+    GoldenVector {
+        protocol_version: "2.3",
+        version_hash: "2557d53453a7a2b1c3f737e08e0faa893141cb8c62486eca2027c086b378937a",
+    },
+    GoldenVector {
+        protocol_version: "2.4",
+        version_hash: "6859716085dffe0b35f99f8e18e8f4dec22c7fee841ab2dfe0a0f20f4b3a18dc",
+    },
+];
+
+/// Body of [`golden_document`]. It is spelled out rather than built, so
+/// that serialization changes cannot move the vectors. It exercises body
+/// canonicalization (CRLF, trailing spaces, a format character) and a trace
+/// block.
+const GOLDEN_BODY: &str = "# Golden Report\r\n\r\nSales reached 1,204 units.   \r\nCaf\u{e9}\u{200B} prices held.\r\n\r\n```trace\nsource: data/sales.csv\nhash: sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08\nselector: '/Total: ([\\d,]+)/'\nexpected: \"1,204\"\ntransforms:\n- trim\n```\n\nClosing remarks.\n";
+
+/// The reference document behind [`GOLDEN_VECTORS`]. It sets the optional
+/// hashed fields that every protocol version knows: author, public key,
+/// modification time, previous hash, trace age policy and an extra field.
+pub fn golden_document(protocol_version: &str) -> TracedDocument {
+    let mut doc = DocumentBuilder::new("Golden Report")
+        .protocol_version(protocol_version)
+        .author("Ada Lovelace")
+        .status(DocStatus::Draft)
+        .extra("department", "Research")
+        .build();
+    doc.body = GOLDEN_BODY.to_string();
+    doc.frontmatter.modified_at = Some(fixture_time());
+    doc.frontmatter.public_key = Some(hex::encode(fixture_keypair().verifying_key.as_bytes()));
+    doc.frontmatter.doc_version = 3;
+    doc.frontmatter.prev_version_hash = Some([0xab; 32]);
+    doc.frontmatter.policy.max_trace_age = Some("90d".to_string());
+    doc
+}
+
+/// Check that [`golden_document`] still hashes to every [`GOLDEN_VECTORS`]
+/// entry, naming the first protocol version that does not.
+pub fn check_golden_vectors() -> Result<()> {
+    for vector in GOLDEN_VECTORS {
+        let computed =
+            hex::encode(golden_document(vector.protocol_version).compute_version_hash()?);
+        if computed != vector.version_hash {
+            return Err(RhodiError::Verification(format!(
+                "Version hash of the golden document changed for protocol {}: expected {}, got {}",
+                vector.protocol_version, vector.version_hash, computed
+            )));
+        }
+    }
+    Ok(())
+}
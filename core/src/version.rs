@@ -1,26 +1,440 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::error::{Result, RhodiError};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Ordered from least to most severe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VersionStatus {
+    #[default]
     Current,
     Deprecated,
     Obsolete,
 }
 
+/// The protocol versions this build implements. Workspaces add their own
+/// versions and change the status of these in their [`VersionRegistry`].
 pub const VERSION_REGISTRY: &[(&str, VersionStatus)] = &[
     ("1.0", VersionStatus::Current),
     ("1.1", VersionStatus::Current),
     ("2.0", VersionStatus::Current),
+    ("2.1", VersionStatus::Current),
+    ("2.2", VersionStatus::Current),
+    ("2.3", VersionStatus::Current),
+    ("2.4", VersionStatus::Current),
 ];
 
 pub const DEFAULT_PROTOCOL_VERSION: &str = "1.0";
 
-pub fn get_version_status(version: &str) -> VersionStatus {
+/// How a protocol version encodes the document for its version hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashingScheme {
+    /// 1.x: canonical body followed by `serde_json` of a flat string map
+    Legacy,
+    /// 2.x: RFC 8785 canonical JSON of the body and typed frontmatter
+    CanonicalJson,
+}
+
+impl HashingScheme {
+    /// Short name recorded in a document's `environment.canonicalization`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Legacy => "legacy",
+            Self::CanonicalJson => "jcs-rfc8785",
+        }
+    }
+}
+
+/// What a protocol version does to text before hashing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanonicalizationProfile {
+    /// Up to 2.0: LF line endings, no trailing whitespace, no control or
+    /// format characters
+    Basic,
+    /// 2.1 and later: as `Basic`, then Unicode NFC, so text typed or pasted
+    /// in decomposed form (as macOS produces it) hashes like composed text
+    Nfc,
+}
+
+impl CanonicalizationProfile {
+    /// Suffix added to the hashing scheme's name in a document's
+    /// `environment.canonicalization`.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Basic => "",
+            Self::Nfc => "+nfc",
+        }
+    }
+}
+
+/// A protocol version known to the registry, with its status and the
+/// advice shown to documents using it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VersionEntry {
+    pub version: String,
+    #[serde(default)]
+    pub status: VersionStatus,
+    /// Built-in version whose hashing and canonicalization rules this one
+    /// follows; required for versions that are not built in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub based_on: Option<String>,
+    /// Day from which the version is deprecated, whatever `status` says
+    #[serde(
+        default,
+        deserialize_with = "deserialize_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deprecated_on: Option<NaiveDate>,
+    /// Day from which the version is obsolete
+    #[serde(
+        default,
+        deserialize_with = "deserialize_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub obsolete_on: Option<NaiveDate>,
+    /// Migration advice, shown with deprecation warnings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl VersionEntry {
+    pub fn new(version: impl Into<String>, status: VersionStatus) -> Self {
+        Self {
+            version: version.into(),
+            status,
+            based_on: None,
+            deprecated_on: None,
+            obsolete_on: None,
+            note: None,
+        }
+    }
+
+    /// The status on `date`: `status`, or what the dates have reached if
+    /// that is more severe.
+    pub fn status_on(&self, date: NaiveDate) -> VersionStatus {
+        let reached =
+            |day: Option<NaiveDate>, status| day.filter(|day| *day <= date).map(|_| status);
+        [
+            Some(self.status),
+            reached(self.deprecated_on, VersionStatus::Deprecated),
+            reached(self.obsolete_on, VersionStatus::Obsolete),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default()
+    }
+}
+
+/// A date written as a string or, in TOML, as a bare local date.
+fn deserialize_date<'de, D>(deserializer: D) -> std::result::Result<Option<NaiveDate>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        Text(NaiveDate),
+        Toml(toml::value::Datetime),
+    }
+    match Date::deserialize(deserializer)? {
+        Date::Text(date) => Ok(Some(date)),
+        Date::Toml(datetime) => match (datetime.date, datetime.time) {
+            (Some(date), None) => {
+                NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())
+                    .map(Some)
+                    .ok_or_else(|| serde::de::Error::custom(format!("invalid date {}", date)))
+            }
+            _ => Err(serde::de::Error::custom(format!(
+                "expected a date, not {}",
+                datetime
+            ))),
+        },
+    }
+}
+
+/// The protocol versions a workspace knows: the built-in ones, with the
+/// entries its configuration adds or overrides. The default knows the
+/// built-in versions only.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionRegistry {
+    entries: Vec<VersionEntry>,
+}
+
+impl VersionRegistry {
+    /// The built-in versions with `entries` added, each replacing earlier
+    /// entries for the same version. An entry for a built-in version may
+    /// change its status, dates and note but not its rules; any other entry
+    /// names the built-in version it is `based_on`, and its name extends
+    /// that version's (`2.1-acme`), as the rules follow from the name.
+    /// Fails if an entry is invalid.
+    pub fn new(entries: &[VersionEntry]) -> Result<Self> {
+        for entry in entries {
+            let invalid = |reason: String| {
+                RhodiError::Format(format!(
+                    "Invalid protocol version entry '{}': {}",
+                    entry.version, reason
+                ))
+            };
+            if entry.version.trim().is_empty() {
+                return Err(invalid("the version is empty".into()));
+            }
+            match (builtin_status(&entry.version), &entry.based_on) {
+                (Some(_), Some(base)) if *base != entry.version => {
+                    return Err(invalid(format!(
+                        "a built-in version cannot be based on {}",
+                        base
+                    )));
+                }
+                (None, None) => {
+                    return Err(invalid("name the built-in version it is based_on".into()));
+                }
+                (None, Some(base)) if builtin_status(base).is_none() => {
+                    return Err(invalid(format!("{} is not a built-in version", base)));
+                }
+                (None, Some(base)) if rules_version(&entry.version) != base => {
+                    return Err(invalid(format!(
+                        "a version based on {0} is named {0}-<label> or {0}+<label>",
+                        base
+                    )));
+                }
+                _ => {}
+            }
+            if let (Some(deprecated), Some(obsolete)) = (entry.deprecated_on, entry.obsolete_on)
+                && obsolete < deprecated
+            {
+                return Err(invalid(format!(
+                    "obsolete_on ({}) is before deprecated_on ({})",
+                    obsolete, deprecated
+                )));
+            }
+        }
+
+        let mut registry = Self::default();
+        for entry in entries {
+            registry
+                .entries
+                .retain(|existing| existing.version != entry.version);
+            registry.entries.push(entry.clone());
+        }
+        Ok(registry)
+    }
+
+    /// The entry for `version`: the configured one, else the built-in one.
+    pub fn entry(&self, version: &str) -> Option<VersionEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.version == version)
+            .cloned()
+            .or_else(|| builtin_status(version).map(|status| VersionEntry::new(version, status)))
+    }
+
+    pub fn is_known(&self, version: &str) -> bool {
+        self.entry(version).is_some()
+    }
+
+    /// The status of `version` today. Unknown versions are obsolete.
+    pub fn status(&self, version: &str) -> VersionStatus {
+        self.entry(version)
+            .map(|entry| entry.status_on(Utc::now().date_naive()))
+            .unwrap_or(VersionStatus::Obsolete)
+    }
+
+    /// A warning for documents on `version` while it is deprecated, with the
+    /// dates and note of its entry.
+    pub fn deprecation_notice(&self, version: &str) -> Option<String> {
+        let entry = self.entry(version)?;
+        let today = Utc::now().date_naive();
+        if entry.status_on(today) != VersionStatus::Deprecated {
+            return None;
+        }
+        let mut notice = format!("Protocol version {} is deprecated", version);
+        if let Some(since) = entry.deprecated_on {
+            notice.push_str(&format!(" since {}", since));
+        }
+        if let Some(obsolete) = entry.obsolete_on {
+            notice.push_str(&format!(" and becomes obsolete on {}", obsolete));
+        }
+        if let Some(note) = &entry.note {
+            notice.push_str(&format!(". {}", note));
+        }
+        Some(notice)
+    }
+
+    /// Fail unless documents on `version` can be verified: it is known and
+    /// not obsolete.
+    pub fn check_supported(&self, version: &str) -> Result<()> {
+        let Some(entry) = self.entry(version) else {
+            return Err(RhodiError::Verification(format!(
+                "Unknown protocol version: {}. Document may be from a future or obsolete version.",
+                version
+            )));
+        };
+        if entry.status_on(Utc::now().date_naive()) == VersionStatus::Obsolete {
+            let note = entry
+                .note
+                .map(|note| format!(" {}", note))
+                .unwrap_or_default();
+            return Err(RhodiError::Verification(format!(
+                "Protocol version {} is obsolete and no longer supported.{}",
+                version, note
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn builtin_status(version: &str) -> Option<VersionStatus> {
     VERSION_REGISTRY
         .iter()
         .find(|(v, _)| *v == version)
         .map(|(_, status)| *status)
-        .unwrap_or(VersionStatus::Obsolete)
 }
 
+/// The built-in version whose rules `version` follows: itself, or the one
+/// its name extends (`2.1-acme` follows 2.1). The rules thus follow from
+/// the version alone, and a document hashes the same whichever registry
+/// verifies it.
+fn rules_version(version: &str) -> &str {
+    VERSION_REGISTRY
+        .iter()
+        .map(|(base, _)| *base)
+        .filter(|base| {
+            version
+                .strip_prefix(base)
+                .is_some_and(|label| label.is_empty() || label.starts_with(['-', '+']))
+        })
+        .max_by_key(|base| base.len())
+        .unwrap_or(version)
+}
+
+/// The (major, minor) release whose rules `version` follows.
+fn rules_release(version: &str) -> (u32, u32) {
+    let mut parts = rules_version(version)
+        .split('.')
+        .map(|p| p.parse::<u32>().unwrap_or(0));
+    (parts.next().unwrap_or(1), parts.next().unwrap_or(0))
+}
+
+pub fn canonicalization_profile(version: &str) -> CanonicalizationProfile {
+    if rules_release(version) >= (2, 1) {
+        CanonicalizationProfile::Nfc
+    } else {
+        CanonicalizationProfile::Basic
+    }
+}
+
+/// Whether `version` leaves review blocks out of the version hash, as 2.2
+/// and later do. Earlier versions hash them like any other body text.
+pub fn excludes_review_blocks(version: &str) -> bool {
+    rules_release(version) >= (2, 2)
+}
+
+/// Whether `version` leaves the `verified_at` and `last_result` trace
+/// annotations out of the version hash, as 2.3 and later do. Earlier
+/// versions hash them like any other body text.
+pub fn excludes_trace_annotations(version: &str) -> bool {
+    rules_release(version) >= (2, 3)
+}
+
+/// Whether `version` hashes `redact` blocks by their commitments, as 2.4
+/// and later do, so a redacted rendition verifies under the original seal.
+/// Earlier versions hash them like any other body text.
+pub fn commits_redactions(version: &str) -> bool {
+    rules_release(version) >= (2, 4)
+}
+
+pub fn hashing_scheme(version: &str) -> HashingScheme {
+    let major: u32 = rules_version(version)
+        .split('.')
+        .next()
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(1);
+    if major >= 2 {
+        HashingScheme::CanonicalJson
+    } else {
+        HashingScheme::Legacy
+    }
+}
+
+/// One step in the upgrade path between adjacent protocol versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    /// What the step rewrites, shown by `rhodi migrate --dry-run`
+    pub summary: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: "1.0",
+        to: "1.1",
+        summary: "Additive release; only protocol_version changes",
+    },
+    Migration {
+        from: "1.1",
+        to: "2.0",
+        summary: "Hashes with RFC 8785 canonical JSON and stores the body in canonical form",
+    },
+    Migration {
+        from: "2.0",
+        to: "2.1",
+        summary: "Normalizes hashed text to Unicode NFC and stores the body in NFC",
+    },
+    Migration {
+        from: "2.1",
+        to: "2.2",
+        summary: "Leaves review blocks out of the version hash",
+    },
+    Migration {
+        from: "2.2",
+        to: "2.3",
+        summary: "Leaves trace result annotations out of the version hash",
+    },
+    Migration {
+        from: "2.3",
+        to: "2.4",
+        summary: "Hashes redact blocks by their commitments",
+    },
+];
+
+/// The ordered migration steps leading from `from` to `to`.
+pub fn migration_path(from: &str, to: &str) -> Result<Vec<Migration>> {
+    if !is_version_known(to) {
+        return Err(RhodiError::Format(format!(
+            "Unknown protocol version: {}",
+            to
+        )));
+    }
+
+    let mut steps = Vec::new();
+    let mut current = from;
+    while current != to {
+        let step = MIGRATIONS
+            .iter()
+            .find(|m| m.from == current)
+            .ok_or_else(|| {
+                RhodiError::Format(format!(
+                    "No migration path from protocol version {} to {}",
+                    from, to
+                ))
+            })?;
+        steps.push(*step);
+        current = step.to;
+    }
+    Ok(steps)
+}
+
+/// The status of a built-in `version`; see [`VersionRegistry::status`]
+/// for a workspace's versions.
+pub fn get_version_status(version: &str) -> VersionStatus {
+    VersionRegistry::default().status(version)
+}
+
+/// The newest built-in version. Registered versions are never chosen
+/// implicitly.
 pub fn get_latest_version() -> &'static str {
     VERSION_REGISTRY
         .iter()
@@ -34,6 +448,8 @@ pub fn get_latest_version() -> &'static str {
         .unwrap_or(DEFAULT_PROTOCOL_VERSION)
 }
 
+/// Whether `version` is built in; see [`VersionRegistry::is_known`] for a
+/// workspace's versions.
 pub fn is_version_known(version: &str) -> bool {
-    VERSION_REGISTRY.iter().any(|(v, _)| *v == version)
+    builtin_status(version).is_some()
 }
@@ -0,0 +1,139 @@
+//! The `rhodi` command line tool, run as built. Runs as an integration
+//! test so Cargo builds the binary and names it.
+#![cfg(feature = "cli")]
+
+use rhodi_core::crypto::hash_bytes;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const RHODI: &str = env!("CARGO_BIN_EXE_rhodi");
+
+/// A fresh directory for one test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rhodi-cli-{}-{}", name, uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run `rhodi` with `args` in `dir`.
+fn rhodi(dir: &Path, args: &[&str]) -> Output {
+    Command::new(RHODI)
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir.join(".config"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn hash_prints_the_digest_traces_pin() {
+    let dir = temp_dir("hash");
+    let content = b"region,total\nnorth,1204\n";
+    std::fs::write(dir.join("sales.csv"), content).unwrap();
+
+    let printed = stdout(&rhodi(&dir, &["hash", "sales.csv"]));
+    assert_eq!(printed, format!("{}\n", hash_bytes(content)));
+
+    // With a selector, the lines are ready to paste into a trace block
+    let printed = stdout(&rhodi(
+        &dir,
+        &["hash", "sales.csv", "--selector", r"north,(\d+)"],
+    ));
+    assert_eq!(
+        printed,
+        format!("hash: {}\nexpected: \"1204\"\n", hash_bytes(content))
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A draft whose one trace reads `expected` from `sales.csv`.
+fn draft(expected: &str) -> String {
+    format!(
+        "---\nid: 01900000-0000-7000-8000-000000000001\ntitle: Sales\ncreated_at: 2025-01-01T00:00:00Z\n\
+         doc_status: draft\ndoc_version: 1\nprotocol_version: '2.4'\n---\n\n\
+         North sold {} units.\n\n```trace\nsource: sales.csv\nselector: 'north,(\\d+)'\n\
+         expected: \"{}\"\n```\n",
+        expected, expected
+    )
+}
+
+#[test]
+fn check_agrees_with_seal_and_writes_nothing() {
+    let dir = temp_dir("check");
+    std::fs::write(dir.join("sales.csv"), "region,total\nnorth,1204\n").unwrap();
+    stdout(&rhodi(&dir, &["keygen"]));
+
+    // Go: check passes without touching the file, then seal passes too
+    std::fs::write(dir.join("ready.tmd"), draft("1204")).unwrap();
+    let before = std::fs::read(dir.join("ready.tmd")).unwrap();
+    stdout(&rhodi(&dir, &["check", "ready.tmd"]));
+    assert_eq!(std::fs::read(dir.join("ready.tmd")).unwrap(), before);
+    stdout(&rhodi(&dir, &["seal", "ready.tmd"]));
+    assert_ne!(std::fs::read(dir.join("ready.tmd")).unwrap(), before);
+
+    // No-go: check fails without touching the file, and seal refuses it too
+    std::fs::write(dir.join("wrong.tmd"), draft("1205")).unwrap();
+    let before = std::fs::read(dir.join("wrong.tmd")).unwrap();
+    let output = rhodi(&dir, &["check", "wrong.tmd"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Not ready to seal"));
+    assert_eq!(std::fs::read(dir.join("wrong.tmd")).unwrap(), before);
+    assert!(!rhodi(&dir, &["seal", "wrong.tmd"]).status.success());
+    assert_eq!(std::fs::read(dir.join("wrong.tmd")).unwrap(), before);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn hooks_install_writes_executable_hooks() {
+    let dir = temp_dir("hooks");
+    let git = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    assert!(git.success());
+
+    stdout(&rhodi(&dir, &["hooks", "install"]));
+    for name in ["pre-commit", "pre-push"] {
+        let path = dir.join(".git/hooks").join(name);
+        let script = std::fs::read_to_string(&path).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n# Installed by rhodi hooks install\n"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0o111, "{} is not executable", name);
+        }
+    }
+    let pre_commit = std::fs::read_to_string(dir.join(".git/hooks/pre-commit")).unwrap();
+    assert!(pre_commit.contains("verify --staged-only --skip-traces"));
+    let pre_push = std::fs::read_to_string(dir.join(".git/hooks/pre-push")).unwrap();
+    assert!(pre_push.contains("--rev \"$local_sha\" --skip-traces"));
+
+    // A hook rhodi did not write is kept unless --force
+    std::fs::write(dir.join(".git/hooks/pre-commit"), "#!/bin/sh\nmine\n").unwrap();
+    assert!(!rhodi(&dir, &["hooks", "install"]).status.success());
+    assert_eq!(
+        std::fs::read_to_string(dir.join(".git/hooks/pre-commit")).unwrap(),
+        "#!/bin/sh\nmine\n"
+    );
+    stdout(&rhodi(&dir, &["hooks", "install", "--force"]));
+    assert_eq!(
+        std::fs::read_to_string(dir.join(".git/hooks/pre-commit")).unwrap(),
+        pre_commit
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
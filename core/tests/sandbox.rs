@@ -0,0 +1,90 @@
+//! Confinement of the real sandbox helper, `rhodi sandbox-extract`. Runs
+//! as an integration test so Cargo builds the binary and names it.
+#![cfg(all(target_os = "linux", feature = "cli"))]
+
+use rhodi_core::compiler::Compiler;
+use rhodi_core::extraction::ExecExtractor;
+use rhodi_core::models::{TraceBlock, TraceResult, TracedDocument};
+use rhodi_core::resolver::FileResolver;
+use rhodi_core::sandbox::Sandbox;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+const HELPER: &str = env!("CARGO_BIN_EXE_rhodi");
+
+/// Check a trace on `data.txt` in `dir` through the real helper, with
+/// `commands` as the workspace's `exec` commands.
+fn check(dir: &Path, selector: &str, commands: &[(&str, Vec<String>)]) -> TraceResult {
+    let trace = TraceBlock::builder()
+        .source("data.txt")
+        .selector(selector)
+        .extractor(if commands.is_empty() { "regex" } else { "exec" })
+        .expected("42")
+        .build()
+        .unwrap();
+    let commands: BTreeMap<String, Vec<String>> = commands
+        .iter()
+        .map(|(name, argv)| (name.to_string(), argv.clone()))
+        .collect();
+    let sandbox = Sandbox::new(vec![HELPER.to_string(), "sandbox-extract".to_string()])
+        .with_timeout(Duration::from_secs(10));
+    let resolver = FileResolver::new(dir).unwrap().with_sandbox(Some(sandbox));
+    Compiler::new(&resolver)
+        .with_exec_extractor(Some(ExecExtractor::new(commands, dir)))
+        .check_document_trace(&TracedDocument::new("Sandboxed", "Body"), &trace)
+}
+
+#[test]
+fn helper_cannot_read_the_workspace_or_open_sockets() {
+    let dir = std::env::temp_dir().join(format!("rhodi-sandbox-{}", uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("data.txt"), "total=42\n").unwrap();
+    let secret = dir.join("secret.txt");
+    std::fs::write(&secret, "42\n").unwrap();
+
+    let result = check(&dir, r"total=(\d+)", &[]);
+    if let Some(error) = result.error.as_deref()
+        && error.contains("Cannot sandbox extraction")
+    {
+        eprintln!("skipped: {}", error);
+        return;
+    }
+    assert!(result.passed, "{:?}", result.error);
+
+    // The helper sends normalized sources back for the verifier to hash
+    std::fs::write(dir.join("page.html"), "<p>total=42</p>").unwrap();
+    let trace = TraceBlock::builder()
+        .source("page.html")
+        .selector(r"total=(\d+)")
+        .extractor("regex")
+        .normalize("html")
+        .expected("42")
+        .hash(&rhodi_core::crypto::hash_bytes(b"total=42"))
+        .build()
+        .unwrap();
+    let sandbox = Sandbox::new(vec![HELPER.to_string(), "sandbox-extract".to_string()]);
+    let resolver = FileResolver::new(&dir).unwrap().with_sandbox(Some(sandbox));
+    let result = Compiler::new(&resolver)
+        .check_document_trace(&TracedDocument::new("Sandboxed", "Body"), &trace);
+    assert!(result.passed, "{:?}", result.error);
+
+    // The helper gets the source on stdin; the workspace itself is out of
+    // reach, even for a file the verifier can read
+    let peek = vec!["cat".to_string(), secret.display().to_string()];
+    let result = check(&dir, "peek", &[("peek", peek)]);
+    let error = result.error.unwrap();
+    assert!(error.contains("Permission denied"), "{}", error);
+
+    // Binding a listener needs a socket, which the helper cannot open
+    let listen = ["serve", "--addr", "127.0.0.1:0"];
+    let listen = std::iter::once(HELPER)
+        .chain(listen)
+        .map(str::to_string)
+        .collect();
+    let result = check(&dir, "listen", &[("listen", listen)]);
+    let error = result.error.unwrap();
+    assert!(error.contains("Permission denied"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,82 @@
+/*
+ * rhodi.h - C ABI for verifying Trace Protocol (.tmd) documents in-process.
+ *
+ * Link against librhodi (built by the rhodi-ffi crate). Structs only ever
+ * grow: new fields are appended and each struct starts with its size, so
+ * callers built against an older header keep working. RHODI_ABI_VERSION
+ * changes only on incompatible changes.
+ */
+#ifndef RHODI_H
+#define RHODI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RHODI_ABI_VERSION 1
+
+/* Flags for RhodiVerifyOptions.flags */
+/* Integrity of the document and its includes, without reading trace sources */
+#define RHODI_VERIFY_SKIP_TRACES (1u << 0)
+/* Version hash and signature of this document only */
+#define RHODI_VERIFY_SIGNATURE_ONLY (1u << 1)
+
+/* Values of RhodiReport.outcome */
+#define RHODI_OUTCOME_PASSED 0
+#define RHODI_OUTCOME_WARNING 1
+#define RHODI_OUTCOME_FAILED 2
+#define RHODI_OUTCOME_UNVERIFIED 3
+#define RHODI_OUTCOME_TIMED_OUT 4
+/* NULL pointers, bad options or a missing base_dir; nothing was verified */
+#define RHODI_OUTCOME_INVALID_ARGUMENT (-1)
+
+typedef struct RhodiVerifyOptions {
+    /* sizeof(RhodiVerifyOptions); the ABI version 1 size is also accepted */
+    uint32_t struct_size;
+    /* RHODI_VERIFY_* flags */
+    uint32_t flags;
+    /* UTF-8 directory that trace sources and includes are resolved
+       against, or NULL to read no sources */
+    const char *base_dir;
+    /* trusted_key_count Ed25519 public keys, 32 bytes each; when given,
+       the document must be signed by one of them */
+    const uint8_t *trusted_keys;
+    size_t trusted_key_count;
+} RhodiVerifyOptions;
+
+typedef struct RhodiReport {
+    /* sizeof(RhodiReport) as the library was built */
+    uint32_t struct_size;
+    /* RHODI_OUTCOME_* */
+    int32_t outcome;
+    uint32_t error_count;
+    uint32_t warning_count;
+    /* Valid when has_version_hash is 1 */
+    uint8_t version_hash[32];
+    uint8_t has_version_hash;
+    /* UTF-8 JSON of the document's verification report entry
+       (specs/report.schema.json), with every finding */
+    char *json;
+} RhodiReport;
+
+uint32_t rhodi_abi_version(void);
+
+/* Library version; a static string, not to be freed */
+const char *rhodi_version(void);
+
+/* Verify the document in bytes[0..len]. options may be NULL for the
+   defaults: every check, no sources readable, any signer accepted. Never
+   returns NULL; free the report with rhodi_report_free. Thread-safe. */
+RhodiReport *rhodi_verify(const uint8_t *bytes, size_t len,
+                          const RhodiVerifyOptions *options);
+
+void rhodi_report_free(RhodiReport *report);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RHODI_H */
@@ -0,0 +1,410 @@
+//! C ABI for verifying `.tmd` documents in-process, so document-management
+//! systems in Java, .NET or C can embed verification without spawning the
+//! `rhodi` binary. `include/rhodi.h` declares everything exported here.
+//!
+//! The structs are `#[repr(C)]` and only ever grow: new fields are appended,
+//! and each struct starts with its `struct_size` so either side can tell
+//! which fields the other knows about. Nothing unwinds across the boundary;
+//! a panic is reported like any other failure.
+
+use ed25519_dalek::VerifyingKey;
+use rhodi_core::compiler::{Compiler, SectionOutcome, VerifyPasses, check_trusted_signer};
+use rhodi_core::error::{Result, RhodiError};
+use rhodi_core::markdown::parse_tmd;
+use rhodi_core::models::TracedDocument;
+use rhodi_core::report::DocumentReport;
+use rhodi_core::resolver::{FileResolver, ResolvedSource, SourceResolver};
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::time::Instant;
+
+/// Version of the ABI, bumped on incompatible changes. Adding fields to the
+/// end of a struct is not one.
+pub const RHODI_ABI_VERSION: u32 = 1;
+
+/// Check integrity only: version hash and signature of the document and
+/// everything it includes, without reading trace sources.
+pub const RHODI_VERIFY_SKIP_TRACES: u32 = 1 << 0;
+/// Check only this document's version hash and signature.
+pub const RHODI_VERIFY_SIGNATURE_ONLY: u32 = 1 << 1;
+
+/// Every check passed.
+pub const RHODI_OUTCOME_PASSED: i32 = 0;
+/// Passed, with warnings (e.g. a failing trace in a draft).
+pub const RHODI_OUTCOME_WARNING: i32 = 1;
+/// At least one check failed, or the document could not be parsed.
+pub const RHODI_OUTCOME_FAILED: i32 = 2;
+/// Some checks were skipped because they need the network.
+pub const RHODI_OUTCOME_UNVERIFIED: i32 = 3;
+/// A trace ran out of time.
+pub const RHODI_OUTCOME_TIMED_OUT: i32 = 4;
+/// The call itself was invalid: a NULL pointer, bad options or a
+/// `base_dir` that does not exist. Nothing was verified.
+pub const RHODI_OUTCOME_INVALID_ARGUMENT: i32 = -1;
+
+/// Options for [`rhodi_verify`]. Pass NULL for the defaults: every check,
+/// no trace sources readable, any signer accepted.
+#[repr(C)]
+pub struct RhodiVerifyOptions {
+    /// `sizeof(RhodiVerifyOptions)` as the caller was compiled with
+    pub struct_size: u32,
+    /// `RHODI_VERIFY_*` flags
+    pub flags: u32,
+    /// NUL-terminated UTF-8 directory that trace sources and includes are
+    /// resolved against, or NULL to read no sources
+    pub base_dir: *const c_char,
+    /// `trusted_key_count` Ed25519 public keys of 32 bytes each; when any
+    /// are given, the document must be signed by one of them
+    pub trusted_keys: *const u8,
+    pub trusted_key_count: usize,
+}
+
+/// `sizeof(RhodiVerifyOptions)` in ABI version 1, the smallest
+/// `struct_size` accepted. Fields appended later are only read when the
+/// caller's `struct_size` covers them.
+const VERIFY_OPTIONS_V1_SIZE: usize =
+    std::mem::offset_of!(RhodiVerifyOptions, trusted_key_count) + std::mem::size_of::<usize>();
+
+/// Result of [`rhodi_verify`], owned by the library until passed to
+/// [`rhodi_report_free`].
+#[repr(C)]
+pub struct RhodiReport {
+    /// `sizeof(RhodiReport)` as the library was compiled with
+    pub struct_size: u32,
+    /// `RHODI_OUTCOME_*`
+    pub outcome: i32,
+    pub error_count: u32,
+    pub warning_count: u32,
+    /// Version hash of the document, valid when `has_version_hash` is 1
+    pub version_hash: [u8; 32],
+    pub has_version_hash: u8,
+    /// NUL-terminated UTF-8 JSON of the document's entry in a verification
+    /// report (see `specs/report.schema.json`), with every finding and the
+    /// outcome of each trace and include
+    pub json: *mut c_char,
+}
+
+/// The ABI version the library was built with, [`RHODI_ABI_VERSION`].
+#[unsafe(no_mangle)]
+pub extern "C" fn rhodi_abi_version() -> u32 {
+    RHODI_ABI_VERSION
+}
+
+/// The library version, as a static NUL-terminated string.
+#[unsafe(no_mangle)]
+pub extern "C" fn rhodi_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Verify the `.tmd` document in `bytes[0..len]`. Always returns a report,
+/// to be released with [`rhodi_report_free`].
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes. `options` must be NULL or
+/// point to a `RhodiVerifyOptions` whose pointers are valid for the sizes
+/// it gives.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rhodi_verify(
+    bytes: *const u8,
+    len: usize,
+    options: *const RhodiVerifyOptions,
+) -> *mut RhodiReport {
+    let started = Instant::now();
+    let report = catch_unwind(AssertUnwindSafe(|| {
+        // SAFETY: the caller guarantees the pointers, per the contract above
+        let input = unsafe { Input::read(bytes, len, options) };
+        match input {
+            Ok(input) => verify(&input, started),
+            Err(e) => invalid_argument(&e),
+        }
+    }))
+    .unwrap_or_else(|_| {
+        failed(&RhodiError::Verification(
+            "Verification panicked; this is a bug in rhodi".into(),
+        ))
+    });
+    Box::into_raw(Box::new(report))
+}
+
+/// Release a report returned by [`rhodi_verify`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `report` must be NULL or a report from [`rhodi_verify`] not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rhodi_report_free(report: *mut RhodiReport) {
+    if report.is_null() {
+        return;
+    }
+    // SAFETY: `report` came from `Box::into_raw` in `rhodi_verify`, and its
+    // `json` from `CString::into_raw`
+    unsafe {
+        let report = Box::from_raw(report);
+        if !report.json.is_null() {
+            drop(CString::from_raw(report.json));
+        }
+    }
+}
+
+/// The arguments of one [`rhodi_verify`] call, checked and copied.
+struct Input {
+    content: String,
+    passes: VerifyPasses,
+    base_dir: Option<String>,
+    trusted_keys: Vec<VerifyingKey>,
+}
+
+impl Input {
+    /// # Safety
+    ///
+    /// As for [`rhodi_verify`].
+    unsafe fn read(
+        bytes: *const u8,
+        len: usize,
+        options: *const RhodiVerifyOptions,
+    ) -> Result<Self> {
+        if bytes.is_null() {
+            return Err(RhodiError::Format("Document bytes are NULL".into()));
+        }
+        // SAFETY: `bytes` points to `len` readable bytes
+        let bytes = unsafe { std::slice::from_raw_parts(bytes, len) };
+        let content = std::str::from_utf8(bytes)
+            .map_err(|e| RhodiError::Format(format!("Document is not valid UTF-8: {}", e)))?
+            .to_string();
+
+        let mut input = Input {
+            content,
+            passes: VerifyPasses::ALL,
+            base_dir: None,
+            trusted_keys: Vec::new(),
+        };
+        // SAFETY: `options` is NULL or points to a valid RhodiVerifyOptions
+        let Some(options) = (unsafe { options.as_ref() }) else {
+            return Ok(input);
+        };
+        if (options.struct_size as usize) < VERIFY_OPTIONS_V1_SIZE {
+            return Err(RhodiError::Format(format!(
+                "RhodiVerifyOptions.struct_size is {}, expected at least {}",
+                options.struct_size, VERIFY_OPTIONS_V1_SIZE
+            )));
+        }
+
+        input.passes = if options.flags & RHODI_VERIFY_SIGNATURE_ONLY != 0 {
+            VerifyPasses::SIGNATURE_ONLY
+        } else if options.flags & RHODI_VERIFY_SKIP_TRACES != 0 {
+            VerifyPasses::SKIP_TRACES
+        } else {
+            VerifyPasses::ALL
+        };
+        if !options.base_dir.is_null() {
+            // SAFETY: a non-NULL `base_dir` is a NUL-terminated string
+            let dir = unsafe { CStr::from_ptr(options.base_dir) };
+            let dir = dir
+                .to_str()
+                .map_err(|e| RhodiError::Format(format!("base_dir is not valid UTF-8: {}", e)))?;
+            input.base_dir = Some(dir.to_string());
+        }
+        if options.trusted_key_count > 0 {
+            if options.trusted_keys.is_null() {
+                return Err(RhodiError::Format(
+                    "trusted_keys is NULL, but trusted_key_count is not 0".into(),
+                ));
+            }
+            let len = options.trusted_key_count.checked_mul(32).ok_or_else(|| {
+                RhodiError::Format(format!(
+                    "trusted_key_count {} is too large",
+                    options.trusted_key_count
+                ))
+            })?;
+            // SAFETY: `trusted_keys` points to 32 bytes per key
+            let keys = unsafe { std::slice::from_raw_parts(options.trusted_keys, len) };
+            input.trusted_keys = keys
+                .as_chunks::<32>()
+                .0
+                .iter()
+                .map(|key| {
+                    VerifyingKey::from_bytes(key)
+                        .map_err(|e| RhodiError::Crypto(format!("Invalid trusted key: {}", e)))
+                })
+                .collect::<Result<_>>()?;
+        }
+        Ok(input)
+    }
+}
+
+/// Serves no sources, for callers that gave no `base_dir`.
+struct NoSources;
+
+impl NoSources {
+    fn unavailable(source: &str) -> RhodiError {
+        RhodiError::Resolution(format!("Cannot read {}: no base_dir was given", source))
+    }
+}
+
+impl SourceResolver for NoSources {
+    fn resolve_bytes(&self, source: &str) -> Result<Vec<u8>> {
+        Err(Self::unavailable(source))
+    }
+
+    fn resolve_document(&self, source: &str) -> Result<TracedDocument> {
+        Err(Self::unavailable(source))
+    }
+
+    fn resolve(&self, source: &str) -> Result<ResolvedSource> {
+        Err(Self::unavailable(source))
+    }
+}
+
+fn verify(input: &Input, started: Instant) -> RhodiReport {
+    let doc = match parse_tmd(&input.content) {
+        Ok(doc) => doc,
+        Err(e) => return failed(&e),
+    };
+    let result = match &input.base_dir {
+        Some(dir) => match FileResolver::new(dir) {
+            Ok(files) => verify_with(&doc, input, &files, started),
+            Err(e) => return invalid_argument(&e),
+        },
+        None => verify_with(&doc, input, &NoSources, started),
+    };
+    result.unwrap_or_else(|e| failed(&e))
+}
+
+fn verify_with<R: SourceResolver>(
+    doc: &TracedDocument,
+    input: &Input,
+    resolver: &R,
+    started: Instant,
+) -> Result<RhodiReport> {
+    let verified = Compiler::new(resolver)
+        .with_passes(input.passes)
+        .verify_detailed(doc)?;
+    let entry = DocumentReport::new(None, doc, &verified);
+    let mut report = verified.into_report();
+    check_trusted_signer(doc, &input.trusted_keys, &mut report)?;
+    let entry = entry.finish(&report, started.elapsed());
+
+    let mut out = report_for(outcome_code(entry.outcome), &entry);
+    out.error_count = report.errors.len() as u32;
+    out.warning_count = report.warnings.len() as u32;
+    if let Some(hash) = doc.frontmatter.version_hash {
+        out.version_hash = hash;
+        out.has_version_hash = 1;
+    }
+    Ok(out)
+}
+
+fn outcome_code(outcome: SectionOutcome) -> i32 {
+    match outcome {
+        SectionOutcome::Passed | SectionOutcome::NotChecked => RHODI_OUTCOME_PASSED,
+        SectionOutcome::Warning => RHODI_OUTCOME_WARNING,
+        SectionOutcome::Failed => RHODI_OUTCOME_FAILED,
+        SectionOutcome::Unverified => RHODI_OUTCOME_UNVERIFIED,
+        SectionOutcome::TimedOut => RHODI_OUTCOME_TIMED_OUT,
+    }
+}
+
+/// The report for a document that could not be verified at all.
+fn failed(error: &RhodiError) -> RhodiReport {
+    unverifiable(RHODI_OUTCOME_FAILED, error)
+}
+
+fn invalid_argument(error: &RhodiError) -> RhodiReport {
+    unverifiable(RHODI_OUTCOME_INVALID_ARGUMENT, error)
+}
+
+fn unverifiable(outcome: i32, error: &RhodiError) -> RhodiReport {
+    let entry = DocumentReport::unverifiable(None, error, Default::default());
+    let mut report = report_for(outcome, &entry);
+    report.error_count = 1;
+    report
+}
+
+fn report_for(outcome: i32, entry: &DocumentReport) -> RhodiReport {
+    // JSON escapes control characters, so the text holds no NUL
+    let json = serde_json::to_string(entry).unwrap_or_else(|_| "{}".to_string());
+    RhodiReport {
+        struct_size: std::mem::size_of::<RhodiReport>() as u32,
+        outcome,
+        error_count: 0,
+        warning_count: 0,
+        version_hash: [0; 32],
+        has_version_hash: 0,
+        json: CString::new(json).unwrap_or_default().into_raw(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhodi_core::crypto::KeyPair;
+    use rhodi_core::markdown::render_tmd;
+
+    fn verify_text(content: &str, options: Option<&RhodiVerifyOptions>) -> (i32, u8, String) {
+        let options = options.map_or(std::ptr::null(), |o| o as *const _);
+        // SAFETY: the pointers come from live Rust values
+        unsafe {
+            let report = rhodi_verify(content.as_ptr(), content.len(), options);
+            let result = (
+                (*report).outcome,
+                (*report).has_version_hash,
+                CStr::from_ptr((*report).json).to_str().unwrap().to_string(),
+            );
+            rhodi_report_free(report);
+            result
+        }
+    }
+
+    #[test]
+    fn test_verify_over_c_abi() {
+        let keypair = KeyPair::generate();
+        let mut doc = TracedDocument::new("Embedded", "Revenue grew.\n");
+        doc.frontmatter.public_key = Some(hex::encode(keypair.verifying_key.as_bytes()));
+        let sealed = render_tmd(&doc.seal(&keypair).unwrap()).unwrap();
+
+        let (outcome, has_hash, json) = verify_text(&sealed, None);
+        assert_eq!(outcome, RHODI_OUTCOME_PASSED, "{}", json);
+        assert_eq!(has_hash, 1);
+        let entry: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(entry["title"], "Embedded");
+
+        let tampered = sealed.replace("Revenue grew.", "Revenue fell.");
+        assert_eq!(verify_text(&tampered, None).0, RHODI_OUTCOME_FAILED);
+        assert_eq!(verify_text("not a document", None).0, RHODI_OUTCOME_FAILED);
+
+        // Only the given signers are trusted
+        let other = KeyPair::generate();
+        let mut options = RhodiVerifyOptions {
+            struct_size: std::mem::size_of::<RhodiVerifyOptions>() as u32,
+            flags: RHODI_VERIFY_SIGNATURE_ONLY,
+            base_dir: std::ptr::null(),
+            trusted_keys: other.verifying_key.as_bytes().as_ptr(),
+            trusted_key_count: 1,
+        };
+        let (outcome, _, json) = verify_text(&sealed, Some(&options));
+        assert_eq!(outcome, RHODI_OUTCOME_FAILED);
+        assert!(json.contains("untrusted key"), "{}", json);
+        options.trusted_keys = keypair.verifying_key.as_bytes().as_ptr();
+        assert_eq!(verify_text(&sealed, Some(&options)).0, RHODI_OUTCOME_PASSED);
+
+        // Bad arguments are reported, not verified
+        options.trusted_key_count = usize::MAX;
+        assert_eq!(
+            verify_text(&sealed, Some(&options)).0,
+            RHODI_OUTCOME_INVALID_ARGUMENT
+        );
+        options.trusted_key_count = 1;
+        options.struct_size = 4;
+        assert_eq!(
+            verify_text(&sealed, Some(&options)).0,
+            RHODI_OUTCOME_INVALID_ARGUMENT
+        );
+        // SAFETY: a NULL document is rejected before it is read
+        unsafe {
+            let report = rhodi_verify(std::ptr::null(), 0, std::ptr::null());
+            assert_eq!((*report).outcome, RHODI_OUTCOME_INVALID_ARGUMENT);
+            rhodi_report_free(report);
+        }
+    }
+}